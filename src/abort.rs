@@ -0,0 +1,84 @@
+//! Controlled abort - cutting a compiled sequence short and ramping every output down to its reset value.
+//!
+//! A hard stop leaves outputs wherever they were (e.g. coils at full current). [`BaseStreamer::compile_abort`]
+//! instead rewrites the compile caches of all active channels: the waveform is kept up to `t_abort`, followed by
+//! a linear ramp from the value at `t_abort` to the channel reset value, which is then held for one more tick.
+//! Ramp durations are set per channel (`"Dev1/ao0"` keys) with a common default. Only `f64` channels ramp,
+//! digital and other channels step to their reset value at `t_abort`.
+//!
+//! Every device stops one tick after its longest ramp. Only the compile caches change - the edit cache still holds
+//! the full sequence, and the next regular compile restores it. Until then the channels stay fresh-compiled, so the
+//! abort sequence is what gets streamed.
+//!
+//! [`BaseStreamer::compile_abort`]: crate::streamer::BaseStreamer::compile_abort
+
+use indexmap::IndexMap;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AbortSpec {
+    /// Time (in seconds) at which the sequence is cut
+    pub t_abort: f64,
+    /// Ramp duration (in seconds) of channels without an entry in `ramp_durs`
+    pub dflt_ramp_dur: f64,
+    /// `"<device>/<channel>"` -> ramp duration in seconds
+    pub ramp_durs: IndexMap<String, f64>,
+}
+
+impl AbortSpec {
+    pub fn new(t_abort: f64, dflt_ramp_dur: f64) -> Self {
+        Self { t_abort, dflt_ramp_dur, ramp_durs: IndexMap::new() }
+    }
+    /// Sets the ramp duration of channel `key` (`"<device>/<channel>"`)
+    pub fn with_ramp(mut self, key: &str, ramp_dur: f64) -> Self {
+        self.ramp_durs.insert(key.to_string(), ramp_dur);
+        self
+    }
+    pub fn ramp_dur(&self, dev_name: &str, chan_name: &str) -> f64 {
+        self.ramp_durs.get(&format!("{dev_name}/{chan_name}")).copied().unwrap_or(self.dflt_ramp_dur)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::channel::BaseChan;
+    use crate::device::BaseDev;
+    use crate::error::StreamerError;
+    use crate::mock::MockStreamTarget;
+    use crate::mock::test_impls::TestStreamer;
+    use crate::streamer::BaseStreamer;
+
+    #[test]
+    fn compile_abort() {
+        let mut streamer = TestStreamer::new();
+        streamer.add_ao_dev("AO", 100.0);
+        streamer.add_do_dev("DO", 100.0);
+        streamer.ao_devs["AO"].add_chan("coil", 0.0);
+        streamer.ao_devs["AO"].add_chan("aom", 0.0);
+        streamer.do_devs["DO"].add_chan("shutter", false);
+        streamer.ao_devs["AO"].chan_mut("coil").unwrap().constant(4.0, 0.0, Some((1.0, false))).unwrap();
+        streamer.ao_devs["AO"].chan_mut("aom").unwrap().constant(1.0, 0.0, Some((1.0, false))).unwrap();
+        streamer.do_devs["DO"].chan_mut("shutter").unwrap().constant(true, 0.0, Some((1.0, false))).unwrap();
+        assert!(matches!(streamer.compile_abort(&AbortSpec::new(0.5, 0.0)), Err(StreamerError::NotCompiled { .. })));
+        streamer.compile(Some(2.0)).unwrap();
+        assert!(matches!(streamer.compile_abort(&AbortSpec::new(3.0, 0.0)), Err(StreamerError::OutOfRange { .. })));
+
+        let spec = AbortSpec::new(0.5, 0.02).with_ramp("AO/coil", 0.04);
+        assert_eq!(streamer.compile_abort(&spec).unwrap(), 0.55);
+        assert!(streamer.ao_devs["AO"].chan("coil").unwrap().is_fresh_compiled());
+        let targets = streamer.run_mock(16).unwrap();
+        let ao = targets["AO"].downcast_ref::<MockStreamTarget<f64>>().unwrap();
+        let coil = ao.chan_samps("coil").unwrap();
+        assert_eq!(coil.len(), 55);
+        assert_eq!((coil[49], coil[50], coil[51], coil[53], coil[54]), (4.0, 4.0, 3.0, 1.0, 0.0));
+        let aom = ao.chan_samps("aom").unwrap();
+        assert!((aom[51] - 0.5).abs() < 1e-9 && aom[52..].iter().all(|&val| val == 0.0));
+        // Digital channels step right away
+        let shutter = targets["DO"].downcast_ref::<MockStreamTarget<bool>>().unwrap().chan_samps("shutter").unwrap();
+        assert_eq!((shutter.len(), shutter[49], shutter[50]), (53, true, false));
+
+        // The edit cache is untouched
+        streamer.compile(Some(2.0)).unwrap();
+        assert_eq!(streamer.ao_devs["AO"].compiled_stop_time(), 2.0);
+    }
+}
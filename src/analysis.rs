@@ -0,0 +1,252 @@
+//! Edge and peak finding on compiled waveforms.
+//!
+//! Automated tests of a sequence usually need a handful of feature times - where a trigger fires, where the center
+//! of a pulse sits - rather than the full waveform. [`BaseChan::find_edges`] and [`BaseChan::find_peaks`] scan the
+//! compile cache chunk by chunk (like [`BaseChan::marker_intervals`]), so memory use does not grow with the
+//! sequence length and nothing has to be exported to Python.
+//!
+//! Times are tick times on the channel's own sample grid (`pos * clk_period`), without the trigger delay of the device:
+//! - an edge ([`Crossing`]) is reported at the first tick on the new side of `threshold`.
+//!   Rising means going from `samp < threshold` to `samp >= threshold`, falling the opposite;
+//! - a peak ([`Peak`]) is a run of equal samples with strictly lower neighbours on both sides and a value of at
+//!   least `min_height`. Flat tops are reported at their center, so the peak of a rectangular pulse is the pulse center.
+//!   Runs touching the start or the compiled stop of the channel are not peaks - their other side is unknown.
+//!
+//! `min_spacing` (in seconds) thins out peaks closer than that: the highest peaks are kept first (earlier ones on ties),
+//! and every peak within `min_spacing` of a kept one is dropped.
+//!
+//! [`BaseChan::integral`] sums `samp * dt` over a time window - e.g. the total light dose of an AOM channel or the
+//! charge through a coil - treating each sample as held for one clock period, as the hardware outputs it.
+//! Ticks cut by the window ends count with the covered fraction of their period.
+//!
+//! [`BaseChan::find_edges`]: crate::channel::BaseChan::find_edges
+//! [`BaseChan::find_peaks`]: crate::channel::BaseChan::find_peaks
+//! [`BaseChan::marker_intervals`]: crate::channel::BaseChan::marker_intervals
+//! [`BaseChan::integral`]: crate::channel::BaseChan::integral
+
+use crate::channel::{BaseChan, ChanSampCursor, samp_to_f64};
+use crate::error::{ErrCtx, StreamerError};
+use crate::skew::EdgeKind;
+
+/// Number of samples calculated at a time
+const CHUNK_SAMPS: usize = 1 << 16;
+
+/// Threshold crossing of a compiled waveform
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Crossing {
+    /// First tick on the new side of the threshold
+    pub pos: usize,
+    /// Time of `pos` in seconds
+    pub t: f64,
+    pub kind: EdgeKind,
+}
+
+/// Local maximum of a compiled waveform
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Peak {
+    /// Time of the center of the flat top in seconds (may fall between two ticks)
+    pub t: f64,
+    pub height: f64,
+    /// Duration of the flat top in seconds - one clock period for a sharp peak
+    pub width: f64,
+}
+
+/// Calls `visit(pos, samp)` for every compiled sample of `chan` in `[start_pos, end_pos)`, in order and `CHUNK_SAMPS` at a time
+fn scan_samps<C: BaseChan + ?Sized>(chan: &C, start_pos: usize, end_pos: usize, mut visit: impl FnMut(usize, f64)) -> Result<(), StreamerError> {
+    let mut cursor = ChanSampCursor::new();
+    let mut samp_buf = vec![chan.dflt_val(); end_pos.saturating_sub(start_pos).min(CHUNK_SAMPS)];
+    let mut chunk_start = start_pos;
+    while chunk_start < end_pos {
+        let chunk = &mut samp_buf[..(end_pos - chunk_start).min(CHUNK_SAMPS)];
+        chan.fill_samps_from_ticks(&mut cursor, chunk_start, chunk)?;
+        for (offs, samp) in chunk.iter().enumerate() {
+            visit(chunk_start + offs, samp_to_f64(samp))
+        }
+        chunk_start += chunk.len();
+    }
+    Ok(())
+}
+
+/// Threshold crossings of the compiled waveform of `chan`, see the [module docs](self).
+///
+/// Returns [`StreamerError::InvalidArgument`] for a non-finite threshold.
+pub fn find_edges<C: BaseChan + ?Sized>(chan: &C, threshold: f64) -> Result<Vec<Crossing>, StreamerError> {
+    if !threshold.is_finite() {
+        return Err(StreamerError::InvalidArgument {
+            ctx: ErrCtx::chan(chan.name()),
+            msg: format!("find_edges(): invalid threshold {threshold}"),
+        })
+    }
+    let stop_pos = chan.try_compiled_stop_pos()?;
+    let clk_period = chan.clk_period();
+    let mut edges = Vec::new();
+    let mut was_high = None;
+    scan_samps(chan, 0, stop_pos, |pos, samp| {
+        let high = samp >= threshold;
+        if was_high.is_some_and(|was_high| was_high != high) {
+            let kind = if high { EdgeKind::Rising } else { EdgeKind::Falling };
+            edges.push(Crossing { pos, t: pos as f64 * clk_period, kind })
+        }
+        was_high = Some(high);
+    })?;
+    Ok(edges)
+}
+
+/// Local maxima of the compiled waveform of `chan` of at least `min_height`, at least `min_spacing` seconds apart,
+/// sorted by time - see the [module docs](self).
+///
+/// Returns [`StreamerError::InvalidArgument`] for a NaN `min_height` and a negative or non-finite `min_spacing`.
+pub fn find_peaks<C: BaseChan + ?Sized>(chan: &C, min_height: f64, min_spacing: f64) -> Result<Vec<Peak>, StreamerError> {
+    if min_height.is_nan() || !(min_spacing.is_finite() && min_spacing >= 0.0) {
+        return Err(StreamerError::InvalidArgument {
+            ctx: ErrCtx::chan(chan.name()),
+            msg: format!("find_peaks(): invalid min_height {min_height} or min_spacing {min_spacing}"),
+        })
+    }
+    let stop_pos = chan.try_compiled_stop_pos()?;
+    let clk_period = chan.clk_period();
+    let mut peaks = Vec::new();
+    // Value of the run before the current one, start and value of the current run
+    let mut prev_val: Option<f64> = None;
+    let mut run: Option<(usize, f64)> = None;
+    scan_samps(chan, 0, stop_pos, |pos, samp| {
+        match run {
+            Some((_start, val)) if val == samp => {},
+            Some((start, val)) => {
+                if prev_val.is_some_and(|prev_val| prev_val < val) && samp < val && val >= min_height {
+                    peaks.push(Peak {
+                        t: (start + pos - 1) as f64 / 2.0 * clk_period,
+                        height: val,
+                        width: (pos - start) as f64 * clk_period,
+                    })
+                }
+                prev_val = Some(val);
+                run = Some((pos, samp));
+            },
+            None => run = Some((pos, samp)),
+        }
+    })?;
+
+    if min_spacing > 0.0 && peaks.len() > 1 {
+        let mut by_height: Vec<usize> = (0..peaks.len()).collect();
+        // Stable sort - earlier peaks win ties
+        by_height.sort_by(|&idx_a, &idx_b| peaks[idx_b].height.total_cmp(&peaks[idx_a].height));
+        let mut kept: Vec<usize> = Vec::new();
+        for idx in by_height {
+            if kept.iter().all(|&kept_idx| (peaks[kept_idx].t - peaks[idx].t).abs() >= min_spacing) {
+                kept.push(idx)
+            }
+        }
+        kept.sort();
+        peaks = kept.into_iter().map(|idx| peaks[idx]).collect();
+    }
+    Ok(peaks)
+}
+
+/// Integral of the compiled waveform of `chan` over `[start_time, end_time]` in `samp * s`, see the [module docs](self).
+/// The window defaults to the full compiled waveform.
+///
+/// Returns [`StreamerError::OutOfRange`] for a window outside of `[0, compiled_stop_time]` or with `end_time < start_time`.
+pub fn integral<C: BaseChan + ?Sized>(chan: &C, start_time: Option<f64>, end_time: Option<f64>) -> Result<f64, StreamerError> {
+    let stop_pos = chan.try_compiled_stop_pos()?;
+    let compiled_stop_time = stop_pos as f64 * chan.clk_period();
+    let start_time = start_time.unwrap_or(0.0);
+    let end_time = end_time.unwrap_or(compiled_stop_time);
+    if !(0.0 <= start_time && start_time <= end_time && end_time <= compiled_stop_time) {
+        return Err(StreamerError::OutOfRange {
+            ctx: ErrCtx::chan(chan.name()),
+            msg: format!(
+                "integral(): window [{start_time}, {end_time}] is not within [0, {compiled_stop_time}]"
+            ),
+        })
+    }
+    // Window ends in (fractional) ticks
+    let (start_tick, end_tick) = (start_time * chan.samp_rate(), end_time * chan.samp_rate());
+    let start_pos = start_tick.floor() as usize;
+    let end_pos = (end_tick.ceil() as usize).min(stop_pos);
+    let mut sum = 0.0;
+    scan_samps(chan, start_pos, end_pos, |pos, samp| {
+        let covered = f64::min(pos as f64 + 1.0, end_tick) - f64::max(pos as f64, start_tick);
+        sum += samp * covered
+    })?;
+    Ok(sum * chan.clk_period())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::analysis::*;
+    use crate::fn_lib_tools::StdFnLib;
+    use crate::mock::test_impls::TestStreamer;
+    use crate::streamer::BaseStreamer;
+    use crate::device::BaseDev;
+
+    #[test]
+    fn edges_and_peaks() {
+        let mut streamer = TestStreamer::new();
+        streamer.add_ao_dev("AO", 1e3);
+        streamer.add_do_dev("DO", 1e4);
+        streamer.ao_devs["AO"].add_chan("ao0", 0.0);
+        streamer.do_devs["DO"].add_chan("port0/line0", false);
+        {
+            let chan = streamer.ao_devs["AO"].chan_mut("ao0").unwrap();
+            // Flat-top pulse [10, 20) ms, lower pulse [40, 44) ms, and a sine (in absolute time) with crests at 125 ms and 225 ms
+            chan.constant(1.0, 0.01, Some((0.01, false))).unwrap();
+            chan.constant(0.5, 0.04, Some((0.004, false))).unwrap();
+            chan.add_instr(StdFnLib::new().Sine(0.8, 10.0, 0.0, 0.0).unwrap().inner, 0.06, Some((0.2, false))).unwrap();
+        }
+        streamer.do_devs["DO"].chan_mut("port0/line0").unwrap().constant(true, 0.0105, Some((0.002, false))).unwrap();
+        assert!(matches!(streamer.find_edges("DO", "port0/line0", 0.5), Err(StreamerError::NotCompiled { .. })));
+        streamer.compile(Some(0.3)).unwrap();
+
+        let edges = streamer.find_edges("DO", "port0/line0", 0.5).unwrap();
+        assert_eq!(edges.iter().map(|edge| (edge.pos, edge.kind)).collect::<Vec<_>>(), [(105, EdgeKind::Rising), (125, EdgeKind::Falling)]);
+        assert!((edges[1].t - 0.0125).abs() < 1e-12);
+        // The sine starts at 0 and crosses 0.25 on its way up twice
+        let edges = streamer.find_edges("AO", "ao0", 0.25).unwrap();
+        let rising: Vec<usize> = edges.iter().filter(|edge| edge.kind == EdgeKind::Rising).map(|edge| edge.pos).collect();
+        assert_eq!(rising[..2], [10, 40]);
+        assert_eq!(rising.len(), 4);
+
+        let peaks = streamer.find_peaks("AO", "ao0", 0.0, 0.0).unwrap();
+        assert_eq!(peaks.len(), 4);
+        // Flat tops are reported at their center
+        assert!((peaks[0].t - 0.0145).abs() < 1e-12 && peaks[0].height == 1.0 && (peaks[0].width - 0.01).abs() < 1e-12);
+        assert!((peaks[1].t - 0.0415).abs() < 1e-12 && peaks[1].height == 0.5);
+        assert!((peaks[2].t - 0.125).abs() < 1e-12 && (peaks[2].height - 0.8).abs() < 1e-12);
+        assert!(streamer.find_peaks("AO", "ao0", 0.9, 0.0).unwrap().len() == 1);
+        // The lower pulse is within 30 ms of the higher one and is dropped
+        let spaced = streamer.find_peaks("AO", "ao0", 0.0, 0.03).unwrap();
+        assert_eq!(spaced.iter().map(|peak| (peak.t * 1e4).round() / 1e4).collect::<Vec<_>>(), [0.0145, 0.125, 0.225]);
+
+        assert!(matches!(streamer.find_peaks("AO", "ao0", 0.0, -1.0), Err(StreamerError::InvalidArgument { .. })));
+        assert!(matches!(streamer.find_edges("AO", "ao0", f64::NAN), Err(StreamerError::InvalidArgument { .. })));
+        assert!(matches!(streamer.find_edges("AO", "nope", 0.5), Err(StreamerError::NotFound { .. })));
+        assert!(matches!(streamer.find_edges("X", "ao0", 0.5), Err(StreamerError::NotFound { .. })));
+    }
+
+    #[test]
+    fn integral() {
+        let mut streamer = TestStreamer::new();
+        streamer.add_ao_dev("AO", 1e3);
+        streamer.add_do_dev("DO", 1e4);
+        streamer.ao_devs["AO"].add_chan("ao0", 0.0);
+        streamer.do_devs["DO"].add_chan("port0/line0", false);
+        streamer.ao_devs["AO"].chan_mut("ao0").unwrap().constant(2.0, 0.01, Some((0.02, false))).unwrap();
+        streamer.ao_devs["AO"].chan_mut("ao0").unwrap().add_instr(StdFnLib::new().LinFn(10.0, 0.0).unwrap().inner, 0.1, Some((0.1, false))).unwrap();
+        streamer.do_devs["DO"].chan_mut("port0/line0").unwrap().constant(true, 0.0105, Some((0.002, false))).unwrap();
+        assert!(matches!(streamer.integral("AO", "ao0", None, None), Err(StreamerError::NotCompiled { .. })));
+        streamer.compile(Some(0.3)).unwrap();
+
+        // Each sample is held for one clock period - the ramp 10 * t over [0.1, 0.2) sums to 0.1495 instead of 0.15
+        assert!((streamer.integral("AO", "ao0", None, None).unwrap() - (0.04 + 0.1495)).abs() < 1e-12);
+        assert!((streamer.integral("AO", "ao0", Some(0.0), Some(0.05)).unwrap() - 0.04).abs() < 1e-12);
+        // Partially covered ticks count with the covered fraction
+        assert!((streamer.integral("AO", "ao0", Some(0.0105), Some(0.0205)).unwrap() - 0.02).abs() < 1e-12);
+        assert!((streamer.integral("DO", "port0/line0", None, None).unwrap() - 0.002).abs() < 1e-12);
+        assert_eq!(streamer.integral("AO", "ao0", Some(0.05), Some(0.05)).unwrap(), 0.0);
+
+        assert!(matches!(streamer.integral("AO", "ao0", None, Some(0.4)), Err(StreamerError::OutOfRange { .. })));
+        assert!(matches!(streamer.integral("AO", "ao0", Some(0.2), Some(0.1)), Err(StreamerError::OutOfRange { .. })));
+        assert!(matches!(streamer.integral("AO", "nope", None, None), Err(StreamerError::NotFound { .. })));
+    }
+}
@@ -0,0 +1,314 @@
+//! Data-rate and duration budget of the compiled experiment.
+//!
+//! Streaming hardware shares a bus (PCIe, PXI, USB), and a run that needs more bandwidth than the bus can sustain
+//! underflows mid-sequence. [`BaseStreamer::stream_budget`] lists, per active device, the samples and bytes per second
+//! it will stream together with the totals over the compiled sequence, so that it can be checked against bus limits
+//! before starting a run.
+//!
+//! Only active channels are counted and a sample takes the in-memory size of the channel sample type.
+//! Unlike [`crate::summary`], the budget requires a fresh compile cache.
+//!
+//! [`BaseStreamer::predicted_samples`] works out the same budget for a hypothetical stop time from the edit cache alone,
+//! without compiling or touching any compile cache - so a script can check the sample count and memory footprint of a
+//! long sequence before committing to its compile. It also reports the ticks `compile` adds or leaves out on top of the
+//! rounded stop time: the closing edge sample and the trigger delay.
+//!
+//! [`BaseStreamer::stream_budget`]: crate::streamer::BaseStreamer::stream_budget
+//! [`BaseStreamer::predicted_samples`]: crate::streamer::BaseStreamer::predicted_samples
+
+use std::fmt;
+use std::fmt::Display;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DevBudget {
+    pub name: String,
+    pub samp_rate: f64,
+    /// Size of one sample in bytes
+    pub samp_bytes: usize,
+    /// Number of active channels
+    pub n_chans: usize,
+    /// Compiled stop position - number of samples per channel
+    pub stop_pos: usize,
+}
+
+impl DevBudget {
+    pub fn samps_per_sec(&self) -> f64 {
+        self.samp_rate * self.n_chans as f64
+    }
+    pub fn bytes_per_sec(&self) -> f64 {
+        self.samps_per_sec() * self.samp_bytes as f64
+    }
+    pub fn total_samps(&self) -> usize {
+        self.stop_pos * self.n_chans
+    }
+    pub fn total_bytes(&self) -> usize {
+        self.total_samps() * self.samp_bytes
+    }
+    /// Streaming duration in seconds
+    pub fn duration(&self) -> f64 {
+        self.stop_pos as f64 / self.samp_rate
+    }
+
+    pub fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("samp_rate", self.samp_rate)?;
+        dict.set_item("samp_bytes", self.samp_bytes)?;
+        dict.set_item("n_chans", self.n_chans)?;
+        dict.set_item("samps_per_sec", self.samps_per_sec())?;
+        dict.set_item("bytes_per_sec", self.bytes_per_sec())?;
+        dict.set_item("total_samps", self.total_samps())?;
+        dict.set_item("total_bytes", self.total_bytes())?;
+        dict.set_item("duration", self.duration())?;
+        Ok(dict)
+    }
+}
+
+impl Display for DevBudget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f, "device {}: {} channels x {} Hz x {} B = {}, {} samples ({}) over {} s",
+            self.name, self.n_chans, self.samp_rate, self.samp_bytes, fmt_bytes_per_sec(self.bytes_per_sec()),
+            self.total_samps(), fmt_bytes(self.total_bytes() as f64), self.duration()
+        )
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct StreamBudget {
+    /// Budgets of active devices
+    pub devs: Vec<DevBudget>,
+}
+
+impl StreamBudget {
+    pub fn samps_per_sec(&self) -> f64 {
+        self.devs.iter().map(DevBudget::samps_per_sec).sum()
+    }
+    /// Combined data rate of all devices streaming at once
+    pub fn bytes_per_sec(&self) -> f64 {
+        self.devs.iter().map(DevBudget::bytes_per_sec).sum()
+    }
+    pub fn total_samps(&self) -> usize {
+        self.devs.iter().map(DevBudget::total_samps).sum()
+    }
+    pub fn total_bytes(&self) -> usize {
+        self.devs.iter().map(DevBudget::total_bytes).sum()
+    }
+    /// Longest device streaming duration in seconds
+    pub fn duration(&self) -> f64 {
+        self.devs.iter().map(DevBudget::duration).fold(0.0, f64::max)
+    }
+    pub fn dev(&self, name: &str) -> Option<&DevBudget> {
+        self.devs.iter().find(|dev| dev.name == name)
+    }
+
+    pub fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("samps_per_sec", self.samps_per_sec())?;
+        dict.set_item("bytes_per_sec", self.bytes_per_sec())?;
+        dict.set_item("total_samps", self.total_samps())?;
+        dict.set_item("total_bytes", self.total_bytes())?;
+        dict.set_item("duration", self.duration())?;
+        let devs = PyDict::new_bound(py);
+        for dev in self.devs.iter() {
+            devs.set_item(&dev.name, dev.to_dict(py)?)?;
+        }
+        dict.set_item("devs", devs)?;
+        Ok(dict)
+    }
+}
+
+impl Display for StreamBudget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f, "stream budget: {}, {} samples ({}) over {} s",
+            fmt_bytes_per_sec(self.bytes_per_sec()), self.total_samps(), fmt_bytes(self.total_bytes() as f64), self.duration()
+        )?;
+        for dev in self.devs.iter() {
+            writeln!(f, "\t{dev}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Budget compiling to a given stop time would produce for one device, see [`BaseStreamer::predicted_samples`]
+///
+/// [`BaseStreamer::predicted_samples`]: crate::streamer::BaseStreamer::predicted_samples
+#[derive(Clone, Debug, PartialEq)]
+pub struct DevPrediction {
+    /// `budget.stop_pos` is the predicted compiled stop position: `stop_tick + closing_edge_ticks - delay_ticks`
+    pub budget: DevBudget,
+    /// Stop time rounded to this device's clock
+    pub stop_tick: usize,
+    /// Extra samples added to form the closing edge of a last instruction ending exactly at `stop_tick`
+    pub closing_edge_ticks: usize,
+    /// Trigger delay in clock ticks - the device starts this much later, so it generates as many samples less
+    pub delay_ticks: usize,
+}
+
+impl DevPrediction {
+    pub fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = self.budget.to_dict(py)?;
+        dict.set_item("stop_tick", self.stop_tick)?;
+        dict.set_item("closing_edge_ticks", self.closing_edge_ticks)?;
+        dict.set_item("delay_ticks", self.delay_ticks)?;
+        dict.set_item("stop_pos", self.budget.stop_pos)?;
+        Ok(dict)
+    }
+}
+
+impl Display for DevPrediction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f, "{} (stop tick {} + {} closing edge - {} trigger delay)",
+            self.budget, self.stop_tick, self.closing_edge_ticks, self.delay_ticks
+        )
+    }
+}
+
+/// Budget compiling to `stop_time` would produce, see [`BaseStreamer::predicted_samples`]
+///
+/// [`BaseStreamer::predicted_samples`]: crate::streamer::BaseStreamer::predicted_samples
+#[derive(Clone, Debug, PartialEq)]
+pub struct SampPrediction {
+    pub stop_time: f64,
+    /// Predictions of active devices
+    pub devs: Vec<DevPrediction>,
+}
+
+impl SampPrediction {
+    /// The predicted [`StreamBudget`] - equal to [`BaseStreamer::stream_budget`] after compiling to `stop_time`
+    ///
+    /// [`BaseStreamer::stream_budget`]: crate::streamer::BaseStreamer::stream_budget
+    pub fn budget(&self) -> StreamBudget {
+        StreamBudget { devs: self.devs.iter().map(|dev| dev.budget.clone()).collect() }
+    }
+    pub fn total_samps(&self) -> usize {
+        self.devs.iter().map(|dev| dev.budget.total_samps()).sum()
+    }
+    /// Memory footprint of all compiled samples
+    pub fn total_bytes(&self) -> usize {
+        self.devs.iter().map(|dev| dev.budget.total_bytes()).sum()
+    }
+    pub fn dev(&self, name: &str) -> Option<&DevPrediction> {
+        self.devs.iter().find(|dev| dev.budget.name == name)
+    }
+
+    pub fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("stop_time", self.stop_time)?;
+        dict.set_item("total_samps", self.total_samps())?;
+        dict.set_item("total_bytes", self.total_bytes())?;
+        let devs = PyDict::new_bound(py);
+        for dev in self.devs.iter() {
+            devs.set_item(&dev.budget.name, dev.to_dict(py)?)?;
+        }
+        dict.set_item("devs", devs)?;
+        Ok(dict)
+    }
+}
+
+impl Display for SampPrediction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f, "predicted for stop_time={} s: {} samples ({})",
+            self.stop_time, self.total_samps(), fmt_bytes(self.total_bytes() as f64)
+        )?;
+        for dev in self.devs.iter() {
+            writeln!(f, "\t{dev}")?;
+        }
+        Ok(())
+    }
+}
+
+fn fmt_bytes(bytes: f64) -> String {
+    match bytes {
+        bytes if bytes >= 1e9 => format!("{:.2} GB", bytes / 1e9),
+        bytes if bytes >= 1e6 => format!("{:.2} MB", bytes / 1e6),
+        bytes if bytes >= 1e3 => format!("{:.2} kB", bytes / 1e3),
+        bytes => format!("{bytes:.0} B"),
+    }
+}
+
+fn fmt_bytes_per_sec(bytes_per_sec: f64) -> String {
+    format!("{}/s", fmt_bytes(bytes_per_sec))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::channel::BaseChan;
+    use crate::device::BaseDev;
+    use crate::error::StreamerError;
+    use crate::mock::test_impls::TestStreamer;
+    use crate::streamer::BaseStreamer;
+
+    #[test]
+    fn stream_budget() {
+        let mut streamer = TestStreamer::new();
+        streamer.add_ao_dev("AO", 1e3);
+        streamer.add_do_dev("DO", 1e4);
+        streamer.add_ao_dev("Idle", 1e3);
+        streamer.ao_devs["AO"].add_chan("ao0", 0.0);
+        streamer.ao_devs["AO"].add_chan("ao1", 0.0);
+        streamer.ao_devs["AO"].add_chan("ao2", 0.0);
+        streamer.do_devs["DO"].add_chan("port0/line0", false);
+        streamer.ao_devs["AO"].chan_mut("ao0").unwrap().constant(1.0, 0.1, Some((0.1, false))).unwrap();
+        streamer.ao_devs["AO"].chan_mut("ao1").unwrap().constant(1.0, 0.2, Some((0.1, false))).unwrap();
+        streamer.do_devs["DO"].chan_mut("port0/line0").unwrap().constant(true, 0.1, Some((0.1, false))).unwrap();
+        assert!(matches!(streamer.stream_budget(), Err(StreamerError::NotCompiled { .. })));
+
+        streamer.compile(Some(1.0)).unwrap();
+        let budget = streamer.stream_budget().unwrap();
+        // Inactive devices and channels are not streamed
+        assert_eq!(budget.devs.len(), 2);
+        let ao = budget.dev("AO").unwrap();
+        assert_eq!((ao.n_chans, ao.samps_per_sec(), ao.bytes_per_sec()), (2, 2e3, 16e3));
+        assert_eq!((ao.total_samps(), ao.total_bytes(), ao.duration()), (2000, 16000, 1.0));
+        let dio = budget.dev("DO").unwrap();
+        assert_eq!((dio.samp_bytes, dio.bytes_per_sec(), dio.total_bytes()), (1, 1e4, 10000));
+        assert_eq!((budget.samps_per_sec(), budget.bytes_per_sec()), (12e3, 26e3));
+        assert_eq!((budget.total_samps(), budget.total_bytes(), budget.duration()), (12000, 26000, 1.0));
+        assert!(budget.to_string().starts_with("stream budget: 26.00 kB/s, 12000 samples (26.00 kB) over 1 s\n"));
+
+        // Edits make the budget stale
+        streamer.ao_devs["AO"].chan_mut("ao2").unwrap().constant(1.0, 0.3, Some((0.1, false))).unwrap();
+        assert!(streamer.stream_budget().is_err());
+    }
+
+    #[test]
+    fn predicted_samples() {
+        let mut streamer = TestStreamer::new();
+        assert!(matches!(streamer.predicted_samples(Some(1.0)), Err(StreamerError::NoInstructions { .. })));
+        streamer.add_ao_dev("AO", 1e3);
+        streamer.add_do_dev("DO", 1e4);
+        streamer.ao_devs["AO"].add_chan("ao0", 0.0);
+        streamer.ao_devs["AO"].add_chan("ao1", 0.0);
+        streamer.do_devs["DO"].add_chan("port0/line0", false);
+        streamer.ao_devs["AO"].chan_mut("ao0").unwrap().constant(1.0, 0.1, Some((0.1, false))).unwrap();
+        streamer.ao_devs["AO"].chan_mut("ao1").unwrap().constant(1.0, 0.2, Some((0.3, false))).unwrap();
+        streamer.do_devs["DO"].chan_mut("port0/line0").unwrap().constant(true, 0.1, Some((0.1, false))).unwrap();
+        streamer.set_trigger_delay("DO", 0.01).unwrap();
+        assert!(matches!(streamer.predicted_samples(Some(0.4)), Err(StreamerError::OutOfRange { .. })));
+
+        // Stop time at the end of the last AO instruction - the closing edge adds a sample
+        let prediction = streamer.predicted_samples(None).unwrap();
+        assert_eq!(prediction.stop_time, 0.5);
+        let ao = prediction.dev("AO").unwrap();
+        assert_eq!((ao.stop_tick, ao.closing_edge_ticks, ao.delay_ticks, ao.budget.stop_pos), (500, 1, 0, 501));
+        let dio = prediction.dev("DO").unwrap();
+        assert_eq!((dio.stop_tick, dio.closing_edge_ticks, dio.delay_ticks, dio.budget.stop_pos), (5000, 0, 100, 4900));
+        assert_eq!((prediction.total_samps(), prediction.total_bytes()), (2 * 501 + 4900, 2 * 501 * 8 + 4900));
+        assert!(prediction.to_string().contains("(stop tick 5000 + 0 closing edge - 100 trigger delay)"));
+        // Nothing got compiled
+        assert!(matches!(streamer.stream_budget(), Err(StreamerError::NotCompiled { .. })));
+
+        // The prediction matches the compiled budget and leaves the compile cache valid
+        for stop_time in [None, Some(1.0)] {
+            streamer.compile(stop_time).unwrap();
+            let prediction = streamer.predicted_samples(stop_time).unwrap();
+            assert_eq!(prediction.budget(), streamer.stream_budget().unwrap());
+        }
+        assert_eq!(streamer.predicted_samples(Some(1.0)).unwrap().dev("AO").unwrap().closing_edge_ticks, 0);
+    }
+}
@@ -20,8 +20,8 @@
 //! Properties of a channel include:
 //! - `samp_rate`: The sampling rate at which the parent device operates.
 //! - `name`: Denotes the channel's identifier as seen by the NI driver. For instance,
-//!    this could be 'ao0' or 'port0/line0'. This name can be viewed using tools like NI-MAX on
-//!    Windows or the NI hardware configuration utilities on Linux.
+//!   this could be 'ao0' or 'port0/line0'. This name can be viewed using tools like NI-MAX on
+//!   Windows or the NI hardware configuration utilities on Linux.
 //!  - `instr_list`: An edit-cache for the channel. Internally, this uses a `BTreeSet` to guarantee
 //!    the sorted ordering of non-overlapping instruction intervals.
 //!  - `task_type`: Specifies the task type associated with the channel. This affects the behavior
@@ -63,13 +63,35 @@
 //! AO channels are both streamable and editable. DO line channels are editable but not streamable, and DO port
 //! channels are non-editable yet streamable.
 
-use std::collections::BTreeSet;
+use std::any::Any;
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Bound;
 use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
 
+use indexmap::IndexMap;
 use ndarray::Array1;
 
-use crate::instruction::Instr;
-use crate::fn_lib_tools::{FnTraitSet, Calc};
+use crate::instruction::{Instr, InstrMeta};
+use crate::fn_lib_tools::{ArrayFn, FnArgs, FnRegistry, FnTraitSet, Calc, Decimate, Describe, Interp, LinFn, Repeat, TimeMap};
+use crate::hash::{StableHasher, samp_checksum};
+use crate::error::{ErrCtx, StreamerError};
+use crate::diff::InstrSnapshot;
+use crate::validation::ChanReport;
+use crate::collisions::{CollisionReport, find_collisions};
+use crate::diagnostics::{Diagnostics, DiagnosticKind, DiagnosticStage, Severity};
+use crate::marker::{MarkerRule, push_merged};
+use crate::padding::{PadGap, PadSegs, PaddingPolicy, PaddingSeg, SharedPaddingPolicy, StepPadding, uncovered};
+use crate::rounding::TickRounding;
+use crate::resample::{self, Resampling};
+use crate::analysis::{self, Crossing, Peak};
+use crate::idle::IdleSeg;
+use crate::export;
+use crate::units::{Duration, TimePoint};
+use crate::quantity::Quantity;
+use crate::profiling::{Profile, ProfileEntry};
+#[cfg(feature = "profiling")]
+use crate::profiling::{Phase, PhaseTimer};
 
 
 pub struct ConstFn<T> {
@@ -84,18 +106,231 @@ impl<T: Clone> Calc<T> for ConstFn<T> {
     fn calc(&self, _t_arr: &[f64], res_arr: &mut [T]) {
         res_arr.fill(self.val.clone())
     }
+    fn calc_one(&self, _t: f64) -> T {
+        self.val.clone()
+    }
+    fn const_val(&self) -> Option<T> {
+        Some(self.val.clone())
+    }
+    fn calc_from_ticks(&self, _start_pos: usize, _clk_period: f64, res_arr: &mut [T]) {
+        res_arr.fill(self.val.clone())
+    }
 }
 impl<T: Clone> Clone for ConstFn<T> {
     fn clone(&self) -> Self {
         Self::new(self.val.clone())
     }
 }
+impl<T: Debug> Describe for ConstFn<T> {
+    fn describe(&self) -> String {
+        format!("ConstFn(val={:?})", self.val)
+    }
+}
 impl<T: Debug> Debug for ConstFn<T> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "ConstFn(val={:?})", self.val)
     }
 }
 
+/// Channel sample read as `f64` by the type-agnostic tools - previews, comparisons, reports, and the NaN/±Inf checks.
+///
+/// Implemented for `bool`, the primitive integers, and the floats. A custom sample type without a numeric value
+/// returns `None`, which opts its channels out of the NaN/±Inf checks and of abort ramps.
+pub trait AsF64 {
+    fn as_f64(&self) -> Option<f64>;
+}
+impl AsF64 for bool {
+    fn as_f64(&self) -> Option<f64> {
+        Some(*self as u8 as f64)
+    }
+}
+macro_rules! impl_as_f64 {
+    ($($ty:ty),*) => {
+        $(impl AsF64 for $ty {
+            fn as_f64(&self) -> Option<f64> {
+                Some(*self as f64)
+            }
+        })*
+    }
+}
+impl_as_f64!(f64, f32, u8, u16, u32, u64, usize, i8, i16, i32, i64);
+
+/// [`AsF64::as_f64`], with `NaN` for samples of non-numeric types
+pub fn samp_to_f64<T: AsF64>(samp: &T) -> f64 {
+    samp.as_f64().unwrap_or(f64::NAN)
+}
+
+/// Instruction of `instr_list` on the left of `start_pos` if it reaches beyond `start_pos`, with the number of overlapping ticks
+fn left_overlap<T>(instr_list: &BTreeSet<Instr<T>>, start_pos: usize) -> Option<(&Instr<T>, usize)> {
+    let prev = instr_list.range(..start_pos).next_back()?;
+    (prev.eff_end_pos() > start_pos).then(|| (prev, prev.eff_end_pos() - start_pos))
+}
+/// Instruction of `instr_list` starting in `[start_pos, eff_end_pos)`, with the number of overlapping ticks
+fn right_overlap<T>(instr_list: &BTreeSet<Instr<T>>, start_pos: usize, eff_end_pos: usize) -> Option<(&Instr<T>, usize)> {
+    let next = instr_list.range(start_pos..).next()?;
+    (eff_end_pos > next.start_pos()).then(|| (next, eff_end_pos - next.start_pos()))
+}
+
+/// Remembers the compile cache position of the previous [`BaseChan::fill_samps_with`] call.
+///
+/// Streaming requests strictly increasing back-to-back windows. When the new window starts exactly
+/// where the previous one ended, the first covered compile cache segment is found by stepping forward
+/// from the remembered index instead of binary-searching the whole cache.
+/// Any other window (rewind, jump, first call) falls back to the binary search, so a stale cursor
+/// is never incorrect - it only loses the speed-up.
+///
+/// A cursor is tied to one channel. Use a separate cursor per channel.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ChanSampCursor {
+    /// Index of the compile cache segment covering `next_pos`
+    idx: usize,
+    /// End of the previously filled window, `None` for a fresh cursor
+    next_pos: Option<usize>,
+}
+impl ChanSampCursor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Forgets the remembered position - the next call does a full binary search
+    pub fn reset(&mut self) {
+        *self = Self::default()
+    }
+    /// Index of the first compile cache segment with end position above `window_start`
+    fn first_instr_idx(&self, ends: &[usize], window_start: usize) -> usize {
+        let resumes = self.next_pos == Some(window_start)
+            && self.idx < ends.len()
+            && (self.idx == 0 || ends[self.idx - 1] <= window_start);
+        if resumes {
+            let mut idx = self.idx;
+            while ends[idx] <= window_start {
+                idx += 1;
+            }
+            idx
+        } else {
+            match ends.binary_search(&window_start) {
+                Ok(idx) => idx + 1,
+                Err(idx) => idx,
+            }
+        }
+    }
+}
+
+/// Constant-value run of a compiled waveform: every sample in `[start_pos, end_pos)` equals `val`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Run<T> {
+    pub start_pos: usize,
+    pub end_pos: usize,
+    pub val: T,
+}
+/// Run-length encoded waveform window - see [`BaseChan::compiled_runs`]
+pub type Runs<T> = Vec<Run<T>>;
+
+/// Value change of an event channel - see [`BaseChan::compiled_events`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Event<T> {
+    /// Clock tick of the change
+    pub pos: usize,
+    /// Time of the change in seconds (`pos * clk_period`)
+    pub t: f64,
+    pub val: T,
+}
+/// Timestamped event list of a compiled event channel
+pub type Events<T> = Vec<Event<T>>;
+
+/// Edit caches of the override layers (`layer > 0`), by layer - see [`BaseChan::add_instr_on_layer`]
+pub type LayerInstrs<T> = BTreeMap<u32, BTreeSet<Instr<T>>>;
+
+/// Named preset values of a channel, in definition order - see [`BaseChan::define_preset`]
+pub type Presets<T> = IndexMap<String, T>;
+
+/// Channel-level `dur_spec` defaults used by the convenience wrappers taking a bare duration -
+/// see [`BaseChan::set_dur_defaults`]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DurDefaults {
+    /// `keep_val` of the instructions
+    pub keep_val: bool,
+    /// Shorter durations are extended to `min_dur` seconds, e.g. the minimum open time of a shutter
+    pub min_dur: Option<f64>,
+}
+
+impl DurDefaults {
+    pub fn new(keep_val: bool) -> Self {
+        Self { keep_val, min_dur: None }
+    }
+    pub fn with_min_dur(mut self, min_dur: f64) -> Self {
+        self.min_dur = Some(min_dur);
+        self
+    }
+    /// `dur_spec` for an instruction lasting `dur` seconds
+    pub fn dur_spec(&self, dur: f64) -> (f64, bool) {
+        (self.min_dur.map_or(dur, |min_dur| dur.max(min_dur)), self.keep_val)
+    }
+}
+
+/// Optional settings of a channel - see [`BaseChan::settings`]
+#[derive(Clone)]
+pub struct ChanSettings<T> {
+    /// Edit caches of the override layers - see [`BaseChan::add_instr_on_layer`]
+    pub layers: LayerInstrs<T>,
+    /// See [`BaseChan::define_preset`]
+    pub presets: Presets<T>,
+    /// See [`BaseChan::set_enabled`]
+    pub enabled: bool,
+    /// See [`BaseChan::set_hold_last_val`]
+    pub hold_last_val: bool,
+    /// See [`BaseChan::set_padding_policy`], `None` means [`StepPadding`]
+    pub padding_policy: Option<SharedPaddingPolicy<T>>,
+    /// See [`BaseChan::set_tick_rounding`]
+    pub tick_rounding: TickRounding,
+    /// See [`BaseChan::set_sub_tick_phase`]
+    pub sub_tick_phase: bool,
+    /// See [`BaseChan::set_dur_defaults`]
+    pub dur_defaults: DurDefaults,
+    /// Clock ticks by which the compile cache is advanced relative to the edit cache - the trigger delay
+    /// of the device, see [`BaseDev::set_trigger_delay`]
+    ///
+    /// [`BaseDev::set_trigger_delay`]: crate::device::BaseDev::set_trigger_delay
+    pub compile_offset: usize,
+}
+
+impl<T> Default for ChanSettings<T> {
+    fn default() -> Self {
+        Self {
+            layers: LayerInstrs::new(),
+            presets: Presets::new(),
+            enabled: true,
+            hold_last_val: false,
+            padding_policy: None,
+            tick_rounding: TickRounding::default(),
+            sub_tick_phase: false,
+            dur_defaults: DurDefaults::default(),
+            compile_offset: 0,
+        }
+    }
+}
+
+/// Padding gaps inserted by [`BaseChan::compile`], summarized in its `Padding` diagnostic
+#[derive(Clone, Copy, Debug, Default)]
+struct PadStats {
+    /// Start of the first gap
+    first_pos: Option<usize>,
+    n_gaps: usize,
+    n_ticks: usize,
+    /// Gaps holding the last value of the preceding instruction
+    n_held: usize,
+}
+impl PadStats {
+    fn add(&mut self, start_pos: usize, end_pos: usize, held: bool) {
+        self.first_pos.get_or_insert(start_pos);
+        self.n_gaps += 1;
+        self.n_ticks += end_pos - start_pos;
+        self.n_held += held as usize;
+    }
+}
+
+/// Instruction preceding a phase-linked one, with its resolved function - see [`BaseChan::add_instr_phase_linked`]
+type PrevInstr<'a, T> = (&'a Instr<T>, &'a dyn FnTraitSet<T>);
+
 /// The [`BaseChannel`] trait defines the core methods required for a channel's interaction with
 /// NI devices. It encapsulates both editing and compilation behaviors of a channel.
 ///
@@ -112,9 +347,12 @@ impl<T: Debug> Debug for ConstFn<T> {
 ///
 /// This trait ensures that any type representing a channel offers the necessary functionality
 /// to interact with NI devices, ensuring consistency and safety in channel operations.
-pub trait BaseChan {
-    /// Output sample type
-    type Samp: Clone + Debug + Send + Sync + 'static;
+///
+/// Channels must be `Send` so that a device can compile them in parallel (see the `parallel` feature).
+pub trait BaseChan: Send {
+    /// Output sample type.
+    /// Type-agnostic tools (previews, comparisons, NaN checks) read samples as `f64` through [`AsF64`].
+    type Samp: Clone + Default + PartialEq + Debug + AsF64 + Send + Sync + 'static;
 
     // Immutable field methods
     fn name(&self) -> String;
@@ -128,10 +366,12 @@ pub trait BaseChan {
     /// Returns the ending points of compiled instructions.
     fn compile_cache_ends(&self) -> &Vec<usize>;
     /// Retrieves the values of compiled instructions.
-    fn compile_cache_fns(&self) -> &Vec<Box<dyn FnTraitSet<Self::Samp>>>;
+    fn compile_cache_fns(&self) -> &Vec<Arc<dyn FnTraitSet<Self::Samp>>>;
     /// The `fresh_compiled` field is set to true by each [`BaseChannel::compile`] call and
     /// `false` by each [`BaseChannel::add_instr`].
     fn is_fresh_compiled(&self) -> bool;
+    /// Record of non-fatal decisions taken while editing and compiling this channel.
+    fn diagnostics(&self) -> &Diagnostics;
 
     // Mutable field methods
     /// Mutable access to the instruction list.
@@ -139,18 +379,253 @@ pub trait BaseChan {
     /// Mutable access to the ending points of compiled instructions.
     fn compile_cache_ends_mut(&mut self) -> &mut Vec<usize>;
     /// Mutable access to the values of compiled instructions.
-    fn compile_cache_fns_mut(&mut self) -> &mut Vec<Box<dyn FnTraitSet<Self::Samp>>>;
+    fn compile_cache_fns_mut(&mut self) -> &mut Vec<Arc<dyn FnTraitSet<Self::Samp>>>;
     /// Mutable access to the `fresh_compiled` status.
     fn is_fresh_compiled_mut(&mut self) -> &mut bool;
+    /// Mutable access to the diagnostics sink.
+    fn diagnostics_mut(&mut self) -> &mut Diagnostics;
+
+    /// Timing sink of this channel - see [`crate::profiling`].
+    /// The default `None` opts the channel out of profiling. Timers only run with the `profiling` feature.
+    fn profile(&self) -> Option<Arc<Profile>> {
+        None
+    }
+    fn profile_entries(&self) -> Vec<ProfileEntry> {
+        self.profile().map_or_else(Vec::new, |profile| profile.entries(&self.name()))
+    }
+    fn clear_profile(&self) {
+        if let Some(profile) = self.profile() {
+            profile.clear()
+        }
+    }
+
+    /// Optional settings of the channel: override layers, presets, muting, padding and tick rounding policies,
+    /// sub-tick phase correction, duration defaults, and the trigger delay offset - see [`ChanSettings`].
+    ///
+    /// A channel opts into all of them at once by storing a [`ChanSettings`] and returning it here. With the default `None`
+    /// every setting reads as in [`ChanSettings::default`] and the methods changing one (e.g. [`BaseChan::set_enabled`])
+    /// return [`StreamerError::Incompatible`].
+    fn settings(&self) -> Option<&ChanSettings<Self::Samp>> {
+        None
+    }
+    fn settings_mut(&mut self) -> Option<&mut ChanSettings<Self::Samp>> {
+        None
+    }
+    /// [`BaseChan::settings_mut`] to change `setting`, [`StreamerError::Incompatible`] if the channel has no settings
+    fn settings_for(&mut self, setting: &str) -> Result<&mut ChanSettings<Self::Samp>, StreamerError> {
+        let name = self.name();
+        self.settings_mut().ok_or_else(|| StreamerError::Incompatible { ctx: ErrCtx::chan(name), msg: format!("does not support {setting}") })
+    }
+    /// Edit caches of the override layers, `None` if the channel only supports the base layer (`instr_list`)
+    fn layer_instrs(&self) -> Option<&LayerInstrs<Self::Samp>> {
+        self.settings().map(|settings| &settings.layers)
+    }
+    fn layer_instrs_mut(&mut self) -> Option<&mut LayerInstrs<Self::Samp>> {
+        self.settings_mut().map(|settings| &mut settings.layers)
+    }
+    /// Whether any override layer holds instructions
+    fn got_layer_instrs(&self) -> bool {
+        self.layer_instrs().is_some_and(|layers| layers.values().any(|instr_list| !instr_list.is_empty()))
+    }
+
+    /// `true` for event-list channels - hardware consuming `(t, value)` edge / time-tag streams rather than sampled buffers.
+    ///
+    /// Event channels share the whole editing API (collisions, reset, compile-to-stop-time) with sampled channels
+    /// but only accept constant functions (see [`BaseChan::add_event`]), and are read out with [`BaseChan::compiled_events`].
+    /// The default is `false` - a sampled channel.
+    fn is_event_chan(&self) -> bool {
+        false
+    }
+
+    /// Physical quantity of the samples - see [`crate::quantity`]. The default is [`Quantity::Voltage`].
+    fn quantity(&self) -> Quantity {
+        Quantity::Voltage
+    }
+    /// Inclusive `(min, max)` range of admissible sample values, checked by [`BaseChan::check_range`].
+    /// The default is the range of the channel quantity ([`Quantity::default_range`]), `None` - no limits.
+    fn val_range(&self) -> Option<(f64, f64)> {
+        self.quantity().default_range()
+    }
+
+    /// Value of preset `name` if the channel defines it
+    fn preset(&self, name: &str) -> Option<Self::Samp> {
+        self.settings().and_then(|settings| settings.presets.get(name).cloned())
+    }
 
     /// Returns sample clock period calculated as `1.0 / self.samp_rate()`
     fn clk_period(&self) -> f64 {
         1.0 / self.samp_rate()
     }
 
-    /// Channel is marked as edited if its edit-cache field `instr_list` is nonempty
+    fn is_enabled(&self) -> bool {
+        self.settings().is_none_or(|settings| settings.enabled)
+    }
+    /// Mutes (`enabled = false`) or unmutes the channel without touching its edit cache.
+    ///
+    /// A disabled channel doesn't count as active ([`BaseChan::got_instructions`] is `false`), so devices neither compile
+    /// nor stream it, and a direct [`BaseChan::compile`] gives the default value throughout. Its instructions still
+    /// count towards the sequence length (e.g. [`BaseChan::last_instr_end_pos`]), so muting doesn't change timing.
+    ///
+    /// Returns [`StreamerError::Incompatible`] if the channel has no [`BaseChan::settings`].
+    fn set_enabled(&mut self, enabled: bool) -> Result<(), StreamerError> {
+        let settings = self.settings_for("being disabled")?;
+        if settings.enabled != enabled {
+            settings.enabled = enabled;
+            self.clear_compile_cache()
+        }
+        Ok(())
+    }
+
+    /// Whether paddings after every instruction hold its last value, as if all of them had `keep_val = true`
+    fn hold_last_val(&self) -> bool {
+        self.settings().is_some_and(|settings| settings.hold_last_val)
+    }
+    /// Sets the gap-filling policy of the channel: with `hold = true`, every padding holds the last value of the
+    /// preceding instruction regardless of its `keep_val` flag - for "set and forget" channels. Only the interval
+    /// before the first instruction still gets the default value.
+    ///
+    /// Returns [`StreamerError::Incompatible`] if the channel has no [`BaseChan::settings`].
+    fn set_hold_last_val(&mut self, hold: bool) -> Result<(), StreamerError> {
+        let settings = self.settings_for("the hold last value policy")?;
+        if settings.hold_last_val != hold {
+            settings.hold_last_val = hold;
+            self.clear_compile_cache()
+        }
+        Ok(())
+    }
+
+    /// Sets the [`PaddingPolicy`] for the gaps after instructions (see [`crate::padding`]), `None` restores [`StepPadding`].
+    /// [`BaseChan::crop`] and [`BaseChan::reverse`] don't work with a custom policy.
+    ///
+    /// Returns [`StreamerError::Incompatible`] if the channel has no [`BaseChan::settings`].
+    fn set_padding_policy(&mut self, policy: Option<SharedPaddingPolicy<Self::Samp>>) -> Result<(), StreamerError> {
+        self.settings_for("padding policies")?.padding_policy = policy;
+        self.clear_compile_cache();
+        Ok(())
+    }
+    /// Custom padding policy, if one is set
+    fn custom_padding_policy(&self) -> Option<&SharedPaddingPolicy<Self::Samp>> {
+        self.settings().and_then(|settings| settings.padding_policy.as_ref())
+    }
+    /// Edit-cache transforms which turn paddings into instructions only know [`StepPadding`]
+    fn check_step_padding(&self, action: &str) -> Result<(), StreamerError> {
+        match self.custom_padding_policy() {
+            Some(policy) => Err(StreamerError::Incompatible {
+                ctx: ErrCtx::chan(self.name()),
+                msg: format!("cannot {action} with the custom padding policy \"{}\"", policy.describe()),
+            }),
+            None => Ok(()),
+        }
+    }
+    /// Padding segments `(end_pos, func)` for the gap `[end_pos, next_edge)` after `instr` (which has a specific duration)
+    /// according to the channel's padding policy.
+    ///
+    /// Returns [`StreamerError::InvalidArgument`] if the policy doesn't cover the gap back-to-back.
+    fn pad_gap(&self, instr: &Instr<Self::Samp>, func: &dyn FnTraitSet<Self::Samp>, next_edge: usize) -> Result<PadSegs<Self::Samp>, StreamerError> {
+        let (end_pos, keep_val) = instr.end_spec().unwrap();
+        let gap = PadGap {
+            start_pos: end_pos,
+            end_pos: next_edge,
+            last_val: self.helper_eval_func(end_pos, func),
+            dflt_val: self.dflt_val(),
+            keep_val: keep_val || self.hold_last_val(),
+            clk_period: self.clk_period(),
+        };
+        let Some(policy) = self.custom_padding_policy() else {
+            return Ok(StepPadding.pad(&gap))
+        };
+        let segs = policy.pad(&gap);
+        let mut seg_start = end_pos;
+        let back_to_back = segs.iter().all(|&(seg_end, _)| {
+            let non_empty = seg_start < seg_end;
+            seg_start = seg_end;
+            non_empty
+        });
+        if !back_to_back || seg_start != next_edge {
+            return Err(StreamerError::InvalidArgument {
+                ctx: ErrCtx::chan(self.name()),
+                msg: format!(
+                    "padding policy \"{}\" did not cover the gap [{end_pos}, {next_edge}) after instruction {instr} back-to-back",
+                    policy.describe()
+                ),
+            })
+        }
+        Ok(segs)
+    }
+
+    /// Time to tick conversion policy - see [`crate::rounding`]
+    fn tick_rounding(&self) -> TickRounding {
+        self.settings().map(|settings| settings.tick_rounding).unwrap_or_default()
+    }
+    /// Sets the time to tick conversion policy used by the `add_instr*` methods.
+    ///
+    /// Returns [`StreamerError::Incompatible`] if the channel has no [`BaseChan::settings`].
+    fn set_tick_rounding(&mut self, rounding: TickRounding) -> Result<(), StreamerError> {
+        self.settings_for("tick rounding policies")?.tick_rounding = rounding;
+        Ok(())
+    }
+    /// Clock tick of time `t` (in seconds) according to [`BaseChan::tick_rounding`].
+    ///
+    /// Returns [`StreamerError::InvalidArgument`] if `t` is off the clock grid and the policy is [`TickRounding::Exact`],
+    /// [`StreamerError::NonFinite`] / [`StreamerError::OutOfRange`] if `t` is not finite, negative, or beyond [`MAX_POS`](crate::rounding::MAX_POS).
+    fn time_to_pos(&self, t: f64) -> Result<usize, StreamerError> {
+        let rounding = self.tick_rounding();
+        rounding.to_pos(t, self.samp_rate())
+            .map_err(|err| err.to_streamer_err(ErrCtx::chan(self.name()), t, self.samp_rate(), rounding))
+    }
+    /// Signed number of clock ticks of the time difference `dt` (e.g. a shift), checked like [`BaseChan::time_to_pos`]
+    fn time_to_ticks(&self, dt: f64) -> Result<i64, StreamerError> {
+        let rounding = self.tick_rounding();
+        rounding.to_ticks(dt, self.samp_rate())
+            .map_err(|err| err.to_streamer_err(ErrCtx::chan(self.name()), dt, self.samp_rate(), rounding))
+    }
+
+    fn sub_tick_phase(&self) -> bool {
+        self.settings().is_some_and(|settings| settings.sub_tick_phase)
+    }
+    /// Opt-in sub-tick phase correction. Start times are rounded to the clock grid, which shifts an oscillation
+    /// relative to its instruction edge by up to half a tick. With `on = true`, oscillatory functions (see [`Calc::phase_at`])
+    /// are re-phased by the residual recorded in [`Instr::sub_tick`], so the first sample carries the phase
+    /// of the requested start time. Phase-linked instructions continue the (corrected) phase of their chain.
+    ///
+    /// Returns [`StreamerError::Incompatible`] if the channel has no [`BaseChan::settings`].
+    fn set_sub_tick_phase(&mut self, on: bool) -> Result<(), StreamerError> {
+        let settings = self.settings_for("sub-tick phase correction")?;
+        if settings.sub_tick_phase != on {
+            settings.sub_tick_phase = on;
+            self.clear_compile_cache()
+        }
+        Ok(())
+    }
+
+    /// `dur_spec` defaults of the convenience wrappers, see [`BaseChan::set_dur_defaults`]
+    fn dur_defaults(&self) -> DurDefaults {
+        self.settings().map(|settings| settings.dur_defaults).unwrap_or_default()
+    }
+    /// Sets the `dur_spec` defaults consulted by [`BaseChan::add_instr_for`] and [`BaseChan::constant_for`],
+    /// so that scripts don't have to repeat the same `keep_val` and minimum duration with every instruction.
+    ///
+    /// Returns [`StreamerError::InvalidArgument`] if `min_dur` is negative or not finite and
+    /// [`StreamerError::Incompatible`] if the channel has no [`BaseChan::settings`].
+    fn set_dur_defaults(&mut self, defaults: DurDefaults) -> Result<(), StreamerError> {
+        if defaults.min_dur.is_some_and(|min_dur| !min_dur.is_finite() || min_dur < 0.0) {
+            return Err(StreamerError::InvalidArgument {
+                ctx: ErrCtx::chan(self.name()),
+                msg: format!("minimum duration must be finite and non-negative, got {defaults:?}"),
+            })
+        }
+        self.settings_for("duration defaults")?.dur_defaults = defaults;
+        Ok(())
+    }
+
+    /// Clock ticks by which the compile cache is advanced relative to the edit cache, see [`ChanSettings::compile_offset`]
+    fn compile_offset_pos(&self) -> usize {
+        self.settings().map_or(0, |settings| settings.compile_offset)
+    }
+
+    /// Channel is marked as edited if it is enabled and its edit-cache field `instr_list` or any of the override layers is nonempty
     fn got_instructions(&self) -> bool {
-        !self.instr_list().is_empty()
+        self.is_enabled() && (!self.instr_list().is_empty() || self.got_layer_instrs())
     }
 
     /// Compiles the instructions in the channel up to the specified `stop_pos`.
@@ -163,25 +638,55 @@ pub trait BaseChan {
     /// # Arguments
     ///
     /// * `stop_pos`: The position up to which the instructions should be compiled. This is used
-    /// to determine if padding is required at the end of the compiled instruction list.
+    ///   to determine if padding is required at the end of the compiled instruction list.
+    ///   With a trigger delay ([`BaseChan::compile_offset_pos`]) it counts device clock ticks - the edit cache is compiled
+    ///   up to `stop_pos + offset` and the compile cache starts at edit cache position `offset`.
     ///
     /// # Panics
     ///
     /// This method will panic if the last instruction's end position in the `instr_list` exceeds the specified `stop_pos`.
     ///
     /// # Examples
-    fn compile(&mut self, stop_pos: usize) -> Result<(), String> {
+    fn compile(&mut self, stop_pos: usize) -> Result<(), StreamerError> {
+        #[cfg(feature = "profiling")]
+        let _timer = PhaseTimer::start(self.profile(), Phase::Compile);
         self.clear_compile_cache();
 
+        // A muted channel outputs its default value throughout
+        if !self.is_enabled() {
+            let dflt_fn = Arc::new(ConstFn::new(self.dflt_val()));
+            self.compile_cache_fns_mut().push(dflt_fn);
+            self.compile_cache_ends_mut().push(stop_pos);
+            *self.is_fresh_compiled_mut() = true;
+            return self.post_compile_hook().inspect_err(|_| self.clear_compile_cache())
+        }
         // Sanity checks:
         if !self.got_instructions() {
-            return Err(format!("Channel {} does not have any instructions", self.name()))
+            return Err(StreamerError::NoInstructions {
+                ctx: ErrCtx::chan(self.name()),
+                msg: "does not have any instructions".to_string(),
+            })
+        }
+        // With a trigger delay, the edit cache is compiled up to `stop_pos + offset` and advanced by `offset` afterwards
+        let offset = self.compile_offset_pos();
+        if self.first_instr_start_pos().is_some_and(|first_start| first_start < offset) {
+            return Err(StreamerError::OutOfRange {
+                ctx: ErrCtx::chan(self.name()),
+                msg: format!(
+                    "the first instruction starts at {} which is before the trigger delay of {offset} clock ticks",
+                    self.first_instr_start_pos().unwrap()
+                ),
+            })
         }
+        let stop_pos = stop_pos + offset;
         if stop_pos < self.last_instr_end_pos().unwrap() {
-            return Err(format!(
-                "[Channel {}] Attempting to compile with stop_pos {} while instructions end at {}",
-                self.name(), stop_pos, self.last_instr_end_pos().unwrap()
-            ))
+            return Err(StreamerError::OutOfRange {
+                ctx: ErrCtx::chan(self.name()),
+                msg: format!(
+                    "Attempting to compile with stop_pos {} while instructions end at {}",
+                    stop_pos, self.last_instr_end_pos().unwrap()
+                ),
+            })
         }
 
         // (1) Calculate exhaustive instruction coverage from 0 to stop_pos (instructions + padding)
@@ -192,59 +697,178 @@ pub trait BaseChan {
            between 1 (no paddings at all) and 2 (a padding for each) per original instruction on average */
 
         let instr_num_estimate = (1.8 * self.instr_list().len() as f64) as usize;
-        let mut instr_fns: Vec<Box<dyn FnTraitSet<Self::Samp>>> = Vec::with_capacity(instr_num_estimate);
+        let mut instr_fns: Vec<Arc<dyn FnTraitSet<Self::Samp>>> = Vec::with_capacity(instr_num_estimate);
         let mut instr_ends: Vec<usize> = Vec::with_capacity(instr_num_estimate);
 
-        // Padding before the first instruction
-        let first_start_pos = self.instr_list().first().unwrap().start_pos();
+        /* Back-to-back segments holding the same constant (e.g. a DO pulse train with `keep_val` paddings,
+           or a constant instruction followed by a default-value padding equal to it) are merged into one segment.
+           This keeps the compile cache short and `fill_samps` fast. Functions are compared with `Calc::const_val()`,
+           non-constant functions are never merged. */
+        fn push_seg<T: PartialEq>(fns: &mut Vec<Arc<dyn FnTraitSet<T>>>, ends: &mut Vec<usize>, func: Arc<dyn FnTraitSet<T>>, end: usize) {
+            if let (Some(last_fn), Some(last_end)) = (fns.last(), ends.last_mut()) {
+                let same_const = last_fn.const_val().is_some_and(|last_val| func.const_val().is_some_and(|val| val == last_val));
+                if same_const {
+                    *last_end = end;
+                    return
+                }
+            }
+            fns.push(func);
+            ends.push(end);
+        }
+
+        // Padding before the first instruction (the whole interval if only override layers got instructions)
+        let first_start_pos = self.instr_list().first().map_or(stop_pos, |first_instr| first_instr.start_pos());
+        // Padding is summarized in one diagnostic per channel - counted here to keep formatting out of the loop
+        let mut pad_stats = PadStats::default();
         if first_start_pos > 0 {
-            instr_fns.push(Box::new(ConstFn::new(self.dflt_val())));
-            instr_ends.push(first_start_pos);
+            push_seg(&mut instr_fns, &mut instr_ends, Arc::new(ConstFn::new(self.dflt_val())), first_start_pos);
+            pad_stats.add(0, first_start_pos, false);
         }
         // All instructions and paddings after them
         let mut instr_list = self.instr_list().iter().peekable();
+        let mut prev_instr = None;
+        let mut prev_func: Option<Arc<dyn FnTraitSet<Self::Samp>>> = None;
         while let Some(instr) = instr_list.next() {
             let next_edge = match instr_list.peek() {
                 Some(next_instr) => next_instr.start_pos(),
                 None => stop_pos
             };
+            let func = if instr.phase_link() {
+                self.link_phase(instr, prev_instr.zip(prev_func.as_deref()))?
+            } else {
+                self.own_func(instr)
+            };
             // Action depends on instruction end_pos type:
             //  - Some: insert the original instruction as-is + add a separate instruction for padding until the next_edge if there is a gap
             //  - None ("run until next"): insert instruction taking the next_edge as end_pos
             match instr.end_spec() {
                 Some((end_pos, keep_val)) => {
                     // The original instruction:
-                    instr_fns.push(instr.func().clone());
-                    instr_ends.push(end_pos);
+                    push_seg(&mut instr_fns, &mut instr_ends, Arc::clone(&func), end_pos);
                     // Padding:
                     if end_pos < next_edge {
-                        // padding value
-                        let pad_val = if keep_val {
-                            self.helper_eval_func(end_pos, instr.func())
-                        } else {
-                            self.dflt_val()
-                        };
-                        // padding instruction
-                        instr_fns.push(Box::new(ConstFn::new(pad_val)));
-                        instr_ends.push(next_edge);
+                        for (pad_end, pad_fn) in self.pad_gap(instr, func.as_ref(), next_edge)? {
+                            push_seg(&mut instr_fns, &mut instr_ends, pad_fn, pad_end);
+                        }
+                        pad_stats.add(end_pos, next_edge, keep_val || self.hold_last_val());
                     }
                 },
                 None => {
-                    instr_fns.push(instr.func().clone());
-                    instr_ends.push(next_edge);
+                    push_seg(&mut instr_fns, &mut instr_ends, Arc::clone(&func), next_edge);
                 },
             }
+            prev_instr = Some(instr);
+            prev_func = Some(func);
         };
 
-        // (2) Transfer prepared `instr_fns` and `instr_ends` into compile cache vectors
+        if let Some(first_pos) = pad_stats.first_pos {
+            let policy = match self.custom_padding_policy() {
+                Some(policy) => format!(" using the \"{}\" padding policy", policy.describe()),
+                None => String::new(),
+            };
+            let message = format!(
+                "{} padding gaps, {} ticks in total ({} gaps holding the last value, {} with the channel default){policy}",
+                pad_stats.n_gaps, pad_stats.n_ticks, pad_stats.n_held, pad_stats.n_gaps - pad_stats.n_held
+            );
+            let chan_name = self.name();
+            self.diagnostics_mut().record(DiagnosticKind::Padding, DiagnosticStage::Compile, Severity::Info, Some(chan_name), Some(first_pos), message);
+        }
+
+        // (2) Paint the override layers over the base layer coverage
+        if self.got_layer_instrs() {
+            let mut pieces = self.layer_coverage(stop_pos)
+                .into_iter()
+                .map(|(start, end, instr)| Ok((start, end, self.resolved_func(instr)?)))
+                .collect::<Result<Vec<_>, StreamerError>>()?
+                .into_iter()
+                .peekable();
+            let base_fns = std::mem::take(&mut instr_fns);
+            let base_ends = std::mem::take(&mut instr_ends);
+            let mut pos = 0;
+            for (base_fn, base_end) in base_fns.into_iter().zip(base_ends) {
+                while pos < base_end {
+                    let next_piece_start = pieces.peek().map(|(start, _end, _func)| *start);
+                    match next_piece_start {
+                        Some(start) if start <= pos => {
+                            let (_start, end, func) = pieces.next().unwrap();
+                            push_seg(&mut instr_fns, &mut instr_ends, func, end);
+                            pos = end;
+                        },
+                        Some(start) if start < base_end => {
+                            push_seg(&mut instr_fns, &mut instr_ends, Arc::clone(&base_fn), start);
+                            pos = start;
+                        },
+                        _ => {
+                            push_seg(&mut instr_fns, &mut instr_ends, Arc::clone(&base_fn), base_end);
+                            pos = base_end;
+                        },
+                    }
+                }
+            }
+        }
+
+        // (3) Advance by the trigger delay: drop the first `offset` ticks, functions keep seeing nominal times
+        if offset > 0 {
+            let first_idx = instr_ends.partition_point(|&end| end <= offset);
+            let t_shift = -(offset as f64) * self.clk_period();
+            instr_fns = instr_fns.drain(first_idx..).map(|func| match func.const_val() {
+                Some(_) => func,
+                None => Arc::new(TimeMap::shift(func, t_shift)),
+            }).collect();
+            instr_ends = instr_ends.drain(first_idx..).map(|end| end - offset).collect();
+        }
+
+        // (4) Transfer prepared `instr_fns` and `instr_ends` into compile cache vectors
         *self.compile_cache_fns_mut() = instr_fns;
         *self.compile_cache_ends_mut() = instr_ends;
 
         // Consistency check
         assert_eq!(self.compile_cache_fns().len(), self.compile_cache_ends().len());
-        assert_eq!(stop_pos, self.compile_cache_ends().last().unwrap().clone());
+        assert_eq!(stop_pos - offset, *self.compile_cache_ends().last().unwrap());
 
         *self.is_fresh_compiled_mut() = true;
+        self.post_compile_hook().inspect_err(|_| self.clear_compile_cache())
+    }
+
+    /// Splits every compile cache segment longer than `max_seg_samps` ticks into pieces of at most `max_seg_samps` ticks,
+    /// so that backends can rely on bounded work per segment (e.g. to pipeline an hour-long hold).
+    ///
+    /// The pieces share the function of the original segment - functions see absolute times, so the samples don't change.
+    /// Returns [`StreamerError::InvalidArgument`] for `max_seg_samps = 0`.
+    fn split_segments(&mut self, max_seg_samps: usize) -> Result<(), StreamerError> {
+        if max_seg_samps == 0 {
+            return Err(StreamerError::InvalidArgument {
+                ctx: ErrCtx::chan(self.name()),
+                msg: "split_segments(): max_seg_samps must be positive".to_string(),
+            })
+        }
+        self.validate_compile_cache()?;
+        let mut fns = Vec::with_capacity(self.compile_cache_fns().len());
+        let mut ends = Vec::with_capacity(self.compile_cache_ends().len());
+        let mut seg_start = 0;
+        for (func, &end) in self.compile_cache_fns().iter().zip(self.compile_cache_ends().iter()) {
+            while end - seg_start > max_seg_samps {
+                seg_start += max_seg_samps;
+                fns.push(Arc::clone(func));
+                ends.push(seg_start);
+            }
+            fns.push(Arc::clone(func));
+            ends.push(end);
+            seg_start = end;
+        }
+        *self.compile_cache_fns_mut() = fns;
+        *self.compile_cache_ends_mut() = ends;
+        Ok(())
+    }
+
+    /// Called at the end of every successful [`BaseChan::compile`] (muted channels included) with a fresh compile cache.
+    /// Override it to apply hardware-specific transforms - inversion, scaling, dithering, ... - through
+    /// [`BaseChan::compile_cache_fns_mut`] and [`BaseChan::compile_cache_ends_mut`] instead of re-implementing `compile`.
+    /// The default does nothing.
+    ///
+    /// The edit cache is left as is, so [`BaseChan::eval_point`] and [`BaseChan::validation_report`] report
+    /// value-changing transforms as mismatches. If the hook returns `Err`, the compile cache is cleared and `compile` fails.
+    fn post_compile_hook(&mut self) -> Result<(), StreamerError> {
         Ok(())
     }
 
@@ -253,6 +877,10 @@ pub trait BaseChan {
     /// If the compiled cache is empty, it also sets the `fresh_compiled` field to `true`.
     fn clear_edit_cache(&mut self) {
         self.instr_list_mut().clear();
+        if let Some(layers) = self.layer_instrs_mut() {
+            layers.clear()
+        }
+        self.diagnostics_mut().clear();
         self.clear_compile_cache();
     }
     /// Clears the compiled cache of the channel.
@@ -262,61 +890,421 @@ pub trait BaseChan {
     fn clear_compile_cache(&mut self) {
         self.compile_cache_ends_mut().clear();
         self.compile_cache_fns_mut().clear();
-        *self.is_fresh_compiled_mut() = self.instr_list().is_empty();
+        self.diagnostics_mut().clear_stage(DiagnosticStage::Compile);
+        *self.is_fresh_compiled_mut() = !self.got_instructions();
     }
 
-    fn validate_compile_cache(&self) -> Result<(), String> {
+    fn validate_compile_cache(&self) -> Result<(), StreamerError> {
         if self.is_fresh_compiled() {
             Ok(())
         } else {
-            Err(format!("Channel {} is not fresh-compiled. Call compile() first", self.name()))
+            Err(StreamerError::NotCompiled {
+                ctx: ErrCtx::chan(self.name()),
+                msg: "is not fresh-compiled. Call compile() first".to_string(),
+            })
+        }
+    }
+
+    /// Every collision the planned instructions `batch` would run into - with the edit cache or among themselves,
+    /// see [`crate::collisions`]. The edit cache is not modified.
+    fn collision_report(&self, batch: &[InstrSnapshot]) -> CollisionReport {
+        find_collisions(&self.name(), self.instr_list().iter().map(InstrSnapshot::from).collect(), batch)
+    }
+
+    /// Detailed state of the compile cache - see [`crate::validation`].
+    fn validation_report(&self) -> ChanReport {
+        let ends = self.compile_cache_ends();
+        let fns = self.compile_cache_fns();
+        let compiled_stop_pos = ends.last().copied();
+        // Instructions are checked in edit cache positions, the compile cache is advanced by the trigger delay
+        let offset = self.compile_offset_pos();
+        let nominal_stop_pos = compiled_stop_pos.map(|stop_pos| stop_pos + offset);
+
+        // Intervals not covered by instructions. "Go-this" instructions cover everything until the next instruction.
+        let mut gaps = Vec::new();
+        let mut cursor = 0;
+        let mut instr_iter = self.instr_list().iter().peekable();
+        while let Some(instr) = instr_iter.next() {
+            if instr.start_pos() > cursor {
+                gaps.push((cursor, instr.start_pos()))
+            }
+            cursor = match (instr.end_pos(), instr_iter.peek()) {
+                (Some(end_pos), _) => end_pos,
+                (None, Some(next)) => next.start_pos(),
+                (None, None) => nominal_stop_pos.unwrap_or(instr.eff_end_pos()).max(instr.eff_end_pos()),
+            };
+        }
+        if let Some(stop_pos) = nominal_stop_pos {
+            if self.got_instructions() && stop_pos > cursor {
+                gaps.push((cursor, stop_pos))
+            }
+        }
+
+        // Each instruction must be a compile cache segment with the same function:
+        // the segment starts at `start_pos` and ends at `end_pos` (or at the next edge for "go-this" instructions).
+        // Constant instructions may have been merged with neighbouring segments of the same value by `compile` -
+        // for them it is enough that the segment covering the instruction interval holds the same constant.
+        // Base layer instructions (partially) overridden by other layers are not checked.
+        let layer_pieces = self.layer_coverage(nominal_stop_pos.or(self.last_instr_end_pos()).unwrap_or(0));
+        let mut first_mismatch = None;
+        let mut instr_iter = self.instr_list().iter().peekable();
+        while let Some(instr) = instr_iter.next() {
+            let cover_end = match (instr.end_pos(), instr_iter.peek()) {
+                (Some(end_pos), _) => end_pos,
+                (None, Some(next)) => next.start_pos(),
+                (None, None) => usize::MAX,
+            };
+            if layer_pieces.iter().any(|&(start, end, _instr)| start < cover_end && instr.start_pos() < end) {
+                continue
+            }
+            let start_pos = instr.start_pos().saturating_sub(offset);
+            let idx = ends.partition_point(|&end| end <= start_pos);
+            let seg_start = if idx == 0 { 0 } else { ends[idx - 1] };
+            // Segments cut by `split_segments` continue with the same function
+            let mut last_idx = idx;
+            while last_idx + 1 < ends.len().min(fns.len()) && Arc::ptr_eq(&fns[last_idx + 1], &fns[idx]) {
+                last_idx += 1
+            }
+            let expected_end = match (instr.end_pos(), instr_iter.peek()) {
+                (Some(end_pos), _) => Some(end_pos.saturating_sub(offset)),
+                (None, Some(next)) => Some(next.start_pos().saturating_sub(offset)),
+                (None, None) => None,
+            };
+            let matches = idx < ends.len() && idx < fns.len() && match instr.func().const_val() {
+                Some(val) => {
+                    fns[idx].const_val().is_some_and(|seg_val| seg_val == val)
+                        && expected_end.is_none_or(|end| ends[last_idx] >= end)
+                },
+                None => {
+                    seg_start == start_pos
+                        && expected_end.is_none_or(|end| ends[last_idx] == end)
+                        && self.resolved_func(instr).is_ok_and(|func| {
+                            let func: Arc<dyn FnTraitSet<Self::Samp>> = match offset {
+                                0 => func,
+                                _ => Arc::new(TimeMap::shift(func, -(offset as f64) * self.clk_period())),
+                            };
+                            format!("{:?}", fns[idx]) == format!("{func:?}")
+                        })
+                },
+            };
+            if !matches {
+                first_mismatch = Some(InstrSnapshot::from(instr));
+                break
+            }
+        }
+
+        ChanReport {
+            name: self.name(),
+            got_instructions: self.got_instructions(),
+            is_fresh_compiled: self.is_fresh_compiled(),
+            compiled_stop_pos,
+            gaps,
+            first_mismatch,
         }
     }
 
     /// Returns the stop position of the compiled instructions.
+    ///
+    /// Returns `Err` if the compile cache is stale (typically due to users forgetting to re-compile
+    /// after adding pulses) or if the channel didn't get any instructions and is inactive.
+    fn try_compiled_stop_pos(&self) -> Result<usize, StreamerError> {
+        self.validate_compile_cache()?;
+        // Compile cache is valid, but it may be empty - this is only possible if `instr_list` is also empty
+        self.compile_cache_ends().last().copied().ok_or_else(|| StreamerError::NoInstructions {
+            ctx: ErrCtx::chan(self.name()),
+            msg: "has a valid, but empty compile cache - this channel didn't get any instructions and is inactive".to_string(),
+        })
+    }
+    /// Same as [`BaseChan::try_compiled_stop_pos`] but the result is multiplied by sample clock period.
+    fn try_compiled_stop_time(&self) -> Result<f64, StreamerError> {
+        Ok(self.try_compiled_stop_pos()? as f64 * self.clk_period())
+    }
+    /// Panicking version of [`BaseChan::try_compiled_stop_pos`].
+    ///
+    /// Only meant for places where a valid and non-empty compile cache is an invariant
+    /// which was already checked. Everywhere else use the `try_` version.
     fn compiled_stop_pos(&self) -> usize {
-        // Sanity checks:
-        if let Err(msg) = self.validate_compile_cache() {
-            panic!(
-                "{msg}\n\
-                \n\
-                @Backend developers: whenever accessing compile cache, you should first call `validate_compile_cache()` \
-                to ensure that compile cache is valid - up-to-date with the edit cache and has no inconsistencies. \n\
-                \n\
-                This function is meant to be the place to gracefully handle the Err variant if it occurs \
-                (typically due to users forgetting to re-compile after adding pulses). \n\
-                \n\
-                In contrast, other functions assume the cache is valid and rely on it. Some may still \
-                do a 'validate_compile_cache()' under the hood to catch bugs but they will panic on Err."
-            )
-        }
-        if self.compile_cache_ends().is_empty() {
-            // Compile cache is valid, but it is empty - this is only possible if `instr_list` is also empty.
-            // Panic, since we are always supposed to filter the inactive channels out.
-            panic!(
-                "Channel {} has a valid, but empty compile cache - this channel didn't get any instructions and is inactive.\n\
-                \n\
-                @Backend developers: when iterating over channels, you should always filter by `got_instructions()` and skip inactive ones",
-                self.name()
-            )
-        }
-
-        self.compile_cache_ends().last().unwrap().clone()
-    }
-    /// Same as [`total_samps`] but the result is multiplied by sample clock period.
+        self.try_compiled_stop_pos().unwrap_or_else(|err| panic!("{err}"))
+    }
+    /// Panicking version of [`BaseChan::try_compiled_stop_time`].
     fn compiled_stop_time(&self) -> f64 {
         self.compiled_stop_pos() as f64 * self.clk_period()
     }
 
-    /// Returns the effective `end_pos` of the last instruction.
+    /// Stable fingerprint of the compile cache - segment end positions and function parameters
+    /// (functions are captured through their `Debug` representation).
+    ///
+    /// Since the compile cache is built from the sorted edit cache, the result does not depend on the order
+    /// in which instructions were added, but any change of timing or function parameters changes the hash.
+    fn content_hash(&self) -> Result<u64, StreamerError> {
+        self.validate_compile_cache()?;
+
+        let mut hasher = StableHasher::new();
+        hasher.update_usize(self.compile_cache_ends().len());
+        for (end_pos, func) in self.compile_cache_ends().iter().zip(self.compile_cache_fns().iter()) {
+            hasher.update_usize(*end_pos);
+            hasher.update_str(&format!("{func:?}"));
+        }
+        Ok(hasher.finish())
+    }
+
+    /// Checksums of the compiled sample stream in chunks of `chunk` samples (the last one may be shorter),
+    /// see [`samp_checksum`]. Backends compare them with checksums of the buffers they wrote.
+    fn segment_checksums(&self, chunk: usize) -> Result<Vec<u64>, StreamerError> {
+        let stop_pos = self.try_compiled_stop_pos()?;
+        if chunk == 0 {
+            return Err(StreamerError::InvalidArgument {
+                ctx: ErrCtx::chan(self.name()),
+                msg: "segment_checksums(): chunk must be at least one sample".to_string(),
+            })
+        }
+        let mut cursor = ChanSampCursor::new();
+        let mut samps = vec![self.dflt_val(); chunk];
+        (0..stop_pos).step_by(chunk).map(|start_pos| {
+            let samps = &mut samps[..chunk.min(stop_pos - start_pos)];
+            self.fill_samps_from_ticks(&mut cursor, start_pos, samps)?;
+            Ok(samp_checksum(samps))
+        }).collect()
+    }
+
+    /// Returns the `start_pos` of the first instruction (on any layer) or `None` if the edit cache is empty.
+    fn first_instr_start_pos(&self) -> Option<usize> {
+        let layer_firsts = self.layer_instrs().into_iter().flat_map(|layers| layers.values().filter_map(|instr_list| instr_list.first()));
+        self.instr_list().first().into_iter().chain(layer_firsts).map(|first_instr| first_instr.start_pos()).min()
+    }
+
+    /// Returns the effective `end_pos` of the last instruction (on any layer).
     /// If the edit cache is empty, it returns `0`.
     fn last_instr_end_pos(&self) -> Option<usize> {
-        self.instr_list().last().map(|last_instr| last_instr.eff_end_pos())
+        let layer_lasts = self.layer_instrs().into_iter().flat_map(|layers| layers.values().filter_map(|instr_list| instr_list.last()));
+        self.instr_list().last().into_iter().chain(layer_lasts).map(|last_instr| last_instr.eff_end_pos()).max()
+    }
+
+    /// Intervals where the override layers determine the waveform up to `stop_pos`, as disjoint
+    /// `(start_pos, end_pos, instr)` pieces sorted by position. Where several layers overlap, the highest one wins.
+    ///
+    /// An override instruction covers exactly `[start_pos, end_pos)` - there is no padding on override layers,
+    /// the layers below show through after `end_pos`. A "go-this" instruction covers everything until the next
+    /// instruction of the same layer.
+    fn layer_coverage(&self, stop_pos: usize) -> Vec<(usize, usize, &Instr<Self::Samp>)> {
+        let mut pieces: Vec<(usize, usize, &Instr<Self::Samp>)> = Vec::new();
+        let Some(layers) = self.layer_instrs() else {
+            return pieces
+        };
+        for instr_list in layers.values().rev() {
+            let mut instr_iter = instr_list.iter().peekable();
+            while let Some(instr) = instr_iter.next() {
+                let end = match (instr.end_pos(), instr_iter.peek()) {
+                    (Some(end_pos), _) => end_pos,
+                    (None, Some(next)) => next.start_pos(),
+                    (None, None) => stop_pos,
+                }.min(stop_pos);
+                // Keep the parts not covered by higher layers yet
+                let mut cur = instr.start_pos();
+                let mut new_pieces = Vec::new();
+                for &(piece_start, piece_end, _instr) in pieces.iter() {
+                    if piece_end <= cur {
+                        continue
+                    }
+                    if piece_start >= end {
+                        break
+                    }
+                    if piece_start > cur {
+                        new_pieces.push((cur, piece_start, instr))
+                    }
+                    cur = piece_end;
+                }
+                if cur < end {
+                    new_pieces.push((cur, end, instr))
+                }
+                pieces.extend(new_pieces);
+                pieces.sort_by_key(|&(piece_start, _end, _instr)| piece_start);
+            }
+        }
+        pieces
+    }
+
+    /// The override layer instruction determining the value at `pos`, `None` if `pos` is not covered by any override layer
+    fn layer_instr_at(&self, pos: usize) -> Option<&Instr<Self::Samp>> {
+        self.layer_instrs()?.values().rev().find_map(|instr_list| {
+            let instr = instr_list.range(..=pos).next_back()?;
+            let covers = match instr.end_pos() {
+                Some(end_pos) => pos < end_pos,
+                None => instr_list.range(pos + 1..).next().is_none_or(|next| pos < next.start_pos()),
+            };
+            covers.then_some(instr)
+        })
+    }
+
+    /// Re-phased function of the phase-linked `instr` continuing the phase of `prev` - the previous instruction
+    /// of the same layer together with its (already resolved) function
+    fn link_phase(&self, instr: &Instr<Self::Samp>, prev: Option<PrevInstr<Self::Samp>>) -> Result<Arc<dyn FnTraitSet<Self::Samp>>, StreamerError> {
+        let link_err = |reason: String| StreamerError::Incompatible {
+            ctx: ErrCtx::chan(self.name()),
+            msg: format!("cannot continue the phase for instruction {instr}: {reason}"),
+        };
+        let (prev_instr, prev_func) = prev.ok_or_else(|| link_err("there is no previous instruction".to_string()))?;
+        // "Go-this" instruction ends where the next one starts
+        let prev_end_pos = prev_instr.end_pos().unwrap_or(instr.start_pos());
+        let phase = prev_func
+            .phase_at(prev_end_pos as f64 * self.clk_period())
+            .ok_or_else(|| link_err(format!("the previous instruction {prev_instr} is not oscillatory")))?;
+        let func = instr.abs_func(self.clk_period())
+            .with_start_phase(instr.start_pos() as f64 * self.clk_period(), phase)
+            .ok_or_else(|| link_err("its function has no phase".to_string()))?;
+        Ok(Arc::from(func))
+    }
+
+    /// The instruction's own function, re-phased for its sub-tick residual if the channel has [`BaseChan::sub_tick_phase`] on.
+    ///
+    /// The corrected function has the phase at the rounded start tick that the original one has at the requested start
+    /// time - the oscillation moves onto the grid together with the instruction. Non-oscillatory functions are returned as is.
+    fn own_func(&self, instr: &Instr<Self::Samp>) -> Arc<dyn FnTraitSet<Self::Samp>> {
+        let func = instr.abs_func(self.clk_period());
+        if !self.sub_tick_phase() || instr.sub_tick() == 0.0 {
+            return func
+        }
+        let t_start = instr.start_pos() as f64 * self.clk_period();
+        func.phase_at(t_start + instr.sub_tick())
+            .and_then(|phase| func.with_start_phase(t_start, phase))
+            .map_or(func, Arc::from)
+    }
+
+    /// The function `compile` uses for `instr` - the instruction's own function with the phase link (if any) resolved
+    /// and, for channels with [`BaseChan::sub_tick_phase`], the sub-tick phase correction applied to unlinked instructions
+    fn resolved_func(&self, instr: &Instr<Self::Samp>) -> Result<Arc<dyn FnTraitSet<Self::Samp>>, StreamerError> {
+        if !instr.phase_link() {
+            return Ok(self.own_func(instr))
+        }
+        let instr_list = match instr.layer() {
+            0 => Some(self.instr_list()),
+            layer => self.layer_instrs().and_then(|layers| layers.get(&layer)),
+        };
+        // Walk back to the start of the chain, then resolve forward
+        let mut chain = vec![instr];
+        while let Some(prev) = instr_list.and_then(|instr_list| instr_list.range(..chain.last().unwrap().start_pos()).next_back()) {
+            chain.push(prev);
+            if !prev.phase_link() {
+                break
+            }
+        }
+        let mut chain_iter = chain.into_iter().rev();
+        let first = chain_iter.next().unwrap();
+        if first.phase_link() {
+            // The chain starts with a linked instruction - there is nothing to link to (returns the error)
+            return self.link_phase(first, None)
+        }
+        let (mut prev_instr, mut prev_func) = (first, self.own_func(first));
+        for linked_instr in chain_iter {
+            prev_func = self.link_phase(linked_instr, Some((prev_instr, prev_func.as_ref())))?;
+            prev_instr = linked_instr;
+        }
+        Ok(prev_func)
+    }
+    /// Same as [`first_instr_start_pos`] but the result is multiplied by sample clock period.
+    fn first_instr_start_time(&self) -> Option<f64> {
+        self.first_instr_start_pos().map(|start_pos| start_pos as f64 * self.clk_period())
     }
     /// Same as [`last_instr_end_pos`] but the result is multiplied by sample clock period.
     fn last_instr_end_time(&self) -> Option<f64> {
         self.last_instr_end_pos().map(|end_pos| end_pos as f64 * self.clk_period())
     }
+    /// `(first_instr_start_time, last_instr_end_time)` - the time window in which the channel is active.
+    /// `None` if the edit cache is empty.
+    fn activity_window(&self) -> Option<(f64, f64)> {
+        self.first_instr_start_time().zip(self.last_instr_end_time())
+    }
+
+    /// Number of instructions in the edit cache, including override layers
+    fn instr_count(&self) -> usize {
+        let layer_count: usize = self.layer_instrs().map_or(0, |layers| layers.values().map(|instr_list| instr_list.len()).sum());
+        self.instr_list().len() + layer_count
+    }
+    /// Number of segments in the compile cache
+    fn compiled_segment_count(&self) -> Result<usize, StreamerError> {
+        self.validate_compile_cache()?;
+        Ok(self.compile_cache_ends().len())
+    }
+    /// Compile cache segments per second of compiled run time.
+    ///
+    /// Streaming backends pay per segment, so a density approaching `samp_rate` (a segment per sample,
+    /// e.g. from a loop adding one pulse per tick) hints at a pathological sequence.
+    fn segment_density(&self) -> Result<f64, StreamerError> {
+        let stop_pos = self.try_compiled_stop_pos()?;
+        Ok(self.compile_cache_ends().len() as f64 / (stop_pos as f64 * self.clk_period()))
+    }
+
+    /// Compile cache as `(start_pos, end_pos, function description)` segments - lets tests assert the exact
+    /// compiled structure (merging, padding) instead of comparing samples
+    fn compiled_segments(&self) -> Result<Vec<(usize, usize, String)>, StreamerError> {
+        self.validate_compile_cache()?;
+        let ends = self.compile_cache_ends();
+        let starts = std::iter::once(0).chain(ends.iter().copied());
+        Ok(starts.zip(ends).zip(self.compile_cache_fns()).map(|((start, &end), func)| (start, end, func.describe())).collect())
+    }
+
+    /// Constant stretches of the compile cache at least `min_samps` ticks long, see [`crate::idle`]
+    fn idle_segs(&self, min_samps: usize) -> Result<Vec<IdleSeg>, StreamerError> {
+        self.validate_compile_cache()?;
+        let mut segs: Vec<IdleSeg> = Vec::new();
+        let mut seg_start = 0;
+        for (func, &end) in self.compile_cache_fns().iter().zip(self.compile_cache_ends().iter()) {
+            if let Some(val) = func.const_val() {
+                let val = samp_to_f64(&val);
+                match segs.last_mut() {
+                    Some(last) if last.end_pos == seg_start && last.val == val => last.end_pos = end,
+                    _ => segs.push(IdleSeg { start_pos: seg_start, end_pos: end, val }),
+                }
+            }
+            seg_start = end;
+        }
+        segs.retain(|seg| seg.len() >= min_samps);
+        Ok(segs)
+    }
+
+    /// Padding segments inserted by the last compile, sorted by position - see [`crate::padding`].
+    /// A muted channel is a single default value padding.
+    fn padding_segs(&self) -> Result<Vec<PaddingSeg>, StreamerError> {
+        let stop_pos = self.try_compiled_stop_pos()?;
+        let dflt_val = samp_to_f64(&self.dflt_val());
+        if !self.is_enabled() {
+            return Ok(vec![PaddingSeg { start_pos: 0, end_pos: stop_pos, val: dflt_val, after_instr: None, keep_val: None }])
+        }
+        // Segments are collected in edit cache positions and advanced by the trigger delay at the end
+        let offset = self.compile_offset_pos();
+        let stop_pos = stop_pos + offset;
+        let mut segs = Vec::new();
+        let first_start_pos = self.instr_list().first().map_or(stop_pos, |first_instr| first_instr.start_pos());
+        if first_start_pos > 0 {
+            segs.push(PaddingSeg { start_pos: 0, end_pos: first_start_pos, val: dflt_val, after_instr: None, keep_val: None })
+        }
+        let mut instr_iter = self.instr_list().iter().peekable();
+        while let Some(instr) = instr_iter.next() {
+            let next_edge = instr_iter.peek().map_or(stop_pos, |next_instr| next_instr.start_pos());
+            let Some((end_pos, keep_val)) = instr.end_spec().filter(|&(end_pos, _keep_val)| end_pos < next_edge) else {
+                continue
+            };
+            let keep_val = keep_val || self.hold_last_val();
+            let mut seg_start = end_pos;
+            for (seg_end, pad_fn) in self.pad_gap(instr, self.resolved_func(instr)?.as_ref(), next_edge)? {
+                let val = samp_to_f64(&self.helper_eval_func(seg_start, pad_fn.as_ref()));
+                segs.push(PaddingSeg { start_pos: seg_start, end_pos: seg_end, val, after_instr: Some(instr.to_string()), keep_val: Some(keep_val) });
+                seg_start = seg_end;
+            }
+        }
+        if self.got_layer_instrs() {
+            let covered: Vec<(usize, usize)> = self.layer_coverage(stop_pos).into_iter().map(|(start, end, _instr)| (start, end)).collect();
+            segs = segs.into_iter().flat_map(|seg| uncovered(seg, &covered)).collect();
+        }
+        if offset > 0 {
+            segs = segs.into_iter().filter(|seg| seg.end_pos > offset).map(|seg| PaddingSeg {
+                start_pos: seg.start_pos.saturating_sub(offset),
+                end_pos: seg.end_pos - offset,
+                ..seg
+            }).collect();
+        }
+        Ok(segs)
+    }
 
     /// Adds an instruction to the channel.
     ///
@@ -334,88 +1322,129 @@ pub trait BaseChan {
     ///       If `keep_val` is `true`, it will be the last instruction value, otherwise it will be the channel default.
     ///     * `None` - no specified duration, instruction will span until the start of the next instruction or global end.
     ///
-    /// # Panics
+    /// # Errors
     ///
-    /// This method will panic if the new instruction overlaps with any existing instruction.
-    ///
-    /// # Example
-    ///
-    /// ```
-    /// # use nicompiler_backend::channel::*;
-    /// # use nicompiler_backend::instruction::*;
-    /// let mut channel = Channel::new(TaskType::DO, "port0/line0", 1e7, 0.);
-    ///
-    /// // Ask the DO channel to go high at t=1 for 0.5 seconds, then return to default value (0)
-    /// channel.add_instr(Instruction::new_const(1.), 1., Some((0.5, false)));
-    ///
-    /// // Asks the DO channel to go high at t=0.5 for 0.001 seconds and keep its value.
-    /// // This will be merged with the instruction above during compilation.
-    /// channel.add_instr(Instruction::new_const(1.), 0.5, Some((0.001, true)));
-    ///
-    /// // The following line is effectively the same as the two lines above after compilation.
-    /// // However, adding it immediately after the previous instructions will cause an overlap panic.
-    /// // Uncommenting the line below will trigger the panic.
-    /// // channel.add_instr(Instruction::new_const(1.), 0.5, 1., false);
-    /// ```
-    ///
-    /// Expected failure:
-    ///
-    /// ```should_panic
-    /// # use nicompiler_backend::channel::*;
-    /// # use nicompiler_backend::instruction::*;
-    /// let mut channel = Channel::new(TaskType::DO, "port0/line0", 1e7, 0.);
-    /// channel.add_instr(Instruction::new_const(1.), 1., Some((0.5, false)));
-    /// channel.add_instr(Instruction::new_const(1.), 0.5, Some((0.001, true)));
-    /// channel.add_instr(Instruction::new_const(1.), 0.5, Some((1., false))); // This will panic
-    /// ```
-    ///
-    /// The panic message will be:
-    /// ```text
-    /// "Channel port0/line0
-    ///  Instruction InstrBook([CONST, {value: 1}], 5000000-15000000, false) overlaps with the next instruction InstrBook([CONST, {value: 1}], 5000000-5010000, true)"
-    /// ```
-    fn add_instr(&mut self, func: Box<dyn FnTraitSet<Self::Samp>>, t: f64, dur_spec: Option<(f64, bool)>) -> Result<(), String> {
-        // Sanity check - non-negative start time (compare with negative clock half-period to avoid virtual panics for nominal t=0.0)
-        assert!(t > -0.5*self.clk_period(), "Attempted to insert an instruction at negative start time {t}");
-
-        // Convert floating-point start and end times to sample clock ticks
-        let start_pos = (t * self.samp_rate()).round() as usize;
-        let end_spec = match dur_spec {
-            Some((dur, keep_val)) => {
-                let end_pos = ((t + dur) * self.samp_rate()).round() as usize;
-                // Sanity check - pulse length is at leas 1 clock period or longer
-                if end_pos - start_pos < 1 {
-                    let t_start_clock = t * self.samp_rate();
-                    let t_stop = t + dur;
-                    let t_stop_clock = t_stop * self.samp_rate();
-                    return Err(format!(
-                        "[Chan {}]\n\
-                        Requested pulse is too short and collapsed due to rounding to the sample clock grid:\n\
-                        \n\
-                        \t       requested start t = {t}s = {t_start_clock} clock periods was rounded to {start_pos}\n\
-                        \t   requested end (t+dur) = {t_stop}s = {t_stop_clock} clock periods was rounded to {end_pos}\n\
-                        \n\
-                        Note: the shortest pulse length the streamer can produce is 1 sample clock period.\n\
-                        For such short pulses it is very important to align pulse edges with the clock grid\n\
-                        otherwise rounding may lead to significant deviations.",
-                        self.name()
-                    ))
-                }
-                Some((end_pos, keep_val))
-            },
-            None => None,
+    /// Returns [`StreamerError::Collision`] if the new instruction overlaps with an existing one by more than 1 tick
+    /// (a 1-tick overlap is auto-fixed and recorded as a [`DiagnosticKind::OneTickFix`] diagnostic), and
+    /// [`StreamerError::InvalidArgument`] if the instruction collapses to less than 1 tick on the clock grid.
+    fn add_instr(&mut self, func: Box<dyn FnTraitSet<Self::Samp>>, t: f64, dur_spec: Option<(f64, bool)>) -> Result<(), StreamerError> {
+        self.add_instr_with_meta(func, t, dur_spec, None)
+    }
+    /// Same as [`BaseChan::add_instr`], attaching `meta` (label, creator, user data) to the new instruction.
+    /// The metadata is shown whenever the instruction is printed - in collision errors, diagnostics, and reports.
+    fn add_instr_with_meta(&mut self, func: Box<dyn FnTraitSet<Self::Samp>>, t: f64, dur_spec: Option<(f64, bool)>, meta: Option<InstrMeta>) -> Result<(), StreamerError> {
+        self.add_instr_base(func, t, dur_spec, 0, meta, false, false)
+    }
+    /// Same as [`BaseChan::add_instr`] for an oscillatory function (e.g. `Sine`) which continues the phase
+    /// of the previous instruction on this channel instead of using its own phase parameter.
+    ///
+    /// `compile` takes the phase the previous instruction reaches at its end (see [`Calc::phase_at`]) and re-phases
+    /// the new function to start with it (see [`Calc::with_start_phase`]). Linked instructions can be chained -
+    /// the phase is carried through the whole chain, so the phase bookkeeping never has to be done by hand.
+    ///
+    /// Returns [`StreamerError::Incompatible`] if `func` has no phase. A missing or non-oscillatory previous
+    /// instruction is reported by `compile` since instructions can be added in any order.
+    fn add_instr_phase_linked(&mut self, func: Box<dyn FnTraitSet<Self::Samp>>, t: f64, dur_spec: Option<(f64, bool)>) -> Result<(), StreamerError> {
+        self.add_instr_base(func, t, dur_spec, 0, None, true, false)
+    }
+    /// Same as [`BaseChan::add_instr`], but places the instruction on an override `layer`.
+    ///
+    /// Layer `0` is the base layer - the regular edit cache with padding between instructions.
+    /// Instructions on higher layers override everything below them within their interval, so a background waveform
+    /// can be written once and selectively replaced. Collisions are only checked between instructions of the same layer.
+    ///
+    /// Override instructions cover exactly `[t, t + dur)` (`keep_val` has no effect) - the layers below show through
+    /// after the end. The layers are flattened by `compile`, see [`BaseChan::layer_coverage`].
+    /// Returns [`StreamerError::Incompatible`] if the channel doesn't support layers (has no [`BaseChan::settings`]).
+    fn add_instr_on_layer(&mut self, func: Box<dyn FnTraitSet<Self::Samp>>, t: f64, dur_spec: Option<(f64, bool)>, layer: u32) -> Result<(), StreamerError> {
+        self.add_instr_base(func, t, dur_spec, layer, None, false, false)
+    }
+    /// Checks that `func` can be used for an instruction of this channel - event channels only accept constant functions
+    fn check_instr_func(&self, func: &dyn FnTraitSet<Self::Samp>) -> Result<(), StreamerError> {
+        if self.is_event_chan() && func.const_val().is_none() {
+            return Err(StreamerError::Incompatible {
+                ctx: ErrCtx::chan(self.name()),
+                msg: format!("is an event channel and only accepts constant functions, got {}", func.describe()),
+            })
+        }
+        Ok(())
+    }
+    /// Converts the start time `t` and the duration spec of a new instruction to clock ticks - `(start_pos, end_spec)`.
+    ///
+    /// Checked conversion - negative (beyond half a clock period, to tolerate nominal t=0.0), non-finite, and huge times are errors,
+    /// as are pulses collapsing to less than 1 clock period due to rounding (negative `dur` included).
+    fn instr_span_to_pos(&self, t: f64, dur_spec: Option<(f64, bool)>) -> Result<(usize, Option<(usize, bool)>), StreamerError> {
+        let start_pos = self.time_to_pos(t)?;
+        let Some((dur, keep_val)) = dur_spec else {
+            return Ok((start_pos, None))
+        };
+        let end_pos = self.time_to_pos(t + dur)?;
+        if end_pos <= start_pos {
+            let t_start_clock = t * self.samp_rate();
+            let t_stop = t + dur;
+            let t_stop_clock = t_stop * self.samp_rate();
+            return Err(StreamerError::InvalidArgument {
+                ctx: ErrCtx::chan(self.name()),
+                msg: format!(
+                    "Requested pulse is too short and collapsed due to rounding to the sample clock grid:\n\
+                    \n\
+                    \t       requested start t = {t}s = {t_start_clock} clock periods was rounded to {start_pos}\n\
+                    \t   requested end (t+dur) = {t_stop}s = {t_stop_clock} clock periods was rounded to {end_pos}\n\
+                    \n\
+                    Note: the shortest pulse length the streamer can produce is 1 sample clock period.\n\
+                    For such short pulses it is very important to align pulse edges with the clock grid\n\
+                    otherwise rounding may lead to significant deviations."
+                ),
+            })
+        }
+        Ok((start_pos, Some((end_pos, keep_val))))
+    }
+    /// Shared implementation of the `add_instr*` methods
+    #[allow(clippy::too_many_arguments)]
+    fn add_instr_base(
+        &mut self,
+        func: Box<dyn FnTraitSet<Self::Samp>>,
+        t: f64,
+        dur_spec: Option<(f64, bool)>,
+        layer: u32,
+        meta: Option<InstrMeta>,
+        phase_link: bool,
+        local_time: bool
+    ) -> Result<(), StreamerError> {
+        #[cfg(feature = "profiling")]
+        let _timer = PhaseTimer::start(self.profile(), Phase::Edit);
+        if phase_link && func.with_start_phase(0.0, 0.0).is_none() {
+            return Err(StreamerError::Incompatible {
+                ctx: ErrCtx::chan(self.name()),
+                msg: format!("cannot continue the phase with function {} since it has no phase", func.describe()),
+            })
+        }
+        self.check_instr_func(func.as_ref())?;
+        let (start_pos, end_spec) = self.instr_span_to_pos(t, dur_spec)?;
+        let mut new_instr = Instr::new(start_pos, end_spec, func)
+            .with_meta(meta)
+            .with_layer(layer)
+            .with_phase_link(phase_link)
+            .with_sub_tick(t - start_pos as f64 * self.clk_period())
+            .with_time_origin(local_time.then_some(start_pos));
+        let mut fix_records = Vec::new();
+
+        // Collisions are checked against the instructions of the same layer
+        let empty_layer = BTreeSet::new();
+        let instr_list = if layer == 0 {
+            self.instr_list()
+        } else {
+            let layers = self.layer_instrs().ok_or_else(|| StreamerError::Incompatible {
+                ctx: ErrCtx::chan(self.name()),
+                msg: format!("does not support override layers, attempted to add an instruction on layer {layer}"),
+            })?;
+            layers.get(&layer).unwrap_or(&empty_layer)
         };
-        let mut new_instr = Instr::new(start_pos, end_spec, func);
 
         // Check for any collisions with already existing instructions
         // - collision on the left
-        if let Some(prev) = self.instr_list().range(..&new_instr).next_back() {
-            // Determine the effective end point of the previous instruction
-            let prev_end = prev.eff_end_pos();
-
-            if prev_end <= new_instr.start_pos() {
-                // All good - no collision here!
-            } else if prev_end == new_instr.start_pos() + 1 {
+        if let Some((prev, overlap)) = left_overlap(instr_list, new_instr.start_pos()) {
+            if overlap == 1 {
                 // Collision of precisely 1 tick
                 //  This might be due to a rounding error for back-to-back pulses. Try to auto-fix it, if possible.
                 //  Action depends on the new instruction duration type:
@@ -423,33 +1452,31 @@ pub trait BaseChan {
                 //      - no spec dur => just shift start_pos by 1 tick (if this leads to a collision with an existing neighbor to the right, next check will catch it)
                 match new_instr.dur() {
                     Some(dur) => {
-                        assert!(dur - 1 >= 1, "1-tick collision on the left cannot be resolved by trimming since the new instruction is only 1 tick long");
+                        assert!(dur > 1, "1-tick collision on the left cannot be resolved by trimming since the new instruction is only 1 tick long");
                         *(new_instr.start_pos_mut()) += 1;
+                        fix_records.push((new_instr.start_pos(), format!("1-tick collision on the left with {prev} - trimmed the new instruction start by 1 tick")));
                     },
                     None => {
                         *(new_instr.start_pos_mut()) += 1;
+                        fix_records.push((new_instr.start_pos(), format!("1-tick collision on the left with {prev} - shifted the new go-this instruction start by 1 tick")));
                     },
                 };
             } else {
                 // Serious collision of 2 or more ticks due to a user mistake
-                return Err(format!(
-                    "[Chan {}]\n\
-                    Collision on the left with the following existing instruction:\n\
-                    \t{prev}\n\
-                    The new instruction is:\n\
-                    \t{new_instr}",
-                    self.name()
-                ))
+                return Err(StreamerError::Collision {
+                    ctx: ErrCtx::chan(self.name()),
+                    msg: format!(
+                        "Collision on the left with the following existing instruction:\n\
+                        \t{prev}\n\
+                        The new instruction is:\n\
+                        \t{new_instr}"
+                    ),
+                })
             }
         }
         // - collision on the right
-        if let Some(next) = self.instr_list().range(&new_instr..).next() {
-            // Determine the effective end position of the new instruction
-            let end_pos = new_instr.eff_end_pos();
-
-            if end_pos <= next.start_pos() {
-                // All good - no collision here!
-            } else if end_pos == next.start_pos() + 1 {
+        if let Some((next, overlap)) = right_overlap(instr_list, new_instr.start_pos(), new_instr.eff_end_pos()) {
+            if overlap == 1 {
                 // Collision of precisely 1 tick
                 //  This might be due to a rounding error for back-to-back pulses. Try to auto-fix it, if possible.
                 //  Action depends on the new instruction duration type:
@@ -457,42 +1484,464 @@ pub trait BaseChan {
                 //      - no spec dur => panic since "go_this" is not meant to be inserted right in front of some other instruction
                 match new_instr.dur() {
                     Some(dur) => {
-                        assert!(dur - 1 >= 1, "1-tick collision on the right cannot be resolved by trimming since the new instruction is only 1 tick long");
+                        assert!(dur > 1, "1-tick collision on the right cannot be resolved by trimming since the new instruction is only 1 tick long");
                         new_instr.end_spec_mut().as_mut().unwrap().0 -= 1;
+                        fix_records.push((new_instr.end_pos().unwrap(), format!("1-tick collision on the right with {next} - trimmed the new instruction end by 1 tick")));
                     },
-                    None => return Err(format!(
-                        "[Chan {}] Attempt to insert go_this-type instruction {new_instr} right at the start of another instruction {next}",
-                        self.name()
-                    )),
+                    None => return Err(StreamerError::Collision {
+                        ctx: ErrCtx::chan(self.name()),
+                        msg: format!(
+                            "Attempt to insert go_this-type instruction {new_instr} right at the start of another instruction {next}"
+                        ),
+                    }),
                 }
             } else {
                 // Serious collision of 2 or more ticks due to a user mistake
-                return Err(format!(
-                    "[Chan {}]\n\
-                    The new instruction:\n\
-                    \t{new_instr}\n\
-                    collides on the right with the following existing instruction:\n\
-                    \t{next}",
-                    self.name()
-                ))
+                return Err(StreamerError::Collision {
+                    ctx: ErrCtx::chan(self.name()),
+                    msg: format!(
+                        "The new instruction:\n\
+                        \t{new_instr}\n\
+                        collides on the right with the following existing instruction:\n\
+                        \t{next}"
+                    ),
+                })
             };
         };
 
-        self.instr_list_mut().insert(new_instr);
+        match self.layer_instrs_mut() {
+            Some(layers) if layer > 0 => layers.entry(layer).or_default().insert(new_instr),
+            _ => self.instr_list_mut().insert(new_instr),
+        };
+        *self.is_fresh_compiled_mut() = false;
+        let chan_name = self.name();
+        for (pos, message) in fix_records {
+            self.diagnostics_mut().record(DiagnosticKind::OneTickFix, DiagnosticStage::Edit, Severity::Warning, Some(chan_name.clone()), Some(pos), message);
+        }
+        Ok(())
+    }
+    /// Inserts a single instruction repeating `func` `n_reps` times with the given `period` (in seconds), starting at `t`.
+    ///
+    /// `func` describes one period - it is evaluated on `[t, t + period)` and every later repetition reproduces
+    /// these samples (see [`Repeat`]). The whole train occupies a single compile cache segment regardless of `n_reps`.
+    /// After the last repetition, the channel keeps the last value if `keep_val` is `true` and the default value otherwise.
+    fn add_repeat_instr(&mut self, func: Box<dyn FnTraitSet<Self::Samp>>, t: f64, period: f64, n_reps: usize, keep_val: bool) -> Result<(), StreamerError> {
+        let start_pos = self.time_to_pos(t)?;
+        let period_ticks = self.time_to_pos(period)?;
+        if period_ticks == 0 || n_reps == 0 {
+            return Err(StreamerError::InvalidArgument {
+                ctx: ErrCtx::chan(self.name()),
+                msg: format!(
+                    "add_repeat_instr(): requested period = {period} s ({period_ticks} clock ticks) and n_reps = {n_reps} \
+                    must both be positive"
+                ),
+            })
+        }
+        let repeat = Repeat::new(func, start_pos, period_ticks, n_reps, self.clk_period());
+        // Pass clock grid-aligned times so that the instruction end lands precisely on the last period boundary
+        let t_start = start_pos as f64 * self.clk_period();
+        let dur = (n_reps * period_ticks) as f64 * self.clk_period();
+        self.add_instr(Box::new(repeat), t_start, Some((dur, keep_val)))
+    }
+    /// Same as [`BaseChan::add_instr`] for a slowly varying function (e.g. a multi-second exponential ramp):
+    /// `func` is only computed on every `factor`-th clock tick from the instruction start and held in between (see [`Decimate`]).
+    ///
+    /// The factor is kept in the compile cache as [`Calc::decimation_hint`] so that backends may also generate
+    /// the held samples themselves. `factor = 1` is a plain `add_instr`.
+    fn add_instr_decimated(&mut self, func: Box<dyn FnTraitSet<Self::Samp>>, t: f64, dur_spec: Option<(f64, bool)>, factor: usize) -> Result<(), StreamerError> {
+        match factor {
+            0 => Err(StreamerError::InvalidArgument {
+                ctx: ErrCtx::chan(self.name()),
+                msg: "add_instr_decimated(): decimation factor must be positive".to_string(),
+            }),
+            1 => self.add_instr(func, t, dur_spec),
+            _ => {
+                let start_pos = self.time_to_pos(t)?;
+                let decimated = Decimate::new(func, start_pos, factor, self.clk_period());
+                self.add_instr(Box::new(decimated), t, dur_spec)
+            }
+        }
+    }
+    /// Same as [`BaseChan::add_instr`], but `func` is evaluated in time local to the instruction: `τ = t - t_start`,
+    /// so that envelopes defined from `τ = 0` don't need their `t0` parameter offset by hand.
+    ///
+    /// `t_start` is the start time rounded to the clock grid, so `func` sees exactly `τ = 0` on the first tick.
+    /// The instruction only records its [`Instr::time_origin`] - `func` itself is not wrapped, so one function object
+    /// can be shared by many instructions. `compile` resolves it to absolute time, see [`Instr::abs_func`].
+    fn add_instr_local(&mut self, func: Box<dyn FnTraitSet<Self::Samp>>, t: f64, dur_spec: Option<(f64, bool)>) -> Result<(), StreamerError> {
+        self.add_instr_base(func, t, dur_spec, 0, None, false, true)
+    }
+
+    /// Stamps the template `func` at every time of `start_times` - one instruction of duration `dur` per pulse,
+    /// after which the channel keeps the last value if `keep_val` is `true` and the default value otherwise.
+    ///
+    /// `func` is evaluated in local time (see [`BaseChan::add_instr_local`]), so the template is defined from `τ = 0`
+    /// and a single function object is shared by all pulses. All pulses are checked for collisions at once -
+    /// among themselves and against the existing instructions - before any of them is inserted: on error nothing is added.
+    ///
+    /// The contract is stricter than that of [`BaseChan::add_instr`]: a 1-tick overlap is a [`StreamerError::Collision`]
+    /// rather than auto-fixed, since trimming pulses of a template array would silently make them unequal.
+    /// The pulses always go to the base layer with an explicit duration - there is no layer, [`BaseChan::dur_defaults`],
+    /// or phase-link variant.
+    fn add_pulse_array(&mut self, func: Box<dyn FnTraitSet<Self::Samp>>, start_times: &[f64], dur: f64, keep_val: bool) -> Result<(), StreamerError> {
+        let func: Arc<dyn FnTraitSet<Self::Samp>> = Arc::from(func);
+        self.add_pulses_base(start_times, |_idx| func.clone(), dur, keep_val)
+    }
+    /// [`BaseChan::add_pulse_array`] with a distinct function instance per pulse: function `func_name` built by `registry`
+    /// from `args`, with the parameters named in `overrides` taking their `idx`-th value for the pulse at `start_times[idx]`
+    /// (e.g. `{"amp": amplitudes}` for per-pulse amplitude compensation). See [`FnRegistry::build_array`] - instances
+    /// with identical parameters share one function object.
+    #[allow(clippy::too_many_arguments)]
+    fn add_pulse_array_by_name(
+        &mut self,
+        registry: &FnRegistry,
+        func_name: &str,
+        args: &FnArgs,
+        overrides: &IndexMap<String, Vec<f64>>,
+        start_times: &[f64],
+        dur: f64,
+        keep_val: bool
+    ) -> Result<(), StreamerError> {
+        let funcs = registry.build_array::<Self::Samp>(func_name, args, overrides, start_times.len())?;
+        self.add_pulses_base(start_times, |idx| funcs[idx].clone(), dur, keep_val)
+    }
+    /// Shared implementation of the pulse array methods - inserts one local-time instruction per start time,
+    /// with the function `func_at(idx)` for the pulse `idx`.
+    ///
+    /// Bypasses [`BaseChan::add_instr`]: the 1-tick collision fix, override layers, [`BaseChan::dur_defaults`], and
+    /// phase links don't apply, see [`BaseChan::add_pulse_array`].
+    fn add_pulses_base(
+        &mut self,
+        start_times: &[f64],
+        func_at: impl Fn(usize) -> Arc<dyn FnTraitSet<Self::Samp>>,
+        dur: f64,
+        keep_val: bool
+    ) -> Result<(), StreamerError> {
+        #[cfg(feature = "profiling")]
+        let _timer = PhaseTimer::start(self.profile(), Phase::Edit);
+        let mut instrs = Vec::with_capacity(start_times.len());
+        for (idx, &t) in start_times.iter().enumerate() {
+            let func = func_at(idx);
+            self.check_instr_func(func.as_ref())?;
+            let (start_pos, end_spec) = self.instr_span_to_pos(t, Some((dur, keep_val)))
+                .map_err(|err| err.prefixed(&format!("pulse {idx} of the array")))?;
+            instrs.push(
+                Instr::new_shared(start_pos, end_spec, func)
+                    .with_sub_tick(t - start_pos as f64 * self.clk_period())
+                    .with_time_origin(Some(start_pos))
+            );
+        }
+        instrs.sort_by_key(|instr| instr.start_pos());
+
+        // Consolidated collision check - the pulses among themselves, then each against its existing neighbours
+        if let Some(pair) = instrs.windows(2).find(|pair| pair[0].eff_end_pos() > pair[1].start_pos()) {
+            return Err(StreamerError::Collision {
+                ctx: ErrCtx::chan(self.name()),
+                msg: format!("pulse array overlaps itself:\n\t{}\n\t{}", pair[0], pair[1]),
+            })
+        }
+        for instr in instrs.iter() {
+            let overlap = left_overlap(self.instr_list(), instr.start_pos())
+                .or_else(|| right_overlap(self.instr_list(), instr.start_pos(), instr.eff_end_pos()));
+            if let Some((existing, _)) = overlap {
+                return Err(StreamerError::Collision {
+                    ctx: ErrCtx::chan(self.name()),
+                    msg: format!(
+                        "pulse array collides with an existing instruction - nothing was added:\n\
+                        \tpulse:    {instr}\n\
+                        \texisting: {existing}"
+                    ),
+                })
+            }
+        }
+        self.instr_list_mut().extend(instrs);
+        *self.is_fresh_compiled_mut() = false;
+        Ok(())
+    }
+
+    /// Plays the sample array `samps` (spacing `dt`, linear interpolation - see [`ArrayFn`]) starting at `t`.
+    /// The instruction lasts `samps.len() * dt`, after it the channel keeps the last sample if `keep_val` is `true`.
+    fn play_array(&mut self, samps: impl Into<Arc<[f64]>>, t: f64, dt: f64, keep_val: bool) -> Result<(), StreamerError>
+        where Self: BaseChan<Samp = f64>
+    {
+        let samps: Arc<[f64]> = samps.into();
+        if samps.is_empty() || !dt.is_finite() || dt <= 0.0 {
+            return Err(StreamerError::InvalidArgument {
+                ctx: ErrCtx::chan(self.name()),
+                msg: format!("play_array(): got {} samples with dt = {dt} s, need at least one sample and positive dt", samps.len()),
+            })
+        }
+        let func = ArrayFn::new(samps, dt, Interp::Linear, t);
+        let dur = func.dur();
+        self.add_instr(Box::new(func), t, Some((dur, keep_val)))
+    }
+
+    /// Moves all edit-cache instructions by `dt` seconds (positive `dt` - later in time).
+    ///
+    /// The shift is converted to whole clock ticks with the channel's [`TickRounding`] policy. Instruction functions are wrapped
+    /// into [`TimeMap`] so that the produced waveform moves together with the instruction interval.
+    ///
+    /// Returns `Err` without changing anything if the shift would move the first instruction to negative time.
+    fn shift(&mut self, dt: f64) -> Result<(), StreamerError> {
+        let shift_ticks = self.time_to_ticks(dt)?;
+        if self.first_instr_start_pos().is_some_and(|first_start| first_start as i64 + shift_ticks < 0) {
+            return Err(StreamerError::OutOfRange {
+                ctx: ErrCtx::chan(self.name()),
+                msg: format!(
+                    "cannot shift by dt = {dt} s ({shift_ticks} clock ticks) since the first instruction \
+                    starts at start_pos = {} and would be moved to negative time",
+                    self.first_instr_start_pos().unwrap()
+                ),
+            })
+        }
+        if shift_ticks == 0 || !self.got_instructions() {
+            return Ok(())
+        }
+
+        let move_pos = |pos: usize| (pos as i64 + shift_ticks) as usize;
+        let t_shift = shift_ticks as f64 * self.clk_period();
+        let shift_list = |instr_list: BTreeSet<Instr<Self::Samp>>| -> BTreeSet<Instr<Self::Samp>> {
+            instr_list.into_iter().map(|mut instr| {
+                *instr.start_pos_mut() = move_pos(instr.start_pos());
+                if let Some((end_pos, _keep_val)) = instr.end_spec_mut() {
+                    *end_pos = move_pos(*end_pos);
+                }
+                // Local-time functions move together with their origin
+                match instr.time_origin_mut() {
+                    Some(origin) => *origin = move_pos(*origin),
+                    None => {
+                        let func = instr.shared_func();
+                        *instr.func_mut() = Arc::new(TimeMap::shift(func, t_shift));
+                    },
+                }
+                instr
+            }).collect()
+        };
+        let old_instr_list = std::mem::take(self.instr_list_mut());
+        *self.instr_list_mut() = shift_list(old_instr_list);
+        if let Some(layers) = self.layer_instrs_mut() {
+            for instr_list in layers.values_mut() {
+                *instr_list = shift_list(std::mem::take(instr_list));
+            }
+        }
+        *self.is_fresh_compiled_mut() = false;
+        Ok(())
+    }
+
+    /// Restricts the edit cache to the window `[t_start, t_end)` and moves it to start at time `0`.
+    ///
+    /// Window edges are rounded to whole clock ticks. Instructions straddling an edge are split - only the part inside
+    /// the window is kept, with the function wrapped into [`TimeMap`] so that it still sees the original times.
+    /// Phase links are resolved (see [`BaseChan::resolved_func`]) since the instruction they continue may be cut away,
+    /// and a `keep_val` padding running across `t_start` turns into a constant instruction at the window start.
+    ///
+    /// Returns `Err` without changing anything if the window is empty, a phase link can't be resolved,
+    /// or the channel has a custom padding policy.
+    fn crop(&mut self, t_start: f64, t_end: f64) -> Result<(), StreamerError> {
+        self.check_step_padding("crop")?;
+        let (start, end) = ((t_start * self.samp_rate()).round(), (t_end * self.samp_rate()).round());
+        if t_start.is_nan() || start < 0.0 || end.is_nan() || end <= start {
+            return Err(StreamerError::InvalidArgument {
+                ctx: ErrCtx::chan(self.name()),
+                msg: format!("cannot crop to the window [{t_start}, {t_end}) s - it must be non-empty and start at non-negative time"),
+            })
+        }
+        let (start, end) = (start as usize, end as usize);
+        let t_shift = -(start as f64) * self.clk_period();
+
+        let crop_list = |instr_list: &BTreeSet<Instr<Self::Samp>>, padded: bool| -> Result<BTreeSet<Instr<Self::Samp>>, StreamerError> {
+            let mut cropped = BTreeSet::new();
+            let mut instr_iter = instr_list.iter().peekable();
+            while let Some(instr) = instr_iter.next() {
+                if instr.start_pos() >= end {
+                    break
+                }
+                let next_start = instr_iter.peek().map(|next| next.start_pos());
+                let func = self.resolved_func(instr)?;
+                match instr.end_spec() {
+                    // Ends before the window - only a `keep_val` padding can reach into it
+                    Some((end_pos, keep_val)) if end_pos <= start => {
+                        if padded && (keep_val || self.hold_last_val()) && next_start.is_none_or(|next_start| next_start > start) {
+                            let pad_val = self.helper_eval_func(end_pos, func.as_ref());
+                            let end_spec = next_start.map(|next_start| (next_start.min(end) - start, true));
+                            cropped.insert(Instr::new(0, end_spec, Box::new(ConstFn::new(pad_val))).with_layer(instr.layer()));
+                        }
+                    },
+                    // "Go-this" instruction which is over before the window
+                    None if next_start.is_some_and(|next_start| next_start <= start) => {},
+                    end_spec => {
+                        let end_spec = end_spec.map(|(end_pos, keep_val)| (end_pos.min(end) - start, keep_val));
+                        cropped.insert(
+                            Instr::new(instr.start_pos().max(start) - start, end_spec, Box::new(TimeMap::shift(func, t_shift)))
+                                .with_meta(instr.meta().cloned())
+                                .with_layer(instr.layer())
+                        );
+                    },
+                }
+            }
+            Ok(cropped)
+        };
+        let instr_list = crop_list(self.instr_list(), true)?;
+        let layers = match self.layer_instrs() {
+            Some(layers) => Some(
+                layers.iter()
+                    .map(|(&layer, instr_list)| Ok((layer, crop_list(instr_list, false)?)))
+                    .collect::<Result<LayerInstrs<Self::Samp>, StreamerError>>()?
+            ),
+            None => None,
+        };
+
+        *self.instr_list_mut() = instr_list;
+        if let (Some(layers), Some(old_layers)) = (layers, self.layer_instrs_mut()) {
+            *old_layers = layers;
+        }
+        *self.is_fresh_compiled_mut() = false;
+        Ok(())
+    }
+
+    /// Re-times the edit cache to play the interval `[0, t_total)` backwards: tick `pos` of the result holds
+    /// what tick `total_pos - 1 - pos` held before. Instruction functions are wrapped into [`TimeMap::mirror`].
+    ///
+    /// Reversed instructions all get a specific duration. Since paddings come after an instruction and would
+    /// end up in front of it, `keep_val` paddings (all paddings with [`BaseChan::hold_last_val`]) become explicit constant
+    /// instructions and the rest stay default value gaps. Phase links are resolved (see [`BaseChan::resolved_func`]) as there is nothing to continue
+    /// once the order is flipped.
+    ///
+    /// Returns `Err` without changing anything if instructions don't fit into `t_total`, a phase link can't be resolved,
+    /// or the channel has a custom padding policy.
+    fn reverse(&mut self, t_total: f64) -> Result<(), StreamerError> {
+        self.check_step_padding("reverse")?;
+        let total_pos = self.time_to_pos(t_total)?;
+        let last_end_pos = self.layer_instrs()
+            .into_iter()
+            .flat_map(|layers| layers.values())
+            .chain(std::iter::once(self.instr_list()))
+            .filter_map(|instr_list| instr_list.last().map(|instr| instr.eff_end_pos()))
+            .max()
+            .unwrap_or(0);
+        if total_pos == 0 || total_pos < last_end_pos {
+            return Err(StreamerError::OutOfRange {
+                ctx: ErrCtx::chan(self.name()),
+                msg: format!(
+                    "cannot reverse over t_total = {t_total} s ({total_pos} clock ticks) - it must be non-empty \
+                    and instructions end at end_pos = {last_end_pos}"
+                ),
+            })
+        }
+        let t_mirror = (total_pos - 1) as f64 * self.clk_period();
+
+        let reverse_list = |instr_list: &BTreeSet<Instr<Self::Samp>>, padded: bool| -> Result<BTreeSet<Instr<Self::Samp>>, StreamerError> {
+            let mut reversed = BTreeSet::new();
+            let mut instr_iter = instr_list.iter().peekable();
+            while let Some(instr) = instr_iter.next() {
+                let next_edge = instr_iter.peek().map_or(total_pos, |next| next.start_pos());
+                let end_pos = instr.end_pos().unwrap_or(next_edge);
+                let func = self.resolved_func(instr)?;
+                if padded && (instr.keep_val() == Some(true) || self.hold_last_val()) && end_pos < next_edge {
+                    let pad_val = self.helper_eval_func(end_pos, func.as_ref());
+                    reversed.insert(Instr::new(total_pos - next_edge, Some((total_pos - end_pos, false)), Box::new(ConstFn::new(pad_val))));
+                }
+                reversed.insert(
+                    Instr::new(total_pos - end_pos, Some((total_pos - instr.start_pos(), false)), Box::new(TimeMap::mirror(func, t_mirror)))
+                        .with_meta(instr.meta().cloned())
+                        .with_layer(instr.layer())
+                );
+            }
+            // With the hold policy, the last reversed instruction would hold its value over the default value interval
+            // which preceded the first instruction
+            if padded && self.hold_last_val() {
+                if let Some(first_start) = instr_list.first().map(|first| first.start_pos()).filter(|&first_start| first_start > 0) {
+                    reversed.insert(Instr::new(total_pos - first_start, Some((total_pos, false)), Box::new(ConstFn::new(self.dflt_val()))));
+                }
+            }
+            Ok(reversed)
+        };
+        let instr_list = reverse_list(self.instr_list(), true)?;
+        let layers = match self.layer_instrs() {
+            Some(layers) => Some(
+                layers.iter()
+                    .map(|(&layer, instr_list)| Ok((layer, reverse_list(instr_list, false)?)))
+                    .collect::<Result<LayerInstrs<Self::Samp>, StreamerError>>()?
+            ),
+            None => None,
+        };
+
+        *self.instr_list_mut() = instr_list;
+        if let (Some(layers), Some(old_layers)) = (layers, self.layer_instrs_mut()) {
+            *old_layers = layers;
+        }
         *self.is_fresh_compiled_mut() = false;
         Ok(())
     }
+
     /// Utility function to add a constant instruction to the channel
-    fn constant(&mut self, val: Self::Samp, t: f64, dur_spec: Option<(f64, bool)>) -> Result<(), String> {
+    fn constant(&mut self, val: Self::Samp, t: f64, dur_spec: Option<(f64, bool)>) -> Result<(), StreamerError> {
         self.add_instr(Box::new(ConstFn::new(val)), t, dur_spec)
     }
-    fn add_reset_instr(&mut self, reset_pos: usize) -> Result<(), String> {
+    /// [`BaseChan::add_instr`] lasting `dur` seconds with `keep_val` and minimum duration taken from [`BaseChan::dur_defaults`]
+    fn add_instr_for(&mut self, func: Box<dyn FnTraitSet<Self::Samp>>, t: f64, dur: f64) -> Result<(), StreamerError> {
+        let dur_spec = self.dur_defaults().dur_spec(dur);
+        self.add_instr(func, t, Some(dur_spec))
+    }
+    /// [`BaseChan::constant`] lasting `dur` seconds with `keep_val` and minimum duration taken from [`BaseChan::dur_defaults`]
+    fn constant_for(&mut self, val: Self::Samp, t: f64, dur: f64) -> Result<(), StreamerError> {
+        self.add_instr_for(Box::new(ConstFn::new(val)), t, dur)
+    }
+    /// Switches the channel to `val` at `t` and keeps it there until the next instruction - the event channel
+    /// form of a "go-this" [`BaseChan::constant`] instruction (usable on sampled channels as well).
+    fn add_event(&mut self, t: f64, val: Self::Samp) -> Result<(), StreamerError> {
+        self.constant(val, t, None)
+    }
+    /// Returns the channel to its default value at `t` with a "go-this" instruction.
+    ///
+    /// Unlike [`BaseChan::add_reset_instr`], usable at any time mid-sequence - it is an ordinary instruction
+    /// going through the usual collision checks, and later instructions may follow it.
+    fn off(&mut self, t: f64) -> Result<(), StreamerError> {
+        self.constant(self.dflt_val(), t, None)
+    }
+    /// [`BaseChan::add_instr`] with unit-carrying times, see [`crate::units`]
+    fn add_instr_at(&mut self, func: Box<dyn FnTraitSet<Self::Samp>>, t: impl Into<TimePoint>, dur_spec: Option<(Duration, bool)>) -> Result<(), StreamerError> {
+        self.add_instr(func, t.into().as_secs(), dur_spec.map(|(dur, keep_val)| (dur.as_secs(), keep_val)))
+    }
+    /// [`BaseChan::constant`] with unit-carrying times, see [`crate::units`]
+    fn constant_at(&mut self, val: Self::Samp, t: impl Into<TimePoint>, dur_spec: Option<(Duration, bool)>) -> Result<(), StreamerError> {
+        self.add_instr_at(Box::new(ConstFn::new(val)), t, dur_spec)
+    }
+    /// [`BaseChan::add_event`] with a unit-carrying time, see [`crate::units`]
+    fn add_event_at(&mut self, t: impl Into<TimePoint>, val: Self::Samp) -> Result<(), StreamerError> {
+        self.constant_at(val, t, None)
+    }
+    /// Defines (or redefines) the named preset value `name`, e.g. a "standby" state to park the hardware at
+    /// between experiment phases. Use it with [`BaseChan::add_preset_instr`].
+    ///
+    /// Returns [`StreamerError::Incompatible`] if the channel has no [`BaseChan::settings`].
+    fn define_preset(&mut self, name: &str, val: Self::Samp) -> Result<(), StreamerError> {
+        self.settings_for("presets")?.presets.insert(name.to_string(), val);
+        Ok(())
+    }
+    /// Moves the channel to the value of preset `name` at `t` with a "go-this" instruction (see [`BaseChan::off`]).
+    fn add_preset_instr(&mut self, name: &str, t: f64) -> Result<(), StreamerError> {
+        let Some(val) = self.preset(name) else {
+            return Err(StreamerError::NotFound {
+                ctx: ErrCtx::chan(self.name()),
+                msg: format!(
+                    "there is no preset \"{name}\" defined. Defined presets are {:?}",
+                    self.settings().map(|settings| settings.presets.keys().collect::<Vec<_>>()).unwrap_or_default()
+                ),
+            })
+        };
+        self.constant(val, t, None)
+    }
+    fn add_reset_instr(&mut self, reset_pos: usize) -> Result<(), StreamerError> {
         if self.last_instr_end_pos().is_some_and(|last_instr_end| reset_pos < last_instr_end) {
-            return Err(format!(
-                "Requested channel {} to insert reset instruction at reset_pos = {reset_pos} \
-                which is below the last_instr_end_pos = {}",
-                self.name(), self.last_instr_end_pos().unwrap()
-            ))
+            return Err(StreamerError::OutOfRange {
+                ctx: ErrCtx::chan(self.name()),
+                msg: format!(
+                    "requested to insert reset instruction at reset_pos = {reset_pos} \
+                    which is below the last_instr_end_pos = {}",
+                    self.last_instr_end_pos().unwrap()
+                ),
+            })
         }
         let reset_instr = Instr::new(
             reset_pos,
@@ -507,98 +1956,376 @@ pub trait BaseChan {
     /// (it can already be calculated knowing `start_pos`, `res_arr.len()`, and `self.samp_rate()`)
     /// but we require it for efficiency reason - the calling `BaseDev` calculates the `t_arr` once
     /// and then reuses it for every channel by lending a read-only view.
-    fn fill_samps(&self, start_pos: usize, res_arr: &mut [Self::Samp], t_arr: &[f64]) -> Result<(), String> {
+    fn fill_samps(&self, start_pos: usize, res_arr: &mut [Self::Samp], t_arr: &[f64]) -> Result<(), StreamerError> {
+        self.fill_samps_with(&mut ChanSampCursor::new(), start_pos, res_arr, t_arr)
+    }
+
+    /// Same as [`BaseChan::fill_samps`] but keeps track of the compile cache position in `cursor`
+    /// so that consecutive windows `[a, b)`, `[b, c)`, ... skip the binary search over the compile cache.
+    fn fill_samps_with(&self, cursor: &mut ChanSampCursor, start_pos: usize, res_arr: &mut [Self::Samp], t_arr: &[f64]) -> Result<(), StreamerError> {
+        self.fill_samps_base(cursor, start_pos, res_arr, Some(t_arr))
+    }
+
+    /// Same as [`BaseChan::fill_samps_with`] but without a materialized time array - every function computes
+    /// its clock grid times from the tick index on the fly (see [`Calc::calc_from_ticks`]).
+    /// Saves the memory traffic of a shared `t_arr` for very large windows.
+    fn fill_samps_from_ticks(&self, cursor: &mut ChanSampCursor, start_pos: usize, res_arr: &mut [Self::Samp]) -> Result<(), StreamerError> {
+        self.fill_samps_base(cursor, start_pos, res_arr, None)
+    }
+
+    /// Base of `fill_samps_with()` and `fill_samps_from_ticks()`: `t_arr = None` selects the on-the-fly time generation
+    fn fill_samps_base(&self, cursor: &mut ChanSampCursor, start_pos: usize, res_arr: &mut [Self::Samp], t_arr: Option<&[f64]>) -> Result<(), StreamerError> {
+        #[cfg(feature = "profiling")]
+        let _timer = PhaseTimer::start(self.profile(), Phase::CalcSamps);
         // Sanity checks (avoid launching panics and return errors instead):
         if !self.got_instructions() {
-            return Err(format!("[Chan {}] fill_samps(): did not get any instructions", self.name()))
+            return Err(StreamerError::NoInstructions {
+                ctx: ErrCtx::chan(self.name()),
+                msg: "fill_samps(): did not get any instructions".to_string(),
+            })
         }
-        self.validate_compile_cache()?;
-        if res_arr.len() != t_arr.len() {
-            return Err(format!(
-                "[Chan {}] fill_samps(): provided res_arr.len() = {} and t_arr.len() = {} do not match",
-                self.name(), res_arr.len(), t_arr.len()
-            ))
+        let compiled_stop_pos = self.try_compiled_stop_pos()?;
+        if let Some(t_arr) = t_arr.filter(|t_arr| t_arr.len() != res_arr.len()) {
+            return Err(StreamerError::InvalidArgument {
+                ctx: ErrCtx::chan(self.name()),
+                msg: format!(
+                    "fill_samps(): provided res_arr.len() = {} and t_arr.len() = {} do not match",
+                    res_arr.len(), t_arr.len()
+                ),
+            })
         }
         // Window boundaries, start_pos is included and end_pos is not included:
         let window_start = start_pos;
         let window_end = window_start + res_arr.len();
-        if window_end > self.compiled_stop_pos() {
-            return Err(format!(
-                "[Chan {}] fill_samps(): Requested window end position \n\
-                \t start_pos + res_arr.len() = {start_pos} + {} = {window_end} \n\
-                goes beyond the compiled stop position {}",
-                self.name(), res_arr.len(), self.compiled_stop_pos()
-            ))
+        if window_end > compiled_stop_pos {
+            return Err(StreamerError::OutOfRange {
+                ctx: ErrCtx::chan(self.name()),
+                msg: format!(
+                    "fill_samps(): Requested window end position \n\
+                    \t start_pos + res_arr.len() = {start_pos} + {} = {window_end} \n\
+                    goes beyond the compiled stop position {}",
+                    res_arr.len(), compiled_stop_pos
+                ),
+            })
         }
 
-        if res_arr.len() == 0 {
+        if res_arr.is_empty() {
             return Ok(())
         }
 
         // Find all instructions covered (fully or partially) by this window
-        let first_instr_idx = match self.compile_cache_ends().binary_search(&window_start) {
-            Ok(idx) => idx + 1,
-            Err(idx) => idx,
-        };
-        let last_instr_idx = match self.compile_cache_ends().binary_search(&window_end) {
-            Ok(idx) => idx,
-            Err(idx) => idx,
-        };
+        // (walk forward from the first one - the window end is below `compiled_stop_pos`, so the walk stays within the cache)
+        let mut idx = cursor.first_instr_idx(self.compile_cache_ends(), window_start);
 
         // Helper to map "absolute" clock grid position onto the appropriate t/res_arr index - subtract window start position
         let rm_offs = |pos| { pos - window_start };
 
         let mut cur_pos = window_start;
-        for idx in first_instr_idx..=last_instr_idx {
+        loop {
             let instr_end = self.compile_cache_ends()[idx];
             let instr_func = &self.compile_cache_fns()[idx];
 
             let next_pos = std::cmp::min(instr_end, window_end);
-            instr_func.calc(
-                &t_arr[rm_offs(cur_pos)..rm_offs(next_pos)],
-                &mut res_arr[rm_offs(cur_pos)..rm_offs(next_pos)]
-            );
+            let res_slice = &mut res_arr[rm_offs(cur_pos)..rm_offs(next_pos)];
+            match t_arr {
+                Some(t_arr) => instr_func.calc(&t_arr[rm_offs(cur_pos)..rm_offs(next_pos)], res_slice),
+                None => instr_func.calc_from_ticks(cur_pos, self.clk_period(), res_slice),
+            }
             cur_pos = next_pos;
+            if cur_pos == window_end {
+                break
+            }
+            idx += 1;
+        };
+        cursor.idx = idx;
+        cursor.next_pos = Some(window_end);
+        Ok(())
+    }
+
+    /// Cuts the compiled waveform at `abort_pos` and appends a linear ramp from the value at `abort_pos` to the reset value
+    /// over `ramp_ticks` ticks, holding the reset value afterwards until `stop_pos` - see [`crate::abort`].
+    ///
+    /// Only `f64` channels ramp (with a [`LinFn`]). Digital and other non-`f64` channels step to the reset value
+    /// at `abort_pos` right away.
+    ///
+    /// Works on the compile cache only, the edit cache is left untouched. The channel stays fresh-compiled so that the
+    /// abort sequence can be streamed - [`BaseChan::is_fresh_compiled`] then means "the compile cache is ready to stream"
+    /// rather than "the compile cache reflects the edit cache". The next [`BaseChan::compile`] restores the full sequence.
+    fn compile_abort(&mut self, abort_pos: usize, ramp_ticks: usize, stop_pos: usize) -> Result<(), StreamerError> {
+        let compiled_stop_pos = self.try_compiled_stop_pos()?;
+        if abort_pos > compiled_stop_pos || stop_pos <= abort_pos + ramp_ticks {
+            return Err(StreamerError::OutOfRange {
+                ctx: ErrCtx::chan(self.name()),
+                msg: format!(
+                    "compile_abort(): abort_pos {abort_pos} must be within the compiled sequence (stop_pos {compiled_stop_pos}) \
+                    and the ramp of {ramp_ticks} ticks must end before the new stop_pos {stop_pos}"
+                ),
+            })
+        }
+        // Value the ramp starts from - the last sample if the abort is at the very end
+        let mut abort_val = vec![self.dflt_val()];
+        self.fill_samps_from_ticks(&mut ChanSampCursor::new(), abort_pos.min(compiled_stop_pos - 1), &mut abort_val)?;
+        let rst_val = self.rst_val();
+        let ramp = if ramp_ticks == 0 || rst_val.as_f64().is_none() {
+            None
+        } else {
+            let (from, to) = (samp_to_f64(&abort_val[0]), samp_to_f64(&rst_val));
+            let abort_time = abort_pos as f64 * self.clk_period();
+            let slope = (to - from) / (ramp_ticks as f64 * self.clk_period());
+            let lin_fn: Box<dyn FnTraitSet<f64>> = Box::new(LinFn::new(slope, from - slope * abort_time));
+            let lin_fn: Box<dyn Any> = Box::new(lin_fn);
+            // `None` for digital (e.g. `bool`) and other non-`f64` samples - they step instead
+            lin_fn.downcast::<Box<dyn FnTraitSet<Self::Samp>>>().ok().map(|lin_fn| *lin_fn)
         };
+
+        // Keep the segments ending before `abort_pos` and clip the one covering it
+        let n_kept = self.compile_cache_ends().partition_point(|&end| end <= abort_pos);
+        let covering = self.compile_cache_fns().get(n_kept).cloned();
+        let covering_start = n_kept.checked_sub(1).map_or(0, |idx| self.compile_cache_ends()[idx]);
+        self.compile_cache_ends_mut().truncate(n_kept);
+        self.compile_cache_fns_mut().truncate(n_kept);
+        if let Some(func) = covering.filter(|_| covering_start < abort_pos) {
+            self.compile_cache_fns_mut().push(func);
+            self.compile_cache_ends_mut().push(abort_pos);
+        }
+        if let Some(ramp) = ramp {
+            self.compile_cache_fns_mut().push(Arc::from(ramp));
+            self.compile_cache_ends_mut().push(abort_pos + ramp_ticks);
+        }
+        self.compile_cache_fns_mut().push(Arc::new(ConstFn::new(rst_val)));
+        self.compile_cache_ends_mut().push(stop_pos);
+        Ok(())
+    }
+
+    /// `true` if every compile cache segment is a constant ([`Calc::const_val`]) - the waveform
+    /// can be streamed as a list of runs (see [`BaseChan::compiled_runs`]). Typical for digital channels.
+    fn is_run_length(&self) -> bool {
+        self.compile_cache_fns().iter().all(|func| func.const_val().is_some())
+    }
+
+    /// Run-length form of the compiled waveform within the window `[start_pos, end_pos)`.
+    ///
+    /// The compile cache of a piecewise-constant channel already is a run-length encoding, so backends
+    /// which consume edge lists (e.g. pattern generators) can use the runs directly instead of
+    /// rasterizing every sample with [`BaseChan::fill_samps`]. Runs are clipped to the window,
+    /// adjacent runs always hold different values.
+    ///
+    /// Returns [`StreamerError::Incompatible`] if a segment within the window is not constant.
+    fn compiled_runs(&self, start_pos: usize, end_pos: usize) -> Result<Runs<Self::Samp>, StreamerError> {
+        let compiled_stop_pos = self.try_compiled_stop_pos()?;
+        if end_pos > compiled_stop_pos || start_pos > end_pos {
+            return Err(StreamerError::OutOfRange {
+                ctx: ErrCtx::chan(self.name()),
+                msg: format!(
+                    "compiled_runs(): requested window [{start_pos}, {end_pos}) is invalid or exceeds the compiled stop position {compiled_stop_pos}"
+                ),
+            })
+        }
+
+        let ends = self.compile_cache_ends();
+        let mut runs: Runs<Self::Samp> = Vec::new();
+        let mut cur_pos = start_pos;
+        let mut idx = ChanSampCursor::new().first_instr_idx(ends, start_pos);
+        while cur_pos < end_pos {
+            let next_pos = std::cmp::min(ends[idx], end_pos);
+            let val = self.compile_cache_fns()[idx].const_val().ok_or_else(|| StreamerError::Incompatible {
+                ctx: ErrCtx::chan(self.name()),
+                msg: format!(
+                    "compiled_runs(): segment [{cur_pos}, {next_pos}) holds non-constant function {:?} - use fill_samps() instead",
+                    self.compile_cache_fns()[idx]
+                ),
+            })?;
+            match runs.last_mut() {
+                Some(last) if last.val == val => last.end_pos = next_pos,
+                _ => runs.push(Run { start_pos: cur_pos, end_pos: next_pos, val }),
+            }
+            cur_pos = next_pos;
+            idx += 1;
+        }
+        Ok(runs)
+    }
+
+    /// Compiled waveform as a list of value changes up to the compiled stop position.
+    ///
+    /// The first event is at `pos = 0` and gives the initial value, every later event is a change to a new value.
+    /// Meant for event channels (see [`BaseChan::is_event_chan`]), but works for any channel whose compile cache
+    /// is piecewise constant - otherwise returns [`StreamerError::Incompatible`] like [`BaseChan::compiled_runs`].
+    fn compiled_events(&self) -> Result<Events<Self::Samp>, StreamerError> {
+        let runs = self.compiled_runs(0, self.try_compiled_stop_pos()?)?;
+        let clk_period = self.clk_period();
+        Ok(runs.into_iter().map(|run| Event { pos: run.start_pos, t: run.start_pos as f64 * clk_period, val: run.val }).collect())
+    }
+
+    /// Writes the level transitions of the compiled waveform to `path` - `.vcd` or CSV, see [`crate::export`].
+    /// Unlike a sample dump, the file size scales with the number of edges, not with the duration.
+    fn export_edges(&self, path: &str) -> Result<(), StreamerError>
+        where Self: BaseChan<Samp = bool>
+    {
+        let events = self.compiled_events()?;
+        export::write_edges(path, &self.name(), &events, self.try_compiled_stop_pos()? as f64 * self.clk_period())
+    }
+
+    /// Reproduces `edges` (`(t, level)`, sorted by time) with one [`BaseChan::add_event`] per level change,
+    /// starting from the default value. Existing instructions are kept - overlaps are reported as usual.
+    fn add_edges(&mut self, edges: &[(f64, bool)]) -> Result<(), StreamerError>
+        where Self: BaseChan<Samp = bool>
+    {
+        let mut level = self.dflt_val();
+        for &(t, new_level) in edges {
+            if new_level != level {
+                self.add_event(t, new_level)?;
+                level = new_level;
+            }
+        }
         Ok(())
     }
 
+    /// Replays the digital signal `signal` of the capture file `path` (`.vcd` or edge-list CSV, see [`crate::export`])
+    /// on this channel with [`BaseChan::add_edges`]. `signal` may be omitted if the file holds a single signal.
+    fn import_edges(&mut self, path: &str, signal: Option<&str>) -> Result<(), StreamerError>
+        where Self: BaseChan<Samp = bool>
+    {
+        let signals = export::read_signals(path)?;
+        let signal = export::pick_signal(&signals, signal, path)?;
+        self.add_edges(&signal.edges)
+    }
+
+    /// Sorted, disjoint `[start_pos, end_pos)` intervals where a marker following this channel with `rule` is high
+    /// (see [`crate::marker`]). Requires a valid compile cache - the intervals end at the compiled stop position at the latest.
+    fn marker_intervals(&self, rule: &MarkerRule) -> Result<Vec<(usize, usize)>, StreamerError> {
+        let stop_pos = self.try_compiled_stop_pos()?;
+        let mut intervals = Vec::new();
+        match rule {
+            MarkerRule::Threshold(threshold) => {
+                let mut cursor = ChanSampCursor::new();
+                let mut samp_buf = vec![self.dflt_val(); stop_pos.min(1 << 16)];
+                let mut high_start = None;
+                let mut chunk_start = 0;
+                while chunk_start < stop_pos {
+                    let chunk = &mut samp_buf[..(stop_pos - chunk_start).min(1 << 16)];
+                    self.fill_samps_from_ticks(&mut cursor, chunk_start, chunk)?;
+                    for (offs, samp) in chunk.iter().enumerate() {
+                        let high = samp_to_f64(samp).abs() > *threshold;
+                        match (high, high_start) {
+                            (true, None) => high_start = Some(chunk_start + offs),
+                            (false, Some(start)) => {
+                                intervals.push((start, chunk_start + offs));
+                                high_start = None
+                            },
+                            _ => {},
+                        }
+                    }
+                    chunk_start += chunk.len();
+                }
+                if let Some(start) = high_start {
+                    intervals.push((start, stop_pos))
+                }
+            },
+            MarkerRule::DuringInstr => {
+                let mut pieces: Vec<(usize, usize)> = self.layer_coverage(stop_pos).into_iter().map(|(start, end, _instr)| (start, end)).collect();
+                let mut instr_iter = self.instr_list().iter().peekable();
+                while let Some(instr) = instr_iter.next() {
+                    let end = match (instr.end_pos(), instr_iter.peek()) {
+                        (Some(end_pos), _) => end_pos,
+                        (None, Some(next)) => next.start_pos(),
+                        (None, None) => stop_pos,
+                    };
+                    pieces.push((instr.start_pos(), end.min(stop_pos)));
+                }
+                pieces.sort();
+                for (start, end) in pieces {
+                    push_merged(&mut intervals, start, end)
+                }
+            },
+        }
+        Ok(intervals)
+    }
+
     /// This this function is only used for plotting in Python
     /// Here samples are calculated at time points which don't necessarily match sample clock grid ticks.
     /// Typically, users will request n_samps which is smaller than the actual number of clock ticks
     /// between start_time and end_time because otherwise plotting may be extremely slow.
-    fn calc_nsamps(&self, n_samps: usize, start_time: Option<f64>, end_time: Option<f64>) -> Result<Vec<Self::Samp>, String> {
+    fn calc_nsamps(&self, n_samps: usize, start_time: Option<f64>, end_time: Option<f64>) -> Result<Vec<Self::Samp>, StreamerError> {
+        let mut res_arr = vec![self.dflt_val(); n_samps];
+        self.calc_nsamps_into(&mut res_arr, start_time, end_time)?;
+        Ok(res_arr)
+    }
+
+    /// Threshold crossings of the compiled waveform, see [`crate::analysis`]
+    fn find_edges(&self, threshold: f64) -> Result<Vec<Crossing>, StreamerError> {
+        analysis::find_edges(self, threshold)
+    }
+
+    /// Local maxima of the compiled waveform of at least `min_height`, at least `min_spacing` seconds apart, see [`crate::analysis`]
+    fn find_peaks(&self, min_height: f64, min_spacing: f64) -> Result<Vec<Peak>, StreamerError> {
+        analysis::find_peaks(self, min_height, min_spacing)
+    }
+
+    /// `∫ samp dt` of the compiled waveform over `[start_time, end_time]` (defaults: the full waveform), see [`crate::analysis`]
+    fn integral(&self, start_time: Option<f64>, end_time: Option<f64>) -> Result<f64, StreamerError> {
+        analysis::integral(self, start_time, end_time)
+    }
+
+    /// The full compiled waveform converted to `f64` and resampled to `to_rate` - see [`crate::resample`]
+    fn resample(&self, to_rate: f64, method: Resampling) -> Result<Vec<f64>, StreamerError> {
+        let stop_pos = self.try_compiled_stop_pos()?;
+        let mut samps = vec![self.dflt_val(); stop_pos];
+        self.fill_samps_from_ticks(&mut ChanSampCursor::new(), 0, &mut samps)?;
+        let samps: Vec<f64> = samps.iter().map(samp_to_f64).collect();
+        resample::resample(&samps, self.samp_rate(), to_rate, method)
+    }
+
+    /// [`BaseChan::calc_nsamps`] together with the time points of the samples - `(t_arr, samps)`, ready for plotting.
+    fn plot_data(&self, n_samps: usize, start_time: Option<f64>, end_time: Option<f64>) -> Result<(Vec<f64>, Vec<Self::Samp>), StreamerError> {
+        let samps = self.calc_nsamps(n_samps, start_time, end_time)?;
+        // `calc_nsamps` succeeded, so the compile cache is valid and the window is within the compiled stop time
+        let end_time = match end_time {
+            Some(end_time) => end_time,
+            None => self.try_compiled_stop_time()?,
+        };
+        let t_arr = Array1::linspace(start_time.unwrap_or(0.0), end_time, n_samps);
+        Ok((t_arr.to_vec(), samps))
+    }
+
+    /// Same as [`BaseChan::calc_nsamps`] with `n_samps = res_arr.len()`, but writes the samples into
+    /// caller-provided memory (e.g. the buffer of a numpy array - see [`crate::py_tools::calc_nsamps_into`]).
+    fn calc_nsamps_into(&self, res_arr: &mut [Self::Samp], start_time: Option<f64>, end_time: Option<f64>) -> Result<(), StreamerError> {
+        let n_samps = res_arr.len();
         // Sanity checks
         if !self.got_instructions() {
-            return Err(format!("Channel {} did not get any instructions", self.name()))
+            return Err(StreamerError::NoInstructions {
+                ctx: ErrCtx::chan(self.name()),
+                msg: "did not get any instructions".to_string(),
+            })
         }
-        self.validate_compile_cache()?;
+        let compiled_stop_time = self.try_compiled_stop_time()?;
 
-        let start_time = match start_time {
-            Some(start_time) => start_time,
-            None => 0.0
-        };
+        let start_time = start_time.unwrap_or(0.0);
         let end_time = match end_time {
             Some(end_time) => {
-                if end_time > self.compiled_stop_time() {
-                    return Err(format!(
-                        "[Chan {}] requested end_time {end_time} exceeds compiled_stop_time {}. \
-                        If you intended to specify end_time = compiled_stop_time, use end_time = None",
-                        self.name(), self.compiled_stop_time()
-                    ))
+                if end_time > compiled_stop_time {
+                    return Err(StreamerError::OutOfRange {
+                        ctx: ErrCtx::chan(self.name()),
+                        msg: format!(
+                            "requested end_time {end_time} exceeds compiled_stop_time {}. \
+                            If you intended to specify end_time = compiled_stop_time, use end_time = None",
+                            compiled_stop_time
+                        ),
+                    })
                 }
                 end_time
             },
-            None => self.compiled_stop_time()
+            None => compiled_stop_time
         };
         if end_time < start_time {
-            return Err(format!(
-                "[Chan {}] requested end_time {end_time} is below start_time {start_time}",
-                self.name()
-            ))
+            return Err(StreamerError::OutOfRange {
+                ctx: ErrCtx::chan(self.name()),
+                msg: format!(
+                    "requested end_time {end_time} is below start_time {start_time}"
+                ),
+            })
         }
 
-        let mut res_arr = vec![self.dflt_val(); n_samps];
         // Using ndarray::Array1::linspace to initialize t_arr (benchmarks showed it was faster than anything we tried with Vec<f64>)
         let t_arr = Array1::linspace(start_time, end_time, n_samps);
         let t_arr_slice = t_arr.as_slice().expect("[BaseChan::calc_nsamps()] BUG: t_arr.as_slice() returned None");
@@ -646,52 +2373,57 @@ pub trait BaseChan {
             );
             cur_pos = next_pos;
         };
-        Ok(res_arr)
+        Ok(())
     }
 
-    fn eval_point(&self, t: f64) -> Result<Self::Samp, String> {
+    fn eval_point(&self, t: f64) -> Result<Self::Samp, StreamerError> {
         // Sanity check - time `t` should be non-negative
         // (compare against negative clock half-period to avoid virtual panics for nominal t=0.0)
         if t < -0.5*self.clk_period() {
-            return Err(format!("[Chan {}] Negative time {t} passed", self.name()))
+            return Err(StreamerError::OutOfRange { ctx: ErrCtx::chan(self.name()), msg: format!("Negative time {t} passed") })
         }
 
         // Convert `t` to the sample clock grid ticks right away
         let t_pos = (t * self.samp_rate()).round() as usize;
 
+        // Override layers take precedence over the base layer
+        if let Some(layer_instr) = self.layer_instr_at(t_pos) {
+            return Ok(self.helper_eval_func(t_pos, self.resolved_func(layer_instr)?.as_ref()))
+        }
+
         // Find the closest preceding instruction which covers `t_pos` (or padding tail of which covers `t_pos`)
         // - the instruction with the greatest `stop_pos` which still satisfies `start_pos <= t_pos`
-        // Since `self.instr_list' has type `BTreeSet<Instr<Self::Samp>>`, we have to make-up an instruction to do the search
-        let makeup_instr = Instr::new(t_pos, None, Box::new(ConstFn::new(self.dflt_val())));
-        // The actual search (works because `Instr<T>` implements comparison by `start_pos`)
-        let prev_instr = self.instr_list().range(..=makeup_instr).next_back();
+        let prev_instr = self.instr_at(t_pos);
 
         let val = if let Some(prev_instr) = prev_instr {
+            let func = self.resolved_func(prev_instr)?;
             // There is some instruction before `t_pos`.
             // It may either have a specified end position or it may be of "go-this" type:
             //
             //  - If `end_spec` is specified, there are 2 possibilities:
             //      - `t_pos` is covered by the instruction interval `[start_pos, end_pos)`
-            //      - or `t_pos` lies in the constant padding tail.
+            //      - or `t_pos` lies in the padding tail.
             //
             //  - If `end_spec` is None, this is a "go-this" instruction and `t_pos` is automatically covered
             match prev_instr.end_spec() {
-                Some((end_pos, keep_val)) => {
+                Some((end_pos, _keep_val)) => {
                     if t_pos < end_pos {
                         // within [start_pos, end_pos) interval
-                        self.helper_eval_func(t_pos, prev_instr.func())
+                        self.helper_eval_func(t_pos, func.as_ref())
                     } else {
-                        // padding tail
-                        if keep_val {
-                            self.helper_eval_func(end_pos, prev_instr.func())
-                        } else {
-                            self.dflt_val()
-                        }
+                        // padding tail - runs until the next instruction (open-ended after the last one)
+                        let next_edge = self.instr_list()
+                            .range::<Instr<Self::Samp>, _>((Bound::Excluded(prev_instr), Bound::Unbounded))
+                            .next()
+                            .map_or(usize::MAX, |next_instr| next_instr.start_pos());
+                        let pad_segs = self.pad_gap(prev_instr, func.as_ref(), next_edge)?;
+                        let (_pad_end, pad_fn) = pad_segs.iter().find(|(pad_end, _pad_fn)| t_pos < *pad_end).unwrap();
+                        self.helper_eval_func(t_pos, pad_fn.as_ref())
                     }
                 },
                 None => {
                     // "go-this" instruction
-                    self.helper_eval_func(t_pos, prev_instr.func())
+                    self.helper_eval_func(t_pos, func.as_ref())
                 }
             }
         } else {
@@ -702,104 +2434,204 @@ pub trait BaseChan {
         Ok(val)
     }
 
-    /// Helper function to evaluate `Box<dyn FnTraitSet<Self::Samp>` instances on single `usize` points
-    fn helper_eval_func(&self, x: usize, func: &Box<dyn FnTraitSet<Self::Samp>>) -> Self::Samp {
-        let t_arr = vec![x as f64 * self.clk_period()];
-        let mut res_arr = vec![self.dflt_val()];
-        func.calc(&t_arr[..], &mut res_arr[..]);
-        res_arr[0].clone()
+    /// Returns the edit-cache instruction which determines the value at `pos`
+    /// (either directly or through the padding after it), `None` if `pos` lies before the first instruction.
+    fn instr_at(&self, pos: usize) -> Option<&Instr<Self::Samp>> {
+        // `Instr<T>` borrows as its `start_pos`, so the search needs no placeholder instruction
+        self.instr_list().range(..=pos).next_back()
     }
-}
-
-// ==================== Unit tests ====================
-// ToDo - tests are outdated
-#[cfg(test)]
-mod test {
-    /*
-    use std::collections::BTreeSet;
-    use crate::fn_lib_tools::FnTraitSet;
-    use crate::channel::{BaseChan, ConstFn};
-    use crate::instruction::Instr;
 
-    pub struct TestChan {
-        name: String,
-        samp_rate: f64,
-        dflt_val: f64,
-        rst_val: f64,
-        instr_list: BTreeSet<Instr<f64>>,
-        compile_cache_ends: Vec<usize>,
-        compile_cache_fns: Vec<Box<dyn FnTraitSet<f64>>>,
-        is_fresh_compiled: bool,
+    /// Opt-in check for NaN and ±Inf values in the compiled waveform.
+    ///
+    /// Every compile cache segment (instruction or padding) is evaluated on up to `max_samps_per_seg`
+    /// evenly spaced clock ticks including its first and last ones (`None` - on every tick).
+    /// Returns `Err` pointing at the offending instruction on the first non-finite value.
+    fn check_finite(&self, max_samps_per_seg: Option<usize>) -> Result<(), StreamerError> {
+        self.check_samps_base(max_samps_per_seg, &|pos, val| match val.is_finite() {
+            true => Ok(()),
+            false => Err(self.non_finite_err(pos, val)),
+        })
     }
 
-    impl TestChan {
-        pub fn new(name: &str, samp_rate: f64, dflt_val: f64) -> Self {
-            Self {
-                name: name.to_string(),
-                samp_rate,
-                dflt_val,
-                rst_val: dflt_val,
-                instr_list: BTreeSet::new(),
-                compile_cache_ends: Vec::new(),
-                compile_cache_fns: Vec::new(),
-                is_fresh_compiled: true,
+    /// Checks the compiled waveform against [`BaseChan::val_range`] (nothing to check if it is `None`).
+    ///
+    /// Segments are sampled the same way as in [`BaseChan::check_finite`].
+    /// Returns [`StreamerError::OutOfRange`] pointing at the offending instruction on the first value outside the range.
+    fn check_range(&self, max_samps_per_seg: Option<usize>) -> Result<(), StreamerError> {
+        let Some((min, max)) = self.val_range() else {
+            return self.try_compiled_stop_pos().map(|_| ())
+        };
+        self.check_samps_base(max_samps_per_seg, &|pos, val| {
+            if (min..=max).contains(&val) {
+                return Ok(())
             }
-        }
+            Err(StreamerError::OutOfRange {
+                ctx: ErrCtx::chan(self.name()),
+                msg: format!(
+                    "{} value {val} {} at pos {pos} (t = {} s) is outside of the range [{min}, {max}], produced by {}",
+                    self.quantity(), self.quantity().unit(), self.compiled_pos_time(pos), self.value_source(pos)
+                ),
+            })
+        })
     }
 
-    impl BaseChan<f64> for TestChan {
-        fn name(&self) -> String {
-            self.name.clone()
+    /// Base of [`BaseChan::check_finite`] and [`BaseChan::check_range`]: evaluates every compile cache segment
+    /// on up to `max_samps_per_seg` evenly spaced ticks and passes each `(pos, value)` to `check`, stopping on the first `Err`.
+    fn check_samps_base(&self, max_samps_per_seg: Option<usize>, check: &dyn Fn(usize, f64) -> Result<(), StreamerError>) -> Result<(), StreamerError> {
+        self.try_compiled_stop_pos()?;
+        // Non-numeric sample types have no values to check
+        if self.dflt_val().as_f64().is_none() {
+            return Ok(())
         }
 
-        fn samp_rate(&self) -> f64 {
-            self.samp_rate
-        }
+        let mut seg_start = 0;
+        for (&seg_end, func) in self.compile_cache_ends().iter().zip(self.compile_cache_fns().iter()) {
+            let seg_len = seg_end - seg_start;
+            let n_samps = max_samps_per_seg.map_or(seg_len, |max_samps| usize::min(max_samps.max(1), seg_len));
+            let pos_arr: Vec<usize> = if n_samps == seg_len {
+                (seg_start..seg_end).collect()
+            } else {
+                Array1::linspace(seg_start as f64, (seg_end - 1) as f64, n_samps)
+                    .iter()
+                    .map(|pos| pos.round() as usize)
+                    .collect()
+            };
+            let t_arr: Vec<f64> = pos_arr.iter().map(|&pos| pos as f64 * self.clk_period()).collect();
+            let mut res_arr = vec![self.dflt_val(); n_samps];
+            func.calc(&t_arr, &mut res_arr);
 
-        fn is_fresh_compiled(&self) -> bool {
-            self.is_fresh_compiled
+            for (&pos, samp) in pos_arr.iter().zip(res_arr) {
+                check(pos, samp_to_f64(&samp))?
+            }
+            seg_start = seg_end;
         }
+        Ok(())
+    }
 
-        fn dflt_val(&self) -> f64 {
-            self.dflt_val
+    /// Describes what determines the value at compile cache position `pos` - an instruction, the padding after it,
+    /// or the default value
+    fn value_source(&self, pos: usize) -> String {
+        // Instructions are in edit cache positions, the compile cache is advanced by the trigger delay
+        let pos = pos + self.compile_offset_pos();
+        match (self.layer_instr_at(pos), self.instr_at(pos)) {
+            (Some(layer_instr), _) => format!("instruction {layer_instr}"),
+            (None, Some(instr)) if instr.end_pos().is_some_and(|end_pos| pos >= end_pos) => format!("the padding after instruction {instr}"),
+            (None, Some(instr)) => format!("instruction {instr}"),
+            (None, None) => "the channel default value".to_string(),
         }
+    }
 
-        fn rst_val(&self) -> f64 {
-            self.rst_val
-        }
+    /// Sequence time (as given to `add_instr`) of compile cache position `pos`, which is advanced by the trigger delay
+    fn compiled_pos_time(&self, pos: usize) -> f64 {
+        (pos + self.compile_offset_pos()) as f64 * self.clk_period()
+    }
 
-        fn instr_list(&self) -> &BTreeSet<Instr<f64>> {
-            &self.instr_list
+    /// Builds the [`StreamerError::NonFinite`] error for a value found at compile cache position `pos`
+    fn non_finite_err(&self, pos: usize, val: f64) -> StreamerError {
+        StreamerError::NonFinite {
+            ctx: ErrCtx::chan(self.name()),
+            msg: format!(
+                "non-finite value {val} at pos {pos} (t = {} s) produced by {}",
+                self.compiled_pos_time(pos), self.value_source(pos)
+            ),
         }
+    }
 
-        fn compile_cache_ends(&self) -> &Vec<usize> {
-            &self.compile_cache_ends
-        }
+    /// Helper function to evaluate `Box<dyn FnTraitSet<Self::Samp>` instances on single `usize` points
+    fn helper_eval_func(&self, x: usize, func: &dyn FnTraitSet<Self::Samp>) -> Self::Samp {
+        func.calc_one(x as f64 * self.clk_period())
+    }
+}
 
-        fn compile_cache_fns(&self) -> &Vec<Box<dyn FnTraitSet<f64>>> {
-            &self.compile_cache_fns
-        }
+// ==================== Unit tests ====================
+#[cfg(test)]
+mod test {
+    mod add_instr {
+        use crate::channel::*;
+        use crate::fn_lib_tools::StdFnLib;
+        use crate::mock::test_impls::TestChan;
 
-        fn fresh_compiled_mut(&mut self) -> &mut bool {
-            &mut self.is_fresh_compiled
+        #[test]
+        fn collision_reports_meta() {
+            let mut my_chan = TestChan::new("ao0", 1e3, 0.0);
+            let meta = InstrMeta { creator: Some("ramsey_seq".to_string()), ..InstrMeta::labeled("pi_pulse") };
+            my_chan.add_instr_with_meta(Box::new(ConstFn::new(1.0)), 0.0, Some((0.1, false)), Some(meta.clone())).unwrap();
+            assert_eq!(my_chan.instr_list().first().unwrap().meta(), Some(&meta));
+
+            let err = my_chan.add_instr(Box::new(ConstFn::new(2.0)), 0.05, None).unwrap_err();
+            assert!(matches!(err, StreamerError::Collision { .. }));
+            assert!(err.to_string().contains(r#"label="pi_pulse", creator="ramsey_seq""#));
+
+            // Metadata survives compile and shift
+            my_chan.compile(200).unwrap();
+            my_chan.shift(0.01).unwrap();
+            assert_eq!(my_chan.instr_list().first().unwrap().meta(), Some(&meta));
         }
 
-        fn instr_list_mut(&mut self) -> &mut BTreeSet<Instr<f64>> {
-            &mut self.instr_list
-        }
+        #[test]
+        fn pulse_array() {
+            let mut my_chan = TestChan::new("ao0", 1e3, 0.0);
+            my_chan.constant(5.0, 0.5, Some((0.1, false))).unwrap();
+            // Local-time ramp template: 0.0, 1.0, 2.0 over each 3-tick pulse
+            let ramp = StdFnLib::new().LinFn(1e3, 0.0).unwrap().inner;
+            let start_times: Vec<f64> = (0..100).map(|idx| 0.01 * idx as f64).collect();
+            // Pulse 50 at 0.5 s hits the existing instruction - nothing is added
+            let err = my_chan.add_pulse_array(ramp.clone_to_box(), &start_times, 0.003, false).unwrap_err();
+            assert!(matches!(err, StreamerError::Collision { .. }));
+            assert_eq!(my_chan.instr_count(), 1);
+            // Overlapping pulses among themselves
+            assert!(matches!(my_chan.add_pulse_array(ramp.clone_to_box(), &[0.0, 0.002], 0.003, false), Err(StreamerError::Collision { .. })));
+            let err = my_chan.add_pulse_array(ramp.clone_to_box(), &[0.0, 0.2], 0.0001, false).unwrap_err();
+            assert!(matches!(err, StreamerError::InvalidArgument { .. }) && err.msg().starts_with("pulse 0 of the array: "));
+            // Event channels take constant functions only, same as `add_instr`
+            let mut event_chan = TestChan::new_event("ev", 1e3, 0.0);
+            assert!(matches!(event_chan.add_pulse_array(ramp.clone_to_box(), &[0.0], 0.003, false), Err(StreamerError::Incompatible { .. })));
+            assert_eq!(my_chan.instr_count(), 1);
+
+            // Unsorted times are fine, all pulses share one function object
+            my_chan.add_pulse_array(ramp, &[0.2, 0.0, 0.1], 0.003, false).unwrap();
+            assert_eq!(my_chan.instr_count(), 4);
+            let funcs: Vec<_> = my_chan.instr_list().iter().map(|instr| instr.shared_func()).collect();
+            assert!(Arc::ptr_eq(&funcs[0], &funcs[1]) && Arc::ptr_eq(&funcs[1], &funcs[2]));
+            my_chan.compile(700).unwrap();
+            for t0 in [0.0, 0.1, 0.2] {
+                for (idx, expected) in [0.0, 1.0, 2.0, 0.0].into_iter().enumerate() {
+                    assert!((my_chan.eval_point(t0 + 1e-3 * idx as f64).unwrap() - expected).abs() < 1e-9);
+                }
+            }
 
-        fn compile_cache_ends_mut(&mut self) -> &mut Vec<usize> {
-            &mut self.compile_cache_ends
+            // A 1-tick overlap which `add_instr` would trim is a collision for pulse arrays
+            let ramp = StdFnLib::new().LinFn(1e3, 0.0).unwrap().inner;
+            assert!(matches!(my_chan.add_pulse_array(ramp.clone_to_box(), &[0.499], 0.002, false), Err(StreamerError::Collision { .. })));
+            assert_eq!(my_chan.instr_count(), 4);
+            my_chan.add_instr_local(ramp, 0.499, Some((0.002, false))).unwrap();
+            assert_eq!(my_chan.instr_count(), 5);
         }
 
-        fn compile_cache_fns_mut(&mut self) -> &mut Vec<Box<dyn FnTraitSet<f64>>> {
-            &mut self.compile_cache_fns
+        #[test]
+        fn pulse_array_by_name() {
+            let registry = FnRegistry::std();
+            let mut my_chan = TestChan::new("ao0", 1e3, 0.0);
+            let args = FnArgs::Named(IndexMap::from([("offs".to_string(), 1.0)]));
+            let overrides = IndexMap::from([("slope".to_string(), vec![1e3, 2e3, 1e3])]);
+            let start_times = [0.0, 0.1, 0.2];
+            // Override lists must match the start times, override names the parameters
+            let short = IndexMap::from([("slope".to_string(), vec![1e3])]);
+            assert!(matches!(my_chan.add_pulse_array_by_name(&registry, "LinFn", &args, &short, &start_times, 0.003, false), Err(StreamerError::InvalidArgument { .. })));
+            let unknown = IndexMap::from([("amp".to_string(), vec![1.0; 3])]);
+            assert!(matches!(my_chan.add_pulse_array_by_name(&registry, "LinFn", &args, &unknown, &start_times, 0.003, false), Err(StreamerError::InvalidArgument { .. })));
+
+            my_chan.add_pulse_array_by_name(&registry, "LinFn", &args, &overrides, &start_times, 0.003, false).unwrap();
+            // Pulses with equal slopes share their function
+            let funcs: Vec<_> = my_chan.instr_list().iter().map(|instr| instr.shared_func()).collect();
+            assert!(Arc::ptr_eq(&funcs[0], &funcs[2]) && !Arc::ptr_eq(&funcs[0], &funcs[1]));
+            my_chan.compile(300).unwrap();
+            for (t0, slope) in [(0.0, 1.0), (0.1, 2.0), (0.2, 1.0)] {
+                for (idx, expected) in [1.0f64, 1.0 + slope, 1.0 + 2.0 * slope, 0.0].into_iter().enumerate() {
+                    assert!((my_chan.eval_point(t0 + 1e-3 * idx as f64).unwrap() - expected).abs() < 1e-9);
+                }
+            }
         }
-    } */
-
-    mod add_instr {
-        use crate::instruction::*;
-        use crate::channel::*;
 
         // #[test]
         // fn back_to_back() {
@@ -816,37 +2648,112 @@ mod test {
     }
 
     mod misc {
-        use crate::instruction::*;
         use crate::channel::*;
+        use crate::mock::test_impls::TestChan;
 
         #[test]
         fn last_instr_end_pos() {
-            let mut my_chan = Channel::new(TaskType::AO, "ao0", 1e6, 0.0);
-            let mock_func = Instruction::new_const(1.23);
+            let mut my_chan = TestChan::new("ao0", 1e6, 0.0);
+            let mock_func = ConstFn::new(1.23);
 
             // No instructions
-            assert_eq!(my_chan.last_instr_end_pos(), 0);
+            assert_eq!(my_chan.last_instr_end_pos(), None);
 
             // Instruction with a specified duration, `eff_end_pos = end_pos`
-            my_chan.add_instr(mock_func.clone(),
+            my_chan.add_instr(Box::new(mock_func.clone()),
                 1.0, Some((1.0, true))
-            );
-            assert_eq!(my_chan.last_instr_end_pos(), 2000000);
+            ).unwrap();
+            assert_eq!(my_chan.last_instr_end_pos(), Some(2000000));
 
             // "Go-this" instruction - unspecified duration, `eff_end_pos = start_pos + 1`
-            my_chan.add_instr(mock_func.clone(),
+            my_chan.add_instr(Box::new(mock_func.clone()),
                 3.0, None
-            );
-            assert_eq!(my_chan.last_instr_end_pos(), 3000001);
+            ).unwrap();
+            assert_eq!(my_chan.last_instr_end_pos(), Some(3000001));
 
             my_chan.clear_edit_cache();
-            assert_eq!(my_chan.last_instr_end_pos(), 0);
+            assert_eq!(my_chan.last_instr_end_pos(), None);
+        }
+
+        #[test]
+        fn event_chan() {
+            use crate::device::BaseDev;
+            use crate::fn_lib_tools::StdFnLib;
+            use crate::mock::test_impls::TestDev;
+
+            let mut dev = TestDev::new("TT", 1e3);
+            dev.add_event_chan("trig", 0.0);
+            let chan = dev.chan_mut("trig").unwrap();
+            chan.add_event(0.01, 1.0).unwrap();
+            chan.constant(2.0, 0.02, Some((0.01, false))).unwrap();
+            // Same value as the padding - no event
+            chan.add_event(0.04, 0.0).unwrap();
+            chan.add_event(0.05, 3.0).unwrap();
+            // Usual collision checks
+            assert!(matches!(chan.add_event(0.025, 5.0), Err(StreamerError::Collision { .. })));
+            // Only constants
+            let sine = StdFnLib::new().Sine(1.0, 10.0, 0.0, 0.0).unwrap().inner;
+            assert!(matches!(chan.add_instr(sine, 0.1, None), Err(StreamerError::Incompatible { .. })));
+
+            dev.compile(0.06).unwrap();
+            let events: Vec<(usize, f64)> = dev.chan("trig").unwrap().compiled_events().unwrap()
+                .into_iter().map(|event| (event.pos, event.val)).collect();
+            assert_eq!(events, vec![(0, 0.0), (10, 1.0), (20, 2.0), (30, 0.0), (50, 3.0)]);
+        }
+
+        #[test]
+        fn dur_defaults() {
+            let mut my_chan = TestChan::new("shutter", 1e3, 0.0);
+            my_chan.constant_for(1.0, 0.0, 0.002).unwrap();
+            assert_eq!(my_chan.instr_list().last().unwrap().end_spec(), Some((2, false)));
+
+            assert!(matches!(my_chan.set_dur_defaults(DurDefaults::new(true).with_min_dur(-1.0)), Err(StreamerError::InvalidArgument { .. })));
+            my_chan.set_dur_defaults(DurDefaults::new(true).with_min_dur(0.005)).unwrap();
+            // Too short - extended to the minimum duration
+            my_chan.constant_for(2.0, 0.01, 0.001).unwrap();
+            assert_eq!(my_chan.instr_list().last().unwrap().end_spec(), Some((15, true)));
+            my_chan.add_instr_for(Box::new(ConstFn::new(3.0)), 0.02, 0.008).unwrap();
+            assert_eq!(my_chan.instr_list().last().unwrap().end_spec(), Some((28, true)));
+        }
+
+        #[test]
+        fn reverse() {
+            use crate::fn_lib_tools::StdFnLib;
+
+            let mut my_chan = TestChan::new("ao0", 1e3, 0.0);
+            let ramp = StdFnLib::new().LinFn(1.0, 0.0).unwrap().inner;
+            my_chan.add_instr(ramp, 0.0, Some((0.004, true))).unwrap();
+            my_chan.constant(-1.0, 0.006, Some((0.001, false))).unwrap();
+            my_chan.compile(10).unwrap();
+            let mut forward = vec![0.0; 10];
+            my_chan.fill_samps_from_ticks(&mut ChanSampCursor::new(), 0, &mut forward).unwrap();
+            assert!(matches!(my_chan.reverse(0.005), Err(StreamerError::OutOfRange { .. })));
+
+            my_chan.reverse(0.01).unwrap();
+            my_chan.compile(10).unwrap();
+            let mut backward = vec![0.0; 10];
+            my_chan.fill_samps_from_ticks(&mut ChanSampCursor::new(), 0, &mut backward).unwrap();
+            forward.reverse();
+            assert!(backward.iter().zip(&forward).all(|(back, fwd)| (back - fwd).abs() < 1e-12));
+
+            // Zero-length interval is rejected, an empty channel stays empty
+            let mut empty_chan = TestChan::new("ao1", 1e3, 0.0);
+            assert!(matches!(empty_chan.reverse(0.0), Err(StreamerError::OutOfRange { .. })));
+            assert!(matches!(empty_chan.reverse(0.0001), Err(StreamerError::OutOfRange { .. })));
+            assert!(matches!(empty_chan.reverse(f64::NAN), Err(StreamerError::NonFinite { .. })));
+            empty_chan.reverse(0.01).unwrap();
+            assert!(empty_chan.instr_list().is_empty());
         }
     }
 
     mod compile {
-        use crate::instruction::*;
         use crate::channel::*;
+        use crate::fn_lib_tools::StdFnLib;
+        use crate::mock::test_impls::TestChan;
+
+        fn sine(freq: f64, offs: f64) -> Box<dyn FnTraitSet<f64>> {
+            StdFnLib::new().Sine(1.0, freq, 0.0, offs).unwrap().inner
+        }
 
         #[test]
         fn pad_before_first_instr() {
@@ -854,31 +2761,25 @@ mod test {
             // If there is no gap, no padding instruction should be inserted.
 
             let chan_dflt = -10.0;
-            let mut my_chan = Channel::new(TaskType::AO, "ao0", 1e6, chan_dflt);
+            let mut my_chan = TestChan::new("ao0", 1e6, chan_dflt);
 
             // Finite gap
-            my_chan.add_instr(
-                Instruction::new_sine(1.23, Some(1.0), None, Some(0.5)),
-                1.0, Some((1.0, false))
-            );
-            my_chan.compile(my_chan.last_instr_end_pos());
-            assert_eq!(my_chan.instr_end()[0], 1000000);
-            assert!(my_chan.instr_fn()[0].instr_type == InstrType::CONST);
+            my_chan.add_instr(sine(1.23, 0.5), 1.0, Some((1.0, false))).unwrap();
+            my_chan.compile(my_chan.last_instr_end_pos().unwrap()).unwrap();
+            assert_eq!(my_chan.compile_cache_ends()[0], 1000000);
+            assert_eq!(my_chan.compile_cache_ends().len(), 2);
             assert!({
-                let &pad_val = my_chan.instr_fn()[0].args.get("value").unwrap();
+                let pad_val = my_chan.helper_eval_func(0, my_chan.compile_cache_fns()[0].as_ref());
                 // Check for float equality with caution
                 (pad_val - chan_dflt).abs() < 1e-10
             });
 
             // No gap
             my_chan.clear_edit_cache();
-            my_chan.add_instr(
-                Instruction::new_sine(1.23, Some(1.0), None, Some(0.5)),
-                0.0, Some((1.0, false))
-            );
-            my_chan.compile(my_chan.last_instr_end_pos());
-            assert_eq!(my_chan.instr_end()[0], 1000000);
-            assert!(my_chan.instr_val[0].instr_type == InstrType::SINE);
+            my_chan.add_instr(sine(1.23, 0.5), 0.0, Some((1.0, false))).unwrap();
+            my_chan.compile(my_chan.last_instr_end_pos().unwrap()).unwrap();
+            assert_eq!(my_chan.compile_cache_ends()[0], 1000000);
+            assert_eq!(my_chan.compile_cache_ends().len(), 1);
         }
 
         #[test]
@@ -888,49 +2789,107 @@ mod test {
             // Otherwise, channel default value is kept.
 
             let chan_dflt = -10.0;
-            let mut my_chan = Channel::new(TaskType::AO, "ao0", 1e6, chan_dflt);
+            let mut my_chan = TestChan::new("ao0", 1e6, chan_dflt);
 
             // Convenience variables
             let freq = 0.12;
             let pulse_dur = 1.0;
             let comp_stop_pos = (2.0 * pulse_dur * my_chan.samp_rate()).round() as usize;
+            let pulse_end_pos = (pulse_dur * my_chan.samp_rate()).round() as usize;
 
             // keep_val = true
-            my_chan.add_instr(
-                Instruction::new_sine(freq, Some(1.0), None, None),
-                0.0, Some((pulse_dur, true))
-            );
-            my_chan.compile(comp_stop_pos);
-            let pad_func = my_chan.instr_fn()[1].clone();
-            assert!(pad_func.instr_type == InstrType::CONST);
+            my_chan.add_instr(sine(freq, 1.0), 0.0, Some((pulse_dur, true))).unwrap();
+            my_chan.compile(comp_stop_pos).unwrap();
             assert!({
-                let &actual_pad_val = pad_func.args.get("value").unwrap();
-                let expected_pad_val = my_chan.instr_val[0].eval_point(pulse_dur);
+                let actual_pad_val = my_chan.helper_eval_func(comp_stop_pos - 1, my_chan.compile_cache_fns()[1].as_ref());
+                let expected_pad_val = my_chan.helper_eval_func(pulse_end_pos, my_chan.compile_cache_fns()[0].as_ref());
                 (actual_pad_val - expected_pad_val).abs() < 1e-10
             });
 
             // keep_val = false
             my_chan.clear_edit_cache();
-            my_chan.add_instr(
-                Instruction::new_sine(freq, Some(2.0), None, None),
-                0.0, Some((pulse_dur, false))
-            );
-            my_chan.compile(comp_stop_pos);
-            let pad_func = my_chan.instr_fn()[1].clone();
-            assert!(pad_func.instr_type == InstrType::CONST);
+            my_chan.add_instr(sine(freq, 2.0), 0.0, Some((pulse_dur, false))).unwrap();
+            my_chan.compile(comp_stop_pos).unwrap();
             assert!({
-                let &actual_pad_val = pad_func.args.get("value").unwrap();
+                let actual_pad_val = my_chan.helper_eval_func(comp_stop_pos - 1, my_chan.compile_cache_fns()[1].as_ref());
                 (actual_pad_val - chan_dflt).abs() < 1e-10
             });
         }
 
-        // #[test]
-        // fn pad_go_this() {
-        //     todo!()
-        // }
+        #[test]
+        fn pad_hold_last_val() {
+            let mut my_chan = TestChan::new("ao0", 1e3, -1.0);
+            let ramp = StdFnLib::new().LinFn(1e3, 0.0).unwrap().inner;
+            my_chan.add_instr(ramp, 0.002, Some((0.002, false))).unwrap();
+            my_chan.constant(5.0, 0.006, Some((0.001, false))).unwrap();
+            my_chan.set_hold_last_val(true).unwrap();
+            assert!(my_chan.hold_last_val());
+            my_chan.compile(10).unwrap();
+            let mut samps = vec![0.0; 10];
+            my_chan.fill_samps_from_ticks(&mut ChanSampCursor::new(), 0, &mut samps).unwrap();
+            // Default value before the first instruction only, the ramp's value at its end_pos is held
+            assert_eq!(samps, vec![-1.0, -1.0, 2.0, 3.0, 4.0, 4.0, 5.0, 5.0, 5.0, 5.0]);
+            assert_eq!(my_chan.eval_point(0.005).unwrap(), 4.0);
+            assert!(my_chan.padding_segs().unwrap().iter().all(|seg| seg.keep_val != Some(false)));
+
+            // Reversing keeps the waveform
+            my_chan.reverse(0.01).unwrap();
+            my_chan.compile(10).unwrap();
+            let mut backward = vec![0.0; 10];
+            my_chan.fill_samps_from_ticks(&mut ChanSampCursor::new(), 0, &mut backward).unwrap();
+            samps.reverse();
+            assert!(backward.iter().zip(&samps).all(|(back, fwd)| (back - fwd).abs() < 1e-12));
+
+            // Switching the policy invalidates the compile cache
+            my_chan.set_hold_last_val(false).unwrap();
+            assert!(!my_chan.is_fresh_compiled());
+        }
+
+        #[test]
+        fn post_compile_hook() {
+            use crate::device::BaseDev;
+            use crate::mock::test_impls::TestDev;
+
+            // Active-low line: the hardware sees the inverted waveform
+            fn invert(chan: &mut TestChan<bool>) -> Result<(), StreamerError> {
+                for func in chan.compile_cache_fns_mut().iter_mut() {
+                    let val = func.const_val().ok_or_else(|| StreamerError::Incompatible { ctx: ErrCtx::none(), msg: "Not constant".to_string() })?;
+                    *func = Arc::new(ConstFn::new(!val));
+                }
+                Ok(())
+            }
+            let mut my_chan = TestChan::new("port0/line0", 1e3, false);
+            my_chan.set_post_compile(invert);
+            my_chan.constant(true, 0.002, Some((0.002, false))).unwrap();
+            my_chan.compile(6).unwrap();
+            let mut samps = vec![false; 6];
+            my_chan.fill_samps_from_ticks(&mut ChanSampCursor::new(), 0, &mut samps).unwrap();
+            assert_eq!(samps, vec![true, true, false, false, true, true]);
+            // Muted channels are transformed as well
+            my_chan.set_enabled(false).unwrap();
+            my_chan.compile(6).unwrap();
+            assert_eq!(my_chan.compile_cache_fns()[0].const_val(), Some(true));
+
+            // A failing device hook fails the compile and leaves no stale compile cache behind
+            fn max_2_segs(dev: &mut TestDev<bool>) -> Result<(), StreamerError> {
+                match dev.active_chans().iter().any(|chan| chan.compile_cache_ends().len() > 2) {
+                    true => Err(StreamerError::OutOfRange { ctx: ErrCtx::none(), msg: "Too many segments".to_string() }),
+                    false => Ok(()),
+                }
+            }
+            let mut dev = TestDev::new("DO", 1e3);
+            dev.add_chan("port0/line0", false);
+            dev.set_post_compile(max_2_segs);
+            dev.chan_mut("port0/line0").unwrap().constant(true, 0.0, Some((0.002, false))).unwrap();
+            dev.compile(0.004).unwrap();
+            dev.chan_mut("port0/line0").unwrap().constant(true, 0.003, Some((0.001, false))).unwrap();
+            let err = dev.compile(0.006).unwrap_err();
+            assert!(matches!(&err, StreamerError::OutOfRange { ctx, .. } if ctx.dev.as_deref() == Some("DO")));
+            assert!(dev.validate_compile_cache().is_err());
+        }
 
         // #[test]
-        // fn no_pad_back_to_back() {
+        // fn pad_go_this() {
         //     todo!()
         // }
 
@@ -938,5 +2897,464 @@ mod test {
         // fn no_pad_back_to_end() {
         //     todo!()
         // }
+
+        /// Compiled waveform must not change when segments are merged
+        fn assert_matches_edit_cache(chan: &TestChan<f64>, stop_pos: usize) {
+            let t_arr: Vec<f64> = (0..stop_pos).map(|pos| pos as f64 * chan.clk_period()).collect();
+            let mut samps = vec![0.0; stop_pos];
+            chan.fill_samps(0, &mut samps, &t_arr).unwrap();
+            for (pos, samp) in samps.iter().enumerate() {
+                assert_eq!(*samp, chan.eval_point(t_arr[pos]).unwrap(), "pos {pos}");
+            }
+            assert!(chan.validation_report().is_valid());
+        }
+
+        #[test]
+        fn merge_const_back_to_back() {
+            let mut my_chan = TestChan::new("ao0", 1e3, 0.0);
+            my_chan.constant(1.0, 0.0, Some((0.1, false))).unwrap();
+            my_chan.constant(1.0, 0.1, Some((0.1, false))).unwrap();
+            my_chan.add_instr(StdFnLib::new().ConstF64(1.0).unwrap().inner, 0.2, Some((0.1, false))).unwrap();
+            my_chan.constant(2.0, 0.3, Some((0.1, false))).unwrap();
+            my_chan.compile(500).unwrap();
+            assert_eq!(my_chan.compile_cache_ends(), &vec![300, 400, 500]);
+            assert_matches_edit_cache(&my_chan, 500);
+
+            // Padding with the same value as the constant around it
+            my_chan.clear_edit_cache();
+            my_chan.constant(0.0, 0.1, Some((0.1, false))).unwrap();
+            my_chan.constant(0.0, 0.3, None).unwrap();
+            my_chan.compile(500).unwrap();
+            assert_eq!(my_chan.compile_cache_ends(), &vec![500]);
+            assert_matches_edit_cache(&my_chan, 500);
+            assert_eq!(my_chan.compiled_segments().unwrap(), vec![(0, 500, ConstFn::new(0.0).describe())]);
+        }
+
+        #[test]
+        fn merge_keep_val() {
+            let mut my_chan = TestChan::new("ao0", 1e3, 0.0);
+            // keep_val padding extends the constant up to the next instruction with the same value
+            my_chan.constant(1.0, 0.1, Some((0.1, true))).unwrap();
+            my_chan.constant(1.0, 0.3, Some((0.1, false))).unwrap();
+            my_chan.compile(500).unwrap();
+            assert_eq!(my_chan.compile_cache_ends(), &vec![100, 400, 500]);
+            assert_matches_edit_cache(&my_chan, 500);
+
+            // keep_val padding after a non-constant function is not merged with the function itself,
+            // but the following constant is merged with the padding if the values are equal (sin(pi/2) = 1.0) ...
+            my_chan.clear_edit_cache();
+            my_chan.add_instr(sine(1.0, 0.0), 0.0, Some((0.25, true))).unwrap();
+            my_chan.constant(1.0, 0.3, Some((0.1, false))).unwrap();
+            my_chan.compile(500).unwrap();
+            assert_eq!(my_chan.compile_cache_ends(), &vec![250, 400, 500]);
+            assert_matches_edit_cache(&my_chan, 500);
+
+            // ... and is kept separate otherwise
+            my_chan.clear_edit_cache();
+            my_chan.add_instr(sine(1.0, 0.0), 0.0, Some((0.25, true))).unwrap();
+            my_chan.constant(2.0, 0.3, Some((0.1, false))).unwrap();
+            my_chan.compile(500).unwrap();
+            assert_eq!(my_chan.compile_cache_ends(), &vec![250, 300, 400, 500]);
+            assert_matches_edit_cache(&my_chan, 500);
+
+            // Back-to-back non-constant functions are never merged
+            my_chan.clear_edit_cache();
+            my_chan.add_instr(sine(1.0, 0.0), 0.0, Some((0.1, false))).unwrap();
+            my_chan.add_instr(sine(1.0, 0.0), 0.1, Some((0.1, false))).unwrap();
+            my_chan.compile(200).unwrap();
+            assert_eq!(my_chan.compile_cache_ends(), &vec![100, 200]);
+        }
+
+        #[test]
+        fn layers() {
+            let mut my_chan = TestChan::new("ao0", 1e3, 0.0);
+            // Background ramp on the base layer, overrides on layers 1 and 2
+            my_chan.add_instr(StdFnLib::new().LinFn(1.0, 0.0).unwrap().inner, 0.0, None).unwrap();
+            my_chan.add_instr_on_layer(Box::new(ConstFn::new(5.0)), 0.1, Some((0.1, true)), 1).unwrap();
+            my_chan.add_instr_on_layer(Box::new(ConstFn::new(7.0)), 0.15, Some((0.02, false)), 2).unwrap();
+            // Collisions are still checked within a layer
+            assert!(my_chan.add_instr_on_layer(Box::new(ConstFn::new(1.0)), 0.15, Some((0.1, false)), 1).is_err());
+            my_chan.compile(1000).unwrap();
+
+            assert_eq!(my_chan.compile_cache_ends(), &vec![100, 150, 170, 200, 1000]);
+            let expected: Vec<f64> = (0..1000).map(|pos| match pos {
+                150..170 => 7.0,
+                100..200 => 5.0,
+                _ => pos as f64 * 1e-3,
+            }).collect();
+            let mut samps = vec![0.0; 1000];
+            my_chan.fill_samps_from_ticks(&mut ChanSampCursor::new(), 0, &mut samps).unwrap();
+            assert_eq!(samps, expected);
+            for pos in [0, 99, 100, 150, 169, 170, 199, 200, 999] {
+                assert_eq!(my_chan.eval_point(pos as f64 * 1e-3).unwrap(), expected[pos], "pos {pos}");
+            }
+            assert!(my_chan.validation_report().first_mismatch.is_none());
+
+            // Override layers alone are enough - the base layer is kept at the channel default
+            let mut my_chan = TestChan::new("ao0", 1e3, -1.0);
+            my_chan.add_instr_on_layer(Box::new(ConstFn::new(1.0)), 0.1, Some((0.1, false)), 3).unwrap();
+            assert!(my_chan.got_instructions());
+            assert_eq!(my_chan.last_instr_end_pos(), Some(200));
+            my_chan.compile(300).unwrap();
+            assert_eq!(my_chan.compile_cache_ends(), &vec![100, 200, 300]);
+        }
+
+        #[test]
+        fn phase_link() {
+            let same_phase = |a: f64, b: f64| (a - b).rem_euclid(2.0 * std::f64::consts::PI).min((b - a).rem_euclid(2.0 * std::f64::consts::PI)) < 1e-9;
+
+            let mut my_chan = TestChan::new("ao0", 1e3, 0.0);
+            my_chan.add_instr(sine(10.0, 0.0), 0.0, Some((0.1, false))).unwrap();
+            my_chan.add_instr_phase_linked(sine(25.0, 0.0), 0.1, Some((0.1, false))).unwrap();
+            // Linking across a gap - the phase the previous instruction reached at its end
+            my_chan.add_instr_phase_linked(sine(5.0, 0.0), 0.25, None).unwrap();
+            my_chan.compile(1000).unwrap();
+            assert!(my_chan.validation_report().first_mismatch.is_none());
+
+            assert_eq!(my_chan.compile_cache_ends(), &vec![100, 200, 250, 1000]);
+            let fns = my_chan.compile_cache_fns();
+            assert!(same_phase(fns[0].phase_at(0.1).unwrap(), fns[1].phase_at(0.1).unwrap()));
+            assert!(same_phase(fns[1].phase_at(0.2).unwrap(), fns[3].phase_at(0.25).unwrap()));
+            // The edit cache keeps the original functions, `eval_point` resolves the chain like `compile`
+            for t in [0.1, 0.15, 0.3] {
+                let seg = my_chan.compile_cache_ends().partition_point(|&end| end <= (t * 1e3) as usize);
+                assert_eq!(my_chan.eval_point(t).unwrap(), my_chan.compile_cache_fns()[seg].calc_one(t));
+            }
+
+            // Nothing to link to
+            let mut my_chan = TestChan::new("ao0", 1e3, 0.0);
+            my_chan.add_instr_phase_linked(sine(10.0, 0.0), 0.1, Some((0.1, false))).unwrap();
+            assert!(matches!(my_chan.compile(1000), Err(StreamerError::Incompatible { .. })));
+            // Non-oscillatory functions have no phase
+            assert!(my_chan.add_instr_phase_linked(Box::new(ConstFn::new(1.0)), 0.5, None).is_err());
+        }
+
+        #[test]
+        fn sub_tick_phase() {
+            let mut my_chan = TestChan::new("ao0", 1e3, 0.0);
+            // 10.4 ticks - rounded to 10
+            my_chan.add_instr(sine(100.0, 0.0), 0.0104, Some((0.01, false))).unwrap();
+            my_chan.constant(1.0, 0.03, Some((0.01, false))).unwrap();
+            let instr = my_chan.instr_list().first().unwrap();
+            assert_eq!(instr.start_pos(), 10);
+            assert!((instr.sub_tick() - 4e-4).abs() < 1e-12);
+
+            my_chan.compile(50).unwrap();
+            assert!(my_chan.eval_point(0.01).unwrap().abs() < 1e-9);
+
+            // The first sample gets the phase of the requested start time
+            my_chan.set_sub_tick_phase(true).unwrap();
+            assert!(!my_chan.is_fresh_compiled());
+            my_chan.compile(50).unwrap();
+            assert!(my_chan.validation_report().is_valid());
+            let expected = (2.0 * std::f64::consts::PI * 100.0 * 0.0104).sin();
+            assert!((my_chan.compile_cache_fns()[1].calc_one(0.01) - expected).abs() < 1e-9);
+            assert!((my_chan.eval_point(0.01).unwrap() - expected).abs() < 1e-9);
+            // Non-oscillatory functions are left alone
+            assert_eq!(my_chan.eval_point(0.035).unwrap(), 1.0);
+        }
+
+        #[test]
+        fn shared_funcs() {
+            // Compile cache refers to the edit cache functions instead of copying them
+            let mut my_chan = TestChan::new("ao0", 1e3, 0.0);
+            my_chan.add_instr(sine(1.0, 0.0), 0.1, Some((0.1, false))).unwrap();
+            my_chan.compile(300).unwrap();
+            let instr = my_chan.instr_list().first().unwrap();
+            assert!(Arc::ptr_eq(&instr.shared_func(), &my_chan.compile_cache_fns()[1]));
+            assert_eq!(Arc::strong_count(&my_chan.compile_cache_fns()[1]), 2);
+
+            my_chan.clear_compile_cache();
+            let instr = my_chan.instr_list().first().unwrap();
+            assert_eq!(Arc::strong_count(&instr.shared_func()), 2);
+        }
+    }
+
+    mod fill_samps {
+        use crate::channel::*;
+        use crate::device::BaseDev;
+        use crate::fn_lib_tools::StdFnLib;
+        use crate::mock::test_impls::TestDev;
+
+        #[test]
+        fn cursor_matches_binary_search() {
+            let mut dev = TestDev::new("AO", 1e3);
+            dev.add_chan("ao0", 0.0);
+            let chan = dev.chan_mut("ao0").unwrap();
+            for i in 0..50 {
+                chan.constant(i as f64, i as f64 * 0.02, Some((0.01 + 0.001 * (i % 3) as f64, false))).unwrap();
+            }
+            dev.compile(1.0).unwrap();
+            let chan = dev.chan("ao0").unwrap();
+            let t_arr: Vec<f64> = (0..1000).map(|pos| pos as f64 * 1e-3).collect();
+
+            let mut expected = vec![0.0; 1000];
+            chan.fill_samps(0, &mut expected, &t_arr).unwrap();
+
+            // Back-to-back chunks of different sizes, including ones ending exactly on segment boundaries
+            let mut cursor = ChanSampCursor::new();
+            let mut actual = vec![0.0; 1000];
+            let mut start_pos = 0;
+            for chunk_len in [10, 7, 3, 1, 19, 40].iter().cycle() {
+                let end_pos = usize::min(start_pos + chunk_len, 1000);
+                chan.fill_samps_with(&mut cursor, start_pos, &mut actual[start_pos..end_pos], &t_arr[start_pos..end_pos]).unwrap();
+                start_pos = end_pos;
+                if start_pos == 1000 {
+                    break
+                }
+            }
+            assert_eq!(actual, expected);
+
+            // Rewinding falls back to the binary search
+            let mut rewind = vec![0.0; 100];
+            chan.fill_samps_with(&mut cursor, 15, &mut rewind, &t_arr[15..115]).unwrap();
+            assert_eq!(rewind, expected[15..115]);
+        }
+
+        #[test]
+        fn from_ticks() {
+            let mut dev = TestDev::new("AO", 1e3);
+            dev.add_chan("ao0", 0.0);
+            let chan = dev.chan_mut("ao0").unwrap();
+            let fn_lib = StdFnLib::new();
+            chan.add_instr(fn_lib.Sine(1.0, 3.0, 0.1, 0.0).unwrap().inner, 0.1, Some((0.5, true))).unwrap();
+            chan.add_instr(fn_lib.LinFn(2.0, -1.0).unwrap().inner, 0.7, Some((0.1, false))).unwrap();
+            chan.constant(0.5, 0.9, None).unwrap();
+            dev.compile(1.0).unwrap();
+            let chan = dev.chan("ao0").unwrap();
+
+            // Times generated from ticks are exactly the ones `eval_point` uses
+            let mut samps = vec![0.0; 1000];
+            let mut cursor = ChanSampCursor::new();
+            chan.fill_samps_from_ticks(&mut cursor, 0, &mut samps[..600]).unwrap();
+            chan.fill_samps_from_ticks(&mut cursor, 600, &mut samps[600..]).unwrap();
+            for (pos, samp) in samps.iter().enumerate() {
+                assert_eq!(*samp, chan.eval_point(pos as f64 * 1e-3).unwrap(), "pos {pos}");
+            }
+        }
+
+        #[test]
+        fn repeat() {
+            let mut dev = TestDev::new("AO", 1e3);
+            dev.add_chan("ao0", 0.0);
+            let chan = dev.chan_mut("ao0").unwrap();
+            // Saw-tooth: 5 periods of a 20-tick ramp starting at pos 100
+            let ramp = StdFnLib::new().LinFn(1.0, -0.1).unwrap().inner;
+            chan.add_repeat_instr(ramp, 0.1, 0.02, 5, false).unwrap();
+            assert!(chan.add_repeat_instr(Box::new(ConstFn::new(1.0)), 0.5, 0.0, 3, false).is_err());
+            dev.compile(0.3).unwrap();
+            let chan = dev.chan("ao0").unwrap();
+
+            // Padding, the whole train, padding
+            assert_eq!(chan.compile_cache_ends(), &vec![100, 200, 300]);
+
+            let expected: Vec<f64> = (0..300).map(|pos| match pos {
+                100..200 => (100 + (pos - 100) % 20) as f64 * 1e-3 - 0.1,
+                _ => 0.0,
+            }).collect();
+            let mut samps = vec![0.0; 300];
+            chan.fill_samps_from_ticks(&mut ChanSampCursor::new(), 0, &mut samps).unwrap();
+            assert_eq!(samps, expected);
+
+            let t_arr: Vec<f64> = (0..300).map(|pos| pos as f64 * 1e-3).collect();
+            chan.fill_samps(0, &mut samps, &t_arr).unwrap();
+            for (samp, expected) in samps.iter().zip(expected.iter()) {
+                assert!((samp - expected).abs() < 1e-12);
+            }
+        }
+
+        #[test]
+        fn from_array() {
+            use pyo3::prelude::*;
+
+            let mut dev = TestDev::new("AO", 1e3);
+            dev.add_chan("ao0", 0.0);
+            // 4 samples, 2 ticks apart, starting at pos 10
+            dev.chan_mut("ao0").unwrap().play_array(vec![0.0, 1.0, 3.0, -1.0], 0.01, 0.002, true).unwrap();
+            assert!(dev.chan_mut("ao0").unwrap().play_array(Vec::new(), 0.1, 0.002, true).is_err());
+            dev.compile(0.03).unwrap();
+            let chan = dev.chan("ao0").unwrap();
+            assert_eq!(chan.compile_cache_ends(), &vec![10, 18, 30]);
+
+            let mut samps = vec![0.0; 30];
+            chan.fill_samps_from_ticks(&mut ChanSampCursor::new(), 0, &mut samps).unwrap();
+            let expected = [0.0, 0.5, 1.0, 2.0, 3.0, 1.0, -1.0, -1.0];
+            for (samp, expected) in samps[10..18].iter().zip(expected) {
+                assert!((samp - expected).abs() < 1e-9);
+            }
+            assert!(samps[18..].iter().all(|&samp| samp == -1.0));
+
+            // Python side - any float64 buffer
+            pyo3::prepare_freethreaded_python();
+            Python::with_gil(|py| {
+                let arr = py.import_bound("array").unwrap().call_method1("array", ("d", vec![0.0, 1.0, 3.0])).unwrap();
+                let fn_lib = StdFnLib::new();
+                let func = fn_lib.FromArray(py, &arr, 0.5, "hold", 1.0).unwrap().inner;
+                let mut res = vec![0.0; 5];
+                func.calc(&[0.0, 1.0, 1.7, 2.0, 5.0], &mut res);
+                assert_eq!(res, vec![0.0, 0.0, 1.0, 3.0, 3.0]);
+                assert!(fn_lib.FromArray(py, &arr, 0.5, "cubic", 0.0).is_err());
+                assert!(fn_lib.FromArray(py, &arr, 0.0, "linear", 0.0).is_err());
+            });
+        }
+
+        #[test]
+        fn from_array_content() {
+            use crate::diff::{diff_instr_lists, InstrSnapshot};
+            use crate::mock::test_impls::TestChan;
+
+            // Same length and timing, different values - must be told apart by content hashes and diffs
+            let chans: Vec<TestChan<f64>> = [[0.0, 1.0, 0.0], [0.0, 5.0, 0.0]].into_iter().map(|samps| {
+                let mut chan = TestChan::new("ao0", 1e3, 0.0);
+                chan.play_array(samps.to_vec(), 0.01, 0.002, true).unwrap();
+                chan.compile(30).unwrap();
+                chan
+            }).collect();
+            assert_ne!(chans[0].content_hash().unwrap(), chans[1].content_hash().unwrap());
+            let snapshots = |chan: &TestChan<f64>| chan.instr_list().iter().map(InstrSnapshot::from).collect::<Vec<_>>();
+            assert_eq!(diff_instr_lists(&snapshots(&chans[0]), &snapshots(&chans[1])).len(), 2);
+        }
+
+        #[test]
+        fn decimated() {
+            let mut dev = TestDev::new("AO", 1e3);
+            dev.add_chan("ao0", 0.0);
+            let chan = dev.chan_mut("ao0").unwrap();
+            // Ramp from pos 10 to 50, recomputed every 8 ticks
+            let ramp = StdFnLib::new().LinFn(1.0, 0.0).unwrap().inner;
+            assert!(chan.add_instr_decimated(ramp.clone(), 0.01, Some((0.04, true)), 0).is_err());
+            chan.add_instr_decimated(ramp, 0.01, Some((0.04, true)), 8).unwrap();
+            dev.compile(0.06).unwrap();
+            let chan = dev.chan("ao0").unwrap();
+
+            assert_eq!(chan.compile_cache_ends(), &vec![10, 50, 60]);
+            assert_eq!(chan.compile_cache_fns()[1].decimation_hint(), Some(8));
+            assert_eq!(chan.compile_cache_fns()[0].decimation_hint(), None);
+
+            let expected: Vec<f64> = (0..60).map(|pos| match pos {
+                10..50 => (10 + (pos - 10) / 8 * 8) as f64 * 1e-3,
+                // keep_val evaluates at the end tick, which starts a new hold interval - the ramp still reaches its final value
+                50.. => 0.05,
+                _ => 0.0,
+            }).collect();
+            let mut samps = vec![0.0; 60];
+            chan.fill_samps_from_ticks(&mut ChanSampCursor::new(), 0, &mut samps).unwrap();
+            assert_eq!(samps, expected);
+            // Chunk boundaries don't move the hold grid
+            let mut chunk = vec![0.0; 13];
+            chan.fill_samps_from_ticks(&mut ChanSampCursor::new(), 21, &mut chunk).unwrap();
+            assert_eq!(chunk, expected[21..34]);
+
+            let t_arr: Vec<f64> = (0..60).map(|pos| pos as f64 * 1e-3).collect();
+            chan.fill_samps(0, &mut samps, &t_arr).unwrap();
+            assert_eq!(samps, expected);
+        }
+
+        #[test]
+        fn local_time() {
+            let mut dev = TestDev::new("AO", 1e3);
+            dev.add_chan("abs", 0.0);
+            dev.add_chan("local", 0.0);
+            let ramp = || StdFnLib::new().LinFn(1.0, 0.0).unwrap().inner;
+            dev.chan_mut("abs").unwrap().add_instr(ramp(), 0.01, Some((0.005, false))).unwrap();
+            // Off-grid start - the function still sees exactly τ = 0 on the first tick
+            dev.chan_mut("local").unwrap().add_instr_local(ramp(), 0.0101, Some((0.005, false))).unwrap();
+            dev.compile(0.02).unwrap();
+
+            let samps = |name: &str| {
+                let mut samps = vec![0.0; 5];
+                dev.chan(name).unwrap().fill_samps_from_ticks(&mut ChanSampCursor::new(), 10, &mut samps).unwrap();
+                samps
+            };
+            let expected = [0.0, 0.001, 0.002, 0.003, 0.004];
+            assert!(samps("abs").iter().zip(expected).all(|(samp, expected)| (samp - 0.01 - expected).abs() < 1e-12));
+            assert!(samps("local").iter().zip(expected).all(|(samp, expected)| (samp - expected).abs() < 1e-12));
+            assert!(matches!(dev.chan_mut("local").unwrap().add_instr_local(ramp(), -1.0, None), Err(StreamerError::OutOfRange { .. })));
+
+            // The function is stored as is, only the origin is recorded
+            let instr = dev.chan("local").unwrap().instr_list().first().unwrap().clone();
+            assert_eq!(instr.time_origin(), Some(10));
+            assert!(instr.to_string().starts_with("Instr(func=LinFn(") && instr.to_string().contains("time_origin=10"));
+            // Shifting moves the origin together with the instruction
+            dev.chan_mut("local").unwrap().shift(0.02).unwrap();
+            assert_eq!(dev.chan("local").unwrap().instr_list().first().unwrap().time_origin(), Some(30));
+            dev.compile(0.04).unwrap();
+            let mut shifted = vec![0.0; 5];
+            dev.chan("local").unwrap().fill_samps_from_ticks(&mut ChanSampCursor::new(), 30, &mut shifted).unwrap();
+            assert!(shifted.iter().zip(expected).all(|(samp, expected)| (samp - expected).abs() < 1e-12));
+        }
+    }
+
+    mod eval_point {
+        use crate::channel::*;
+        use crate::device::BaseDev;
+        use crate::fn_lib_tools::{StdFnLib, TimeMap};
+        use crate::mock::test_impls::TestDev;
+
+        #[test]
+        fn calc_one_matches_calc() {
+            let fn_lib = StdFnLib::new();
+            let funcs: Vec<Box<dyn FnTraitSet<f64>>> = vec![
+                Box::new(ConstFn::new(1.5)),
+                fn_lib.ConstF64(2.5).unwrap().inner,
+                fn_lib.Sine(0.7, 1.0, 0.3, 0.1).unwrap().inner,
+                Box::new(TimeMap::new(fn_lib.LinFn(2.0, 1.0).unwrap().inner, 3.0, -0.5)),
+            ];
+            for func in funcs.iter() {
+                for t in [0.0, 0.123, 1.5, 42.0] {
+                    let mut res_arr = [0.0];
+                    func.calc(&[t], &mut res_arr);
+                    assert_eq!(func.calc_one(t), res_arr[0]);
+                }
+            }
+        }
+
+        #[test]
+        fn instr_and_padding() {
+            let mut dev = TestDev::new("AO", 1e3);
+            dev.add_chan("ao0", -1.0);
+            let chan = dev.chan_mut("ao0").unwrap();
+            let lin = StdFnLib::new().LinFn(1.0, 0.0).unwrap().inner;
+            chan.add_instr(lin, 0.1, Some((0.1, true))).unwrap();
+            chan.constant(3.0, 0.5, Some((0.1, false))).unwrap();
+
+            assert_eq!(chan.eval_point(0.05).unwrap(), -1.0);
+            assert!((chan.eval_point(0.15).unwrap() - 0.15_f64).abs() < 1e-12);
+            // keep_val padding holds the value at `end_pos`
+            assert!((chan.eval_point(0.3).unwrap() - 0.2_f64).abs() < 1e-12);
+            assert_eq!(chan.eval_point(0.55).unwrap(), 3.0);
+            assert_eq!(chan.eval_point(0.9).unwrap(), -1.0);
+        }
+    }
+
+    mod check_finite {
+        use crate::channel::*;
+        use crate::device::BaseDev;
+        use crate::error::StreamerError;
+        use crate::fn_lib_tools::StdFnLib;
+        use crate::mock::test_impls::TestDev;
+
+        #[test]
+        fn negative_base_pow() {
+            let mut dev = TestDev::new("AO", 1e3);
+            dev.add_chan("ao0", 0.0);
+            let chan = dev.chan_mut("ao0").unwrap();
+            chan.constant(1.0, 0.0, Some((0.1, false))).unwrap();
+            // Negative base with fractional exponent for t < 0.5 - NaN on the whole interval
+            let pow = StdFnLib::new().Pow(0.5, 0.5, 1.0, 0.0).unwrap().inner;
+            chan.add_instr(pow, 0.2, Some((0.1, false))).unwrap();
+            dev.compile(1.0).unwrap();
+
+            let err = dev.check_finite(Some(3)).unwrap_err();
+            assert!(matches!(err, StreamerError::NonFinite { .. }));
+            assert_eq!(err.ctx().dev.as_deref(), Some("AO"));
+            assert!(err.msg().contains("pos 200"));
+
+            // Chunk-level check during streaming
+            let mut samp_buf = vec![0.0; 1000];
+            assert!(dev.calc_samps_checked(&mut samp_buf[..100], 0, 100).is_ok());
+            let err = dev.calc_samps_checked(&mut samp_buf[..100], 150, 250).unwrap_err();
+            assert!(err.msg().contains("pos 200"));
+        }
     }
 }
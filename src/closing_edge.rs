@@ -0,0 +1,107 @@
+//! Closing edge policy - whether `compile` appends an extra sample after the stop tick.
+//!
+//! If the last instruction of a channel ends exactly at the stop tick, generation would stop on its last sample and
+//! the after-end padding (the "closing edge" of a pulse) would never be output - NI cards simply keep the last value.
+//! By default ([`ClosingEdge::Auto`]) [`BaseDev::compile_base`] then runs the device one clock cycle longer.
+//! That silently changes the device length, which breaks setups requiring all devices to stream exactly
+//! the same number of samples. The policy is set per device with [`BaseDev::set_closing_edge`], or for all devices
+//! with [`BaseStreamer::set_closing_edge_all`]:
+//! - [`ClosingEdge::Auto`] - one extra sample only when a closing edge would be clipped;
+//! - [`ClosingEdge::Always`] - one extra sample on every compile, so the length only depends on the stop time;
+//! - [`ClosingEdge::Never`] - no extra sample; a clipped closing edge is reported as a warning instead.
+//!
+//! Every decision to add or skip the sample is recorded as a [`DiagnosticKind::ClosingEdge`] compile diagnostic.
+//!
+//! [`BaseDev::compile_base`]: crate::device::BaseDev::compile_base
+//! [`BaseDev::set_closing_edge`]: crate::device::BaseDev::set_closing_edge
+//! [`BaseStreamer::set_closing_edge_all`]: crate::streamer::BaseStreamer::set_closing_edge_all
+//! [`DiagnosticKind::ClosingEdge`]: crate::diagnostics::DiagnosticKind::ClosingEdge
+
+use std::fmt;
+use std::fmt::Display;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ClosingEdge {
+    /// Extra sample only if the closing edge of a last instruction would be clipped
+    #[default]
+    Auto,
+    /// Extra sample on every compile
+    Always,
+    /// Never an extra sample
+    Never,
+}
+
+impl ClosingEdge {
+    /// Number of extra samples to append after the stop tick, `clipped` - whether a closing edge ends there
+    pub fn extra_ticks(&self, clipped: bool) -> usize {
+        match self {
+            Self::Auto => clipped as usize,
+            Self::Always => 1,
+            Self::Never => 0,
+        }
+    }
+}
+
+impl Display for ClosingEdge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Auto => "auto",
+            Self::Always => "always",
+            Self::Never => "never",
+        };
+        write!(f, "{name}")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::channel::BaseChan;
+    use crate::device::BaseDev;
+    use crate::diagnostics::{DiagnosticKind, Severity};
+    use crate::mock::test_impls::TestStreamer;
+    use crate::streamer::BaseStreamer;
+
+    #[test]
+    fn policies() {
+        let mut streamer = TestStreamer::new();
+        streamer.add_ao_dev("AO", 1e3);
+        streamer.add_do_dev("DO", 1e3);
+        streamer.ao_devs["AO"].add_chan("ao0", 0.0);
+        streamer.do_devs["DO"].add_chan("port0/line0", false);
+        // AO pulse ends at the stop time, DO pulse well before it
+        streamer.ao_devs["AO"].chan_mut("ao0").unwrap().constant(1.0, 0.1, Some((0.4, false))).unwrap();
+        streamer.do_devs["DO"].chan_mut("port0/line0").unwrap().constant(true, 0.1, Some((0.1, false))).unwrap();
+        let stop_positions = |streamer: &TestStreamer| {
+            (streamer.ao_devs["AO"].compiled_stop_pos(), streamer.do_devs["DO"].compiled_stop_pos())
+        };
+        let closing_edge_diag = |streamer: &TestStreamer, dev: &str| {
+            streamer.diagnostics().into_iter().find(|entry| entry.kind == DiagnosticKind::ClosingEdge && entry.dev.as_deref() == Some(dev))
+        };
+
+        streamer.compile(None).unwrap();
+        assert_eq!(stop_positions(&streamer), (501, 500));
+        assert_eq!(closing_edge_diag(&streamer, "AO").unwrap().severity, Severity::Info);
+        assert!(closing_edge_diag(&streamer, "DO").is_none());
+        assert_eq!(streamer.ao_devs["AO"].chan("ao0").unwrap().eval_point(0.5).unwrap(), 0.0);
+
+        streamer.set_closing_edge_all(ClosingEdge::Always).unwrap();
+        streamer.compile(None).unwrap();
+        assert_eq!(stop_positions(&streamer), (501, 501));
+        assert!(closing_edge_diag(&streamer, "DO").unwrap().message.contains("policy always"));
+        assert_eq!(streamer.predicted_samples(None).unwrap().dev("DO").unwrap().closing_edge_ticks, 1);
+
+        streamer.set_closing_edge_all(ClosingEdge::Never).unwrap();
+        streamer.compile(None).unwrap();
+        assert_eq!(stop_positions(&streamer), (500, 500));
+        let diag = closing_edge_diag(&streamer, "AO").unwrap();
+        assert_eq!(diag.severity, Severity::Warning);
+        assert!(diag.message.contains("not added"));
+        assert!(closing_edge_diag(&streamer, "DO").is_none());
+
+        streamer.set_closing_edge("DO", ClosingEdge::Auto).unwrap();
+        assert_eq!(streamer.do_devs["DO"].closing_edge(), ClosingEdge::Auto);
+        assert_eq!(streamer.ao_devs["AO"].closing_edge(), ClosingEdge::Never);
+        assert!(streamer.set_closing_edge("Missing", ClosingEdge::Auto).is_err());
+    }
+}
@@ -0,0 +1,130 @@
+//! Consolidated collision reports for bulk imports.
+//!
+//! `add_instr()` stops at the first collision, so fixing a large imported schedule one error at a time is tedious.
+//! The reports in this module check a whole batch of planned instructions against the existing edit cache
+//! and against each other, listing every colliding pair at once. Nothing is added to the edit cache.
+//!
+//! Pairs overlapping by 2 or more ticks and pairs starting at the same tick are reported - `add_instr()` would reject them.
+//! 1-tick overlaps are left out since `add_instr()` resolves them by itself (recording a diagnostic).
+//! Only the base layer is checked.
+//!
+//! Reports are built by [`BaseChan::collision_report`], [`BaseDev::collision_report`],
+//! and [`BaseStreamer::schedule_collision_report`].
+//!
+//! [`BaseChan::collision_report`]: crate::channel::BaseChan::collision_report
+//! [`BaseDev::collision_report`]: crate::device::BaseDev::collision_report
+//! [`BaseStreamer::schedule_collision_report`]: crate::streamer::BaseStreamer::schedule_collision_report
+
+use std::fmt;
+use std::fmt::Display;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use crate::diff::InstrSnapshot;
+
+/// Pair of colliding instructions, `first` starting no later than `second`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Collision {
+    /// Filled in by the device
+    pub dev: Option<String>,
+    pub chan: String,
+    pub first: InstrSnapshot,
+    pub second: InstrSnapshot,
+    /// Number of overlapping ticks
+    pub overlap: usize,
+}
+
+impl Collision {
+    pub fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("dev", &self.dev)?;
+        dict.set_item("chan", &self.chan)?;
+        dict.set_item("first", self.first.to_string())?;
+        dict.set_item("second", self.second.to_string())?;
+        dict.set_item("overlap", self.overlap)?;
+        Ok(dict)
+    }
+}
+
+impl Display for Collision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.dev {
+            Some(dev) => write!(f, "{dev}/{}", self.chan)?,
+            None => write!(f, "{}", self.chan)?,
+        }
+        write!(f, ": {} overlaps {} by {} ticks", self.first, self.second, self.overlap)
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CollisionReport {
+    pub collisions: Vec<Collision>,
+}
+
+impl CollisionReport {
+    pub fn is_empty(&self) -> bool {
+        self.collisions.is_empty()
+    }
+    pub fn len(&self) -> usize {
+        self.collisions.len()
+    }
+    /// Appends the collisions of `other`
+    pub fn extend(&mut self, other: CollisionReport) {
+        self.collisions.extend(other.collisions)
+    }
+    /// Fills in the device name of all collisions
+    pub fn in_dev(mut self, dev_name: &str) -> Self {
+        for collision in self.collisions.iter_mut() {
+            collision.dev.get_or_insert_with(|| dev_name.to_string());
+        }
+        self
+    }
+
+    pub fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("n_collisions", self.len())?;
+        let collisions = self.collisions.iter().map(|collision| collision.to_dict(py)).collect::<PyResult<Vec<_>>>()?;
+        dict.set_item("collisions", collisions)?;
+        Ok(dict)
+    }
+}
+
+impl Display for CollisionReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "no collisions")
+        }
+        writeln!(f, "{} collisions:", self.len())?;
+        for collision in self.collisions.iter() {
+            writeln!(f, "\t{collision}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Every colliding pair among `existing` and `batch` involving at least one `batch` instruction
+pub(crate) fn find_collisions(chan_name: &str, existing: Vec<InstrSnapshot>, batch: &[InstrSnapshot]) -> CollisionReport {
+    // (instruction, is_new) sorted by start
+    let mut all: Vec<(&InstrSnapshot, bool)> = existing.iter().map(|instr| (instr, false))
+        .chain(batch.iter().map(|instr| (instr, true)))
+        .collect();
+    all.sort_by_key(|(instr, _is_new)| instr.start_pos);
+
+    let eff_end = |instr: &InstrSnapshot| instr.end_spec.map_or(instr.start_pos + 1, |(end_pos, _keep_val)| end_pos);
+    let mut collisions = Vec::new();
+    for (idx, &(first, first_new)) in all.iter().enumerate() {
+        let first_end = eff_end(first);
+        for &(second, second_new) in all[idx + 1..].iter().take_while(|(second, _)| second.start_pos < first_end) {
+            let overlap = first_end.min(eff_end(second)) - second.start_pos;
+            if (first_new || second_new) && (overlap >= 2 || first.start_pos == second.start_pos) {
+                collisions.push(Collision {
+                    dev: None,
+                    chan: chan_name.to_string(),
+                    first: first.clone(),
+                    second: second.clone(),
+                    overlap,
+                })
+            }
+        }
+    }
+    CollisionReport { collisions }
+}
@@ -0,0 +1,145 @@
+//! Dead-time (break-before-make) enforcement between pairs of digital channels of one device.
+//!
+//! H-bridges, push-pull drivers, and relay pairs are damaged if both sides conduct at once, and many also need a
+//! minimum pause between one side switching off and the other switching on. A [`DeadTime`] declares such a pair:
+//! whenever one channel goes high, the other must have gone low at least `min_dead_time` earlier (and must not be
+//! high at that moment at all).
+//!
+//! Dead times are registered with [`BaseDev::add_dead_time`] and checked after every device compile, so a violating
+//! sequence fails to compile with [`StreamerError::RuleViolation`] listing the offending tick positions.
+//! The check runs on the compiled samples, so it sees exactly what is going to be streamed.
+//!
+//! [`BaseDev::add_dead_time`]: crate::device::BaseDev::add_dead_time
+//! [`StreamerError::RuleViolation`]: crate::error::StreamerError::RuleViolation
+
+use std::fmt;
+use std::fmt::Display;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeadTime {
+    pub chan_a: String,
+    pub chan_b: String,
+    /// Minimum time in seconds between one channel going low and the other going high
+    pub min_dead_time: f64,
+}
+
+impl DeadTime {
+    pub fn new(chan_a: &str, chan_b: &str, min_dead_time: f64) -> Self {
+        Self { chan_a: chan_a.to_string(), chan_b: chan_b.to_string(), min_dead_time }
+    }
+
+    /// Minimum dead time in clock ticks of a device with sample rate `samp_rate` (rounded up, so it is never shorter than requested)
+    pub fn min_ticks(&self, samp_rate: f64) -> usize {
+        // Tolerate float noise, e.g. `1e-3 * 1e4` is slightly above 10
+        (self.min_dead_time * samp_rate - 1e-9).ceil().max(0.0) as usize
+    }
+
+    /// Every rising edge in the sorted, disjoint high intervals `a_intervals` / `b_intervals` (of `chan_a` / `chan_b`)
+    /// which comes less than `min_ticks` after the other channel went low
+    pub fn violations(&self, a_intervals: &[(usize, usize)], b_intervals: &[(usize, usize)], min_ticks: usize) -> Vec<DeadTimeViolation> {
+        let mut rises: Vec<(usize, usize, bool)> = a_intervals.iter().map(|&(start, end)| (start, end, true))
+            .chain(b_intervals.iter().map(|&(start, end)| (start, end, false)))
+            .collect();
+        // Stable sort keeps `chan_a` first on simultaneous rising edges
+        rises.sort_by_key(|&(start, _, _)| start);
+
+        // End of the latest high interval of `chan_a` / `chan_b` seen so far
+        let (mut a_end, mut b_end): (Option<usize>, Option<usize>) = (None, None);
+        let mut violations = Vec::new();
+        for (start, end, is_a) in rises {
+            let (other_end, high_chan, low_chan) = if is_a {
+                (b_end, &self.chan_a, &self.chan_b)
+            } else {
+                (a_end, &self.chan_b, &self.chan_a)
+            };
+            if let Some(low_pos) = other_end.filter(|&low_pos| start < low_pos + min_ticks) {
+                violations.push(DeadTimeViolation { high_chan: high_chan.clone(), high_pos: start, low_chan: low_chan.clone(), low_pos })
+            }
+            if is_a { a_end = Some(end) } else { b_end = Some(end) }
+        }
+        violations
+    }
+}
+
+impl Display for DeadTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "dead time {} s between {} and {}", self.min_dead_time, self.chan_a, self.chan_b)
+    }
+}
+
+/// Channel `high_chan` goes high at tick `high_pos` too early after channel `low_chan` went low at tick `low_pos`
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeadTimeViolation {
+    pub high_chan: String,
+    pub high_pos: usize,
+    pub low_chan: String,
+    /// Can be above `high_pos` if `low_chan` is still high when `high_chan` goes high
+    pub low_pos: usize,
+}
+
+impl Display for DeadTimeViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.high_pos < self.low_pos {
+            write!(f, "{} goes high at tick {} while {} is high until tick {}", self.high_chan, self.high_pos, self.low_chan, self.low_pos)
+        } else {
+            write!(
+                f, "{} goes high at tick {}, only {} ticks after {} goes low at tick {}",
+                self.high_chan, self.high_pos, self.high_pos - self.low_pos, self.low_chan, self.low_pos
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::channel::BaseChan;
+    use crate::device::BaseDev;
+    use crate::error::StreamerError;
+    use crate::mock::test_impls::TestDev;
+
+    #[test]
+    fn violations() {
+        let dead_time = DeadTime::new("a", "b", 1e-3);
+        assert_eq!(dead_time.min_ticks(1e4), 10);
+        let found = dead_time.violations(&[(0, 100), (300, 400)], &[(105, 200), (410, 500), (600, 700)], 10);
+        assert_eq!(found, vec![
+            DeadTimeViolation { high_chan: "b".into(), high_pos: 105, low_chan: "a".into(), low_pos: 100 },
+        ]);
+        // Overlap and simultaneous rising edges
+        let found = dead_time.violations(&[(0, 100), (200, 300)], &[(50, 60), (200, 250)], 10);
+        assert_eq!(found, vec![
+            DeadTimeViolation { high_chan: "b".into(), high_pos: 50, low_chan: "a".into(), low_pos: 100 },
+            DeadTimeViolation { high_chan: "b".into(), high_pos: 200, low_chan: "a".into(), low_pos: 300 },
+        ]);
+        assert_eq!(found[0].to_string(), "b goes high at tick 50 while a is high until tick 100");
+    }
+
+    #[test]
+    fn compile() {
+        let mut dev = TestDev::<bool>::new("DO", 1e4);
+        dev.add_chan("hi_side", false);
+        dev.add_chan("lo_side", false);
+        dev.add_dead_time("hi_side", "lo_side", 1e-3).unwrap();
+        assert!(matches!(dev.add_dead_time("hi_side", "nope", 1e-3), Err(StreamerError::NotFound { .. })));
+        assert!(matches!(dev.add_dead_time("hi_side", "hi_side", 1e-3), Err(StreamerError::InvalidArgument { .. })));
+
+        dev.chan_mut("hi_side").unwrap().constant(true, 0.0, Some((0.01, false))).unwrap();
+        dev.chan_mut("lo_side").unwrap().constant(true, 0.0105, Some((0.01, false))).unwrap();
+        let err = dev.compile(0.1).unwrap_err();
+        assert!(matches!(err, StreamerError::RuleViolation { .. }));
+        assert_eq!(err.ctx().chan.as_deref(), Some("lo_side"));
+        assert!(err.to_string().contains("lo_side goes high at tick 105, only 5 ticks after hi_side goes low at tick 100"));
+
+        dev.clear_edit_cache();
+        dev.chan_mut("hi_side").unwrap().constant(true, 0.0, Some((0.01, false))).unwrap();
+        dev.chan_mut("lo_side").unwrap().constant(true, 0.011, Some((0.01, false))).unwrap();
+        dev.compile(0.1).unwrap();
+
+        // Dead times only apply to digital channels
+        let mut ao_dev = TestDev::<f64>::new("AO", 1e4);
+        ao_dev.add_chan("ao0", 0.0);
+        ao_dev.add_chan("ao1", 0.0);
+        assert!(matches!(ao_dev.add_dead_time("ao0", "ao1", 1e-3), Err(StreamerError::Incompatible { .. })));
+    }
+}
@@ -35,7 +35,72 @@
 use ndarray::Array1;
 use indexmap::IndexMap;
 use itertools::Itertools;
-use crate::channel::BaseChan;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use std::any::{Any, TypeId};
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use crate::channel::{AsF64, BaseChan, ChanSampCursor, ConstFn, LayerInstrs, Runs, samp_to_f64};
+use crate::fn_lib_tools::{Complex64, CounterBit, FnTraitSet, IqPart, Quadrature, TimeMap};
+use crate::instruction::Instr;
+use crate::mock::MockStreamTarget;
+use crate::padding::DevPadding;
+use crate::sync::SyncSpec;
+use crate::skew::Edge;
+use crate::rounding::TickRounding;
+use crate::closing_edge::ClosingEdge;
+use crate::spi::{SpiMode, SpiPatterns};
+use crate::hash::StableHasher;
+use crate::error::{ErrCtx, StreamerError};
+use crate::validation::DevReport;
+use crate::options::CompileOptions;
+use crate::diagnostics::{Diagnostic, Diagnostics, DiagnosticKind, DiagnosticStage, Severity};
+use crate::profiling::ProfileEntry;
+use crate::marker::{MarkerRule, push_merged};
+use crate::dead_time::DeadTime;
+use crate::abort::AbortSpec;
+use crate::openpulse::PulseQobj;
+use crate::collisions::CollisionReport;
+use crate::diff::InstrSnapshot;
+use crate::inspect::{ChanInfo, DevInfo};
+use crate::summary::{ChanSummary, DevSummary};
+use crate::budget::{DevBudget, DevPrediction};
+use crate::dry_run::DevDryRun;
+use crate::idle::{self, DevIdle};
+
+/// Activity windows of a device - channel name -> `(first_instr_start_time, last_instr_end_time)`, see [`BaseDev::activity_windows`]
+pub type ActivityWindows = IndexMap<String, (f64, f64)>;
+
+/// Plotting data of a device - channel name -> `(t_arr, samps)`, see [`BaseDev::plot_data`]
+pub type DevPlotData = IndexMap<String, (Vec<f64>, Vec<f64>)>;
+
+/// Optional settings of a device - see [`BaseDev::settings`]
+#[derive(Clone, Debug, Default)]
+pub struct DevSettings {
+    /// See [`BaseDev::set_sync_spec`]
+    pub sync_spec: Option<SyncSpec>,
+    /// See [`BaseDev::set_tick_rounding`]
+    pub tick_rounding: TickRounding,
+    /// See [`BaseDev::set_trigger_delay`]
+    pub trigger_delay: f64,
+    /// See [`BaseDev::add_dead_time`]
+    pub dead_times: Vec<DeadTime>,
+    /// See [`BaseDev::set_closing_edge`]
+    pub closing_edge: ClosingEdge,
+}
+
+/// Edit caches, enabled flags, and diagnostics of a device and its channels, see [`BaseDev::edit_state`]
+pub struct DevEditState<T> {
+    chans: Vec<ChanEditState<T>>,
+    diagnostics: Diagnostics,
+}
+struct ChanEditState<T> {
+    instr_list: BTreeSet<Instr<T>>,
+    layers: Option<LayerInstrs<T>>,
+    enabled: Option<bool>,
+    diagnostics: Diagnostics,
+}
 
 /// The `BaseDevice` trait defines the fundamental operations and attributes of a National Instruments (NI) device.
 ///
@@ -48,35 +113,37 @@ use crate::channel::BaseChan;
 /// # Trait Methods and Their Functionality:
 ///
 /// - **Field methods**: These provide direct access to the properties of a device, such as its channels, physical name,
-/// sampling rate, and various configuration parameters.
+///   sampling rate, and various configuration parameters.
 ///
 /// - **Synchronization configuration**: Customize the synchronization behavior of devices via [`BaseDevice::cfg_trig`],
-/// [`BaseDevice::cfg_ref_clk`], [`BaseDevice::cfg_samp_clk_src`]. See [`Device`] for more details.
+///   [`BaseDevice::cfg_ref_clk`], [`BaseDevice::cfg_samp_clk_src`]. See [`Device`] for more details.
 ///
 /// - **Channel management**: Methods like [`BaseDevice::editable_channels`], [`BaseDevice::editable_channels_`], and
-/// [`BaseDevice::add_channel`] allow for the retrieval and manipulation of channels associated with the device.
+///   [`BaseDevice::add_channel`] allow for the retrieval and manipulation of channels associated with the device.
 ///
 /// - **Device status checks**: Methods like [`BaseDevice::is_compiled`], [`BaseDevice::is_edited`], and
-/// [`BaseDevice::is_fresh_compiled`] enable checking the compilation and editing status of the device's channels.
+///   [`BaseDevice::is_fresh_compiled`] enable checking the compilation and editing status of the device's channels.
 ///
 /// - **Cache operations**: The methods [`BaseDevice::clear_edit_cache`] and [`BaseDevice::clear_compile_cache`] are
-/// used to clear the edit and compile caches of the device's channels, respectively.
+///   used to clear the edit and compile caches of the device's channels, respectively.
 ///
 /// - **Compilation**: The [`BaseDevice::compile`] method takes care of the signal compilation process for the device's
-/// channels. For Digital Output (DO) channels, it provides additional functionality to merge line channels into port channels.
+///   channels. For Digital Output (DO) channels, it provides additional functionality to merge line channels into port channels.
 ///
 /// - **Signal generation**: The [`BaseDevice::fill_signal_nsamps`] and [`BaseDevice::calc_signal_nsamps`] methods are
-/// central to signal generation, allowing for the sampling of float-point values from compiled instructions based on
-/// various criteria.
+///   central to signal generation, allowing for the sampling of float-point values from compiled instructions based on
+///   various criteria.
 ///
 /// - **Utility functions**: Methods like [`BaseDevice::unique_port_numbers`] offer utility functionalities specific to certain
-/// task types, aiding in operations like identifying unique ports in Digital Output (DO) devices.
+///   task types, aiding in operations like identifying unique ports in Digital Output (DO) devices.
 ///
 ///
 /// # Implementing [`BaseDevice`]:
 ///
 /// When creating a new type that represents an NI device, implementing this trait ensures that the type has all the necessary methods and behaviors typical of NI devices. Implementers can then extend or override these methods as necessary to provide device-specific behavior or optimizations.
-pub trait BaseDev {
+///
+/// Devices must be `Send` so that a streamer can compile them in parallel (see the `parallel` feature).
+pub trait BaseDev: Send {
     /// Output channel type
     type Chan: BaseChan;
 
@@ -86,25 +153,54 @@ pub trait BaseDev {
 
     fn chans(&self) -> Vec<&Self::Chan>;
     fn chans_mut(&mut self) -> Vec<&mut Self::Chan>;
+    /// Record of device-level non-fatal decisions (channel-level entries are kept by channels)
+    fn diagnostics(&self) -> &Diagnostics;
+    fn diagnostics_mut(&mut self) -> &mut Diagnostics;
+
+    /// Device-level and all channel-level diagnostic entries, tagged with the device name
+    fn collect_diagnostics(&self) -> Vec<Diagnostic> {
+        let mut entries: Vec<Diagnostic> = self.diagnostics().entries().clone();
+        for chan in self.chans() {
+            entries.extend(chan.diagnostics().entries().iter().cloned())
+        }
+        for entry in entries.iter_mut() {
+            entry.dev = Some(self.name())
+        }
+        entries
+    }
+
+    /// Timing entries of all channels, tagged with the device name - see [`crate::profiling`]
+    fn profile_entries(&self) -> Vec<ProfileEntry> {
+        let mut entries: Vec<ProfileEntry> = self.chans().iter().flat_map(|chan| chan.profile_entries()).collect();
+        for entry in entries.iter_mut() {
+            entry.dev = Some(self.name())
+        }
+        entries
+    }
+    fn clear_profile(&self) {
+        for chan in self.chans() {
+            chan.clear_profile()
+        }
+    }
 
     /// Shortcut to borrow channel instance by name
-    fn chan(&self, name: &str) -> Result<&Self::Chan, String> {
-        let search_idx = self.chans().iter().position(|chan| chan.name() == name.to_string());
+    fn chan(&self, name: &str) -> Result<&Self::Chan, StreamerError> {
+        let search_idx = self.chans().iter().position(|chan| chan.name() == name);
 
         if let Some(idx) = search_idx {
             Ok(self.chans().swap_remove(idx))
         } else {
-            Err(format!("Device {} does not have channel {name}", self.name()))
+            Err(StreamerError::NotFound { ctx: ErrCtx::dev(self.name()), msg: format!("does not have channel {name}") })
         }
     }
     /// Shortcut to mutably borrow channel instance by name
-    fn chan_mut(&mut self, name: &str) -> Result<&mut Self::Chan, String> {
-        let search_res = self.chans().iter().position(|chan| chan.name() == name.to_string());
+    fn chan_mut(&mut self, name: &str) -> Result<&mut Self::Chan, StreamerError> {
+        let search_res = self.chans().iter().position(|chan| chan.name() == name);
 
         if let Some(idx) = search_res {
             Ok(self.chans_mut().swap_remove(idx))
         } else {
-            Err(format!("Device {} does not have channel {name}", self.name()))
+            Err(StreamerError::NotFound { ctx: ErrCtx::dev(self.name()), msg: format!("does not have channel {name}") })
         }
     }
 
@@ -114,41 +210,517 @@ pub trait BaseDev {
     }
 
     /// Adds a new channel to the device.
-    fn check_can_add_chan(&mut self, chan: &Self::Chan) -> Result<(), String> {
+    fn check_can_add_chan(&mut self, chan: &Self::Chan) -> Result<(), StreamerError> {
         if f64::abs(chan.samp_rate() - self.samp_rate()) >= 1e-10 {
-            return Err(format!(
-                "Cannot add channel {} with samp_rate={} to device {} with a different samp_rate={}",
-                chan.name(), chan.samp_rate(), self.name(), self.samp_rate()
-            ))
+            return Err(StreamerError::Incompatible {
+                ctx: ErrCtx::dev(self.name()),
+                msg: format!(
+                    "cannot add channel {} with samp_rate={} to a device with a different samp_rate={}",
+                    chan.name(), chan.samp_rate(), self.samp_rate()
+                ),
+            })
         };
         let chan_names: Vec<_> = self.chans().iter().map(|chan| chan.name()).collect();
         if chan_names.contains(&chan.name()) {
-            return Err(format!(
-                "There is already a channel with name {} registered. Registered channels are {:?}",
-                chan.name(), chan_names
-            ))
+            return Err(StreamerError::AlreadyExists {
+                ctx: ErrCtx::dev(self.name()),
+                msg: format!(
+                    "There is already a channel with name {} registered. Registered channels are {:?}",
+                    chan.name(), chan_names
+                ),
+            })
         };
         Ok(())
     }
 
-    fn add_reset_instr(&mut self, reset_time: f64) -> Result<(), String> {
-        let reset_pos = (reset_time * self.samp_rate()).round() as usize;
+    fn add_reset_instr(&mut self, reset_time: f64) -> Result<(), StreamerError> {
+        let reset_pos = self.time_to_pos(reset_time)?;
 
         // Sanity check - reset_pos does not clip any existing instructions
         if self.last_instr_end_pos().is_some_and(|last_instr_end| reset_pos < last_instr_end) {
-            return Err(format!(
-                "[Device {}] given reset_time {reset_time} was rounded to {reset_pos} clock cycles \
-                which is below the last instruction end position {}",
-                self.name(), self.last_instr_end_pos().unwrap()
-            ))
+            return Err(StreamerError::OutOfRange {
+                ctx: ErrCtx::dev(self.name()),
+                msg: format!(
+                    "given reset_time {reset_time} was rounded to {reset_pos} clock cycles \
+                    which is below the last instruction end position {}",
+                    self.last_instr_end_pos().unwrap()
+                ),
+            })
+        }
+
+        let dev_name = self.name();
+        for chan in self.chans_mut() {
+            chan.add_reset_instr(reset_pos).map_err(|err| err.in_dev(dev_name.clone()))?
+        };
+        Ok(())
+    }
+
+    /// Reset value of every channel (converted to `f64`) - the state [`BaseDev::compile_safe_state`] drives the device to
+    fn safe_state(&self) -> IndexMap<String, f64> {
+        self.chans().iter().map(|chan| (chan.name(), samp_to_f64(&chan.rst_val()))).collect()
+    }
+
+    /// Replaces the sequence with the minimal abort waveform: every channel is held at its reset value for `dur` seconds.
+    /// The result is compiled and ready to stream.
+    ///
+    /// Muted channels are unmuted for good (see [`BaseChan::set_enabled`]) since the abort waveform has to reach every channel.
+    /// The previous edit cache is discarded - snapshot it beforehand if it is needed again.
+    /// All or nothing: if the safe state can't be compiled (e.g. `dur` rounds to zero ticks or doesn't exceed the trigger delay),
+    /// the edit caches and enabled flags are restored (with a cleared compile cache) and the error is returned.
+    fn compile_safe_state(&mut self, dur: f64) -> Result<(), StreamerError> {
+        if self.time_to_pos(dur)? == 0 {
+            return Err(StreamerError::OutOfRange {
+                ctx: ErrCtx::dev(self.name()),
+                msg: format!("compile_safe_state(): dur = {dur} s is shorter than one clock period"),
+            })
+        }
+        let state = self.edit_state();
+        self.clear_edit_cache();
+        let dev_name = self.name();
+        let res = self.chans_mut().into_iter().try_for_each(|chan| {
+            if chan.settings().is_some() {
+                chan.set_enabled(true)?
+            }
+            chan.constant(chan.rst_val(), 0.0, None)
+        }).map_err(|err| err.in_dev(dev_name)).and_then(|()| self.compile(dur));
+        if res.is_err() {
+            self.restore_edit_state(state)
+        }
+        res
+    }
+    /// Snapshot of the edit caches, enabled flags, and diagnostics to undo an edit with [`BaseDev::restore_edit_state`]
+    fn edit_state(&self) -> DevEditState<<Self::Chan as BaseChan>::Samp> {
+        DevEditState {
+            chans: self.chans().iter().map(|chan| ChanEditState {
+                instr_list: chan.instr_list().clone(),
+                layers: chan.layer_instrs().cloned(),
+                enabled: chan.settings().map(|settings| settings.enabled),
+                diagnostics: chan.diagnostics().clone(),
+            }).collect(),
+            diagnostics: self.diagnostics().clone(),
+        }
+    }
+    /// Restores a snapshot taken by [`BaseDev::edit_state`]. The compile cache is cleared.
+    fn restore_edit_state(&mut self, state: DevEditState<<Self::Chan as BaseChan>::Samp>) {
+        self.clear_compile_cache();
+        for (chan, chan_state) in self.chans_mut().into_iter().zip(state.chans) {
+            *chan.instr_list_mut() = chan_state.instr_list;
+            if let (Some(settings), Some(layers), Some(enabled)) = (chan.settings_mut(), chan_state.layers, chan_state.enabled) {
+                settings.layers = layers;
+                settings.enabled = enabled;
+            }
+            *chan.diagnostics_mut() = chan_state.diagnostics;
+            *chan.is_fresh_compiled_mut() = false;
+        }
+        *self.diagnostics_mut() = state.diagnostics;
+    }
+
+    /// Returns every channel to its default value at `t`, see [`BaseChan::off`].
+    fn all_off(&mut self, t: f64) -> Result<(), StreamerError> {
+        let dev_name = self.name();
+        for chan in self.chans_mut() {
+            chan.off(t).map_err(|err| err.in_dev(dev_name.clone()))?
+        };
+        Ok(())
+    }
+
+    /// Whether any channel defines preset `name`
+    fn has_preset(&self, name: &str) -> bool {
+        self.chans().iter().any(|chan| chan.preset(name).is_some())
+    }
+    /// Moves every channel defining preset `name` to its preset value at `t`, see [`BaseChan::add_preset_instr`].
+    /// Channels without this preset are left untouched.
+    ///
+    /// Returns [`StreamerError::NotFound`] if no channel of the device defines the preset.
+    fn add_preset_instr(&mut self, name: &str, t: f64) -> Result<(), StreamerError> {
+        if !self.has_preset(name) {
+            return Err(StreamerError::NotFound {
+                ctx: ErrCtx::dev(self.name()),
+                msg: format!("no channel defines preset \"{name}\""),
+            })
+        }
+        let dev_name = self.name();
+        for chan in self.chans_mut().into_iter().filter(|chan| chan.preset(name).is_some()) {
+            chan.add_preset_instr(name, t).map_err(|err| err.in_dev(dev_name.clone()))?
+        };
+        Ok(())
+    }
+
+    /// Drives the channel pair `i_chan` / `q_chan` with a single complex function: the real part goes to `i_chan`
+    /// and the imaginary part to `q_chan` (see [`IqPart`]). Both instructions share `func`, start time, and duration,
+    /// so the pair can't drift apart the way separately edited I and Q instructions can (e.g. for SSB modulation).
+    ///
+    /// Either both instructions are inserted or neither - a failure on `q_chan` restores the edit cache and diagnostics of `i_chan`.
+    fn add_iq_instr(&mut self, i_chan: &str, q_chan: &str, func: Box<dyn FnTraitSet<Complex64>>, t: f64, dur_spec: Option<(f64, bool)>) -> Result<(), StreamerError>
+        where Self::Chan: BaseChan<Samp = f64>
+    {
+        if i_chan == q_chan {
+            return Err(StreamerError::InvalidArgument {
+                ctx: ErrCtx::dev(self.name()),
+                msg: format!("add_iq_instr(): I and Q must be different channels, got \"{i_chan}\" for both"),
+            })
+        }
+        self.chan(q_chan)?;
+        let dev_name = self.name();
+        let func: Arc<dyn FnTraitSet<Complex64>> = func.into();
+
+        let i = self.chan_mut(i_chan)?;
+        // A 1-tick collision fix may trim a neighbour and records a diagnostic - restore both on failure
+        let snapshot = (i.instr_list().clone(), i.diagnostics().clone());
+        i.add_instr(Box::new(IqPart::new(Arc::clone(&func), Quadrature::I)), t, dur_spec).map_err(|err| err.in_dev(dev_name.clone()))?;
+
+        if let Err(err) = self.chan_mut(q_chan)?.add_instr(Box::new(IqPart::new(func, Quadrature::Q)), t, dur_spec) {
+            let i = self.chan_mut(i_chan)?;
+            (*i.instr_list_mut(), *i.diagnostics_mut()) = snapshot;
+            return Err(err.in_dev(dev_name))
+        }
+        Ok(())
+    }
+
+    /// Adds one frame per active channel (named `"<device>/<channel>"`) to `qobj`, see [`crate::openpulse`]
+    fn add_to_pulse_qobj(&self, qobj: &mut PulseQobj) -> Result<(), StreamerError> {
+        self.validate_compile_cache()?;
+        for chan in self.active_chans() {
+            qobj.add_chan(&format!("{}/{}", self.name(), chan.name()), chan).map_err(|err| err.in_dev(self.name()))?
+        }
+        Ok(())
+    }
+
+    /// Marker intervals of channel `chan_name` in seconds, see [`BaseChan::marker_intervals`]
+    fn marker_intervals(&self, chan_name: &str, rule: &MarkerRule) -> Result<Vec<(f64, f64)>, StreamerError> {
+        let chan = self.chan(chan_name)?;
+        let clk_period = self.clk_period();
+        let intervals = chan.marker_intervals(rule).map_err(|err| err.in_dev(self.name()))?;
+        Ok(intervals.into_iter().map(|(start, end)| (start as f64 * clk_period, end as f64 * clk_period)).collect())
+    }
+    /// Edges of channel `chan_name` on the trigger time axis (tick time plus trigger delay), see [`crate::skew`]
+    fn edges(&self, chan_name: &str, threshold: f64) -> Result<Vec<Edge>, StreamerError> {
+        let intervals = self.marker_intervals(chan_name, &MarkerRule::Threshold(threshold))?;
+        let stop_time = self.chan(chan_name)?.try_compiled_stop_time().map_err(|err| err.in_dev(self.name()))?;
+        let delay = self.trigger_delay();
+        Ok(Edge::from_intervals(&intervals, stop_time).into_iter().map(|edge| Edge { t: edge.t + delay, ..edge }).collect())
+    }
+    /// Replaces the edit cache of the marker channel `chan_name` with one "high" instruction per interval (in seconds).
+    /// Intervals are rounded to this device's clock, merged where they touch, and dropped if shorter than one clock period.
+    ///
+    /// Returns [`StreamerError::Incompatible`] if the channels of this device don't have `bool` samples.
+    fn write_marker(&mut self, chan_name: &str, intervals: &[(f64, f64)]) -> Result<(), StreamerError> {
+        let high: Box<dyn Any> = Box::new(ConstFn::new(true));
+        let Ok(high) = high.downcast::<ConstFn<<Self::Chan as BaseChan>::Samp>>() else {
+            return Err(StreamerError::Incompatible {
+                ctx: ErrCtx::dev(self.name()),
+                msg: format!("marker channel {chan_name} must have bool samples"),
+            })
+        };
+        let samp_rate = self.samp_rate();
+        let dev_name = self.name();
+        let chan = self.chan_mut(chan_name)?;
+        let mut pos_intervals = Vec::new();
+        for &(start, end) in intervals {
+            let to_pos = |t| chan.time_to_pos(t).map_err(|err| err.in_dev(dev_name.clone()));
+            push_merged(&mut pos_intervals, to_pos(start)?, to_pos(end)?)
+        }
+        chan.clear_edit_cache();
+        for (start_pos, end_pos) in pos_intervals {
+            chan.add_instr(
+                high.clone_to_box(), start_pos as f64 / samp_rate, Some(((end_pos - start_pos) as f64 / samp_rate, false))
+            ).map_err(|err| err.in_dev(dev_name.clone()))?
+        }
+        Ok(())
+    }
+
+    /// [`BaseChan::add_edges`] on channel `chan_name`.
+    ///
+    /// Returns [`StreamerError::Incompatible`] if the channels of this device don't have `bool` samples.
+    fn add_edges(&mut self, chan_name: &str, edges: &[(f64, bool)]) -> Result<(), StreamerError> {
+        let levels: Box<dyn Any> = Box::new([ConstFn::new(false), ConstFn::new(true)]);
+        let Ok(levels) = levels.downcast::<[ConstFn<<Self::Chan as BaseChan>::Samp>; 2]>() else {
+            return Err(StreamerError::Incompatible {
+                ctx: ErrCtx::dev(self.name()),
+                msg: format!("can't replay digital edges on channel {chan_name} - it must have bool samples"),
+            })
+        };
+        let dev_name = self.name();
+        let chan = self.chan_mut(chan_name)?;
+        let dflt: Box<dyn Any> = Box::new(chan.dflt_val());
+        let mut level = *dflt.downcast::<bool>().expect("sample type checked above");
+        for &(t, new_level) in edges {
+            if new_level != level {
+                chan.add_instr(levels[new_level as usize].clone_to_box(), t, None).map_err(|err| err.in_dev(dev_name.clone()))?;
+                level = new_level;
+            }
+        }
+        Ok(())
+    }
+
+    /// [`BaseChan::add_pulse_array`] on channel `chan_name` - stamps the template `func` at every time of `start_times`
+    fn add_pulse_array(
+        &mut self, chan_name: &str, func: Box<dyn FnTraitSet<<Self::Chan as BaseChan>::Samp>>, start_times: &[f64], dur: f64, keep_val: bool
+    ) -> Result<(), StreamerError> {
+        let dev_name = self.name();
+        self.chan_mut(chan_name)?.add_pulse_array(func, start_times, dur, keep_val).map_err(|err| err.in_dev(dev_name))
+    }
+
+    /// Drives the bool channels `chan_names` with a counter incremented every `period` seconds, e.g. to step a multiplexer
+    /// address through its inputs: `chan_names[0]` outputs the least significant bit. With `gray` the channels carry the
+    /// Gray code of the count, so only one line switches per step. Counting starts from 0 at `t` and runs for `dur` seconds,
+    /// wrapping around after `2^chan_names.len()` steps. Each channel gets one [`CounterBit`] instruction.
+    ///
+    /// Either all instructions are inserted or none. Returns [`StreamerError::Incompatible`] if the channels of this device
+    /// don't have `bool` samples and [`StreamerError::InvalidArgument`] for an empty, repeated, or over 64 channel list
+    /// or a non-positive period.
+    ///
+    fn add_counter(&mut self, chan_names: &[&str], t: f64, dur: f64, period: f64, gray: bool, keep_val: bool) -> Result<(), StreamerError> {
+        let dev_name = self.name();
+        if chan_names.is_empty() || chan_names.len() > u64::BITS as usize || period.is_nan() || period <= 0.0 {
+            return Err(StreamerError::InvalidArgument {
+                ctx: ErrCtx::dev(dev_name.clone()),
+                msg: format!(
+                    "add_counter(): needs 1 to 64 channels and a positive period, got {chan_names:?} and period {period}"
+                ),
+            })
+        }
+        let bit_fns = (0..chan_names.len()).map(|bit| -> Box<dyn FnTraitSet<bool>> {
+            Box::new(CounterBit::new(bit as u32, period, t, gray))
+        }).collect();
+        self.add_bool_instr_set(chan_names, bit_fns, t, Some((dur, keep_val)))
+    }
+
+    /// Shifts `word` (bits in order of output) out over the bool channels `clk_chan`, `data_chan`, and `cs_chan`
+    /// (chip select, active low) at `bit_rate` bits per second starting from `t0`, see [`crate::spi`] for the timing.
+    ///
+    /// Either all three instructions are inserted or none. Returns [`StreamerError::Incompatible`] if the channels
+    /// of this device don't have `bool` samples and [`StreamerError::InvalidArgument`] for an empty word, repeated
+    /// channels, or a half bit period which is not a whole number (at least 1) of clock ticks.
+    #[allow(clippy::too_many_arguments)]
+    fn spi_transaction(
+        &mut self, clk_chan: &str, data_chan: &str, cs_chan: &str, t0: f64, word: &[bool], bit_rate: f64, mode: SpiMode
+    ) -> Result<(), StreamerError> {
+        let dev_name = self.name();
+        let half_period_ticks = self.samp_rate() / (2.0 * bit_rate);
+        if word.is_empty() || !half_period_ticks.is_finite() || half_period_ticks.round() < 1.0 || (half_period_ticks - half_period_ticks.round()).abs() > 1e-6 {
+            return Err(StreamerError::InvalidArgument {
+                ctx: ErrCtx::dev(dev_name.clone()),
+                msg: format!(
+                    "spi_transaction(): needs a non-empty word and a half bit period of a whole number of clock ticks, \
+                    got {} bits at {bit_rate} bit/s ({half_period_ticks} ticks per half bit)", word.len()
+                ),
+            })
+        }
+        let patterns = SpiPatterns::new(t0, word, bit_rate, mode);
+        let fns: Vec<Box<dyn FnTraitSet<bool>>> = vec![Box::new(patterns.clk), Box::new(patterns.data), Box::new(patterns.cs)];
+        self.add_bool_instr_set(&[clk_chan, data_chan, cs_chan], fns, t0, Some((patterns.dur, true)))
+    }
+
+    /// Adds one instruction with function `fns[i]` on bool channel `chan_names[i]` each, all with the same timing.
+    /// Either all instructions are inserted or none - a failure restores the edit caches of the channels changed before.
+    ///
+    /// Returns [`StreamerError::Incompatible`] if the channels of this device don't have `bool` samples
+    /// and [`StreamerError::InvalidArgument`] if a channel is named twice.
+    fn add_bool_instr_set(
+        &mut self, chan_names: &[&str], fns: Vec<Box<dyn FnTraitSet<bool>>>, t: f64, dur_spec: Option<(f64, bool)>
+    ) -> Result<(), StreamerError> {
+        let dev_name = self.name();
+        if !chan_names.iter().all_unique() {
+            return Err(StreamerError::InvalidArgument {
+                ctx: ErrCtx::dev(dev_name.clone()),
+                msg: format!("every channel can only be used once, got {chan_names:?}"),
+            })
+        }
+        let fns: Box<dyn Any> = Box::new(fns);
+        let Ok(fns) = fns.downcast::<Vec<Box<dyn FnTraitSet<<Self::Chan as BaseChan>::Samp>>>>() else {
+            return Err(StreamerError::Incompatible {
+                ctx: ErrCtx::dev(dev_name.clone()),
+                msg: format!("channels {chan_names:?} must have bool samples"),
+            })
+        };
+
+        let mut snapshots = Vec::with_capacity(chan_names.len());
+        for &chan_name in chan_names {
+            snapshots.push(self.chan(chan_name)?.instr_list().clone())
+        }
+        for (idx, func) in fns.into_iter().enumerate() {
+            if let Err(err) = self.chan_mut(chan_names[idx])?.add_instr(func, t, dur_spec) {
+                for (&chan_name, snapshot) in chan_names.iter().zip(snapshots).take(idx) {
+                    *self.chan_mut(chan_name)?.instr_list_mut() = snapshot
+                }
+                return Err(err.in_dev(dev_name))
+            }
+        }
+        Ok(())
+    }
+
+    /// Optional settings of the device: trigger and clock metadata, tick rounding and closing edge policies,
+    /// the trigger delay, and dead times - see [`DevSettings`].
+    ///
+    /// A device opts into all of them at once by storing a [`DevSettings`] and returning it here. With the default `None`
+    /// every setting reads as in [`DevSettings::default`] and the methods changing one (e.g. [`BaseDev::set_trigger_delay`])
+    /// return [`StreamerError::Incompatible`]. The channels opt in separately, see [`BaseChan::settings`].
+    fn settings(&self) -> Option<&DevSettings> {
+        None
+    }
+    fn settings_mut(&mut self) -> Option<&mut DevSettings> {
+        None
+    }
+    /// [`BaseDev::settings_mut`] to change `setting`, [`StreamerError::Incompatible`] if the device has no settings
+    fn settings_for(&mut self, setting: &str) -> Result<&mut DevSettings, StreamerError> {
+        let name = self.name();
+        self.settings_mut().ok_or_else(|| StreamerError::Incompatible { ctx: ErrCtx::dev(name), msg: format!("does not support {setting}") })
+    }
+
+    /// Trigger and clock metadata - see [`crate::sync`]. `None` if not configured.
+    fn sync_spec(&self) -> Option<&SyncSpec> {
+        self.settings().and_then(|settings| settings.sync_spec.as_ref())
+    }
+    /// Sets the trigger and clock metadata of the device. Consistency across devices is checked by [`BaseStreamer::check_sync`].
+    ///
+    /// Returns [`StreamerError::Incompatible`] if the device has no [`BaseDev::settings`].
+    ///
+    /// [`BaseStreamer::check_sync`]: crate::streamer::BaseStreamer::check_sync
+    fn set_sync_spec(&mut self, spec: SyncSpec) -> Result<(), StreamerError> {
+        if spec.trig_dev.as_deref() == Some(self.name().as_str()) || spec.ref_clk.as_ref().is_some_and(|ref_clk| ref_clk.freq.is_nan() || ref_clk.freq <= 0.0) {
+            return Err(StreamerError::InvalidArgument {
+                ctx: ErrCtx::dev(self.name()),
+                msg: format!("invalid sync spec {spec}: a device can't trigger itself and clock frequency must be positive"),
+            })
         }
+        self.settings_for("trigger and clock metadata")?.sync_spec = Some(spec);
+        Ok(())
+    }
 
+    /// Time to tick conversion policy of the device - see [`crate::rounding`]
+    fn tick_rounding(&self) -> TickRounding {
+        self.settings().map(|settings| settings.tick_rounding).unwrap_or_default()
+    }
+    /// Sets the time to tick conversion policy of the device (`add_reset_instr`, `compile`) and all its channels.
+    ///
+    /// Returns [`StreamerError::Incompatible`] without changing anything if the device or any of its channels has no settings.
+    fn set_tick_rounding(&mut self, rounding: TickRounding) -> Result<(), StreamerError> {
+        if self.settings().is_none() || self.chans().iter().any(|chan| chan.settings().is_none()) {
+            return Err(StreamerError::Incompatible {
+                ctx: ErrCtx::dev(self.name()),
+                msg: "does not support tick rounding policies on the device and all its channels".to_string(),
+            })
+        }
+        let dev_name = self.name();
         for chan in self.chans_mut() {
-            chan.add_reset_instr(reset_pos)?
+            chan.set_tick_rounding(rounding).map_err(|err| err.in_dev(dev_name.clone()))?
+        }
+        if let Some(settings) = self.settings_mut() {
+            settings.tick_rounding = rounding
+        }
+        Ok(())
+    }
+    /// Clock tick of time `t` (in seconds) according to [`BaseDev::tick_rounding`], checked like [`BaseChan::time_to_pos`]
+    fn time_to_pos(&self, t: f64) -> Result<usize, StreamerError> {
+        let rounding = self.tick_rounding();
+        rounding.to_pos(t, self.samp_rate())
+            .map_err(|err| err.to_streamer_err(ErrCtx::dev(self.name()), t, self.samp_rate(), rounding))
+    }
+    /// Signed number of clock ticks of the time difference `dt`, checked like [`BaseChan::time_to_ticks`]
+    fn time_to_ticks(&self, dt: f64) -> Result<i64, StreamerError> {
+        let rounding = self.tick_rounding();
+        rounding.to_ticks(dt, self.samp_rate())
+            .map_err(|err| err.to_streamer_err(ErrCtx::dev(self.name()), dt, self.samp_rate(), rounding))
+    }
+
+    /// Number of samples (clock ticks times active channels) compiling to `stop_time` would produce - the closing edge
+    /// samples of the [`BaseDev::closing_edge`] policy included, the trigger delay excluded. `None` if it overflows `usize`.
+    fn samp_count(&self, stop_time: f64) -> Result<Option<usize>, StreamerError> {
+        let stop_tick = self.time_to_pos(stop_time)?;
+        // Below the last instruction end `compile` fails anyway - there is no closing edge to account for
+        let closing_edge_ticks = match self.last_instr_end_pos() {
+            Some(last_end_pos) if stop_tick < last_end_pos => 0,
+            _ => self.closing_edge_ticks(stop_tick),
         };
+        let stop_pos = stop_tick + closing_edge_ticks;
+        Ok(stop_pos.saturating_sub(self.trigger_delay_pos()).checked_mul(self.active_chans().len()))
+    }
+
+    /// Delay (in seconds) between the start trigger and the first generated sample of this device
+    fn trigger_delay(&self) -> f64 {
+        self.settings().map_or(0.0, |settings| settings.trigger_delay)
+    }
+    /// Trigger delay rounded to clock ticks
+    fn trigger_delay_pos(&self) -> usize {
+        (self.trigger_delay() * self.samp_rate()).round() as usize
+    }
+    /// Sets the start trigger delay of the device. Instructions keep their nominal times and the compiler
+    /// advances this device's waveforms by `delay`, so pulses entered at the same time on different devices
+    /// come out simultaneously. Instructions must not start before `delay`.
+    ///
+    /// Returns [`StreamerError::InvalidArgument`] if `delay` is negative or not finite and
+    /// [`StreamerError::Incompatible`] if the device has no [`BaseDev::settings`].
+    fn set_trigger_delay(&mut self, delay: f64) -> Result<(), StreamerError> {
+        if !delay.is_finite() || delay < 0.0 {
+            return Err(StreamerError::InvalidArgument {
+                ctx: ErrCtx::dev(self.name()),
+                msg: format!("trigger delay must be finite and non-negative, got {delay}"),
+            })
+        }
+        self.settings_for("trigger delay compensation")?.trigger_delay = delay;
+        self.clear_compile_cache();
         Ok(())
     }
 
+    /// Requires at least `min_dead_time` seconds between one of the channels `chan_a` / `chan_b` going low
+    /// and the other going high (see [`crate::dead_time`]). Checked on every compile.
+    ///
+    /// Returns [`StreamerError::Incompatible`] if the device has no [`BaseDev::settings`] or its channels don't have `bool` samples.
+    fn add_dead_time(&mut self, chan_a: &str, chan_b: &str, min_dead_time: f64) -> Result<(), StreamerError> {
+        if TypeId::of::<<Self::Chan as BaseChan>::Samp>() != TypeId::of::<bool>() || self.settings().is_none() {
+            return Err(StreamerError::Incompatible {
+                ctx: ErrCtx::dev(self.name()),
+                msg: "dead times are only supported between digital channels".to_string(),
+            })
+        }
+        if chan_a == chan_b || min_dead_time.is_nan() || min_dead_time < 0.0 {
+            return Err(StreamerError::InvalidArgument {
+                ctx: ErrCtx::dev(self.name()),
+                msg: format!(
+                    "dead time needs two different channels and a non-negative time, got {chan_a}, {chan_b}, {min_dead_time}"
+                ),
+            })
+        }
+        self.chan(chan_a)?;
+        self.chan(chan_b)?;
+        if let Some(settings) = self.settings_mut() {
+            settings.dead_times.push(DeadTime::new(chan_a, chan_b, min_dead_time))
+        }
+        Ok(())
+    }
+    /// Checks the compiled channels against all registered dead times. Called at the end of every compile.
+    ///
+    /// Returns [`StreamerError::RuleViolation`] listing every offending rising edge.
+    fn check_dead_times(&self) -> Result<(), StreamerError> {
+        let Some(dead_times) = self.settings().map(|settings| &settings.dead_times).filter(|dead_times| !dead_times.is_empty()) else {
+            return Ok(())
+        };
+        let high_intervals = |chan_name: &str| -> Result<Vec<(usize, usize)>, StreamerError> {
+            let chan = self.chan(chan_name)?;
+            if !chan.got_instructions() {
+                return Ok(Vec::new())
+            }
+            chan.marker_intervals(&MarkerRule::Threshold(0.0)).map_err(|err| err.in_dev(self.name()))
+        };
+        let mut msgs = Vec::new();
+        let mut first_chan = None;
+        for dead_time in dead_times {
+            let min_ticks = dead_time.min_ticks(self.samp_rate());
+            for violation in dead_time.violations(&high_intervals(&dead_time.chan_a)?, &high_intervals(&dead_time.chan_b)?, min_ticks) {
+                first_chan.get_or_insert_with(|| violation.high_chan.clone());
+                msgs.push(format!("\t{violation} (required {min_ticks} ticks)"))
+            }
+        }
+        match first_chan {
+            None => Ok(()),
+            Some(chan) => Err(StreamerError::RuleViolation {
+                ctx: ErrCtx { dev: Some(self.name()), chan: Some(chan) },
+                msg: format!("dead time violations:\n{}", msgs.join("\n")),
+            }),
+        }
+    }
+
     /// A device is marked edited if any of its editable channels are edited.
     /// Also see [`BaseChannel::is_edited`]
     fn got_instructions(&self) -> bool {
@@ -175,6 +747,7 @@ pub trait BaseDev {
         for chan in self.chans_mut() {
             chan.clear_edit_cache()
         }
+        self.diagnostics_mut().clear();
         self.clear_compile_cache();
     }
     /// Clears the compile-cache fields for all channels.
@@ -183,6 +756,7 @@ pub trait BaseDev {
         for chan in self.chans_mut() {
             chan.clear_compile_cache()
         }
+        self.diagnostics_mut().clear_stage(DiagnosticStage::Compile);
     }
 
     fn clear_compile_cache(&mut self) {
@@ -204,6 +778,23 @@ pub trait BaseDev {
             })
     }
 
+    /// Closing edge policy - see [`crate::closing_edge`]
+    fn closing_edge(&self) -> ClosingEdge {
+        self.settings().map(|settings| settings.closing_edge).unwrap_or_default()
+    }
+    /// Sets the closing edge policy of the device, see [`crate::closing_edge`].
+    ///
+    /// Returns [`StreamerError::Incompatible`] if the device has no [`BaseDev::settings`].
+    fn set_closing_edge(&mut self, policy: ClosingEdge) -> Result<(), StreamerError> {
+        self.settings_for("configuring the closing edge policy")?.closing_edge = policy;
+        self.clear_compile_cache();
+        Ok(())
+    }
+    /// Number of extra samples `compile` appends after `stop_tick` according to [`BaseDev::closing_edge`]
+    fn closing_edge_ticks(&self, stop_tick: usize) -> usize {
+        self.closing_edge().extra_ticks(self.is_closing_edge_clipped(stop_tick))
+    }
+
     /// Compiles all editable channels to produce a continuous instruction stream.
     ///
     /// The method starts by compiling each individual editable channel to obtain a continuous
@@ -223,22 +814,29 @@ pub trait BaseDev {
     ///
     /// # Arguments
     /// - `stop_time`: The stop time used to compile the channels.
-    fn compile_base(&mut self, stop_time: f64) -> Result<(), String> {
+    fn compile_base(&mut self, stop_time: f64) -> Result<(), StreamerError> {
         if !self.got_instructions() {
             // @Backend developers: whenever iterating over devices, you should always
             // filter by `got_instructions()` to only interact with active devices.
-            return Err(format!("Device {} did not get any instructions", self.name()))
+            return Err(StreamerError::NoInstructions {
+                ctx: ErrCtx::dev(self.name()),
+                msg: "did not get any instructions".to_string(),
+            })
         }
-        let stop_tick = (stop_time * self.samp_rate()).round() as usize;
+        let stop_tick = self.time_to_pos(stop_time)?;
         if stop_tick < self.last_instr_end_pos().unwrap() {
-            return Err(format!(
-                "[Device {}] requested stop_time {stop_time} was rounded to {stop_tick} clock cycles \
-                which is below the last instruction end_pos {}",
-                self.name(), self.last_instr_end_pos().unwrap()
-            ))
+            return Err(StreamerError::OutOfRange {
+                ctx: ErrCtx::dev(self.name()),
+                msg: format!(
+                    "requested stop_time {stop_time} was rounded to {stop_tick} clock cycles \
+                    which is below the last instruction end_pos {}",
+                    self.last_instr_end_pos().unwrap()
+                ),
+            })
         }
 
-        // If on any of the channels, the last instruction has `end_spec = Some(end_pos, ...)`
+        // With the default `ClosingEdge::Auto` policy (see `crate::closing_edge`):
+        // if on any of the channels, the last instruction has `end_spec = Some(end_pos, ...)`
         // and requested `stop_tick` precisely matches `end_pos`,
         // we ask the card to generate an additional sample at the end to ensure this "closing edge" is reliably formed.
         //
@@ -251,26 +849,164 @@ pub trait BaseDev {
         // we explicitly ask the card to run for one more clock cycle longer and generate the extra sample at the end.
         // Channel's `compile()` logic will fill this sample with the last instruction's after-end padding
         // thus reliably forming its' "closing edge".
-        let stop_pos = if self.is_closing_edge_clipped(stop_tick) {
-            stop_tick + 1
-        } else {
-            stop_tick
+        self.diagnostics_mut().clear_stage(DiagnosticStage::Compile);
+        let policy = self.closing_edge();
+        let clipped = self.is_closing_edge_clipped(stop_tick);
+        let closing_edge_ticks = policy.extra_ticks(clipped);
+        let decision = match (clipped, closing_edge_ticks) {
+            (true, 0) => Some((Severity::Warning, format!(
+                "stop_tick {stop_tick} clips the closing edge of a last instruction - extra sample not added (closing edge policy {policy}), \
+                the level after the end is hardware-dependent"
+            ))),
+            (true, _) => Some((Severity::Info, format!(
+                "stop_tick {stop_tick} clips the closing edge of a last instruction - added {closing_edge_ticks} extra sample (closing edge policy {policy})"
+            ))),
+            (false, 0) => None,
+            (false, _) => Some((Severity::Info, format!("added {closing_edge_ticks} extra sample after stop_tick {stop_tick} (closing edge policy {policy})"))),
         };
+        if let Some((severity, message)) = decision {
+            self.diagnostics_mut().record(DiagnosticKind::ClosingEdge, DiagnosticStage::Compile, severity, None, Some(stop_tick), message);
+        }
+        let stop_pos = stop_tick + closing_edge_ticks;
+
+        // Trigger delay: channels are compiled in device clock ticks, advanced by `delay_pos` relative to the edit cache
+        let delay_pos = self.trigger_delay_pos();
+        if delay_pos >= stop_pos {
+            return Err(StreamerError::OutOfRange {
+                ctx: ErrCtx::dev(self.name()),
+                msg: format!("trigger delay of {delay_pos} clock cycles is not below the stop position {stop_pos}"),
+            })
+        }
+        let dev_name = self.name();
+        for chan in self.chans_mut() {
+            match chan.settings_mut() {
+                Some(settings) => settings.compile_offset = delay_pos,
+                None if delay_pos > 0 => return Err(StreamerError::Incompatible {
+                    ctx: ErrCtx { dev: Some(dev_name.clone()), chan: Some(chan.name()) },
+                    msg: "does not support trigger delay compensation".to_string(),
+                }),
+                None => {},
+            }
+        }
+        let stop_pos = stop_pos - delay_pos;
 
         // Compile all active channels
+        #[cfg(not(feature = "parallel"))]
         for chan in self.active_chans_mut() {
-            chan.compile(stop_pos)?
+            chan.compile(stop_pos).map_err(|err| err.in_dev(dev_name.clone()))?
         };
+        // Channels are independent, so with the `parallel` feature they are compiled on the rayon thread pool.
+        // Results are collected in channel order, so the reported error does not depend on scheduling.
+        #[cfg(feature = "parallel")]
+        {
+            let results: Vec<Result<(), StreamerError>> = self
+                .active_chans_mut()
+                .into_par_iter()
+                .map(|chan| chan.compile(stop_pos))
+                .collect();
+            for res in results {
+                res.map_err(|err| err.in_dev(dev_name.clone()))?
+            }
+        }
+
+        self.check_dead_times()?;
+        self.post_compile_hook().map_err(|err| {
+            self.clear_compile_cache();
+            err.in_dev(dev_name)
+        })
+    }
 
+    /// Called at the end of every successful compile, after all active channels were compiled (and ran their own
+    /// [`BaseChan::post_compile_hook`]) and passed the dead time checks. Override it for device-wide transforms or
+    /// checks of the compile caches, e.g. hardware segment limits. The default does nothing.
+    ///
+    /// If the hook returns `Err`, the compile cache of the device is cleared and `compile` fails.
+    fn post_compile_hook(&mut self) -> Result<(), StreamerError> {
         Ok(())
     }
 
-    fn compile(&mut self, stop_time: f64) -> Result<(), String> {
+    fn compile(&mut self, stop_time: f64) -> Result<(), StreamerError> {
         self.compile_base(stop_time)
     }
 
+    /// Runs `compile()` followed by the safety checks enabled in `opts`.
+    /// If a check fails, the compile cache is cleared so the rejected waveform can't be streamed.
+    fn compile_with(&mut self, stop_time: f64, opts: &CompileOptions) -> Result<(), StreamerError> {
+        self.compile(stop_time)?;
+        self.check_compiled(opts).inspect_err(|_| self.clear_compile_cache())
+    }
+
+    /// Segment splitting and safety checks of `compile_with()` on the fresh compile cache
+    fn check_compiled(&mut self, opts: &CompileOptions) -> Result<(), StreamerError> {
+        if let Some(max_seg_samps) = opts.max_seg_samps {
+            self.split_segments(max_seg_samps)?;
+        }
+        if opts.strict {
+            if let Some(warning) = self.collect_diagnostics().iter().find(|entry| entry.severity == Severity::Warning) {
+                return Err(StreamerError::StrictViolation {
+                    ctx: ErrCtx { dev: warning.dev.clone(), chan: warning.chan.clone() },
+                    msg: format!("Strict compile mode does not allow warnings, got: {warning}"),
+                })
+            }
+        }
+        if opts.check_limits {
+            self.check_limits()?;
+        }
+        if opts.check_nan {
+            self.check_finite(opts.nan_samps_per_seg)?;
+        }
+        Ok(())
+    }
+
+    /// [`BaseChan::split_segments`] for all active channels. Called by `compile_with()` if `max_seg_samps` is set.
+    fn split_segments(&mut self, max_seg_samps: usize) -> Result<(), StreamerError> {
+        let dev_name = self.name();
+        for chan in self.active_chans_mut() {
+            chan.split_segments(max_seg_samps).map_err(|err| err.in_dev(dev_name.clone()))?
+        }
+        Ok(())
+    }
+
+    /// Cuts the compiled sequence at `spec.t_abort` and ramps all active channels down to their reset values,
+    /// see [`crate::abort`]. The device stops one tick after the end of its longest ramp.
+    fn compile_abort(&mut self, spec: &AbortSpec) -> Result<(), StreamerError> {
+        let compiled_stop_pos = self.try_compiled_stop_pos()?;
+        // `t_abort` is nominal - the compile cache runs ahead by the trigger delay
+        let abort_pos = self.time_to_pos(spec.t_abort)?.saturating_sub(self.trigger_delay_pos());
+        if spec.t_abort < 0.0 || abort_pos > compiled_stop_pos {
+            return Err(StreamerError::OutOfRange {
+                ctx: ErrCtx::dev(self.name()),
+                msg: format!(
+                    "t_abort {} was rounded to {abort_pos} clock cycles which is outside the compiled sequence [0, {compiled_stop_pos}]",
+                    spec.t_abort
+                ),
+            })
+        }
+        let dev_name = self.name();
+        let ramp_ticks: Vec<usize> = self.active_chans()
+            .iter()
+            .map(|chan| chan.time_to_pos(spec.ramp_dur(&dev_name, &chan.name())).map_err(|err| err.in_dev(dev_name.clone())))
+            .collect::<Result<_, _>>()?;
+        let stop_pos = abort_pos + ramp_ticks.iter().copied().max().unwrap_or(0) + 1;
+        for (chan, ramp_ticks) in self.active_chans_mut().into_iter().zip(ramp_ticks) {
+            chan.compile_abort(abort_pos, ramp_ticks, stop_pos).map_err(|err| err.in_dev(dev_name.clone()))?
+        }
+        Ok(())
+    }
+
+    /// Checks compiled waveforms against hardware limits. Called by `compile_with()` if `check_limits` is enabled.
+    ///
+    /// The base implementation only checks the value ranges declared by the channels themselves ([`BaseChan::check_range`]) -
+    /// hardware crates override it with the actual output range of the card.
+    fn check_limits(&self) -> Result<(), StreamerError> {
+        for chan in self.active_chans() {
+            chan.check_range(None).map_err(|err| err.in_dev(self.name()))?
+        }
+        Ok(())
+    }
+
     /// Base of `validate_compile_cache()`
-    fn validate_compile_cache_base(&self) -> Result<(), String> {
+    fn validate_compile_cache_base(&self) -> Result<(), StreamerError> {
         // 3 checks:
         // - this device is active in the first place;
         // - each active channels passes `validate_compile_cache()` test (meaning it is "fresh compiled" - compile cache matches current edit cache);
@@ -279,90 +1015,235 @@ pub trait BaseDev {
         if !self.got_instructions() {
             // @Backend developers: whenever iterating over devices, you should always
             // filter by `got_instructions()` to only interact with active devices.
-            return Err(format!("Device {} does not have any instructions and is not active", self.name()))
+            return Err(StreamerError::NoInstructions {
+                ctx: ErrCtx::dev(self.name()),
+                msg: "does not have any instructions and is not active".to_string(),
+            })
         }
 
         let failed_chan_msgs: Vec<String> = self
             .active_chans()
             .iter()
-            .filter_map(|chan| chan.validate_compile_cache().err())
+            .filter_map(|chan| chan.validate_compile_cache().err().map(|err| err.to_string()))
             .collect();
         if !failed_chan_msgs.is_empty() {
             let mut full_err_msg = String::new();
             for msg in failed_chan_msgs {
                 full_err_msg.push_str(&format!("{msg}\n"))
             };
-            return Err(format!("[{}] The following channels failed compile cache validation:\n{full_err_msg}", self.name()))
+            return Err(StreamerError::NotCompiled {
+                ctx: ErrCtx::dev(self.name()),
+                msg: format!("the following channels failed compile cache validation:\n{full_err_msg}"),
+            })
         }
 
         let compiled_stop_positions: IndexMap<String, usize> = self
             .active_chans()
             .iter()
-            .map(|chan| (chan.name(), chan.compiled_stop_pos()))
-            .collect();
+            .map(|chan| Ok((chan.name(), chan.try_compiled_stop_pos()?)))
+            .collect::<Result<_, StreamerError>>()
+            .map_err(|err| err.in_dev(self.name()))?;
         if !compiled_stop_positions.values().all_equal() {
-            return Err(format!("[{}] Channels have different compiled stop positions: \n{compiled_stop_positions:?}", self.name()))
+            return Err(StreamerError::NotCompiled {
+                ctx: ErrCtx::dev(self.name()),
+                msg: format!("channels have different compiled stop positions: \n{compiled_stop_positions:?}"),
+            })
         }
 
         Ok(())
     }
 
     /// Ensures that compile cache is fresh (matches current edit cache) and is self-consistent
-    fn validate_compile_cache(&self) -> Result<(), String> {
+    fn validate_compile_cache(&self) -> Result<(), StreamerError> {
         self.validate_compile_cache_base()
     }
 
+    /// Collision report of planned instructions `batch` (channel name -> instructions), see [`BaseChan::collision_report`].
+    ///
+    /// Returns [`StreamerError::NotFound`] if `batch` refers to an unknown channel.
+    fn collision_report(&self, batch: &IndexMap<String, Vec<InstrSnapshot>>) -> Result<CollisionReport, StreamerError> {
+        let mut report = CollisionReport::default();
+        for (chan_name, instrs) in batch {
+            report.extend(self.chan(chan_name)?.collision_report(instrs))
+        }
+        Ok(report.in_dev(&self.name()))
+    }
+
+    /// Detailed state of the compile cache of all active channels - see [`crate::validation`].
+    fn validation_report(&self) -> DevReport {
+        DevReport {
+            name: self.name(),
+            got_instructions: self.got_instructions(),
+            chans: self.active_chans().iter().map(|chan| chan.validation_report()).collect(),
+        }
+    }
+
+    /// Human-readable summary of the device and its active channels - see [`crate::summary`].
+    fn summary(&self) -> DevSummary {
+        DevSummary {
+            name: self.name(),
+            samp_rate: self.samp_rate(),
+            samp_bytes: std::mem::size_of::<<Self::Chan as BaseChan>::Samp>(),
+            n_chans: self.chans().len(),
+            chans: self.active_chans().into_iter().map(|chan| ChanSummary {
+                name: chan.name(),
+                n_instrs: chan.instr_list().len(),
+                first_instr_time: chan.first_instr_start_time(),
+                last_instr_end_time: chan.last_instr_end_time(),
+            }).collect(),
+            compiled_stop_pos: self.try_compiled_stop_pos().ok(),
+        }
+    }
+
+    /// Data rate and totals the device will stream according to the compile cache - see [`crate::budget`].
+    ///
+    /// Returns `Err` if the device is inactive or the compile cache is stale.
+    fn stream_budget(&self) -> Result<DevBudget, StreamerError> {
+        Ok(DevBudget {
+            name: self.name(),
+            samp_rate: self.samp_rate(),
+            samp_bytes: std::mem::size_of::<<Self::Chan as BaseChan>::Samp>(),
+            n_chans: self.active_chans().len(),
+            stop_pos: self.try_compiled_stop_pos()?,
+        })
+    }
+
+    /// Budget compiling to `stop_time` would produce, worked out from the edit cache without compiling - see [`crate::budget`].
+    ///
+    /// Returns the errors `compile` would give for a device without instructions, a `stop_time` below the last instruction end,
+    /// or a trigger delay not below the stop position.
+    fn predicted_budget(&self, stop_time: f64) -> Result<DevPrediction, StreamerError> {
+        let Some(last_end_pos) = self.last_instr_end_pos() else {
+            return Err(StreamerError::NoInstructions {
+                ctx: ErrCtx::dev(self.name()),
+                msg: "did not get any instructions".to_string(),
+            })
+        };
+        let stop_tick = self.time_to_pos(stop_time)?;
+        if stop_tick < last_end_pos {
+            return Err(StreamerError::OutOfRange {
+                ctx: ErrCtx::dev(self.name()),
+                msg: format!(
+                    "requested stop_time {stop_time} was rounded to {stop_tick} clock cycles \
+                    which is below the last instruction end_pos {last_end_pos}"
+                ),
+            })
+        }
+        let closing_edge_ticks = self.closing_edge_ticks(stop_tick);
+        let delay_ticks = self.trigger_delay_pos();
+        let Some(stop_pos) = (stop_tick + closing_edge_ticks).checked_sub(delay_ticks).filter(|&stop_pos| stop_pos > 0) else {
+            return Err(StreamerError::OutOfRange {
+                ctx: ErrCtx::dev(self.name()),
+                msg: format!("trigger delay of {delay_ticks} clock cycles is not below the stop position {}", stop_tick + closing_edge_ticks),
+            })
+        };
+        Ok(DevPrediction {
+            budget: DevBudget {
+                name: self.name(),
+                samp_rate: self.samp_rate(),
+                samp_bytes: std::mem::size_of::<<Self::Chan as BaseChan>::Samp>(),
+                n_chans: self.active_chans().len(),
+                stop_pos,
+            },
+            stop_tick,
+            closing_edge_ticks,
+            delay_ticks,
+        })
+    }
+
+    /// Read-only snapshot of the device and all its channels - see [`crate::inspect`].
+    fn info(&self) -> DevInfo {
+        let dev_name = self.name();
+        let chans = self.chans().into_iter().map(|chan| ChanInfo {
+            dev: dev_name.clone(),
+            name: chan.name(),
+            samp_rate: chan.samp_rate(),
+            dflt_val: samp_to_f64(&chan.dflt_val()),
+            quantity: chan.quantity().to_string(),
+            is_event_chan: chan.is_event_chan(),
+            is_enabled: chan.is_enabled(),
+            hold_last_val: chan.hold_last_val(),
+            got_instructions: chan.got_instructions(),
+            n_instrs: chan.instr_list().len(),
+            first_instr_time: chan.first_instr_start_time(),
+            last_instr_end_time: chan.last_instr_end_time(),
+            is_fresh_compiled: chan.is_fresh_compiled(),
+            compiled_stop_time: chan.try_compiled_stop_time().ok(),
+            compiled_segments: chan.compiled_segments().ok().filter(|segs| !segs.is_empty()),
+        }).collect();
+        DevInfo { name: dev_name, samp_rate: self.samp_rate(), got_instructions: self.got_instructions(), chans }
+    }
+
     /// Returns the total number of samples the card will generate according to the current compile cache.
-    fn compiled_stop_pos(&self) -> usize {
-        // Sanity checks:
+    ///
+    /// Returns `Err` if the device is inactive (didn't get any instructions) or if the compile cache is stale.
+    fn try_compiled_stop_pos(&self) -> Result<usize, StreamerError> {
         if !self.got_instructions() {
-            panic!(
-                "Device {} hasn't gotten any instructions yet and is currently inactive.\n\
-                \n\
-                @Backend developers: when iterating over devices, you should always filter by `got_instructions()` and skip inactive ones",
-                self.name()
-            )
-        }
-        if let Err(msg) = self.validate_compile_cache() {
-            panic!(
-                "{msg}\n\
-                \n\
-                @Backend developers: whenever accessing compile cache, you should first call `validate_compile_cache()` \
-                to ensure that compile cache is valid - up-to-date with the edit cache and has no inconsistencies. \n\
-                \n\
-                This function is meant to be the place to gracefully handle the Err variant if it occurs \
-                (typically due to users forgetting to re-compile after adding pulses). \n\
-                \n\
-                In contrast, other functions assume the cache is valid and rely on it. Some may still \
-                do a 'validate_compile_cache()' under the hood to catch bugs but they will panic on Err."
-            )
+            return Err(StreamerError::NoInstructions {
+                ctx: ErrCtx::dev(self.name()),
+                msg: "hasn't gotten any instructions yet and is currently inactive".to_string(),
+            })
         }
-
+        self.validate_compile_cache()?;
+        // All active channels share the same stop position - this was checked by `validate_compile_cache()`
         self.active_chans()
             .last()
             .unwrap()
-            .compiled_stop_pos()
+            .try_compiled_stop_pos()
+            .map_err(|err| err.in_dev(self.name()))
     }
 
-    /// Calculates the maximum stop time among all compiled channels.
-    ///
-    /// Iterates over all the compiled channels in the device, regardless of their streamability or
-    /// editability, and determines the maximum stop time.
-    /// See [`BaseChannel::total_run_time`] for more information.
+    /// Same as [`BaseDev::try_compiled_stop_pos`] but the result is multiplied by sample clock period.
+    fn try_compiled_stop_time(&self) -> Result<f64, StreamerError> {
+        Ok(self.try_compiled_stop_pos()? as f64 * self.clk_period())
+    }
+
+    /// Panicking version of [`BaseDev::try_compiled_stop_pos`].
     ///
-    /// # Returns
-    /// A `f64` representing the maximum stop time (in seconds) across all compiled channels.
+    /// Only meant for places where a valid compile cache is an invariant which was already checked.
+    fn compiled_stop_pos(&self) -> usize {
+        self.try_compiled_stop_pos().unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Panicking version of [`BaseDev::try_compiled_stop_time`].
     fn compiled_stop_time(&self) -> f64 {
         self.compiled_stop_pos() as f64 * self.clk_period()
     }
 
+    /// Stable fingerprint of the compile caches of all active channels, see [`BaseChan::content_hash`].
+    ///
+    /// Channels are combined in the order of their names, so the result does not depend on channel registration order.
+    fn content_hash(&self) -> Result<u64, StreamerError> {
+        self.validate_compile_cache()?;
+
+        let mut chan_hashes = Vec::new();
+        for chan in self.active_chans() {
+            chan_hashes.push((chan.name(), chan.content_hash().map_err(|err| err.in_dev(self.name()))?))
+        }
+        chan_hashes.sort_by(|(name_a, _), (name_b, _)| name_a.cmp(name_b));
+
+        let mut hasher = StableHasher::new();
+        hasher.update_f64(self.samp_rate());
+        hasher.update_usize(chan_hashes.len());
+        for (name, chan_hash) in chan_hashes {
+            hasher.update_str(&name);
+            hasher.update_u64(chan_hash);
+        }
+        Ok(hasher.finish())
+    }
+
+    fn first_instr_start_pos(&self) -> Option<usize> {
+        self.chans()
+            .iter()
+            .filter_map(|chan| chan.first_instr_start_pos())
+            .min()
+    }
+
     fn last_instr_end_pos(&self) -> Option<usize> {
         self.chans()
             .iter()
             .filter_map(|chan| chan.last_instr_end_pos())
-            .reduce(
-                |largest_so_far, this| std::cmp::max(largest_so_far, this)
-            )
+            .reduce(std::cmp::max)
     }
 
     /// Calculates the maximum stop time among all editable channels and optionally adds an extra tick duration.
@@ -380,6 +1261,158 @@ pub trait BaseDev {
         self.last_instr_end_pos().map(|end_pos| end_pos as f64 * self.clk_period())
     }
 
+    fn first_instr_start_time(&self) -> Option<f64> {
+        self.first_instr_start_pos().map(|start_pos| start_pos as f64 * self.clk_period())
+    }
+
+    /// `(first_instr_start_time, last_instr_end_time)` over all channels, `None` if the device is inactive
+    fn activity_window(&self) -> Option<(f64, f64)> {
+        self.first_instr_start_time().zip(self.last_instr_end_time())
+    }
+
+    /// Number of instructions of all channels, see [`BaseChan::instr_count`]
+    fn instr_count(&self) -> usize {
+        self.chans().iter().map(|chan| chan.instr_count()).sum()
+    }
+
+    /// Compile cache segment density (segments per second) of every active channel, see [`BaseChan::segment_density`]
+    fn segment_densities(&self) -> Result<IndexMap<String, f64>, StreamerError> {
+        self.validate_compile_cache()?;
+        self.active_chans()
+            .iter()
+            .map(|chan| Ok((chan.name(), chan.segment_density().map_err(|err| err.in_dev(self.name()))?)))
+            .collect()
+    }
+
+    /// Padding segments of all active channels, see [`BaseChan::padding_segs`]
+    fn padding_report(&self) -> Result<DevPadding, StreamerError> {
+        self.active_chans()
+            .iter()
+            .map(|chan| Ok((chan.name(), chan.padding_segs().map_err(|err| err.in_dev(self.name()))?)))
+            .collect()
+    }
+
+    /// Idle segments of all active channels and the intervals where all of them are idle, see [`crate::idle`]
+    fn idle_map(&self, min_samps: usize) -> Result<DevIdle, StreamerError> {
+        let stop_pos = self.try_compiled_stop_pos()?;
+        let mut chans = IndexMap::new();
+        let mut intervals = vec![(0, stop_pos)];
+        for chan in self.active_chans() {
+            let segs = chan.idle_segs(min_samps).map_err(|err| err.in_dev(self.name()))?;
+            let chan_intervals: Vec<(usize, usize)> = segs.iter().map(|seg| (seg.start_pos, seg.end_pos)).collect();
+            intervals = idle::intersect(&intervals, &chan_intervals);
+            chans.insert(chan.name(), segs);
+        }
+        intervals.retain(|(start, end)| end - start >= min_samps);
+        Ok(DevIdle { samp_rate: self.samp_rate(), stop_pos, chans, intervals })
+    }
+
+    /// Activity windows of all active channels, see [`BaseChan::activity_window`]
+    fn activity_windows(&self) -> ActivityWindows {
+        self.active_chans()
+            .iter()
+            .filter_map(|chan| chan.activity_window().map(|window| (chan.name(), window)))
+            .collect()
+    }
+
+    /// Checks that [`BaseDev::shift`] by `dt` would not move any instruction to negative time
+    fn check_can_shift(&self, dt: f64) -> Result<(), StreamerError> {
+        let shift_ticks = self.time_to_ticks(dt)?;
+        match self.first_instr_start_pos() {
+            Some(first_start) if first_start as i64 + shift_ticks < 0 => Err(StreamerError::OutOfRange {
+                ctx: ErrCtx::dev(self.name()),
+                msg: format!(
+                    "cannot shift by dt = {dt} s ({shift_ticks} clock ticks) since the first instruction \
+                    starts at start_pos = {first_start} and would be moved to negative time"
+                ),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Moves instructions of all channels by `dt` seconds. See [`BaseChan::shift`].
+    ///
+    /// Nothing is changed if any of the channels can't be shifted.
+    fn shift(&mut self, dt: f64) -> Result<(), StreamerError> {
+        self.check_can_shift(dt)?;
+        let dev_name = self.name();
+        for chan in self.chans_mut() {
+            chan.shift(dt).map_err(|err| err.in_dev(dev_name.clone()))?
+        }
+        Ok(())
+    }
+
+    /// Restricts instructions of all channels to the window `[t_start, t_end)` moved to start at `0`. See [`BaseChan::crop`].
+    fn crop(&mut self, t_start: f64, t_end: f64) -> Result<(), StreamerError> {
+        let dev_name = self.name();
+        for chan in self.chans_mut() {
+            chan.crop(t_start, t_end).map_err(|err| err.in_dev(dev_name.clone()))?
+        }
+        Ok(())
+    }
+
+    /// Inserts copies of all instructions of `other` into the channels with the same names (at the same times).
+    ///
+    /// All channels of `other` that got instructions must be present in this device, and both devices must
+    /// share the same sample rate. These requirements are checked before anything is inserted.
+    fn copy_instrs_from(&mut self, other: &Self) -> Result<(), StreamerError> {
+        self.copy_instrs_from_shifted(other, 0.0)
+    }
+
+    /// Same as [`BaseDev::copy_instrs_from`] but the copies are moved later by `dt` seconds
+    /// (converted to whole clock ticks with the device's [`TickRounding`] policy).
+    /// Functions are wrapped into [`TimeMap`] so the waveforms move together with the instructions, as in [`BaseChan::shift`].
+    fn copy_instrs_from_shifted(&mut self, other: &Self, dt: f64) -> Result<(), StreamerError> {
+        self.check_can_copy_from(other, dt)?;
+
+        let dev_name = self.name();
+        let shift_ticks = self.time_to_pos(dt)?;
+        for other_chan in other.active_chans() {
+            let clk_period = other_chan.clk_period();
+            let t_shift = shift_ticks as f64 * clk_period;
+            let chan = self.chan_mut(&other_chan.name())?;
+            let layer_instrs = other_chan.layer_instrs().into_iter().flat_map(|layers| layers.values().flatten());
+            for instr in other_chan.instr_list().iter().chain(layer_instrs) {
+                let t = (instr.start_pos() + shift_ticks) as f64 * clk_period;
+                let dur_spec = instr.end_spec().map(|(end_pos, keep_val)| {
+                    ((end_pos - instr.start_pos()) as f64 * clk_period, keep_val)
+                });
+                // Local-time instructions are copied in absolute time
+                let func = match (shift_ticks, instr.local_time()) {
+                    (0, false) => instr.func().clone_to_box(),
+                    _ => Box::new(TimeMap::shift(instr.abs_func(clk_period), t_shift)),
+                };
+                chan.add_instr_base(func, t, dur_spec, instr.layer(), instr.meta().cloned(), instr.phase_link(), false)
+                    .map_err(|err| err.in_dev(dev_name.clone()))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks of [`BaseDev::copy_instrs_from_shifted`] made before anything is copied: same sample rate, non-negative `dt`,
+    /// and every active channel of `other` present in this device
+    fn check_can_copy_from(&self, other: &Self, dt: f64) -> Result<(), StreamerError> {
+        if f64::abs(other.samp_rate() - self.samp_rate()) >= 1e-10 {
+            return Err(StreamerError::Incompatible {
+                ctx: ErrCtx::dev(self.name()),
+                msg: format!(
+                    "cannot copy instructions from a device with a different samp_rate={} (own samp_rate={})",
+                    other.samp_rate(), self.samp_rate()
+                ),
+            })
+        }
+        if dt < 0.0 {
+            return Err(StreamerError::OutOfRange {
+                ctx: ErrCtx::dev(self.name()),
+                msg: format!("cannot copy instructions to an earlier time, got dt = {dt}"),
+            })
+        }
+        for other_chan in other.active_chans() {
+            self.chan(&other_chan.name())?;
+        }
+        Ok(())
+    }
+
     /// Computes and returns the signal values for specified channels in a device.
     ///
     /// This method calculates the signal values by sampling float-point values from compiled instructions
@@ -402,7 +1435,15 @@ pub trait BaseDev {
     /// This method will panic if:
     /// - There are no channels that fulfill the provided requirements.
     /// - The device's task type is not AO (Analog Output) when initializing the buffer with time data.
-    fn calc_samps(&self, samp_buf: &mut [<Self::Chan as BaseChan>::Samp], start_pos: usize, end_pos: usize) -> Result<(), String> {
+    fn calc_samps(&self, samp_buf: &mut [<Self::Chan as BaseChan>::Samp], start_pos: usize, end_pos: usize) -> Result<(), StreamerError> {
+        let mut cursors = vec![ChanSampCursor::new(); self.active_chans().len()];
+        self.calc_samps_with(&mut cursors, samp_buf, start_pos, end_pos)
+    }
+
+    /// Same as [`BaseDev::calc_samps`] but resumes from `cursors` - one [`ChanSampCursor`] per active channel, in the
+    /// order of [`BaseDev::active_chans`]. Reusing the cursors across consecutive chunks of a streaming run avoids
+    /// the binary search over each channel's compile cache on every chunk.
+    fn calc_samps_with(&self, cursors: &mut [ChanSampCursor], samp_buf: &mut [<Self::Chan as BaseChan>::Samp], start_pos: usize, end_pos: usize) -> Result<(), StreamerError> {
         // Sanity checks
         //  Do not launch panics in this function since it is used during streaming runtime. Return `Result::Err` instead.
         /*      During streaming, there is an active connection to the hardware driver.
@@ -411,26 +1452,56 @@ pub trait BaseDev {
                 and thus fail to free-up hardware properly leading to unpredictable consequences like OS freezes.
         */
         if !self.got_instructions() {
-            return Err(format!("calc_samps(): device {} did not get any instructions", self.name()))
+            return Err(StreamerError::NoInstructions {
+                ctx: ErrCtx::dev(self.name()),
+                msg: "calc_samps(): did not get any instructions".to_string(),
+            })
         }
-        self.validate_compile_cache()?;
+        let compiled_stop_pos = self.try_compiled_stop_pos()?;
 
-        if !(end_pos >= start_pos + 1) {
-            return Err(format!("calc_samps(): requested start_pos={start_pos} and end_pos={end_pos} are invalid - end_pos must be no less than start_pos + 1"))
+        if end_pos <= start_pos {
+            return Err(StreamerError::InvalidArgument {
+                ctx: ErrCtx::dev(self.name()),
+                msg: format!("calc_samps(): requested start_pos={start_pos} and end_pos={end_pos} are invalid - end_pos must be no less than start_pos + 1"),
+            })
         }
 
-        if !(end_pos <= self.compiled_stop_pos()) {
-            return Err(format!("calc_samps(): requested end_pos={end_pos} exceeds the compiled stop position {}", self.compiled_stop_pos()))
+        if end_pos > compiled_stop_pos {
+            return Err(StreamerError::OutOfRange {
+                ctx: ErrCtx::dev(self.name()),
+                msg: format!("calc_samps(): requested end_pos={end_pos} exceeds the compiled stop position {compiled_stop_pos}"),
+            })
         }
 
         let n_chans = self.active_chans().len();
+        if cursors.len() != n_chans {
+            return Err(StreamerError::InvalidArgument {
+                ctx: ErrCtx::dev(self.name()),
+                msg: format!("calc_samps(): got {} cursors for {n_chans} active channels", cursors.len()),
+            })
+        }
         let n_samps = end_pos - start_pos;
         if n_chans * n_samps > samp_buf.len() {
-            return Err(format!(
-                "calc_samps(): provided samp_buf has insufficient size:\n\
-                \t n_chans*n_samps={} exceeds samp_buf.len()={}",
-                n_chans * n_samps, samp_buf.len()
-            ))
+            return Err(StreamerError::InvalidArgument {
+                ctx: ErrCtx::dev(self.name()),
+                msg: format!(
+                    "calc_samps(): provided samp_buf has insufficient size:\n\
+                    \t n_chans*n_samps={} exceeds samp_buf.len()={}",
+                    n_chans * n_samps, samp_buf.len()
+                ),
+            })
+        }
+
+        if self.use_tick_path(n_samps) {
+            // Every channel generates its times on the fly
+            for (chan_row_idx, (chan, cursor)) in self.active_chans().iter().zip(cursors.iter_mut()).enumerate() {
+                chan.fill_samps_from_ticks(
+                    cursor,
+                    start_pos,
+                    &mut samp_buf[chan_row_idx * n_samps .. (chan_row_idx + 1) * n_samps],
+                ).map_err(|err| err.in_dev(self.name()))?;
+            }
+            return Ok(())
         }
 
         let start_t = start_pos as f64 * self.clk_period();
@@ -438,154 +1509,666 @@ pub trait BaseDev {
         let t_arr = Array1::linspace(start_t, end_t, n_samps);
         let t_arr_slice = t_arr.as_slice().expect("[BaseDev::calc_samps()] BUG: t_arr.as_slice() returned None");
 
-        for (chan_row_idx, chan) in self.active_chans().iter().enumerate() {
-            chan.fill_samps(
+        for (chan_row_idx, (chan, cursor)) in self.active_chans().iter().zip(cursors.iter_mut()).enumerate() {
+            chan.fill_samps_with(
+                cursor,
                 start_pos,
                 &mut samp_buf[chan_row_idx * n_samps .. (chan_row_idx + 1) * n_samps],
-                &t_arr_slice
-            )?;
+                t_arr_slice
+            ).map_err(|err| err.in_dev(self.name()))?;
+        }
+        Ok(())
+    }
+
+    /// Whether `calc_samps` should skip the shared `t_arr` and let every channel generate times on the fly
+    /// ([`BaseChan::fill_samps_from_ticks`]) for a chunk of `n_samps` samples.
+    ///
+    /// A shared `t_arr` is computed once and reused by all channels, but once it no longer fits in cache
+    /// every channel re-reads it from memory. The default switches to the tick path at 32k samples (256 KiB of `f64`).
+    fn use_tick_path(&self, n_samps: usize) -> bool {
+        n_samps >= 1 << 15
+    }
+
+    /// Same as [`BaseDev::calc_samps`] but additionally scans the produced chunk
+    /// and returns [`StreamerError::NonFinite`] on the first NaN or ±Inf sample.
+    fn calc_samps_checked(&self, samp_buf: &mut [<Self::Chan as BaseChan>::Samp], start_pos: usize, end_pos: usize) -> Result<(), StreamerError> {
+        self.calc_samps(samp_buf, start_pos, end_pos)?;
+        self.check_samps_finite(samp_buf, start_pos, end_pos)
+    }
+
+    /// Scans the chunk `[start_pos, end_pos)` produced by [`BaseDev::calc_samps`] into `samp_buf`
+    /// and returns [`StreamerError::NonFinite`] on the first NaN or ±Inf sample.
+    fn check_samps_finite(&self, samp_buf: &[<Self::Chan as BaseChan>::Samp], start_pos: usize, end_pos: usize) -> Result<(), StreamerError> {
+        let n_samps = end_pos - start_pos;
+        for (chan_row_idx, chan) in self.active_chans().iter().enumerate() {
+            let row = &samp_buf[chan_row_idx * n_samps .. (chan_row_idx + 1) * n_samps];
+            for (offs, samp) in row.iter().enumerate() {
+                // Non-numeric samples have nothing to check
+                let Some(val) = samp.as_f64() else { break };
+                if !val.is_finite() {
+                    return Err(chan.non_finite_err(start_pos + offs, val).in_dev(self.name()))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Same as [`BaseDev::calc_samps`] but writes into externally owned memory given as a raw pointer and element count -
+    /// e.g. a DMA ring buffer or a shared-memory segment mapped by the driver. Saves the copy from an intermediate `samp_buf`.
+    ///
+    /// Null and misaligned pointers are rejected with [`StreamerError::InvalidArgument`], as is `len` smaller than
+    /// `n_chans * (end_pos - start_pos)` (the same check as for a slice).
+    ///
+    /// # Safety
+    /// `ptr` must be valid for reads and writes of `len` consecutive, initialized elements
+    /// and no other reference to this memory may be used for the duration of the call.
+    unsafe fn calc_samps_into_raw(&self, ptr: *mut <Self::Chan as BaseChan>::Samp, len: usize, start_pos: usize, end_pos: usize) -> Result<(), StreamerError> {
+        let mut cursors = vec![ChanSampCursor::new(); self.active_chans().len()];
+        self.calc_samps_into_raw_with(&mut cursors, ptr, len, start_pos, end_pos)
+    }
+
+    /// [`BaseDev::calc_samps_into_raw`] resuming from `cursors` - see [`BaseDev::calc_samps_with`].
+    ///
+    /// # Safety
+    /// Same as for [`BaseDev::calc_samps_into_raw`].
+    unsafe fn calc_samps_into_raw_with(
+        &self,
+        cursors: &mut [ChanSampCursor],
+        ptr: *mut <Self::Chan as BaseChan>::Samp,
+        len: usize,
+        start_pos: usize,
+        end_pos: usize
+    ) -> Result<(), StreamerError> {
+        if ptr.is_null() || !ptr.is_aligned() {
+            return Err(StreamerError::InvalidArgument {
+                ctx: ErrCtx::dev(self.name()),
+                msg: format!("calc_samps_into_raw(): samp_buf pointer {ptr:?} is null or not aligned for the sample type"),
+            })
+        }
+        // SAFETY: pointer is non-null and aligned, validity for `len` elements and exclusive access are guaranteed by the caller
+        let samp_buf = unsafe { std::slice::from_raw_parts_mut(ptr, len) };
+        self.calc_samps_with(cursors, samp_buf, start_pos, end_pos)
+    }
+
+    /// Run-length form of the window `[start_pos, end_pos)` for every active channel, in the order of [`BaseDev::active_chans`].
+    ///
+    /// Alternative to [`BaseDev::calc_samps`] for backends consuming edge lists - see [`BaseChan::compiled_runs`].
+    /// Fails with [`StreamerError::Incompatible`] if any channel is not piecewise-constant within the window.
+    fn calc_runs(&self, start_pos: usize, end_pos: usize) -> Result<Vec<Runs<<Self::Chan as BaseChan>::Samp>>, StreamerError> {
+        self.validate_compile_cache()?;
+        self.active_chans()
+            .iter()
+            .map(|chan| chan.compiled_runs(start_pos, end_pos).map_err(|err| err.in_dev(self.name())))
+            .collect()
+    }
+
+    /// Runs [`BaseChan::check_finite`] on all active channels.
+    fn check_finite(&self, max_samps_per_seg: Option<usize>) -> Result<(), StreamerError> {
+        for chan in self.active_chans() {
+            chan.check_finite(max_samps_per_seg).map_err(|err| err.in_dev(self.name()))?
         }
         Ok(())
     }
+
+    /// [`BaseChan::plot_data`] of all active channels with samples converted to `f64` - channel name -> `(t_arr, samps)`
+    fn plot_data(&self, n_samps: usize, start_time: Option<f64>, end_time: Option<f64>) -> Result<DevPlotData, StreamerError> {
+        let mut data = IndexMap::new();
+        for chan in self.active_chans() {
+            let (t_arr, samps) = chan.plot_data(n_samps, start_time, end_time).map_err(|err| err.in_dev(self.name()))?;
+            data.insert(chan.name(), (t_arr, samps.iter().map(samp_to_f64).collect()));
+        }
+        Ok(data)
+    }
+
+    /// Streams the full compiled sequence of all active channels into a fresh [`MockStreamTarget`]
+    /// in chunks of `chunk_samps` samples (the last chunk may be shorter) using [`BaseDev::calc_samps_with`].
+    ///
+    /// This is the software-only equivalent of the hardware streaming loop and is meant for end-to-end testing.
+    fn run_mock(&self, chunk_samps: usize) -> Result<MockStreamTarget<<Self::Chan as BaseChan>::Samp>, StreamerError> {
+        self.run_mock_masked(chunk_samps, &[])
+    }
+
+    /// Same as [`BaseDev::run_mock`] but the active channels listed in `masked` stream their default value
+    /// instead of the compiled waveform - see [`crate::selection`].
+    fn run_mock_masked(&self, chunk_samps: usize, masked: &[String]) -> Result<MockStreamTarget<<Self::Chan as BaseChan>::Samp>, StreamerError> {
+        if chunk_samps == 0 {
+            return Err(StreamerError::InvalidArgument {
+                ctx: ErrCtx::dev(self.name()),
+                msg: "run_mock(): chunk_samps must be positive".to_string(),
+            })
+        }
+        if !self.got_instructions() {
+            return Err(StreamerError::NoInstructions {
+                ctx: ErrCtx::dev(self.name()),
+                msg: "run_mock(): did not get any instructions".to_string(),
+            })
+        }
+        let stop_pos = self.try_compiled_stop_pos()?;
+
+        let active_chans = self.active_chans();
+        let chan_names: Vec<String> = active_chans.iter().map(|chan| chan.name()).collect();
+        let mut target = MockStreamTarget::new(&self.name(), chan_names);
+
+        let n_chans = active_chans.len();
+        let mut samp_buf = vec![active_chans[0].dflt_val(); n_chans * chunk_samps];
+        let mut cursors = vec![ChanSampCursor::new(); n_chans];
+
+        let mut start_pos = 0;
+        while start_pos < stop_pos {
+            let end_pos = std::cmp::min(start_pos + chunk_samps, stop_pos);
+            let n_samps = end_pos - start_pos;
+            let buf_len = n_chans * n_samps;
+            self.calc_samps_with(&mut cursors, &mut samp_buf[..buf_len], start_pos, end_pos)?;
+            // Channel-major buffer: one row of `n_samps` per active channel
+            for (row, chan) in samp_buf[..buf_len].chunks_mut(n_samps).zip(active_chans.iter()) {
+                if masked.contains(&chan.name()) {
+                    row.fill(chan.dflt_val())
+                }
+            }
+            target.consume(start_pos, end_pos, &samp_buf[..buf_len])?;
+            start_pos = end_pos;
+        }
+        Ok(target)
+    }
+
+    /// Calculates the full compiled sequence of all active channels in chunks of `chunk_samps` samples like
+    /// [`BaseDev::run_mock`] does, but discards the samples and times every chunk instead - see [`crate::dry_run`].
+    ///
+    /// Chunks are checked for NaN/±Inf. The first failing chunk ends the run and its error is returned in the report,
+    /// `Err` is only returned if the run can't be started.
+    fn dry_run(&self, chunk_samps: usize) -> Result<DevDryRun, StreamerError> {
+        if chunk_samps == 0 {
+            return Err(StreamerError::InvalidArgument {
+                ctx: ErrCtx::dev(self.name()),
+                msg: "dry_run(): chunk_samps must be positive".to_string(),
+            })
+        }
+        if !self.got_instructions() {
+            return Err(StreamerError::NoInstructions {
+                ctx: ErrCtx::dev(self.name()),
+                msg: "dry_run(): did not get any instructions".to_string(),
+            })
+        }
+        let stop_pos = self.try_compiled_stop_pos()?;
+
+        let active_chans = self.active_chans();
+        let n_chans = active_chans.len();
+        let mut samp_buf = vec![active_chans[0].dflt_val(); n_chans * chunk_samps];
+        let mut cursors = vec![ChanSampCursor::new(); n_chans];
+        let mut report = DevDryRun {
+            name: self.name(),
+            samp_rate: self.samp_rate(),
+            chunk_samps,
+            stop_pos,
+            n_chunks: 0,
+            total_time: Duration::ZERO,
+            max_chunk_time: Duration::ZERO,
+            slowest_chunk_pos: 0,
+            error: None,
+        };
+
+        let mut start_pos = 0;
+        while start_pos < stop_pos {
+            let end_pos = std::cmp::min(start_pos + chunk_samps, stop_pos);
+            let buf_len = n_chans * (end_pos - start_pos);
+            let start = Instant::now();
+            let res = self.calc_samps_with(&mut cursors, &mut samp_buf[..buf_len], start_pos, end_pos)
+                .and_then(|()| self.check_samps_finite(&samp_buf[..buf_len], start_pos, end_pos));
+            let chunk_time = start.elapsed();
+            if let Err(err) = res {
+                report.error = Some((start_pos, err));
+                break
+            }
+            report.n_chunks += 1;
+            report.total_time += chunk_time;
+            if chunk_time > report.max_chunk_time {
+                report.max_chunk_time = chunk_time;
+                report.slowest_chunk_pos = start_pos;
+            }
+            start_pos = end_pos;
+        }
+        Ok(report)
+    }
 }
 
 #[cfg(test)]
 mod test {
+    use crate::channel::{BaseChan, ConstFn};
     use crate::device::*;
-    use crate::instruction::*;
-
-    /*
-    pub struct Device {
-        chans: IndexMap<String, Channel>,
-        name: String,
-        samp_rate: f64,
-    }
-
-    impl Device {
-        /// Constructs a new `Device` instance.
-        ///
-        /// This constructor initializes a device with the provided parameters. The `channels` field
-        /// is initialized as an empty collection. All synchronization fields are initialized to `None`
-        /// by default. For nontrivial synchronization behavior, use the methods [`BaseDevice::cfg_samp_clk_src`],
-        /// [`BaseDevice::cfg_trig`], and [`BaseDevice::cfg_ref_clk`].
-        ///
-        /// # Arguments
-        /// - `name`: Name of the device as seen by the NI driver.
-        /// - `task_type`: The type of task associated with the device.
-        /// - `samp_rate`: Desired sampling rate in Hz.
-        ///
-        /// # Returns
-        /// A new instance of `Device` with the specified configurations and all synchronization-related fields set to `None`.
-        pub fn new(name: &str, task_type: TaskType, samp_rate: f64) -> Self {
-            Self {
-                channels: IndexMap::new(),
-
-                name: name.to_string(),
-                task_type,
-                samp_rate,
-
-                // ToDo: move this to NIStreamer crate:
-                start_trig_in: None,
-                start_trig_out: None,
-                samp_clk_in: None,
-                samp_clk_out: None,
-                ref_clk_in: None,
-                min_bufwrite_timeout: Some(5.0),
-            }
-        }
-    }
-    */
+    use crate::mock::test_impls::TestDev;
 
     #[test]
     fn last_instr_end_pos() {
-        let mut dev = Device::new("Dev1", TaskType::AO, 1e3);
-        dev.add_channel("ao0", 0.0);
-        dev.add_channel("ao1", 0.0);
-        let mock_func = Instruction::new_const(0.0);
+        let mut dev = TestDev::new("Dev1", 1e3);
+        dev.add_chan("ao0", 0.0);
+        dev.add_chan("ao1", 0.0);
+        let mock_func = ConstFn::new(0.0);
 
         // No instructions
-        assert_eq!(dev.last_instr_end_pos(), 0);
+        assert_eq!(dev.last_instr_end_pos(), None);
 
         // Instruction t=0..1 on ao0
-        dev.chan_("ao0").add_instr(mock_func.clone(),
+        dev.chan_mut("ao0").unwrap().add_instr(Box::new(mock_func.clone()),
             0.0, Some((1.0, false))
-        );
-        assert_eq!(dev.last_instr_end_pos(), 1000);
+        ).unwrap();
+        assert_eq!(dev.last_instr_end_pos(), Some(1000));
 
         // Instruction t=1..2 on ao1
-        dev.chan_("ao1").add_instr(mock_func.clone(),
+        dev.chan_mut("ao1").unwrap().add_instr(Box::new(mock_func.clone()),
             1.0, Some((1.0, false))
-        );
-        assert_eq!(dev.last_instr_end_pos(), 2000);
+        ).unwrap();
+        assert_eq!(dev.last_instr_end_pos(), Some(2000));
 
         // "Go-something" instruction on ao1 at t=2
-        dev.chan_("ao1").add_instr(mock_func.clone(),
+        dev.chan_mut("ao1").unwrap().add_instr(Box::new(mock_func.clone()),
             2.0, None
-        );
-        assert_eq!(dev.last_instr_end_pos(), 2001);
+        ).unwrap();
+        assert_eq!(dev.last_instr_end_pos(), Some(2001));
 
         dev.clear_edit_cache();
-        assert_eq!(dev.last_instr_end_pos(), 0);
+        assert_eq!(dev.last_instr_end_pos(), None);
     }
 
     #[test]
-    fn check_end_clipped() {
-        let mut dev = Device::new("Dev1", TaskType::AO, 1.0);
-        dev.add_channel("ao0", 0.0);
-        let mock_func = Instruction::new_const(0.0);
+    fn is_closing_edge_clipped() {
+        let mut dev = TestDev::new("Dev1", 1.0);
+        dev.add_chan("ao0", 0.0);
+        let mock_func = ConstFn::new(0.0);
 
         // (1) No instructions
-        assert_eq!(dev.check_end_clipped(0), false);
+        assert!(!dev.is_closing_edge_clipped(0));
 
         // (2) Finite duration instruction t = 0..1s:
         //      start_pos = 0
         //      end_pos = 1
-        dev.chan_("ao0").add_instr(mock_func.clone(),
+        dev.chan_mut("ao0").unwrap().add_instr(Box::new(mock_func.clone()),
             0.0, Some((1.0, false))
-        );
-        assert_eq!(dev.chan("ao0").last_instr_end_pos(), 1);
-        assert_eq!(dev.check_end_clipped(2), false);
-        assert_eq!(dev.check_end_clipped(1), true);
+        ).unwrap();
+        assert_eq!(dev.chan("ao0").unwrap().last_instr_end_pos(), Some(1));
+        assert!(!dev.is_closing_edge_clipped(2));
+        assert!(dev.is_closing_edge_clipped(1));
         dev.clear_edit_cache();
 
         // (3) "Go-something" instruction at t = 0s:
         //      start_pos = 0
         //      eff_end_pos = 1
-        dev.chan_("ao0").add_instr(mock_func.clone(),
+        dev.chan_mut("ao0").unwrap().add_instr(Box::new(mock_func.clone()),
             0.0, None
-        );
-        assert_eq!(dev.chan("ao0").last_instr_end_pos(), 1);
+        ).unwrap();
+        assert_eq!(dev.chan("ao0").unwrap().last_instr_end_pos(), Some(1));
         //  A "go-something" instruction is not meant to have the "closing" edge
         //  so setting `stop_tick` to precisely `eff_end_pos` is not considered clipping
-        assert_eq!(dev.check_end_clipped(1), false);
+        assert!(!dev.is_closing_edge_clipped(1));
     }
 
     #[test]
     fn compile() {
-        let mut dev = Device::new("Dev1", TaskType::AO, 1e3);
-        dev.add_channel("ao0", 0.0);
-        dev.add_channel("ao1", 0.0);
-        let mock_func = Instruction::new_const(0.0);
+        let mut dev = TestDev::new("Dev1", 1e3);
+        dev.add_chan("ao0", 0.0);
+        dev.add_chan("ao1", 0.0);
+        let mock_func = ConstFn::new(0.0);
 
         // Not compiled yet
-        assert_eq!(dev.total_samps(), 0);
+        assert!(dev.compile(1.0).is_err());
 
         // Add some instructions on both channels
-        dev.chan_("ao0").add_instr(mock_func.clone(),
+        dev.chan_mut("ao0").unwrap().add_instr(Box::new(mock_func.clone()),
             0.0, Some((1.0, false))
-        );
-        dev.chan_("ao1").add_instr(mock_func.clone(),
+        ).unwrap();
+        dev.chan_mut("ao1").unwrap().add_instr(Box::new(mock_func.clone()),
             1.0, Some((1.0, false))
-        );
-        assert_eq!(dev.last_instr_end_pos(), 2000);
+        ).unwrap();
+        assert_eq!(dev.last_instr_end_pos(), Some(2000));
 
         // Compile without clipping of the "closing edge" - no extra sample should be added
-        dev.compile(3.0);
-        assert_eq!(dev.total_samps(), 3000);
+        dev.compile(3.0).unwrap();
+        assert_eq!(dev.compiled_stop_pos(), 3000);
 
         // Compile with stop_pos matching the end of a finite-duration instruction on "ao1" -
         //  an additional sample should be added to form the "closing edge"
-        dev.compile(2.0);
-        assert_eq!(dev.total_samps(), 2001);
+        dev.compile(2.0).unwrap();
+        assert_eq!(dev.compiled_stop_pos(), 2001);
+    }
+
+    #[test]
+    fn check_finite_with_trigger_delay() {
+        let mut dev = TestDev::new("Dev1", 1e3);
+        dev.add_chan("ao0", 0.0);
+        dev.set_trigger_delay(0.005).unwrap();
+        let chan = dev.chan_mut("ao0").unwrap();
+        chan.constant(1.0, 0.005, Some((0.005, false))).unwrap();
+        chan.constant(f64::NAN, 0.02, Some((0.01, false))).unwrap();
+        dev.compile(0.04).unwrap();
+        // The compile cache is advanced by the delay - the error still points at the NaN instruction and its sequence time
+        let err = dev.check_finite(None).unwrap_err();
+        assert!(matches!(err, StreamerError::NonFinite { .. }));
+        assert!(err.msg().contains("(t = 0.02 s) produced by instruction"), "{err}");
+        assert!(!err.msg().contains("padding"), "{err}");
+    }
+
+    #[test]
+    fn check_finite_f32() {
+        // Any numeric sample type is checked, not only `f64`
+        let mut dev = TestDev::new("Dev1", 1e3);
+        dev.add_chan("ao0", 0.0f32);
+        dev.chan_mut("ao0").unwrap().constant(f32::NAN, 0.01, Some((0.01, false))).unwrap();
+        dev.compile(0.03).unwrap();
+        assert!(matches!(dev.check_finite(None), Err(StreamerError::NonFinite { .. })));
+    }
+
+    #[test]
+    fn all_off() {
+        let mut dev = TestDev::new("Dev1", 1e3);
+        dev.add_chan("ao0", 0.5);
+        dev.add_chan("ao1", -1.0);
+        dev.chan_mut("ao0").unwrap().constant(2.0, 0.0, None).unwrap();
+        dev.chan_mut("ao1").unwrap().constant(3.0, 0.0, Some((0.5, true))).unwrap();
+        dev.all_off(0.5).unwrap();
+        dev.chan_mut("ao0").unwrap().constant(4.0, 0.8, None).unwrap();
+        dev.compile(1.0).unwrap();
+
+        let mut samps = vec![0.0; 2000];
+        dev.calc_samps(&mut samps, 0, 1000).unwrap();
+        let (ao0, ao1) = samps.split_at(1000);
+        assert_eq!((ao0[499], ao0[500], ao0[799], ao0[800]), (2.0, 0.5, 0.5, 4.0));
+        assert_eq!((ao1[499], ao1[500], ao1[999]), (3.0, -1.0, -1.0));
+
+        // Ordinary instruction - collides with anything already covering `t`
+        assert!(matches!(dev.all_off(0.2), Err(StreamerError::Collision { .. })));
+    }
+
+    #[test]
+    fn presets() {
+        let mut dev = TestDev::new("Dev1", 1e3);
+        dev.add_chan("ao0", 0.0);
+        dev.add_chan("ao1", 0.0);
+        dev.add_chan("ao2", 0.0);
+        dev.chan_mut("ao0").unwrap().define_preset("standby", 1.0).unwrap();
+        dev.chan_mut("ao1").unwrap().define_preset("standby", -1.0).unwrap();
+        dev.chan_mut("ao1").unwrap().define_preset("cooling", 2.0).unwrap();
+        // Redefinition replaces the value
+        dev.chan_mut("ao0").unwrap().define_preset("standby", 0.5).unwrap();
+        assert_eq!(dev.chan("ao0").unwrap().preset("standby"), Some(0.5));
+
+        assert!(matches!(dev.chan_mut("ao0").unwrap().add_preset_instr("cooling", 0.0), Err(StreamerError::NotFound { .. })));
+        assert!(matches!(dev.add_preset_instr("warmup", 0.0), Err(StreamerError::NotFound { .. })));
+        dev.add_preset_instr("standby", 0.1).unwrap();
+        dev.chan_mut("ao1").unwrap().add_preset_instr("cooling", 0.2).unwrap();
+        dev.compile(0.3).unwrap();
+
+        let mut samps = vec![0.0; 900];
+        dev.calc_samps(&mut samps, 0, 300).unwrap();
+        let (ao0, rest) = samps.split_at(300);
+        let (ao1, ao2) = rest.split_at(300);
+        assert_eq!((ao0[99], ao0[100], ao0[299]), (0.0, 0.5, 0.5));
+        assert_eq!((ao1[100], ao1[199], ao1[200]), (-1.0, -1.0, 2.0));
+        // No "standby" preset on ao2 - untouched
+        assert!(ao2.iter().all(|&samp| samp == 0.0));
+    }
+
+    #[test]
+    fn iq_pair() {
+        use crate::fn_lib_tools::IqTone;
+
+        let mut dev = TestDev::new("Dev1", 1e3);
+        dev.add_chan("i", 0.0);
+        dev.add_chan("q", 0.0);
+        dev.add_iq_instr("i", "q", Box::new(IqTone::new(2.0, 50.0, 0.0)), 0.0, Some((0.1, false))).unwrap();
+
+        // Collision on Q - nothing is inserted on I either
+        dev.chan_mut("q").unwrap().constant(1.0, 0.2, Some((0.1, false))).unwrap();
+        let res = dev.add_iq_instr("i", "q", Box::new(IqTone::new(1.0, 50.0, 0.0)), 0.25, None);
+        assert!(matches!(res, Err(StreamerError::Collision { .. })));
+        assert_eq!(dev.chan("i").unwrap().instr_list().len(), 1);
+        // A 1-tick fix on I is undone together with its diagnostic
+        let i_spans = |dev: &TestDev<f64>| -> Vec<_> {
+            dev.chan("i").unwrap().instr_list().iter().map(|instr| (instr.start_pos(), instr.end_pos())).collect()
+        };
+        let i_before = i_spans(&dev);
+        let res = dev.add_iq_instr("i", "q", Box::new(IqTone::new(1.0, 50.0, 0.0)), 0.099, Some((0.15, false)));
+        assert!(matches!(res, Err(StreamerError::Collision { .. })));
+        assert_eq!(i_spans(&dev), i_before);
+        assert!(dev.chan("i").unwrap().diagnostics().entries().is_empty());
+        assert!(matches!(dev.add_iq_instr("i", "i", Box::new(IqTone::new(1.0, 50.0, 0.0)), 0.5, None), Err(StreamerError::InvalidArgument { .. })));
+        assert!(matches!(dev.add_iq_instr("i", "x", Box::new(IqTone::new(1.0, 50.0, 0.0)), 0.5, None), Err(StreamerError::NotFound { .. })));
+
+        dev.compile(0.3).unwrap();
+        let mut samps = vec![0.0; 600];
+        dev.calc_samps(&mut samps, 0, 300).unwrap();
+        let (i, q) = samps.split_at(300);
+        for pos in 0..100 {
+            let arg = 2.0 * std::f64::consts::PI * 50.0 * (pos as f64 * 1e-3);
+            assert!((i[pos] - 2.0 * arg.cos()).abs() < 1e-12);
+            assert!((q[pos] - 2.0 * arg.sin()).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn word_bus() {
+        use crate::fn_lib_tools::StdFnLib;
+
+        let fn_lib = StdFnLib::new();
+        let mut dev = TestDev::<u16>::new("Bus", 1e3);
+        dev.add_chan("profile", 0);
+        let chan = dev.chan_mut("profile").unwrap();
+        chan.add_instr(fn_lib.ConstWord(0x00ff).unwrap().inner, 0.0, Some((0.01, true))).unwrap();
+        chan.add_instr(fn_lib.WordSequence(vec![1, 2, 3], 0.02, 0.005).unwrap().inner, 0.02, Some((0.015, true))).unwrap();
+        dev.compile(0.04).unwrap();
+
+        let mut samps = vec![0; 40];
+        dev.calc_samps(&mut samps, 0, 40).unwrap();
+        let expected: Vec<u16> = (0..40).map(|pos| match pos {
+            0..20 => 0x00ff,
+            20..25 => 1,
+            25..30 => 2,
+            _ => 3,
+        }).collect();
+        assert_eq!(samps, expected);
+        assert_eq!(fn_lib.WordSequence(vec![7, 7], 0.0, 1.0).unwrap().inner.const_val(), Some(7));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn counter() {
+        let mut dev = TestDev::<bool>::new("Mux", 1e3);
+        for line in 0..3 {
+            dev.add_chan(&format!("port0/line{line}"), false);
+        }
+        let lines = ["port0/line0", "port0/line1", "port0/line2"];
+        let count_at = |dev: &TestDev<bool>, t: f64| {
+            lines.iter().enumerate().map(|(bit, line)| (dev.chan(line).unwrap().eval_point(t).unwrap() as u64) << bit).sum::<u64>()
+        };
+
+        // Binary count stepping every 10 ms from t = 10 ms, wrapping after 8 steps
+        dev.add_counter(&lines, 0.01, 0.1, 0.01, false, false).unwrap();
+        dev.compile(0.2).unwrap();
+        let counts: Vec<u64> = (0..12).map(|step| count_at(&dev, 0.0105 + 0.01 * step as f64)).collect();
+        assert_eq!(counts, [0, 1, 2, 3, 4, 5, 6, 7, 0, 1, 0, 0]);
+
+        // Gray code changes one line per step
+        dev.clear_edit_cache();
+        dev.add_counter(&lines, 0.0, 0.08, 0.01, true, true).unwrap();
+        dev.compile(0.1).unwrap();
+        let counts: Vec<u64> = (0..10).map(|step| count_at(&dev, 0.0005 + 0.01 * step as f64)).collect();
+        assert_eq!(counts, [0, 1, 3, 2, 6, 7, 5, 4, 4, 4]);
+
+        // A collision on any line leaves all lines untouched
+        dev.clear_edit_cache();
+        dev.chan_mut("port0/line2").unwrap().constant(true, 0.05, Some((0.01, false))).unwrap();
+        assert!(matches!(dev.add_counter(&lines, 0.0, 0.1, 0.01, false, false), Err(StreamerError::Collision { .. })));
+        assert!(dev.chan("port0/line0").unwrap().instr_list().is_empty() && dev.chan("port0/line1").unwrap().instr_list().is_empty());
+        assert_eq!(dev.chan("port0/line2").unwrap().instr_list().len(), 1);
+
+        assert!(matches!(dev.add_counter(&["port0/line0", "port0/line0"], 0.0, 0.1, 0.01, false, false), Err(StreamerError::InvalidArgument { .. })));
+        let mut ao_dev = TestDev::new("AO", 1e3);
+        ao_dev.add_chan("ao0", 0.0);
+        assert!(matches!(ao_dev.add_counter(&["ao0"], 0.0, 0.1, 0.01, false, false), Err(StreamerError::Incompatible { .. })));
+    }
+
+    #[test]
+    fn bit_pattern() {
+        use crate::fn_lib_tools::StdFnLib;
+
+        let fn_lib = StdFnLib::new();
+        let mut dev = TestDev::<bool>::new("DO", 1e3);
+        dev.add_chan("port0/line0", false);
+        // Word 0b1011 MSB first, 2 ms per bit, inside an instruction starting before the first bit
+        let word = fn_lib.BitPattern(0.012, 0.002, vec![true, false, true, true], true).unwrap();
+        dev.chan_mut("port0/line0").unwrap().add_instr(word.inner, 0.01, Some((0.012, false))).unwrap();
+        dev.compile(0.03).unwrap();
+
+        let mut samps = vec![false; 30];
+        dev.calc_samps(&mut samps, 0, 30).unwrap();
+        let expected: Vec<bool> = (0..30).map(|pos| match pos {
+            // idle and first bit, last two bits and idle until the instruction end
+            10..14 | 16..22 => true,
+            _ => false,
+        }).collect();
+        assert_eq!(samps, expected);
+        assert_eq!(fn_lib.BitPattern(0.0, 1.0, vec![true; 3], true).unwrap().inner.const_val(), Some(true));
+        assert_eq!(fn_lib.BitPattern(0.0, 1.0, vec![], false).unwrap().inner.const_val(), Some(false));
+        assert_eq!(fn_lib.BitPattern(0.0, 1.0, vec![true], false).unwrap().inner.const_val(), None);
+    }
+
+    #[test]
+    fn dds_chans() {
+        use crate::fn_lib_tools::StdFnLib;
+        use crate::options::CompileOptions;
+        use crate::quantity::Quantity;
+
+        let fn_lib = StdFnLib::new();
+        let opts = CompileOptions { check_limits: true, ..CompileOptions::preview() };
+        let mut dev = TestDev::new("DDS", 1e3);
+        dev.add_chan("freq", 10e6);
+        dev.add_chan("phase", 0.0);
+        dev.chan_mut("freq").unwrap().set_quantity(Quantity::Frequency, None);
+        dev.chan_mut("phase").unwrap().set_quantity(Quantity::Phase, Some((0.0, 2.0 * std::f64::consts::PI)));
+        assert_eq!(dev.chan("freq").unwrap().val_range(), Some((0.0, f64::INFINITY)));
+
+        dev.chan_mut("freq").unwrap().add_instr(fn_lib.FreqRamp(0.1, 0.1, 10e6, 20e6).unwrap().inner, 0.1, Some((0.1, true))).unwrap();
+        dev.chan_mut("phase").unwrap().add_instr(fn_lib.PhaseStep(0.15, 0.0, 1.0).unwrap().inner, 0.1, Some((0.1, true))).unwrap();
+        dev.compile_with(0.3, &opts).unwrap();
+        let mut samps = vec![0.0; 600];
+        dev.calc_samps(&mut samps, 0, 300).unwrap();
+        assert_eq!((samps[100], samps[150], samps[250]), (10e6, 15e6, 20e6));
+        assert_eq!((samps[300 + 149], samps[300 + 150]), (0.0, 1.0));
+
+        // Negative frequency and phase outside the declared range
+        dev.chan_mut("freq").unwrap().add_instr(fn_lib.FreqRamp(0.3, 0.1, 20e6, -1e6).unwrap().inner, 0.3, Some((0.1, true))).unwrap();
+        let err = dev.compile_with(0.5, &opts).unwrap_err();
+        assert!(matches!(err, StreamerError::OutOfRange { .. }));
+        assert!(err.to_string().contains("FreqRamp"));
+        dev.chan_mut("freq").unwrap().clear_edit_cache();
+        dev.chan_mut("phase").unwrap().add_instr(fn_lib.PhaseStep(0.3, 1.0, 7.0).unwrap().inner, 0.3, None).unwrap();
+        assert!(matches!(dev.compile_with(0.5, &opts), Err(StreamerError::OutOfRange { .. })));
+        // Not checked without `check_limits`
+        dev.compile(0.5).unwrap();
+    }
+
+    #[test]
+    fn try_compiled_stop_pos() {
+        let mut dev = TestDev::new("Dev1", 1e3);
+        dev.add_chan("ao0", 0.0);
+        assert!(matches!(dev.try_compiled_stop_pos(), Err(StreamerError::NoInstructions { .. })));
+
+        dev.chan_mut("ao0").unwrap().constant(1.0, 0.0, Some((1.0, false))).unwrap();
+        // Stale compile cache - an error instead of a panic
+        assert!(matches!(dev.try_compiled_stop_pos(), Err(StreamerError::NotCompiled { .. })));
+        assert!(dev.chan("ao0").unwrap().try_compiled_stop_time().is_err());
+
+        dev.compile(1.5).unwrap();
+        assert_eq!(dev.try_compiled_stop_pos(), Ok(1500));
+        assert_eq!(dev.chan("ao0").unwrap().try_compiled_stop_time(), Ok(1.5));
+    }
+
+    #[test]
+    fn calc_samps_into_raw() {
+        let mut dev = TestDev::new("Dev1", 1e3);
+        dev.add_chan("ao0", 0.0);
+        dev.add_chan("ao1", 0.0);
+        dev.chan_mut("ao0").unwrap().constant(1.0, 0.0, Some((0.002, false))).unwrap();
+        dev.chan_mut("ao1").unwrap().constant(2.0, 0.001, None).unwrap();
+        dev.compile(0.004).unwrap();
+
+        let mut expected = vec![0.0; 8];
+        dev.calc_samps(&mut expected, 0, 4).unwrap();
+        let mut samp_buf = vec![-1.0; 8];
+        unsafe { dev.calc_samps_into_raw(samp_buf.as_mut_ptr(), samp_buf.len(), 0, 4) }.unwrap();
+        assert_eq!(samp_buf, expected);
+
+        // Checks
+        let res = unsafe { dev.calc_samps_into_raw(std::ptr::null_mut(), 8, 0, 4) };
+        assert!(matches!(res, Err(StreamerError::InvalidArgument { .. })));
+        let misaligned = unsafe { samp_buf.as_mut_ptr().byte_add(1) };
+        let res = unsafe { dev.calc_samps_into_raw(misaligned, 4, 0, 2) };
+        assert!(matches!(res, Err(StreamerError::InvalidArgument { .. })));
+        let res = unsafe { dev.calc_samps_into_raw(samp_buf.as_mut_ptr(), 7, 0, 4) };
+        assert!(matches!(res, Err(StreamerError::InvalidArgument { .. })));
+    }
+
+    #[test]
+    fn calc_runs() {
+        use crate::channel::Run;
+        use crate::fn_lib_tools::StdFnLib;
+
+        let mut dev = TestDev::new("DO", 1e3);
+        dev.add_chan("line0", false);
+        dev.add_chan("line1", false);
+        dev.chan_mut("line0").unwrap().constant(true, 0.1, Some((0.1, false))).unwrap();
+        dev.chan_mut("line0").unwrap().constant(true, 0.2, Some((0.1, true))).unwrap();
+        dev.chan_mut("line1").unwrap().constant(true, 0.5, None).unwrap();
+        dev.compile(1.0).unwrap();
+        assert!(dev.chan("line0").unwrap().is_run_length());
+
+        let runs = dev.calc_runs(0, 1000).unwrap();
+        assert_eq!(runs[0], vec![
+            Run { start_pos: 0, end_pos: 100, val: false },
+            Run { start_pos: 100, end_pos: 1000, val: true },
+        ]);
+        assert_eq!(runs[1], vec![
+            Run { start_pos: 0, end_pos: 500, val: false },
+            Run { start_pos: 500, end_pos: 1000, val: true },
+        ]);
+        // Runs are clipped to the window
+        let runs = dev.calc_runs(150, 600).unwrap();
+        assert_eq!(runs[0], vec![Run { start_pos: 150, end_pos: 600, val: true }]);
+        assert!(matches!(dev.calc_runs(0, 1001), Err(StreamerError::OutOfRange { .. })));
+
+        // Non-constant functions have to be rasterized
+        let mut dev = TestDev::new("AO", 1e3);
+        dev.add_chan("ao0", 0.0);
+        let sine = StdFnLib::new().Sine(1.0, 1.0, 0.0, 0.0).unwrap().inner;
+        dev.chan_mut("ao0").unwrap().add_instr(sine, 0.5, Some((0.1, false))).unwrap();
+        dev.compile(1.0).unwrap();
+        assert!(!dev.chan("ao0").unwrap().is_run_length());
+        assert_eq!(dev.calc_runs(0, 500).unwrap()[0].len(), 1);
+        let err = dev.calc_runs(0, 1000).unwrap_err();
+        assert!(matches!(err, StreamerError::Incompatible { .. }));
+        assert_eq!(err.ctx().dev.as_deref(), Some("AO"));
+    }
+
+    #[test]
+    fn plot_data() {
+        let mut dev = TestDev::new("AO", 1e3);
+        dev.add_chan("ao0", 0.0);
+        dev.add_chan("ao1", 0.0);
+        dev.add_chan("ao2", 0.0);
+        dev.chan_mut("ao0").unwrap().constant(1.0, 0.0, Some((0.5, false))).unwrap();
+        dev.chan_mut("ao1").unwrap().constant(2.0, 0.5, None).unwrap();
+        dev.compile(1.0).unwrap();
+
+        let (t_arr, samps) = dev.chan("ao0").unwrap().plot_data(3, None, Some(0.9)).unwrap();
+        assert_eq!(t_arr, vec![0.0, 0.45, 0.9]);
+        assert_eq!(samps, vec![1.0, 1.0, 0.0]);
+        // End time defaults to the compiled stop time
+        let (t_arr, _samps) = dev.chan("ao0").unwrap().plot_data(2, Some(0.5), None).unwrap();
+        assert_eq!(t_arr, vec![0.5, 1.0]);
+
+        // Active channels only
+        let data = dev.plot_data(3, None, Some(0.9)).unwrap();
+        assert_eq!(data.keys().collect::<Vec<_>>(), vec!["ao0", "ao1"]);
+        assert_eq!(data["ao1"], (vec![0.0, 0.45, 0.9], vec![0.0, 0.0, 2.0]));
+        let err = dev.plot_data(3, None, Some(2.0)).unwrap_err();
+        assert!(matches!(err, StreamerError::OutOfRange { .. }));
+        assert_eq!(err.ctx().dev.as_deref(), Some("AO"));
+    }
+}
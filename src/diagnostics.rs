@@ -0,0 +1,179 @@
+//! Structured record of non-fatal decisions taken by the backend.
+//!
+//! Several steps silently adjust the user's intent: 1-tick collisions in `add_instr` are auto-fixed by trimming,
+//! `compile` inserts padding between instructions (summarized in one entry per channel), and devices add an extra
+//! sample to form a clipped closing edge. Each of these decisions is recorded as a [`Diagnostic`] entry
+//! in the [`Diagnostics`] sink of the channel or device which took it.
+//!
+//! Entries are collected across the whole streamer via [`BaseStreamer::diagnostics`].
+//! Compile-stage entries are dropped together with the compile cache, edit-stage entries - together with the edit cache.
+//!
+//! [`BaseStreamer::diagnostics`]: crate::streamer::BaseStreamer::diagnostics
+
+use std::fmt;
+use std::fmt::Display;
+use pyo3::prelude::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiagnosticKind {
+    /// A 1-tick collision between instructions was resolved by trimming or shifting the new instruction
+    OneTickFix,
+    /// `compile` inserted padding segments between instructions - one entry per channel with the gap count and total length
+    Padding,
+    /// Decision of the closing edge policy - an extra sample added at the end or a clipped closing edge left as is (see [`crate::closing_edge`])
+    ClosingEdge,
+    /// Finding of a registered validation rule (see [`crate::rules`])
+    Rule,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiagnosticStage {
+    Edit,
+    Compile,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Info,
+    Warning,
+    /// Fails the compile - only reported by validation rules
+    Error,
+}
+
+/// Single diagnostic entry
+#[pyclass]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub kind: DiagnosticKind,
+    pub stage: DiagnosticStage,
+    pub severity: Severity,
+    /// Device name. Filled in when entries are collected at the device level
+    pub dev: Option<String>,
+    /// Channel name. `None` for device-level decisions
+    pub chan: Option<String>,
+    /// Clock grid position the decision refers to
+    pub pos: Option<usize>,
+    pub message: String,
+}
+
+#[pymethods]
+impl Diagnostic {
+    #[getter]
+    fn kind(&self) -> String {
+        format!("{:?}", self.kind)
+    }
+    #[getter]
+    fn stage(&self) -> String {
+        format!("{:?}", self.stage)
+    }
+    #[getter]
+    fn severity(&self) -> String {
+        format!("{:?}", self.severity)
+    }
+    #[getter]
+    fn dev(&self) -> Option<String> {
+        self.dev.clone()
+    }
+    #[getter]
+    fn chan(&self) -> Option<String> {
+        self.chan.clone()
+    }
+    #[getter]
+    fn pos(&self) -> Option<usize> {
+        self.pos
+    }
+    #[getter]
+    fn message(&self) -> String {
+        self.message.clone()
+    }
+    fn __repr__(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let location = match (&self.dev, &self.chan) {
+            (Some(dev), Some(chan)) => format!("{dev}/{chan}"),
+            (Some(dev), None) => dev.clone(),
+            (None, Some(chan)) => chan.clone(),
+            (None, None) => "?".to_string(),
+        };
+        let pos = match self.pos {
+            Some(pos) => format!(" @ pos {pos}"),
+            None => String::new(),
+        };
+        write!(f, "[{:?}][{:?}] {location}{pos}: {}", self.severity, self.kind, self.message)
+    }
+}
+
+/// Diagnostics sink owned by every channel and device
+#[derive(Clone, Debug, Default)]
+pub struct Diagnostics {
+    entries: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn entries(&self) -> &Vec<Diagnostic> {
+        &self.entries
+    }
+    pub fn push(&mut self, entry: Diagnostic) {
+        self.entries.push(entry)
+    }
+    /// Shortcut to record a channel- or device-level entry
+    pub fn record(&mut self, kind: DiagnosticKind, stage: DiagnosticStage, severity: Severity, chan: Option<String>, pos: Option<usize>, message: String) {
+        self.push(Diagnostic { kind, stage, severity, dev: None, chan, pos, message })
+    }
+    /// Drops all entries recorded at the given stage
+    pub fn clear_stage(&mut self, stage: DiagnosticStage) {
+        self.entries.retain(|entry| entry.stage != stage)
+    }
+    pub fn clear(&mut self) {
+        self.entries.clear()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::channel::BaseChan;
+    use crate::device::BaseDev;
+    use crate::diagnostics::{DiagnosticKind, DiagnosticStage};
+    use crate::mock::test_impls::TestStreamer;
+    use crate::streamer::BaseStreamer;
+
+    #[test]
+    fn collect_and_clear() {
+        let mut streamer = TestStreamer::new();
+        streamer.add_ao_dev("AO", 100.0);
+        streamer.ao_devs["AO"].add_chan("ao0", 0.0);
+        let chan = streamer.ao_devs["AO"].chan_mut("ao0").unwrap();
+        chan.constant(1.0, 0.1, Some((0.2, false))).unwrap();
+        // Starts 1 tick before the previous instruction ends - auto-fixed by trimming
+        chan.constant(2.0, 0.29, Some((0.1, false))).unwrap();
+
+        let entries = streamer.diagnostics();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].kind, DiagnosticKind::OneTickFix);
+        assert_eq!(entries[0].dev.as_deref(), Some("AO"));
+        assert_eq!(entries[0].chan.as_deref(), Some("ao0"));
+
+        streamer.compile(Some(1.0)).unwrap();
+        let entries = streamer.diagnostics();
+        let padding: Vec<_> = entries.iter().filter(|entry| entry.kind == DiagnosticKind::Padding).collect();
+        assert_eq!(padding.len(), 1);
+        assert_eq!((padding[0].stage, padding[0].pos), (DiagnosticStage::Compile, Some(0)));
+        assert_eq!(padding[0].message, "2 padding gaps, 71 ticks in total (0 gaps holding the last value, 2 with the channel default)");
+        // Re-compiling does not accumulate duplicates
+        let n_entries = entries.len();
+        streamer.compile(Some(1.0)).unwrap();
+        assert_eq!(streamer.diagnostics().len(), n_entries);
+
+        streamer.clear_compile_cache();
+        assert!(streamer.diagnostics().iter().all(|entry| entry.stage == DiagnosticStage::Edit));
+        streamer.clear_edit_cache();
+        assert!(streamer.diagnostics().is_empty());
+    }
+}
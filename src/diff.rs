@@ -0,0 +1,361 @@
+//! Comparison of two streamers - "what changed between yesterday's sequence and today's?"
+//!
+//! See [`BaseStreamer::diff`] for the entry point. The comparison is done on the edit-cache level:
+//! devices and channels are matched by name and instruction lists are compared using type-agnostic
+//! [`InstrSnapshot`]s. Optionally, compiled waveforms of matching channels are sampled and compared
+//! with a tolerance.
+//!
+//! [`BaseStreamer::diff`]: crate::streamer::BaseStreamer::diff
+
+use std::cmp::Ordering;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::fmt::Display;
+use ndarray::Array1;
+use crate::instruction::{Instr, InstrMeta};
+use crate::streamer::TagBaseDev;
+use crate::error::StreamerError;
+
+/// Type-agnostic snapshot of an edit-cache instruction.
+///
+/// The function is captured by its [`Describe`](crate::fn_lib_tools::Describe) representation, so two instructions are considered
+/// to carry the same function if they print identically. Metadata is carried along for display
+/// but does not take part in the comparison.
+#[derive(Clone, Debug)]
+pub struct InstrSnapshot {
+    pub start_pos: usize,
+    pub end_spec: Option<(usize, bool)>,
+    pub func: String,
+    pub meta: Option<InstrMeta>,
+}
+impl PartialEq for InstrSnapshot {
+    fn eq(&self, other: &Self) -> bool {
+        self.start_pos == other.start_pos && self.end_spec == other.end_spec && self.func == other.func
+    }
+}
+impl<T> From<&Instr<T>> for InstrSnapshot {
+    fn from(instr: &Instr<T>) -> Self {
+        Self {
+            start_pos: instr.start_pos(),
+            end_spec: instr.end_spec(),
+            func: instr.func().describe(),
+            meta: instr.meta().cloned(),
+        }
+    }
+}
+impl Display for InstrSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let end_spec = match self.end_spec {
+            Some((end_pos, keep_val)) => format!("end_pos={end_pos}, keep_val={keep_val}"),
+            None => "no specified end".to_string(),
+        };
+        let meta = match &self.meta {
+            Some(meta) => format!(", {meta}"),
+            None => String::new(),
+        };
+        write!(f, "Instr(func={}, start_pos={}, {}{})", self.func, self.start_pos, end_spec, meta)
+    }
+}
+
+/// Single instruction-level change between the old and the new channel edit caches
+#[derive(Clone, Debug, PartialEq)]
+pub enum InstrChange {
+    Added(InstrSnapshot),
+    Removed(InstrSnapshot),
+    /// Same function, but different `start_pos` and/or `end_spec`
+    Retimed { old: InstrSnapshot, new: InstrSnapshot },
+}
+impl Display for InstrChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InstrChange::Added(instr) => write!(f, "+ {instr}"),
+            InstrChange::Removed(instr) => write!(f, "- {instr}"),
+            InstrChange::Retimed { old, new } => write!(f, "~ {old} -> {new}"),
+        }
+    }
+}
+
+/// Result of comparing sampled compiled waveforms of the same channel
+#[derive(Clone, Debug, PartialEq)]
+pub struct WaveformDiff {
+    /// Maximal absolute deviation between the two waveforms over the compared window
+    pub max_abs_dev: f64,
+    /// Time of the first sample where the deviation exceeds the tolerance (`None` if within tolerance everywhere)
+    pub first_violation_time: Option<f64>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChanDiff {
+    pub name: String,
+    pub instr_changes: Vec<InstrChange>,
+    /// Only present if sampled comparison was requested
+    pub waveform: Option<WaveformDiff>,
+}
+impl ChanDiff {
+    /// `true` if neither instructions nor (if compared) sampled waveforms differ beyond tolerance
+    pub fn is_empty(&self) -> bool {
+        self.instr_changes.is_empty()
+            && self.waveform.as_ref().is_none_or(|waveform| waveform.first_violation_time.is_none())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DevDiff {
+    pub name: String,
+    /// `Some((old, new))` if the sample rate has changed
+    pub samp_rate_change: Option<(f64, f64)>,
+    pub added_chans: Vec<String>,
+    pub removed_chans: Vec<String>,
+    /// Only channels which actually differ are listed
+    pub chan_diffs: Vec<ChanDiff>,
+}
+impl DevDiff {
+    pub fn is_empty(&self) -> bool {
+        self.samp_rate_change.is_none()
+            && self.added_chans.is_empty()
+            && self.removed_chans.is_empty()
+            && self.chan_diffs.is_empty()
+    }
+}
+
+/// Full comparison report produced by [`BaseStreamer::diff`]
+///
+/// [`BaseStreamer::diff`]: crate::streamer::BaseStreamer::diff
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct DiffReport {
+    pub added_devs: Vec<String>,
+    pub removed_devs: Vec<String>,
+    /// Only devices which actually differ are listed
+    pub dev_diffs: Vec<DevDiff>,
+}
+impl DiffReport {
+    pub fn is_empty(&self) -> bool {
+        self.added_devs.is_empty()
+            && self.removed_devs.is_empty()
+            && self.dev_diffs.is_empty()
+    }
+}
+impl Display for DiffReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "No differences")
+        }
+        for dev_name in self.added_devs.iter() {
+            writeln!(f, "+ device {dev_name}")?;
+        }
+        for dev_name in self.removed_devs.iter() {
+            writeln!(f, "- device {dev_name}")?;
+        }
+        for dev_diff in self.dev_diffs.iter() {
+            writeln!(f, "~ device {}", dev_diff.name)?;
+            if let Some((old, new)) = dev_diff.samp_rate_change {
+                writeln!(f, "\tsamp_rate: {old} -> {new}")?;
+            }
+            for chan_name in dev_diff.added_chans.iter() {
+                writeln!(f, "\t+ channel {chan_name}")?;
+            }
+            for chan_name in dev_diff.removed_chans.iter() {
+                writeln!(f, "\t- channel {chan_name}")?;
+            }
+            for chan_diff in dev_diff.chan_diffs.iter() {
+                writeln!(f, "\t~ channel {}", chan_diff.name)?;
+                for change in chan_diff.instr_changes.iter() {
+                    writeln!(f, "\t\t{change}")?;
+                }
+                if let Some(waveform) = &chan_diff.waveform {
+                    match waveform.first_violation_time {
+                        Some(t) => writeln!(f, "\t\twaveforms deviate by up to {} (first violation at t={t} s)", waveform.max_abs_dev)?,
+                        None => writeln!(f, "\t\twaveforms match within tolerance (max deviation {})", waveform.max_abs_dev)?,
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Compares two instruction lists (both sorted by `start_pos`, as channel edit caches are).
+///
+/// Identical instructions are matched first. Among the remaining ones, instructions with the same function
+/// are paired in order of appearance and reported as re-timed. Everything left over is reported as added or removed.
+pub fn diff_instr_lists(old: &[InstrSnapshot], new: &[InstrSnapshot]) -> Vec<InstrChange> {
+    // (1) Exact matches - a single merge-style pass over both lists
+    let (mut old_left, mut new_left) = (Vec::new(), Vec::new());
+    let (mut old_iter, mut new_iter) = (old.iter().peekable(), new.iter().peekable());
+    while let (Some(&old_instr), Some(&new_instr)) = (old_iter.peek(), new_iter.peek()) {
+        match old_instr.start_pos.cmp(&new_instr.start_pos) {
+            Ordering::Less => old_left.push(old_iter.next().unwrap()),
+            Ordering::Greater => new_left.push(new_iter.next().unwrap()),
+            Ordering::Equal => {
+                if old_instr != new_instr {
+                    old_left.push(old_instr);
+                    new_left.push(new_instr);
+                }
+                old_iter.next();
+                new_iter.next();
+            },
+        }
+    }
+    old_left.extend(old_iter);
+    new_left.extend(new_iter);
+
+    // (2) Same function, different timing - old leftovers queued by function
+    let mut old_by_func: HashMap<&str, VecDeque<usize>> = HashMap::new();
+    for (idx, old_instr) in old_left.iter().enumerate() {
+        old_by_func.entry(old_instr.func.as_str()).or_default().push_back(idx);
+    }
+    let mut old_paired = vec![false; old_left.len()];
+    let mut changes = Vec::new();
+    for &new_instr in new_left.iter() {
+        match old_by_func.get_mut(new_instr.func.as_str()).and_then(|idxs| idxs.pop_front()) {
+            Some(idx) => {
+                old_paired[idx] = true;
+                changes.push(InstrChange::Retimed {
+                    old: old_left[idx].clone(),
+                    new: new_instr.clone(),
+                });
+            },
+            // (3) Leftovers
+            None => changes.push(InstrChange::Added(new_instr.clone())),
+        }
+    }
+    changes.extend(old_left.into_iter().zip(old_paired).filter(|(_, paired)| !paired).map(|(instr, _)| InstrChange::Removed(instr.clone())));
+    changes.sort_by_key(|change| match change {
+        InstrChange::Added(instr) | InstrChange::Removed(instr) => instr.start_pos,
+        InstrChange::Retimed { old, new } => std::cmp::min(old.start_pos, new.start_pos),
+    });
+    changes
+}
+
+/// Compares two sampled waveforms point by point
+pub fn diff_waveforms(old: &[f64], new: &[f64], t_arr: &[f64], tol: f64) -> WaveformDiff {
+    let mut max_abs_dev: f64 = 0.0;
+    let mut first_violation_time = None;
+    for ((old_val, new_val), t) in old.iter().zip(new.iter()).zip(t_arr.iter()) {
+        let dev = (old_val - new_val).abs();
+        // NaN deviations are always treated as violations
+        if first_violation_time.is_none() && (dev > tol || dev.is_nan()) {
+            first_violation_time = Some(*t);
+        }
+        max_abs_dev = f64::max(max_abs_dev, dev);
+    }
+    WaveformDiff { max_abs_dev, first_violation_time }
+}
+
+/// Compares two devices with the same name. See [`BaseStreamer::diff`] for the meaning of `samp_cmp`.
+///
+/// [`BaseStreamer::diff`]: crate::streamer::BaseStreamer::diff
+pub fn diff_devs(old: &dyn TagBaseDev, new: &dyn TagBaseDev, samp_cmp: Option<(usize, f64)>) -> Result<DevDiff, StreamerError> {
+    let old_snapshots = old.tag_instr_snapshots();
+    let new_snapshots = new.tag_instr_snapshots();
+
+    let samp_rate_change = if f64::abs(old.tag_samp_rate() - new.tag_samp_rate()) >= 1e-10 {
+        Some((old.tag_samp_rate(), new.tag_samp_rate()))
+    } else {
+        None
+    };
+    let added_chans = new_snapshots.keys().filter(|name| !old_snapshots.contains_key(*name)).cloned().collect();
+    let removed_chans = old_snapshots.keys().filter(|name| !new_snapshots.contains_key(*name)).cloned().collect();
+
+    let mut chan_diffs = Vec::new();
+    for (chan_name, old_instrs) in old_snapshots.iter() {
+        let Some(new_instrs) = new_snapshots.get(chan_name) else { continue };
+
+        let waveform = match samp_cmp {
+            Some((n_samps, tol)) if !old_instrs.is_empty() && !new_instrs.is_empty() => {
+                let end_time = f64::min(old.tag_try_compiled_stop_time()?, new.tag_try_compiled_stop_time()?);
+                let t_arr = Array1::linspace(0.0, end_time, n_samps);
+                let old_samps = old.tag_calc_nsamps(chan_name, n_samps, Some(0.0), Some(end_time))?;
+                let new_samps = new.tag_calc_nsamps(chan_name, n_samps, Some(0.0), Some(end_time))?;
+                Some(diff_waveforms(&old_samps, &new_samps, t_arr.as_slice().unwrap(), tol))
+            },
+            _ => None,
+        };
+        let chan_diff = ChanDiff {
+            name: chan_name.clone(),
+            instr_changes: diff_instr_lists(old_instrs, new_instrs),
+            waveform,
+        };
+        if !chan_diff.is_empty() {
+            chan_diffs.push(chan_diff)
+        }
+    }
+
+    Ok(DevDiff {
+        name: old.tag_name(),
+        samp_rate_change,
+        added_chans,
+        removed_chans,
+        chan_diffs,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::diff::*;
+
+    fn snap(start_pos: usize, end_pos: usize, func: &str) -> InstrSnapshot {
+        InstrSnapshot { start_pos, end_spec: Some((end_pos, false)), func: func.to_string(), meta: None }
+    }
+
+    #[test]
+    fn instr_lists() {
+        let old = vec![snap(0, 10, "A"), snap(20, 30, "B"), snap(40, 50, "C")];
+        let new = vec![snap(0, 10, "A"), snap(25, 35, "B"), snap(60, 70, "D")];
+
+        let changes = diff_instr_lists(&old, &new);
+        assert_eq!(changes, vec![
+            InstrChange::Retimed { old: snap(20, 30, "B"), new: snap(25, 35, "B") },
+            InstrChange::Removed(snap(40, 50, "C")),
+            InstrChange::Added(snap(60, 70, "D")),
+        ]);
+        assert!(diff_instr_lists(&old, &old).is_empty());
+
+        // Replaced in place, and repeated functions paired in order of appearance
+        let old = vec![snap(0, 10, "A"), snap(20, 30, "B"), snap(40, 50, "B"), snap(60, 70, "C")];
+        let new = vec![snap(0, 10, "E"), snap(25, 35, "B"), snap(45, 55, "B"), snap(60, 70, "C")];
+        assert_eq!(diff_instr_lists(&old, &new), vec![
+            InstrChange::Added(snap(0, 10, "E")),
+            InstrChange::Removed(snap(0, 10, "A")),
+            InstrChange::Retimed { old: snap(20, 30, "B"), new: snap(25, 35, "B") },
+            InstrChange::Retimed { old: snap(40, 50, "B"), new: snap(45, 55, "B") },
+        ]);
+    }
+
+    #[test]
+    fn streamers() {
+        use crate::channel::BaseChan;
+        use crate::device::BaseDev;
+        use crate::mock::test_impls::TestStreamer;
+        use crate::streamer::BaseStreamer;
+
+        let build = |pulse_t: f64| {
+            let mut streamer = TestStreamer::new();
+            streamer.add_ao_dev("AO", 100.0);
+            streamer.ao_devs["AO"].add_chan("ao0", 0.0);
+            streamer.ao_devs["AO"].add_chan("ao1", 0.0);
+            streamer.ao_devs["AO"].chan_mut("ao0").unwrap().constant(1.0, pulse_t, Some((0.1, false))).unwrap();
+            streamer.ao_devs["AO"].chan_mut("ao1").unwrap().constant(2.0, 0.0, Some((0.1, false))).unwrap();
+            streamer.compile(Some(1.0)).unwrap();
+            streamer
+        };
+        let old = build(0.2);
+        let mut new = build(0.3);
+        new.add_do_dev("DO", 100.0);
+
+        let report = old.diff(&new, Some((101, 1e-6))).unwrap();
+        assert_eq!(report.added_devs, vec!["DO".to_string()]);
+        assert!(report.removed_devs.is_empty());
+        assert_eq!(report.dev_diffs.len(), 1);
+
+        let chan_diffs = &report.dev_diffs[0].chan_diffs;
+        assert_eq!(chan_diffs.len(), 1);
+        assert_eq!(chan_diffs[0].name, "ao0");
+        assert!(matches!(chan_diffs[0].instr_changes[..], [InstrChange::Retimed { .. }]));
+        let waveform = chan_diffs[0].waveform.as_ref().unwrap();
+        assert_eq!(waveform.max_abs_dev, 1.0);
+        assert!((waveform.first_violation_time.unwrap() - 0.2).abs() < 1e-9);
+
+        assert!(old.diff(&build(0.2), Some((101, 1e-6))).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,179 @@
+//! Software-only rehearsal of a streaming session.
+//!
+//! [`BaseStreamer::dry_run`] walks the whole compiled sequence of every streamed device through
+//! [`BaseDev::calc_samps_with`] chunk by chunk - exactly as the hardware streaming loop would - discarding the samples.
+//! Every chunk is timed and scanned for NaN/±Inf, so that sample calculation errors and chunks too slow to keep up
+//! with the sample clock show up before any hardware is involved.
+//!
+//! Runtime errors do not abort the dry run: the device stops at its first failing chunk and the error is kept
+//! in its [`DevDryRun`], while the other devices carry on. Use [`BaseStreamer::run_mock`] to keep the samples.
+//!
+//! [`BaseStreamer::dry_run`]: crate::streamer::BaseStreamer::dry_run
+//! [`BaseStreamer::run_mock`]: crate::streamer::BaseStreamer::run_mock
+//! [`BaseDev::calc_samps_with`]: crate::device::BaseDev::calc_samps_with
+
+use std::fmt;
+use std::fmt::Display;
+use std::time::Duration;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use crate::error::StreamerError;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DevDryRun {
+    pub name: String,
+    pub samp_rate: f64,
+    pub chunk_samps: usize,
+    /// Compiled stop position
+    pub stop_pos: usize,
+    /// Number of chunks calculated without error
+    pub n_chunks: usize,
+    /// Calculation time summed over all chunks
+    pub total_time: Duration,
+    pub max_chunk_time: Duration,
+    /// Start position of the slowest chunk
+    pub slowest_chunk_pos: usize,
+    /// Start position of the failed chunk and the error - the device is not run any further
+    pub error: Option<(usize, StreamerError)>,
+}
+
+impl DevDryRun {
+    /// Wall-clock time the hardware takes to play out one full chunk
+    pub fn chunk_period(&self) -> Duration {
+        Duration::from_secs_f64(self.chunk_samps as f64 / self.samp_rate)
+    }
+    pub fn mean_chunk_time(&self) -> Duration {
+        match self.n_chunks {
+            0 => Duration::ZERO,
+            n_chunks => self.total_time / n_chunks as u32,
+        }
+    }
+    /// Ratio of [`DevDryRun::chunk_period`] to the slowest chunk calculation.
+    /// Values below 1 mean that streaming would fall behind the sample clock.
+    pub fn headroom(&self) -> f64 {
+        self.chunk_period().as_secs_f64() / self.max_chunk_time.as_secs_f64()
+    }
+    pub fn is_ok(&self) -> bool {
+        self.error.is_none()
+    }
+
+    pub fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("samp_rate", self.samp_rate)?;
+        dict.set_item("chunk_samps", self.chunk_samps)?;
+        dict.set_item("n_chunks", self.n_chunks)?;
+        dict.set_item("total_s", self.total_time.as_secs_f64())?;
+        dict.set_item("mean_chunk_s", self.mean_chunk_time().as_secs_f64())?;
+        dict.set_item("max_chunk_s", self.max_chunk_time.as_secs_f64())?;
+        dict.set_item("slowest_chunk_pos", self.slowest_chunk_pos)?;
+        dict.set_item("headroom", self.headroom())?;
+        dict.set_item("error_pos", self.error.as_ref().map(|(pos, _err)| *pos))?;
+        dict.set_item("error", self.error.as_ref().map(|(_pos, err)| err.to_string()))?;
+        Ok(dict)
+    }
+}
+
+impl Display for DevDryRun {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f, "device {}: {} chunks of {} samples, total {:?}, mean {:?}, max {:?} at pos {} (headroom {:.1}x)",
+            self.name, self.n_chunks, self.chunk_samps, self.total_time, self.mean_chunk_time(), self.max_chunk_time,
+            self.slowest_chunk_pos, self.headroom()
+        )?;
+        if let Some((pos, err)) = &self.error {
+            write!(f, "\n\t\tfailed at pos {pos}: {err}")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DryRunReport {
+    /// Dry runs of streamed devices
+    pub devs: Vec<DevDryRun>,
+}
+
+impl DryRunReport {
+    pub fn is_ok(&self) -> bool {
+        self.devs.iter().all(DevDryRun::is_ok)
+    }
+    /// Errors of the failed devices
+    pub fn errors(&self) -> Vec<&StreamerError> {
+        self.devs.iter().filter_map(|dev| dev.error.as_ref().map(|(_pos, err)| err)).collect()
+    }
+    /// Smallest [`DevDryRun::headroom`] over all devices
+    pub fn headroom(&self) -> f64 {
+        self.devs.iter().map(DevDryRun::headroom).fold(f64::INFINITY, f64::min)
+    }
+    pub fn dev(&self, name: &str) -> Option<&DevDryRun> {
+        self.devs.iter().find(|dev| dev.name == name)
+    }
+
+    pub fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("ok", self.is_ok())?;
+        dict.set_item("headroom", self.headroom())?;
+        let devs = PyDict::new_bound(py);
+        for dev in self.devs.iter() {
+            devs.set_item(&dev.name, dev.to_dict(py)?)?;
+        }
+        dict.set_item("devs", devs)?;
+        Ok(dict)
+    }
+}
+
+impl Display for DryRunReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let n_failed = self.devs.iter().filter(|dev| !dev.is_ok()).count();
+        writeln!(f, "dry run: {} devices, {n_failed} failed, headroom {:.1}x", self.devs.len(), self.headroom())?;
+        for dev in self.devs.iter() {
+            writeln!(f, "\t{dev}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::channel::BaseChan;
+    use crate::device::BaseDev;
+    use crate::error::StreamerError;
+    use crate::fn_lib_tools::StdFnLib;
+    use crate::mock::test_impls::TestStreamer;
+    use crate::streamer::BaseStreamer;
+
+    #[test]
+    fn dry_run() {
+        let mut streamer = TestStreamer::new();
+        streamer.add_ao_dev("AO", 1e3);
+        streamer.add_ao_dev("Bad", 1e3);
+        streamer.add_do_dev("DO", 1e4);
+        streamer.ao_devs["AO"].add_chan("ao0", 0.0);
+        streamer.ao_devs["Bad"].add_chan("ao0", 0.0);
+        streamer.do_devs["DO"].add_chan("port0/line0", false);
+        streamer.ao_devs["AO"].chan_mut("ao0").unwrap().constant(1.0, 0.1, Some((0.1, false))).unwrap();
+        streamer.do_devs["DO"].chan_mut("port0/line0").unwrap().constant(true, 0.1, Some((0.1, false))).unwrap();
+        // Negative base with fractional exponent - NaN from t = 0.5 s on
+        let pow = StdFnLib::new().Pow(2.0, 0.5, 1.0, 0.0).unwrap().inner;
+        streamer.ao_devs["Bad"].chan_mut("ao0").unwrap().add_instr(pow, 0.5, None).unwrap();
+        assert!(matches!(streamer.dry_run(100), Err(StreamerError::NotCompiled { .. })));
+
+        streamer.compile(Some(1.5)).unwrap();
+        assert!(matches!(streamer.dry_run(0), Err(StreamerError::InvalidArgument { .. })));
+        let report = streamer.dry_run(300).unwrap();
+        assert!(!report.is_ok());
+
+        let ao = report.dev("AO").unwrap();
+        assert!(ao.is_ok());
+        assert_eq!((ao.n_chunks, ao.stop_pos), (5, 1500));
+        assert!(ao.max_chunk_time <= ao.total_time && ao.headroom() > 0.0);
+        assert_eq!(report.dev("DO").unwrap().n_chunks, 50);
+
+        // The device stops at the first failing chunk, the others carry on
+        let bad = report.dev("Bad").unwrap();
+        assert_eq!(bad.n_chunks, 1);
+        assert!(matches!(bad.error, Some((300, StreamerError::NonFinite { .. }))));
+        assert_eq!(report.errors().len(), 1);
+        assert!(report.to_string().starts_with("dry run: 3 devices, 1 failed"));
+    }
+}
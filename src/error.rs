@@ -0,0 +1,250 @@
+//! Error type shared by channels, devices, and streamers.
+//!
+//! Every fallible backend call returns [`StreamerError`]. The variant tells *what* went wrong
+//! so that calling code can branch on it, [`ErrCtx`] tells *where* (device and/or channel name),
+//! and the message keeps the human-readable explanation.
+//!
+//! In the Python layer each variant is raised as a distinct exception class - see [`register_exceptions`].
+//! All classes derive from `StreamerException`, which itself derives from `ValueError`
+//! so that existing `except ValueError` handlers keep working.
+// pyo3 0.22 `create_exception!` expansion checks the `gil-refs` feature of the calling crate
+#![allow(unexpected_cfgs)]
+
+use std::fmt;
+use std::fmt::Display;
+use pyo3::prelude::*;
+use pyo3::create_exception;
+use pyo3::exceptions::PyValueError;
+use serde::{Deserialize, Serialize};
+
+/// Location the error refers to
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrCtx {
+    pub dev: Option<String>,
+    pub chan: Option<String>,
+}
+
+impl ErrCtx {
+    pub fn none() -> Self {
+        Self::default()
+    }
+    pub fn dev(name: String) -> Self {
+        Self { dev: Some(name), chan: None }
+    }
+    pub fn chan(name: String) -> Self {
+        Self { dev: None, chan: Some(name) }
+    }
+}
+
+impl Display for ErrCtx {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.dev, &self.chan) {
+            (Some(dev), Some(chan)) => write!(f, "{dev}/{chan}"),
+            (Some(dev), None) => write!(f, "{dev}"),
+            (None, Some(chan)) => write!(f, "{chan}"),
+            (None, None) => write!(f, "streamer"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum StreamerError {
+    /// New instruction overlaps with an existing one and the overlap cannot be auto-fixed
+    Collision { ctx: ErrCtx, msg: String },
+    /// Compile cache is stale or inconsistent - `compile()` has to be called first
+    NotCompiled { ctx: ErrCtx, msg: String },
+    /// Operation requires instructions but none were added
+    NoInstructions { ctx: ErrCtx, msg: String },
+    /// Requested time, position, or window lies outside the allowed range
+    OutOfRange { ctx: ErrCtx, msg: String },
+    /// Named channel or device does not exist
+    NotFound { ctx: ErrCtx, msg: String },
+    /// Channel or device with this name is already registered
+    AlreadyExists { ctx: ErrCtx, msg: String },
+    /// Objects cannot be combined (e.g. different sample rates or types)
+    Incompatible { ctx: ErrCtx, msg: String },
+    /// Waveform function produced NaN or ±Inf
+    NonFinite { ctx: ErrCtx, msg: String },
+    /// Auto-fix or other warning rejected in strict compile mode
+    StrictViolation { ctx: ErrCtx, msg: String },
+    /// Compiled sequence violates a registered validation rule (see [`crate::rules`])
+    RuleViolation { ctx: ErrCtx, msg: String },
+    /// Any other invalid argument
+    InvalidArgument { ctx: ErrCtx, msg: String },
+}
+
+impl StreamerError {
+    pub fn ctx(&self) -> &ErrCtx {
+        match self {
+            Self::Collision { ctx, .. }
+            | Self::NotCompiled { ctx, .. }
+            | Self::NoInstructions { ctx, .. }
+            | Self::OutOfRange { ctx, .. }
+            | Self::NotFound { ctx, .. }
+            | Self::AlreadyExists { ctx, .. }
+            | Self::Incompatible { ctx, .. }
+            | Self::NonFinite { ctx, .. }
+            | Self::StrictViolation { ctx, .. }
+            | Self::RuleViolation { ctx, .. }
+            | Self::InvalidArgument { ctx, .. } => ctx,
+        }
+    }
+    fn ctx_mut(&mut self) -> &mut ErrCtx {
+        match self {
+            Self::Collision { ctx, .. }
+            | Self::NotCompiled { ctx, .. }
+            | Self::NoInstructions { ctx, .. }
+            | Self::OutOfRange { ctx, .. }
+            | Self::NotFound { ctx, .. }
+            | Self::AlreadyExists { ctx, .. }
+            | Self::Incompatible { ctx, .. }
+            | Self::NonFinite { ctx, .. }
+            | Self::StrictViolation { ctx, .. }
+            | Self::RuleViolation { ctx, .. }
+            | Self::InvalidArgument { ctx, .. } => ctx,
+        }
+    }
+    pub fn msg(&self) -> &str {
+        match self {
+            Self::Collision { msg, .. }
+            | Self::NotCompiled { msg, .. }
+            | Self::NoInstructions { msg, .. }
+            | Self::OutOfRange { msg, .. }
+            | Self::NotFound { msg, .. }
+            | Self::AlreadyExists { msg, .. }
+            | Self::Incompatible { msg, .. }
+            | Self::NonFinite { msg, .. }
+            | Self::StrictViolation { msg, .. }
+            | Self::RuleViolation { msg, .. }
+            | Self::InvalidArgument { msg, .. } => msg,
+        }
+    }
+
+    /// Prepends `prefix` to the message, e.g. the location in an input file the error refers to
+    pub fn prefixed(mut self, prefix: &str) -> Self {
+        let msg = match &mut self {
+            Self::Collision { msg, .. }
+            | Self::NotCompiled { msg, .. }
+            | Self::NoInstructions { msg, .. }
+            | Self::OutOfRange { msg, .. }
+            | Self::NotFound { msg, .. }
+            | Self::AlreadyExists { msg, .. }
+            | Self::Incompatible { msg, .. }
+            | Self::NonFinite { msg, .. }
+            | Self::StrictViolation { msg, .. }
+            | Self::RuleViolation { msg, .. }
+            | Self::InvalidArgument { msg, .. } => msg,
+        };
+        *msg = format!("{prefix}: {msg}");
+        self
+    }
+
+    /// Fills in the device name if it is not set yet.
+    /// Used by devices when forwarding channel errors.
+    pub fn in_dev(mut self, dev_name: String) -> Self {
+        let ctx = self.ctx_mut();
+        if ctx.dev.is_none() {
+            ctx.dev = Some(dev_name)
+        }
+        self
+    }
+}
+
+/// Prints the message after the location from the context, e.g. `[AO/ao0] ...`
+impl Display for StreamerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.ctx() {
+            ctx if ctx == &ErrCtx::none() => write!(f, "{}", self.msg()),
+            ctx => write!(f, "[{ctx}] {}", self.msg()),
+        }
+    }
+}
+
+impl std::error::Error for StreamerError {}
+
+create_exception!(base_streamer, StreamerException, PyValueError, "Base class for all streamer backend errors");
+create_exception!(base_streamer, CollisionError, StreamerException);
+create_exception!(base_streamer, NotCompiledError, StreamerException);
+create_exception!(base_streamer, NoInstructionsError, StreamerException);
+create_exception!(base_streamer, OutOfRangeError, StreamerException);
+create_exception!(base_streamer, NotFoundError, StreamerException);
+create_exception!(base_streamer, AlreadyExistsError, StreamerException);
+create_exception!(base_streamer, IncompatibleError, StreamerException);
+create_exception!(base_streamer, NonFiniteError, StreamerException);
+create_exception!(base_streamer, StrictViolationError, StreamerException);
+create_exception!(base_streamer, RuleViolationError, StreamerException);
+create_exception!(base_streamer, InvalidArgumentError, StreamerException);
+
+impl From<StreamerError> for PyErr {
+    fn from(err: StreamerError) -> Self {
+        let msg = err.to_string();
+        match err {
+            StreamerError::Collision { .. } => CollisionError::new_err(msg),
+            StreamerError::NotCompiled { .. } => NotCompiledError::new_err(msg),
+            StreamerError::NoInstructions { .. } => NoInstructionsError::new_err(msg),
+            StreamerError::OutOfRange { .. } => OutOfRangeError::new_err(msg),
+            StreamerError::NotFound { .. } => NotFoundError::new_err(msg),
+            StreamerError::AlreadyExists { .. } => AlreadyExistsError::new_err(msg),
+            StreamerError::Incompatible { .. } => IncompatibleError::new_err(msg),
+            StreamerError::NonFinite { .. } => NonFiniteError::new_err(msg),
+            StreamerError::StrictViolation { .. } => StrictViolationError::new_err(msg),
+            StreamerError::RuleViolation { .. } => RuleViolationError::new_err(msg),
+            StreamerError::InvalidArgument { .. } => InvalidArgumentError::new_err(msg),
+        }
+    }
+}
+
+/// Adds all exception classes to the Python module of a downstream streamer crate
+pub fn register_exceptions(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    let py = m.py();
+    m.add("StreamerException", py.get_type_bound::<StreamerException>())?;
+    m.add("CollisionError", py.get_type_bound::<CollisionError>())?;
+    m.add("NotCompiledError", py.get_type_bound::<NotCompiledError>())?;
+    m.add("NoInstructionsError", py.get_type_bound::<NoInstructionsError>())?;
+    m.add("OutOfRangeError", py.get_type_bound::<OutOfRangeError>())?;
+    m.add("NotFoundError", py.get_type_bound::<NotFoundError>())?;
+    m.add("AlreadyExistsError", py.get_type_bound::<AlreadyExistsError>())?;
+    m.add("IncompatibleError", py.get_type_bound::<IncompatibleError>())?;
+    m.add("NonFiniteError", py.get_type_bound::<NonFiniteError>())?;
+    m.add("StrictViolationError", py.get_type_bound::<StrictViolationError>())?;
+    m.add("RuleViolationError", py.get_type_bound::<RuleViolationError>())?;
+    m.add("InvalidArgumentError", py.get_type_bound::<InvalidArgumentError>())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::channel::BaseChan;
+    use crate::device::BaseDev;
+    use crate::error::{ErrCtx, StreamerError};
+    use crate::mock::test_impls::TestDev;
+
+    #[test]
+    fn variants_and_context() {
+        let mut dev = TestDev::new("AO", 1e3);
+        dev.add_chan("ao0", 0.0);
+
+        assert!(matches!(dev.chan("ao1"), Err(StreamerError::NotFound { .. })));
+        assert!(matches!(dev.compile(1.0), Err(StreamerError::NoInstructions { .. })));
+
+        let chan = dev.chan_mut("ao0").unwrap();
+        chan.constant(1.0, 0.0, Some((0.5, false))).unwrap();
+        let err = chan.constant(2.0, 0.2, Some((0.5, false))).unwrap_err();
+        assert!(matches!(err, StreamerError::Collision { .. }));
+        assert_eq!(err.ctx(), &ErrCtx::chan("ao0".to_string()));
+
+        // Channel errors forwarded by the device get the device name attached
+        let err = dev.compile(0.1).unwrap_err();
+        assert!(matches!(err, StreamerError::OutOfRange { .. }));
+        let err = dev.calc_samps(&mut [0.0; 10], 0, 10).unwrap_err();
+        assert!(matches!(err, StreamerError::NotCompiled { .. }));
+        assert_eq!(err.ctx().dev.as_deref(), Some("AO"));
+
+        // Location is printed from the context
+        let err = StreamerError::OutOfRange { ctx: ErrCtx::chan("ao0".to_string()), msg: "too late".to_string() };
+        assert_eq!(err.to_string(), "[ao0] too late");
+        assert_eq!(err.in_dev("AO".to_string()).to_string(), "[AO/ao0] too late");
+        let err = StreamerError::NotFound { ctx: ErrCtx::none(), msg: "There is no device DO registered".to_string() };
+        assert_eq!(err.to_string(), "There is no device DO registered");
+    }
+}
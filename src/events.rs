@@ -0,0 +1,245 @@
+//! Observer registry for streamer-level operations.
+//!
+//! Logging, GUIs, and electronic lab notebooks need to follow what was done to the sequence. Instead of wrapping every
+//! API call, they register an [`Observer`] with [`BaseStreamer::add_observer`] and receive a [`StreamerEvent`] after each
+//! successful streamer-level operation:
+//! - [`BaseStreamer::add_instr_by_name`] (also used by schedule import and the command server);
+//! - [`BaseStreamer::add_reset_instr`], [`BaseStreamer::shift_all`], and [`BaseStreamer::crop`];
+//! - [`BaseStreamer::compile_with`];
+//! - [`BaseStreamer::clear_compile_cache`] and [`BaseStreamer::clear_edit_cache`]
+//!   (clearing the edit cache also clears the compile cache, so both events are fired).
+//!
+//! Edits made directly on devices or channels bypass the streamer and are not reported. Failed operations fire nothing.
+//!
+//! Observers are called synchronously in registration order and cannot fail the operation.
+//! Python callables are registered with [`crate::py_tools::add_observer`].
+//!
+//! [`BaseStreamer::add_observer`]: crate::streamer::BaseStreamer::add_observer
+//! [`BaseStreamer::add_instr_by_name`]: crate::streamer::BaseStreamer::add_instr_by_name
+//! [`BaseStreamer::add_reset_instr`]: crate::streamer::BaseStreamer::add_reset_instr
+//! [`BaseStreamer::shift_all`]: crate::streamer::BaseStreamer::shift_all
+//! [`BaseStreamer::crop`]: crate::streamer::BaseStreamer::crop
+//! [`BaseStreamer::compile_with`]: crate::streamer::BaseStreamer::compile_with
+//! [`BaseStreamer::clear_compile_cache`]: crate::streamer::BaseStreamer::clear_compile_cache
+//! [`BaseStreamer::clear_edit_cache`]: crate::streamer::BaseStreamer::clear_edit_cache
+
+use std::fmt;
+use std::fmt::Display;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use crate::fn_lib_tools::FnArgs;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum StreamerEvent {
+    AddInstr {
+        dev: String,
+        chan: String,
+        func: String,
+        args: FnArgs,
+        t: f64,
+        dur_spec: Option<(f64, bool)>,
+    },
+    AddResetInstr { reset_time: f64 },
+    Shift { dt: f64 },
+    Crop { t_start: f64, t_end: f64 },
+    /// Successful compile, `run_time` is the value returned by [`crate::streamer::BaseStreamer::compile_with`]
+    Compile { stop_time: Option<f64>, run_time: f64 },
+    ClearCompileCache,
+    ClearEditCache,
+}
+
+impl StreamerEvent {
+    /// Snake-case event name, e.g. `"add_instr"`
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::AddInstr { .. } => "add_instr",
+            Self::AddResetInstr { .. } => "add_reset_instr",
+            Self::Shift { .. } => "shift",
+            Self::Crop { .. } => "crop",
+            Self::Compile { .. } => "compile",
+            Self::ClearCompileCache => "clear_compile_cache",
+            Self::ClearEditCache => "clear_edit_cache",
+        }
+    }
+
+    pub fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("kind", self.kind())?;
+        match self {
+            Self::AddInstr { dev, chan, func, args, t, dur_spec } => {
+                dict.set_item("dev", dev)?;
+                dict.set_item("chan", chan)?;
+                dict.set_item("func", func)?;
+                match args {
+                    FnArgs::Positional(vals) => dict.set_item("args", vals.clone())?,
+                    FnArgs::Named(vals) => {
+                        let named = PyDict::new_bound(py);
+                        for (name, val) in vals {
+                            named.set_item(name, val)?;
+                        }
+                        dict.set_item("args", named)?
+                    },
+                }
+                dict.set_item("t", t)?;
+                dict.set_item("dur", dur_spec.map(|(dur, _keep_val)| dur))?;
+                dict.set_item("keep_val", dur_spec.map(|(_dur, keep_val)| keep_val))?;
+            },
+            Self::AddResetInstr { reset_time } => dict.set_item("reset_time", reset_time)?,
+            Self::Shift { dt } => dict.set_item("dt", dt)?,
+            Self::Crop { t_start, t_end } => {
+                dict.set_item("t_start", t_start)?;
+                dict.set_item("t_end", t_end)?;
+            },
+            Self::Compile { stop_time, run_time } => {
+                dict.set_item("stop_time", stop_time)?;
+                dict.set_item("run_time", run_time)?;
+            },
+            Self::ClearCompileCache | Self::ClearEditCache => {},
+        }
+        Ok(dict)
+    }
+}
+
+impl Display for StreamerEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AddInstr { dev, chan, func, args, t, dur_spec } => {
+                write!(f, "add_instr {dev}/{chan}: {func}({args:?}) at t={t} s")?;
+                match dur_spec {
+                    Some((dur, keep_val)) => write!(f, " for {dur} s, keep_val={keep_val}"),
+                    None => write!(f, " until the next instruction"),
+                }
+            },
+            Self::AddResetInstr { reset_time } => write!(f, "add_reset_instr at t={reset_time} s"),
+            Self::Shift { dt } => write!(f, "shift by {dt} s"),
+            Self::Crop { t_start, t_end } => write!(f, "crop to [{t_start}, {t_end}) s"),
+            Self::Compile { stop_time, run_time } => match stop_time {
+                Some(stop_time) => write!(f, "compile with stop_time={stop_time} s, run time {run_time} s"),
+                None => write!(f, "compile, run time {run_time} s"),
+            },
+            Self::ClearCompileCache | Self::ClearEditCache => write!(f, "{}", self.kind()),
+        }
+    }
+}
+
+pub type Observer = Box<dyn Fn(&StreamerEvent) + Send + Sync>;
+
+/// Handle returned by [`Observers::add`] to remove the observer later
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ObserverId(pub usize);
+
+/// Registered observers in registration order
+#[derive(Default)]
+pub struct Observers {
+    next_id: usize,
+    entries: Vec<(ObserverId, Observer)>,
+}
+
+impl Observers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn add(&mut self, observer: Observer) -> ObserverId {
+        let id = ObserverId(self.next_id);
+        self.next_id += 1;
+        self.entries.push((id, observer));
+        id
+    }
+    /// Returns `false` if there is no observer `id`
+    pub fn remove(&mut self, id: ObserverId) -> bool {
+        let len = self.entries.len();
+        self.entries.retain(|(entry_id, _)| *entry_id != id);
+        self.entries.len() != len
+    }
+    pub fn clear(&mut self) {
+        self.entries.clear()
+    }
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    pub fn emit(&self, event: &StreamerEvent) {
+        for (_, observer) in self.entries.iter() {
+            observer(event)
+        }
+    }
+}
+
+/// Python callable receiving [`StreamerEvent::to_dict`]. Exceptions it raises are reported as unraisable
+/// (printed by Python) since observers cannot fail the operation.
+pub struct PyObserver {
+    pub func: PyObject,
+}
+
+impl PyObserver {
+    pub fn new(func: PyObject) -> Self {
+        Self { func }
+    }
+
+    pub fn notify(&self, event: &StreamerEvent) {
+        Python::with_gil(|py| {
+            let res = event.to_dict(py).and_then(|dict| self.func.call1(py, (dict,)));
+            if let Err(err) = res {
+                err.write_unraisable_bound(py, Some(self.func.bind(py)))
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+    use crate::error::StreamerError;
+    use crate::events::StreamerEvent;
+    use crate::fn_lib_tools::{FnArgs, FnRegistry};
+    use crate::mock::test_impls::TestStreamer;
+    use crate::streamer::BaseStreamer;
+
+    #[test]
+    fn observers() {
+        let mut streamer = TestStreamer::new();
+        streamer.add_ao_dev("AO", 1e3);
+        streamer.ao_devs["AO"].add_chan("ao0", 0.0);
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let id = {
+            let log = log.clone();
+            streamer.add_observer(Box::new(move |event| log.lock().unwrap().push(event.clone()))).unwrap()
+        };
+        let kinds = Arc::new(Mutex::new(Vec::new()));
+        {
+            let kinds = kinds.clone();
+            streamer.add_observer(Box::new(move |event| kinds.lock().unwrap().push(event.kind()))).unwrap();
+        }
+
+        let registry = FnRegistry::std();
+        let args = FnArgs::Positional(vec![1.0]);
+        streamer.add_instr_by_name("AO", "ao0", &registry, "ConstF64", &args, 0.1, Some((0.1, false))).unwrap();
+        // Failed operations fire nothing
+        assert!(matches!(
+            streamer.add_instr_by_name("AO", "ao0", &registry, "ConstF64", &args, 0.15, None),
+            Err(StreamerError::Collision { .. })
+        ));
+        assert!(matches!(streamer.add_instr_by_name("X", "ao0", &registry, "ConstF64", &args, 1.0, None), Err(StreamerError::NotFound { .. })));
+        streamer.shift_all(0.1).unwrap();
+        assert_eq!(streamer.compile(None).unwrap(), 0.301);
+        streamer.clear_edit_cache();
+
+        assert_eq!(log.lock().unwrap()[..3], [
+            StreamerEvent::AddInstr {
+                dev: "AO".to_string(), chan: "ao0".to_string(), func: "ConstF64".to_string(), args, t: 0.1, dur_spec: Some((0.1, false)),
+            },
+            StreamerEvent::Shift { dt: 0.1 },
+            StreamerEvent::Compile { stop_time: None, run_time: 0.301 },
+        ]);
+        assert_eq!(log.lock().unwrap()[0].to_string(), "add_instr AO/ao0: ConstF64(Positional([1.0])) at t=0.1 s for 0.1 s, keep_val=false");
+        assert_eq!(*kinds.lock().unwrap(), ["add_instr", "shift", "compile", "clear_compile_cache", "clear_edit_cache"]);
+
+        streamer.remove_observer(id).unwrap();
+        assert!(matches!(streamer.remove_observer(id), Err(StreamerError::NotFound { .. })));
+        streamer.add_reset_instr(None).unwrap();
+        assert_eq!(log.lock().unwrap().len(), 5);
+        assert_eq!(kinds.lock().unwrap().last(), Some(&"add_reset_instr"));
+    }
+}
@@ -0,0 +1,518 @@
+//! Edge export of digital channels for logic-analyzer viewers.
+//!
+//! A digital line of a long sequence mostly holds its level, so writing every compiled sample (see
+//! [`crate::replay::save_npy`]) produces huge files of repeated values. [`BaseChan::export_edges`] writes only the
+//! level transitions of a `bool` channel, taken from the run-length form of the compile cache
+//! (see [`BaseChan::compiled_events`]), so the file size scales with the number of edges instead of the duration.
+//!
+//! The format follows the file extension ([`EdgeFormat::from_path`]):
+//! - `.vcd` - Value Change Dump, opened directly by GTKWave, sigrok/PulseView and most logic-analyzer tools.
+//!   Timestamps are integer picoseconds ([`VCD_TIMESCALE`]), a final timestamp marks the compiled stop time;
+//! - anything else - CSV with the header `time,level`, one `t,0|1` row per transition, the first row at `t = 0`.
+//!
+//! [`BaseStreamer::export_vcd`] writes every active `bool` channel of the streamer into one `.vcd` file, one `$scope`
+//! per device. Its times are output times - compile cache time plus the trigger delay of the device - so that the
+//! channels line up the same way as on a logic-analyzer capture of the real outputs.
+//!
+//! The reverse direction replays captured patterns: [`read_signals`] parses a `.vcd` file (any `$timescale`,
+//! 1-bit variables only, `x`/`z` read as low) or an edge-list CSV in the format above into [`Signal`]s, and
+//! [`BaseChan::import_edges`] / [`BaseStreamer::import_vcd`] turn them into "go-this" constant instructions,
+//! one per level change.
+//!
+//! [`BaseChan::export_edges`]: crate::channel::BaseChan::export_edges
+//! [`BaseChan::compiled_events`]: crate::channel::BaseChan::compiled_events
+//! [`BaseStreamer::export_vcd`]: crate::streamer::BaseStreamer::export_vcd
+//! [`BaseChan::import_edges`]: crate::channel::BaseChan::import_edges
+//! [`BaseStreamer::import_vcd`]: crate::streamer::BaseStreamer::import_vcd
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::fs;
+use std::str::SplitWhitespace;
+use crate::channel::{Event, Events};
+use crate::error::{ErrCtx, StreamerError};
+
+/// `$timescale` of exported `.vcd` files
+pub const VCD_TIMESCALE: &str = "1 ps";
+/// Timestamp units per second matching [`VCD_TIMESCALE`]
+const VCD_TICKS_PER_S: f64 = 1e12;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EdgeFormat {
+    Csv,
+    Vcd,
+}
+
+impl EdgeFormat {
+    /// [`EdgeFormat::Vcd`] for paths ending in `.vcd` (any case), [`EdgeFormat::Csv`] otherwise
+    pub fn from_path(path: &str) -> Self {
+        match path.to_ascii_lowercase().ends_with(".vcd") {
+            true => EdgeFormat::Vcd,
+            false => EdgeFormat::Csv,
+        }
+    }
+}
+
+/// CSV text of `events` - see the [module docs](self)
+pub fn edges_csv(events: &[Event<bool>]) -> String {
+    let mut text = String::from("time,level\n");
+    for event in events {
+        writeln!(text, "{},{}", event.t, event.val as u8).unwrap();
+    }
+    text
+}
+
+/// VCD text of `events` of the signal `name`, ending at `stop_time` - see the [module docs](self)
+pub fn edges_vcd(name: &str, events: &[Event<bool>], stop_time: f64) -> String {
+    vcd_text(&[("base_streamer".to_string(), vec![(name.to_string(), events.to_vec())])], stop_time)
+}
+
+/// One `$scope` of a VCD file: scope name and `(signal name, events)` of its signals
+pub type VcdScope = (String, Vec<(String, Events<bool>)>);
+
+/// VCD text of all signals of `scopes`, ending at `stop_time`.
+///
+/// The first event of every signal gives its level from time 0 on, later events are written as changes.
+/// Whitespace in names is replaced by `_` since VCD identifiers can not contain it.
+pub fn vcd_text(scopes: &[VcdScope], stop_time: f64) -> String {
+    let vcd_time = |t: f64| (t * VCD_TICKS_PER_S).round() as u64;
+    let vcd_name = |name: &str| -> String { name.chars().map(|c| if c.is_whitespace() { '_' } else { c }).collect() };
+    let mut text = String::new();
+    writeln!(text, "$version base_streamer {} $end", env!("CARGO_PKG_VERSION")).unwrap();
+    writeln!(text, "$timescale {VCD_TIMESCALE} $end").unwrap();
+    let mut initial = Vec::new();
+    let mut changes = Vec::new();
+    let mut sig_idx = 0;
+    for (scope_name, signals) in scopes {
+        writeln!(text, "$scope module {} $end", vcd_name(scope_name)).unwrap();
+        for (name, events) in signals {
+            let id = vcd_id(sig_idx);
+            writeln!(text, "$var wire 1 {id} {} $end", vcd_name(name)).unwrap();
+            if let Some((first, rest)) = events.split_first() {
+                initial.push(format!("{}{id}", first.val as u8));
+                changes.extend(rest.iter().map(|event| (vcd_time(event.t), format!("{}{id}", event.val as u8))));
+            }
+            sig_idx += 1;
+        }
+        writeln!(text, "$upscope $end").unwrap();
+    }
+    writeln!(text, "$enddefinitions $end").unwrap();
+    writeln!(text, "#0\n$dumpvars").unwrap();
+    for change in initial {
+        writeln!(text, "{change}").unwrap();
+    }
+    writeln!(text, "$end").unwrap();
+    // Stable sort keeps the scope / signal order within one timestamp
+    changes.sort_by_key(|(time, _change)| *time);
+    let mut last_time = 0;
+    for (time, change) in changes {
+        if time != last_time {
+            writeln!(text, "#{time}").unwrap();
+            last_time = time;
+        }
+        writeln!(text, "{change}").unwrap();
+    }
+    writeln!(text, "#{}", vcd_time(stop_time).max(last_time)).unwrap();
+    text
+}
+
+/// Short VCD identifier code of the signal number `idx`, in base 94 over the printable characters `!` to `~`
+fn vcd_id(mut idx: usize) -> String {
+    let mut id = String::new();
+    loop {
+        id.push((b'!' + (idx % 94) as u8) as char);
+        idx /= 94;
+        if idx == 0 {
+            return id
+        }
+        idx -= 1;
+    }
+}
+
+/// Writes `events` of the signal `name` to `path` in the format picked by [`EdgeFormat::from_path`].
+///
+/// Returns [`StreamerError::InvalidArgument`] naming the path if the file can not be written.
+pub fn write_edges(path: &str, name: &str, events: &[Event<bool>], stop_time: f64) -> Result<(), StreamerError> {
+    let text = match EdgeFormat::from_path(path) {
+        EdgeFormat::Csv => edges_csv(events),
+        EdgeFormat::Vcd => edges_vcd(name, events, stop_time),
+    };
+    write_text(path, text)
+}
+
+/// Writes [`vcd_text`] of `scopes` to `path`, see [`write_edges`] for errors
+pub fn write_vcd(path: &str, scopes: &[VcdScope], stop_time: f64) -> Result<(), StreamerError> {
+    write_text(path, vcd_text(scopes, stop_time))
+}
+
+fn write_text(path: &str, text: String) -> Result<(), StreamerError> {
+    fs::write(path, text).map_err(|err| StreamerError::InvalidArgument { ctx: ErrCtx::none(), msg: format!("{path}: failed to write: {err}") })
+}
+
+/// Level transitions `(time in seconds, level)` of an imported signal, sorted by time
+pub type Edges = Vec<(f64, bool)>;
+
+/// One digital signal of a capture file
+#[derive(Clone, Debug, PartialEq)]
+pub struct Signal {
+    /// Enclosing `$scope` names joined with `.`, empty for CSV files and top-level VCD variables
+    pub scope: String,
+    pub name: String,
+    /// Level changes only - repeated levels of the file are dropped, the first entry is the first value of the file
+    pub edges: Edges,
+}
+
+impl Signal {
+    fn new(scope: String, name: String) -> Self {
+        Self { scope, name, edges: Vec::new() }
+    }
+    /// Records `level` from `t` on. A later value at the same time replaces the earlier one.
+    fn push(&mut self, t: f64, level: bool) {
+        if let Some(&(last_t, _)) = self.edges.last() {
+            if last_t == t {
+                self.edges.pop();
+            }
+        }
+        if self.edges.last().is_none_or(|&(_, last_level)| last_level != level) {
+            self.edges.push((t, level))
+        }
+    }
+    /// Whether `key` names this signal - either `name` alone or `scope.name`
+    pub fn matches(&self, key: &str) -> bool {
+        key == self.name || (!self.scope.is_empty() && key == format!("{}.{}", self.scope, self.name))
+    }
+}
+
+/// Edges of an edge-list CSV (`time,level` rows, `level` one of `0`/`1`/`true`/`false`).
+/// A non-numeric first row is taken as the header, empty lines and `#` comments are skipped.
+pub fn parse_edges_csv(text: &str) -> Result<Edges, String> {
+    let mut signal = Signal::new(String::new(), String::new());
+    for (line_idx, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue
+        }
+        let row = line.split_once(',').and_then(|(t, level)| {
+            let level = match level.trim().to_ascii_lowercase().as_str() {
+                "0" | "false" => false,
+                "1" | "true" => true,
+                _ => return None,
+            };
+            Some((t.trim().parse::<f64>().ok()?, level))
+        });
+        let (t, level) = match row {
+            Some(row) => row,
+            None if line_idx == 0 => continue,
+            None => return Err(format!("line {}: expected `time,level`, got `{line}`", line_idx + 1)),
+        };
+        if !t.is_finite() || signal.edges.last().is_some_and(|&(last_t, _)| t < last_t) {
+            return Err(format!("line {}: time {t} is not finite or goes backwards", line_idx + 1))
+        }
+        signal.push(t, level)
+    }
+    Ok(signal.edges)
+}
+
+/// All 1-bit signals of VCD `text`, in declaration order. Multi-bit vectors and reals are skipped.
+pub fn parse_vcd(text: &str) -> Result<Vec<Signal>, String> {
+    /// Tokens up to the `$end` closing `keyword`
+    fn until_end(tokens: &mut SplitWhitespace, keyword: &str) -> Result<Vec<String>, String> {
+        let mut body = Vec::new();
+        for token in tokens.by_ref() {
+            if token == "$end" {
+                return Ok(body)
+            }
+            body.push(token.to_string())
+        }
+        Err(format!("{keyword} is missing its $end"))
+    }
+    let mut tokens = text.split_whitespace();
+    let mut signals = Vec::new();
+    // A VCD identifier code may be shared by several variables
+    let mut id_signals: HashMap<String, Vec<usize>> = HashMap::new();
+    let mut scopes: Vec<String> = Vec::new();
+    let mut timescale = None;
+    let mut t = 0.0;
+    while let Some(token) = tokens.next() {
+        match token {
+            "$timescale" => timescale = Some(parse_timescale(&until_end(&mut tokens, token)?.concat())?),
+            "$scope" => {
+                let body = until_end(&mut tokens, token)?;
+                scopes.push(body.get(1).cloned().unwrap_or_default())
+            },
+            "$upscope" => {
+                until_end(&mut tokens, token)?;
+                scopes.pop();
+            },
+            "$var" => {
+                let body = until_end(&mut tokens, token)?;
+                let [_var_type, width, id, name, ..] = body.as_slice() else {
+                    return Err(format!("malformed $var declaration `{}`", body.join(" ")))
+                };
+                if width == "1" {
+                    id_signals.entry(id.clone()).or_default().push(signals.len());
+                    signals.push(Signal::new(scopes.join("."), name.clone()))
+                }
+            },
+            // Value changes within these blocks are read as ordinary changes below
+            "$dumpvars" | "$dumpall" | "$dumpon" | "$dumpoff" | "$end" => {},
+            _ if token.starts_with('$') => {
+                until_end(&mut tokens, token)?;
+            },
+            _ if token.starts_with('#') => {
+                let (mult, per_sec) = timescale.ok_or("value changes start before $timescale")?;
+                let ticks: u64 = token[1..].parse().map_err(|_| format!("invalid timestamp `{token}`"))?;
+                t = ticks as f64 * mult / per_sec;
+            },
+            _ if token.starts_with(['b', 'B', 'r', 'R']) => {
+                // Vector or real value - its identifier follows as a separate token
+                tokens.next();
+            },
+            _ => {
+                let (level, id) = token.split_at(1);
+                let level = match level {
+                    "1" => true,
+                    "0" | "x" | "X" | "z" | "Z" => false,
+                    _ => return Err(format!("unexpected token `{token}`")),
+                };
+                for &idx in id_signals.get(id).into_iter().flatten() {
+                    signals[idx].push(t, level)
+                }
+            },
+        }
+    }
+    Ok(signals)
+}
+
+/// Tick multiplier and units per second of a `$timescale` body such as `1ps` or `10 us`.
+/// Kept apart so that times are computed as `ticks * mult / per_sec`, exact for round numbers.
+fn parse_timescale(spec: &str) -> Result<(f64, f64), String> {
+    let split = spec.find(|c: char| !c.is_ascii_digit()).unwrap_or(spec.len());
+    let (mult, unit) = spec.split_at(split);
+    let per_sec = match unit {
+        "s" => 1.0,
+        "ms" => 1e3,
+        "us" => 1e6,
+        "ns" => 1e9,
+        "ps" => 1e12,
+        "fs" => 1e15,
+        _ => return Err(format!("unsupported $timescale `{spec}`")),
+    };
+    let mult: f64 = mult.parse().map_err(|_| format!("unsupported $timescale `{spec}`"))?;
+    Ok((mult, per_sec))
+}
+
+/// Reads the signals of the capture file `path` - a `.vcd` file or an edge-list CSV (one signal named after the file),
+/// see [`EdgeFormat::from_path`].
+///
+/// Returns [`StreamerError::InvalidArgument`] naming the path if the file can not be read or parsed.
+pub fn read_signals(path: &str) -> Result<Vec<Signal>, StreamerError> {
+    let invalid = |msg: String| StreamerError::InvalidArgument { ctx: ErrCtx::none(), msg: format!("{path}: {msg}") };
+    let text = fs::read_to_string(path).map_err(|err| invalid(format!("failed to read: {err}")))?;
+    match EdgeFormat::from_path(path) {
+        EdgeFormat::Csv => {
+            let name = std::path::Path::new(path).file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+            Ok(vec![Signal { scope: String::new(), name, edges: parse_edges_csv(&text).map_err(invalid)? }])
+        },
+        EdgeFormat::Vcd => parse_vcd(&text).map_err(invalid),
+    }
+}
+
+/// The signal of `signals` picked by `key` (see [`Signal::matches`]), or the only signal if `key` is `None`
+pub fn pick_signal<'a>(signals: &'a [Signal], key: Option<&str>, path: &str) -> Result<&'a Signal, StreamerError> {
+    let found = match key {
+        Some(key) => signals.iter().find(|signal| signal.matches(key)),
+        None if signals.len() == 1 => signals.first(),
+        None => return Err(StreamerError::InvalidArgument {
+            ctx: ErrCtx::none(),
+            msg: format!("{path} holds {} digital signals - pick one by name", signals.len()),
+        }),
+    };
+    found.ok_or_else(|| StreamerError::NotFound {
+        ctx: ErrCtx::none(),
+        msg: format!("{path} has no digital signal {}", key.unwrap_or_default()),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::channel::BaseChan;
+    use crate::device::BaseDev;
+    use crate::error::StreamerError;
+    use crate::export::*;
+    use crate::mock::test_impls::TestStreamer;
+    use crate::streamer::BaseStreamer;
+
+    #[test]
+    fn export_edges() {
+        assert_eq!(EdgeFormat::from_path("trace.VCD"), EdgeFormat::Vcd);
+        assert_eq!(EdgeFormat::from_path("trace.vcd.csv"), EdgeFormat::Csv);
+
+        let mut streamer = TestStreamer::new();
+        streamer.add_do_dev("DO", 1e3);
+        streamer.do_devs["DO"].add_chan("port0/line0", false);
+        {
+            let line = streamer.do_devs["DO"].chan_mut("port0/line0").unwrap();
+            line.constant(true, 0.2, Some((0.001, false))).unwrap();
+            line.constant(true, 0.5, Some((0.25, false))).unwrap();
+        }
+        let dir = std::env::temp_dir().join(format!("base_streamer_edges_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let csv_path = dir.join("line0.csv");
+        let vcd_path = dir.join("line0.vcd");
+        let line = streamer.do_devs["DO"].chan("port0/line0").unwrap();
+        assert!(matches!(line.export_edges(csv_path.to_str().unwrap()), Err(StreamerError::NotCompiled { .. })));
+        streamer.compile(Some(1.0)).unwrap();
+
+        let line = streamer.do_devs["DO"].chan("port0/line0").unwrap();
+        line.export_edges(csv_path.to_str().unwrap()).unwrap();
+        assert_eq!(std::fs::read_to_string(&csv_path).unwrap(), "time,level\n0,0\n0.2,1\n0.201,0\n0.5,1\n0.75,0\n");
+
+        line.export_edges(vcd_path.to_str().unwrap()).unwrap();
+        let vcd = std::fs::read_to_string(&vcd_path).unwrap();
+        assert!(vcd.contains("$timescale 1 ps $end\n"));
+        assert!(vcd.contains("$var wire 1 ! port0/line0 $end\n"));
+        let body = vcd.split("$enddefinitions $end\n").nth(1).unwrap();
+        assert_eq!(body, "#0\n$dumpvars\n0!\n$end\n#200000000000\n1!\n#201000000000\n0!\n#500000000000\n1!\n#750000000000\n0!\n#1000000000000\n");
+
+        assert!(matches!(line.export_edges(dir.join("missing/line0.csv").to_str().unwrap()), Err(StreamerError::InvalidArgument { .. })));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn export_vcd() {
+        assert_eq!([0, 93, 94, 95].map(vcd_id), ["!", "~", "!!", "\"!"]);
+
+        let mut streamer = TestStreamer::new();
+        streamer.add_ao_dev("AO", 1e3);
+        streamer.add_do_dev("DO1", 1e3);
+        streamer.add_do_dev("DO2", 1e3);
+        streamer.ao_devs["AO"].add_chan("ao0", 0.0);
+        streamer.do_devs["DO1"].add_chan("port0/line0", false);
+        streamer.do_devs["DO1"].add_chan("port0/line1", false);
+        streamer.do_devs["DO2"].add_chan("port0/line0", false);
+        streamer.ao_devs["AO"].chan_mut("ao0").unwrap().constant(1.0, 0.1, Some((0.1, false))).unwrap();
+        streamer.do_devs["DO1"].chan_mut("port0/line0").unwrap().constant(true, 0.2, Some((0.001, false))).unwrap();
+        streamer.do_devs["DO1"].chan_mut("port0/line1").unwrap().constant(true, 0.5, Some((0.1, true))).unwrap();
+        streamer.do_devs["DO2"].chan_mut("port0/line0").unwrap().constant(true, 0.2, Some((0.01, false))).unwrap();
+        streamer.set_trigger_delay("DO2", 0.005).unwrap();
+        let path = std::env::temp_dir().join(format!("base_streamer_vcd_{}.vcd", std::process::id()));
+        let path = path.to_str().unwrap();
+        assert!(matches!(streamer.export_vcd(path), Err(StreamerError::NotCompiled { .. })));
+        streamer.compile(Some(1.0)).unwrap();
+
+        streamer.export_vcd(path).unwrap();
+        let vcd = std::fs::read_to_string(path).unwrap();
+        let (header, body) = vcd.split_once("$enddefinitions $end\n").unwrap();
+        // The AO device is skipped, one scope per DO device
+        assert!(header.ends_with(
+            "$scope module DO1 $end\n$var wire 1 ! port0/line0 $end\n$var wire 1 \" port0/line1 $end\n$upscope $end\n\
+            $scope module DO2 $end\n$var wire 1 # port0/line0 $end\n$upscope $end\n"
+        ));
+        // Trigger delay compensation keeps the DO2 pulse at 200 ms output time
+        assert_eq!(body, "\
+            #0\n$dumpvars\n0!\n0\"\n0#\n$end\n\
+            #200000000000\n1!\n1#\n\
+            #201000000000\n0!\n\
+            #210000000000\n0#\n\
+            #500000000000\n1\"\n\
+            #1000000000000\n\
+        ");
+        std::fs::remove_file(path).unwrap();
+
+        let mut streamer = TestStreamer::new();
+        streamer.add_ao_dev("AO", 1e3);
+        streamer.ao_devs["AO"].add_chan("ao0", 0.0);
+        streamer.ao_devs["AO"].chan_mut("ao0").unwrap().constant(1.0, 0.1, Some((0.1, false))).unwrap();
+        streamer.compile(Some(1.0)).unwrap();
+        assert!(matches!(streamer.export_vcd(path), Err(StreamerError::NotFound { .. })));
+    }
+
+    #[test]
+    fn parse_capture() {
+        assert_eq!(parse_timescale("10us"), Ok((10.0, 1e6)));
+        assert!(parse_timescale("1 min").is_err());
+        // sigrok-style capture: nested scope, shared identifier, vector and `x` values
+        let vcd = "$date today $end\n$timescale 1 us $end\n$scope module la $end\n$scope module probe $end\n\
+            $var wire 1 ! D0 $end\n$var wire 1 ! D0_copy $end\n$var wire 8 \" bus [7:0] $end\n$var wire 1 # D1 $end\n\
+            $upscope $end\n$upscope $end\n$enddefinitions $end\n\
+            #0 x! b0 \" 1#\n#5 1! b101 \"\n#7 1! 0# 1#\n#10 0! 0#\n";
+        let signals = parse_vcd(vcd).unwrap();
+        assert_eq!(signals.iter().map(|signal| signal.name.as_str()).collect::<Vec<_>>(), ["D0", "D0_copy", "D1"]);
+        assert_eq!(signals[0].scope, "la.probe");
+        assert!(signals[0].matches("D0") && signals[0].matches("la.probe.D0") && !signals[0].matches("probe.D0"));
+        assert_eq!(signals[0].edges, [(0.0, false), (5e-6, true), (10e-6, false)]);
+        assert_eq!(signals[1].edges, signals[0].edges);
+        // The repeated value at 7 us replaces the change - no glitch
+        assert_eq!(signals[2].edges, [(0.0, true), (10e-6, false)]);
+        assert!(parse_vcd("#0 1!").is_err());
+        assert!(parse_vcd("$timescale 1 ps").is_err());
+
+        assert_eq!(parse_edges_csv("time,level\n0,0\n0.2,1\n# comment\n\n0.3,true\n0.5,0\n"), Ok(vec![(0.0, false), (0.2, true), (0.5, false)]));
+        assert!(parse_edges_csv("0,1\n0.5,2\n").is_err());
+        assert!(parse_edges_csv("0.5,1\n0.2,0\n").is_err());
+    }
+
+    #[test]
+    fn import_edges() {
+        let dir = std::env::temp_dir().join(format!("base_streamer_import_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let csv_path = dir.join("trig.csv");
+        std::fs::write(&csv_path, "time,level\n0,0\n0.2,1\n0.201,0\n0.5,1\n").unwrap();
+        let csv_path = csv_path.to_str().unwrap();
+
+        let mut streamer = TestStreamer::new();
+        streamer.add_do_dev("DO", 1e3);
+        streamer.do_devs["DO"].add_chan("port0/line0", false);
+        let line = streamer.do_devs["DO"].chan_mut("port0/line0").unwrap();
+        assert!(matches!(line.import_edges(csv_path, Some("other")), Err(StreamerError::NotFound { .. })));
+        line.import_edges(csv_path, None).unwrap();
+        assert_eq!(line.instr_count(), 3);
+        // Overlapping replay is a collision
+        assert!(line.import_edges(csv_path, Some("trig")).is_err());
+        streamer.compile(Some(1.0)).unwrap();
+        let events = streamer.do_devs["DO"].chan("port0/line0").unwrap().compiled_events().unwrap();
+        assert_eq!(events.iter().map(|event| (event.pos, event.val)).collect::<Vec<_>>(), [(0, false), (200, true), (201, false), (500, true)]);
+
+        // Round trip through `export_vcd`, trigger delays included
+        let mut src = TestStreamer::new();
+        src.add_do_dev("DO1", 1e3);
+        src.add_do_dev("DO2", 1e3);
+        src.do_devs["DO1"].add_chan("port0/line0", false);
+        src.do_devs["DO1"].add_chan("port0/line1", true);
+        src.do_devs["DO2"].add_chan("port0/line0", false);
+        src.do_devs["DO1"].chan_mut("port0/line0").unwrap().constant(true, 0.2, Some((0.001, false))).unwrap();
+        src.do_devs["DO1"].chan_mut("port0/line1").unwrap().constant(false, 0.5, Some((0.1, false))).unwrap();
+        src.do_devs["DO2"].chan_mut("port0/line0").unwrap().constant(true, 0.2, Some((0.01, false))).unwrap();
+        src.set_trigger_delay("DO2", 0.005).unwrap();
+        src.compile(Some(1.0)).unwrap();
+        let vcd_path = dir.join("all.vcd");
+        let vcd_path = vcd_path.to_str().unwrap();
+        src.export_vcd(vcd_path).unwrap();
+
+        let mut dst = TestStreamer::new();
+        dst.add_do_dev("DO1", 1e3);
+        dst.add_do_dev("DO2", 1e3);
+        dst.add_ao_dev("AO", 1e3);
+        dst.do_devs["DO1"].add_chan("port0/line0", false);
+        dst.do_devs["DO1"].add_chan("port0/line1", true);
+        dst.do_devs["DO2"].add_chan("port0/line0", false);
+        dst.ao_devs["AO"].add_chan("ao0", 0.0);
+        dst.set_trigger_delay("DO2", 0.005).unwrap();
+        assert_eq!(dst.import_vcd(vcd_path).unwrap(), ["DO1/port0/line0", "DO1/port0/line1", "DO2/port0/line0"]);
+        dst.compile(Some(1.0)).unwrap();
+        for (dev, chan) in [("DO1", "port0/line0"), ("DO1", "port0/line1"), ("DO2", "port0/line0")] {
+            assert_eq!(
+                dst.do_devs[dev].chan(chan).unwrap().compiled_events().unwrap(),
+                src.do_devs[dev].chan(chan).unwrap().compiled_events().unwrap(),
+            );
+        }
+
+        let mut other = TestStreamer::new();
+        other.add_do_dev("DO3", 1e3);
+        other.do_devs["DO3"].add_chan("port0/line0", false);
+        assert!(matches!(other.import_vcd(vcd_path), Err(StreamerError::NotFound { .. })));
+        assert!(matches!(other.import_vcd(dir.join("missing.vcd").to_str().unwrap()), Err(StreamerError::InvalidArgument { .. })));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
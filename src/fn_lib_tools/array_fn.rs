@@ -0,0 +1,105 @@
+//! Waveform given by a buffer of samples (measured or calibrated pulses)
+
+use std::fmt::{Debug, Formatter};
+use std::str::FromStr;
+use std::sync::Arc;
+use crate::fn_lib_tools::{Calc, Describe};
+use crate::hash::StableHasher;
+
+/// Interpolation between the array samples
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Interp {
+    /// Zero-order hold - every sample is held for `dt`
+    Hold,
+    /// Linear interpolation between neighbouring samples
+    Linear,
+}
+
+impl FromStr for Interp {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hold" => Ok(Interp::Hold),
+            "linear" => Ok(Interp::Linear),
+            _ => Err(format!("Unknown interpolation \"{s}\", expected \"hold\" or \"linear\"")),
+        }
+    }
+}
+
+/// Samples `samps[i]` placed at times `t0 + i * dt`.
+///
+/// Before `t0` the first and after the last sample time the last sample is held.
+/// The samples are shared behind an `Arc`, so cloning the function (e.g. when compiling) never copies them.
+#[derive(Clone)]
+pub struct ArrayFn {
+    samps: Arc<[f64]>,
+    dt: f64,
+    interp: Interp,
+    t0: f64,
+}
+impl ArrayFn {
+    /// # Panics
+    /// If `samps` is empty or `dt` is not positive.
+    pub fn new(samps: impl Into<Arc<[f64]>>, dt: f64, interp: Interp, t0: f64) -> Self {
+        let samps = samps.into();
+        assert!(!samps.is_empty(), "ArrayFn needs at least one sample");
+        assert!(dt > 0.0, "ArrayFn sample spacing dt must be positive, got {dt}");
+        Self { samps, dt, interp, t0 }
+    }
+    pub fn n_samps(&self) -> usize {
+        self.samps.len()
+    }
+    /// Time span covered by the samples, each held for `dt`: `n_samps * dt`
+    pub fn dur(&self) -> f64 {
+        self.samps.len() as f64 * self.dt
+    }
+    /// Stable hash of the sample values, stands in for the samples in `describe()`
+    pub fn samps_hash(&self) -> u64 {
+        let mut hasher = StableHasher::new();
+        for &samp in self.samps.iter() {
+            hasher.update_f64(samp)
+        }
+        hasher.finish()
+    }
+    fn val_at(&self, t: f64) -> f64 {
+        let x = ((t - self.t0) / self.dt).max(0.0);
+        // Tolerance keeps times landing on a sample time (up to float error) on that sample
+        let idx = (x + 1e-9).floor() as usize;
+        let last = self.samps.len() - 1;
+        if idx >= last {
+            return self.samps[last]
+        }
+        match self.interp {
+            Interp::Hold => self.samps[idx],
+            Interp::Linear => {
+                let frac = (x - idx as f64).clamp(0.0, 1.0);
+                self.samps[idx] + frac * (self.samps[idx + 1] - self.samps[idx])
+            },
+        }
+    }
+}
+impl Calc<f64> for ArrayFn {
+    fn calc(&self, t_arr: &[f64], res_arr: &mut [f64]) {
+        for (res, &t) in res_arr.iter_mut().zip(t_arr.iter()) {
+            *res = self.val_at(t)
+        }
+    }
+    fn calc_one(&self, t: f64) -> f64 {
+        self.val_at(t)
+    }
+}
+impl Describe for ArrayFn {
+    fn describe(&self) -> String {
+        format!(
+            "FromArray(n_samps={}, dt={:?}, interp={:?}, t0={:?}, samps_hash={:#018x})",
+            self.samps.len(), self.dt, self.interp, self.t0, self.samps_hash()
+        )
+    }
+}
+impl Debug for ArrayFn {
+    /// Same as `describe()` - the samples themselves are only represented by their hash, so that content hashes
+    /// and diffs still see any change of the values
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.describe())
+    }
+}
@@ -0,0 +1,93 @@
+//! Reduced-rate evaluation wrapper for slowly varying functions
+
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+use crate::fn_lib_tools::{Calc, Describe, FnTraitSet};
+
+/// Evaluates the wrapped function only on every `factor`-th clock tick counting from `start_pos`
+/// and holds the value in between - sample-and-hold at `1/factor` of the channel sample rate.
+///
+/// Meant for slowly varying waveforms (e.g. multi-second exponential ramps) where computing every sample
+/// costs time without any fidelity gain. The factor stays visible in the compile cache through
+/// [`Calc::decimation_hint`], so backends which can produce held samples on their own may use it directly.
+pub struct Decimate<T> {
+    inner: Arc<dyn FnTraitSet<T>>,
+    start_pos: usize,
+    factor: usize,
+    clk_period: f64,
+}
+impl<T> Decimate<T> {
+    /// `inner` can be either a `Box` (taken over) or an `Arc` (shared).
+    ///
+    /// # Panics
+    /// If `factor` is 0.
+    pub fn new(inner: impl Into<Arc<dyn FnTraitSet<T>>>, start_pos: usize, factor: usize, clk_period: f64) -> Self {
+        assert!(factor > 0, "Decimation factor must be positive");
+        Self { inner: inner.into(), start_pos, factor, clk_period }
+    }
+    pub fn factor(&self) -> usize {
+        self.factor
+    }
+    /// The tick whose value is held at `pos`
+    fn hold_pos(&self, pos: usize) -> usize {
+        match pos.checked_sub(self.start_pos) {
+            Some(offs) => pos - offs % self.factor,
+            None => pos,
+        }
+    }
+}
+impl<T: Clone + Default + 'static> Decimate<T> {
+    /// Evaluates `inner` once per distinct hold position of `pos_arr` (sorted) and spreads the values over `res_arr`
+    fn calc_held(&self, pos_arr: impl Iterator<Item = usize>, res_arr: &mut [T]) {
+        let hold_arr: Vec<usize> = pos_arr.map(|pos| self.hold_pos(pos)).collect();
+        let mut hold_t_arr: Vec<f64> = hold_arr.iter().map(|&pos| pos as f64 * self.clk_period).collect();
+        hold_t_arr.dedup();
+        let mut held_vals = vec![T::default(); hold_t_arr.len()];
+        self.inner.calc(&hold_t_arr, &mut held_vals);
+
+        let mut val_idx = 0;
+        for (idx, res) in res_arr.iter_mut().enumerate() {
+            if idx > 0 && hold_arr[idx] != hold_arr[idx - 1] {
+                val_idx += 1;
+            }
+            *res = held_vals[val_idx].clone();
+        }
+    }
+}
+impl<T: Clone + Default + 'static> Calc<T> for Decimate<T> {
+    fn calc(&self, t_arr: &[f64], res_arr: &mut [T]) {
+        let pos_arr = t_arr.iter().map(|t| (t / self.clk_period).round().max(0.0) as usize);
+        self.calc_held(pos_arr, res_arr)
+    }
+    fn calc_one(&self, t: f64) -> T {
+        let pos = (t / self.clk_period).round().max(0.0) as usize;
+        self.inner.calc_one(self.hold_pos(pos) as f64 * self.clk_period)
+    }
+    fn const_val(&self) -> Option<T> {
+        self.inner.const_val()
+    }
+    fn calc_from_ticks(&self, start_pos: usize, _clk_period: f64, res_arr: &mut [T]) {
+        self.calc_held(start_pos..start_pos + res_arr.len(), res_arr)
+    }
+    fn decimation_hint(&self) -> Option<usize> {
+        Some(self.factor)
+    }
+}
+impl<T> Clone for Decimate<T> {
+    fn clone(&self) -> Self {
+        Self::new(Arc::clone(&self.inner), self.start_pos, self.factor, self.clk_period)
+    }
+}
+impl<T> Describe for Decimate<T> {
+    fn describe(&self) -> String {
+        format!("Decimate(inner={}, factor={})", self.inner.describe(), self.factor)
+    }
+}
+impl<T> Debug for Decimate<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f, "Decimate(inner={:?}, start_pos={}, factor={}, clk_period={:?})",
+            self.inner, self.start_pos, self.factor, self.clk_period
+        )
+    }
+}
@@ -0,0 +1,105 @@
+//! Complex (IQ) functions driving a pair of `f64` channels
+
+use std::f64::consts::PI;
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+use num_complex::Complex64;
+use crate::fn_lib_tools::{Calc, Describe, FnTraitSet};
+
+/// Which component of a complex function an [`IqPart`] outputs
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Quadrature {
+    /// In-phase - real part
+    I,
+    /// Quadrature - imaginary part
+    Q,
+}
+
+/// Real or imaginary part of a shared complex function.
+///
+/// `BaseDev::add_iq_instr` inserts one `IqPart` on the I and one on the Q channel, both holding the same `inner`,
+/// so the two outputs always come from one coherent complex waveform. Each part evaluates `inner` on its own channel.
+pub struct IqPart {
+    inner: Arc<dyn FnTraitSet<Complex64>>,
+    quad: Quadrature,
+}
+impl IqPart {
+    /// `inner` can be either a `Box` (taken over) or an `Arc` (shared).
+    pub fn new(inner: impl Into<Arc<dyn FnTraitSet<Complex64>>>, quad: Quadrature) -> Self {
+        Self { inner: inner.into(), quad }
+    }
+    pub fn quad(&self) -> Quadrature {
+        self.quad
+    }
+    fn part(&self, val: Complex64) -> f64 {
+        match self.quad {
+            Quadrature::I => val.re,
+            Quadrature::Q => val.im,
+        }
+    }
+}
+impl Calc<f64> for IqPart {
+    fn calc(&self, t_arr: &[f64], res_arr: &mut [f64]) {
+        let mut vals = vec![Complex64::default(); t_arr.len()];
+        self.inner.calc(t_arr, &mut vals);
+        for (res, val) in res_arr.iter_mut().zip(vals) {
+            *res = self.part(val)
+        }
+    }
+    fn calc_one(&self, t: f64) -> f64 {
+        self.part(self.inner.calc_one(t))
+    }
+    fn const_val(&self) -> Option<f64> {
+        self.inner.const_val().map(|val| self.part(val))
+    }
+    fn calc_from_ticks(&self, start_pos: usize, clk_period: f64, res_arr: &mut [f64]) {
+        let mut vals = vec![Complex64::default(); res_arr.len()];
+        self.inner.calc_from_ticks(start_pos, clk_period, &mut vals);
+        for (res, val) in res_arr.iter_mut().zip(vals) {
+            *res = self.part(val)
+        }
+    }
+}
+impl Clone for IqPart {
+    fn clone(&self) -> Self {
+        Self::new(Arc::clone(&self.inner), self.quad)
+    }
+}
+impl Describe for IqPart {
+    fn describe(&self) -> String {
+        format!("IqPart(inner={}, quad={:?})", self.inner.describe(), self.quad)
+    }
+}
+impl Debug for IqPart {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "IqPart(inner={:?}, quad={:?})", self.inner, self.quad)
+    }
+}
+
+/// Complex tone for single-sideband modulation:
+/// `IqTone(t) = amp * exp[i * (2Pi * freq * t + phase)]`
+///
+/// I gets `amp * cos(...)` and Q gets `amp * sin(...)`. The sign of `freq` selects the sideband.
+#[derive(Clone, Debug)]
+pub struct IqTone {
+    amp: f64,
+    freq: f64,
+    phase: f64,
+}
+impl IqTone {
+    pub fn new(amp: f64, freq: f64, phase: f64) -> Self {
+        Self { amp, freq, phase }
+    }
+}
+impl Calc<Complex64> for IqTone {
+    fn calc(&self, t_arr: &[f64], res_arr: &mut [Complex64]) {
+        for (res, &t) in res_arr.iter_mut().zip(t_arr.iter()) {
+            *res = Complex64::from_polar(self.amp, 2.0*PI * self.freq * t + self.phase)
+        }
+    }
+}
+impl Describe for IqTone {
+    fn describe(&self) -> String {
+        format!("IqTone(amp={:?}, freq={:?}, phase={:?})", self.amp, self.freq, self.phase)
+    }
+}
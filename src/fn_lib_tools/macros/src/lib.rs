@@ -70,6 +70,19 @@ fn lib_fn_macro_base(target_lib: &str, samp_type: &str, attr_tokens: TokenStream
     };
     // println!("impl_pub_fn_new_tokens: \n{impl_pub_fn_new_tokens}\n");
 
+    // "Name(field_1={:?}, field_2={:?}, ...)"
+    let describe_fmt_str = format!(
+        "{struct_ident}({})",
+        field_idents.iter().map(|ident_| format!("{ident_}={{:?}}")).collect::<Vec<_>>().join(", ")
+    );
+    let impl_describe_tokens = quote!{
+        impl Describe for #struct_ident {
+            fn describe(&self) -> String {
+                format!(#describe_fmt_str, #(self.#field_idents),*)
+            }
+        }
+    };
+
     let pyo3_sig_tokens = if attr_tokens.is_empty() {
         quote!{#(#field_idents),*}
     } else {
@@ -105,6 +118,8 @@ fn lib_fn_macro_base(target_lib: &str, samp_type: &str, attr_tokens: TokenStream
 
         #impl_pub_fn_new_tokens
 
+        #impl_describe_tokens
+
         #pymethods_impl_target_lib_tokens
     };
     if cfg!(feature = "debug_token_print") {
@@ -125,6 +140,16 @@ pub fn usr_fn_bool(attr_tokens: TokenStream, input_tokens: TokenStream) -> Token
     lib_fn_macro_base("UsrFnLib", "Bool", attr_tokens, input_tokens)
 }
 
+#[proc_macro_attribute]
+pub fn usr_fn_u8(attr_tokens: TokenStream, input_tokens: TokenStream) -> TokenStream {
+    lib_fn_macro_base("UsrFnLib", "U8", attr_tokens, input_tokens)
+}
+
+#[proc_macro_attribute]
+pub fn usr_fn_u16(attr_tokens: TokenStream, input_tokens: TokenStream) -> TokenStream {
+    lib_fn_macro_base("UsrFnLib", "U16", attr_tokens, input_tokens)
+}
+
 #[proc_macro_attribute]
 pub fn std_fn_f64(attr_tokens: TokenStream, input_tokens: TokenStream) -> TokenStream {
     lib_fn_macro_base("StdFnLib", "F64", attr_tokens, input_tokens)
@@ -133,4 +158,8 @@ pub fn std_fn_f64(attr_tokens: TokenStream, input_tokens: TokenStream) -> TokenS
 #[proc_macro_attribute]
 pub fn std_fn_bool(attr_tokens: TokenStream, input_tokens: TokenStream) -> TokenStream {
     lib_fn_macro_base("StdFnLib", "Bool", attr_tokens, input_tokens)
-}
\ No newline at end of file
+}
+#[proc_macro_attribute]
+pub fn std_fn_u16(attr_tokens: TokenStream, input_tokens: TokenStream) -> TokenStream {
+    lib_fn_macro_base("StdFnLib", "U16", attr_tokens, input_tokens)
+}
@@ -1,21 +1,106 @@
 use pyo3::prelude::*;
 
 mod std_fn_lib;
-pub use std_fn_lib::StdFnLib;
+pub use std_fn_lib::{BitPattern, CounterBit, LinFn, StdFnLib};
+mod time_map;
+pub use time_map::TimeMap;
+mod repeat;
+pub use repeat::Repeat;
+mod decimate;
+pub use decimate::Decimate;
+mod array_fn;
+pub use array_fn::{ArrayFn, Interp};
+mod iq;
+mod registry;
+pub use registry::{FnArgs, FnRegistry};
+pub use iq::{IqPart, IqTone, Quadrature};
+pub use num_complex::Complex64;
+pub mod simd;
 use std::fmt::Debug;
 
 pub mod usr_lib_prelude;
 
+/// Number of time points generated at once by the default [`Calc::calc_from_ticks`] (fits on the stack and in L1 cache)
+pub const TICK_BLOCK: usize = 256;
+
 pub trait Calc<T> {
     fn calc(&self, t_arr: &[f64], res_arr: &mut [T]);
+
+    /// Evaluates the function at a single time point.
+    ///
+    /// Used for single-sample evaluation in tight loops (`eval_point`, keep_val padding in `compile`).
+    /// The default falls back to `calc` on a one-element slice; functions with a cheap closed form override it.
+    /// Being part of `Calc`, it is also available on every `dyn FnTraitSet<T>`.
+    fn calc_one(&self, t: f64) -> T
+        where T: Default
+    {
+        let mut res_arr = [T::default()];
+        self.calc(&[t], &mut res_arr);
+        let [res] = res_arr;
+        res
+    }
+
+    /// Returns `Some(val)` if the function is the constant `val` at every time point, `None` otherwise.
+    ///
+    /// Comparison hook for `compile`, which merges adjacent compile cache segments holding the same constant.
+    /// The default `None` is always safe - it only disables merging for this function.
+    fn const_val(&self) -> Option<T> {
+        None
+    }
+
+    /// Evaluates the function on the clock grid `t_i = (start_pos + i) * clk_period` for `i in 0..res_arr.len()`.
+    ///
+    /// Unlike `calc`, no time array has to be materialized by the caller.
+    /// The default generates the times in stack blocks of [`TICK_BLOCK`] points and passes them to `calc`.
+    /// Functions which can derive `t` from the index directly (or don't depend on `t` at all) override it.
+    fn calc_from_ticks(&self, start_pos: usize, clk_period: f64, res_arr: &mut [T]) {
+        let mut t_buf = [0.0; TICK_BLOCK];
+        for (block_idx, res_block) in res_arr.chunks_mut(TICK_BLOCK).enumerate() {
+            let block_start = start_pos + block_idx * TICK_BLOCK;
+            let t_block = &mut t_buf[..res_block.len()];
+            for (offs, t) in t_block.iter_mut().enumerate() {
+                *t = (block_start + offs) as f64 * clk_period
+            }
+            self.calc(t_block, res_block)
+        }
+    }
+
+    /// Instantaneous phase (in radians) at time `t` for oscillatory functions, `None` for everything else.
+    ///
+    /// Together with [`Calc::with_start_phase`], used by `compile` to chain phase-linked instructions
+    /// (see `BaseChan::add_instr_phase_linked`).
+    fn phase_at(&self, _t: f64) -> Option<f64> {
+        None
+    }
+
+    /// Copy of the function with its phase offset chosen such that `phase_at(t) == phase`.
+    /// `None` if the function has no phase.
+    fn with_start_phase(&self, _t: f64, _phase: f64) -> Option<Box<dyn FnTraitSet<T>>> {
+        None
+    }
+
+    /// `Some(factor)` if the function is only computed on every `factor`-th clock tick and held in between (see [`Decimate`]).
+    ///
+    /// Pure hint for backends reading the compile cache - the samples produced by `calc` / `calc_from_ticks` already are the held ones.
+    fn decimation_hint(&self) -> Option<usize> {
+        None
+    }
+}
+
+/// Short human-readable form of a function with its parameters, e.g. `Sine(amp=1.0, freq=5000.0, phase=0.0, offs=0.0)`.
+///
+/// Generated by the `std_fn_*` / `usr_fn_*` macros. Wrappers describe their inner function recursively,
+/// so nested functions stay readable where the derived `Debug` output would not.
+pub trait Describe {
+    fn describe(&self) -> String;
 }
 
-pub trait FnTraitSet<T>: Calc<T> + Debug + Send + Sync {
+pub trait FnTraitSet<T>: Calc<T> + Describe + Debug + Send + Sync {
     fn clone_to_box(&self) -> Box<dyn FnTraitSet<T>>;
 }
 
 impl<S, T> FnTraitSet<T> for S
-    where S: Calc<T> + Clone + Debug + Send + Sync + 'static
+    where S: Calc<T> + Describe + Clone + Debug + Send + Sync + 'static
 {
     fn clone_to_box(&self) -> Box<dyn FnTraitSet<T>> {
         Box::new(self.clone())
@@ -38,4 +123,76 @@ pub struct FnBoxF64 {
 #[derive(Clone)]
 pub struct FnBoxBool {
     pub inner: Box<dyn FnTraitSet<bool>>
-}
\ No newline at end of file
+}
+
+/// Word-valued function for 8-bit digital bus channels
+#[pyclass]
+#[derive(Clone)]
+pub struct FnBoxU8 {
+    pub inner: Box<dyn FnTraitSet<u8>>
+}
+
+/// Word-valued function for 16-bit digital bus channels (e.g. DDS profile words)
+#[pyclass]
+#[derive(Clone)]
+pub struct FnBoxU16 {
+    pub inner: Box<dyn FnTraitSet<u16>>
+}
+/// Pickle support: the state of a function box is its `describe()` string, rebuilt with [`FnRegistry::global`].
+///
+/// `__new__` takes the same string, so `FnBoxF64("Sine(amp=1.0, freq=1000.0, phase=0.0, offs=0.0)")` works as well.
+/// Pickling fails early (with `IncompatibleError` / `NotFoundError`) if the global registry can't rebuild the function.
+macro_rules! impl_fn_box_pickle {
+    ($fn_box:ident, $samp:ty) => {
+        #[pymethods]
+        impl $fn_box {
+            #[new]
+            fn py_new(desc: &str) -> PyResult<Self> {
+                Ok(Self { inner: Self::rebuild(desc)? })
+            }
+            fn __getnewargs__(&self) -> PyResult<(String,)> {
+                Ok((self.__getstate__()?,))
+            }
+            fn __getstate__(&self) -> PyResult<String> {
+                let desc = self.inner.describe();
+                Self::rebuild(&desc)?;
+                Ok(desc)
+            }
+            fn __setstate__(&mut self, state: &str) -> PyResult<()> {
+                self.inner = Self::rebuild(state)?;
+                Ok(())
+            }
+            fn __repr__(&self) -> String {
+                self.inner.describe()
+            }
+        }
+
+        impl $fn_box {
+            fn rebuild(desc: &str) -> Result<Box<dyn FnTraitSet<$samp>>, crate::error::StreamerError> {
+                FnRegistry::global().read().expect("global registry lock poisoned").build_described::<$samp>(desc)
+            }
+        }
+    };
+}
+impl_fn_box_pickle!(FnBoxF64, f64);
+impl_fn_box_pickle!(FnBoxBool, bool);
+impl_fn_box_pickle!(FnBoxU8, u8);
+impl_fn_box_pickle!(FnBoxU16, u16);
+
+/// Adds the function box classes to the Python module of a downstream streamer crate.
+///
+/// Also points their `__module__` to `m`, so that pickle can find the classes when loading.
+pub fn register_fn_boxes(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    let py = m.py();
+    let module_name = m.name()?;
+    for (name, cls) in [
+        ("FnBoxF64", py.get_type_bound::<FnBoxF64>()),
+        ("FnBoxBool", py.get_type_bound::<FnBoxBool>()),
+        ("FnBoxU8", py.get_type_bound::<FnBoxU8>()),
+        ("FnBoxU16", py.get_type_bound::<FnBoxU16>()),
+    ] {
+        cls.setattr("__module__", &module_name)?;
+        m.add(name, cls)?;
+    }
+    Ok(())
+}
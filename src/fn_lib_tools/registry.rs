@@ -0,0 +1,191 @@
+//! Functions constructed by name - for schedules loaded from files and other text-based sources.
+//!
+//! Every entry has a name, a list of scalar parameters (with optional defaults), and a constructor.
+//! Entries are kept per sample type, so the same name can exist for e.g. `f64` and `bool` channels,
+//! and type-agnostic code can still pick the right one for a channel of an unknown sample type
+//! (see [`TagBaseDev::tag_add_instr_by_name`]).
+//!
+//! [`TagBaseDev::tag_add_instr_by_name`]: crate::streamer::TagBaseDev::tag_add_instr_by_name
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock, RwLock};
+use indexmap::IndexMap;
+use crate::error::{ErrCtx, StreamerError};
+use crate::fn_lib_tools::FnTraitSet;
+
+/// Function arguments - either in the order of the parameters or by parameter name
+#[derive(Clone, Debug, PartialEq)]
+pub enum FnArgs {
+    Positional(Vec<f64>),
+    Named(IndexMap<String, f64>),
+}
+
+type Ctor<T> = Box<dyn Fn(&[f64]) -> Box<dyn FnTraitSet<T>> + Send + Sync>;
+
+struct FnSpec<T> {
+    params: Vec<(&'static str, Option<f64>)>,
+    ctor: Ctor<T>,
+}
+
+pub struct FnRegistry {
+    /// `(sample type, name)` -> `FnSpec<sample type>`
+    specs: IndexMap<(TypeId, String), Box<dyn Any + Send + Sync>>,
+}
+
+impl FnRegistry {
+    /// Empty registry
+    pub fn new() -> Self {
+        Self { specs: IndexMap::new() }
+    }
+    /// Registry with the standard library functions (those of `StdFnLib` with scalar parameters) and `ConstFn`
+    pub fn std() -> Self {
+        let mut registry = Self::new();
+        super::std_fn_lib::register_std_fns(&mut registry);
+        registry
+    }
+
+    /// Process-wide registry used to restore unpickled functions (see [`crate::fn_lib_tools::FnBoxF64`] and friends).
+    ///
+    /// Starts out as [`FnRegistry::std`]. User function libraries register their functions here to make them picklable.
+    pub fn global() -> &'static RwLock<FnRegistry> {
+        static GLOBAL: OnceLock<RwLock<FnRegistry>> = OnceLock::new();
+        GLOBAL.get_or_init(|| RwLock::new(Self::std()))
+    }
+
+    /// Registers (or replaces) function `name` for channels with samples of type `T`.
+    ///
+    /// `params` lists the parameter names in order with optional defaults. `ctor` receives the values of all parameters
+    /// in the same order - missing ones already replaced with their defaults.
+    pub fn register<T: 'static>(
+        &mut self,
+        name: &str,
+        params: &[(&'static str, Option<f64>)],
+        ctor: impl Fn(&[f64]) -> Box<dyn FnTraitSet<T>> + Send + Sync + 'static
+    ) {
+        let spec: FnSpec<T> = FnSpec { params: params.to_vec(), ctor: Box::new(ctor) };
+        self.specs.insert((TypeId::of::<T>(), name.to_string()), Box::new(spec));
+    }
+
+    /// Names of the functions registered for sample type `T`
+    pub fn names<T: 'static>(&self) -> Vec<&str> {
+        self.specs.keys().filter(|(type_id, _name)| *type_id == TypeId::of::<T>()).map(|(_type_id, name)| name.as_str()).collect()
+    }
+
+    /// Constructs function `name` for sample type `T` from `args`.
+    ///
+    /// Returns [`StreamerError::NotFound`] for unknown names and [`StreamerError::InvalidArgument`] for missing,
+    /// unknown, or extra arguments.
+    pub fn build<T: 'static>(&self, name: &str, args: &FnArgs) -> Result<Box<dyn FnTraitSet<T>>, StreamerError> {
+        let spec = self.spec::<T>(name)?;
+        Ok((spec.ctor)(&self.full_args(spec, name, args, &[])?))
+    }
+
+    /// Builds `n` instances of function `name` - `args` shared by all, except the parameters named in `overrides`
+    /// which take the `idx`-th value of their list for instance `idx`. Every override list must have `n` values.
+    ///
+    /// Arguments are resolved once for the whole array and instances with identical parameters share one function object.
+    /// Returns the errors of [`FnRegistry::build`], and [`StreamerError::InvalidArgument`] for unknown override names
+    /// or lists of the wrong length.
+    pub fn build_array<T: 'static>(
+        &self, name: &str, args: &FnArgs, overrides: &IndexMap<String, Vec<f64>>, n: usize
+    ) -> Result<Vec<Arc<dyn FnTraitSet<T>>>, StreamerError> {
+        let spec = self.spec::<T>(name)?;
+        let mut override_idxs = Vec::with_capacity(overrides.len());
+        for (param, vals) in overrides {
+            let idx = spec.params.iter().position(|(spec_param, _dflt)| spec_param == param).ok_or_else(|| StreamerError::InvalidArgument {
+                ctx: ErrCtx::none(),
+                msg: format!("{name}(): unknown override \"{param}\"")
+            })?;
+            if vals.len() != n {
+                return Err(StreamerError::InvalidArgument {
+                    ctx: ErrCtx::none(),
+                    msg: format!("{name}(): got {} values of \"{param}\" for {n} instances", vals.len()),
+                })
+            }
+            override_idxs.push((idx, vals));
+        }
+        let overridden: Vec<usize> = override_idxs.iter().map(|&(idx, _vals)| idx).collect();
+        let mut base_args = self.full_args(spec, name, args, &overridden)?;
+        // Instances are keyed by the bit patterns of their arguments, so that e.g. repeated amplitudes build one function
+        let mut built: HashMap<Vec<u64>, Arc<dyn FnTraitSet<T>>> = HashMap::new();
+        let mut funcs = Vec::with_capacity(n);
+        for instance in 0..n {
+            for &(idx, vals) in override_idxs.iter() {
+                base_args[idx] = vals[instance]
+            }
+            let key = base_args.iter().map(|arg| arg.to_bits()).collect();
+            let func = built.entry(key).or_insert_with(|| Arc::from((spec.ctor)(&base_args)));
+            funcs.push(func.clone());
+        }
+        Ok(funcs)
+    }
+
+    fn spec<T: 'static>(&self, name: &str) -> Result<&FnSpec<T>, StreamerError> {
+        self.specs
+            .get(&(TypeId::of::<T>(), name.to_string()))
+            .and_then(|spec| spec.downcast_ref::<FnSpec<T>>())
+            .ok_or_else(|| StreamerError::NotFound {
+                ctx: ErrCtx::none(),
+                msg: format!(
+                    "There is no function {name} registered for {} samples. Registered functions are {:?}",
+                    std::any::type_name::<T>(), self.names::<T>()
+                ),
+            })
+    }
+
+    /// Values of all parameters of `spec` - `args` with missing ones replaced by their defaults.
+    /// Parameters at `overridden` positions may be missing, they are set by the caller.
+    fn full_args<T>(&self, spec: &FnSpec<T>, name: &str, args: &FnArgs, overridden: &[usize]) -> Result<Vec<f64>, StreamerError> {
+        let arg_err = |msg: String| StreamerError::InvalidArgument {
+            ctx: ErrCtx::none(),
+            msg: format!("{name}(): {msg}. Parameters are {:?}", spec.params.iter().map(|(param, _dflt)| param).collect::<Vec<_>>()),
+        };
+
+        let mut vals: Vec<Option<f64>> = vec![None; spec.params.len()];
+        match args {
+            FnArgs::Positional(args) => {
+                if args.len() > spec.params.len() {
+                    return Err(arg_err(format!("got {} arguments", args.len())))
+                }
+                for (val, &arg) in vals.iter_mut().zip(args) {
+                    *val = Some(arg)
+                }
+            },
+            FnArgs::Named(args) => {
+                for (arg_name, &arg) in args {
+                    let idx = spec.params.iter().position(|(param, _dflt)| param == arg_name)
+                        .ok_or_else(|| arg_err(format!("unknown argument \"{arg_name}\"")))?;
+                    vals[idx] = Some(arg)
+                }
+            },
+        }
+        let mut full_args = Vec::with_capacity(vals.len());
+        for (idx, (val, &(param, dflt))) in vals.into_iter().zip(spec.params.iter()).enumerate() {
+            if overridden.contains(&idx) {
+                full_args.push(f64::NAN);
+                continue
+            }
+            full_args.push(val.or(dflt).ok_or_else(|| arg_err(format!("missing argument \"{param}\"")))?)
+        }
+        Ok(full_args)
+    }
+
+    /// Constructs a function from its `describe()` string, e.g. `"Sine(amp=1.0, freq=1000.0, phase=0.0, offs=0.0)"`.
+    ///
+    /// Returns [`StreamerError::Incompatible`] if the description doesn't have the `"Name(param=number, ...)"` form,
+    /// otherwise the errors of [`FnRegistry::build`].
+    pub fn build_described<T: 'static>(&self, desc: &str) -> Result<Box<dyn FnTraitSet<T>>, StreamerError> {
+        let (name, args) = crate::schedule::parse_describe(desc).ok_or_else(|| StreamerError::Incompatible {
+            ctx: ErrCtx::none(),
+            msg: format!("Function {desc} can't be reconstructed from name + numeric arguments"),
+        })?;
+        self.build(&name, &FnArgs::Named(args))
+    }
+}
+
+impl Default for FnRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
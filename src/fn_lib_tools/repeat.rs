@@ -0,0 +1,97 @@
+//! Periodic repetition wrapper for boxed functions
+
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+use crate::fn_lib_tools::{Calc, Describe, FnTraitSet};
+
+/// Repeats one period of the wrapped function `n_reps` times - a pulse train or a looped block
+/// stored as a single compile cache segment instead of `n_reps` separate ones.
+///
+/// The period is `[start_pos, start_pos + period_ticks)` on the clock grid. Any position is translated into this interval
+/// modulo `period_ticks` before evaluating `inner`, so `inner` only ever sees times of the first period.
+/// The translation is done on integer clock ticks, so every repetition reproduces the first one sample by sample.
+pub struct Repeat<T> {
+    inner: Arc<dyn FnTraitSet<T>>,
+    start_pos: usize,
+    period_ticks: usize,
+    n_reps: usize,
+    clk_period: f64,
+}
+impl<T> Repeat<T> {
+    /// `inner` can be either a `Box` (taken over) or an `Arc` (shared).
+    ///
+    /// # Panics
+    /// If `period_ticks` is 0.
+    pub fn new(inner: impl Into<Arc<dyn FnTraitSet<T>>>, start_pos: usize, period_ticks: usize, n_reps: usize, clk_period: f64) -> Self {
+        assert!(period_ticks > 0, "Repeat period must be at least 1 clock tick");
+        Self { inner: inner.into(), start_pos, period_ticks, n_reps, clk_period }
+    }
+    pub fn period_ticks(&self) -> usize {
+        self.period_ticks
+    }
+    pub fn n_reps(&self) -> usize {
+        self.n_reps
+    }
+    /// Position within the first period corresponding to `pos`
+    fn fold_pos(&self, pos: usize) -> usize {
+        match pos.checked_sub(self.start_pos) {
+            Some(offs) => self.start_pos + offs % self.period_ticks,
+            // Before the first period - nothing to fold
+            None => pos,
+        }
+    }
+    /// Maps time `t` into the first period, keeping any sub-tick offset from the clock grid
+    fn fold_t(&self, t: f64) -> f64 {
+        let pos = (t / self.clk_period).round().max(0.0) as usize;
+        t + (self.fold_pos(pos) as f64 - pos as f64) * self.clk_period
+    }
+}
+impl<T> Calc<T> for Repeat<T> {
+    fn calc(&self, t_arr: &[f64], res_arr: &mut [T]) {
+        let folded_t_arr: Vec<f64> = t_arr.iter().map(|&t| self.fold_t(t)).collect();
+        self.inner.calc(&folded_t_arr, res_arr)
+    }
+    fn calc_one(&self, t: f64) -> T
+        where T: Default
+    {
+        self.inner.calc_one(self.fold_t(t))
+    }
+    fn const_val(&self) -> Option<T> {
+        self.inner.const_val()
+    }
+    /// Splits the window at period boundaries and passes every piece to `inner.calc_from_ticks` - no time array is built
+    fn calc_from_ticks(&self, start_pos: usize, clk_period: f64, res_arr: &mut [T]) {
+        let mut done = 0;
+        while done < res_arr.len() {
+            let pos = start_pos + done;
+            let folded_pos = self.fold_pos(pos);
+            // Samples left until the next period boundary
+            let left_in_period = if pos < self.start_pos {
+                self.start_pos - pos
+            } else {
+                self.start_pos + self.period_ticks - folded_pos
+            };
+            let piece_len = left_in_period.min(res_arr.len() - done);
+            self.inner.calc_from_ticks(folded_pos, clk_period, &mut res_arr[done..done + piece_len]);
+            done += piece_len;
+        }
+    }
+}
+impl<T> Clone for Repeat<T> {
+    fn clone(&self) -> Self {
+        Self::new(Arc::clone(&self.inner), self.start_pos, self.period_ticks, self.n_reps, self.clk_period)
+    }
+}
+impl<T> Describe for Repeat<T> {
+    fn describe(&self) -> String {
+        format!("Repeat(inner={}, period_ticks={}, n_reps={})", self.inner.describe(), self.period_ticks, self.n_reps)
+    }
+}
+impl<T> Debug for Repeat<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f, "Repeat(inner={:?}, start_pos={}, period_ticks={}, n_reps={}, clk_period={:?})",
+            self.inner, self.start_pos, self.period_ticks, self.n_reps, self.clk_period
+        )
+    }
+}
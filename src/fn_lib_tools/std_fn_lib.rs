@@ -1,10 +1,16 @@
 //! The library of built-in waveform functions
+// pyo3 0.22 `#[pymethods]` expansion trips this lint on hand-written `PyResult` returns
+#![allow(clippy::useless_conversion)]
 
 use pyo3::prelude::*;
+use pyo3::buffer::PyBuffer;
 use pyo3::exceptions::PyValueError;
 use std::f64::consts::PI;
-use fn_lib_macros::{std_fn_f64, std_fn_bool};
-use crate::fn_lib_tools::{Calc, FnBoxF64, FnBoxBool};
+use fn_lib_macros::{std_fn_f64, std_fn_bool, std_fn_u16};
+use crate::channel::ConstFn;
+use crate::fn_lib_tools::{ArrayFn, Calc, Describe, FnBoxF64, FnBoxBool, FnBoxU16, FnRegistry, FnTraitSet, Interp};
+#[cfg(feature = "simd")]
+use crate::fn_lib_tools::simd;
 
 #[pyclass]
 pub struct StdFnLib {}
@@ -16,6 +22,11 @@ impl StdFnLib {
         Self {}
     }
 }
+impl Default for StdFnLib {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 // region F64 functions
 /// Constant function:
@@ -28,6 +39,15 @@ impl Calc<f64> for ConstF64 {
     fn calc(&self, _t_arr: &[f64], res_arr: &mut [f64]) {
         res_arr.fill(self.val)
     }
+    fn calc_one(&self, _t: f64) -> f64 {
+        self.val
+    }
+    fn const_val(&self) -> Option<f64> {
+        Some(self.val)
+    }
+    fn calc_from_ticks(&self, _start_pos: usize, _clk_period: f64, res_arr: &mut [f64]) {
+        res_arr.fill(self.val)
+    }
 }
 
 /// Linear function:
@@ -38,11 +58,21 @@ pub struct LinFn {
     offs: f64,
 }
 impl Calc<f64> for LinFn {
+    #[cfg(not(feature = "simd"))]
     fn calc(&self, t_arr: &[f64], res_arr: &mut[f64]) {
         for (res, &t) in res_arr.iter_mut().zip(t_arr.iter()) {
             *res = self.slope * t + self.offs
         }
     }
+    #[cfg(feature = "simd")]
+    fn calc(&self, t_arr: &[f64], res_arr: &mut[f64]) {
+        simd::lin_fn(self.slope, self.offs, t_arr, res_arr)
+    }
+    fn calc_from_ticks(&self, start_pos: usize, clk_period: f64, res_arr: &mut [f64]) {
+        for (offs, res) in res_arr.iter_mut().enumerate() {
+            *res = self.slope * ((start_pos + offs) as f64 * clk_period) + self.offs
+        }
+    }
 }
 
 /// Sine function:
@@ -59,11 +89,29 @@ pub struct Sine {
     offs: f64,
 }
 impl Calc<f64> for Sine {
+    #[cfg(not(feature = "simd"))]
     fn calc(&self, t_arr: &[f64], res_arr: &mut[f64]) {
         for (res, &t) in res_arr.iter_mut().zip(t_arr.iter()) {
             *res = self.offs + self.amp * f64::sin(2.0*PI * self.freq * t + self.phase)
         }
     }
+    #[cfg(feature = "simd")]
+    fn calc(&self, t_arr: &[f64], res_arr: &mut[f64]) {
+        // Argument in cycles: freq * t + phase / 2Pi
+        let phase_cycles = self.phase / (2.0*PI);
+        for (res, &t) in res_arr.iter_mut().zip(t_arr.iter()) {
+            *res = self.freq * t + phase_cycles
+        }
+        simd::sin_cycles_inplace(res_arr);
+        simd::scale_offs_inplace(self.amp, self.offs, res_arr)
+    }
+    fn phase_at(&self, t: f64) -> Option<f64> {
+        Some(2.0*PI * self.freq * t + self.phase)
+    }
+    fn with_start_phase(&self, t: f64, phase: f64) -> Option<Box<dyn FnTraitSet<f64>>> {
+        let phase = (phase - 2.0*PI * self.freq * t).rem_euclid(2.0*PI);
+        Some(Box::new(Self { phase, ..self.clone() }))
+    }
 }
 
 /// Gaussian function:
@@ -76,6 +124,7 @@ pub struct Gaussian {
     offs: f64,
 }
 impl Calc<f64> for Gaussian {
+    #[cfg(not(feature = "simd"))]
     fn calc(&self, t_arr: &[f64], res_arr: &mut [f64]) {
         let denominator = 2.0 * self.sigma.powi(2);
         for (res, &t) in res_arr.iter_mut().zip(t_arr.iter()) {
@@ -84,6 +133,15 @@ impl Calc<f64> for Gaussian {
             )
         }
     }
+    #[cfg(feature = "simd")]
+    fn calc(&self, t_arr: &[f64], res_arr: &mut [f64]) {
+        let denominator = 2.0 * self.sigma.powi(2);
+        for (res, &t) in res_arr.iter_mut().zip(t_arr.iter()) {
+            *res = -(t - self.t0).powi(2) / denominator
+        }
+        simd::exp_inplace(res_arr);
+        simd::scale_offs_inplace(self.scale, self.offs, res_arr)
+    }
 }
 
 /// Lorentzian function:
@@ -131,11 +189,20 @@ pub struct Exp {
     offs: f64
 }
 impl Calc<f64> for Exp {
+    #[cfg(not(feature = "simd"))]
     fn calc(&self, t_arr: &[f64], res_arr: &mut [f64]) {
         for (res, &t) in res_arr.iter_mut().zip(t_arr.iter()) {
             *res = self.offs + self.scale * f64::exp(t / self.tau)
         }
     }
+    #[cfg(feature = "simd")]
+    fn calc(&self, t_arr: &[f64], res_arr: &mut [f64]) {
+        for (res, &t) in res_arr.iter_mut().zip(t_arr.iter()) {
+            *res = t / self.tau
+        }
+        simd::exp_inplace(res_arr);
+        simd::scale_offs_inplace(self.scale, self.offs, res_arr)
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -147,6 +214,11 @@ impl Poly {
         Self { prms }
     }
 }
+impl Describe for Poly {
+    fn describe(&self) -> String {
+        format!("Poly(prms={:?})", self.prms)
+    }
+}
 #[pymethods]
 impl StdFnLib {
     #[allow(non_snake_case)]
@@ -196,6 +268,78 @@ impl Calc<f64> for Pow {
         }
     }
 }
+/// Linear frequency ramp for DDS frequency channels (in Hz):
+///     t0 - ramp start time (in seconds)
+///     dur - ramp duration (in seconds)
+///     f_start - frequency before and at `t0`
+///     f_stop - frequency at and after `t0 + dur`
+#[std_fn_f64(t0, dur, f_start, f_stop)]
+pub struct FreqRamp {
+    t0: f64,
+    dur: f64,
+    f_start: f64,
+    f_stop: f64,
+}
+impl Calc<f64> for FreqRamp {
+    fn calc(&self, t_arr: &[f64], res_arr: &mut [f64]) {
+        for (res, &t) in res_arr.iter_mut().zip(t_arr.iter()) {
+            let frac = if self.dur > 0.0 { ((t - self.t0) / self.dur).clamp(0.0, 1.0) } else { (t >= self.t0) as u8 as f64 };
+            *res = self.f_start + frac * (self.f_stop - self.f_start)
+        }
+    }
+    fn const_val(&self) -> Option<f64> {
+        (self.f_start == self.f_stop).then_some(self.f_start)
+    }
+}
+
+/// Phase step for DDS phase channels (in radians):
+///     t0 - step time (in seconds)
+///     phase_before - phase before `t0`
+///     phase_after - phase at and after `t0`
+#[std_fn_f64(t0, phase_before, phase_after)]
+pub struct PhaseStep {
+    t0: f64,
+    phase_before: f64,
+    phase_after: f64,
+}
+impl Calc<f64> for PhaseStep {
+    fn calc(&self, t_arr: &[f64], res_arr: &mut [f64]) {
+        for (res, &t) in res_arr.iter_mut().zip(t_arr.iter()) {
+            *res = if t < self.t0 { self.phase_before } else { self.phase_after }
+        }
+    }
+    fn const_val(&self) -> Option<f64> {
+        (self.phase_before == self.phase_after).then_some(self.phase_after)
+    }
+}
+#[pymethods]
+impl StdFnLib {
+    #[allow(non_snake_case)]
+    /// Waveform from a 1D buffer of `float64` samples (numpy array, `array.array('d')`, ...):
+    ///     arr - samples, `arr[i]` is placed at `t0 + i * dt`
+    ///     dt - sample spacing (in seconds)
+    ///     interp - "linear" or "hold" (zero-order hold)
+    ///     t0 - time of the first sample (in seconds)
+    /// Before `t0` the first and after the last sample the last value is held.
+    /// The samples are copied once, since the Python array may be changed after the call.
+    #[pyo3(signature = (arr, dt, interp="linear", t0=0.0))]
+    pub fn FromArray(&self, py: Python<'_>, arr: &Bound<'_, PyAny>, dt: f64, interp: &str, t0: f64) -> PyResult<FnBoxF64> {
+        let interp: Interp = interp.parse().map_err(PyValueError::new_err)?;
+        if !dt.is_finite() || dt <= 0.0 {
+            return Err(PyValueError::new_err(format!("Sample spacing dt must be positive and finite, got {dt}")))
+        }
+        let buf = PyBuffer::<f64>::get_bound(arr)?;
+        if buf.dimensions() != 1 {
+            return Err(PyValueError::new_err(format!("Expected a 1D array, got {} dimensions", buf.dimensions())))
+        }
+        let samps = buf.to_vec(py)?;
+        buf.release(py);
+        if samps.is_empty() {
+            return Err(PyValueError::new_err("Empty sample array passed"))
+        }
+        Ok(FnBoxF64 { inner: Box::new(ArrayFn::new(samps, dt, interp, t0)) })
+    }
+}
 // endregion
 
 // region Bool functions
@@ -209,5 +353,186 @@ impl Calc<bool> for ConstBool {
     fn calc(&self, _t_arr: &[f64], res_arr: &mut [bool]) {
         res_arr.fill(self.val)
     }
+    fn calc_one(&self, _t: f64) -> bool {
+        self.val
+    }
+    fn const_val(&self) -> Option<bool> {
+        Some(self.val)
+    }
+    fn calc_from_ticks(&self, _start_pos: usize, _clk_period: f64, res_arr: &mut [bool]) {
+        res_arr.fill(self.val)
+    }
+}
+
+/// Bit `bit` of a counter incremented every `period` seconds starting from 0 at `t0`:
+///     bit - bit index, 0 is the least significant bit
+///     period - time between counts (in seconds)
+///     t0 - time of count 0 (in seconds)
+///     gray - use the Gray code of the count, so only one bit changes per step
+/// Before `t0` the count is held at 0. A counter wraps around on its own once all bits of a channel set are high.
+#[std_fn_bool(bit, period, t0=0.0, gray=false)]
+pub struct CounterBit {
+    bit: u32,
+    period: f64,
+    t0: f64,
+    gray: bool,
+}
+impl CounterBit {
+    fn bit_at(&self, t: f64) -> bool {
+        // Same boundary tolerance as `WordSequence`
+        let count = ((t - self.t0) / self.period + 1e-9).floor().max(0.0) as u64;
+        let code = if self.gray { count ^ (count >> 1) } else { count };
+        self.bit < u64::BITS && (code >> self.bit) & 1 == 1
+    }
+}
+impl Calc<bool> for CounterBit {
+    fn calc(&self, t_arr: &[f64], res_arr: &mut [bool]) {
+        for (res, &t) in res_arr.iter_mut().zip(t_arr.iter()) {
+            *res = self.bit_at(t)
+        }
+    }
+    fn calc_one(&self, t: f64) -> bool {
+        self.bit_at(t)
+    }
+}
+
+/// Serial bit stream, e.g. a data word shifted out over SPI:
+///     t0 - start of the first bit (in seconds)
+///     bit_period - duration of every bit (in seconds)
+///     bits - the bits in order of output
+///     idle - level before `t0` and after the last bit
+#[std_fn_bool(t0, bit_period, bits, idle=false)]
+pub struct BitPattern {
+    t0: f64,
+    bit_period: f64,
+    bits: Vec<bool>,
+    idle: bool,
+}
+impl BitPattern {
+    fn bit_at(&self, t: f64) -> bool {
+        // Same boundary tolerance as `WordSequence`
+        let rel = (t - self.t0) / self.bit_period + 1e-9;
+        if rel < 0.0 {
+            return self.idle
+        }
+        self.bits.get(rel.floor() as usize).copied().unwrap_or(self.idle)
+    }
+}
+impl Calc<bool> for BitPattern {
+    fn calc(&self, t_arr: &[f64], res_arr: &mut [bool]) {
+        for (res, &t) in res_arr.iter_mut().zip(t_arr.iter()) {
+            *res = self.bit_at(t)
+        }
+    }
+    fn calc_one(&self, t: f64) -> bool {
+        self.bit_at(t)
+    }
+    fn const_val(&self) -> Option<bool> {
+        self.bits.iter().all(|&bit| bit == self.idle).then_some(self.idle)
+    }
 }
 // endregion
+
+// region Word functions
+/// Constant bus word:
+///     val - word
+#[std_fn_u16]
+pub struct ConstWord {
+    val: u16
+}
+impl Calc<u16> for ConstWord {
+    fn calc(&self, _t_arr: &[f64], res_arr: &mut [u16]) {
+        res_arr.fill(self.val)
+    }
+    fn calc_one(&self, _t: f64) -> u16 {
+        self.val
+    }
+    fn const_val(&self) -> Option<u16> {
+        Some(self.val)
+    }
+    fn calc_from_ticks(&self, _start_pos: usize, _clk_period: f64, res_arr: &mut [u16]) {
+        res_arr.fill(self.val)
+    }
+}
+
+/// Sequence of bus words, each held for `step` seconds starting from `t0`:
+///     words - the words in order of output
+///     t0 - time of the first word (in seconds)
+///     step - hold time of every word (in seconds)
+/// Before `t0` the first and after the sequence end the last word is held.
+#[std_fn_u16(words, t0, step)]
+pub struct WordSequence {
+    words: Vec<u16>,
+    t0: f64,
+    step: f64,
+}
+impl WordSequence {
+    fn word_at(&self, t: f64) -> u16 {
+        // Tolerance keeps times landing exactly on a step boundary (up to float error) on the new word
+        let idx = ((t - self.t0) / self.step + 1e-9).floor().max(0.0) as usize;
+        self.words.get(idx).or(self.words.last()).copied().unwrap_or_default()
+    }
+}
+impl Calc<u16> for WordSequence {
+    fn calc(&self, t_arr: &[f64], res_arr: &mut [u16]) {
+        for (res, &t) in res_arr.iter_mut().zip(t_arr.iter()) {
+            *res = self.word_at(t)
+        }
+    }
+    fn calc_one(&self, t: f64) -> u16 {
+        self.word_at(t)
+    }
+    fn const_val(&self) -> Option<u16> {
+        match self.words.first() {
+            Some(&first) if self.words.iter().all(|&word| word == first) => Some(first),
+            _ => None,
+        }
+    }
+}
+// endregion
+
+/// Registers the functions with scalar parameters (see [`FnRegistry::std`])
+pub(crate) fn register_std_fns(registry: &mut FnRegistry) {
+    registry.register::<f64>("ConstF64", &[("val", None)], |a| Box::new(ConstF64::new(a[0])));
+    registry.register::<f64>("LinFn", &[("slope", None), ("offs", None)], |a| Box::new(LinFn::new(a[0], a[1])));
+    registry.register::<f64>(
+        "Sine", &[("amp", None), ("freq", None), ("phase", Some(0.0)), ("offs", Some(0.0))],
+        |a| Box::new(Sine::new(a[0], a[1], a[2], a[3]))
+    );
+    registry.register::<f64>(
+        "Gaussian", &[("t0", None), ("sigma", None), ("scale", None), ("offs", Some(0.0))],
+        |a| Box::new(Gaussian::new(a[0], a[1], a[2], a[3]))
+    );
+    registry.register::<f64>(
+        "Lorentzian", &[("t0", None), ("tau", None), ("scale", None), ("offs", Some(0.0))],
+        |a| Box::new(Lorentzian::new(a[0], a[1], a[2], a[3]))
+    );
+    registry.register::<f64>(
+        "TanH", &[("t0", None), ("tau", None), ("scale", None), ("offs", Some(0.0))],
+        |a| Box::new(TanH::new(a[0], a[1], a[2], a[3]))
+    );
+    registry.register::<f64>("Exp", &[("tau", None), ("scale", None), ("offs", Some(0.0))], |a| Box::new(Exp::new(a[0], a[1], a[2])));
+    registry.register::<f64>(
+        "Pow", &[("t0", None), ("pow", None), ("scale", None), ("offs", Some(0.0))],
+        |a| Box::new(Pow::new(a[0], a[1], a[2], a[3]))
+    );
+    registry.register::<f64>(
+        "FreqRamp", &[("t0", None), ("dur", None), ("f_start", None), ("f_stop", None)],
+        |a| Box::new(FreqRamp::new(a[0], a[1], a[2], a[3]))
+    );
+    registry.register::<f64>(
+        "PhaseStep", &[("t0", None), ("phase_before", None), ("phase_after", None)],
+        |a| Box::new(PhaseStep::new(a[0], a[1], a[2]))
+    );
+    // Non-zero is `true`
+    registry.register::<bool>("ConstBool", &[("val", None)], |a| Box::new(ConstBool::new(a[0] != 0.0)));
+    registry.register::<bool>(
+        "CounterBit", &[("bit", None), ("period", None), ("t0", Some(0.0)), ("gray", Some(0.0))],
+        |a| Box::new(CounterBit::new(a[0] as u32, a[1], a[2], a[3] != 0.0))
+    );
+    registry.register::<u16>("ConstWord", &[("val", None)], |a| Box::new(ConstWord::new(a[0] as u16)));
+    // Constants created by `BaseChan::constant()` and friends, so exported schedules can be read back
+    registry.register::<f64>("ConstFn", &[("val", None)], |a| Box::new(ConstFn::new(a[0])));
+    registry.register::<bool>("ConstFn", &[("val", None)], |a| Box::new(ConstFn::new(a[0] != 0.0)));
+    registry.register::<u16>("ConstFn", &[("val", None)], |a| Box::new(ConstFn::new(a[0] as u16)));
+}
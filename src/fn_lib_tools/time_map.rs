@@ -0,0 +1,72 @@
+//! Time-argument transformation wrapper for boxed functions
+
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+use crate::fn_lib_tools::{Calc, Describe, FnTraitSet};
+
+/// Evaluates the wrapped function at affinely transformed times:
+/// `TimeMap(t) = inner(scale * t + offs)`
+///
+/// This is how already constructed functions are re-timed without knowing their parameters.
+/// For example, moving an instruction `dt` later in time wraps its function as `TimeMap::shift(func, dt)`
+/// so that the produced waveform moves together with the instruction interval.
+///
+/// The wrapped function is shared (`Arc`), so wrapping and cloning never copy its data.
+pub struct TimeMap<T> {
+    inner: Arc<dyn FnTraitSet<T>>,
+    scale: f64,
+    offs: f64,
+}
+impl<T> TimeMap<T> {
+    /// `inner` can be either a `Box` (taken over) or an `Arc` (shared)
+    pub fn new(inner: impl Into<Arc<dyn FnTraitSet<T>>>, scale: f64, offs: f64) -> Self {
+        Self { inner: inner.into(), scale, offs }
+    }
+    /// Wrapper producing the same waveform delayed by `dt`: `inner(t - dt)`
+    pub fn shift(inner: impl Into<Arc<dyn FnTraitSet<T>>>, dt: f64) -> Self {
+        Self::new(inner, 1.0, -dt)
+    }
+    /// Wrapper playing the waveform backwards, mirrored around `t_mirror / 2`: `inner(t_mirror - t)`
+    pub fn mirror(inner: impl Into<Arc<dyn FnTraitSet<T>>>, t_mirror: f64) -> Self {
+        Self::new(inner, -1.0, t_mirror)
+    }
+}
+impl<T: 'static> Calc<T> for TimeMap<T> {
+    fn calc(&self, t_arr: &[f64], res_arr: &mut [T]) {
+        let mapped_t_arr: Vec<f64> = t_arr.iter().map(|t| self.scale * t + self.offs).collect();
+        self.inner.calc(&mapped_t_arr, res_arr)
+    }
+    fn calc_one(&self, t: f64) -> T
+        where T: Default
+    {
+        self.inner.calc_one(self.scale * t + self.offs)
+    }
+    fn const_val(&self) -> Option<T> {
+        self.inner.const_val()
+    }
+    fn phase_at(&self, t: f64) -> Option<f64> {
+        self.inner.phase_at(self.scale * t + self.offs)
+    }
+    fn with_start_phase(&self, t: f64, phase: f64) -> Option<Box<dyn FnTraitSet<T>>> {
+        let inner = self.inner.with_start_phase(self.scale * t + self.offs, phase)?;
+        Some(Box::new(Self::new(inner, self.scale, self.offs)))
+    }
+    fn decimation_hint(&self) -> Option<usize> {
+        self.inner.decimation_hint()
+    }
+}
+impl<T> Clone for TimeMap<T> {
+    fn clone(&self) -> Self {
+        Self::new(Arc::clone(&self.inner), self.scale, self.offs)
+    }
+}
+impl<T> Describe for TimeMap<T> {
+    fn describe(&self) -> String {
+        format!("TimeMap(inner={}, scale={:?}, offs={:?})", self.inner.describe(), self.scale, self.offs)
+    }
+}
+impl<T> Debug for TimeMap<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "TimeMap(inner={:?}, scale={:?}, offs={:?})", self.inner, self.scale, self.offs)
+    }
+}
@@ -1,4 +1,4 @@
 pub use pyo3::prelude::*;
 
-pub use crate::fn_lib_tools::{Calc, FnBoxF64, FnBoxBool};
-pub use fn_lib_macros::{usrlib_boilerplate, usr_fn_f64, usr_fn_bool};
\ No newline at end of file
+pub use crate::fn_lib_tools::{Calc, Complex64, Describe, FnBoxF64, FnBoxBool, FnBoxU8, FnBoxU16};
+pub use fn_lib_macros::{usrlib_boilerplate, usr_fn_f64, usr_fn_bool, usr_fn_u8, usr_fn_u16};
\ No newline at end of file
@@ -0,0 +1,288 @@
+//! Golden-waveform regression testing (feature `test-utils`).
+//!
+//! A [`Snapshot`] holds the complete compiled sample stream of every active channel of a streamer. It can be saved
+//! to a compressed reference file and later compared against a fresh compile with a tolerance, which locks in the
+//! behavior of a sequence across refactors of the sequence code, of a function library, or of this crate.
+//!
+//! [`assert_golden`] wraps the usual workflow for tests: the first run (or any run with the environment variable
+//! [`BLESS_VAR`] set) writes the reference file, later runs compare against it and panic with a [`GoldenReport`]
+//! on mismatch.
+//!
+//! Reference files start with [`MAGIC`] followed by a deflate-compressed little-endian payload: the channel count,
+//! then per channel its `"<device>/<channel>"` key, sample rate, sample count, and samples as `f64`.
+
+use std::fmt;
+use std::fmt::Display;
+use std::fs;
+use std::io;
+use std::io::{Read, Write};
+use std::path::Path;
+use flate2::Compression;
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use indexmap::IndexMap;
+use crate::error::StreamerError;
+use crate::streamer::BaseStreamer;
+
+/// First bytes of every reference file
+pub const MAGIC: &[u8; 8] = b"BSGOLD1\n";
+/// Environment variable which makes [`assert_golden`] overwrite reference files instead of comparing
+pub const BLESS_VAR: &str = "BASE_STREAMER_BLESS";
+
+/// Compiled samples of one channel
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChanTrace {
+    pub samp_rate: f64,
+    pub samps: Vec<f64>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Snapshot {
+    /// `"<device>/<channel>"` -> samples, active channels only
+    pub chans: IndexMap<String, ChanTrace>,
+}
+
+impl Snapshot {
+    /// Samples the full compiled sequence of all active channels of `streamer`.
+    ///
+    /// Returns `Err` if the compile cache is stale.
+    pub fn capture<S: BaseStreamer + ?Sized>(streamer: &S) -> Result<Self, StreamerError> {
+        streamer.validate_compile_cache()?;
+        let mut chans = IndexMap::new();
+        for dev in streamer.active_devs() {
+            for chan in dev.tag_info().chans.into_iter().filter(|chan| chan.got_instructions) {
+                let stop_pos = dev.tag_compiled_segments(&chan.name)?.last().map_or(0, |seg| seg.end_pos);
+                let samps = dev.tag_calc_chan_samps(&chan.name, 0, stop_pos)?;
+                chans.insert(format!("{}/{}", dev.tag_name(), chan.name), ChanTrace { samp_rate: chan.samp_rate, samps });
+            }
+        }
+        Ok(Self { chans })
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&(self.chans.len() as u64).to_le_bytes())?;
+        for (key, trace) in self.chans.iter() {
+            encoder.write_all(&(key.len() as u64).to_le_bytes())?;
+            encoder.write_all(key.as_bytes())?;
+            encoder.write_all(&trace.samp_rate.to_le_bytes())?;
+            encoder.write_all(&(trace.samps.len() as u64).to_le_bytes())?;
+            for samp in trace.samps.iter() {
+                encoder.write_all(&samp.to_le_bytes())?;
+            }
+        }
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(encoder.finish()?);
+        fs::write(path, bytes)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+        let Some(payload) = bytes.strip_prefix(MAGIC.as_slice()) else {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Not a golden-waveform reference file"))
+        };
+        let mut decoder = DeflateDecoder::new(payload);
+        let read_u64 = |decoder: &mut DeflateDecoder<&[u8]>| -> io::Result<u64> {
+            let mut buf = [0u8; 8];
+            decoder.read_exact(&mut buf)?;
+            Ok(u64::from_le_bytes(buf))
+        };
+        let mut chans = IndexMap::new();
+        for _ in 0..read_u64(&mut decoder)? {
+            let mut key = vec![0u8; read_u64(&mut decoder)? as usize];
+            decoder.read_exact(&mut key)?;
+            let key = String::from_utf8(key).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+            let samp_rate = f64::from_bits(read_u64(&mut decoder)?);
+            let n_samps = read_u64(&mut decoder)?;
+            let samps = (0..n_samps).map(|_| read_u64(&mut decoder).map(f64::from_bits)).collect::<io::Result<_>>()?;
+            chans.insert(key, ChanTrace { samp_rate, samps });
+        }
+        Ok(Self { chans })
+    }
+
+    /// Compares this snapshot against `reference`: channels are matched by key and samples pass if
+    /// `|samp - ref_samp| <= tol.abs + tol.rel * |ref_samp|`.
+    pub fn compare(&self, reference: &Snapshot, tol: Tolerance) -> GoldenReport {
+        let mut report = GoldenReport::default();
+        for (key, ref_trace) in reference.chans.iter() {
+            let Some(trace) = self.chans.get(key) else {
+                report.missing_chans.push(key.clone());
+                continue
+            };
+            if trace.samp_rate != ref_trace.samp_rate || trace.samps.len() != ref_trace.samps.len() {
+                report.mismatches.push(ChanMismatch::Shape {
+                    chan: key.clone(),
+                    samp_rate: trace.samp_rate,
+                    ref_samp_rate: ref_trace.samp_rate,
+                    n_samps: trace.samps.len(),
+                    ref_n_samps: ref_trace.samps.len(),
+                });
+                continue
+            }
+            let mut first_pos = None;
+            let mut n_samps = 0;
+            let mut max_dev: f64 = 0.0;
+            for (pos, (samp, ref_samp)) in trace.samps.iter().zip(ref_trace.samps.iter()).enumerate() {
+                let dev = (samp - ref_samp).abs();
+                // NaN on either side counts as a mismatch
+                if dev.is_nan() || dev > tol.abs + tol.rel * ref_samp.abs() {
+                    first_pos.get_or_insert(pos);
+                    n_samps += 1;
+                    max_dev = if dev.is_nan() { f64::NAN } else { max_dev.max(dev) };
+                }
+            }
+            if let Some(first_pos) = first_pos {
+                report.mismatches.push(ChanMismatch::Samps { chan: key.clone(), first_pos, n_samps, max_dev })
+            }
+        }
+        report.extra_chans = self.chans.keys().filter(|key| !reference.chans.contains_key(*key)).cloned().collect();
+        report
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tolerance {
+    pub abs: f64,
+    pub rel: f64,
+}
+
+impl Tolerance {
+    pub fn exact() -> Self {
+        Self { abs: 0.0, rel: 0.0 }
+    }
+    pub fn abs(abs: f64) -> Self {
+        Self { abs, rel: 0.0 }
+    }
+}
+
+impl Default for Tolerance {
+    /// Absorbs float noise only
+    fn default() -> Self {
+        Self { abs: 1e-12, rel: 1e-9 }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ChanMismatch {
+    /// Sample rate or sample count differ - samples were not compared
+    Shape { chan: String, samp_rate: f64, ref_samp_rate: f64, n_samps: usize, ref_n_samps: usize },
+    /// `n_samps` samples are out of tolerance, the first one at `first_pos`
+    Samps { chan: String, first_pos: usize, n_samps: usize, max_dev: f64 },
+}
+
+impl Display for ChanMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Shape { chan, samp_rate, ref_samp_rate, n_samps, ref_n_samps } => write!(
+                f, "{chan}: {n_samps} samples at {samp_rate} Hz, reference has {ref_n_samps} samples at {ref_samp_rate} Hz"
+            ),
+            Self::Samps { chan, first_pos, n_samps, max_dev } => write!(
+                f, "{chan}: {n_samps} samples out of tolerance, first at tick {first_pos}, max deviation {max_dev:e}"
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GoldenReport {
+    /// Channels of the reference missing from the snapshot
+    pub missing_chans: Vec<String>,
+    /// Channels of the snapshot missing from the reference
+    pub extra_chans: Vec<String>,
+    pub mismatches: Vec<ChanMismatch>,
+}
+
+impl GoldenReport {
+    pub fn is_match(&self) -> bool {
+        self.missing_chans.is_empty() && self.extra_chans.is_empty() && self.mismatches.is_empty()
+    }
+}
+
+impl Display for GoldenReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_match() {
+            return writeln!(f, "Matches the reference")
+        }
+        writeln!(f, "Differs from the reference:")?;
+        for chan in self.missing_chans.iter() {
+            writeln!(f, "\t{chan}: missing")?;
+        }
+        for chan in self.extra_chans.iter() {
+            writeln!(f, "\t{chan}: not in the reference")?;
+        }
+        for mismatch in self.mismatches.iter() {
+            writeln!(f, "\t{mismatch}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Compares the compiled waveforms of `streamer` against the reference file at `path`, see the [module docs](self).
+///
+/// Writes the reference instead if it doesn't exist yet or [`BLESS_VAR`] is set.
+///
+/// # Panics
+/// If the streamer is not compiled, the reference can't be read or written, or the waveforms differ.
+pub fn assert_golden<S: BaseStreamer + ?Sized>(streamer: &S, path: impl AsRef<Path>, tol: Tolerance) {
+    let path = path.as_ref();
+    let snapshot = Snapshot::capture(streamer).unwrap_or_else(|err| panic!("{err}"));
+    if !path.exists() || std::env::var_os(BLESS_VAR).is_some() {
+        snapshot.save(path).unwrap_or_else(|err| panic!("Failed to write reference {}: {err}", path.display()));
+        return
+    }
+    let reference = Snapshot::load(path).unwrap_or_else(|err| panic!("Failed to read reference {}: {err}", path.display()));
+    let report = snapshot.compare(&reference, tol);
+    assert!(report.is_match(), "{}: {report}", path.display());
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::channel::BaseChan;
+    use crate::device::BaseDev;
+    use crate::fn_lib_tools::StdFnLib;
+    use crate::mock::test_impls::TestStreamer;
+
+    fn streamer(freq: f64) -> TestStreamer {
+        let mut streamer = TestStreamer::new();
+        streamer.add_ao_dev("AO", 1e3);
+        streamer.add_do_dev("DO", 1e3);
+        streamer.ao_devs["AO"].add_chan("ao0", 0.0);
+        streamer.do_devs["DO"].add_chan("port0/line0", false);
+        let sine = StdFnLib::new().Sine(1.0, freq, 0.0, 0.0).unwrap().inner;
+        streamer.ao_devs["AO"].chan_mut("ao0").unwrap().add_instr(sine, 0.1, Some((0.5, false))).unwrap();
+        streamer.do_devs["DO"].chan_mut("port0/line0").unwrap().constant(true, 0.2, Some((0.1, false))).unwrap();
+        streamer.compile(Some(1.0)).unwrap();
+        streamer
+    }
+
+    #[test]
+    fn golden() {
+        let path = std::env::temp_dir().join(format!("base_streamer_golden_{}.bin", std::process::id()));
+        let snapshot = Snapshot::capture(&streamer(10.0)).unwrap();
+        assert_eq!(snapshot.chans.keys().collect::<Vec<_>>(), ["AO/ao0", "DO/port0/line0"]);
+        assert_eq!(snapshot.chans["DO/port0/line0"].samps.len(), 1000);
+        snapshot.save(&path).unwrap();
+        let reference = Snapshot::load(&path).unwrap();
+        assert_eq!(reference, snapshot);
+        // Constant stretches compress well
+        assert!(fs::metadata(&path).unwrap().len() < 1000 * 8);
+        assert_golden(&streamer(10.0), &path, Tolerance::exact());
+
+        let report = Snapshot::capture(&streamer(10.1)).unwrap().compare(&reference, Tolerance::abs(1e-3));
+        assert!(!report.is_match());
+        let ChanMismatch::Samps { chan, first_pos, .. } = &report.mismatches[0] else { panic!("{report}") };
+        assert_eq!((chan.as_str(), report.mismatches.len()), ("AO/ao0", 1));
+        assert!(*first_pos >= 100);
+
+        let mut other = streamer(10.0);
+        other.do_devs["DO"].chan_mut("port0/line0").unwrap().constant(true, 0.4, Some((0.1, false))).unwrap();
+        other.compile(Some(1.5)).unwrap();
+        let report = Snapshot::capture(&other).unwrap().compare(&reference, Tolerance::default());
+        assert!(matches!(report.mismatches[..], [ChanMismatch::Shape { n_samps: 1500, ref_n_samps: 1000, .. }, ..]));
+
+        fs::write(&path, b"garbage").unwrap();
+        assert_eq!(Snapshot::load(&path).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        fs::remove_file(&path).unwrap();
+    }
+}
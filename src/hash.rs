@@ -0,0 +1,151 @@
+//! Stable hashing used for compiled-experiment fingerprints.
+//!
+//! `std::collections::hash_map::DefaultHasher` is explicitly not guaranteed to produce the same output
+//! across Rust releases, which makes it unsuitable for fingerprints that callers may persist
+//! (e.g. to decide whether hardware buffers need to be re-uploaded). [`StableHasher`] implements
+//! 64-bit FNV-1a and feeds all numbers in little-endian byte order, so the result only depends on the hashed content.
+//!
+//! See [`BaseChan::content_hash`], [`BaseDev::content_hash`], and [`BaseStreamer::content_hash`].
+//!
+//! Content hashes describe the compile cache. To verify the samples themselves (e.g. read back from a hardware buffer),
+//! [`BaseChan::segment_checksums`] hashes the sample stream chunk by chunk with [`samp_checksum`], which backends
+//! apply to the chunks they wrote.
+//!
+//! [`BaseChan::segment_checksums`]: crate::channel::BaseChan::segment_checksums
+//! [`BaseChan::content_hash`]: crate::channel::BaseChan::content_hash
+//! [`BaseDev::content_hash`]: crate::device::BaseDev::content_hash
+//! [`BaseStreamer::content_hash`]: crate::streamer::BaseStreamer::content_hash
+
+use std::fmt::Debug;
+use crate::channel::AsF64;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// 64-bit FNV-1a hasher with platform-independent input encoding
+#[derive(Clone, Debug)]
+pub struct StableHasher {
+    state: u64,
+}
+
+impl StableHasher {
+    pub fn new() -> Self {
+        Self { state: FNV_OFFSET_BASIS }
+    }
+
+    pub fn update(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.state ^= byte as u64;
+            self.state = self.state.wrapping_mul(FNV_PRIME);
+        }
+    }
+    pub fn update_u64(&mut self, val: u64) {
+        self.update(&val.to_le_bytes())
+    }
+    pub fn update_usize(&mut self, val: usize) {
+        self.update_u64(val as u64)
+    }
+    pub fn update_f64(&mut self, val: f64) {
+        self.update_u64(val.to_bits())
+    }
+    /// Strings are length-prefixed so that e.g. `("ab", "c")` and `("a", "bc")` hash differently
+    pub fn update_str(&mut self, val: &str) {
+        self.update_usize(val.len());
+        self.update(val.as_bytes())
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.state
+    }
+}
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Checksum of a run of samples, each fed as `f64` - the same value for `f64` and `bool` samples alike.
+/// Samples of non-numeric types are fed by their `Debug` form.
+pub fn samp_checksum<T: AsF64 + Debug>(samps: &[T]) -> u64 {
+    let mut hasher = StableHasher::new();
+    for samp in samps {
+        match samp.as_f64() {
+            Some(val) => hasher.update_f64(val),
+            None => hasher.update_str(&format!("{samp:?}")),
+        }
+    }
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::hash::StableHasher;
+
+    #[test]
+    fn fnv1a_reference() {
+        // Reference values of 64-bit FNV-1a
+        assert_eq!(StableHasher::new().finish(), 0xcbf29ce484222325);
+        let mut hasher = StableHasher::new();
+        hasher.update(b"a");
+        assert_eq!(hasher.finish(), 0xaf63dc4c8601ec8c);
+        let mut hasher = StableHasher::new();
+        hasher.update(b"foobar");
+        assert_eq!(hasher.finish(), 0x85944171f73967e8);
+    }
+
+    #[test]
+    fn content_hash() {
+        use crate::channel::BaseChan;
+        use crate::device::BaseDev;
+        use crate::mock::test_impls::TestStreamer;
+        use crate::streamer::BaseStreamer;
+
+        // `pulses` are added in the given order
+        let build = |pulses: &[(f64, f64)]| {
+            let mut streamer = TestStreamer::new();
+            streamer.add_ao_dev("AO", 100.0);
+            streamer.ao_devs["AO"].add_chan("ao0", 0.0);
+            for &(t, val) in pulses {
+                streamer.ao_devs["AO"].chan_mut("ao0").unwrap().constant(val, t, Some((0.1, false))).unwrap();
+            }
+            streamer.compile(Some(1.0)).unwrap();
+            streamer
+        };
+
+        let reference = build(&[(0.1, 1.0), (0.5, 2.0)]).content_hash().unwrap();
+        // Invariant to edit order
+        assert_eq!(build(&[(0.5, 2.0), (0.1, 1.0)]).content_hash().unwrap(), reference);
+        // Sensitive to timing
+        assert_ne!(build(&[(0.1, 1.0), (0.6, 2.0)]).content_hash().unwrap(), reference);
+        // Sensitive to function parameters
+        assert_ne!(build(&[(0.1, 1.0), (0.5, 2.5)]).content_hash().unwrap(), reference);
+
+        // Stale compile cache
+        let mut streamer = build(&[(0.1, 1.0)]);
+        streamer.ao_devs["AO"].chan_mut("ao0").unwrap().constant(1.0, 0.5, None).unwrap();
+        assert!(streamer.content_hash().is_err());
+    }
+
+    #[test]
+    fn segment_checksums() {
+        use crate::channel::{BaseChan, ChanSampCursor};
+        use crate::error::StreamerError;
+        use crate::hash::samp_checksum;
+        use crate::mock::test_impls::TestChan;
+
+        let mut chan = TestChan::new("ao0", 1e3, 0.0);
+        chan.constant(1.0, 0.01, Some((0.01, true))).unwrap();
+        assert!(matches!(chan.segment_checksums(10), Err(StreamerError::NotCompiled { .. })));
+        chan.compile(25).unwrap();
+        assert!(matches!(chan.segment_checksums(0), Err(StreamerError::InvalidArgument { .. })));
+
+        let checksums = chan.segment_checksums(10).unwrap();
+        let mut samps = vec![0.0; 25];
+        chan.fill_samps_from_ticks(&mut ChanSampCursor::new(), 0, &mut samps).unwrap();
+        let expected: Vec<u64> = samps.chunks(10).map(samp_checksum).collect();
+        assert_eq!(checksums, expected);
+        assert_eq!(checksums[1], samp_checksum(&[1.0; 10]));
+        assert_ne!(checksums[0], checksums[2]);
+    }
+}
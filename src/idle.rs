@@ -0,0 +1,162 @@
+//! Idle map - long constant stretches of the compiled sequence which need not be streamed sample by sample.
+//!
+//! An hours-long hold compiles to a single constant compile cache segment, but streaming it still means writing
+//! gigabytes of identical samples. Backends which can pause the output (or regenerate a short buffer at a reduced rate)
+//! query [`BaseStreamer::idle_map`] after compiling and handle the reported intervals themselves.
+//!
+//! A channel is idle wherever its compile cache holds a constant for at least `min_samps` ticks - neighbouring constant
+//! segments of the same value (e.g. pieces cut by [`BaseChan::split_segments`]) count as one. Since a device can only
+//! pause all of its outputs together, the device-level idle intervals are those where all active channels are idle
+//! at the same time, again at least `min_samps` ticks long.
+//!
+//! Positions are compile cache ticks, so they already include the trigger delay advance of the device.
+//!
+//! [`BaseStreamer::idle_map`]: crate::streamer::BaseStreamer::idle_map
+//! [`BaseChan::split_segments`]: crate::channel::BaseChan::split_segments
+
+use std::fmt;
+use std::fmt::Display;
+use indexmap::IndexMap;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+/// Constant stretch `[start_pos, end_pos)` of a compiled channel
+#[derive(Clone, Debug, PartialEq)]
+pub struct IdleSeg {
+    pub start_pos: usize,
+    pub end_pos: usize,
+    /// Held value converted to `f64`
+    pub val: f64,
+}
+
+impl IdleSeg {
+    pub fn len(&self) -> usize {
+        self.end_pos - self.start_pos
+    }
+    pub fn is_empty(&self) -> bool {
+        self.end_pos == self.start_pos
+    }
+}
+
+/// Idle map of one device, see the [module docs](self)
+#[derive(Clone, Debug, PartialEq)]
+pub struct DevIdle {
+    pub samp_rate: f64,
+    /// Compiled stop position
+    pub stop_pos: usize,
+    /// Channel name -> idle segments of the channel
+    pub chans: IndexMap<String, Vec<IdleSeg>>,
+    /// `[start_pos, end_pos)` intervals where all active channels are idle - the intervals to pause in
+    pub intervals: Vec<(usize, usize)>,
+}
+
+impl DevIdle {
+    /// Number of ticks the device may pause for
+    pub fn idle_samps(&self) -> usize {
+        self.intervals.iter().map(|(start, end)| end - start).sum()
+    }
+    /// Share of the compiled sequence the device may pause for
+    pub fn idle_fraction(&self) -> f64 {
+        match self.stop_pos {
+            0 => 0.0,
+            stop_pos => self.idle_samps() as f64 / stop_pos as f64,
+        }
+    }
+    /// [`DevIdle::intervals`] in seconds
+    pub fn intervals_s(&self) -> Vec<(f64, f64)> {
+        self.intervals.iter().map(|&(start, end)| (start as f64 / self.samp_rate, end as f64 / self.samp_rate)).collect()
+    }
+
+    pub fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("samp_rate", self.samp_rate)?;
+        dict.set_item("stop_pos", self.stop_pos)?;
+        dict.set_item("intervals", self.intervals.clone())?;
+        dict.set_item("intervals_s", self.intervals_s())?;
+        dict.set_item("idle_fraction", self.idle_fraction())?;
+        let chans = PyDict::new_bound(py);
+        for (name, segs) in self.chans.iter() {
+            chans.set_item(name, segs.iter().map(|seg| (seg.start_pos, seg.end_pos, seg.val)).collect::<Vec<_>>())?;
+        }
+        dict.set_item("chans", chans)?;
+        Ok(dict)
+    }
+}
+
+impl Display for DevIdle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} idle intervals, {:.1}% of {} ticks", self.intervals.len(), 100.0 * self.idle_fraction(), self.stop_pos)?;
+        for (start, end) in self.intervals.iter() {
+            write!(f, "\n\t[{start}, {end})")?;
+        }
+        Ok(())
+    }
+}
+
+/// Overlaps of two sorted lists of disjoint `[start, end)` intervals
+pub fn intersect(intervals_a: &[(usize, usize)], intervals_b: &[(usize, usize)]) -> Vec<(usize, usize)> {
+    let mut res = Vec::new();
+    let (mut idx_a, mut idx_b) = (0, 0);
+    while idx_a < intervals_a.len() && idx_b < intervals_b.len() {
+        let ((start_a, end_a), (start_b, end_b)) = (intervals_a[idx_a], intervals_b[idx_b]);
+        let (start, end) = (start_a.max(start_b), end_a.min(end_b));
+        if start < end {
+            res.push((start, end))
+        }
+        if end_a <= end_b {
+            idx_a += 1
+        } else {
+            idx_b += 1
+        }
+    }
+    res
+}
+
+#[cfg(test)]
+mod test {
+    use crate::channel::BaseChan;
+    use crate::device::BaseDev;
+    use crate::error::StreamerError;
+    use crate::fn_lib_tools::StdFnLib;
+    use crate::idle::*;
+    use crate::mock::test_impls::TestStreamer;
+    use crate::options::CompileOptions;
+    use crate::streamer::BaseStreamer;
+
+    #[test]
+    fn idle_map() {
+        assert_eq!(intersect(&[(0, 10), (20, 30)], &[(5, 25), (28, 40)]), [(5, 10), (20, 25), (28, 30)]);
+
+        let mut streamer = TestStreamer::new();
+        streamer.add_ao_dev("AO", 1e3);
+        streamer.add_do_dev("DO", 1e3);
+        streamer.ao_devs["AO"].add_chan("ao0", 0.0);
+        streamer.ao_devs["AO"].add_chan("ao1", 0.0);
+        streamer.do_devs["DO"].add_chan("port0/line0", false);
+        {
+            let ao0 = streamer.ao_devs["AO"].chan_mut("ao0").unwrap();
+            ao0.constant(1.0, 0.0, Some((0.4, true))).unwrap();
+            ao0.add_instr(StdFnLib::new().LinFn(1.0, 0.0).unwrap().inner, 0.5, Some((0.1, false))).unwrap();
+        }
+        streamer.ao_devs["AO"].chan_mut("ao1").unwrap().add_instr(StdFnLib::new().LinFn(1.0, 0.0).unwrap().inner, 0.1, Some((0.05, false))).unwrap();
+        streamer.do_devs["DO"].chan_mut("port0/line0").unwrap().constant(true, 0.2, Some((0.001, false))).unwrap();
+        assert!(matches!(streamer.idle_map(0.01), Err(StreamerError::NotCompiled { .. })));
+        // Split pieces of the long holds still count as one idle segment
+        streamer.compile_with(Some(1.0), &CompileOptions { max_seg_samps: Some(64), ..CompileOptions::preview() }).unwrap();
+
+        let idle = streamer.idle_map(0.15).unwrap();
+        assert_eq!(idle.keys().collect::<Vec<_>>(), ["AO", "DO"]);
+        let ao = &idle["AO"];
+        assert_eq!(ao.chans["ao0"], [IdleSeg { start_pos: 0, end_pos: 500, val: 1.0 }, IdleSeg { start_pos: 600, end_pos: 1000, val: 0.0 }]);
+        assert_eq!(ao.chans["ao1"].iter().map(|seg| (seg.start_pos, seg.end_pos)).collect::<Vec<_>>(), [(150, 1000)]);
+        // [150, 500) and [600, 1000) - both channels are idle
+        assert_eq!(ao.intervals, [(150, 500), (600, 1000)]);
+        assert!((ao.idle_fraction() - 0.75).abs() < 1e-12);
+        // The 1 ms pulse splits the DO hold in two
+        assert_eq!(idle["DO"].intervals, [(0, 200), (201, 1000)]);
+
+        // Shorter stretches count with a lower threshold
+        assert_eq!(streamer.idle_map(0.05).unwrap()["AO"].intervals, [(0, 100), (150, 500), (600, 1000)]);
+        assert!(matches!(streamer.idle_map(-1.0), Err(StreamerError::InvalidArgument { .. })));
+    }
+}
@@ -0,0 +1,133 @@
+//! Read-only views of devices and channels for the Python layer.
+//!
+//! [`DevInfo`] and [`ChanInfo`] are snapshots of the device/channel properties taken by [`BaseDev::info`].
+//! `DevInfo` behaves like a read-only dict of its channels (`len()`, iteration, `dev["ao0"]`, `"ao0" in dev`).
+//! Streamer pyclasses of downstream crates get the same protocol over devices by forwarding
+//! `__len__`, `__iter__`, `__getitem__`, and `__contains__` to the helpers in [`crate::py_tools`],
+//! which also accept `"Dev1/ao0"` keys:
+//!
+//! ```ignore
+//! #[pymethods]
+//! impl Streamer {
+//!     fn __getitem__(&self, py: Python<'_>, key: &str) -> PyResult<PyObject> {
+//!         py_tools::getitem(py, self, key)
+//!     }
+//! }
+//! ```
+//!
+//! [`BaseDev::info`]: crate::device::BaseDev::info
+
+use std::fmt;
+use std::fmt::Display;
+use pyo3::prelude::*;
+use pyo3::exceptions::PyKeyError;
+use pyo3::types::{PyIterator, PyList};
+
+#[pyclass]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChanInfo {
+    #[pyo3(get)]
+    pub dev: String,
+    #[pyo3(get)]
+    pub name: String,
+    #[pyo3(get)]
+    pub samp_rate: f64,
+    /// Default value converted to `f64`
+    #[pyo3(get)]
+    pub dflt_val: f64,
+    /// Quantity with unit, e.g. `Voltage [V]`
+    #[pyo3(get)]
+    pub quantity: String,
+    #[pyo3(get)]
+    pub is_event_chan: bool,
+    /// `false` if the channel is muted, see [`BaseChan::set_enabled`](crate::channel::BaseChan::set_enabled)
+    #[pyo3(get)]
+    pub is_enabled: bool,
+    /// Paddings hold the last instruction value, see [`BaseChan::set_hold_last_val`](crate::channel::BaseChan::set_hold_last_val)
+    #[pyo3(get)]
+    pub hold_last_val: bool,
+    #[pyo3(get)]
+    pub got_instructions: bool,
+    /// Number of base-layer instructions in the edit cache
+    #[pyo3(get)]
+    pub n_instrs: usize,
+    #[pyo3(get)]
+    pub first_instr_time: Option<f64>,
+    #[pyo3(get)]
+    pub last_instr_end_time: Option<f64>,
+    #[pyo3(get)]
+    pub is_fresh_compiled: bool,
+    /// `None` if the channel is not compiled
+    #[pyo3(get)]
+    pub compiled_stop_time: Option<f64>,
+    /// See [`BaseChan::compiled_segments`](crate::channel::BaseChan::compiled_segments), `None` if the channel is not compiled
+    pub compiled_segments: Option<Vec<(usize, usize, String)>>,
+}
+
+#[pymethods]
+impl ChanInfo {
+    /// Compile cache as a list of `(start_pos, end_pos, fn_description)` tuples, `None` if the channel is not compiled
+    pub fn compiled_segments(&self) -> Option<Vec<(usize, usize, String)>> {
+        self.compiled_segments.clone()
+    }
+    fn __repr__(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl Display for ChanInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f, "ChanInfo({}/{}, {}, n_instrs={}, last_instr_end_time={:?}, compiled_stop_time={:?})",
+            self.dev, self.name, self.quantity, self.n_instrs, self.last_instr_end_time, self.compiled_stop_time
+        )
+    }
+}
+
+#[pyclass]
+#[derive(Clone, Debug, PartialEq)]
+pub struct DevInfo {
+    #[pyo3(get)]
+    pub name: String,
+    #[pyo3(get)]
+    pub samp_rate: f64,
+    #[pyo3(get)]
+    pub got_instructions: bool,
+    pub chans: Vec<ChanInfo>,
+}
+
+impl DevInfo {
+    pub fn chan(&self, chan_name: &str) -> Option<&ChanInfo> {
+        self.chans.iter().find(|chan| chan.name == chan_name)
+    }
+}
+
+#[pymethods]
+impl DevInfo {
+    pub fn chan_names(&self) -> Vec<String> {
+        self.chans.iter().map(|chan| chan.name.clone()).collect()
+    }
+    pub fn __len__(&self) -> usize {
+        self.chans.len()
+    }
+    /// Iterates over the `ChanInfo` of all channels
+    pub fn __iter__<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyIterator>> {
+        let chans = self.chans.iter().map(|chan| Py::new(py, chan.clone())).collect::<PyResult<Vec<_>>>()?;
+        PyIterator::from_bound_object(&PyList::new_bound(py, chans))
+    }
+    pub fn __getitem__(&self, chan_name: &str) -> PyResult<ChanInfo> {
+        self.chan(chan_name).cloned().ok_or_else(|| PyKeyError::new_err(format!("Device {} does not have channel {chan_name}", self.name)))
+    }
+    pub fn __contains__(&self, chan_name: &str) -> bool {
+        self.chan(chan_name).is_some()
+    }
+    fn __repr__(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl Display for DevInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "DevInfo({}, samp_rate={}, chans={:?})", self.name, self.samp_rate, self.chan_names())
+    }
+}
@@ -20,10 +20,55 @@
 //! - Ability to evaluate instructions and in-place populate given time array views with the resulting float-point values.
 //! - Support for default values in instructions, allowing for flexibility and ease of use.
 
+use std::borrow::Borrow;
 use std::cmp::Ordering;
 use std::fmt;
 use std::fmt::Display;
-use crate::fn_lib_tools::FnTraitSet;
+use std::sync::Arc;
+use indexmap::IndexMap;
+use crate::fn_lib_tools::{FnTraitSet, TimeMap};
+
+/// Optional user-facing annotation of an instruction.
+///
+/// Metadata has no effect on the produced waveform. It stays with the instruction in the edit cache
+/// (through `compile`, `shift`, and `copy_instrs_from`) and is printed as part of the instruction
+/// in error messages, diagnostics, and reports - so that a collision or a non-finite value
+/// can be traced back to the code which created the pulse.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InstrMeta {
+    /// Human-readable name, e.g. `"cooling_pulse"`
+    pub label: Option<String>,
+    /// Where the instruction came from, e.g. the name of the Python helper that inserted it
+    pub creator: Option<String>,
+    /// Arbitrary key-value annotations
+    pub user_data: IndexMap<String, String>,
+}
+
+impl InstrMeta {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn labeled(label: &str) -> Self {
+        Self { label: Some(label.to_string()), ..Self::default() }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.label.is_none() && self.creator.is_none() && self.user_data.is_empty()
+    }
+}
+
+impl Display for InstrMeta {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(label) = &self.label {
+            parts.push(format!("label={label:?}"));
+        }
+        if let Some(creator) = &self.creator {
+            parts.push(format!("creator={creator:?}"));
+        }
+        parts.extend(self.user_data.iter().map(|(key, val)| format!("{key}={val:?}")));
+        write!(f, "{}", parts.join(", "))
+    }
+}
 
 /// Struct containing function and start/end edge data of the instruction.
 ///
@@ -53,7 +98,12 @@ use crate::fn_lib_tools::FnTraitSet;
 pub struct Instr<T> {
     start_pos: usize,
     end_spec: Option<(usize, bool)>,
-    func: Box<dyn FnTraitSet<T>>,
+    func: Arc<dyn FnTraitSet<T>>,
+    meta: Option<InstrMeta>,
+    layer: u32,
+    phase_link: bool,
+    sub_tick: f64,
+    time_origin: Option<usize>,
 }
 impl<T> Instr<T> {
     /// Constructs a new `InstrBook` object.
@@ -71,29 +121,33 @@ impl<T> Instr<T> {
     ///
     /// # Examples
     ///
-    /// Constructing a valid `InstrBook`:
+    /// Constructing a valid `Instr`:
     ///
     /// ```
-    /// # use nicompiler_backend::instruction::*;
-    /// let instruction = Instruction::new(InstrType::CONST, [("value".to_string(), 1.0)].iter().cloned().collect());
-    /// let book = InstrBook::new(0, Some((5, true)), instruction);
+    /// # use base_streamer::instruction::Instr;
+    /// # use base_streamer::channel::ConstFn;
+    /// let instr = Instr::new(0, Some((5, true)), Box::new(ConstFn::new(1.0)));
     /// ```
     ///
-    /// Attempting to construct an `InstrBook` with `end_pos` not greater than `start_pos` will panic:
+    /// Attempting to construct an `Instr` with `end_pos` not greater than `start_pos` will panic:
     ///
     /// ```should_panic
-    /// # use nicompiler_backend::instruction::*;
-    /// let instruction = Instruction::new(InstrType::CONST, [("value".to_string(), 1.0)].iter().cloned().collect());
-    /// let book = InstrBook::new(5, Some((5, true)), instruction);
+    /// # use base_streamer::instruction::Instr;
+    /// # use base_streamer::channel::ConstFn;
+    /// let instr = Instr::new(5, Some((5, true)), Box::new(ConstFn::new(1.0)));
     /// ```
     ///
     /// The panic message will be:
-    /// `Instruction { /* ... */ } end_pos 5 should be strictly greater than start_pos 5`.
+    /// `Instruction must satisfy `start_pos + 1 <= end_pos` [...] start_pos = 5 and end_pos = 5`.
     pub fn new(start_pos: usize, end_spec: Option<(usize, bool)>, func: Box<dyn FnTraitSet<T>>) -> Self {
+        Self::new_shared(start_pos, end_spec, Arc::from(func))
+    }
+    /// Same as [`Instr::new`] for a function object shared with other instructions
+    pub fn new_shared(start_pos: usize, end_spec: Option<(usize, bool)>, func: Arc<dyn FnTraitSet<T>>) -> Self {
         if let Some((end_pos, _keep_val)) = &end_spec {
             // Sanity check - the smallest permissible instruction length is 1 tick
             assert!(
-                start_pos + 1 <= *end_pos,
+                start_pos < *end_pos,
                 "Instruction must satisfy `start_pos + 1 <= end_pos` \n\
                  However, provided instruction has start_pos = {start_pos} and end_pos = {end_pos}"
             )
@@ -102,8 +156,18 @@ impl<T> Instr<T> {
             start_pos,
             end_spec,
             func,
+            meta: None,
+            layer: 0,
+            phase_link: false,
+            sub_tick: 0.0,
+            time_origin: None,
         }
     }
+    /// Attaches metadata to the instruction. Empty metadata is dropped.
+    pub fn with_meta(mut self, meta: Option<InstrMeta>) -> Self {
+        self.meta = meta.filter(|meta| !meta.is_empty());
+        self
+    }
     /// Returns the value of the `start_pos` field
     pub fn start_pos(&self) -> usize {
         self.start_pos
@@ -113,24 +177,18 @@ impl<T> Instr<T> {
     }
     /// Returns the value of the `end_spec` field
     pub fn end_spec(&self) -> Option<(usize, bool)> {
-        self.end_spec.clone()
+        self.end_spec
     }
     pub fn end_spec_mut(&mut self) -> &mut Option<(usize, bool)> {
         &mut self.end_spec
     }
     /// Returns the value of the `end_pos` sub-field
     pub fn end_pos(&self) -> Option<usize> {
-        match self.end_spec {
-            Some((end_pos, _keep_val)) => Some(end_pos),
-            None => None,
-        }
+        self.end_spec.map(|(end_pos, _keep_val)| end_pos)
     }
     /// Returns the value of the `keep_val` sub-field
     pub fn keep_val(&self) -> Option<bool> {
-        match self.end_spec {
-            Some((_end_pos, keep_val)) => Some(keep_val),
-            None => None,
-        }
+        self.end_spec.map(|(_end_pos, keep_val)| keep_val)
     }
     /// "Effective" end position
     ///
@@ -150,14 +208,104 @@ impl<T> Instr<T> {
     }
     /// Returns `Some(end_pos - start_pos)` or `None` if not specified
     pub fn dur(&self) -> Option<usize> {
-        match self.end_spec {
-            Some((end_pos, _keep_val)) => Some(end_pos - self.start_pos),
-            None => None,
+        self.end_spec.map(|(end_pos, _keep_val)| end_pos - self.start_pos)
+    }
+
+    pub fn func(&self) -> &dyn FnTraitSet<T> {
+        self.func.as_ref()
+    }
+    /// Returns a new reference to the shared function - a refcount increment, the function itself is not copied
+    pub fn shared_func(&self) -> Arc<dyn FnTraitSet<T>> {
+        Arc::clone(&self.func)
+    }
+    pub fn func_mut(&mut self) -> &mut Arc<dyn FnTraitSet<T>> {
+        &mut self.func
+    }
+    pub fn meta(&self) -> Option<&InstrMeta> {
+        self.meta.as_ref()
+    }
+    pub fn meta_mut(&mut self) -> &mut Option<InstrMeta> {
+        &mut self.meta
+    }
+    /// Override layer of the instruction, `0` is the base layer - see [`BaseChan::add_instr_on_layer`].
+    ///
+    /// [`BaseChan::add_instr_on_layer`]: crate::channel::BaseChan::add_instr_on_layer
+    pub fn layer(&self) -> u32 {
+        self.layer
+    }
+    pub fn with_layer(mut self, layer: u32) -> Self {
+        self.layer = layer;
+        self
+    }
+    /// Whether the function phase is continued from the previous instruction of the same layer -
+    /// see [`BaseChan::add_instr_phase_linked`].
+    ///
+    /// [`BaseChan::add_instr_phase_linked`]: crate::channel::BaseChan::add_instr_phase_linked
+    pub fn phase_link(&self) -> bool {
+        self.phase_link
+    }
+    pub fn with_phase_link(mut self, phase_link: bool) -> Self {
+        self.phase_link = phase_link;
+        self
+    }
+    /// Sub-tick residual (in seconds) discarded when the requested start time was rounded to `start_pos`:
+    /// `t - start_pos * clk_period`, within half a clock period. Used by [`BaseChan::set_sub_tick_phase`].
+    ///
+    /// [`BaseChan::set_sub_tick_phase`]: crate::channel::BaseChan::set_sub_tick_phase
+    pub fn sub_tick(&self) -> f64 {
+        self.sub_tick
+    }
+    pub fn with_sub_tick(mut self, sub_tick: f64) -> Self {
+        self.sub_tick = sub_tick;
+        self
+    }
+    /// Tick which the function sees as time `0` - `Some` for instructions evaluated in local time
+    /// (see [`BaseChan::add_instr_local`]), `None` for absolute channel time.
+    ///
+    /// The origin is set to the start tick when the instruction is added and stays in place if the start
+    /// is later trimmed, so that the waveform doesn't move.
+    ///
+    /// [`BaseChan::add_instr_local`]: crate::channel::BaseChan::add_instr_local
+    pub fn time_origin(&self) -> Option<usize> {
+        self.time_origin
+    }
+    pub fn time_origin_mut(&mut self) -> &mut Option<usize> {
+        &mut self.time_origin
+    }
+    pub fn with_time_origin(mut self, time_origin: Option<usize>) -> Self {
+        self.time_origin = time_origin;
+        self
+    }
+    /// Whether the function receives time relative to [`Instr::time_origin`] instead of absolute channel time
+    pub fn local_time(&self) -> bool {
+        self.time_origin.is_some()
+    }
+}
+
+impl<T: 'static> Instr<T> {
+    /// The function in absolute channel time: the shared function itself, or for local-time instructions
+    /// the function wrapped into [`TimeMap::shift`] by the time of [`Instr::time_origin`]
+    pub fn abs_func(&self, clk_period: f64) -> Arc<dyn FnTraitSet<T>> {
+        match self.time_origin {
+            Some(origin) => Arc::new(TimeMap::shift(Arc::clone(&self.func), origin as f64 * clk_period)),
+            None => Arc::clone(&self.func),
         }
     }
+}
 
-    pub fn func(&self) -> &Box<dyn FnTraitSet<T>> {
-        &self.func
+// Cloning shares the function (`Arc`)
+impl<T> Clone for Instr<T> {
+    fn clone(&self) -> Self {
+        Instr {
+            start_pos: self.start_pos,
+            end_spec: self.end_spec,
+            func: Arc::clone(&self.func),
+            meta: self.meta.clone(),
+            layer: self.layer,
+            phase_link: self.phase_link,
+            sub_tick: self.sub_tick,
+            time_origin: self.time_origin,
+        }
     }
 }
 
@@ -179,6 +327,13 @@ impl<T> PartialEq for Instr<T> {
     }
 }
 impl<T> Eq for Instr<T> {}
+/// Ordering and equality are by `start_pos` only, so `BTreeSet<Instr<T>>` can be searched by position directly
+/// (e.g. `instr_list.range(..=pos)`) without constructing a placeholder instruction.
+impl<T> Borrow<usize> for Instr<T> {
+    fn borrow(&self) -> &usize {
+        &self.start_pos
+    }
+}
 
 impl<T> Display for Instr<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -186,10 +341,59 @@ impl<T> Display for Instr<T> {
             Some((end_pos, keep_val)) => format!("end_pos={end_pos}, keep_val={keep_val}"),
             None => "no specified end".to_string(),
         };
+        let layer = match self.layer {
+            0 => String::new(),
+            layer => format!(", layer={layer}"),
+        };
+        let phase_link = if self.phase_link { ", phase_link=true" } else { "" };
+        let time_origin = match self.time_origin {
+            Some(origin) => format!(", time_origin={origin}"),
+            None => String::new(),
+        };
+        let meta = match &self.meta {
+            Some(meta) => format!(", {meta}"),
+            None => String::new(),
+        };
         write!(
             f,
-            "Instr(func={:?}, start_pos={}, {})",  // ToDo: a way to implement Display for Box<dyn FnTraitSet>
-            self.func, self.start_pos, end_spec
+            "Instr(func={}, start_pos={}, {}{}{}{}{})",
+            self.func.describe(), self.start_pos, end_spec, layer, phase_link, time_origin, meta
         )
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::channel::ConstFn;
+    use crate::instruction::*;
+
+    #[test]
+    fn meta_display() {
+        let instr = Instr::new(0, Some((5, true)), Box::new(ConstFn::new(1.0)));
+        assert!(!instr.to_string().contains("label"));
+
+        let mut meta = InstrMeta::labeled("gate");
+        meta.creator = Some("pulse_train".to_string());
+        meta.user_data.insert("shot".to_string(), "3".to_string());
+        let instr = instr.with_meta(Some(meta));
+        assert!(instr.to_string().ends_with(r#"keep_val=true, label="gate", creator="pulse_train", shot="3")"#));
+
+        // Empty metadata is not stored
+        let instr = Instr::new(0, None, Box::new(ConstFn::new(1.0))).with_meta(Some(InstrMeta::new()));
+        assert!(instr.meta().is_none());
+    }
+
+    #[test]
+    fn describe_func() {
+        use crate::fn_lib_tools::{StdFnLib, TimeMap};
+
+        let sine = StdFnLib::new().Sine(1.0, 5e3, 0.0, 0.0).unwrap().inner;
+        assert_eq!(sine.describe(), "Sine(amp=1.0, freq=5000.0, phase=0.0, offs=0.0)");
+        let shifted = TimeMap::shift(sine, 0.5);
+        let instr = Instr::new(0, None, Box::new(shifted));
+        assert_eq!(
+            instr.to_string(),
+            "Instr(func=TimeMap(inner=Sine(amp=1.0, freq=5000.0, phase=0.0, offs=0.0), scale=1.0, offs=-0.5), start_pos=0, no specified end)"
+        );
+    }
+}
@@ -0,0 +1,196 @@
+//! Built-in cross-channel constraints (interlocks) on top of [`crate::rules`].
+//!
+//! A channel is "high" wherever `|value| > 0`: the compiled waveform is scanned tick by tick, so non-constant
+//! functions are checked exactly on the sample clock grid. Channels without instructions are never high.
+//! Channels are named `"<device>/<channel>"` (split at the first `/`). Intervals of channels on different devices
+//! are compared in seconds.
+//!
+//! - [`Interlock::requires`] - channel A must be high whenever channel B is high
+//!   (e.g. "the shutter must be open whenever the AOM is on");
+//! - [`Interlock::exclusive`] - no two of the channels may be high at the same time.
+//!
+//! Every violated interval is reported as a separate [`Severity::Error`] finding with its start and end times,
+//! so an interlock fails the compile. Register interlocks with [`BaseStreamer::add_rule`]:
+//!
+//! ```ignore
+//! streamer.add_rule(Box::new(Interlock::requires("DO/shutter", "AO/aom")))?;
+//! ```
+//!
+//! [`BaseStreamer::add_rule`]: crate::streamer::BaseStreamer::add_rule
+
+use std::fmt;
+use std::fmt::Display;
+use crate::diagnostics::{Diagnostic, DiagnosticKind, DiagnosticStage, Severity};
+use crate::rules::{StreamerView, ValidationRule};
+
+/// Violated `[start, end)` interval in seconds
+#[derive(Clone, Debug, PartialEq)]
+pub struct Violation {
+    /// Channel the violation is reported at (the constrained channel, or the first of an exclusive pair)
+    pub chan: String,
+    /// The channel it conflicts with
+    pub other: String,
+    pub start: f64,
+    pub end: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Interlock {
+    /// `chan` must be high whenever `whenever` is high
+    Requires { chan: String, whenever: String },
+    /// At most one of `chans` may be high at any time
+    Exclusive { chans: Vec<String> },
+}
+
+impl Interlock {
+    pub fn requires(chan: &str, whenever: &str) -> Self {
+        Self::Requires { chan: chan.to_string(), whenever: whenever.to_string() }
+    }
+    pub fn exclusive(chans: &[&str]) -> Self {
+        Self::Exclusive { chans: chans.iter().map(|chan| chan.to_string()).collect() }
+    }
+
+    /// All violated intervals, sorted by start time
+    pub fn violations(&self, view: &StreamerView) -> Vec<Violation> {
+        match self {
+            Self::Requires { chan, whenever } => {
+                let needed = high_intervals(view, whenever);
+                let present = high_intervals(view, chan);
+                subtract(&needed, &present).into_iter()
+                    .map(|(start, end)| Violation { chan: chan.clone(), other: whenever.clone(), start, end })
+                    .collect()
+            },
+            Self::Exclusive { chans } => {
+                let intervals: Vec<_> = chans.iter().map(|chan| high_intervals(view, chan)).collect();
+                let mut violations = Vec::new();
+                for (first_idx, first) in intervals.iter().enumerate() {
+                    for (second_idx, second) in intervals.iter().enumerate().skip(first_idx + 1) {
+                        violations.extend(intersect(first, second).into_iter().map(|(start, end)| Violation {
+                            chan: chans[first_idx].clone(), other: chans[second_idx].clone(), start, end,
+                        }))
+                    }
+                }
+                violations.sort_by(|a, b| a.start.total_cmp(&b.start));
+                violations
+            },
+        }
+    }
+}
+
+impl Display for Interlock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Requires { chan, whenever } => write!(f, "{chan} requires-when {whenever}"),
+            Self::Exclusive { chans } => write!(f, "exclusive({})", chans.join(", ")),
+        }
+    }
+}
+
+impl ValidationRule for Interlock {
+    fn name(&self) -> String {
+        format!("interlock {self}")
+    }
+    fn validate_global(&self, view: &StreamerView) -> Vec<Diagnostic> {
+        self.violations(view).into_iter().map(|Violation { chan, other, start, end }| {
+            let message = match self {
+                Self::Requires { .. } => format!("{chan} is not high while {other} is, from {start} s to {end} s"),
+                Self::Exclusive { .. } => format!("{chan} and {other} are high simultaneously from {start} s to {end} s"),
+            };
+            let (dev, chan) = chan.split_once('/').map(|(dev, chan)| (dev.to_string(), chan.to_string())).unzip();
+            Diagnostic { kind: DiagnosticKind::Rule, stage: DiagnosticStage::Compile, severity: Severity::Error, dev, chan, pos: None, message }
+        }).collect()
+    }
+}
+
+fn high_intervals(view: &StreamerView, name: &str) -> Vec<(f64, f64)> {
+    let Some((dev_name, chan_name)) = name.split_once('/') else {
+        return Vec::new()
+    };
+    view.chan(dev_name, chan_name).and_then(|chan| chan.high_intervals(0.0).ok()).unwrap_or_default()
+}
+
+/// Parts of the sorted, disjoint intervals `a` not covered by the sorted, disjoint intervals `b`
+fn subtract(a: &[(f64, f64)], b: &[(f64, f64)]) -> Vec<(f64, f64)> {
+    let mut res = Vec::new();
+    for &(start, end) in a {
+        let mut cur = start;
+        for &(b_start, b_end) in b.iter().filter(|(b_start, b_end)| *b_end > start && *b_start < end) {
+            if b_start > cur {
+                res.push((cur, b_start))
+            }
+            cur = cur.max(b_end);
+        }
+        if cur < end {
+            res.push((cur, end))
+        }
+    }
+    res
+}
+
+/// Overlaps of the sorted, disjoint intervals `a` and `b`
+fn intersect(a: &[(f64, f64)], b: &[(f64, f64)]) -> Vec<(f64, f64)> {
+    let mut res = Vec::new();
+    let (mut a_idx, mut b_idx) = (0, 0);
+    while a_idx < a.len() && b_idx < b.len() {
+        let start = a[a_idx].0.max(b[b_idx].0);
+        let end = a[a_idx].1.min(b[b_idx].1);
+        if start < end {
+            res.push((start, end))
+        }
+        if a[a_idx].1 < b[b_idx].1 { a_idx += 1 } else { b_idx += 1 }
+    }
+    res
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::channel::BaseChan;
+    use crate::device::BaseDev;
+    use crate::error::StreamerError;
+    use crate::mock::test_impls::TestStreamer;
+    use crate::streamer::BaseStreamer;
+
+    #[test]
+    fn interlocks() {
+        let mut streamer = TestStreamer::new();
+        streamer.add_ao_dev("AO", 1e3);
+        streamer.add_do_dev("DO", 1e2);
+        streamer.ao_devs["AO"].add_chan("aom", 0.0);
+        streamer.do_devs["DO"].add_chan("shutter", false);
+        streamer.do_devs["DO"].add_chan("c", false);
+        streamer.do_devs["DO"].add_chan("d", false);
+        streamer.ao_devs["AO"].chan_mut("aom").unwrap().constant(0.5, 0.1, Some((0.2, false))).unwrap();
+        streamer.ao_devs["AO"].chan_mut("aom").unwrap().constant(0.5, 0.5, Some((0.1, false))).unwrap();
+        // Opens late for the first pulse and in time for the second one
+        streamer.do_devs["DO"].chan_mut("shutter").unwrap().constant(true, 0.15, Some((0.2, false))).unwrap();
+        streamer.do_devs["DO"].chan_mut("shutter").unwrap().constant(true, 0.5, Some((0.1, false))).unwrap();
+        streamer.do_devs["DO"].chan_mut("c").unwrap().constant(true, 0.0, Some((0.3, false))).unwrap();
+        streamer.do_devs["DO"].chan_mut("d").unwrap().constant(true, 0.25, Some((0.05, false))).unwrap();
+        streamer.compile(Some(1.0)).unwrap();
+
+        let view = StreamerView::new(streamer.active_devs());
+        let span = |violation: &Violation| (violation.chan.clone(), violation.other.clone(), violation.start, violation.end);
+        let requires = Interlock::requires("DO/shutter", "AO/aom").violations(&view);
+        assert_eq!(requires.iter().map(span).collect::<Vec<_>>(), vec![("DO/shutter".into(), "AO/aom".into(), 0.1, 0.15)]);
+        assert_eq!(Interlock::requires("AO/aom", "DO/shutter").violations(&view).len(), 1);
+        let exclusive = Interlock::exclusive(&["DO/c", "DO/d", "DO/shutter"]).violations(&view);
+        assert_eq!(exclusive.iter().map(span).collect::<Vec<_>>(), vec![
+            ("DO/c".into(), "DO/shutter".into(), 0.15, 0.3),
+            ("DO/c".into(), "DO/d".into(), 0.25, 0.3),
+            ("DO/d".into(), "DO/shutter".into(), 0.25, 0.3),
+        ]);
+        // Inactive channels are never high
+        assert!(Interlock::requires("DO/nope", "DO/nope2").violations(&view).is_empty());
+        drop(view);
+
+        streamer.add_rule(Box::new(Interlock::requires("DO/shutter", "AO/aom"))).unwrap();
+        let err = streamer.compile(Some(1.0)).unwrap_err();
+        assert!(matches!(err, StreamerError::RuleViolation { .. }));
+        assert_eq!((err.ctx().dev.as_deref(), err.ctx().chan.as_deref()), (Some("DO"), Some("shutter")));
+        assert!(err.to_string().contains("DO/shutter is not high while AO/aom is, from 0.1 s to 0.15 s"));
+
+        streamer.do_devs["DO"].chan_mut("shutter").unwrap().constant(true, 0.05, Some((0.1, false))).unwrap();
+        streamer.compile(Some(1.0)).unwrap();
+    }
+}
@@ -43,58 +43,115 @@
 //!
 //! ## Example usage
 //! ### Rust
+//! A backend implements [`channel::BaseChan`], [`device::BaseDev`], and [`streamer::BaseStreamer`] for its own
+//! channel, device, and streamer types - only the field accessors are required, editing, compiling, and sampling
+//! come with the traits. The optional settings (layers, trigger delay, ...) are left at their "unsupported" defaults here.
 //! ```
-//! use nicompiler_backend::*;
-//! let mut exp = Experiment::new();
-//! // Define devices and associated channels
-//! exp.add_ao_device("PXI1Slot3", 1e6);
-//! exp.add_ao_channel("PXI1Slot3", 0, 0.);
-//!
-//! exp.add_ao_device("PXI1Slot4", 1e6);
-//! exp.add_ao_channel("PXI1Slot4", 0, 0.);
-//!
-//! exp.add_do_device("PXI1Slot6", 1e7);
-//! exp.add_do_channel("PXI1Slot6", 0, 0, 0.);
-//! exp.add_do_channel("PXI1Slot6", 0, 4, 0.);
-//!
-//! // Define synchronization behavior:
-//! exp.device_cfg_trig("PXI1Slot3", "PXI1_Trig0", true);
-//! exp.device_cfg_ref_clk("PXI1Slot3", "PXI1_Trig7", 1e7, true);
-//!
-//! exp.device_cfg_trig("PXI1Slot4", "PXI1_Trig0", false);
-//! exp.device_cfg_ref_clk("PXI1Slot4", "PXI1_Trig7", 1e7, false);
-//!
-//! exp.device_cfg_samp_clk_src("PXI1Slot6", "PXI1_Trig7");
-//! exp.device_cfg_trig("PXI1Slot6", "PXI1_Trig0", false);
-//!
-//! // PXI1Slot3/ao0 starts with a 1s-long 7Hz sine wave with offset 1
-//! // and unit amplitude, zero phase. Does not keep its value.
-//! exp.sine("PXI1Slot3", "ao0", 0., 1., false, 7., None, None, Some(1.));
-//! // Ends with a half-second long 1V constant signal which returns to zero
-//! exp.constant("PXI1Slot3", "ao0", 9., 0.5, 1., false);
-//!
-//! // We can also leave a defined channel empty: the device / channel will simply not be compiled
-//!
-//! // Both lines of PXI1Slot6 start with a one-second "high" at t=0 and a half-second high at t=9
-//! exp.high("PXI1Slot6", "port0/line0", 0., 1.);
-//! exp.high("PXI1Slot6", "port0/line0", 9., 0.5);
-//! // Alternatively, we can also define the same behavior via go_high/go_low
-//! exp.go_high("PXI1Slot6", "port0/line4", 0.);
-//! exp.go_low("PXI1Slot6", "port0/line4", 1.);
-//!
-//! exp.go_high("PXI1Slot6", "port0/line4", 9.);
-//! exp.go_low("PXI1Slot6", "port0/line4", 9.5);
-//!
-//! // Compile the experiment: this will stop the experiment at the last edit-time plus one tick
-//! exp.compile(true);
-//!
-//! // We can compile again with a specific stop_time (and add instructions in between)
-//! exp.compile_with_stoptime(10.); // Experiment signal will stop at t=10 now
-//! assert_eq!(exp.compiled_stop_time(), 10.);
+//! use std::collections::BTreeSet;
+//! use std::sync::Arc;
+//! use indexmap::IndexMap;
+//! use base_streamer::channel::{AsF64, BaseChan};
+//! use base_streamer::device::BaseDev;
+//! use base_streamer::diagnostics::Diagnostics;
+//! use base_streamer::fn_lib_tools::{FnTraitSet, StdFnLib};
+//! use base_streamer::instruction::Instr;
+//! use base_streamer::streamer::{BaseStreamer, TagBaseDev};
+//!
+//! #[derive(Default)]
+//! struct Chan<T> {
+//!     name: String,
+//!     samp_rate: f64,
+//!     dflt_val: T,
+//!     instr_list: BTreeSet<Instr<T>>,
+//!     compile_cache_ends: Vec<usize>,
+//!     compile_cache_fns: Vec<Arc<dyn FnTraitSet<T>>>,
+//!     is_fresh_compiled: bool,
+//!     diagnostics: Diagnostics,
+//! }
+//! impl<T: Clone + Default + PartialEq + std::fmt::Debug + AsF64 + Send + Sync + 'static> BaseChan for Chan<T> {
+//!     type Samp = T;
+//!     fn name(&self) -> String { self.name.clone() }
+//!     fn samp_rate(&self) -> f64 { self.samp_rate }
+//!     fn dflt_val(&self) -> T { self.dflt_val.clone() }
+//!     fn rst_val(&self) -> T { self.dflt_val.clone() }
+//!     fn instr_list(&self) -> &BTreeSet<Instr<T>> { &self.instr_list }
+//!     fn compile_cache_ends(&self) -> &Vec<usize> { &self.compile_cache_ends }
+//!     fn compile_cache_fns(&self) -> &Vec<Arc<dyn FnTraitSet<T>>> { &self.compile_cache_fns }
+//!     fn is_fresh_compiled(&self) -> bool { self.is_fresh_compiled }
+//!     fn diagnostics(&self) -> &Diagnostics { &self.diagnostics }
+//!     fn instr_list_mut(&mut self) -> &mut BTreeSet<Instr<T>> { &mut self.instr_list }
+//!     fn compile_cache_ends_mut(&mut self) -> &mut Vec<usize> { &mut self.compile_cache_ends }
+//!     fn compile_cache_fns_mut(&mut self) -> &mut Vec<Arc<dyn FnTraitSet<T>>> { &mut self.compile_cache_fns }
+//!     fn is_fresh_compiled_mut(&mut self) -> &mut bool { &mut self.is_fresh_compiled }
+//!     fn diagnostics_mut(&mut self) -> &mut Diagnostics { &mut self.diagnostics }
+//! }
+//!
+//! struct Dev<T> {
+//!     name: String,
+//!     samp_rate: f64,
+//!     chans: IndexMap<String, Chan<T>>,
+//!     diagnostics: Diagnostics,
+//! }
+//! impl<T: Clone + Default + PartialEq + std::fmt::Debug + AsF64 + Send + Sync + 'static> Dev<T> {
+//!     fn new(name: &str, samp_rate: f64) -> Self {
+//!         Self { name: name.to_string(), samp_rate, chans: IndexMap::new(), diagnostics: Diagnostics::new() }
+//!     }
+//!     fn add_chan(&mut self, name: &str, dflt_val: T) {
+//!         let chan = Chan { name: name.to_string(), samp_rate: self.samp_rate, dflt_val, is_fresh_compiled: true, ..Default::default() };
+//!         self.check_can_add_chan(&chan).unwrap();
+//!         self.chans.insert(name.to_string(), chan);
+//!     }
+//! }
+//! impl<T: Clone + Default + PartialEq + std::fmt::Debug + AsF64 + Send + Sync + 'static> BaseDev for Dev<T> {
+//!     type Chan = Chan<T>;
+//!     fn name(&self) -> String { self.name.clone() }
+//!     fn samp_rate(&self) -> f64 { self.samp_rate }
+//!     fn chans(&self) -> Vec<&Chan<T>> { self.chans.values().collect() }
+//!     fn chans_mut(&mut self) -> Vec<&mut Chan<T>> { self.chans.values_mut().collect() }
+//!     fn diagnostics(&self) -> &Diagnostics { &self.diagnostics }
+//!     fn diagnostics_mut(&mut self) -> &mut Diagnostics { &mut self.diagnostics }
+//! }
+//!
+//! struct Streamer {
+//!     ao: Dev<f64>,
+//!     dio: Dev<bool>,
+//! }
+//! impl BaseStreamer for Streamer {
+//!     fn devs(&self) -> Vec<&dyn TagBaseDev> { vec![&self.ao, &self.dio] }
+//!     fn devs_mut(&mut self) -> Vec<&mut dyn TagBaseDev> { vec![&mut self.ao, &mut self.dio] }
+//! }
+//!
+//! let mut streamer = Streamer { ao: Dev::new("PXI1Slot3", 1e6), dio: Dev::new("PXI1Slot6", 1e7) };
+//! streamer.ao.add_chan("ao0", 0.0);
+//! streamer.dio.add_chan("port0/line0", false);
+//! streamer.dio.add_chan("port0/line4", false);
+//!
+//! // PXI1Slot3/ao0 starts with a 1s-long 7Hz sine wave with offset 1 and unit amplitude, which does not keep its value,
+//! // and ends with a half-second long 1V constant signal which returns to zero
+//! let ao0 = streamer.ao.chan_mut("ao0").unwrap();
+//! ao0.add_instr(StdFnLib::new().Sine(1.0, 7.0, 0.0, 1.0).unwrap().inner, 0.0, Some((1.0, false))).unwrap();
+//! ao0.constant(1.0, 9.0, Some((0.5, false))).unwrap();
+//!
+//! // A one-second "high" at t=0 and a half-second "high" at t=9 - with a duration or with a "go-this" instruction
+//! let line0 = streamer.dio.chan_mut("port0/line0").unwrap();
+//! line0.constant(true, 0.0, Some((1.0, false))).unwrap();
+//! line0.constant(true, 9.0, Some((0.5, false))).unwrap();
+//! let line4 = streamer.dio.chan_mut("port0/line4").unwrap();
+//! for (t, val) in [(0.0, true), (1.0, false), (9.0, true), (9.5, false)] {
+//!     line4.constant(val, t, None).unwrap();
+//! }
+//!
+//! // Without a stop time the sequence ends with the last instruction (plus the closing edge sample)
+//! streamer.compile(None).unwrap();
+//! // A later stop time holds the last values
+//! streamer.compile(Some(10.0)).unwrap();
+//! assert_eq!(streamer.ao.compiled_stop_time(), 10.0);
+//! let samps = streamer.dio.chan("port0/line4").unwrap().calc_nsamps(5, Some(0.0), Some(10.0)).unwrap();
+//! assert_eq!(samps, vec![true, false, false, false, false]);
 //! ```
 //!
 //! ### Python
-//! Functionally the same code, additionally samples and plots the signal for `PXI1Slot6/port0/line4`.
+//! Defines a few devices and channels, compiles the experiment, then samples and plots the signal for `PXI1Slot6/port0/line4`.
 //! The primary goal of the `Experiment` object is to expose a complete set of fast rust-implemented methods
 //! for interfacing with a NI experiment. One may easily customize syntactic sugar and higher-level abstractions
 //! by wrapping `nicompiler_backend` module in another layer of python code,
@@ -188,5 +245,50 @@ pub mod instruction;
 pub mod channel;
 pub mod device;
 pub mod streamer;
+pub mod mock;
+pub mod diff;
+pub mod hash;
+pub mod diagnostics;
+pub mod error;
+pub mod validation;
+pub mod collisions;
+pub mod inspect;
+pub mod summary;
+pub mod budget;
+pub mod dry_run;
+pub mod events;
+pub mod timeline;
+pub mod rules;
+pub mod interlocks;
+pub mod options;
+pub mod profiling;
+pub mod py_tools;
+pub mod marker;
+pub mod dead_time;
+pub mod selection;
+pub mod abort;
+pub mod padding;
+pub mod replay;
+pub mod resample;
+pub mod analysis;
+pub mod idle;
+pub mod export;
+pub mod sync;
+pub mod skew;
+pub mod rounding;
+pub mod closing_edge;
+pub mod units;
+pub mod spi;
+pub mod quantity;
+pub mod schedule;
+pub mod openpulse;
+pub mod shots;
+#[cfg(feature = "server")]
+pub mod server;
+#[cfg(feature = "test-utils")]
+pub mod golden;
+#[cfg(feature = "test-utils")]
+pub mod prop_tools;
 
 pub use fn_lib_tools::usr_lib_prelude;
+pub use error::{ErrCtx, StreamerError};
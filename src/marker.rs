@@ -0,0 +1,63 @@
+//! Digital marker channels following analog channels automatically.
+//!
+//! A [`Marker`] binds a boolean destination channel to a source channel (typically analog, possibly on another device)
+//! through a [`MarkerRule`]. On every [`BaseStreamer::compile`] the destination channel is regenerated from the compiled
+//! source: its edit cache is replaced by one "high" instruction per marker interval and its device is compiled again.
+//! The destination channel is owned by the marker - instructions added to it by hand are discarded at compile.
+//!
+//! Marker intervals are found on the source clock grid and rounded to the destination clock. Intervals which touch
+//! or overlap after rounding are merged, intervals shorter than one destination clock period are dropped.
+//!
+//! [`BaseStreamer::compile`]: crate::streamer::BaseStreamer::compile
+
+use std::fmt;
+use std::fmt::Display;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum MarkerRule {
+    /// High wherever `|signal| > threshold`
+    Threshold(f64),
+    /// High during every instruction interval of the source channel (including override layers).
+    /// A "go-this" instruction lasts until the next instruction.
+    DuringInstr,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Marker {
+    pub src_dev: String,
+    pub src_chan: String,
+    /// Device of the marker channel. Its channels must have `bool` samples
+    pub dst_dev: String,
+    pub dst_chan: String,
+    pub rule: MarkerRule,
+}
+
+impl Marker {
+    pub fn new(src_dev: &str, src_chan: &str, dst_dev: &str, dst_chan: &str, rule: MarkerRule) -> Self {
+        Self {
+            src_dev: src_dev.to_string(),
+            src_chan: src_chan.to_string(),
+            dst_dev: dst_dev.to_string(),
+            dst_chan: dst_chan.to_string(),
+            rule,
+        }
+    }
+}
+
+impl Display for Marker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{} -> {}/{} ({:?})", self.src_dev, self.src_chan, self.dst_dev, self.dst_chan, self.rule)
+    }
+}
+
+/// Appends `[start, end)` to sorted, disjoint `intervals`, merging it with the last one if they touch or overlap.
+/// `start` must not be below the start of the last interval.
+pub(crate) fn push_merged(intervals: &mut Vec<(usize, usize)>, start: usize, end: usize) {
+    if end <= start {
+        return
+    }
+    match intervals.last_mut() {
+        Some(last) if start <= last.1 => last.1 = last.1.max(end),
+        _ => intervals.push((start, end)),
+    }
+}
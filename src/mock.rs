@@ -0,0 +1,536 @@
+//! Software-only streaming target for end-to-end testing without hardware.
+//!
+//! Downstream hardware crates stream compiled samples chunk by chunk into the driver buffers.
+//! [`MockStreamTarget`] plays the role of such a driver buffer: it consumes chunks produced by
+//! [`BaseDev::calc_samps`] and records them, so the complete "compile -> calc_samps -> write" path
+//! can be exercised in CI where no hardware is attached.
+//!
+//! Use [`BaseDev::run_mock`] to stream a single device or [`BaseStreamer::run_mock`] to stream
+//! every active device of a streamer.
+//!
+//! [`BaseDev::calc_samps`]: crate::device::BaseDev::calc_samps
+//! [`BaseDev::run_mock`]: crate::device::BaseDev::run_mock
+//! [`BaseStreamer::run_mock`]: crate::streamer::BaseStreamer::run_mock
+
+use crate::error::{ErrCtx, StreamerError};
+
+/// A single recorded chunk.
+///
+/// `samps` has the same layout as the `samp_buf` passed to [`BaseDev::calc_samps`]:
+/// channel rows of `end_pos - start_pos` samples each, in the order of the device's active channels.
+///
+/// [`BaseDev::calc_samps`]: crate::device::BaseDev::calc_samps
+pub struct MockChunk<T> {
+    pub start_pos: usize,
+    pub end_pos: usize,
+    pub samps: Vec<T>,
+}
+
+/// Minimal "consume chunks, record them" sink.
+///
+/// Like a real driver buffer, the target expects chunks to arrive back-to-back starting from `start_pos = 0`
+/// and with the number of samples matching `n_chans * (end_pos - start_pos)`. Violations are returned as `Err`.
+pub struct MockStreamTarget<T> {
+    name: String,
+    chan_names: Vec<String>,
+    chunks: Vec<MockChunk<T>>,
+}
+
+impl<T: Clone> MockStreamTarget<T> {
+    pub fn new(name: &str, chan_names: Vec<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            chan_names,
+            chunks: Vec::new(),
+        }
+    }
+
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+    pub fn chan_names(&self) -> &Vec<String> {
+        &self.chan_names
+    }
+    pub fn chunks(&self) -> &Vec<MockChunk<T>> {
+        &self.chunks
+    }
+
+    /// Number of samples per channel recorded so far
+    pub fn total_samps(&self) -> usize {
+        self.chunks.last().map_or(0, |chunk| chunk.end_pos)
+    }
+
+    /// Records the next chunk of samples.
+    pub fn consume(&mut self, start_pos: usize, end_pos: usize, samp_buf: &[T]) -> Result<(), StreamerError> {
+        if start_pos != self.total_samps() {
+            return Err(StreamerError::OutOfRange {
+                ctx: ErrCtx::dev(self.name.clone()),
+                msg: format!(
+                    "mock target chunk start_pos={start_pos} does not continue the recorded stream which ends at {}",
+                    self.total_samps()
+                ),
+            })
+        }
+        if end_pos <= start_pos {
+            return Err(StreamerError::InvalidArgument {
+                ctx: ErrCtx::dev(self.name.clone()),
+                msg: format!("invalid mock target chunk start_pos={start_pos}, end_pos={end_pos} - end_pos must be no less than start_pos + 1"),
+            })
+        }
+        let expected_len = self.chan_names.len() * (end_pos - start_pos);
+        if samp_buf.len() != expected_len {
+            return Err(StreamerError::InvalidArgument {
+                ctx: ErrCtx::dev(self.name.clone()),
+                msg: format!("mock target got samp_buf.len()={} while n_chans*n_samps={expected_len}", samp_buf.len()),
+            })
+        }
+        self.chunks.push(MockChunk {
+            start_pos,
+            end_pos,
+            samps: samp_buf.to_vec(),
+        });
+        Ok(())
+    }
+
+    /// Returns the full recorded sample stream of the given channel, stitched across all chunks.
+    pub fn chan_samps(&self, name: &str) -> Result<Vec<T>, StreamerError> {
+        let row_idx = self.chan_names
+            .iter()
+            .position(|chan_name| chan_name == name)
+            .ok_or_else(|| StreamerError::NotFound {
+                ctx: ErrCtx::dev(self.name.clone()),
+                msg: format!("no channel {name} was recorded by the mock target. Recorded channels are {:?}", self.chan_names),
+            })?;
+
+        let mut res = Vec::with_capacity(self.total_samps());
+        for chunk in self.chunks.iter() {
+            let n_samps = chunk.end_pos - chunk.start_pos;
+            res.extend_from_slice(&chunk.samps[row_idx * n_samps..(row_idx + 1) * n_samps]);
+        }
+        Ok(res)
+    }
+}
+
+/// Minimal channel, device, and streamer implementations used by unit tests across the crate.
+#[cfg(test)]
+pub mod test_impls {
+    use std::collections::BTreeSet;
+    use std::sync::Arc;
+    use indexmap::IndexMap;
+    use crate::channel::{AsF64, BaseChan, ChanSettings};
+    use crate::device::{BaseDev, DevSettings};
+    use crate::diagnostics::Diagnostics;
+    use crate::profiling::Profile;
+    use crate::fn_lib_tools::FnTraitSet;
+    use crate::instruction::Instr;
+    use crate::marker::Marker;
+    use crate::quantity::Quantity;
+    use crate::events::Observers;
+    use crate::rules::ValidationRule;
+    use crate::selection::StreamSelection;
+    use crate::streamer::{BaseStreamer, TagBaseDev};
+    use crate::error::StreamerError;
+
+    /// Test-defined [`BaseChan::post_compile_hook`] / [`BaseDev::post_compile_hook`]
+    pub type PostCompile<S> = fn(&mut S) -> Result<(), StreamerError>;
+
+    pub struct TestChan<T> {
+        name: String,
+        samp_rate: f64,
+        dflt_val: T,
+        rst_val: T,
+        instr_list: BTreeSet<Instr<T>>,
+        is_event_chan: bool,
+        quantity: Quantity,
+        val_range: Option<(f64, f64)>,
+        compile_cache_ends: Vec<usize>,
+        compile_cache_fns: Vec<Arc<dyn FnTraitSet<T>>>,
+        is_fresh_compiled: bool,
+        settings: ChanSettings<T>,
+        post_compile: Option<PostCompile<Self>>,
+        diagnostics: Diagnostics,
+        profile: Arc<Profile>,
+    }
+    impl<T: Clone> TestChan<T> {
+        pub fn new(name: &str, samp_rate: f64, dflt_val: T) -> Self {
+            Self {
+                name: name.to_string(),
+                samp_rate,
+                dflt_val: dflt_val.clone(),
+                rst_val: dflt_val,
+                instr_list: BTreeSet::new(),
+                is_event_chan: false,
+                quantity: Quantity::Voltage,
+                val_range: None,
+                compile_cache_ends: Vec::new(),
+                compile_cache_fns: Vec::new(),
+                is_fresh_compiled: true,
+                settings: ChanSettings::default(),
+                post_compile: None,
+                diagnostics: Diagnostics::new(),
+                profile: Arc::new(Profile::new()),
+            }
+        }
+        /// Sets the sample quantity and value range, see [`BaseChan::quantity`] and [`BaseChan::val_range`]
+        pub fn set_quantity(&mut self, quantity: Quantity, val_range: Option<(f64, f64)>) {
+            self.quantity = quantity;
+            self.val_range = val_range.or(quantity.default_range());
+        }
+        /// Event-list channel, see [`BaseChan::is_event_chan`]
+        pub fn new_event(name: &str, samp_rate: f64, dflt_val: T) -> Self {
+            Self { is_event_chan: true, ..Self::new(name, samp_rate, dflt_val) }
+        }
+        /// Function run by [`BaseChan::post_compile_hook`]
+        pub fn set_post_compile(&mut self, hook: PostCompile<Self>) {
+            self.post_compile = Some(hook);
+        }
+    }
+    // The clone gets its own (empty) profile
+    impl<T: Clone> Clone for TestChan<T> {
+        fn clone(&self) -> Self {
+            Self {
+                name: self.name.clone(),
+                samp_rate: self.samp_rate,
+                dflt_val: self.dflt_val.clone(),
+                rst_val: self.rst_val.clone(),
+                instr_list: self.instr_list.clone(),
+                is_event_chan: self.is_event_chan,
+                quantity: self.quantity,
+                val_range: self.val_range,
+                compile_cache_ends: self.compile_cache_ends.clone(),
+                compile_cache_fns: self.compile_cache_fns.clone(),
+                is_fresh_compiled: self.is_fresh_compiled,
+                settings: self.settings.clone(),
+                post_compile: self.post_compile,
+                diagnostics: self.diagnostics.clone(),
+                profile: Arc::new(Profile::new()),
+            }
+        }
+    }
+    impl<T: Clone + Default + PartialEq + std::fmt::Debug + AsF64 + Send + Sync + 'static> BaseChan for TestChan<T> {
+        type Samp = T;
+
+        fn name(&self) -> String {
+            self.name.clone()
+        }
+        fn samp_rate(&self) -> f64 {
+            self.samp_rate
+        }
+        fn dflt_val(&self) -> T {
+            self.dflt_val.clone()
+        }
+        fn rst_val(&self) -> T {
+            self.rst_val.clone()
+        }
+        fn instr_list(&self) -> &BTreeSet<Instr<T>> {
+            &self.instr_list
+        }
+        fn compile_cache_ends(&self) -> &Vec<usize> {
+            &self.compile_cache_ends
+        }
+        fn compile_cache_fns(&self) -> &Vec<Arc<dyn FnTraitSet<T>>> {
+            &self.compile_cache_fns
+        }
+        fn is_fresh_compiled(&self) -> bool {
+            self.is_fresh_compiled
+        }
+        fn instr_list_mut(&mut self) -> &mut BTreeSet<Instr<T>> {
+            &mut self.instr_list
+        }
+        fn compile_cache_ends_mut(&mut self) -> &mut Vec<usize> {
+            &mut self.compile_cache_ends
+        }
+        fn compile_cache_fns_mut(&mut self) -> &mut Vec<Arc<dyn FnTraitSet<T>>> {
+            &mut self.compile_cache_fns
+        }
+        fn is_fresh_compiled_mut(&mut self) -> &mut bool {
+            &mut self.is_fresh_compiled
+        }
+        fn diagnostics(&self) -> &Diagnostics {
+            &self.diagnostics
+        }
+        fn diagnostics_mut(&mut self) -> &mut Diagnostics {
+            &mut self.diagnostics
+        }
+        fn profile(&self) -> Option<Arc<Profile>> {
+            Some(self.profile.clone())
+        }
+        fn settings(&self) -> Option<&ChanSettings<T>> {
+            Some(&self.settings)
+        }
+        fn settings_mut(&mut self) -> Option<&mut ChanSettings<T>> {
+            Some(&mut self.settings)
+        }
+        fn is_event_chan(&self) -> bool {
+            self.is_event_chan
+        }
+        fn quantity(&self) -> Quantity {
+            self.quantity
+        }
+        fn val_range(&self) -> Option<(f64, f64)> {
+            self.val_range
+        }
+        fn post_compile_hook(&mut self) -> Result<(), StreamerError> {
+            self.post_compile.map_or(Ok(()), |hook| hook(self))
+        }
+    }
+
+    #[derive(Clone)]
+    pub struct TestDev<T> {
+        name: String,
+        samp_rate: f64,
+        chans: IndexMap<String, TestChan<T>>,
+        diagnostics: Diagnostics,
+        settings: DevSettings,
+        post_compile: Option<PostCompile<Self>>,
+    }
+    impl<T: Clone + Default + PartialEq + std::fmt::Debug + AsF64 + Send + Sync + 'static> TestDev<T> {
+        pub fn new(name: &str, samp_rate: f64) -> Self {
+            Self {
+                name: name.to_string(),
+                samp_rate,
+                chans: IndexMap::new(),
+                diagnostics: Diagnostics::new(),
+                settings: DevSettings::default(),
+                post_compile: None,
+            }
+        }
+        pub fn add_chan(&mut self, name: &str, dflt_val: T) {
+            let chan = TestChan::new(name, self.samp_rate, dflt_val);
+            self.check_can_add_chan(&chan).unwrap();
+            self.chans.insert(name.to_string(), chan);
+        }
+        pub fn add_event_chan(&mut self, name: &str, dflt_val: T) {
+            let chan = TestChan::new_event(name, self.samp_rate, dflt_val);
+            self.check_can_add_chan(&chan).unwrap();
+            self.chans.insert(name.to_string(), chan);
+        }
+        /// Function run by [`BaseDev::post_compile_hook`]
+        pub fn set_post_compile(&mut self, hook: PostCompile<Self>) {
+            self.post_compile = Some(hook);
+        }
+    }
+    impl<T: Clone + Default + PartialEq + std::fmt::Debug + AsF64 + Send + Sync + 'static> BaseDev for TestDev<T> {
+        type Chan = TestChan<T>;
+
+        fn name(&self) -> String {
+            self.name.clone()
+        }
+        fn samp_rate(&self) -> f64 {
+            self.samp_rate
+        }
+        fn chans(&self) -> Vec<&TestChan<T>> {
+            self.chans.values().collect()
+        }
+        fn chans_mut(&mut self) -> Vec<&mut TestChan<T>> {
+            self.chans.values_mut().collect()
+        }
+        fn diagnostics(&self) -> &Diagnostics {
+            &self.diagnostics
+        }
+        fn diagnostics_mut(&mut self) -> &mut Diagnostics {
+            &mut self.diagnostics
+        }
+        fn settings(&self) -> Option<&DevSettings> {
+            Some(&self.settings)
+        }
+        fn settings_mut(&mut self) -> Option<&mut DevSettings> {
+            Some(&mut self.settings)
+        }
+        fn post_compile_hook(&mut self) -> Result<(), StreamerError> {
+            self.post_compile.map_or(Ok(()), |hook| hook(self))
+        }
+    }
+
+    /// Streamer with separate maps for analog (`f64`) and digital (`bool`) devices
+    #[derive(Default)]
+    pub struct TestStreamer {
+        pub ao_devs: IndexMap<String, TestDev<f64>>,
+        pub do_devs: IndexMap<String, TestDev<bool>>,
+        pub markers: Vec<Marker>,
+        pub rules: Vec<Box<dyn ValidationRule>>,
+        pub selection: StreamSelection,
+        pub samp_limit: Option<usize>,
+        pub observers: Observers,
+    }
+    // Validation rules and observers are boxed trait objects and are not cloned
+    impl Clone for TestStreamer {
+        fn clone(&self) -> Self {
+            Self {
+                ao_devs: self.ao_devs.clone(),
+                do_devs: self.do_devs.clone(),
+                markers: self.markers.clone(),
+                rules: Vec::new(),
+                selection: self.selection.clone(),
+                samp_limit: self.samp_limit,
+                observers: Observers::new(),
+            }
+        }
+    }
+    impl TestStreamer {
+        pub fn new() -> Self {
+            Self::default()
+        }
+        pub fn add_ao_dev(&mut self, name: &str, samp_rate: f64) {
+            self.check_can_add_dev(name.to_string()).unwrap();
+            self.ao_devs.insert(name.to_string(), TestDev::new(name, samp_rate));
+        }
+        pub fn add_do_dev(&mut self, name: &str, samp_rate: f64) {
+            self.check_can_add_dev(name.to_string()).unwrap();
+            self.do_devs.insert(name.to_string(), TestDev::new(name, samp_rate));
+        }
+    }
+    impl BaseStreamer for TestStreamer {
+        fn devs(&self) -> Vec<&dyn TagBaseDev> {
+            let mut devs: Vec<&dyn TagBaseDev> = Vec::new();
+            devs.extend(self.ao_devs.values().map(|dev| dev as &dyn TagBaseDev));
+            devs.extend(self.do_devs.values().map(|dev| dev as &dyn TagBaseDev));
+            devs
+        }
+        fn devs_mut(&mut self) -> Vec<&mut dyn TagBaseDev> {
+            let mut devs: Vec<&mut dyn TagBaseDev> = Vec::new();
+            devs.extend(self.ao_devs.values_mut().map(|dev| dev as &mut dyn TagBaseDev));
+            devs.extend(self.do_devs.values_mut().map(|dev| dev as &mut dyn TagBaseDev));
+            devs
+        }
+        fn markers(&self) -> Option<&Vec<Marker>> {
+            Some(&self.markers)
+        }
+        fn markers_mut(&mut self) -> Option<&mut Vec<Marker>> {
+            Some(&mut self.markers)
+        }
+        fn rules(&self) -> Option<&Vec<Box<dyn ValidationRule>>> {
+            Some(&self.rules)
+        }
+        fn rules_mut(&mut self) -> Option<&mut Vec<Box<dyn ValidationRule>>> {
+            Some(&mut self.rules)
+        }
+        fn selection(&self) -> Option<&StreamSelection> {
+            Some(&self.selection)
+        }
+        fn selection_mut(&mut self) -> Option<&mut StreamSelection> {
+            Some(&mut self.selection)
+        }
+        fn samp_limit(&self) -> Option<&Option<usize>> {
+            Some(&self.samp_limit)
+        }
+        fn samp_limit_mut(&mut self) -> Option<&mut Option<usize>> {
+            Some(&mut self.samp_limit)
+        }
+        fn observers(&self) -> Option<&Observers> {
+            Some(&self.observers)
+        }
+        fn observers_mut(&mut self) -> Option<&mut Observers> {
+            Some(&mut self.observers)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::channel::{BaseChan, ConstFn};
+    use crate::device::BaseDev;
+    use crate::mock::MockStreamTarget;
+    use crate::mock::test_impls::*;
+    use crate::streamer::BaseStreamer;
+
+    #[test]
+    fn consume_checks() {
+        let mut target = MockStreamTarget::new("Dev1", vec!["ao0".to_string(), "ao1".to_string()]);
+        // Chunk must start at 0
+        assert!(target.consume(1, 3, &[0.0; 4]).is_err());
+        // Buffer length must match n_chans * n_samps
+        assert!(target.consume(0, 2, &[0.0; 3]).is_err());
+
+        target.consume(0, 2, &[1.0, 2.0, 10.0, 20.0]).unwrap();
+        target.consume(2, 3, &[3.0, 30.0]).unwrap();
+        // Gap between chunks
+        assert!(target.consume(4, 5, &[0.0; 2]).is_err());
+
+        assert_eq!(target.total_samps(), 3);
+        assert_eq!(target.chan_samps("ao0").unwrap(), vec![1.0, 2.0, 3.0]);
+        assert_eq!(target.chan_samps("ao1").unwrap(), vec![10.0, 20.0, 30.0]);
+        assert!(target.chan_samps("ao2").is_err());
+    }
+
+    #[test]
+    fn dev_run_mock() {
+        let mut dev = TestDev::new("Dev1", 10.0);
+        dev.add_chan("ao0", 0.0);
+        dev.add_chan("ao1", -1.0);
+        dev.chan_mut("ao0").unwrap().add_instr(Box::new(ConstFn::new(1.0)), 0.2, Some((0.3, false))).unwrap();
+        dev.chan_mut("ao1").unwrap().add_instr(Box::new(ConstFn::new(2.0)), 0.5, None).unwrap();
+
+        // Not compiled yet
+        assert!(dev.run_mock(3).is_err());
+
+        dev.compile(1.0).unwrap();
+        assert!(dev.run_mock(0).is_err());
+        let target = dev.run_mock(3).unwrap();
+
+        assert_eq!(target.chunks().len(), 4);
+        assert_eq!(target.total_samps(), 10);
+        assert_eq!(
+            target.chan_samps("ao0").unwrap(),
+            vec![0.0, 0.0, 1.0, 1.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0]
+        );
+        assert_eq!(
+            target.chan_samps("ao1").unwrap(),
+            vec![-1.0, -1.0, -1.0, -1.0, -1.0, 2.0, 2.0, 2.0, 2.0, 2.0]
+        );
+    }
+
+    #[test]
+    fn streamer_run_mock() {
+        let mut streamer = TestStreamer::new();
+        streamer.add_ao_dev("AO", 10.0);
+        streamer.add_do_dev("DO", 100.0);
+        streamer.add_ao_dev("Idle", 10.0);
+        streamer.ao_devs["AO"].add_chan("ao0", 0.0);
+        streamer.do_devs["DO"].add_chan("port0/line0", false);
+        streamer.ao_devs["AO"].chan_mut("ao0").unwrap().constant(1.0, 0.0, Some((0.5, true))).unwrap();
+        streamer.do_devs["DO"].chan_mut("port0/line0").unwrap().constant(true, 0.1, Some((0.1, false))).unwrap();
+
+        streamer.compile(Some(1.0)).unwrap();
+        let targets = streamer.run_mock(7).unwrap();
+
+        // Inactive devices are not streamed
+        assert_eq!(targets.keys().collect::<Vec<_>>(), vec!["AO", "DO"]);
+
+        let ao_target = targets["AO"].downcast_ref::<MockStreamTarget<f64>>().unwrap();
+        assert_eq!(ao_target.chan_samps("ao0").unwrap(), vec![1.0; 10]);
+
+        let do_target = targets["DO"].downcast_ref::<MockStreamTarget<bool>>().unwrap();
+        let line_samps = do_target.chan_samps("port0/line0").unwrap();
+        assert_eq!(line_samps.len(), 100);
+        assert_eq!(line_samps.iter().filter(|&&samp| samp).count(), 10);
+        assert!(line_samps[10..20].iter().all(|&samp| samp));
+    }
+
+    #[test]
+    fn solo() {
+        let mut streamer = TestStreamer::new();
+        streamer.add_ao_dev("AO", 10.0);
+        streamer.add_do_dev("DO", 10.0);
+        streamer.ao_devs["AO"].add_chan("ao0", 0.0);
+        streamer.ao_devs["AO"].add_chan("ao1", -1.0);
+        streamer.do_devs["DO"].add_chan("port0/line0", false);
+        streamer.ao_devs["AO"].chan_mut("ao0").unwrap().constant(1.0, 0.0, Some((0.5, true))).unwrap();
+        streamer.ao_devs["AO"].chan_mut("ao1").unwrap().constant(2.0, 0.0, Some((0.5, true))).unwrap();
+        streamer.do_devs["DO"].chan_mut("port0/line0").unwrap().constant(true, 0.1, Some((0.1, false))).unwrap();
+        streamer.compile(Some(1.0)).unwrap();
+
+        assert!(matches!(streamer.solo(&["AO/nope"]), Err(crate::error::StreamerError::NotFound { .. })));
+        streamer.solo(&["AO/ao1"]).unwrap();
+        assert_eq!(streamer.streamed_devs().len(), 1);
+        let targets = streamer.run_mock(4).unwrap();
+        assert_eq!(targets.keys().collect::<Vec<_>>(), vec!["AO"]);
+        // Unselected channels of a streamed device keep their default value
+        let ao_target = targets["AO"].downcast_ref::<MockStreamTarget<f64>>().unwrap();
+        assert_eq!(ao_target.chan_samps("ao0").unwrap(), vec![0.0; 10]);
+        assert_eq!(ao_target.chan_samps("ao1").unwrap(), vec![2.0; 10]);
+
+        streamer.clear_solo();
+        assert_eq!(streamer.run_mock(4).unwrap().len(), 2);
+    }
+}
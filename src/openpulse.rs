@@ -0,0 +1,154 @@
+//! OpenPulse/Qobj-like description of the compiled schedule - for cross-checking sequences against quantum-control tooling.
+//!
+//! Every active channel becomes a frame named `"<device>/<channel>"` with the sample period `dt` of its device.
+//! The compiled waveform of the channel is walked segment by segment:
+//! - constant segments at the channel default value become `delay` instructions;
+//! - other constant segments become `parametric_pulse` instructions with `pulse_shape: "constant"`;
+//! - all remaining segments are sampled into `pulse_library` waveforms played by instructions named after them.
+//!   Identical waveforms (on any channel) are stored once.
+//!
+//! Adjacent constant segments holding the same value are merged. Times (`t0`, `duration`) are in clock ticks of the frame,
+//! samples and amplitudes are `[re, im]` pairs with `im = 0`. Instructions are sorted by `t0`.
+//!
+//! Produced by [`BaseStreamer::export_openpulse`].
+//!
+//! [`BaseStreamer::export_openpulse`]: crate::streamer::BaseStreamer::export_openpulse
+
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use crate::channel::{BaseChan, ChanSampCursor, samp_to_f64};
+use crate::error::StreamerError;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Frame {
+    pub name: String,
+    /// Sample period in seconds
+    pub dt: f64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Waveform {
+    pub name: String,
+    pub samples: Vec<[f64; 2]>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ConstParams {
+    pub duration: usize,
+    pub amp: [f64; 2],
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PulseInstr {
+    /// `"delay"`, `"parametric_pulse"`, or the name of a `pulse_library` waveform to play
+    pub name: String,
+    /// Frame name
+    pub ch: String,
+    pub t0: usize,
+    /// Set for `delay`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration: Option<usize>,
+    /// Set for `parametric_pulse`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pulse_shape: Option<String>,
+    /// Set for `parametric_pulse`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parameters: Option<ConstParams>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PulseQobj {
+    pub frames: Vec<Frame>,
+    pub pulse_library: Vec<Waveform>,
+    pub instructions: Vec<PulseInstr>,
+    /// Sample bits -> `pulse_library` index, to store identical waveforms once
+    #[serde(skip)]
+    waveform_ids: HashMap<Vec<u64>, usize>,
+}
+
+impl PulseQobj {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("PulseQobj only holds JSON-compatible values")
+    }
+
+    /// Name of the `pulse_library` waveform with these samples, adding it if needed
+    fn waveform(&mut self, samples: Vec<f64>) -> String {
+        let key: Vec<u64> = samples.iter().map(|samp| samp.to_bits()).collect();
+        let idx = match self.waveform_ids.get(&key) {
+            Some(&idx) => idx,
+            None => {
+                let idx = self.pulse_library.len();
+                self.pulse_library.push(Waveform {
+                    name: format!("wf{idx}"),
+                    samples: samples.into_iter().map(|samp| [samp, 0.0]).collect(),
+                });
+                self.waveform_ids.insert(key, idx);
+                idx
+            },
+        };
+        self.pulse_library[idx].name.clone()
+    }
+
+    fn push_const(&mut self, ch: &str, start_pos: usize, end_pos: usize, val: f64, dflt_val: f64) {
+        let duration = end_pos - start_pos;
+        self.instructions.push(if val == dflt_val {
+            PulseInstr { name: "delay".to_string(), ch: ch.to_string(), t0: start_pos, duration: Some(duration), pulse_shape: None, parameters: None }
+        } else {
+            PulseInstr {
+                name: "parametric_pulse".to_string(),
+                ch: ch.to_string(),
+                t0: start_pos,
+                duration: None,
+                pulse_shape: Some("constant".to_string()),
+                parameters: Some(ConstParams { duration, amp: [val, 0.0] }),
+            }
+        })
+    }
+
+    /// Adds frame `ch` with the compiled waveform of `chan`. The channel must be freshly compiled.
+    pub fn add_chan<C: BaseChan>(&mut self, ch: &str, chan: &C) -> Result<(), StreamerError> {
+        chan.validate_compile_cache()?;
+        self.frames.push(Frame { name: ch.to_string(), dt: chan.clk_period() });
+
+        let dflt_val = samp_to_f64(&chan.dflt_val());
+        // Constant run not pushed yet: (start_pos, val)
+        let mut pending: Option<(usize, f64)> = None;
+        let mut cursor = ChanSampCursor::new();
+        let mut start_pos = 0;
+        for (&end_pos, func) in chan.compile_cache_ends().iter().zip(chan.compile_cache_fns().iter()) {
+            match func.const_val() {
+                Some(val) => {
+                    let val = samp_to_f64(&val);
+                    match pending {
+                        Some((_run_start, run_val)) if run_val == val => {},
+                        Some((run_start, run_val)) => {
+                            self.push_const(ch, run_start, start_pos, run_val, dflt_val);
+                            pending = Some((start_pos, val))
+                        },
+                        None => pending = Some((start_pos, val)),
+                    }
+                },
+                None => {
+                    if let Some((run_start, run_val)) = pending.take() {
+                        self.push_const(ch, run_start, start_pos, run_val, dflt_val)
+                    }
+                    let mut samps = vec![chan.dflt_val(); end_pos - start_pos];
+                    chan.fill_samps_from_ticks(&mut cursor, start_pos, &mut samps)?;
+                    let name = self.waveform(samps.iter().map(samp_to_f64).collect());
+                    self.instructions.push(PulseInstr { name, ch: ch.to_string(), t0: start_pos, duration: None, pulse_shape: None, parameters: None })
+                },
+            }
+            start_pos = end_pos;
+        }
+        if let Some((run_start, run_val)) = pending {
+            self.push_const(ch, run_start, start_pos, run_val, dflt_val)
+        }
+        // Stable - keeps the channel order among instructions starting at the same tick
+        self.instructions.sort_by_key(|instr| instr.t0);
+        Ok(())
+    }
+}
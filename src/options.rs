@@ -0,0 +1,153 @@
+//! Compile options - which safety checks to run on top of the plain compile and how to post-process the compile cache.
+//!
+//! Production runs typically want every check enabled ([`CompileOptions::strict`]),
+//! while quick interactive previews can skip the expensive ones ([`CompileOptions::preview`], the default).
+//!
+//! Options are passed to [`BaseStreamer::compile_with`] and forwarded to every active device through [`BaseDev::compile_with`].
+//! Plain `compile()` is equivalent to `compile_with()` with default options.
+//!
+//! [`BaseStreamer::compile_with`]: crate::streamer::BaseStreamer::compile_with
+//! [`BaseDev::compile_with`]: crate::device::BaseDev::compile_with
+
+use pyo3::prelude::*;
+
+#[pyclass]
+#[derive(Clone, Debug, PartialEq)]
+pub struct CompileOptions {
+    /// Treat every `Warning`-severity diagnostic (e.g. an auto-fixed 1-tick collision) as an error
+    #[pyo3(get, set)]
+    pub strict: bool,
+    /// Run [`BaseDev::check_limits`](crate::device::BaseDev::check_limits) after compiling
+    #[pyo3(get, set)]
+    pub check_limits: bool,
+    /// Run the NaN/Inf detection pass [`BaseDev::check_finite`](crate::device::BaseDev::check_finite) after compiling
+    #[pyo3(get, set)]
+    pub check_nan: bool,
+    /// Number of points per compile cache segment sampled by the NaN/Inf pass (`None` - every tick)
+    #[pyo3(get, set)]
+    pub nan_samps_per_seg: Option<usize>,
+    /// Split compile cache segments longer than this many ticks (`None` - no limit),
+    /// see [`BaseChan::split_segments`](crate::channel::BaseChan::split_segments)
+    #[pyo3(get, set)]
+    pub max_seg_samps: Option<usize>,
+}
+
+impl CompileOptions {
+    /// All checks disabled - the plain compile
+    pub fn preview() -> Self {
+        Self {
+            strict: false,
+            check_limits: false,
+            check_nan: false,
+            nan_samps_per_seg: None,
+            max_seg_samps: None,
+        }
+    }
+    /// All checks enabled, NaN/Inf pass on every tick
+    pub fn strict() -> Self {
+        Self {
+            strict: true,
+            check_limits: true,
+            check_nan: true,
+            nan_samps_per_seg: None,
+            max_seg_samps: None,
+        }
+    }
+}
+
+impl Default for CompileOptions {
+    fn default() -> Self {
+        Self::preview()
+    }
+}
+
+#[pymethods]
+impl CompileOptions {
+    #[new]
+    #[pyo3(signature = (strict=false, check_limits=false, check_nan=false, nan_samps_per_seg=None, max_seg_samps=None))]
+    fn py_new(strict: bool, check_limits: bool, check_nan: bool, nan_samps_per_seg: Option<usize>, max_seg_samps: Option<usize>) -> Self {
+        Self { strict, check_limits, check_nan, nan_samps_per_seg, max_seg_samps }
+    }
+    #[staticmethod]
+    #[pyo3(name = "strict")]
+    fn py_strict() -> Self {
+        Self::strict()
+    }
+    #[staticmethod]
+    #[pyo3(name = "preview")]
+    fn py_preview() -> Self {
+        Self::preview()
+    }
+    fn __repr__(&self) -> String {
+        format!("{self:?}")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::channel::{BaseChan, ChanSampCursor};
+    use crate::device::BaseDev;
+    use crate::error::StreamerError;
+    use crate::fn_lib_tools::StdFnLib;
+    use crate::mock::test_impls::TestStreamer;
+    use crate::options::CompileOptions;
+    use crate::streamer::BaseStreamer;
+
+    #[test]
+    fn strict_vs_preview() {
+        let mut streamer = TestStreamer::new();
+        streamer.add_ao_dev("AO", 100.0);
+        streamer.ao_devs["AO"].add_chan("ao0", 0.0);
+        let chan = streamer.ao_devs["AO"].chan_mut("ao0").unwrap();
+        chan.constant(1.0, 0.1, Some((0.2, false))).unwrap();
+        // 1-tick collision - auto-fixed with a warning
+        chan.constant(2.0, 0.29, Some((0.1, false))).unwrap();
+        let pow = StdFnLib::new().Pow(1.0, 0.5, 1.0, 0.0).unwrap().inner;
+        chan.add_instr(pow, 0.5, Some((0.1, false))).unwrap();
+
+        // Preview (default) runs no extra checks
+        streamer.compile(Some(1.0)).unwrap();
+        streamer.compile_with(Some(1.0), &CompileOptions::preview()).unwrap();
+
+        let err = streamer.compile_with(Some(1.0), &CompileOptions::strict()).unwrap_err();
+        assert!(matches!(err, StreamerError::StrictViolation { .. }));
+        assert_eq!(err.ctx().chan.as_deref(), Some("ao0"));
+        // A rejected waveform can't be streamed
+        assert!(streamer.validate_compile_cache().is_err() && streamer.run_mock(10).is_err());
+
+        let opts = CompileOptions { check_nan: true, ..CompileOptions::preview() };
+        let err = streamer.compile_with(Some(1.0), &opts).unwrap_err();
+        assert!(matches!(err, StreamerError::NonFinite { .. }));
+        assert!(streamer.validate_compile_cache().is_err() && streamer.run_mock(10).is_err());
+    }
+
+    #[test]
+    fn max_seg_samps() {
+        let mut streamer = TestStreamer::new();
+        streamer.add_ao_dev("AO", 1e3);
+        streamer.ao_devs["AO"].add_chan("ao0", 0.0);
+        let chan = streamer.ao_devs["AO"].chan_mut("ao0").unwrap();
+        chan.constant(1.0, 0.0, Some((0.25, true))).unwrap();
+        chan.add_instr(StdFnLib::new().LinFn(1.0, 0.0).unwrap().inner, 0.3, Some((0.35, false))).unwrap();
+        streamer.compile(Some(1.0)).unwrap();
+        let samps = |streamer: &TestStreamer| {
+            let mut samps = vec![0.0; 1000];
+            streamer.ao_devs["AO"].chan("ao0").unwrap().fill_samps_from_ticks(&mut ChanSampCursor::new(), 0, &mut samps).unwrap();
+            samps
+        };
+        let plain = samps(&streamer);
+        assert_eq!(streamer.ao_devs["AO"].chan("ao0").unwrap().compile_cache_ends(), &vec![300, 650, 1000]);
+
+        let opts = CompileOptions { max_seg_samps: Some(200), ..CompileOptions::preview() };
+        streamer.compile_with(Some(1.0), &opts).unwrap();
+        let chan = streamer.ao_devs["AO"].chan("ao0").unwrap();
+        assert_eq!(chan.compile_cache_ends(), &vec![200, 300, 500, 650, 850, 1000]);
+        assert_eq!(samps(&streamer), plain);
+        // The edit cache still matches the split compile cache
+        assert!(chan.validation_report().is_valid());
+
+        let opts = CompileOptions { max_seg_samps: Some(0), ..CompileOptions::preview() };
+        assert!(matches!(streamer.compile_with(Some(1.0), &opts), Err(StreamerError::InvalidArgument { .. })));
+        assert!(streamer.run_mock(10).is_err());
+    }
+}
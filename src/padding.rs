@@ -0,0 +1,227 @@
+//! Padding report - which gaps between instructions the compiler filled, and with what.
+//!
+//! `compile` pads every interval not covered by an instruction: with the channel default before the first
+//! instruction, and after an instruction with a specific duration with either its last value (`keep_val=true`)
+//! or the channel default (`keep_val=false`) until the next instruction or the stop time. When an output sits
+//! at an unexpected value between pulses, [`BaseStreamer::padding_report`] tells which of these rules produced it.
+//!
+//! Parts of a padding painted over by override layers are left out, so every reported tick streams the reported value.
+//!
+//! How the gap after an instruction is filled is decided by the channel's [`PaddingPolicy`]. The default [`StepPadding`]
+//! implements the rules above, [`RampToDefault`] ramps linearly back to the default instead of stepping - for actuators
+//! such as piezos which must not see sudden jumps. A non-constant padding is reported with its first value.
+//! Set a policy with [`BaseChan::set_padding_policy`].
+//!
+//! [`BaseStreamer::padding_report`]: crate::streamer::BaseStreamer::padding_report
+//! [`BaseChan::set_padding_policy`]: crate::channel::BaseChan::set_padding_policy
+
+use std::fmt;
+use std::fmt::Display;
+use std::sync::Arc;
+use indexmap::IndexMap;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use crate::channel::ConstFn;
+use crate::fn_lib_tools::{FnTraitSet, StdFnLib};
+
+/// Gap `[start_pos, end_pos)` after an instruction with a specific duration, to be filled by a [`PaddingPolicy`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct PadGap<T> {
+    /// End position of the instruction
+    pub start_pos: usize,
+    /// Start of the next instruction or the stop position. `usize::MAX` when the gap is open-ended - the stop position
+    /// is not known when evaluating the edit cache (see [`BaseChan::eval_point`])
+    ///
+    /// [`BaseChan::eval_point`]: crate::channel::BaseChan::eval_point
+    pub end_pos: usize,
+    /// Value of the instruction function at `start_pos`
+    pub last_val: T,
+    pub dflt_val: T,
+    /// `keep_val` flag of the instruction - always `true` on channels with [`BaseChan::hold_last_val`]
+    ///
+    /// [`BaseChan::hold_last_val`]: crate::channel::BaseChan::hold_last_val
+    pub keep_val: bool,
+    pub clk_period: f64,
+}
+
+/// Segments `(end_pos, func)` filling a gap, see [`PaddingPolicy::pad`]
+pub type PadSegs<T> = Vec<(usize, Arc<dyn FnTraitSet<T>>)>;
+
+/// Fills the gaps after instructions with a specific duration
+pub trait PaddingPolicy<T>: Send + Sync {
+    /// Segments `(end_pos, func)` covering `[gap.start_pos, gap.end_pos)` back-to-back. Functions are evaluated
+    /// at the same (edit-cache) times as instruction functions, `pos * gap.clk_period`.
+    fn pad(&self, gap: &PadGap<T>) -> PadSegs<T>;
+    fn describe(&self) -> String;
+}
+
+/// Shared handle to a padding policy, as stored by channels
+pub type SharedPaddingPolicy<T> = Arc<dyn PaddingPolicy<T>>;
+
+/// Steps to the last value (`keep_val=true`) or the channel default (`keep_val=false`) - the default policy
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct StepPadding;
+
+impl<T: Clone + std::fmt::Debug + Send + Sync + 'static> PaddingPolicy<T> for StepPadding {
+    fn pad(&self, gap: &PadGap<T>) -> PadSegs<T> {
+        let val = if gap.keep_val { gap.last_val.clone() } else { gap.dflt_val.clone() };
+        vec![(gap.end_pos, Arc::new(ConstFn::new(val)))]
+    }
+    fn describe(&self) -> String {
+        "step".to_string()
+    }
+}
+
+/// Ramps linearly from the last value to the channel default over `ramp_time` seconds (rounded to clock ticks),
+/// then holds the default. A gap shorter than the ramp is cut short with the ramp unfinished.
+/// `keep_val` paddings hold the last value as with [`StepPadding`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RampToDefault {
+    pub ramp_time: f64,
+}
+
+impl RampToDefault {
+    pub fn new(ramp_time: f64) -> Self {
+        Self { ramp_time }
+    }
+}
+
+impl PaddingPolicy<f64> for RampToDefault {
+    fn pad(&self, gap: &PadGap<f64>) -> PadSegs<f64> {
+        let ramp_ticks = (self.ramp_time / gap.clk_period).round() as usize;
+        if gap.keep_val || ramp_ticks == 0 || gap.last_val == gap.dflt_val {
+            return StepPadding.pad(gap)
+        }
+        let slope = (gap.dflt_val - gap.last_val) / (ramp_ticks as f64 * gap.clk_period);
+        let offs = gap.last_val - slope * gap.start_pos as f64 * gap.clk_period;
+        let Ok(ramp) = StdFnLib::new().LinFn(slope, offs) else {
+            return StepPadding.pad(gap)
+        };
+        let ramp_end = gap.start_pos.saturating_add(ramp_ticks).min(gap.end_pos);
+        let mut segs: PadSegs<f64> = vec![(ramp_end, Arc::from(ramp.inner))];
+        if ramp_end < gap.end_pos {
+            segs.push((gap.end_pos, Arc::new(ConstFn::new(gap.dflt_val))));
+        }
+        segs
+    }
+    fn describe(&self) -> String {
+        format!("ramp to default over {} s", self.ramp_time)
+    }
+}
+
+/// Padding segments of all padded channels of a device: channel name -> segments sorted by position
+pub type DevPadding = IndexMap<String, Vec<PaddingSeg>>;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PaddingSeg {
+    pub start_pos: usize,
+    pub end_pos: usize,
+    pub val: f64,
+    /// The instruction the padding follows, `None` for the padding before the first instruction (or of a muted channel)
+    pub after_instr: Option<String>,
+    /// `keep_val` flag of `after_instr` - always `true` on channels with [`BaseChan::hold_last_val`]
+    ///
+    /// [`BaseChan::hold_last_val`]: crate::channel::BaseChan::hold_last_val
+    pub keep_val: Option<bool>,
+}
+
+impl PaddingSeg {
+    pub fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("start_pos", self.start_pos)?;
+        dict.set_item("end_pos", self.end_pos)?;
+        dict.set_item("val", self.val)?;
+        dict.set_item("after_instr", &self.after_instr)?;
+        dict.set_item("keep_val", self.keep_val)?;
+        Ok(dict)
+    }
+}
+
+impl Display for PaddingSeg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}, {}) = {}: ", self.start_pos, self.end_pos, self.val)?;
+        match (&self.after_instr, self.keep_val) {
+            (Some(instr), Some(true)) => write!(f, "last value of instruction {instr} (keep_val=true)"),
+            (Some(instr), _) => write!(f, "channel default after instruction {instr} (keep_val=false)"),
+            (None, _) => write!(f, "channel default before the first instruction"),
+        }
+    }
+}
+
+/// Parts of `seg` not covered by the sorted, disjoint `[start, end)` intervals `covered`
+pub(crate) fn uncovered(seg: PaddingSeg, covered: &[(usize, usize)]) -> Vec<PaddingSeg> {
+    let mut parts = Vec::new();
+    let mut cursor = seg.start_pos;
+    for &(start, end) in covered.iter().filter(|&&(start, end)| start < seg.end_pos && seg.start_pos < end) {
+        if start > cursor {
+            parts.push(PaddingSeg { start_pos: cursor, end_pos: start, ..seg.clone() })
+        }
+        cursor = cursor.max(end);
+    }
+    if cursor < seg.end_pos {
+        parts.push(PaddingSeg { start_pos: cursor, ..seg })
+    }
+    parts
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::channel::{BaseChan, ChanSampCursor, ConstFn};
+    use crate::device::BaseDev;
+    use crate::error::StreamerError;
+    use crate::mock::test_impls::{TestChan, TestStreamer};
+    use crate::streamer::BaseStreamer;
+
+    #[test]
+    fn padding_report() {
+        let mut streamer = TestStreamer::new();
+        streamer.add_ao_dev("AO", 1e3);
+        streamer.ao_devs["AO"].add_chan("ao0", -1.0);
+        streamer.ao_devs["AO"].add_chan("ao1", 0.0);
+        let ao0 = streamer.ao_devs["AO"].chan_mut("ao0").unwrap();
+        ao0.constant(2.0, 0.01, Some((0.01, true))).unwrap();
+        ao0.constant(3.0, 0.03, Some((0.01, false))).unwrap();
+        ao0.constant(4.0, 0.05, None).unwrap();
+        // Override covering part of the `keep_val` padding
+        ao0.add_instr_on_layer(Box::new(ConstFn::new(9.0)), 0.022, Some((0.004, false)), 1).unwrap();
+        streamer.ao_devs["AO"].chan_mut("ao1").unwrap().constant(1.0, 0.0, None).unwrap();
+        assert!(matches!(streamer.padding_report(), Err(StreamerError::NotCompiled { .. })));
+
+        streamer.compile(Some(0.06)).unwrap();
+        let report = streamer.padding_report().unwrap();
+        let segs: Vec<(usize, usize, f64, Option<bool>)> = report["AO"]["ao0"].iter()
+            .map(|seg| (seg.start_pos, seg.end_pos, seg.val, seg.keep_val))
+            .collect();
+        assert_eq!(segs, [(0, 10, -1.0, None), (20, 22, 2.0, Some(true)), (26, 30, 2.0, Some(true)), (40, 50, -1.0, Some(false))]);
+        assert!(report["AO"]["ao1"].is_empty());
+        assert!(report["AO"]["ao0"][3].to_string().starts_with("[40, 50) = -1: channel default after instruction"));
+    }
+
+    #[test]
+    fn ramp_to_default() {
+        let mut piezo = TestChan::new("piezo", 1e3, 0.0);
+        piezo.set_padding_policy(Some(Arc::new(RampToDefault::new(0.004)))).unwrap();
+        piezo.constant(1.0, 0.002, Some((0.002, false))).unwrap();
+        // The next instruction cuts the second ramp short
+        piezo.constant(2.0, 0.010, Some((0.002, false))).unwrap();
+        piezo.constant(3.0, 0.014, Some((0.001, true))).unwrap();
+        piezo.compile(18).unwrap();
+        let mut samps = vec![0.0; 18];
+        piezo.fill_samps_from_ticks(&mut ChanSampCursor::new(), 0, &mut samps).unwrap();
+        let expected = [0.0, 0.0, 1.0, 1.0, 1.0, 0.75, 0.5, 0.25, 0.0, 0.0, 2.0, 2.0, 2.0, 1.5, 3.0, 3.0, 3.0, 3.0];
+        assert!(samps.iter().zip(expected).all(|(samp, expected)| (samp - expected).abs() < 1e-9), "{samps:?}");
+        // The edit cache agrees, also after the last instruction where the stop position is unknown
+        assert!((0..18).all(|pos| (piezo.eval_point(pos as f64 * 1e-3).unwrap() - samps[pos]).abs() < 1e-9));
+        let segs: Vec<(usize, usize)> = piezo.padding_segs().unwrap().iter().map(|seg| (seg.start_pos, seg.end_pos)).collect();
+        assert_eq!(segs, [(0, 2), (4, 8), (8, 10), (12, 14), (15, 18)]);
+        assert!(matches!(piezo.crop(0.0, 0.01), Err(StreamerError::Incompatible { .. })));
+
+        // Back to stepping
+        piezo.set_padding_policy(None).unwrap();
+        assert!(!piezo.is_fresh_compiled());
+        piezo.compile(18).unwrap();
+        assert_eq!(piezo.eval_point(0.005).unwrap(), 0.0);
+        piezo.crop(0.0, 0.01).unwrap();
+    }
+}
@@ -0,0 +1,259 @@
+//! Opt-in timing instrumentation for finding performance bottlenecks.
+//!
+//! With the `profiling` cargo feature enabled, channels time the main phases of their work:
+//! editing (`add_instr`), compiling (`compile`), and sample calculation (`fill_samps`, once per chunk).
+//! Durations are accumulated in the channel's [`Profile`] - per phase call count, total, and maximum time.
+//! Without the feature no timers are started and the only cost is an unused `Profile` field.
+//!
+//! A channel takes part by returning its profile from [`BaseChan::profile`] (the default `None` opts out).
+//! Reports are collected across the whole streamer via [`BaseStreamer::profile_report`].
+//!
+//! [`BaseChan::profile`]: crate::channel::BaseChan::profile
+//! [`BaseStreamer::profile_report`]: crate::streamer::BaseStreamer::profile_report
+
+use std::fmt;
+use std::fmt::Display;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Phase {
+    /// `add_instr` call
+    Edit,
+    /// `compile` call
+    Compile,
+    /// `fill_samps` call - one streaming chunk
+    CalcSamps,
+}
+
+impl Phase {
+    pub const ALL: [Phase; 3] = [Phase::Edit, Phase::Compile, Phase::CalcSamps];
+}
+
+/// Accumulated statistics of one phase.
+/// Atomic so that `fill_samps`, which only borrows the channel immutably, can record into it.
+#[derive(Debug, Default)]
+struct PhaseStats {
+    count: AtomicU64,
+    total_ns: AtomicU64,
+    max_ns: AtomicU64,
+}
+
+impl PhaseStats {
+    fn record(&self, dur: Duration) {
+        let dur_ns = dur.as_nanos().min(u64::MAX as u128) as u64;
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.total_ns.fetch_add(dur_ns, Ordering::Relaxed);
+        self.max_ns.fetch_max(dur_ns, Ordering::Relaxed);
+    }
+    fn clear(&self) {
+        self.count.store(0, Ordering::Relaxed);
+        self.total_ns.store(0, Ordering::Relaxed);
+        self.max_ns.store(0, Ordering::Relaxed);
+    }
+}
+
+/// Per-channel timing sink
+#[derive(Debug, Default)]
+pub struct Profile {
+    edit: PhaseStats,
+    compile: PhaseStats,
+    calc_samps: PhaseStats,
+}
+
+impl Profile {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    fn stats(&self, phase: Phase) -> &PhaseStats {
+        match phase {
+            Phase::Edit => &self.edit,
+            Phase::Compile => &self.compile,
+            Phase::CalcSamps => &self.calc_samps,
+        }
+    }
+    pub fn record(&self, phase: Phase, dur: Duration) {
+        self.stats(phase).record(dur)
+    }
+    pub fn clear(&self) {
+        for phase in Phase::ALL {
+            self.stats(phase).clear()
+        }
+    }
+    /// Returns `(count, total, max)` for `phase`
+    pub fn summary(&self, phase: Phase) -> (u64, Duration, Duration) {
+        let stats = self.stats(phase);
+        (
+            stats.count.load(Ordering::Relaxed),
+            Duration::from_nanos(stats.total_ns.load(Ordering::Relaxed)),
+            Duration::from_nanos(stats.max_ns.load(Ordering::Relaxed)),
+        )
+    }
+    /// One entry per phase which was recorded at least once
+    pub fn entries(&self, chan: &str) -> Vec<ProfileEntry> {
+        Phase::ALL
+            .into_iter()
+            .map(|phase| (phase, self.summary(phase)))
+            .filter(|(_phase, (count, _total, _max))| *count > 0)
+            .map(|(phase, (count, total, max))| ProfileEntry {
+                dev: None,
+                chan: chan.to_string(),
+                phase,
+                count,
+                total,
+                max,
+            })
+            .collect()
+    }
+}
+
+/// Records the time elapsed since construction into the profile when dropped
+pub struct PhaseTimer {
+    profile: Option<Arc<Profile>>,
+    phase: Phase,
+    start: Instant,
+}
+
+impl PhaseTimer {
+    /// `profile = None` gives an inert timer
+    pub fn start(profile: Option<Arc<Profile>>, phase: Phase) -> Self {
+        Self { profile, phase, start: Instant::now() }
+    }
+}
+
+impl Drop for PhaseTimer {
+    fn drop(&mut self) {
+        if let Some(profile) = &self.profile {
+            profile.record(self.phase, self.start.elapsed())
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProfileEntry {
+    /// Device name. Filled in when entries are collected at the device level
+    pub dev: Option<String>,
+    pub chan: String,
+    pub phase: Phase,
+    pub count: u64,
+    pub total: Duration,
+    pub max: Duration,
+}
+
+impl ProfileEntry {
+    pub fn mean(&self) -> Duration {
+        if self.count == 0 {
+            Duration::ZERO
+        } else {
+            Duration::from_nanos((self.total.as_nanos() / self.count as u128) as u64)
+        }
+    }
+
+    pub fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("dev", &self.dev)?;
+        dict.set_item("chan", &self.chan)?;
+        dict.set_item("phase", format!("{:?}", self.phase))?;
+        dict.set_item("count", self.count)?;
+        dict.set_item("total_s", self.total.as_secs_f64())?;
+        dict.set_item("mean_s", self.mean().as_secs_f64())?;
+        dict.set_item("max_s", self.max.as_secs_f64())?;
+        Ok(dict)
+    }
+}
+
+impl Display for ProfileEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f, "{}/{} {:?}: {} calls, total {:?}, mean {:?}, max {:?}",
+            self.dev.as_deref().unwrap_or("-"), self.chan, self.phase, self.count, self.total, self.mean(), self.max
+        )
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProfileReport {
+    pub entries: Vec<ProfileEntry>,
+}
+
+impl ProfileReport {
+    /// Total time spent in `phase` across all channels
+    pub fn total(&self, phase: Phase) -> Duration {
+        self.entries.iter().filter(|entry| entry.phase == phase).map(|entry| entry.total).sum()
+    }
+
+    pub fn to_list<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyList>> {
+        let list = PyList::empty_bound(py);
+        for entry in self.entries.iter() {
+            list.append(entry.to_dict(py)?)?;
+        }
+        Ok(list)
+    }
+}
+
+impl Display for ProfileReport {
+    /// Entries sorted by total time, the most expensive first
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.entries.is_empty() {
+            return writeln!(f, "profile: no entries (is the `profiling` feature enabled?)")
+        }
+        let mut entries: Vec<&ProfileEntry> = self.entries.iter().collect();
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.total));
+        for entry in entries {
+            writeln!(f, "{entry}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+    use crate::profiling::*;
+
+    #[test]
+    fn accumulate() {
+        let profile = Profile::new();
+        profile.record(Phase::Compile, Duration::from_micros(3));
+        profile.record(Phase::Compile, Duration::from_micros(5));
+        assert_eq!(profile.summary(Phase::Compile), (2, Duration::from_micros(8), Duration::from_micros(5)));
+
+        let entries = profile.entries("ao0");
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].mean(), Duration::from_micros(4));
+
+        profile.clear();
+        assert!(profile.entries("ao0").is_empty());
+    }
+
+    #[cfg(feature = "profiling")]
+    #[test]
+    fn streamer_report() {
+        use crate::channel::BaseChan;
+        use crate::device::BaseDev;
+        use crate::mock::test_impls::TestStreamer;
+        use crate::streamer::BaseStreamer;
+
+        let mut streamer = TestStreamer::new();
+        streamer.add_ao_dev("AO", 1e3);
+        streamer.ao_devs["AO"].add_chan("ao0", 0.0);
+        streamer.ao_devs["AO"].chan_mut("ao0").unwrap().constant(1.0, 0.0, Some((0.5, false))).unwrap();
+        streamer.ao_devs["AO"].chan_mut("ao0").unwrap().constant(2.0, 0.6, None).unwrap();
+        streamer.compile(Some(1.0)).unwrap();
+        streamer.ao_devs["AO"].run_mock(300).unwrap();
+
+        let report = streamer.profile_report();
+        let count = |phase| report.entries.iter().find(|entry| entry.phase == phase).unwrap().count;
+        assert_eq!(count(Phase::Edit), 2);
+        assert_eq!(count(Phase::Compile), 1);
+        // 4 chunks
+        assert_eq!(count(Phase::CalcSamps), 4);
+        assert_eq!(report.entries[0].dev.as_deref(), Some("AO"));
+
+        streamer.clear_profile();
+        assert!(streamer.profile_report().entries.is_empty());
+    }
+}
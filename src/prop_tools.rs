@@ -0,0 +1,167 @@
+//! Property-test utilities for [`BaseChan`] implementations (feature `test-utils`).
+//!
+//! Downstream crates implement [`BaseChan`] for their hardware channels and override parts of it. This module lets them
+//! fuzz such implementations against the trait contract with [`proptest`]:
+//! - [`instr_specs`] generates random lists of non-overlapping constant instructions on the clock grid;
+//! - [`apply_specs`] adds them to a channel;
+//! - the `check_*` functions verify the compile cache invariants and return [`TestCaseError`]s, so they can be used with
+//!   `?` inside `proptest!` bodies. [`compile_and_check`] does all of the above in one call.
+//!
+//! ```ignore
+//! proptest! {
+//!     #[test]
+//!     fn my_chan_contract(specs in instr_specs(-10.0..10.0, 20, 100)) {
+//!         compile_and_check(&mut MyChan::new("ao0", 1e6), &specs, 10)?;
+//!     }
+//! }
+//! ```
+
+use std::fmt::Debug;
+use proptest::prelude::*;
+use proptest::test_runner::TestCaseError;
+use crate::channel::{BaseChan, ChanSampCursor};
+
+/// Constant instruction on the clock grid
+#[derive(Clone, Debug, PartialEq)]
+pub struct InstrSpec<T> {
+    pub start_pos: usize,
+    /// Duration in clock ticks, `None` for a "go-this" instruction which lasts until the next one
+    pub dur: Option<usize>,
+    pub keep_val: bool,
+    pub val: T,
+}
+
+impl<T> InstrSpec<T> {
+    /// End of the instruction as given - for "go-this" instructions the tick after the start
+    pub fn end_pos(&self) -> usize {
+        self.start_pos + self.dur.unwrap_or(1)
+    }
+}
+
+/// Up to `max_instrs` instructions sorted by start, with values from `val`. Gaps between instructions
+/// and durations are up to `max_ticks` clock ticks, about one in ten instructions is a "go-this" one.
+pub fn instr_specs<T: Clone + Debug>(
+    val: impl Strategy<Value = T>,
+    max_instrs: usize,
+    max_ticks: usize,
+) -> impl Strategy<Value = Vec<InstrSpec<T>>> {
+    let raw_spec = (0..=max_ticks, 1..=max_ticks.max(1), prop::bool::weighted(0.1), any::<bool>(), val);
+    prop::collection::vec(raw_spec, 0..=max_instrs).prop_map(|raw_specs| {
+        let mut start_pos = 0;
+        raw_specs.into_iter().map(|(gap, dur, go_this, keep_val, val)| {
+            start_pos += gap;
+            let spec = InstrSpec { start_pos, dur: (!go_this).then_some(dur), keep_val, val };
+            start_pos += dur;
+            spec
+        }).collect()
+    })
+}
+
+/// Stop position leaving `tail` ticks after the last instruction
+pub fn stop_pos_for<T>(specs: &[InstrSpec<T>], tail: usize) -> usize {
+    specs.last().map_or(0, InstrSpec::end_pos) + tail
+}
+
+/// Adds `specs` to `chan` with [`BaseChan::constant`]
+pub fn apply_specs<C: BaseChan>(chan: &mut C, specs: &[InstrSpec<C::Samp>]) -> Result<(), TestCaseError> {
+    let clk_period = chan.clk_period();
+    for spec in specs {
+        let dur_spec = spec.dur.map(|dur| (dur as f64 * clk_period, spec.keep_val));
+        chan.constant(spec.val.clone(), spec.start_pos as f64 * clk_period, dur_spec)
+            .map_err(|err| TestCaseError::fail(format!("Failed to add {spec:?}: {err}")))?;
+    }
+    Ok(())
+}
+
+/// Compile cache is fresh and its segments are non-empty, contiguous, and cover `[0, stop_pos)`
+pub fn check_compile_cache<C: BaseChan>(chan: &C, stop_pos: usize) -> Result<(), TestCaseError> {
+    let ends = chan.compile_cache_ends();
+    prop_assert!(chan.is_fresh_compiled(), "compile cache is not fresh");
+    prop_assert_eq!(ends.len(), chan.compile_cache_fns().len(), "one function per segment");
+    prop_assert!(!ends.is_empty(), "no segments");
+    prop_assert!(ends[0] > 0 && ends.windows(2).all(|pair| pair[0] < pair[1]), "segment ends not strictly increasing: {:?}", ends);
+    prop_assert_eq!(*ends.last().unwrap(), stop_pos, "segments don't end at the stop position");
+    Ok(())
+}
+
+/// Compiled samples match [`BaseChan::eval_point`], which works on the edit cache
+pub fn check_matches_edit_cache<C: BaseChan>(chan: &C, stop_pos: usize) -> Result<(), TestCaseError> {
+    let mut samps = vec![chan.dflt_val(); stop_pos];
+    chan.fill_samps_from_ticks(&mut ChanSampCursor::new(), 0, &mut samps).map_err(|err| TestCaseError::fail(err.to_string()))?;
+    for (pos, samp) in samps.into_iter().enumerate() {
+        let expected = chan.eval_point(pos as f64 * chan.clk_period()).map_err(|err| TestCaseError::fail(err.to_string()))?;
+        prop_assert_eq!(samp, expected, "tick {}", pos);
+    }
+    Ok(())
+}
+
+/// Every tick covered by an instruction of `specs` carries its value. "Go-this" instructions cover
+/// everything up to the next instruction or `stop_pos`.
+pub fn check_instr_vals<C: BaseChan>(chan: &C, specs: &[InstrSpec<C::Samp>], stop_pos: usize) -> Result<(), TestCaseError> {
+    let mut samps = vec![chan.dflt_val(); stop_pos];
+    chan.fill_samps_from_ticks(&mut ChanSampCursor::new(), 0, &mut samps).map_err(|err| TestCaseError::fail(err.to_string()))?;
+    for (idx, spec) in specs.iter().enumerate() {
+        let end_pos = match spec.dur {
+            Some(dur) => spec.start_pos + dur,
+            None => specs.get(idx + 1).map_or(stop_pos, |next| next.start_pos),
+        };
+        if let Some(pos) = (spec.start_pos..end_pos).find(|&pos| samps[pos] != spec.val) {
+            return Err(TestCaseError::fail(format!("tick {pos} of {spec:?} has value {:?}", samps[pos])))
+        }
+    }
+    Ok(())
+}
+
+/// Clears `chan`, adds `specs`, compiles with `tail` ticks after the last instruction, and runs all checks
+pub fn compile_and_check<C: BaseChan>(chan: &mut C, specs: &[InstrSpec<C::Samp>], tail: usize) -> Result<(), TestCaseError> {
+    chan.clear_edit_cache();
+    apply_specs(chan, specs)?;
+    let stop_pos = stop_pos_for(specs, tail);
+    if !chan.got_instructions() {
+        return Ok(())
+    }
+    chan.compile(stop_pos).map_err(|err| TestCaseError::fail(err.to_string()))?;
+    check_compile_cache(chan, stop_pos)?;
+    check_matches_edit_cache(chan, stop_pos)?;
+    check_instr_vals(chan, specs, stop_pos)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::mock::test_impls::TestChan;
+
+    proptest! {
+        #[test]
+        fn test_chan_contract(specs in instr_specs(-10.0..10.0, 12, 30), tail in 0..5usize) {
+            compile_and_check(&mut TestChan::new("ao0", 1e3, 0.0), &specs, tail)?;
+        }
+
+        #[test]
+        fn test_chan_contract_bool(specs in instr_specs(any::<bool>(), 12, 30), tail in 0..5usize) {
+            let mut chan = TestChan::new("port0/line0", 1e6, false);
+            chan.set_hold_last_val(true).unwrap();
+            compile_and_check(&mut chan, &specs, tail)?;
+        }
+    }
+
+    #[test]
+    fn detects_broken_cache() {
+        let specs = vec![
+            InstrSpec { start_pos: 2, dur: Some(3), keep_val: false, val: 1.0 },
+            InstrSpec { start_pos: 5, dur: None, keep_val: false, val: 2.0 },
+        ];
+        let mut chan = TestChan::new("ao0", 1e3, 0.0);
+        compile_and_check(&mut chan, &specs, 2).unwrap();
+        assert_eq!(stop_pos_for(&specs, 2), 8);
+        assert!(check_compile_cache(&chan, 9).is_err());
+        chan.compile_cache_ends_mut().swap(0, 1);
+        assert!(check_compile_cache(&chan, 8).is_err());
+        // The value of the "go-this" instruction must reach the stop position
+        chan.compile(8).unwrap();
+        check_instr_vals(&chan, &specs, 8).unwrap();
+        let mut specs = specs;
+        specs[1].val = 3.0;
+        assert!(check_instr_vals(&chan, &specs, 8).is_err());
+    }
+}
@@ -0,0 +1,609 @@
+//! Glue for the pyo3 wrapper layer of downstream streamer crates.
+//!
+//! Compiling and sample calculation can take seconds for long sequences. Holding the GIL for that time
+//! freezes every other Python thread (GUIs, data loggers, watchdogs). Wrappers should therefore run
+//! heavy backend calls through [`nogil!`], which releases the GIL for the duration of the call and converts
+//! [`StreamerError`] into the matching Python exception.
+//!
+//! The functions below are the ready-made GIL-releasing versions of the heavy [`BaseStreamer`] entry points,
+//! so that a `#[pymethods]` block only has to forward to them:
+//!
+//! ```ignore
+//! #[pymethods]
+//! impl Streamer {
+//!     #[pyo3(signature = (stop_time=None))]
+//!     fn compile(&mut self, py: Python<'_>, stop_time: Option<f64>) -> PyResult<f64> {
+//!         py_tools::compile(py, self, stop_time)
+//!     }
+//! }
+//! ```
+//!
+//! Sample calculation for plotting can also be done without the intermediate `Vec` and the copy into a new
+//! Python object: [`calc_nsamps_into`] writes directly into the memory of any writable `float64` buffer
+//! (a numpy array, `array.array('d')`, ...), and [`calc_nsamps_numpy`] allocates the numpy array first and fills it.
+//!
+//! Streamer pyclasses also get a read-only dict-like protocol over their devices from [`dev_count`], [`iter_devs`],
+//! [`getitem`], and [`contains`] (forwarded by `__len__`, `__iter__`, `__getitem__`, and `__contains__`),
+//! see [`crate::inspect`].
+//!
+//! [`plot_data`] and [`dev_plot_data`] return the time points together with the samples, so that plotting a channel
+//! or a whole device is a one-liner. [`calc_all`] does the same for all devices at once on a shared time grid.
+//!
+//! Python callables are registered as validation rules with [`add_rule`] and as event observers with [`add_observer`].
+//!
+//! For pickling, streamer pyclasses forward `__getstate__` / `__setstate__` to [`getstate`] / [`setstate`].
+//! The state only holds the instructions, so `__new__` / `__getnewargs__` of the pyclass must recreate the devices and channels.
+//!
+//! [`StreamerError`]: crate::error::StreamerError
+//! [`nogil!`]: crate::nogil
+
+use pyo3::prelude::*;
+use pyo3::buffer::PyBuffer;
+use pyo3::exceptions::{PyBufferError, PyKeyError};
+use pyo3::types::{PyDict, PyIterator, PyList};
+use crate::error::{ErrCtx, StreamerError};
+use crate::fn_lib_tools::FnRegistry;
+use crate::inspect::DevInfo;
+use crate::diagnostics::Severity;
+use crate::rules::PyRule;
+use crate::events::{ObserverId, PyObserver};
+use crate::interlocks::Interlock;
+use crate::options::CompileOptions;
+use crate::skew::SkewSpec;
+use crate::resample::Resampling;
+use crate::streamer::BaseStreamer;
+
+/// Evaluates `$body` (an expression returning `Result<_, StreamerError>`) with the GIL released
+/// and converts the error into `PyErr`. Everything captured by `$body` must be `Send`.
+#[macro_export]
+macro_rules! nogil {
+    ($py:expr, $body:expr) => {
+        $py.allow_threads(|| $body).map_err(::pyo3::PyErr::from)
+    };
+}
+
+/// [`BaseStreamer::compile`] with the GIL released
+pub fn compile<S: BaseStreamer + Send>(py: Python<'_>, streamer: &mut S, stop_time: Option<f64>) -> PyResult<f64> {
+    nogil!(py, streamer.compile(stop_time))
+}
+
+/// [`BaseStreamer::compile_with`] with the GIL released
+pub fn compile_with<S: BaseStreamer + Send>(py: Python<'_>, streamer: &mut S, stop_time: Option<f64>, opts: &CompileOptions) -> PyResult<f64> {
+    nogil!(py, streamer.compile_with(stop_time, opts))
+}
+
+/// [`BaseStreamer::check_finite`] with the GIL released
+pub fn check_finite<S: BaseStreamer + Sync>(py: Python<'_>, streamer: &S, max_samps_per_seg: Option<usize>) -> PyResult<()> {
+    nogil!(py, streamer.check_finite(max_samps_per_seg))
+}
+
+/// [`BaseStreamer::dry_run`] with the GIL released, the report as a dict
+pub fn dry_run<'py, S: BaseStreamer + Sync>(py: Python<'py>, streamer: &S, chunk_samps: usize) -> PyResult<Bound<'py, PyDict>> {
+    nogil!(py, streamer.dry_run(chunk_samps))?.to_dict(py)
+}
+
+/// Plotting samples of channel `chan_name` of device `dev_name` (see [`BaseChan::calc_nsamps`]) with the GIL released
+///
+/// [`BaseChan::calc_nsamps`]: crate::channel::BaseChan::calc_nsamps
+pub fn calc_nsamps<S: BaseStreamer + Sync>(
+    py: Python<'_>,
+    streamer: &S,
+    dev_name: &str,
+    chan_name: &str,
+    n_samps: usize,
+    start_time: Option<f64>,
+    end_time: Option<f64>,
+) -> PyResult<Vec<f64>> {
+    nogil!(py, streamer.dev(dev_name)?.tag_calc_nsamps(chan_name, n_samps, start_time, end_time))
+}
+
+/// Same as [`calc_nsamps`] with `n_samps` equal to the length of `out`, but the samples are written
+/// directly into the memory of `out` - a writable, C-contiguous, 1D buffer of `float64`.
+///
+/// The GIL is released during the calculation. Like numpy's own GIL-releasing operations, the function relies on
+/// no other Python thread writing into `out` at the same time (the exported buffer itself can't be resized or freed).
+pub fn calc_nsamps_into<S: BaseStreamer + Sync>(
+    py: Python<'_>,
+    streamer: &S,
+    dev_name: &str,
+    chan_name: &str,
+    out: &Bound<'_, PyAny>,
+    start_time: Option<f64>,
+    end_time: Option<f64>,
+) -> PyResult<()> {
+    let buf = PyBuffer::<f64>::get_bound(out)?;
+    if buf.readonly() {
+        return Err(PyBufferError::new_err("output buffer is read-only"))
+    }
+    if buf.dimensions() != 1 || !buf.is_c_contiguous() {
+        return Err(PyBufferError::new_err(format!(
+            "output buffer must be 1D and C-contiguous, got {} dimensions", buf.dimensions()
+        )))
+    }
+    // SAFETY: the buffer is writable, contiguous, holds `item_count` properly aligned `f64` elements (checked by `get_bound`),
+    // and stays alive and un-resized until `buf` is released below
+    let res_arr = unsafe { std::slice::from_raw_parts_mut(buf.buf_ptr() as *mut f64, buf.item_count()) };
+    let res = nogil!(py, streamer.dev(dev_name)?.tag_calc_nsamps_into(chan_name, res_arr, start_time, end_time));
+    buf.release(py);
+    res
+}
+
+/// Allocates an `n_samps` long `numpy.float64` array and fills it with [`calc_nsamps_into`]
+pub fn calc_nsamps_numpy<'py, S: BaseStreamer + Sync>(
+    py: Python<'py>,
+    streamer: &S,
+    dev_name: &str,
+    chan_name: &str,
+    n_samps: usize,
+    start_time: Option<f64>,
+    end_time: Option<f64>,
+) -> PyResult<Bound<'py, PyAny>> {
+    let np = py.import_bound("numpy")?;
+    let arr = np.call_method1("empty", (n_samps, np.getattr("float64")?))?;
+    calc_nsamps_into(py, streamer, dev_name, chan_name, &arr, start_time, end_time)?;
+    Ok(arr)
+}
+
+/// `(t_arr, samps)` of channel `chan_name` of device `dev_name` as two aligned `numpy.float64` arrays
+/// (see [`BaseChan::plot_data`]) - e.g. `plt.plot(*streamer.plot_data("Dev1", "ao0", 1000))`.
+/// The samples are calculated with the GIL released.
+///
+/// [`BaseChan::plot_data`]: crate::channel::BaseChan::plot_data
+pub fn plot_data<'py, S: BaseStreamer + Sync>(
+    py: Python<'py>,
+    streamer: &S,
+    dev_name: &str,
+    chan_name: &str,
+    n_samps: usize,
+    start_time: Option<f64>,
+    end_time: Option<f64>,
+) -> PyResult<(Bound<'py, PyAny>, Bound<'py, PyAny>)> {
+    let (t_arr, samps) = nogil!(py, streamer.dev(dev_name)?.tag_chan_plot_data(chan_name, n_samps, start_time, end_time))?;
+    Ok((to_numpy(py, &t_arr)?, to_numpy(py, &samps)?))
+}
+
+/// [`plot_data`] of all active channels of device `dev_name` - a dict of channel name -> `(t_arr, samps)`
+pub fn dev_plot_data<'py, S: BaseStreamer + Sync>(
+    py: Python<'py>,
+    streamer: &S,
+    dev_name: &str,
+    n_samps: usize,
+    start_time: Option<f64>,
+    end_time: Option<f64>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let data = nogil!(py, streamer.dev(dev_name)?.tag_plot_data(n_samps, start_time, end_time))?;
+    let dict = PyDict::new_bound(py);
+    for (chan_name, (t_arr, samps)) in data {
+        dict.set_item(chan_name, (to_numpy(py, &t_arr)?, to_numpy(py, &samps)?))?;
+    }
+    Ok(dict)
+}
+
+/// [`BaseStreamer::calc_all`] with the GIL released - `(t_arr, {"dev/chan": samps})` with `numpy.float64` arrays
+pub fn calc_all<'py, S: BaseStreamer + Sync>(
+    py: Python<'py>,
+    streamer: &S,
+    n_samps: usize,
+    start_time: Option<f64>,
+    end_time: Option<f64>,
+) -> PyResult<(Bound<'py, PyAny>, Bound<'py, PyDict>)> {
+    let (t_arr, chans) = nogil!(py, streamer.calc_all(n_samps, start_time, end_time))?;
+    let dict = PyDict::new_bound(py);
+    for (name, samps) in chans {
+        dict.set_item(name, to_numpy(py, &samps)?)?;
+    }
+    Ok((to_numpy(py, &t_arr)?, dict))
+}
+
+fn to_numpy<'py>(py: Python<'py>, vals: &[f64]) -> PyResult<Bound<'py, PyAny>> {
+    let np = py.import_bound("numpy")?;
+    let arr = np.call_method1("empty", (vals.len(), np.getattr("float64")?))?;
+    let buf = PyBuffer::<f64>::get_bound(&arr)?;
+    let res = buf.copy_from_slice(py, vals);
+    buf.release(py);
+    res.map(|()| arr)
+}
+
+/// Registers the Python callable `func` as a per-channel validation rule named `name`, see [`PyRule`].
+/// `severity` of the findings is `"info"`, `"warning"`, or `"error"`.
+pub fn add_rule<S: BaseStreamer>(streamer: &mut S, name: &str, func: PyObject, severity: &str) -> PyResult<()> {
+    let severity = match severity {
+        "info" => Severity::Info,
+        "warning" => Severity::Warning,
+        "error" => Severity::Error,
+        _ => return Err(StreamerError::InvalidArgument {
+            ctx: ErrCtx::none(),
+            msg: format!("Unknown severity \"{severity}\", expected \"info\", \"warning\", or \"error\""),
+        }.into()),
+    };
+    Ok(streamer.add_rule(Box::new(PyRule::new(name, func, severity)))?)
+}
+
+/// Registers the Python callable `func` as an event observer, see [`PyObserver`]. Returns the id for [`remove_observer`].
+pub fn add_observer<S: BaseStreamer>(streamer: &mut S, func: PyObject) -> PyResult<usize> {
+    let observer = PyObserver::new(func);
+    Ok(streamer.add_observer(Box::new(move |event| observer.notify(event)))?.0)
+}
+
+/// Removes the observer registered by [`add_observer`]
+pub fn remove_observer<S: BaseStreamer>(streamer: &mut S, id: usize) -> PyResult<()> {
+    Ok(streamer.remove_observer(ObserverId(id))?)
+}
+
+/// Registers the interlock "`chan` must be high whenever `whenever` is high", see [`Interlock::Requires`]
+pub fn add_interlock_requires<S: BaseStreamer>(streamer: &mut S, chan: &str, whenever: &str) -> PyResult<()> {
+    Ok(streamer.add_rule(Box::new(Interlock::requires(chan, whenever)))?)
+}
+
+/// Registers the interlock "no two of `chans` are high at the same time", see [`Interlock::Exclusive`]
+pub fn add_interlock_exclusive<S: BaseStreamer>(streamer: &mut S, chans: Vec<String>) -> PyResult<()> {
+    Ok(streamer.add_rule(Box::new(Interlock::Exclusive { chans }))?)
+}
+
+/// Activity windows of all active channels as `{dev_name: {chan_name: (first_instr_start_time, last_instr_end_time)}}`,
+/// see [`BaseStreamer::activity_windows`]
+pub fn activity_windows<'py, S: BaseStreamer>(py: Python<'py>, streamer: &S) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new_bound(py);
+    for (dev_name, windows) in streamer.activity_windows() {
+        let dev_dict = PyDict::new_bound(py);
+        for (chan_name, window) in windows {
+            dev_dict.set_item(chan_name, window)?;
+        }
+        dict.set_item(dev_name, dev_dict)?;
+    }
+    Ok(dict)
+}
+
+/// Segment densities of all active channels as `{dev_name: {chan_name: segments_per_second}}`,
+/// see [`BaseStreamer::segment_densities`]
+pub fn segment_densities<'py, S: BaseStreamer>(py: Python<'py>, streamer: &S) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new_bound(py);
+    for (dev_name, densities) in streamer.segment_densities()? {
+        let dev_dict = PyDict::new_bound(py);
+        for (chan_name, density) in densities {
+            dev_dict.set_item(chan_name, density)?;
+        }
+        dict.set_item(dev_name, dev_dict)?;
+    }
+    Ok(dict)
+}
+
+/// Padding segments of all active channels as `{dev_name: {chan_name: [segment_dict, ...]}}`,
+/// see [`BaseStreamer::padding_report`]
+pub fn padding_report<'py, S: BaseStreamer>(py: Python<'py>, streamer: &S) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new_bound(py);
+    for (dev_name, chans) in streamer.padding_report()? {
+        let dev_dict = PyDict::new_bound(py);
+        for (chan_name, segs) in chans {
+            let seg_dicts = segs.iter().map(|seg| seg.to_dict(py)).collect::<PyResult<Vec<_>>>()?;
+            dev_dict.set_item(chan_name, seg_dicts)?;
+        }
+        dict.set_item(dev_name, dev_dict)?;
+    }
+    Ok(dict)
+}
+
+/// Per-device and total data rates of the compiled experiment as a dict, see [`BaseStreamer::stream_budget`]
+pub fn stream_budget<'py, S: BaseStreamer>(py: Python<'py>, streamer: &S) -> PyResult<Bound<'py, PyDict>> {
+    streamer.stream_budget()?.to_dict(py)
+}
+
+/// Sample counts and memory footprint of a hypothetical compile as a dict, see [`BaseStreamer::predicted_samples`]
+pub fn predicted_samples<'py, S: BaseStreamer>(py: Python<'py>, streamer: &S, stop_time: Option<f64>) -> PyResult<Bound<'py, PyDict>> {
+    streamer.predicted_samples(stop_time)?.to_dict(py)
+}
+
+/// Edge pairs and skews of two channels as a dict, see [`BaseStreamer::measure_skew`]
+pub fn measure_skew<'py, S: BaseStreamer>(py: Python<'py>, streamer: &S, spec: &SkewSpec) -> PyResult<Bound<'py, PyDict>> {
+    streamer.measure_skew(spec)?.to_dict(py)
+}
+
+/// Lag of best alignment of two channels as a dict, see [`BaseStreamer::relative_delay`]
+pub fn relative_delay<'py, S: BaseStreamer + Sync>(
+    py: Python<'py>, streamer: &S, chan_a: &str, chan_b: &str, window: (f64, f64)
+) -> PyResult<Bound<'py, PyDict>> {
+    nogil!(py, streamer.relative_delay(chan_a, chan_b, window))?.to_dict(py)
+}
+
+/// [`BaseStreamer::resample_all`] with the GIL released as a dict `"dev/chan"` -> samples.
+/// `method` is `"hold"`, `"linear"`, or `"sinc"` (using `half_width`).
+pub fn resample_all<'py, S: BaseStreamer + Sync>(
+    py: Python<'py>, streamer: &S, to_rate: f64, method: &str, half_width: usize
+) -> PyResult<Bound<'py, PyDict>> {
+    let method = match method {
+        "hold" => Resampling::Hold,
+        "linear" => Resampling::Linear,
+        "sinc" => Resampling::Sinc { half_width },
+        _ => return Err(StreamerError::InvalidArgument {
+            ctx: ErrCtx::none(),
+            msg: format!("Unknown resampling method \"{method}\", expected \"hold\", \"linear\", or \"sinc\""),
+        }.into()),
+    };
+    let res = nogil!(py, streamer.resample_all(to_rate, method))?;
+    let dict = PyDict::new_bound(py);
+    for (key, samps) in res {
+        dict.set_item(key, samps)?;
+    }
+    Ok(dict)
+}
+
+/// [`BaseStreamer::find_edges`] with the GIL released as a list of `(kind, t)` with kind `"rising"` or `"falling"`
+pub fn find_edges<S: BaseStreamer + Sync>(py: Python<'_>, streamer: &S, dev_name: &str, chan_name: &str, threshold: f64) -> PyResult<Vec<(String, f64)>> {
+    let edges = nogil!(py, streamer.find_edges(dev_name, chan_name, threshold))?;
+    Ok(edges.into_iter().map(|edge| (format!("{:?}", edge.kind).to_lowercase(), edge.t)).collect())
+}
+
+/// [`BaseStreamer::find_peaks`] with the GIL released as a list of `(t, height, width)`
+pub fn find_peaks<S: BaseStreamer + Sync>(
+    py: Python<'_>, streamer: &S, dev_name: &str, chan_name: &str, min_height: f64, min_spacing: f64
+) -> PyResult<Vec<(f64, f64, f64)>> {
+    let peaks = nogil!(py, streamer.find_peaks(dev_name, chan_name, min_height, min_spacing))?;
+    Ok(peaks.into_iter().map(|peak| (peak.t, peak.height, peak.width)).collect())
+}
+
+/// [`BaseStreamer::integral`] with the GIL released
+pub fn integral<S: BaseStreamer + Sync>(
+    py: Python<'_>, streamer: &S, dev_name: &str, chan_name: &str, start_time: Option<f64>, end_time: Option<f64>
+) -> PyResult<f64> {
+    nogil!(py, streamer.integral(dev_name, chan_name, start_time, end_time))
+}
+
+/// [`BaseStreamer::export_vcd`] with the GIL released
+pub fn export_vcd<S: BaseStreamer + Sync>(py: Python<'_>, streamer: &S, path: &str) -> PyResult<()> {
+    nogil!(py, streamer.export_vcd(path))
+}
+
+/// [`BaseStreamer::import_vcd`] with the GIL released
+pub fn import_vcd<S: BaseStreamer + Send>(py: Python<'_>, streamer: &mut S, path: &str) -> PyResult<Vec<String>> {
+    nogil!(py, streamer.import_vcd(path))
+}
+
+/// [`BaseStreamer::idle_map`] as a dict device name -> idle map dict
+pub fn idle_map<'py, S: BaseStreamer>(py: Python<'py>, streamer: &S, min_idle_time: f64) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new_bound(py);
+    for (dev_name, dev_idle) in streamer.idle_map(min_idle_time)? {
+        dict.set_item(dev_name, dev_idle.to_dict(py)?)?;
+    }
+    Ok(dict)
+}
+
+/// Number of registered devices - for `__len__`
+pub fn dev_count<S: BaseStreamer>(streamer: &S) -> usize {
+    streamer.devs().len()
+}
+
+/// Iterator over the [`DevInfo`] of all registered devices - for `__iter__`
+pub fn iter_devs<'py, S: BaseStreamer>(py: Python<'py>, streamer: &S) -> PyResult<Bound<'py, PyIterator>> {
+    let devs = streamer.devs().into_iter().map(|dev| Py::new(py, dev.tag_info())).collect::<PyResult<Vec<_>>>()?;
+    PyIterator::from_bound_object(&PyList::new_bound(py, devs))
+}
+
+/// [`DevInfo`] for key `"Dev1"`, [`ChanInfo`] for key `"Dev1/ao0"` - for `__getitem__`.
+/// Raises `KeyError` for unknown devices and channels.
+///
+/// The key is split at the first `/`, so channel names may contain `/` themselves (`"Dev1/port0/line0"`).
+///
+/// [`ChanInfo`]: crate::inspect::ChanInfo
+pub fn getitem<S: BaseStreamer>(py: Python<'_>, streamer: &S, key: &str) -> PyResult<PyObject> {
+    let (dev_name, chan_name) = match key.split_once('/') {
+        Some((dev_name, chan_name)) => (dev_name, Some(chan_name)),
+        None => (key, None),
+    };
+    let info = dev_info(streamer, dev_name).ok_or_else(|| PyKeyError::new_err(format!("There is no device with name {dev_name} registered")))?;
+    match chan_name {
+        Some(chan_name) => Ok(info.__getitem__(chan_name)?.into_py(py)),
+        None => Ok(info.into_py(py)),
+    }
+}
+
+/// Whether [`getitem`] finds `key` - for `__contains__`
+pub fn contains<S: BaseStreamer>(streamer: &S, key: &str) -> bool {
+    match key.split_once('/') {
+        Some((dev_name, chan_name)) => dev_info(streamer, dev_name).is_some_and(|info| info.__contains__(chan_name)),
+        None => dev_info(streamer, key).is_some(),
+    }
+}
+
+fn dev_info<S: BaseStreamer>(streamer: &S, dev_name: &str) -> Option<DevInfo> {
+    streamer.dev(dev_name).ok().map(|dev| dev.tag_info())
+}
+
+/// Pickle state of the streamer - its edit caches as a JSON schedule document (see [`BaseStreamer::export_schedule_json`])
+pub fn getstate<S: BaseStreamer>(streamer: &S) -> PyResult<String> {
+    Ok(streamer.export_schedule_json()?)
+}
+
+/// Replaces the edit caches of the streamer with the instructions of `state`, see [`getstate`].
+/// Functions are rebuilt with [`FnRegistry::global`].
+pub fn setstate<S: BaseStreamer>(streamer: &mut S, state: &str) -> PyResult<()> {
+    streamer.clear_edit_cache();
+    let registry = FnRegistry::global().read().expect("global registry lock poisoned");
+    Ok(streamer.import_schedule_json_with(state, &registry)?)
+}
+
+#[cfg(test)]
+mod test {
+    use pyo3::prelude::*;
+    use crate::channel::BaseChan;
+    use crate::device::BaseDev;
+    use pyo3::exceptions::PyKeyError;
+    use crate::channel::ConstFn;
+    use crate::diagnostics::DiagnosticKind;
+    use crate::error::{IncompatibleError, NotFoundError, RuleViolationError, StrictViolationError};
+    use crate::options::CompileOptions;
+    use crate::streamer::BaseStreamer;
+    use crate::fn_lib_tools::{self, ArrayFn, FnArgs, FnBoxBool, FnBoxF64, FnRegistry, Interp};
+    use crate::inspect::{ChanInfo, DevInfo};
+    use crate::mock::test_impls::TestStreamer;
+    use crate::py_tools;
+
+    #[test]
+    fn release_gil() {
+        let mut streamer = TestStreamer::new();
+        streamer.add_ao_dev("AO", 1e3);
+        streamer.ao_devs["AO"].add_chan("ao0", 0.0);
+        streamer.ao_devs["AO"].chan_mut("ao0").unwrap().constant(1.0, 0.0, Some((0.5, false))).unwrap();
+
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            assert_eq!(py_tools::compile(py, &mut streamer, Some(1.0)).unwrap(), 1.0);
+            py_tools::check_finite(py, &streamer, None).unwrap();
+            let samps = py_tools::calc_nsamps(py, &streamer, "AO", "ao0", 3, Some(0.0), Some(0.9)).unwrap();
+            assert_eq!(samps, vec![1.0, 1.0, 0.0]);
+
+            let err = py_tools::calc_nsamps(py, &streamer, "AO1", "ao0", 3, None, None).unwrap_err();
+            assert!(err.is_instance_of::<NotFoundError>(py));
+        });
+    }
+
+    #[test]
+    fn calc_into_buffer() {
+        let mut streamer = TestStreamer::new();
+        streamer.add_ao_dev("AO", 1e3);
+        streamer.add_do_dev("DO", 1e3);
+        streamer.ao_devs["AO"].add_chan("ao0", 0.0);
+        streamer.do_devs["DO"].add_chan("port0/line0", false);
+        streamer.ao_devs["AO"].chan_mut("ao0").unwrap().constant(1.0, 0.0, Some((0.5, false))).unwrap();
+        streamer.do_devs["DO"].chan_mut("port0/line0").unwrap().constant(true, 0.5, None).unwrap();
+
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            py_tools::compile(py, &mut streamer, Some(1.0)).unwrap();
+            let array = py.import_bound("array").unwrap();
+            let new_buf = || array.call_method1("array", ("d", vec![-1.0; 3])).unwrap();
+
+            let out = new_buf();
+            py_tools::calc_nsamps_into(py, &streamer, "AO", "ao0", &out, Some(0.0), Some(0.9)).unwrap();
+            assert_eq!(out.extract::<Vec<f64>>().unwrap(), vec![1.0, 1.0, 0.0]);
+            // Non-f64 channels are converted
+            let out = new_buf();
+            py_tools::calc_nsamps_into(py, &streamer, "DO", "port0/line0", &out, Some(0.0), Some(0.9)).unwrap();
+            assert_eq!(out.extract::<Vec<f64>>().unwrap(), vec![0.0, 0.0, 1.0]);
+
+            // Read-only buffers and mismatching element types are rejected
+            let read_only = pyo3::types::PyMemoryView::from_bound(&new_buf()).unwrap().call_method0("toreadonly").unwrap();
+            assert!(py_tools::calc_nsamps_into(py, &streamer, "AO", "ao0", &read_only, None, None).is_err());
+            let ints = array.call_method1("array", ("i", vec![0; 3])).unwrap();
+            assert!(py_tools::calc_nsamps_into(py, &streamer, "AO", "ao0", &ints, None, None).is_err());
+        });
+    }
+
+    #[test]
+    fn dict_access() {
+        let mut streamer = TestStreamer::new();
+        streamer.add_ao_dev("AO", 1e3);
+        streamer.add_do_dev("DO", 1e3);
+        streamer.ao_devs["AO"].add_chan("ao0", 0.0);
+        streamer.ao_devs["AO"].add_chan("ao1", 0.0);
+        streamer.do_devs["DO"].add_chan("port0/line0", false);
+        streamer.ao_devs["AO"].chan_mut("ao1").unwrap().constant(1.0, 0.1, Some((0.2, false))).unwrap();
+
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            py_tools::compile(py, &mut streamer, Some(1.0)).unwrap();
+            assert_eq!(py_tools::dev_count(&streamer), 2);
+            let names: Vec<String> = py_tools::iter_devs(py, &streamer).unwrap()
+                .map(|dev| dev.unwrap().getattr("name").unwrap().extract().unwrap())
+                .collect();
+            assert_eq!(names, vec!["AO", "DO"]);
+
+            let ao = py_tools::getitem(py, &streamer, "AO").unwrap().extract::<DevInfo>(py).unwrap();
+            assert_eq!(ao.chan_names(), vec!["ao0", "ao1"]);
+            assert!(ao.got_instructions);
+            let ao1 = py_tools::getitem(py, &streamer, "AO/ao1").unwrap().extract::<ChanInfo>(py).unwrap();
+            assert_eq!(ao1, ao.chans[1]);
+            assert_eq!((ao1.n_instrs, ao1.first_instr_time, ao1.last_instr_end_time), (1, Some(0.1), Some(0.3)));
+            assert_eq!(ao1.compiled_stop_time, Some(1.0));
+            assert_eq!(ao.chans[0].compiled_stop_time, None);
+            // Channel names containing '/'
+            let line0 = py_tools::getitem(py, &streamer, "DO/port0/line0").unwrap().extract::<ChanInfo>(py).unwrap();
+            assert_eq!(line0.name, "port0/line0");
+
+            assert!(py_tools::contains(&streamer, "AO/ao0"));
+            assert!(!py_tools::contains(&streamer, "AO/ao2"));
+            assert!(!py_tools::contains(&streamer, "XX"));
+            assert!(py_tools::getitem(py, &streamer, "AO/ao2").unwrap_err().is_instance_of::<PyKeyError>(py));
+            assert!(py_tools::getitem(py, &streamer, "XX").unwrap_err().is_instance_of::<PyKeyError>(py));
+
+            // Python-side protocol of DevInfo
+            let ao = Py::new(py, ao).unwrap().into_bound(py);
+            assert_eq!(ao.len().unwrap(), 2);
+            assert!(ao.contains("ao1").unwrap());
+            assert_eq!(ao.get_item("ao1").unwrap().getattr("name").unwrap().extract::<String>().unwrap(), "ao1");
+            assert_eq!(ao.iter().unwrap().count(), 2);
+        });
+    }
+
+    #[test]
+    fn pickle() {
+        let mut streamer = TestStreamer::new();
+        streamer.add_ao_dev("AO", 1e3);
+        streamer.ao_devs["AO"].add_chan("ao0", 0.0);
+        streamer.ao_devs["AO"].chan_mut("ao0").unwrap().constant(1.0, 0.1, Some((0.2, true))).unwrap();
+        let mut restored = TestStreamer::new();
+        restored.add_ao_dev("AO", 1e3);
+        restored.ao_devs["AO"].add_chan("ao0", 0.0);
+        restored.ao_devs["AO"].chan_mut("ao0").unwrap().constant(2.0, 0.5, None).unwrap();
+        let state = py_tools::getstate(&streamer).unwrap();
+        py_tools::setstate(&mut restored, &state).unwrap();
+        assert_eq!(py_tools::getstate(&restored).unwrap(), state);
+
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let m = PyModule::new_bound(py, "pickle_test").unwrap();
+            fn_lib_tools::register_fn_boxes(&m).unwrap();
+            py.import_bound("sys").unwrap().getattr("modules").unwrap().set_item("pickle_test", &m).unwrap();
+            let pickle = py.import_bound("pickle").unwrap();
+            let round_trip = |obj: Bound<'_, PyAny>| {
+                let data = pickle.call_method1("dumps", (obj,)).unwrap();
+                pickle.call_method1("loads", (data,)).unwrap().repr().unwrap().to_string()
+            };
+
+            let sine = FnBoxF64 { inner: FnRegistry::std().build("Sine", &FnArgs::Positional(vec![1.0, 1e3, 0.5])).unwrap() };
+            assert_eq!(round_trip(Py::new(py, sine).unwrap().into_bound(py).into_any()), "Sine(amp=1.0, freq=1000.0, phase=0.5, offs=0.0)");
+            let high = FnBoxBool { inner: Box::new(ConstFn::new(true)) };
+            assert_eq!(round_trip(Py::new(py, high).unwrap().into_bound(py).into_any()), "ConstFn(val=true)");
+
+            // Functions the global registry can't rebuild fail at pickling time
+            let array = FnBoxF64 { inner: Box::new(ArrayFn::new(vec![0.0, 1.0], 1e-3, Interp::Linear, 0.0)) };
+            let err = pickle.call_method1("dumps", (Py::new(py, array).unwrap(),)).unwrap_err();
+            assert!(err.is_instance_of::<IncompatibleError>(py));
+        });
+    }
+
+    #[test]
+    fn py_rule() {
+        let mut streamer = TestStreamer::new();
+        streamer.add_ao_dev("AO", 1e3);
+        streamer.ao_devs["AO"].add_chan("ao0", 0.0);
+        streamer.ao_devs["AO"].add_chan("ao1", 0.0);
+        streamer.ao_devs["AO"].chan_mut("ao0").unwrap().constant(1.0, 0.0, Some((0.1, false))).unwrap();
+        streamer.ao_devs["AO"].chan_mut("ao1").unwrap().constant(5.0, 0.2, Some((0.1, false))).unwrap();
+
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let max_amp = py.eval_bound(
+                "lambda chan: [(t0, f'{chan[\"chan\"]} exceeds 2 V') for (t0, t1, val) in chan['segments'] if val is not None and abs(val) > 2]",
+                None, None
+            ).unwrap().unbind();
+            py_tools::add_rule(&mut streamer, "max_amp", max_amp.clone_ref(py), "warning").unwrap();
+            assert!(py_tools::add_rule(&mut streamer, "max_amp", max_amp, "fatal").is_err());
+
+            // Warnings only fail strict compiles
+            py_tools::compile(py, &mut streamer, Some(1.0)).unwrap();
+            let entries = streamer.diagnostics();
+            let finding = entries.iter().find(|entry| entry.kind == DiagnosticKind::Rule).unwrap();
+            assert_eq!(finding.message, "[max_amp] ao1 exceeds 2 V");
+            assert_eq!((finding.chan.as_deref(), finding.pos), (Some("ao1"), Some(200)));
+            let err = py_tools::compile_with(py, &mut streamer, Some(1.0), &CompileOptions::strict()).unwrap_err();
+            assert!(err.is_instance_of::<StrictViolationError>(py));
+
+            // Exceptions raised by the rule are reported as errors
+            let broken = py.eval_bound("lambda chan: 1 / 0", None, None).unwrap().unbind();
+            py_tools::add_rule(&mut streamer, "broken", broken, "info").unwrap();
+            let err = py_tools::compile(py, &mut streamer, Some(1.0)).unwrap_err();
+            assert!(err.is_instance_of::<RuleViolationError>(py));
+            assert!(err.to_string().contains("ZeroDivisionError"));
+        });
+    }
+}
@@ -0,0 +1,48 @@
+//! Physical quantity represented by channel samples.
+//!
+//! Most channels output voltages, but DDS-style hardware is programmed with frequency and phase profiles.
+//! A channel declares its quantity with [`BaseChan::quantity`] and its admissible value range with
+//! [`BaseChan::val_range`] (by default [`Quantity::default_range`]). The range is checked by
+//! [`BaseChan::check_range`] - and so by every compile with `CompileOptions::check_limits` enabled.
+//!
+//! [`BaseChan::quantity`]: crate::channel::BaseChan::quantity
+//! [`BaseChan::val_range`]: crate::channel::BaseChan::val_range
+//! [`BaseChan::check_range`]: crate::channel::BaseChan::check_range
+
+use std::fmt;
+use std::fmt::Display;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Quantity {
+    /// Voltage in V (also used for unitless digital values)
+    #[default]
+    Voltage,
+    /// Frequency in Hz
+    Frequency,
+    /// Phase in rad
+    Phase,
+}
+
+impl Quantity {
+    pub fn unit(&self) -> &'static str {
+        match self {
+            Quantity::Voltage => "V",
+            Quantity::Frequency => "Hz",
+            Quantity::Phase => "rad",
+        }
+    }
+    /// Inclusive `(min, max)` range every channel of this quantity must respect: frequencies can't be negative,
+    /// voltages and phases are not limited (voltage limits are hardware-specific).
+    pub fn default_range(&self) -> Option<(f64, f64)> {
+        match self {
+            Quantity::Frequency => Some((0.0, f64::INFINITY)),
+            Quantity::Voltage | Quantity::Phase => None,
+        }
+    }
+}
+
+impl Display for Quantity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?} [{}]", self.unit())
+    }
+}
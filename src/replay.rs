@@ -0,0 +1,440 @@
+//! Replay of archived sample files through the regular device interface.
+//!
+//! [`ReplayDev`] is a [`BaseDev`] whose channels play back recorded samples instead of instructions written by hand,
+//! so that an archived run can be streamed through a downstream backend again, e.g. to debug the hardware side.
+//! Each [`ReplayChan`] holds one instruction covering its recording - a [`ReplaySamps`] function which indexes
+//! the samples by clock tick. Compiling, streaming, plotting, and every other `BaseDev` / `BaseChan` method
+//! work as usual; past the end of the recording the channel outputs its default value.
+//!
+//! Recordings are read from NumPy `.npy` files of shape `(n_chans, n_samps)` (or `(n_samps,)` for a single channel)
+//! holding `float64` or `bool` samples. [`save_npy`] writes the compiled samples of any device in this format.
+//! HDF5 is not supported - datasets can be converted with `np.save(path, h5file[key][()])`.
+//!
+//! ```ignore
+//! save_npy(&streamer.ao_devs["Dev1"], "run_42.npy")?;
+//! let mut dev = ReplayDev::<f64>::load_npy("Dev1", 1e6, "run_42.npy", &["ao0", "ao1"])?;
+//! let stop_time = dev.last_instr_end_time().unwrap();
+//! dev.compile(stop_time)?;
+//! ```
+
+use std::collections::BTreeSet;
+use std::fmt::Debug;
+use std::fs;
+use std::sync::Arc;
+use indexmap::IndexMap;
+use crate::channel::{AsF64, BaseChan};
+use crate::device::BaseDev;
+use crate::diagnostics::Diagnostics;
+use crate::error::{ErrCtx, StreamerError};
+use crate::fn_lib_tools::{Calc, Describe, FnTraitSet};
+use crate::instruction::{Instr, InstrMeta};
+
+const NPY_MAGIC: &[u8] = b"\x93NUMPY";
+
+/// Sample type stored in `.npy` files
+pub trait NpySamp: Sized {
+    /// NumPy dtype string
+    const DESCR: &'static str;
+    const SIZE: usize;
+    fn from_le_bytes(bytes: &[u8]) -> Self;
+    fn extend_le_bytes(&self, bytes: &mut Vec<u8>);
+}
+
+impl NpySamp for f64 {
+    const DESCR: &'static str = "<f8";
+    const SIZE: usize = 8;
+    fn from_le_bytes(bytes: &[u8]) -> Self {
+        f64::from_le_bytes(bytes.try_into().expect("NpySamp::from_le_bytes() is called with SIZE bytes"))
+    }
+    fn extend_le_bytes(&self, bytes: &mut Vec<u8>) {
+        bytes.extend(self.to_le_bytes())
+    }
+}
+
+impl NpySamp for bool {
+    const DESCR: &'static str = "|b1";
+    const SIZE: usize = 1;
+    fn from_le_bytes(bytes: &[u8]) -> Self {
+        bytes[0] != 0
+    }
+    fn extend_le_bytes(&self, bytes: &mut Vec<u8>) {
+        bytes.push(*self as u8)
+    }
+}
+
+fn npy_err(path: &str, msg: String) -> StreamerError {
+    StreamerError::InvalidArgument { ctx: ErrCtx::none(), msg: format!("{path}: {msg}") }
+}
+
+/// Value of `key` in the header dict of a `.npy` file, e.g. `'<f8'` for `'descr'`
+fn header_val<'a>(header: &'a str, key: &str) -> Option<&'a str> {
+    let rest = &header[header.find(&format!("'{key}'"))? + key.len() + 2..];
+    let rest = rest.trim_start().strip_prefix(':')?.trim_start();
+    let end = match rest.chars().next()? {
+        '(' => rest.find(')')? + 1,
+        '\'' => rest[1..].find('\'')? + 2,
+        _ => rest.find([',', '}'])?,
+    };
+    Some(rest[..end].trim())
+}
+
+/// Rows of the 1- or 2-dimensional array in the `.npy` file at `path`. A 1-dimensional array is a single row.
+pub fn read_npy<T: NpySamp>(path: &str) -> Result<Vec<Vec<T>>, StreamerError> {
+    let bytes = fs::read(path).map_err(|err| StreamerError::NotFound {
+        ctx: ErrCtx::none(),
+        msg: format!("Failed to read replay file {path}: {err}"),
+    })?;
+    if !bytes.starts_with(NPY_MAGIC) || bytes.len() < 10 {
+        return Err(npy_err(path, "not a .npy file".to_string()))
+    }
+    let (header_len, header_start) = match bytes[6] {
+        1 => (u16::from_le_bytes([bytes[8], bytes[9]]) as usize, 10),
+        _ if bytes.len() >= 12 => (u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]) as usize, 12),
+        _ => return Err(npy_err(path, "truncated header".to_string())),
+    };
+    let header = bytes.get(header_start..header_start + header_len)
+        .and_then(|header| std::str::from_utf8(header).ok())
+        .ok_or_else(|| npy_err(path, "truncated or non-UTF-8 header".to_string()))?;
+
+    let descr = header_val(header, "descr").unwrap_or_default().trim_matches('\'');
+    if descr != T::DESCR {
+        return Err(npy_err(path, format!("expected dtype '{}', got '{descr}'", T::DESCR)))
+    }
+    let fortran_order = header_val(header, "fortran_order") == Some("True");
+    let shape: Vec<usize> = header_val(header, "shape")
+        .and_then(|shape| shape.trim_matches(['(', ')']).split(',').map(str::trim).filter(|dim| !dim.is_empty()).map(|dim| dim.parse().ok()).collect())
+        .ok_or_else(|| npy_err(path, format!("invalid header {header}")))?;
+    let (n_rows, n_cols) = match shape[..] {
+        [n_samps] => (1, n_samps),
+        [n_chans, n_samps] => (n_chans, n_samps),
+        _ => return Err(npy_err(path, format!("expected a 1- or 2-dimensional array, got shape {shape:?}"))),
+    };
+
+    let data = &bytes[header_start + header_len..];
+    if data.len() != n_rows * n_cols * T::SIZE {
+        return Err(npy_err(path, format!("shape {shape:?} needs {} data bytes, the file has {}", n_rows * n_cols * T::SIZE, data.len())))
+    }
+    let samp = |idx: usize| T::from_le_bytes(&data[idx * T::SIZE..(idx + 1) * T::SIZE]);
+    Ok((0..n_rows).map(|row| {
+        (0..n_cols).map(|col| samp(if fortran_order { col * n_rows + row } else { row * n_cols + col })).collect()
+    }).collect())
+}
+
+/// Writes equal-length `rows` as a `(rows.len(), n_samps)` array in `.npy` format (version 1.0) to `path`
+pub fn write_npy<T: NpySamp>(path: &str, rows: &[Vec<T>]) -> Result<(), StreamerError> {
+    let n_cols = rows.first().map_or(0, Vec::len);
+    if rows.iter().any(|row| row.len() != n_cols) {
+        return Err(npy_err(path, "all rows must have the same length".to_string()))
+    }
+    let mut header = format!("{{'descr': '{}', 'fortran_order': False, 'shape': ({}, {n_cols}), }}", T::DESCR, rows.len());
+    // Data starts 64-byte aligned, the header ends with a newline
+    let padded_len = (NPY_MAGIC.len() + 4 + header.len() + 1).div_ceil(64) * 64;
+    header.push_str(&" ".repeat(padded_len - (NPY_MAGIC.len() + 4 + header.len() + 1)));
+    header.push('\n');
+
+    let mut bytes = NPY_MAGIC.to_vec();
+    bytes.extend([1, 0]);
+    bytes.extend((header.len() as u16).to_le_bytes());
+    bytes.extend(header.as_bytes());
+    for samp in rows.iter().flatten() {
+        samp.extend_le_bytes(&mut bytes)
+    }
+    fs::write(path, bytes).map_err(|err| npy_err(path, format!("failed to write: {err}")))
+}
+
+/// Writes the full compiled sequence of all active channels of `dev` to `path`, one row per channel
+/// in the order of [`BaseDev::active_chans`] - see [`ReplayDev::load_npy`].
+pub fn save_npy<D: BaseDev>(dev: &D, path: &str) -> Result<(), StreamerError>
+where <D::Chan as BaseChan>::Samp: NpySamp
+{
+    let stop_pos = dev.try_compiled_stop_pos()?;
+    let active_chans = dev.active_chans();
+    let mut samp_buf = vec![active_chans[0].dflt_val(); active_chans.len() * stop_pos];
+    dev.calc_samps(&mut samp_buf, 0, stop_pos)?;
+    write_npy(path, &samp_buf.chunks(stop_pos).map(<[_]>::to_vec).collect::<Vec<_>>())
+}
+
+/// Recorded samples as an instruction function - the value at time `t` is the sample at tick `round(t * samp_rate)`
+#[derive(Clone, Debug)]
+pub struct ReplaySamps<T> {
+    samps: Arc<Vec<T>>,
+    samp_rate: f64,
+}
+
+impl<T> ReplaySamps<T> {
+    pub fn new(samps: Arc<Vec<T>>, samp_rate: f64) -> Self {
+        Self { samps, samp_rate }
+    }
+    fn samp(&self, pos: usize) -> &T {
+        &self.samps[pos.min(self.samps.len() - 1)]
+    }
+}
+
+impl<T: Clone> Calc<T> for ReplaySamps<T> {
+    fn calc(&self, t_arr: &[f64], res_arr: &mut [T]) {
+        for (t, res) in t_arr.iter().zip(res_arr.iter_mut()) {
+            *res = self.samp((t * self.samp_rate).round() as usize).clone()
+        }
+    }
+    fn calc_from_ticks(&self, start_pos: usize, _clk_period: f64, res_arr: &mut [T]) {
+        for (offs, res) in res_arr.iter_mut().enumerate() {
+            *res = self.samp(start_pos + offs).clone()
+        }
+    }
+}
+
+impl<T> Describe for ReplaySamps<T> {
+    fn describe(&self) -> String {
+        format!("ReplaySamps(n_samps={}, samp_rate={})", self.samps.len(), self.samp_rate)
+    }
+}
+
+/// Channel playing back recorded samples
+#[derive(Clone)]
+pub struct ReplayChan<T> {
+    name: String,
+    samp_rate: f64,
+    dflt_val: T,
+    samps: Arc<Vec<T>>,
+    instr_list: BTreeSet<Instr<T>>,
+    compile_cache_ends: Vec<usize>,
+    compile_cache_fns: Vec<Arc<dyn FnTraitSet<T>>>,
+    is_fresh_compiled: bool,
+    diagnostics: Diagnostics,
+}
+
+impl<T: Clone + Default + PartialEq + Debug + AsF64 + Send + Sync + 'static> ReplayChan<T> {
+    /// Channel replaying `samps` from `t = 0`, returns [`StreamerError::InvalidArgument`] if `samps` is empty
+    pub fn new(name: &str, samp_rate: f64, samps: Vec<T>) -> Result<Self, StreamerError> {
+        if samps.is_empty() {
+            return Err(StreamerError::InvalidArgument {
+                ctx: ErrCtx::chan(name.to_string()),
+                msg: "Replay channel needs at least one sample".to_string(),
+            })
+        }
+        let mut chan = Self {
+            name: name.to_string(),
+            samp_rate,
+            dflt_val: T::default(),
+            samps: Arc::new(samps),
+            instr_list: BTreeSet::new(),
+            compile_cache_ends: Vec::new(),
+            compile_cache_fns: Vec::new(),
+            is_fresh_compiled: true,
+            diagnostics: Diagnostics::new(),
+        };
+        chan.add_replay_instr()?;
+        Ok(chan)
+    }
+    pub fn samps(&self) -> &[T] {
+        &self.samps
+    }
+    /// Adds the instruction playing back the recording - e.g. after [`BaseChan::clear_edit_cache`]
+    pub fn add_replay_instr(&mut self) -> Result<(), StreamerError> {
+        let func = ReplaySamps::new(self.samps.clone(), self.samp_rate);
+        let dur = self.samps.len() as f64 / self.samp_rate;
+        self.add_instr_with_meta(Box::new(func), 0.0, Some((dur, false)), Some(InstrMeta::labeled("replay")))
+    }
+}
+
+impl<T: Clone + Default + PartialEq + Debug + AsF64 + Send + Sync + 'static> BaseChan for ReplayChan<T> {
+    type Samp = T;
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+    fn samp_rate(&self) -> f64 {
+        self.samp_rate
+    }
+    fn dflt_val(&self) -> T {
+        self.dflt_val.clone()
+    }
+    fn rst_val(&self) -> T {
+        self.dflt_val.clone()
+    }
+    fn instr_list(&self) -> &BTreeSet<Instr<T>> {
+        &self.instr_list
+    }
+    fn compile_cache_ends(&self) -> &Vec<usize> {
+        &self.compile_cache_ends
+    }
+    fn compile_cache_fns(&self) -> &Vec<Arc<dyn FnTraitSet<T>>> {
+        &self.compile_cache_fns
+    }
+    fn is_fresh_compiled(&self) -> bool {
+        self.is_fresh_compiled
+    }
+    fn instr_list_mut(&mut self) -> &mut BTreeSet<Instr<T>> {
+        &mut self.instr_list
+    }
+    fn compile_cache_ends_mut(&mut self) -> &mut Vec<usize> {
+        &mut self.compile_cache_ends
+    }
+    fn compile_cache_fns_mut(&mut self) -> &mut Vec<Arc<dyn FnTraitSet<T>>> {
+        &mut self.compile_cache_fns
+    }
+    fn is_fresh_compiled_mut(&mut self) -> &mut bool {
+        &mut self.is_fresh_compiled
+    }
+    fn diagnostics(&self) -> &Diagnostics {
+        &self.diagnostics
+    }
+    fn diagnostics_mut(&mut self) -> &mut Diagnostics {
+        &mut self.diagnostics
+    }
+}
+
+/// Device with [`ReplayChan`]s - see the [module docs](self)
+#[derive(Clone)]
+pub struct ReplayDev<T> {
+    name: String,
+    samp_rate: f64,
+    chans: IndexMap<String, ReplayChan<T>>,
+    diagnostics: Diagnostics,
+}
+
+impl<T: Clone + Default + PartialEq + Debug + AsF64 + Send + Sync + 'static> ReplayDev<T> {
+    pub fn new(name: &str, samp_rate: f64) -> Self {
+        Self { name: name.to_string(), samp_rate, chans: IndexMap::new(), diagnostics: Diagnostics::new() }
+    }
+
+    /// Adds channel `chan_name` replaying `samps`
+    pub fn add_chan(&mut self, chan_name: &str, samps: Vec<T>) -> Result<(), StreamerError> {
+        let chan = ReplayChan::new(chan_name, self.samp_rate, samps).map_err(|err| err.in_dev(self.name.clone()))?;
+        self.check_can_add_chan(&chan)?;
+        self.chans.insert(chan_name.to_string(), chan);
+        Ok(())
+    }
+
+    /// Device replaying the rows of the `.npy` file at `path` (see [`read_npy`]) on channels `chan_names`, one per row
+    pub fn load_npy(name: &str, samp_rate: f64, path: &str, chan_names: &[&str]) -> Result<Self, StreamerError>
+    where T: NpySamp
+    {
+        let rows = read_npy::<T>(path)?;
+        if rows.len() != chan_names.len() {
+            return Err(StreamerError::InvalidArgument {
+                ctx: ErrCtx::dev(name.to_string()),
+                msg: format!("{path} holds {} rows but {} channel names were given: {chan_names:?}", rows.len(), chan_names.len()),
+            })
+        }
+        let mut dev = Self::new(name, samp_rate);
+        for (chan_name, samps) in chan_names.iter().zip(rows) {
+            dev.add_chan(chan_name, samps)?;
+        }
+        Ok(dev)
+    }
+
+    /// Re-adds the replay instruction to every channel left without instructions, e.g. after [`BaseDev::clear_edit_cache`]
+    pub fn rearm(&mut self) -> Result<(), StreamerError> {
+        for chan in self.chans.values_mut().filter(|chan| !chan.got_instructions()) {
+            chan.add_replay_instr().map_err(|err| err.in_dev(self.name.clone()))?
+        }
+        Ok(())
+    }
+}
+
+impl<T: Clone + Default + PartialEq + Debug + AsF64 + Send + Sync + 'static> BaseDev for ReplayDev<T> {
+    type Chan = ReplayChan<T>;
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+    fn samp_rate(&self) -> f64 {
+        self.samp_rate
+    }
+    fn chans(&self) -> Vec<&ReplayChan<T>> {
+        self.chans.values().collect()
+    }
+    fn chans_mut(&mut self) -> Vec<&mut ReplayChan<T>> {
+        self.chans.values_mut().collect()
+    }
+    fn diagnostics(&self) -> &Diagnostics {
+        &self.diagnostics
+    }
+    fn diagnostics_mut(&mut self) -> &mut Diagnostics {
+        &mut self.diagnostics
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::channel::BaseChan;
+    use crate::device::BaseDev;
+    use crate::error::StreamerError;
+    use crate::fn_lib_tools::StdFnLib;
+    use crate::mock::test_impls::TestDev;
+    use crate::replay::*;
+
+    #[test]
+    fn round_trip() {
+        let dir = std::env::temp_dir().join(format!("base_streamer_replay_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("dev.npy");
+        let path = path.to_str().unwrap();
+
+        let mut dev = TestDev::new("AO", 1e3);
+        dev.add_chan("ao0", 0.0);
+        dev.add_chan("ao1", 0.0);
+        dev.add_chan("idle", 0.0);
+        dev.chan_mut("ao0").unwrap().add_instr(StdFnLib::new().Sine(1.0, 7.0, 0.0, 0.5).unwrap().inner, 0.1, Some((0.5, true))).unwrap();
+        dev.chan_mut("ao1").unwrap().constant(-2.0, 0.3, Some((0.1, false))).unwrap();
+        dev.compile(1.0).unwrap();
+        save_npy(&dev, path).unwrap();
+
+        assert!(matches!(ReplayDev::<f64>::load_npy("AO", 1e3, path, &["ao0"]), Err(StreamerError::InvalidArgument { .. })));
+        assert!(matches!(ReplayDev::<bool>::load_npy("DO", 1e3, path, &["a", "b"]), Err(StreamerError::InvalidArgument { .. })));
+        let mut replay = ReplayDev::<f64>::load_npy("AO", 1e3, path, &["ao0", "ao1"]).unwrap();
+        assert_eq!(replay.last_instr_end_time(), Some(1.0));
+        replay.compile(1.2).unwrap();
+
+        let mut expected = vec![0.0; 2000];
+        dev.calc_samps(&mut expected, 0, 1000).unwrap();
+        let mut samps = vec![0.0; 2400];
+        replay.calc_samps(&mut samps, 0, 1200).unwrap();
+        for row in 0..2 {
+            assert_eq!(samps[row * 1200..row * 1200 + 1000], expected[row * 1000..(row + 1) * 1000]);
+            // Default value past the end of the recording
+            assert!(samps[row * 1200 + 1000..(row + 1) * 1200].iter().all(|&samp| samp == 0.0));
+        }
+        // The chunked streaming path serves the same samples
+        let target = replay.run_mock(128).unwrap();
+        assert_eq!(target.chan_samps("ao0").unwrap(), samps[..1200]);
+
+        replay.clear_edit_cache();
+        assert!(!replay.got_instructions());
+        replay.rearm().unwrap();
+        assert_eq!(replay.last_instr_end_time(), Some(1.0));
+
+        // Single-channel boolean recording
+        write_npy(path, &[vec![false, true, true, false]]).unwrap();
+        assert_eq!(read_npy::<bool>(path).unwrap(), vec![vec![false, true, true, false]]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn npy_headers() {
+        let dir = std::env::temp_dir().join(format!("base_streamer_npy_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("arr.npy");
+        let path = path.to_str().unwrap();
+        let npy = |header: &str, data: &[f64]| {
+            let mut bytes = b"\x93NUMPY\x01\x00".to_vec();
+            bytes.extend((header.len() as u16).to_le_bytes());
+            bytes.extend(header.as_bytes());
+            bytes.extend(data.iter().flat_map(|val| val.to_le_bytes()));
+            std::fs::write(path, bytes).unwrap();
+        };
+
+        // 1-dimensional array as written by `np.save`
+        npy("{'descr': '<f8', 'fortran_order': False, 'shape': (3,), }\n", &[1.0, 2.0, 3.0]);
+        assert_eq!(read_npy::<f64>(path).unwrap(), vec![vec![1.0, 2.0, 3.0]]);
+        npy("{'descr': '<f8', 'fortran_order': True, 'shape': (2, 3), }\n", &[1.0, 4.0, 2.0, 5.0, 3.0, 6.0]);
+        assert_eq!(read_npy::<f64>(path).unwrap(), vec![vec![1.0, 2.0, 3.0], vec![4.0, 5.0, 6.0]]);
+        npy("{'descr': '<f8', 'fortran_order': False, 'shape': (2, 3), }\n", &[1.0, 2.0]);
+        assert!(matches!(read_npy::<f64>(path), Err(StreamerError::InvalidArgument { .. })));
+        npy("{'descr': '<f4', 'fortran_order': False, 'shape': (1,), }\n", &[1.0]);
+        assert!(read_npy::<f64>(path).unwrap_err().to_string().contains("expected dtype '<f8', got '<f4'"));
+        assert!(matches!(read_npy::<f64>(dir.join("nope.npy").to_str().unwrap()), Err(StreamerError::NotFound { .. })));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,157 @@
+//! Conversion of compiled waveforms between sample rates.
+//!
+//! Devices of one streamer often run at different sample rates. To compare their channels on a common time grid
+//! (e.g. for a preview plot), [`BaseStreamer::resample_all`] converts every active channel to one rate.
+//! [`BaseChan::resample`] converts a single channel - e.g. to move a sequence to a card with a lower maximal rate,
+//! where the result can be played back by a [`ReplayChan`].
+//!
+//! Output sample `i` sits at `t = i / to_rate` and the output covers the same duration as the input. Methods:
+//! - [`Resampling::Hold`] - value of the last input sample at or before `t`, exact for digital and piecewise-constant channels;
+//! - [`Resampling::Linear`] - linear interpolation between the two neighbouring input samples;
+//! - [`Resampling::Sinc`] - Lanczos-windowed sinc interpolation. When downsampling, the kernel is widened to
+//!   low-pass at the new Nyquist frequency, so that content above it is removed instead of aliased.
+//!
+//! Input samples beyond the ends are taken equal to the first / last sample.
+//!
+//! [`BaseStreamer::resample_all`]: crate::streamer::BaseStreamer::resample_all
+//! [`BaseChan::resample`]: crate::channel::BaseChan::resample
+//! [`ReplayChan`]: crate::replay::ReplayChan
+
+use std::f64::consts::PI;
+use crate::error::{ErrCtx, StreamerError};
+
+/// Relative tolerance for input positions landing on an input tick, absorbs rounding in `t * from_rate`
+const POS_TOL: f64 = 1e-9;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Resampling {
+    Hold,
+    Linear,
+    /// Kernel reaches `half_width` input samples (output samples when downsampling) to each side
+    Sinc { half_width: usize },
+}
+
+/// Number of output samples covering the duration of `n_samps` input samples
+pub fn resampled_len(n_samps: usize, from_rate: f64, to_rate: f64) -> usize {
+    (n_samps as f64 * to_rate / from_rate * (1.0 + POS_TOL)).floor() as usize
+}
+
+/// `samps` taken at `from_rate` converted to `to_rate` with `method` - see the [module docs](self).
+///
+/// Returns [`StreamerError::InvalidArgument`] for non-positive or non-finite rates and for a zero sinc half-width.
+pub fn resample(samps: &[f64], from_rate: f64, to_rate: f64, method: Resampling) -> Result<Vec<f64>, StreamerError> {
+    for rate in [from_rate, to_rate] {
+        if !(rate.is_finite() && rate > 0.0) {
+            return Err(StreamerError::InvalidArgument { ctx: ErrCtx::none(), msg: format!("resample(): invalid sample rate {rate} Hz") })
+        }
+    }
+    if method == (Resampling::Sinc { half_width: 0 }) {
+        return Err(StreamerError::InvalidArgument { ctx: ErrCtx::none(), msg: "resample(): sinc half_width must be positive".to_string() })
+    }
+    if samps.is_empty() {
+        return Ok(Vec::new())
+    }
+    let ratio = from_rate / to_rate;
+    let last = samps.len() - 1;
+    let samp = |idx: isize| samps[idx.clamp(0, last as isize) as usize];
+    let res = (0..resampled_len(samps.len(), from_rate, to_rate)).map(|out_idx| {
+        let pos = out_idx as f64 * ratio;
+        match method {
+            Resampling::Hold => samp((pos + POS_TOL * pos.max(1.0)).floor() as isize),
+            Resampling::Linear => {
+                let idx = pos.floor();
+                let frac = pos - idx;
+                let (prev, next) = (samp(idx as isize), samp(idx as isize + 1));
+                prev + (next - prev) * frac
+            },
+            Resampling::Sinc { half_width } => {
+                // Cutoff relative to the input Nyquist frequency
+                let cutoff = f64::min(1.0, 1.0 / ratio);
+                let reach = half_width as f64 / cutoff;
+                let (mut acc, mut weight_sum) = (0.0, 0.0);
+                for idx in (pos - reach).ceil() as isize..=(pos + reach).floor() as isize {
+                    let x = (pos - idx as f64) * cutoff;
+                    let weight = sinc(x) * sinc(x / half_width as f64);
+                    acc += weight * samp(idx);
+                    weight_sum += weight;
+                }
+                // Normalized so that constant signals pass unchanged
+                acc / weight_sum
+            },
+        }
+    }).collect();
+    Ok(res)
+}
+
+fn sinc(x: f64) -> f64 {
+    match x == 0.0 {
+        true => 1.0,
+        false => (PI * x).sin() / (PI * x),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::channel::BaseChan;
+    use crate::device::BaseDev;
+    use crate::error::StreamerError;
+    use crate::fn_lib_tools::StdFnLib;
+    use crate::mock::test_impls::TestStreamer;
+    use crate::resample::*;
+    use crate::streamer::BaseStreamer;
+
+    #[test]
+    fn methods() {
+        let samps = [0.0, 1.0, 2.0, 3.0];
+        assert_eq!(resample(&samps, 2.0, 4.0, Resampling::Hold).unwrap(), [0.0, 0.0, 1.0, 1.0, 2.0, 2.0, 3.0, 3.0]);
+        assert_eq!(resample(&samps, 2.0, 4.0, Resampling::Linear).unwrap(), [0.0, 0.5, 1.0, 1.5, 2.0, 2.5, 3.0, 3.0]);
+        assert_eq!(resample(&samps, 4.0, 2.0, Resampling::Hold).unwrap(), [0.0, 2.0]);
+        // Rates which are not exact binary fractions still hit the input ticks
+        assert_eq!(resample(&[0.0, 1.0, 2.0], 0.3, 0.1, Resampling::Hold).unwrap(), [0.0]);
+        assert_eq!(resample(&[0.0; 10], 1e3, 1e3 / 3.0, Resampling::Hold).unwrap().len(), 3);
+
+        // Sinc passes constants and reproduces the input on the input grid
+        let sinc = Resampling::Sinc { half_width: 4 };
+        assert!(resample(&[2.0; 50], 1e3, 7e3, sinc).unwrap().iter().all(|samp| (samp - 2.0).abs() < 1e-12));
+        let sine: Vec<f64> = (0..200).map(|idx| (2.0 * std::f64::consts::PI * 0.05 * idx as f64).sin()).collect();
+        let up = resample(&sine, 1.0, 3.0, sinc).unwrap();
+        assert!(sine.iter().zip(up.iter().step_by(3)).all(|(samp, up_samp)| (samp - up_samp).abs() < 1e-12));
+        // In the middle, the interpolated points follow the sine closely
+        let max_err = (150..450).map(|idx| (up[idx] - (2.0 * std::f64::consts::PI * 0.05 * idx as f64 / 3.0).sin()).abs()).fold(0.0, f64::max);
+        assert!(max_err < 1e-2, "{max_err}");
+        // Content above the new Nyquist frequency is filtered out on downsampling
+        let alternating: Vec<f64> = (0..200).map(|idx| if idx % 2 == 0 { 1.0 } else { -1.0 }).collect();
+        let down = resample(&alternating, 1.0, 0.25, sinc).unwrap();
+        assert!(down[10..40].iter().all(|samp| samp.abs() < 0.05));
+
+        assert!(matches!(resample(&samps, 0.0, 1.0, Resampling::Hold), Err(StreamerError::InvalidArgument { .. })));
+        assert!(matches!(resample(&samps, 1.0, 1.0, Resampling::Sinc { half_width: 0 }), Err(StreamerError::InvalidArgument { .. })));
+    }
+
+    #[test]
+    fn common_grid() {
+        let mut streamer = TestStreamer::new();
+        streamer.add_ao_dev("AO", 1e3);
+        streamer.add_do_dev("DO", 1e4);
+        streamer.ao_devs["AO"].add_chan("ao0", 0.0);
+        streamer.ao_devs["AO"].add_chan("idle", 0.0);
+        streamer.do_devs["DO"].add_chan("port0/line0", false);
+        streamer.ao_devs["AO"].chan_mut("ao0").unwrap().add_instr(StdFnLib::new().LinFn(10.0, 0.0).unwrap().inner, 0.0, Some((0.1, false))).unwrap();
+        streamer.do_devs["DO"].chan_mut("port0/line0").unwrap().constant(true, 0.0105, Some((0.002, false))).unwrap();
+        assert!(matches!(streamer.resample_all(2e3, Resampling::Hold), Err(StreamerError::NotCompiled { .. })));
+        streamer.compile(Some(0.1)).unwrap();
+
+        let grid = streamer.resample_all(2e3, Resampling::Hold).unwrap();
+        assert_eq!(grid.keys().collect::<Vec<_>>(), ["AO/ao0", "DO/port0/line0"]);
+        // The instruction on AO ends at the stop time, so AO gets a closing tick - 101 ticks (202 samples at 2 kHz) vs. 1000 on DO
+        assert!(grid.values().all(|samps| samps.len() == 200));
+        assert_eq!(grid["AO/ao0"][..4], [0.0, 0.0, 0.01, 0.01]);
+        // The 2 ms pulse starting at 10.5 ms covers output ticks 21..25
+        let high: Vec<usize> = (0..200).filter(|&idx| grid["DO/port0/line0"][idx] == 1.0).collect();
+        assert_eq!(high, [21, 22, 23, 24]);
+
+        let linear = streamer.resample_chan("AO", "ao0", 2e3, Resampling::Linear).unwrap();
+        assert!((linear[3] - 0.015).abs() < 1e-12);
+        assert!(matches!(streamer.resample_chan("AO", "nope", 2e3, Resampling::Hold), Err(StreamerError::NotFound { .. })));
+    }
+}
@@ -0,0 +1,189 @@
+//! Time to clock tick conversion policies.
+//!
+//! Instruction times are given in seconds and converted to sample clock ticks by [`BaseChan::add_instr`],
+//! [`BaseDev::add_reset_instr`], [`BaseDev::compile`], and the editing operations such as [`BaseChan::shift`]
+//! (which use the signed [`TickRounding::to_ticks`]). By default they are rounded to the nearest tick, which
+//! silently moves any time that is off the clock grid by up to half a period. [`TickRounding::Exact`] turns such
+//! times into errors instead - what production sequences want, so that e.g. a camera trigger can't drift unnoticed.
+//!
+//! Conversions are checked: non-finite times, times far before zero, and times beyond [`MAX_POS`] ticks are errors
+//! rather than silently saturated positions.
+//!
+//! The policy is set per device with [`BaseDev::set_tick_rounding`] (applied to the device and all its channels)
+//! or per channel with [`BaseChan::set_tick_rounding`].
+//!
+//! [`BaseChan::add_instr`]: crate::channel::BaseChan::add_instr
+//! [`BaseChan::set_tick_rounding`]: crate::channel::BaseChan::set_tick_rounding
+//! [`BaseChan::shift`]: crate::channel::BaseChan::shift
+//! [`BaseDev::add_reset_instr`]: crate::device::BaseDev::add_reset_instr
+//! [`BaseDev::compile`]: crate::device::BaseDev::compile
+//! [`BaseDev::set_tick_rounding`]: crate::device::BaseDev::set_tick_rounding
+
+use std::fmt;
+use std::fmt::Display;
+use crate::error::{ErrCtx, StreamerError};
+
+/// Times within this many clock periods of a tick count as being on it - float noise
+/// (e.g. `0.0103 * 1e4 = 102.99999999999999`) must not make `Floor` or `Exact` misbehave
+const TICK_TOL: f64 = 1e-6;
+
+/// Largest clock tick a time may convert to. Beyond `2^53`, `f64` can't represent every tick any more
+/// (and no realistic sequence gets anywhere close - it is 2.8 years at 100 MHz).
+pub const MAX_POS: usize = 1 << 53;
+
+/// Reason a time can't be converted to a clock tick
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PosError {
+    NonFinite,
+    /// More than half a clock period before zero
+    Negative,
+    /// Beyond [`MAX_POS`]
+    TooLarge,
+    /// Off the clock grid with [`TickRounding::Exact`]
+    OffGrid,
+}
+
+impl PosError {
+    /// [`StreamerError`] for time `t` failing conversion under `rounding`
+    pub(crate) fn to_streamer_err(self, ctx: ErrCtx, t: f64, samp_rate: f64, rounding: TickRounding) -> StreamerError {
+        let ticks = t * samp_rate;
+        match self {
+            Self::NonFinite => StreamerError::NonFinite { ctx, msg: format!("time {t} s can't be converted to clock ticks") },
+            Self::Negative => StreamerError::OutOfRange { ctx, msg: format!("time {t} s = {ticks} clock periods is negative") },
+            Self::TooLarge => StreamerError::OutOfRange {
+                ctx,
+                msg: format!("time {t} s = {ticks} clock periods is beyond the largest supported position {MAX_POS}"),
+            },
+            Self::OffGrid => StreamerError::InvalidArgument {
+                ctx,
+                msg: format!("time {t} s = {ticks} clock periods is not on the clock grid (tick rounding policy: {rounding})"),
+            },
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TickRounding {
+    /// Nearest tick
+    #[default]
+    Round,
+    /// Last tick at or before the time
+    Floor,
+    /// First tick at or after the time
+    Ceil,
+    /// Times off the clock grid are rejected
+    Exact,
+}
+
+impl TickRounding {
+    /// Clock tick of time `t` (in seconds)
+    pub fn to_pos(&self, t: f64, samp_rate: f64) -> Result<usize, PosError> {
+        let ticks = t * samp_rate;
+        if !ticks.is_finite() {
+            return Err(PosError::NonFinite)
+        }
+        if ticks < -0.5 {
+            return Err(PosError::Negative)
+        }
+        let pos = self.round_ticks(ticks)?;
+        if pos > MAX_POS as f64 {
+            return Err(PosError::TooLarge)
+        }
+        Ok(pos.max(0.0) as usize)
+    }
+    /// Signed number of clock ticks of the time difference `dt` (in seconds), e.g. of a shift
+    pub fn to_ticks(&self, dt: f64, samp_rate: f64) -> Result<i64, PosError> {
+        let ticks = dt * samp_rate;
+        if !ticks.is_finite() {
+            return Err(PosError::NonFinite)
+        }
+        let ticks = self.round_ticks(ticks)?;
+        if ticks.abs() > MAX_POS as f64 {
+            return Err(PosError::TooLarge)
+        }
+        Ok(ticks as i64)
+    }
+    /// Whole number of ticks for the finite `ticks`
+    fn round_ticks(&self, ticks: f64) -> Result<f64, PosError> {
+        let nearest = ticks.round();
+        if (ticks - nearest).abs() <= TICK_TOL {
+            return Ok(nearest)
+        }
+        match self {
+            Self::Round => Ok(nearest),
+            Self::Floor => Ok(ticks.floor()),
+            Self::Ceil => Ok(ticks.ceil()),
+            Self::Exact => Err(PosError::OffGrid),
+        }
+    }
+}
+
+impl Display for TickRounding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Round => "round",
+            Self::Floor => "floor",
+            Self::Ceil => "ceil",
+            Self::Exact => "exact",
+        };
+        write!(f, "{name}")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::channel::BaseChan;
+    use crate::device::BaseDev;
+    use crate::error::StreamerError;
+    use crate::mock::test_impls::TestDev;
+
+    #[test]
+    fn to_pos() {
+        assert_eq!(TickRounding::Round.to_pos(0.0106, 1e3), Ok(11));
+        assert_eq!(TickRounding::Floor.to_pos(0.0106, 1e3), Ok(10));
+        assert_eq!(TickRounding::Ceil.to_pos(0.0104, 1e3), Ok(11));
+        assert_eq!(TickRounding::Exact.to_pos(0.0104, 1e3), Err(PosError::OffGrid));
+        // Float noise is not a sub-tick residual
+        assert_eq!(TickRounding::Floor.to_pos(0.0103, 1e4), Ok(103));
+        assert_eq!(TickRounding::Exact.to_pos(0.0103, 1e4), Ok(103));
+        // Pathological inputs
+        assert_eq!(TickRounding::Round.to_pos(f64::NAN, 1e3), Err(PosError::NonFinite));
+        assert_eq!(TickRounding::Round.to_pos(1e300, 1e9), Err(PosError::NonFinite));
+        assert_eq!(TickRounding::Round.to_pos(1e10, 1e9), Err(PosError::TooLarge));
+        assert_eq!(TickRounding::Round.to_pos(-0.01, 1e3), Err(PosError::Negative));
+        assert_eq!(TickRounding::Round.to_pos(-1e-12, 1e3), Ok(0));
+        // Signed tick counts
+        assert_eq!(TickRounding::Floor.to_ticks(-0.0106, 1e3), Ok(-11));
+        assert_eq!(TickRounding::Exact.to_ticks(-0.0104, 1e3), Err(PosError::OffGrid));
+        assert_eq!(TickRounding::Round.to_ticks(f64::NAN, 1e3), Err(PosError::NonFinite));
+    }
+
+    #[test]
+    fn exact_dev() {
+        let mut dev = TestDev::new("Dev1", 1e3);
+        dev.add_chan("cam_trig", 0.0);
+        dev.set_tick_rounding(TickRounding::Exact).unwrap();
+        let chan = dev.chan_mut("cam_trig").unwrap();
+        assert!(matches!(chan.constant(1.0, 0.0105, Some((0.001, false))), Err(StreamerError::InvalidArgument { .. })));
+        assert!(matches!(chan.constant(1.0, 0.01, Some((0.0015, false))), Err(StreamerError::InvalidArgument { .. })));
+        chan.constant(1.0, 0.01, Some((0.001, false))).unwrap();
+        assert!(matches!(dev.add_reset_instr(0.0505), Err(StreamerError::InvalidArgument { .. })));
+        assert!(matches!(dev.compile(0.0205), Err(StreamerError::InvalidArgument { .. })));
+        dev.compile(0.02).unwrap();
+
+        // A single channel can use its own policy
+        let chan = dev.chan_mut("cam_trig").unwrap();
+        chan.set_tick_rounding(TickRounding::Floor).unwrap();
+        chan.constant(1.0, 0.0309, None).unwrap();
+        assert_eq!(chan.instr_list().last().unwrap().start_pos(), 30);
+
+        // Shifts are converted with the same policy
+        assert!(matches!(dev.shift(0.0105), Err(StreamerError::InvalidArgument { .. })));
+        assert!(matches!(dev.shift(f64::NAN), Err(StreamerError::NonFinite { .. })));
+        let chan = dev.chan_mut("cam_trig").unwrap();
+        assert!(matches!(chan.shift(f64::NAN), Err(StreamerError::NonFinite { .. })));
+        chan.shift(0.0109).unwrap();
+        assert_eq!(chan.instr_list().last().unwrap().start_pos(), 40);
+    }
+}
@@ -0,0 +1,295 @@
+//! Site-specific validation rules run on every compile.
+//!
+//! Labs encode their interlocks and sanity checks ("shutter X must be open whenever AOM Y is on") as [`ValidationRule`]s
+//! and register them on the streamer with [`BaseStreamer::add_rule`]. After every successful [`BaseStreamer::compile_with`],
+//! each rule checks every active channel ([`ValidationRule::validate`]) and the whole streamer at once
+//! ([`ValidationRule::validate_global`]) and returns its findings as [`Diagnostic`]s of kind [`DiagnosticKind::Rule`].
+//!
+//! Findings are stored in the diagnostics sink of the device they refer to (see [`crate::diagnostics`]).
+//! Any [`Severity::Error`] finding fails the compile with [`StreamerError::RuleViolation`],
+//! in strict mode ([`CompileOptions::strict`]) so does any [`Severity::Warning`] finding.
+//!
+//! Rules see the compiled channels through read-only [`ChanView`]s. Python rules are plain callables wrapped in [`PyRule`].
+//!
+//! [`BaseStreamer::add_rule`]: crate::streamer::BaseStreamer::add_rule
+//! [`BaseStreamer::compile_with`]: crate::streamer::BaseStreamer::compile_with
+//! [`StreamerError::RuleViolation`]: crate::error::StreamerError::RuleViolation
+//! [`CompileOptions::strict`]: crate::options::CompileOptions::strict
+
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use crate::diagnostics::{Diagnostic, DiagnosticKind, DiagnosticStage, Severity};
+use crate::diff::InstrSnapshot;
+use crate::error::{ErrCtx, StreamerError};
+use crate::marker::MarkerRule;
+use crate::streamer::TagBaseDev;
+
+/// Compile cache segment `[start_pos, end_pos)`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Segment {
+    pub start_pos: usize,
+    pub end_pos: usize,
+    /// Value converted to `f64` if the segment function is a constant
+    pub const_val: Option<f64>,
+}
+
+/// Read-only view of a compiled active channel
+pub struct ChanView<'a> {
+    dev: &'a dyn TagBaseDev,
+    chan: String,
+    instrs: Vec<InstrSnapshot>,
+}
+
+impl<'a> ChanView<'a> {
+    pub fn dev_name(&self) -> String {
+        self.dev.tag_name()
+    }
+    pub fn chan_name(&self) -> &str {
+        &self.chan
+    }
+    pub fn samp_rate(&self) -> f64 {
+        self.dev.tag_samp_rate()
+    }
+    /// Edit-cache instructions of the base layer
+    pub fn instrs(&self) -> &[InstrSnapshot] {
+        &self.instrs
+    }
+    pub fn segments(&self) -> Result<Vec<Segment>, StreamerError> {
+        self.dev.tag_compiled_segments(&self.chan)
+    }
+    pub fn compiled_stop_pos(&self) -> Result<usize, StreamerError> {
+        Ok(self.segments()?.last().map_or(0, |seg| seg.end_pos))
+    }
+    /// Compiled samples of the ticks `[start_pos, end_pos)` converted to `f64`
+    pub fn samps(&self, start_pos: usize, end_pos: usize) -> Result<Vec<f64>, StreamerError> {
+        self.dev.tag_calc_chan_samps(&self.chan, start_pos, end_pos)
+    }
+    /// `[start, end)` time intervals (in seconds) where `|value| > threshold`, see [`MarkerRule::Threshold`]
+    pub fn high_intervals(&self, threshold: f64) -> Result<Vec<(f64, f64)>, StreamerError> {
+        self.dev.tag_marker_intervals(&self.chan, &MarkerRule::Threshold(threshold))
+    }
+    /// Rule finding located at this channel (and clock grid position `pos`)
+    pub fn diagnostic(&self, severity: Severity, pos: Option<usize>, message: String) -> Diagnostic {
+        Diagnostic {
+            kind: DiagnosticKind::Rule,
+            stage: DiagnosticStage::Compile,
+            severity,
+            dev: Some(self.dev_name()),
+            chan: Some(self.chan.clone()),
+            pos,
+            message,
+        }
+    }
+}
+
+/// Read-only view of all compiled active channels of a streamer
+pub struct StreamerView<'a> {
+    chans: Vec<ChanView<'a>>,
+}
+
+impl<'a> StreamerView<'a> {
+    pub fn new(devs: Vec<&'a dyn TagBaseDev>) -> Self {
+        let mut chans = Vec::new();
+        for dev in devs {
+            for (chan, instrs) in dev.tag_instr_snapshots() {
+                if !instrs.is_empty() {
+                    chans.push(ChanView { dev, chan, instrs })
+                }
+            }
+        }
+        Self { chans }
+    }
+    pub fn chans(&self) -> &[ChanView<'a>] {
+        &self.chans
+    }
+    /// `None` if the channel doesn't exist or is inactive
+    pub fn chan(&self, dev_name: &str, chan_name: &str) -> Option<&ChanView<'a>> {
+        self.chans.iter().find(|chan| chan.chan == chan_name && chan.dev.tag_name() == dev_name)
+    }
+}
+
+pub trait ValidationRule: Send + Sync {
+    /// Prefixed to the messages of the findings
+    fn name(&self) -> String;
+    /// Checks a single channel. The default finds nothing.
+    fn validate(&self, _chan: &ChanView) -> Vec<Diagnostic> {
+        Vec::new()
+    }
+    /// Checks relations between channels. The default finds nothing.
+    fn validate_global(&self, _view: &StreamerView) -> Vec<Diagnostic> {
+        Vec::new()
+    }
+}
+
+/// Runs all `rules` on `view`. Finding messages are prefixed with the rule name.
+pub fn run_rules(rules: &[Box<dyn ValidationRule>], view: &StreamerView) -> Vec<Diagnostic> {
+    let mut entries = Vec::new();
+    for rule in rules {
+        let mut found: Vec<Diagnostic> = view.chans().iter().flat_map(|chan| rule.validate(chan)).collect();
+        found.extend(rule.validate_global(view));
+        for entry in found.iter_mut() {
+            entry.message = format!("[{}] {}", rule.name(), entry.message);
+        }
+        entries.extend(found)
+    }
+    entries
+}
+
+/// Per-channel rule implemented by a Python callable.
+///
+/// The callable receives a dict with `dev`, `chan`, `samp_rate`, `instrs` (list of `(start_time, end_time or None, func)`),
+/// and `segments` (list of `(start_time, end_time, value or None)` for the compile cache) and returns a list of findings -
+/// messages or `(time, message)` pairs. An exception raised by the callable is reported as a finding itself.
+pub struct PyRule {
+    pub name: String,
+    pub func: PyObject,
+    pub severity: Severity,
+}
+
+impl PyRule {
+    pub fn new(name: &str, func: PyObject, severity: Severity) -> Self {
+        Self { name: name.to_string(), func, severity }
+    }
+
+    fn call<'py>(&self, py: Python<'py>, chan: &ChanView) -> PyResult<Vec<(Option<f64>, String)>> {
+        let clk_period = 1.0 / chan.samp_rate();
+        let to_time = |pos: usize| pos as f64 * clk_period;
+        let arg = PyDict::new_bound(py);
+        arg.set_item("dev", chan.dev_name())?;
+        arg.set_item("chan", chan.chan_name())?;
+        arg.set_item("samp_rate", chan.samp_rate())?;
+        let instrs: Vec<(f64, Option<f64>, String)> = chan.instrs().iter()
+            .map(|instr| (to_time(instr.start_pos), instr.end_spec.map(|(end_pos, _keep_val)| to_time(end_pos)), instr.func.clone()))
+            .collect();
+        arg.set_item("instrs", instrs)?;
+        let segments: Vec<(f64, f64, Option<f64>)> = chan.segments()?.iter()
+            .map(|seg| (to_time(seg.start_pos), to_time(seg.end_pos), seg.const_val))
+            .collect();
+        arg.set_item("segments", segments)?;
+
+        let mut findings = Vec::new();
+        for item in self.func.call1(py, (arg,))?.bind(py).iter()? {
+            let item = item?;
+            findings.push(match item.extract::<String>() {
+                Ok(msg) => (None, msg),
+                Err(_) => {
+                    let (t, msg): (f64, String) = item.extract()?;
+                    (Some(t), msg)
+                },
+            })
+        }
+        Ok(findings)
+    }
+}
+
+impl ValidationRule for PyRule {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+    fn validate(&self, chan: &ChanView) -> Vec<Diagnostic> {
+        Python::with_gil(|py| match self.call(py, chan) {
+            Ok(findings) => findings.into_iter()
+                .map(|(t, msg)| chan.diagnostic(self.severity, t.map(|t| (t * chan.samp_rate()).round() as usize), msg))
+                .collect(),
+            Err(err) => vec![chan.diagnostic(Severity::Error, None, format!("rule raised {err}"))],
+        })
+    }
+}
+
+/// Error for the first `Error`-severity finding - or, if `strict`, the first `Warning`-severity one
+pub(crate) fn findings_err(entries: &[Diagnostic], strict: bool) -> Option<StreamerError> {
+    let ctx = |entry: &Diagnostic| ErrCtx { dev: entry.dev.clone(), chan: entry.chan.clone() };
+    if let Some(entry) = entries.iter().find(|entry| entry.severity == Severity::Error) {
+        let n_errors = entries.iter().filter(|entry| entry.severity == Severity::Error).count();
+        return Some(StreamerError::RuleViolation {
+            ctx: ctx(entry),
+            msg: format!("{n_errors} validation rule violation(s), the first one: {entry}"),
+        })
+    }
+    if strict {
+        if let Some(entry) = entries.iter().find(|entry| entry.severity == Severity::Warning) {
+            return Some(StreamerError::StrictViolation {
+                ctx: ctx(entry),
+                msg: format!("Strict compile mode does not allow warnings, got: {entry}"),
+            })
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::channel::BaseChan;
+    use crate::device::BaseDev;
+    use crate::fn_lib_tools::StdFnLib;
+    use crate::mock::test_impls::TestStreamer;
+    use crate::streamer::BaseStreamer;
+
+    /// Flags every sample above `max`
+    struct MaxVal(f64);
+    impl ValidationRule for MaxVal {
+        fn name(&self) -> String {
+            "max_val".to_string()
+        }
+        fn validate(&self, chan: &ChanView) -> Vec<Diagnostic> {
+            let samps = chan.samps(0, chan.compiled_stop_pos().unwrap()).unwrap();
+            match samps.iter().position(|&samp| samp > self.0) {
+                Some(pos) => vec![chan.diagnostic(Severity::Error, Some(pos), format!("exceeds {}", self.0))],
+                None => Vec::new(),
+            }
+        }
+    }
+
+    /// Requires channel `AO/ao1` to have instructions whenever `AO/ao0` has
+    struct Requires;
+    impl ValidationRule for Requires {
+        fn name(&self) -> String {
+            "requires".to_string()
+        }
+        fn validate_global(&self, view: &StreamerView) -> Vec<Diagnostic> {
+            match (view.chan("AO", "ao0"), view.chan("AO", "ao1")) {
+                (Some(ao0), None) => vec![ao0.diagnostic(Severity::Warning, None, "ao1 is idle".to_string())],
+                _ => Vec::new(),
+            }
+        }
+    }
+
+    #[test]
+    fn rules() {
+        let mut streamer = TestStreamer::new();
+        streamer.add_ao_dev("AO", 1e3);
+        streamer.ao_devs["AO"].add_chan("ao0", 0.0);
+        streamer.ao_devs["AO"].add_chan("ao1", 0.0);
+        let sine = StdFnLib::new().Sine(1.5, 1.0, 0.0, 0.0).unwrap().inner;
+        streamer.ao_devs["AO"].chan_mut("ao0").unwrap().add_instr(sine, 0.0, Some((1.0, false))).unwrap();
+        streamer.add_rule(Box::new(Requires)).unwrap();
+        streamer.compile(Some(1.0)).unwrap();
+        let findings = streamer.check_rules().unwrap();
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].message, "[requires] ao1 is idle");
+        // Findings are recorded with the device and replaced on recompile
+        streamer.compile(Some(1.0)).unwrap();
+        assert_eq!(streamer.diagnostics().iter().filter(|entry| entry.kind == DiagnosticKind::Rule).count(), 1);
+
+        streamer.add_rule(Box::new(MaxVal(1.0))).unwrap();
+        let err = streamer.compile(Some(1.0)).unwrap_err();
+        assert!(matches!(err, StreamerError::RuleViolation { .. }));
+        assert_eq!((err.ctx().dev.as_deref(), err.ctx().chan.as_deref()), (Some("AO"), Some("ao0")));
+        // The rejected compile cache is dropped - recompile the device alone to inspect the findings
+        assert!(streamer.run_mock(100).is_err());
+        streamer.ao_devs["AO"].compile(1.0).unwrap();
+        let findings = streamer.check_rules().unwrap();
+        assert_eq!(findings.len(), 2);
+        // 1.5 sin(2pi t) first exceeds 1 at t = 0.116 s
+        assert_eq!((findings[1].severity, findings[1].pos), (Severity::Error, Some(117)));
+
+        streamer.ao_devs["AO"].chan_mut("ao1").unwrap().constant(1.0, 0.0, None).unwrap();
+        streamer.rules.pop();
+        streamer.compile(Some(1.0)).unwrap();
+        assert!(streamer.check_rules().unwrap().is_empty());
+        let segments = StreamerView::new(streamer.active_devs()).chan("AO", "ao1").unwrap().segments().unwrap();
+        // Including the closing edge tick added by the device
+        assert_eq!(segments, vec![Segment { start_pos: 0, end_pos: 1001, const_val: Some(1.0) }]);
+    }
+}
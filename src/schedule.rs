@@ -0,0 +1,365 @@
+//! Pulse schedules loaded from text files.
+//!
+//! # CSV
+//!
+//! A CSV (or TSV) schedule has one instruction per row:
+//!
+//! ```text
+//! # device, channel, t, duration, keep_val, function, params...
+//! ao,       ao0,     0.0, 1e-3,   true,     Sine,     1.0, 1e3
+//! ao,       ao1,     0.0, ,       ,         LinFn,    slope=2.0, offs=0.5
+//! do,       do0,     1e-3, 2e-3,  false,    ConstBool, 1
+//! ```
+//!
+//! - fields are separated by tabs if the line contains a tab and by commas otherwise; surrounding whitespace is ignored;
+//! - blank lines and lines starting with `#` are skipped, as is a header row whose first field is `device`;
+//! - an empty `duration` makes a "go-this" instruction lasting until the next one (`keep_val` is then ignored);
+//! - `keep_val` is `true`/`false`/`1`/`0`, empty means `false`;
+//! - the function parameters are either all positional numbers or all `name=value` pairs.
+//!   `true`/`false` are accepted as `1`/`0`.
+//!
+//! # JSON
+//!
+//! The JSON schema ([`ScheduleDoc`]) describes the same edit-level content grouped by device and channel.
+//! It is meant for hand-editing and for external editors, as opposed to a full dump of the streamer state:
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "devices": [
+//!     {"name": "ao", "samp_rate": 1e6, "channels": [
+//!       {"name": "ao0", "instructions": [
+//!         {"t": 0.0, "dur": 1e-3, "keep_val": true, "func": "Sine", "args": {"amp": 1.0, "freq": 1e3}},
+//!         {"t": 2e-3, "func": "ConstF64", "args": {"val": 0.5}}
+//!       ]}
+//!     ]}
+//!   ]
+//! }
+//! ```
+//!
+//! - `dur` missing or `null` makes a "go-this" instruction, `keep_val` defaults to `false`;
+//! - `args` maps parameter names to numbers, parameters with defaults may be omitted;
+//! - `samp_rate` is optional on import - if given, it must match the device;
+//! - documents with a `version` newer than [`SCHEDULE_JSON_VERSION`] are rejected.
+//!
+//! Export writes every instruction as `func` + `args` parsed back from its `describe()` output, so only functions
+//! with scalar parameters (like those of the standard registry) can be exported.
+//!
+//! Functions are constructed by name through a [`FnRegistry`] - [`FnRegistry::std`] unless the caller provides one.
+//! Loading goes through [`BaseStreamer::load_schedule_csv`] and [`BaseStreamer::import_schedule_json`].
+//!
+//! [`FnRegistry`]: crate::fn_lib_tools::FnRegistry
+//! [`FnRegistry::std`]: crate::fn_lib_tools::FnRegistry::std
+//! [`BaseStreamer::load_schedule_csv`]: crate::streamer::BaseStreamer::load_schedule_csv
+//! [`BaseStreamer::import_schedule_json`]: crate::streamer::BaseStreamer::import_schedule_json
+
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use crate::error::{ErrCtx, StreamerError};
+use crate::fn_lib_tools::FnArgs;
+use crate::diff::InstrSnapshot;
+use crate::instruction::InstrMeta;
+use crate::rounding::TickRounding;
+
+/// One parsed schedule row
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScheduleRow {
+    /// Location in the source (e.g. `line 4`) - for error messages
+    pub loc: String,
+    pub dev: String,
+    pub chan: String,
+    pub t: f64,
+    /// `(duration, keep_val)`, `None` for a "go-this" instruction
+    pub dur_spec: Option<(f64, bool)>,
+    pub func: String,
+    pub args: FnArgs,
+}
+
+impl ScheduleRow {
+    /// Instruction this row would add to a channel running at `samp_rate` with the tick rounding policy `rounding`,
+    /// labeled with the row location. Conversion to the clock grid follows `add_instr()`.
+    pub fn snapshot(&self, samp_rate: f64, rounding: TickRounding) -> Result<InstrSnapshot, StreamerError> {
+        let to_pos = |t: f64| rounding.to_pos(t, samp_rate).map_err(|err| {
+            err.to_streamer_err(ErrCtx::chan(self.chan.clone()), t, samp_rate, rounding)
+                .in_dev(self.dev.clone())
+                .prefixed(&format!("Schedule {}", self.loc))
+        });
+        let start_pos = to_pos(self.t)?;
+        let end_spec = match self.dur_spec {
+            // Collapsed pulses are rejected by `add_instr()` - keep them 1 tick long here
+            Some((dur, keep_val)) => Some((to_pos(self.t + dur)?.max(start_pos + 1), keep_val)),
+            None => None,
+        };
+        let args = match &self.args {
+            FnArgs::Positional(args) => args.iter().map(|arg| format!("{arg:?}")).collect::<Vec<_>>(),
+            FnArgs::Named(args) => args.iter().map(|(name, arg)| format!("{name}={arg:?}")).collect(),
+        };
+        Ok(InstrSnapshot {
+            start_pos,
+            end_spec,
+            func: format!("{}({})", self.func, args.join(", ")),
+            meta: Some(InstrMeta::labeled(&self.loc)),
+        })
+    }
+}
+
+fn row_err(line: usize, msg: String) -> StreamerError {
+    StreamerError::InvalidArgument { ctx: ErrCtx::none(), msg: format!("Schedule line {line}: {msg}") }
+}
+
+fn parse_num(line: usize, what: &str, field: &str) -> Result<f64, StreamerError> {
+    match field {
+        "true" => Ok(1.0),
+        "false" => Ok(0.0),
+        _ => field.parse::<f64>().map_err(|_| row_err(line, format!("{what} \"{field}\" is not a number"))),
+    }
+}
+
+/// Parses a CSV/TSV schedule (see the [module docs](self) for the format)
+pub fn parse_csv(text: &str) -> Result<Vec<ScheduleRow>, StreamerError> {
+    let mut rows = Vec::new();
+    for (idx, raw_line) in text.lines().enumerate() {
+        let line = idx + 1;
+        let trimmed = raw_line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue
+        }
+        let delim = if raw_line.contains('\t') { '\t' } else { ',' };
+        let mut fields: Vec<&str> = raw_line.split(delim).map(str::trim).collect();
+        while fields.last().is_some_and(|field| field.is_empty()) {
+            fields.pop();
+        }
+        if fields[0] == "device" {
+            continue
+        }
+        if fields.len() < 6 {
+            return Err(row_err(line, format!(
+                "expected at least 6 fields (device, channel, t, duration, keep_val, function), got {}", fields.len()
+            )))
+        }
+        let t = parse_num(line, "time", fields[2])?;
+        let keep_val = match fields[4] {
+            "" | "false" | "0" => false,
+            "true" | "1" => true,
+            other => return Err(row_err(line, format!("keep_val \"{other}\" is not one of true/false/1/0"))),
+        };
+        let dur_spec = match fields[3] {
+            "" => None,
+            dur => Some((parse_num(line, "duration", dur)?, keep_val)),
+        };
+        let params = &fields[6..];
+        let args = if params.iter().any(|param| param.contains('=')) {
+            let mut named = IndexMap::new();
+            for param in params {
+                let Some((name, val)) = param.split_once('=') else {
+                    return Err(row_err(line, format!("can't mix positional parameter \"{param}\" with named ones")))
+                };
+                let name = name.trim();
+                if named.insert(name.to_string(), parse_num(line, "parameter", val.trim())?).is_some() {
+                    return Err(row_err(line, format!("parameter \"{name}\" given twice")))
+                }
+            }
+            FnArgs::Named(named)
+        } else {
+            FnArgs::Positional(params.iter().map(|param| parse_num(line, "parameter", param)).collect::<Result<_, _>>()?)
+        };
+        rows.push(ScheduleRow {
+            loc: format!("line {line}"),
+            dev: fields[0].to_string(),
+            chan: fields[1].to_string(),
+            t,
+            dur_spec,
+            func: fields[5].to_string(),
+            args,
+        });
+    }
+    Ok(rows)
+}
+
+/// Reads and parses a CSV/TSV schedule file
+pub fn read_csv(path: &str) -> Result<Vec<ScheduleRow>, StreamerError> {
+    let text = std::fs::read_to_string(path).map_err(|err| StreamerError::NotFound {
+        ctx: ErrCtx::none(),
+        msg: format!("Failed to read schedule file {path}: {err}"),
+    })?;
+    parse_csv(&text).map_err(|err| err.prefixed(path))
+}
+
+/// Current version of the JSON schedule schema
+pub const SCHEDULE_JSON_VERSION: u32 = 1;
+
+/// JSON schedule document (see the [module docs](self) for an example)
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScheduleDoc {
+    pub version: u32,
+    pub devices: Vec<DevSchedule>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DevSchedule {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub samp_rate: Option<f64>,
+    pub channels: Vec<ChanSchedule>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ChanSchedule {
+    pub name: String,
+    #[serde(default)]
+    pub instructions: Vec<InstrSchedule>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct InstrSchedule {
+    pub t: f64,
+    /// `None` for a "go-this" instruction
+    #[serde(default)]
+    pub dur: Option<f64>,
+    #[serde(default)]
+    pub keep_val: bool,
+    pub func: String,
+    #[serde(default)]
+    pub args: IndexMap<String, f64>,
+}
+
+impl ScheduleDoc {
+    /// Parses a JSON schedule, rejecting malformed documents and unsupported versions
+    pub fn from_json(text: &str) -> Result<Self, StreamerError> {
+        let doc: Self = serde_json::from_str(text).map_err(|err| StreamerError::InvalidArgument {
+            ctx: ErrCtx::none(),
+            msg: format!("Invalid JSON schedule: {err}"),
+        })?;
+        if doc.version > SCHEDULE_JSON_VERSION {
+            return Err(StreamerError::Incompatible {
+                ctx: ErrCtx::none(),
+                msg: format!("JSON schedule version {} is newer than the supported version {SCHEDULE_JSON_VERSION}", doc.version),
+            })
+        }
+        Ok(doc)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("ScheduleDoc only holds JSON-compatible values")
+    }
+
+    /// Flattens the document into schedule rows, in document order
+    pub fn rows(&self) -> Vec<ScheduleRow> {
+        let mut rows = Vec::new();
+        for dev in &self.devices {
+            for chan in &dev.channels {
+                for (idx, instr) in chan.instructions.iter().enumerate() {
+                    rows.push(ScheduleRow {
+                        loc: format!("{}/{} instruction {idx}", dev.name, chan.name),
+                        dev: dev.name.clone(),
+                        chan: chan.name.clone(),
+                        t: instr.t,
+                        dur_spec: instr.dur.map(|dur| (dur, instr.keep_val)),
+                        func: instr.func.clone(),
+                        args: FnArgs::Named(instr.args.clone()),
+                    })
+                }
+            }
+        }
+        rows
+    }
+}
+
+/// Splits a `describe()` string `"Name(param_1=val_1, ...)"` into the name and numeric arguments.
+/// `true`/`false` are converted to `1`/`0`.
+///
+/// Returns `None` if the string doesn't have this form or some value is not a finite number.
+pub fn parse_describe(desc: &str) -> Option<(String, IndexMap<String, f64>)> {
+    let (name, rest) = desc.split_once('(')?;
+    let params = rest.strip_suffix(')')?;
+    let mut args = IndexMap::new();
+    for param in params.split(',').map(str::trim).filter(|param| !param.is_empty()) {
+        let (param_name, val) = param.split_once('=')?;
+        let val = parse_num(0, "parameter", val.trim()).ok().filter(|val| val.is_finite())?;
+        args.insert(param_name.trim().to_string(), val);
+    }
+    Some((name.trim().to_string(), args))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let text = "\
+            device, channel, t, duration, keep_val, function, params\n\
+            # comment\n\
+            \n\
+            ao, ao0, 0.0, 1e-3, true, Sine, 1.0, 1e3,,\n\
+            ao\tao1\t0.5\t\t\tLinFn\tslope=2\toffs=0.5\n\
+            do, do0, 1, 2, , ConstBool, true\n";
+        let rows = parse_csv(text).unwrap();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0], ScheduleRow {
+            loc: "line 4".to_string(),
+            dev: "ao".to_string(),
+            chan: "ao0".to_string(),
+            t: 0.0,
+            dur_spec: Some((1e-3, true)),
+            func: "Sine".to_string(),
+            args: FnArgs::Positional(vec![1.0, 1e3]),
+        });
+        assert_eq!(rows[1].dur_spec, None);
+        assert_eq!(rows[1].args, FnArgs::Named(IndexMap::from([("slope".to_string(), 2.0), ("offs".to_string(), 0.5)])));
+        assert_eq!(rows[2].dur_spec, Some((2.0, false)));
+        assert_eq!(rows[2].args, FnArgs::Positional(vec![1.0]));
+
+        for bad in [
+            "ao, ao0, 0.0, 1e-3, true",
+            "ao, ao0, zero, 1e-3, true, Sine, 1, 1",
+            "ao, ao0, 0.0, 1e-3, maybe, Sine, 1, 1",
+            "ao, ao0, 0.0, 1e-3, true, Sine, amp=1, 1",
+            "ao, ao0, 0.0, 1e-3, true, Sine, amp=1, amp=2",
+        ] {
+            let err = parse_csv(bad).unwrap_err();
+            assert!(matches!(err, StreamerError::InvalidArgument { .. }), "{bad}: {err}");
+            assert!(err.msg().starts_with("Schedule line 1:"), "{}", err.msg());
+        }
+    }
+
+    #[test]
+    fn json_doc() {
+        let text = r#"{
+            "version": 1,
+            "devices": [{"name": "ao", "channels": [{"name": "ao0", "instructions": [
+                {"t": 0.0, "dur": 0.1, "keep_val": true, "func": "Sine", "args": {"amp": 1.0, "freq": 10.0}},
+                {"t": 0.2, "func": "ConstF64", "args": {"val": 0.5}}
+            ]}]}]
+        }"#;
+        let doc = ScheduleDoc::from_json(text).unwrap();
+        let rows = doc.rows();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].dur_spec, Some((0.1, true)));
+        assert_eq!(rows[1].dur_spec, None);
+        assert_eq!(rows[1].loc, "ao/ao0 instruction 1");
+        assert_eq!(rows[1].args, FnArgs::Named(IndexMap::from([("val".to_string(), 0.5)])));
+        assert_eq!(ScheduleDoc::from_json(&doc.to_json()).unwrap(), doc);
+
+        assert!(matches!(ScheduleDoc::from_json(r#"{"version": 2, "devices": []}"#), Err(StreamerError::Incompatible { .. })));
+        assert!(matches!(ScheduleDoc::from_json(r#"{"version": 1}"#), Err(StreamerError::InvalidArgument { .. })));
+        assert!(matches!(
+            ScheduleDoc::from_json(r#"{"version": 1, "devices": [], "extra": 0}"#),
+            Err(StreamerError::InvalidArgument { .. })
+        ));
+
+        assert_eq!(
+            parse_describe("Sine(amp=1.0, freq=1e-5, phase=0.0, offs=-2.0)"),
+            Some(("Sine".to_string(), IndexMap::from([
+                ("amp".to_string(), 1.0), ("freq".to_string(), 1e-5), ("phase".to_string(), 0.0), ("offs".to_string(), -2.0)
+            ])))
+        );
+        assert_eq!(parse_describe("ConstBool(val=true)"), Some(("ConstBool".to_string(), IndexMap::from([("val".to_string(), 1.0)]))));
+        assert_eq!(parse_describe("Poly(prms=[1.0, 2.0])"), None);
+        assert_eq!(parse_describe("Exp(tau=inf, scale=1.0, offs=0.0)"), None);
+    }
+}
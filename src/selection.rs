@@ -0,0 +1,71 @@
+//! Solo mode - streaming only a subset of devices and channels.
+//!
+//! A [`StreamSelection`] is a set of keys: `"Dev1"` selects a whole device, `"Dev1/ao0"` a single channel.
+//! While the selection is non-empty, the whole sequence is still compiled (so timing and validation are unaffected),
+//! but only selected devices are streamed, and unselected channels of a selected device stream their default value.
+//! An empty selection streams everything.
+//!
+//! Set with [`BaseStreamer::solo`], cleared with [`BaseStreamer::clear_solo`].
+//!
+//! [`BaseStreamer::solo`]: crate::streamer::BaseStreamer::solo
+//! [`BaseStreamer::clear_solo`]: crate::streamer::BaseStreamer::clear_solo
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fmt::Display;
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StreamSelection {
+    keys: BTreeSet<String>,
+}
+
+impl StreamSelection {
+    pub fn new(keys: &[&str]) -> Self {
+        Self { keys: keys.iter().map(|key| key.to_string()).collect() }
+    }
+    pub fn keys(&self) -> &BTreeSet<String> {
+        &self.keys
+    }
+    /// `true` if nothing is soloed - everything is streamed
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+    pub fn clear(&mut self) {
+        self.keys.clear()
+    }
+    /// Whether device `dev_name` is streamed - it is selected as a whole or any of its channels is
+    pub fn selects_dev(&self, dev_name: &str) -> bool {
+        self.is_empty() || self.keys.iter().any(|key| key.split_once('/').map_or(key.as_str(), |(dev, _chan)| dev) == dev_name)
+    }
+    /// Whether channel `chan_name` of device `dev_name` streams its compiled waveform
+    pub fn selects_chan(&self, dev_name: &str, chan_name: &str) -> bool {
+        self.is_empty() || self.keys.contains(dev_name) || self.keys.contains(&format!("{dev_name}/{chan_name}"))
+    }
+}
+
+impl Display for StreamSelection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            write!(f, "all")
+        } else {
+            write!(f, "{}", self.keys.iter().cloned().collect::<Vec<_>>().join(", "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn selects() {
+        let everything = StreamSelection::default();
+        assert!(everything.selects_dev("AO") && everything.selects_chan("AO", "ao0"));
+
+        let solo = StreamSelection::new(&["AO/ao1", "DO"]);
+        assert!(solo.selects_dev("AO") && solo.selects_dev("DO") && !solo.selects_dev("AO2"));
+        assert!(solo.selects_chan("AO", "ao1") && !solo.selects_chan("AO", "ao0"));
+        assert!(solo.selects_chan("DO", "port0/line0"));
+        assert_eq!(solo.to_string(), "AO/ao1, DO");
+    }
+}
@@ -0,0 +1,324 @@
+//! Remote control of a streamer over TCP or Unix sockets (feature `server`).
+//!
+//! A sequence-design process can drive a streamer living in another process - typically on the machine attached
+//! to the hardware. The protocol is line-based: every request is one JSON-encoded [`Command`] terminated by `\n`,
+//! answered by one JSON-encoded [`Response`] line:
+//!
+//! ```text
+//! -> {"cmd": "add_instr", "dev": "ao", "chan": "ao0", "t": 0.0, "dur": 1e-3, "func": "Sine", "args": {"amp": 1.0, "freq": 1e3}}
+//! <- {"ok": null}
+//! -> {"cmd": "compile", "stop_time": null}
+//! <- {"ok": 0.001}
+//! -> {"cmd": "add_instr", "dev": "xx", ...}
+//! <- {"err": {"NotFound": {"ctx": {"dev": null, "chan": null}, "msg": "..."}}}
+//! ```
+//!
+//! The server handles one connection at a time, in the order they arrive, and returns after a `shutdown` command.
+//! Requests which are not valid UTF-8 or JSON are answered with [`StreamerError::InvalidArgument`], and a connection
+//! failing with an io error (e.g. reset by the peer) is dropped like a regular disconnect - the server keeps running.
+//! Functions are constructed by name through a [`FnRegistry`], as for schedule files (see [`crate::schedule`]).
+//! [`Client`] is the matching client side.
+
+use std::io;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::error::{ErrCtx, StreamerError};
+use crate::fn_lib_tools::{FnArgs, FnRegistry};
+use crate::streamer::BaseStreamer;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+pub enum Command {
+    /// Replies `null` - connection check
+    Ping,
+    /// Adds an instruction with a function from the registry. `dur: null` makes a "go-this" instruction
+    AddInstr {
+        dev: String,
+        chan: String,
+        t: f64,
+        #[serde(default)]
+        dur: Option<f64>,
+        #[serde(default)]
+        keep_val: bool,
+        func: String,
+        #[serde(default)]
+        args: IndexMap<String, f64>,
+    },
+    /// Adds the instructions of a JSON schedule document, see [`BaseStreamer::import_schedule_json`]
+    ImportSchedule { schedule: Value },
+    /// Replies the edit caches as a JSON schedule document, see [`BaseStreamer::export_schedule_json`]
+    ExportSchedule,
+    ClearEditCache,
+    AddResetInstr {
+        #[serde(default)]
+        reset_time: Option<f64>,
+    },
+    /// Replies the compiled stop time
+    Compile {
+        #[serde(default)]
+        stop_time: Option<f64>,
+    },
+    /// Replies the end time of the last instruction or `null`
+    LastInstrEndTime,
+    /// Replies the samples of a compiled channel converted to `f64`, see [`BaseChan::calc_nsamps`]
+    ///
+    /// [`BaseChan::calc_nsamps`]: crate::channel::BaseChan::calc_nsamps
+    CalcNsamps {
+        dev: String,
+        chan: String,
+        n_samps: usize,
+        #[serde(default)]
+        start_time: Option<f64>,
+        #[serde(default)]
+        end_time: Option<f64>,
+    },
+    /// Replies `null` and stops the server
+    Shutdown,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Response {
+    Ok(Value),
+    Err(StreamerError),
+}
+
+fn to_value<T: Serialize>(val: T) -> Value {
+    serde_json::to_value(val).expect("command replies only hold JSON-compatible values")
+}
+
+/// Executes `cmd` on `streamer` and returns the reply value
+pub fn handle<S: BaseStreamer>(streamer: &mut S, registry: &FnRegistry, cmd: Command) -> Result<Value, StreamerError> {
+    match cmd {
+        Command::Ping | Command::Shutdown => Ok(Value::Null),
+        Command::AddInstr { dev, chan, t, dur, keep_val, func, args } => {
+            streamer.add_instr_by_name(&dev, &chan, registry, &func, &FnArgs::Named(args), t, dur.map(|dur| (dur, keep_val)))?;
+            Ok(Value::Null)
+        },
+        Command::ImportSchedule { schedule } => {
+            streamer.import_schedule_json_with(&schedule.to_string(), registry)?;
+            Ok(Value::Null)
+        },
+        Command::ExportSchedule => {
+            let json = streamer.export_schedule_json()?;
+            Ok(serde_json::from_str(&json).expect("export_schedule_json() produces valid JSON"))
+        },
+        Command::ClearEditCache => {
+            streamer.clear_edit_cache();
+            Ok(Value::Null)
+        },
+        Command::AddResetInstr { reset_time } => {
+            streamer.add_reset_instr(reset_time)?;
+            Ok(Value::Null)
+        },
+        Command::Compile { stop_time } => Ok(to_value(streamer.compile(stop_time)?)),
+        Command::LastInstrEndTime => Ok(to_value(streamer.last_instr_end_time())),
+        Command::CalcNsamps { dev, chan, n_samps, start_time, end_time } => {
+            Ok(to_value(streamer.dev(&dev)?.tag_calc_nsamps(&chan, n_samps, start_time, end_time)?))
+        },
+    }
+}
+
+fn invalid_request(msg: String) -> Response {
+    Response::Err(StreamerError::InvalidArgument { ctx: ErrCtx::none(), msg })
+}
+
+/// Serves requests read from `reader` until the peer disconnects or sends `shutdown`.
+/// Returns `true` in the latter case.
+pub fn serve_connection<S: BaseStreamer>(
+    streamer: &mut S, registry: &FnRegistry, mut reader: impl BufRead, mut writer: impl Write
+) -> io::Result<bool> {
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        if reader.read_until(b'\n', &mut buf)? == 0 {
+            return Ok(false)
+        }
+        let line = match std::str::from_utf8(&buf) {
+            Ok(line) if line.trim().is_empty() => continue,
+            Ok(line) => line,
+            Err(err) => {
+                serde_json::to_writer(&mut writer, &invalid_request(format!("Request is not valid UTF-8: {err}")))?;
+                writer.write_all(b"\n")?;
+                writer.flush()?;
+                continue
+            },
+        };
+        let (response, shutdown) = match serde_json::from_str::<Command>(line) {
+            Ok(cmd) => {
+                let shutdown = cmd == Command::Shutdown;
+                let response = match handle(streamer, registry, cmd) {
+                    Ok(val) => Response::Ok(val),
+                    Err(err) => Response::Err(err),
+                };
+                (response, shutdown)
+            },
+            Err(err) => (invalid_request(format!("Invalid command: {err}")), false),
+        };
+        serde_json::to_writer(&mut writer, &response)?;
+        writer.write_all(b"\n")?;
+        writer.flush()?;
+        if shutdown {
+            return Ok(true)
+        }
+    }
+}
+
+/// Accepted connection, or `None` if the peer gave up before it was accepted
+fn accepted<T>(stream: io::Result<T>) -> io::Result<Option<T>> {
+    match stream {
+        Ok(stream) => Ok(Some(stream)),
+        Err(err) if matches!(err.kind(), io::ErrorKind::ConnectionAborted | io::ErrorKind::ConnectionReset | io::ErrorKind::Interrupted) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+fn serve_tcp_stream<S: BaseStreamer>(streamer: &mut S, registry: &FnRegistry, stream: TcpStream) -> io::Result<bool> {
+    // Replies are small and awaited one by one - don't let Nagle's algorithm hold them back
+    stream.set_nodelay(true)?;
+    serve_connection(streamer, registry, BufReader::new(stream.try_clone()?), stream)
+}
+
+/// Accepts connections on `listener` one after another until a client sends `shutdown`.
+///
+/// An io error on a connection ends that connection only. Returns `Err` if accepting connections fails.
+pub fn serve_tcp<S: BaseStreamer>(streamer: &mut S, registry: &FnRegistry, listener: TcpListener) -> io::Result<()> {
+    for stream in listener.incoming() {
+        let Some(stream) = accepted(stream)? else {
+            continue
+        };
+        if serve_tcp_stream(streamer, registry, stream).unwrap_or(false) {
+            break
+        }
+    }
+    Ok(())
+}
+
+/// Same as [`serve_tcp`] for a Unix socket
+#[cfg(unix)]
+pub fn serve_unix<S: BaseStreamer>(streamer: &mut S, registry: &FnRegistry, listener: UnixListener) -> io::Result<()> {
+    for stream in listener.incoming() {
+        let Some(stream) = accepted(stream)? else {
+            continue
+        };
+        let served = stream.try_clone().and_then(|reader| serve_connection(streamer, registry, BufReader::new(reader), stream));
+        if served.unwrap_or(false) {
+            break
+        }
+    }
+    Ok(())
+}
+
+/// Client side of the protocol
+pub struct Client<S: Read + Write> {
+    reader: BufReader<S>,
+    writer: S,
+}
+
+impl Client<TcpStream> {
+    pub fn connect_tcp(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let stream = TcpStream::connect(addr)?;
+        stream.set_nodelay(true)?;
+        Ok(Self { reader: BufReader::new(stream.try_clone()?), writer: stream })
+    }
+}
+
+#[cfg(unix)]
+impl Client<UnixStream> {
+    pub fn connect_unix(path: &str) -> io::Result<Self> {
+        let stream = UnixStream::connect(path)?;
+        Ok(Self { reader: BufReader::new(stream.try_clone()?), writer: stream })
+    }
+}
+
+impl<S: Read + Write> Client<S> {
+    /// Sends `cmd` and waits for the reply. The outer `Result` reports transport failures,
+    /// the inner one the outcome of the command on the server.
+    pub fn request(&mut self, cmd: &Command) -> io::Result<Result<Value, StreamerError>> {
+        serde_json::to_writer(&mut self.writer, cmd)?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()?;
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "server closed the connection"))
+        }
+        match serde_json::from_str(&line)? {
+            Response::Ok(val) => Ok(Ok(val)),
+            Response::Err(err) => Ok(Err(err)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::device::BaseDev;
+    use crate::mock::test_impls::TestStreamer;
+
+    #[test]
+    fn tcp_round_trip() {
+        let mut streamer = TestStreamer::new();
+        streamer.add_ao_dev("AO", 1e3);
+        streamer.ao_devs["AO"].add_chan("ao0", 0.0);
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            serve_tcp(&mut streamer, &FnRegistry::std(), listener).unwrap();
+            streamer
+        });
+
+        let mut client = Client::connect_tcp(addr).unwrap();
+        assert_eq!(client.request(&Command::Ping).unwrap(), Ok(Value::Null));
+        let add_instr = |dev: &str, func: &str| Command::AddInstr {
+            dev: dev.to_string(),
+            chan: "ao0".to_string(),
+            t: 0.1,
+            dur: Some(0.1),
+            keep_val: false,
+            func: func.to_string(),
+            args: IndexMap::from([("val".to_string(), 2.0)]),
+        };
+        assert_eq!(client.request(&add_instr("AO", "ConstF64")).unwrap(), Ok(Value::Null));
+        // Errors keep their type across the connection
+        assert!(matches!(client.request(&add_instr("XX", "ConstF64")).unwrap(), Err(StreamerError::NotFound { .. })));
+        assert!(matches!(client.request(&add_instr("AO", "Foo")).unwrap(), Err(StreamerError::NotFound { .. })));
+        assert_eq!(client.request(&Command::LastInstrEndTime).unwrap(), Ok(Value::from(0.2)));
+        assert_eq!(client.request(&Command::Compile { stop_time: Some(0.5) }).unwrap(), Ok(Value::from(0.5)));
+        let samps = client.request(&Command::CalcNsamps {
+            dev: "AO".to_string(), chan: "ao0".to_string(), n_samps: 3, start_time: Some(0.0), end_time: Some(0.3)
+        }).unwrap().unwrap();
+        assert_eq!(samps, Value::from(vec![0.0, 2.0, 0.0]));
+        let schedule = client.request(&Command::ExportSchedule).unwrap().unwrap();
+        assert_eq!(schedule["devices"][0]["channels"][0]["instructions"][0]["func"], "ConstF64");
+
+        // Malformed requests are answered without dropping the connection
+        client.writer.write_all(b"{\"cmd\": \"nope\"}\n").unwrap();
+        let mut line = String::new();
+        client.reader.read_line(&mut line).unwrap();
+        assert!(matches!(serde_json::from_str(&line).unwrap(), Response::Err(StreamerError::InvalidArgument { .. })));
+
+        // So are requests which are not UTF-8
+        client.writer.write_all(b"{\"cmd\": \"ping\xff\"}\n").unwrap();
+        line.clear();
+        client.reader.read_line(&mut line).unwrap();
+        assert!(matches!(serde_json::from_str(&line).unwrap(), Response::Err(StreamerError::InvalidArgument { .. })));
+        assert_eq!(client.request(&Command::Ping).unwrap(), Ok(Value::Null));
+
+        // Second connection after the first one closes
+        drop(client);
+        // A peer dropping the connection with unread replies resets it - the server carries on
+        let mut client = Client::connect_tcp(addr).unwrap();
+        client.writer.write_all(&b"{\"cmd\": \"ping\"}\n".repeat(1000)).unwrap();
+        drop(client);
+        let mut client = Client::connect_tcp(addr).unwrap();
+        assert_eq!(client.request(&Command::ClearEditCache).unwrap(), Ok(Value::Null));
+        assert_eq!(client.request(&Command::Shutdown).unwrap(), Ok(Value::Null));
+        let streamer = server.join().unwrap();
+        assert!(!streamer.ao_devs["AO"].got_instructions());
+    }
+}
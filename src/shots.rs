@@ -0,0 +1,381 @@
+//! Repeated experiments - a template sequence plus a table of per-shot parameter values.
+//!
+//! Every [`TemplateInstr`] takes its timing and function arguments either as fixed numbers or as named
+//! parameters ([`Param`]). Each row of the parameter table is one shot. [`ShotSequence::iter`] walks the table,
+//! writing the resolved instructions into the streamer and compiling it once per shot.
+//!
+//! Work is reused between shots: only channels whose resolved instructions differ from the previous shot
+//! have their edit caches rewritten, and only their devices are compiled again - as long as the stop time stays the same.
+//! Channels not mentioned in the template keep whatever instructions they were given before and are compiled with the first shot.
+//! Template channels are owned by the sequence - their previous instructions are replaced on the first shot.
+//!
+//! The iterator holds the streamer, so loop with `while let` to access it between shots:
+//!
+//! ```ignore
+//! let mut shots = seq.iter(&mut streamer);
+//! while let Some(shot) = shots.next() {
+//!     let shot = shot?;
+//!     upload(shots.streamer(), &shot);
+//! }
+//! ```
+
+use std::collections::HashSet;
+use indexmap::IndexMap;
+use crate::error::{ErrCtx, StreamerError};
+use crate::fn_lib_tools::{FnArgs, FnRegistry};
+use crate::options::CompileOptions;
+use crate::streamer::BaseStreamer;
+
+/// Fixed value or the name of a parameter table column
+#[derive(Clone, Debug, PartialEq)]
+pub enum Param {
+    Fixed(f64),
+    Var(String),
+}
+impl From<f64> for Param {
+    fn from(val: f64) -> Self {
+        Param::Fixed(val)
+    }
+}
+impl From<&str> for Param {
+    fn from(name: &str) -> Self {
+        Param::Var(name.to_string())
+    }
+}
+impl Param {
+    fn resolve(&self, vals: &IndexMap<String, f64>) -> f64 {
+        match self {
+            Param::Fixed(val) => *val,
+            Param::Var(name) => vals[name.as_str()],
+        }
+    }
+}
+
+/// Instruction of the template with parameter-dependent timing and function arguments
+#[derive(Clone, Debug, PartialEq)]
+pub struct TemplateInstr {
+    pub dev: String,
+    pub chan: String,
+    pub t: Param,
+    /// `None` for a "go-this" instruction
+    pub dur: Option<Param>,
+    pub keep_val: bool,
+    /// Function name in the registry, see [`FnRegistry`]
+    pub func: String,
+    pub args: IndexMap<String, Param>,
+}
+impl TemplateInstr {
+    pub fn new(dev: &str, chan: &str, t: impl Into<Param>, dur: Option<Param>, keep_val: bool, func: &str) -> Self {
+        Self { dev: dev.to_string(), chan: chan.to_string(), t: t.into(), dur, keep_val, func: func.to_string(), args: IndexMap::new() }
+    }
+    /// Adds function argument `name`
+    pub fn arg(mut self, name: &str, val: impl Into<Param>) -> Self {
+        self.args.insert(name.to_string(), val.into());
+        self
+    }
+    fn vars(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(&self.t).chain(self.dur.iter()).chain(self.args.values()).filter_map(|param| match param {
+            Param::Var(name) => Some(name.as_str()),
+            Param::Fixed(_) => None,
+        })
+    }
+}
+
+/// Instruction with all parameters substituted - compared between shots to find the channels which changed
+#[derive(Clone, Debug, PartialEq)]
+struct ResolvedInstr {
+    t: f64,
+    dur_spec: Option<(f64, bool)>,
+    func: String,
+    args: IndexMap<String, f64>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ShotSequence {
+    params: Vec<String>,
+    instrs: Vec<TemplateInstr>,
+    shots: Vec<Vec<f64>>,
+    /// Stop time of every shot, `None` - end of the last instruction of the shot
+    pub stop_time: Option<f64>,
+    pub opts: CompileOptions,
+}
+
+/// Outcome of one shot
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShotReport {
+    pub idx: usize,
+    pub params: IndexMap<String, f64>,
+    /// Compiled stop time
+    pub stop_time: f64,
+    /// Devices compiled for this shot (the remaining active devices kept the compile cache of the previous shot)
+    pub compiled_devs: Vec<String>,
+}
+
+impl ShotSequence {
+    /// Empty sequence with parameter table columns `params`
+    pub fn new(params: &[&str]) -> Self {
+        Self { params: params.iter().map(|param| param.to_string()).collect(), ..Default::default() }
+    }
+    pub fn params(&self) -> &[String] {
+        &self.params
+    }
+    pub fn n_shots(&self) -> usize {
+        self.shots.len()
+    }
+
+    /// Returns [`StreamerError::NotFound`] if the instruction uses a parameter which is not a table column
+    pub fn add_instr(&mut self, instr: TemplateInstr) -> Result<(), StreamerError> {
+        if let Some(var) = instr.vars().find(|var| !self.params.iter().any(|param| param == var)) {
+            return Err(StreamerError::NotFound {
+                ctx: ErrCtx { dev: Some(instr.dev.clone()), chan: Some(instr.chan.clone()) },
+                msg: format!("Template instruction {}() uses parameter \"{var}\" which is not one of {:?}", instr.func, self.params),
+            })
+        }
+        self.instrs.push(instr);
+        Ok(())
+    }
+
+    /// Appends a row of the parameter table - one value per column
+    pub fn add_shot(&mut self, vals: &[f64]) -> Result<(), StreamerError> {
+        if vals.len() != self.params.len() {
+            return Err(StreamerError::InvalidArgument {
+                ctx: ErrCtx::none(),
+                msg: format!("Shot has {} values while the parameter table has {} columns {:?}", vals.len(), self.params.len(), self.params),
+            })
+        }
+        self.shots.push(vals.to_vec());
+        Ok(())
+    }
+
+    /// Iterates over the shots, constructing functions with the standard [`FnRegistry::std`]
+    pub fn iter<'a, S: BaseStreamer>(&'a self, streamer: &'a mut S) -> ShotIter<'a, S> {
+        self.iter_with(streamer, FnRegistry::std())
+    }
+
+    /// Same as [`ShotSequence::iter`] with a custom function registry
+    pub fn iter_with<'a, S: BaseStreamer>(&'a self, streamer: &'a mut S, registry: FnRegistry) -> ShotIter<'a, S> {
+        ShotIter { seq: self, streamer, registry, next_idx: 0, prev: None, prev_stop_time: None, failed: false }
+    }
+
+    /// Independent copy of `streamer` with the template instructions written in, parameters taken from `overrides`.
+    /// Functions are constructed with the standard [`FnRegistry::std`]. The copy is not compiled.
+    ///
+    /// Unlike [`ShotSequence::iter`], this doesn't touch `streamer` and doesn't need the parameter table,
+    /// so scan points can be generated and compiled in parallel, e.g. one copy per worker thread.
+    ///
+    /// Returns [`StreamerError::NotFound`] if `overrides` misses a parameter used by the template
+    /// or names one which is not a table column.
+    pub fn instantiate<S: BaseStreamer + Clone>(&self, streamer: &S, overrides: &IndexMap<String, f64>) -> Result<S, StreamerError> {
+        if let Some(name) = overrides.keys().find(|name| !self.params.contains(name)) {
+            return Err(StreamerError::NotFound {
+                ctx: ErrCtx::none(),
+                msg: format!("Override \"{name}\" is not one of the parameters {:?}", self.params),
+            })
+        }
+        let missing = self.instrs.iter().find_map(|instr| instr.vars().find(|var| !overrides.contains_key(*var)).map(|var| (instr, var)));
+        if let Some((instr, var)) = missing {
+            return Err(StreamerError::NotFound {
+                ctx: ErrCtx { dev: Some(instr.dev.clone()), chan: Some(instr.chan.clone()) },
+                msg: format!("Template instruction {}() uses parameter \"{var}\" which has no value in the overrides", instr.func),
+            })
+        }
+        let registry = FnRegistry::std();
+        let mut copy = streamer.clone();
+        for ((dev_name, chan_name), instrs) in &self.resolve(overrides) {
+            write_chan(&mut copy, &registry, dev_name, chan_name, instrs)?
+        }
+        Ok(copy)
+    }
+
+    /// Instructions resolved with parameter values `vals`, grouped by `(device, channel)` in template order
+    fn resolve(&self, vals: &IndexMap<String, f64>) -> IndexMap<(String, String), Vec<ResolvedInstr>> {
+        let mut chans: IndexMap<(String, String), Vec<ResolvedInstr>> = IndexMap::new();
+        for instr in &self.instrs {
+            chans.entry((instr.dev.clone(), instr.chan.clone())).or_default().push(ResolvedInstr {
+                t: instr.t.resolve(vals),
+                dur_spec: instr.dur.as_ref().map(|dur| (dur.resolve(vals), instr.keep_val)),
+                func: instr.func.clone(),
+                args: instr.args.iter().map(|(name, param)| (name.clone(), param.resolve(vals))).collect(),
+            })
+        }
+        chans
+    }
+}
+
+/// Replaces the edit cache of channel `chan_name` of device `dev_name` with the resolved `instrs`
+fn write_chan<S: BaseStreamer>(streamer: &mut S, registry: &FnRegistry, dev_name: &str, chan_name: &str, instrs: &[ResolvedInstr]) -> Result<(), StreamerError> {
+    let dev = streamer.dev_mut(dev_name).map_err(|err| err.prefixed("Template"))?;
+    dev.tag_clear_chan_edit_cache(chan_name)?;
+    for instr in instrs {
+        dev.tag_add_instr_by_name(chan_name, registry, &instr.func, &FnArgs::Named(instr.args.clone()), instr.t, instr.dur_spec)?
+    }
+    Ok(())
+}
+
+/// Iterator over compiled shots, see [`ShotSequence::iter`].
+///
+/// Stops after the first error.
+pub struct ShotIter<'a, S: BaseStreamer> {
+    seq: &'a ShotSequence,
+    streamer: &'a mut S,
+    registry: FnRegistry,
+    next_idx: usize,
+    prev: Option<IndexMap<(String, String), Vec<ResolvedInstr>>>,
+    prev_stop_time: Option<f64>,
+    failed: bool,
+}
+
+impl<S: BaseStreamer> ShotIter<'_, S> {
+    /// The streamer compiled for the last returned shot
+    pub fn streamer(&self) -> &S {
+        self.streamer
+    }
+
+    fn run_shot(&mut self, idx: usize) -> Result<ShotReport, StreamerError> {
+        let vals: IndexMap<String, f64> = self.seq.params.iter().cloned().zip(self.seq.shots[idx].iter().copied()).collect();
+        let chans = self.seq.resolve(&vals);
+
+        // Rewrite the channels which changed since the previous shot
+        let mut touched_devs = HashSet::new();
+        for ((dev_name, chan_name), instrs) in &chans {
+            if self.prev.as_ref().is_some_and(|prev| prev.get(&(dev_name.clone(), chan_name.clone())) == Some(instrs)) {
+                continue
+            }
+            write_chan(self.streamer, &self.registry, dev_name, chan_name, instrs).map_err(|err| err.prefixed(&format!("Shot {idx}")))?;
+            touched_devs.insert(dev_name.clone());
+        }
+        self.prev = Some(chans);
+
+        let stop_time = match self.seq.stop_time {
+            Some(stop_time) => stop_time,
+            None => self.streamer.last_instr_end_time().ok_or_else(|| StreamerError::NoInstructions {
+                ctx: ErrCtx::none(),
+                msg: "Shot sequence did not produce any instructions".to_string(),
+            })?,
+        };
+        // Markers link devices, so partial recompiles are only safe without them
+        let full_compile = self.prev_stop_time != Some(stop_time) || self.streamer.markers().is_some_and(|markers| !markers.is_empty());
+        let compiled_devs = if full_compile {
+            self.streamer.compile_with(Some(stop_time), &self.seq.opts)?;
+            self.streamer.active_dev_names()
+        } else {
+            let mut compiled_devs = Vec::new();
+            for dev in self.streamer.active_devs_mut().into_iter().filter(|dev| touched_devs.contains(&dev.tag_name())) {
+                dev.tag_compile_with(stop_time, &self.seq.opts)?;
+                compiled_devs.push(dev.tag_name())
+            }
+            compiled_devs
+        };
+        self.prev_stop_time = Some(stop_time);
+        Ok(ShotReport { idx, params: vals, stop_time, compiled_devs })
+    }
+}
+
+impl<S: BaseStreamer> Iterator for ShotIter<'_, S> {
+    type Item = Result<ShotReport, StreamerError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.next_idx >= self.seq.shots.len() {
+            return None
+        }
+        let idx = self.next_idx;
+        self.next_idx += 1;
+        let res = self.run_shot(idx);
+        if res.is_err() {
+            self.failed = true
+        }
+        Some(res)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::channel::BaseChan;
+    use crate::device::BaseDev;
+    use crate::mock::test_impls::TestStreamer;
+
+    #[test]
+    fn shots() {
+        let mut streamer = TestStreamer::new();
+        streamer.add_ao_dev("AO", 1e3);
+        streamer.add_ao_dev("AO2", 1e3);
+        streamer.ao_devs["AO"].add_chan("ao0", 0.0);
+        streamer.ao_devs["AO2"].add_chan("ao0", 0.0);
+        // Not in the template - kept as is
+        streamer.ao_devs["AO2"].add_chan("ao1", 0.0);
+        streamer.ao_devs["AO2"].chan_mut("ao1").unwrap().constant(5.0, 0.0, Some((0.1, false))).unwrap();
+
+        let mut seq = ShotSequence::new(&["amp", "t_pulse"]);
+        seq.stop_time = Some(1.0);
+        seq.add_instr(TemplateInstr::new("AO", "ao0", "t_pulse", Some(0.1.into()), false, "ConstF64").arg("val", "amp")).unwrap();
+        seq.add_instr(TemplateInstr::new("AO2", "ao0", 0.5, None, false, "ConstF64").arg("val", 2.0)).unwrap();
+        assert!(matches!(
+            seq.add_instr(TemplateInstr::new("AO", "ao0", "t_x", None, false, "ConstF64").arg("val", 1.0)),
+            Err(StreamerError::NotFound { .. })
+        ));
+        assert!(matches!(seq.add_shot(&[1.0]), Err(StreamerError::InvalidArgument { .. })));
+        seq.add_shot(&[1.0, 0.1]).unwrap();
+        seq.add_shot(&[2.0, 0.1]).unwrap();
+        seq.add_shot(&[2.0, 0.3]).unwrap();
+        seq.add_shot(&[2.0, 0.3]).unwrap();
+
+        let mut shots = seq.iter(&mut streamer);
+        let mut expected = [(1.0, 0.15), (2.0, 0.15), (2.0, 0.35), (2.0, 0.35)].into_iter();
+        let mut compiled = Vec::new();
+        while let Some(shot) = shots.next() {
+            let shot = shot.unwrap();
+            let (amp, t) = expected.next().unwrap();
+            let streamer = shots.streamer();
+            assert_eq!(streamer.ao_devs["AO"].chan("ao0").unwrap().eval_point(t).unwrap(), amp);
+            assert_eq!(streamer.ao_devs["AO2"].chan("ao0").unwrap().eval_point(0.6).unwrap(), 2.0);
+            assert_eq!(streamer.ao_devs["AO2"].chan("ao1").unwrap().eval_point(0.05).unwrap(), 5.0);
+            assert!(streamer.validate_compile_cache().is_ok());
+            compiled.push(shot.compiled_devs);
+        }
+        // Only the device with the changed channel is compiled again, nothing if the shot repeats
+        assert_eq!(compiled, [vec!["AO".to_string(), "AO2".to_string()], vec!["AO".to_string()], vec!["AO".to_string()], vec![]]);
+        assert_eq!(streamer.ao_devs["AO"].chan("ao0").unwrap().instr_list().len(), 1);
+
+        // Shot errors end the iteration
+        let mut seq = ShotSequence::new(&["amp"]);
+        seq.add_instr(TemplateInstr::new("AO", "ao0", 0.0, None, false, "Foo").arg("val", "amp")).unwrap();
+        seq.add_shot(&[1.0]).unwrap();
+        seq.add_shot(&[2.0]).unwrap();
+        let mut shots = seq.iter(&mut streamer);
+        assert!(matches!(shots.next(), Some(Err(StreamerError::NotFound { .. }))));
+        assert!(shots.next().is_none());
+    }
+
+    #[test]
+    fn instantiate() {
+        let mut streamer = TestStreamer::new();
+        streamer.add_ao_dev("AO", 1e3);
+        streamer.ao_devs["AO"].add_chan("ao0", 0.0);
+        streamer.ao_devs["AO"].add_chan("ao1", 0.0);
+        streamer.ao_devs["AO"].chan_mut("ao1").unwrap().constant(5.0, 0.0, Some((0.1, false))).unwrap();
+        let mut seq = ShotSequence::new(&["amp", "t_pulse"]);
+        seq.add_instr(TemplateInstr::new("AO", "ao0", "t_pulse", Some(0.1.into()), false, "ConstF64").arg("val", "amp")).unwrap();
+
+        let overrides = |amp: f64| IndexMap::from([("amp".to_string(), amp), ("t_pulse".to_string(), 0.2)]);
+        assert!(matches!(streamer.instantiate(&seq, &IndexMap::from([("amp".to_string(), 1.0)])), Err(StreamerError::NotFound { .. })));
+        let mut typo = overrides(1.0);
+        typo.insert("ampl".to_string(), 1.0);
+        assert!(matches!(streamer.instantiate(&seq, &typo), Err(StreamerError::NotFound { .. })));
+
+        // Scan points compiled in parallel, the original streamer is untouched
+        let vals: Vec<f64> = std::thread::scope(|scope| {
+            let workers: Vec<_> = [1.0, 2.0, 3.0].into_iter().map(|amp| {
+                let mut point = streamer.instantiate(&seq, &overrides(amp)).unwrap();
+                scope.spawn(move || {
+                    point.compile(None).unwrap();
+                    point.ao_devs["AO"].chan("ao0").unwrap().eval_point(0.25).unwrap()
+                })
+            }).collect();
+            workers.into_iter().map(|worker| worker.join().unwrap()).collect()
+        });
+        assert_eq!(vals, [1.0, 2.0, 3.0]);
+        assert!(!streamer.ao_devs["AO"].chan("ao0").unwrap().got_instructions());
+        assert_eq!(streamer.ao_devs["AO"].chan("ao1").unwrap().instr_list().len(), 1);
+    }
+}
@@ -0,0 +1,340 @@
+//! Inter-device skew verification - how far apart matching edges of two channels come out.
+//!
+//! Synchronization assumptions (shared start trigger, trigger delays, reference clocks) are easy to get wrong and hard
+//! to spot on a scope. [`BaseStreamer::measure_skew`] finds the edges of two compiled channels inside a time window
+//! and pairs them up, so a sequence meant to fire simultaneously on two devices can be checked before a campaign.
+//!
+//! An edge is a crossing of `|signal| > threshold` on the channel's own sample grid, so edges of devices with different
+//! clocks are quantized differently. Edge times are given on the trigger time axis: the tick time plus the
+//! trigger delay of the device (see [`BaseDev::set_trigger_delay`]). Edges at the very start and at the compiled stop
+//! time of a channel are not reported - they mark the ends of the sequence, not a feature of the waveform.
+//!
+//! Edges are matched in time order: each edge of `chan_a` is paired with the next unmatched edge of `chan_b` of the
+//! same kind, if that one is within `max_skew`.
+//!
+//! For waveforms without clean edges (ramps, sines, shaped pulses), [`BaseStreamer::relative_delay`] compares the
+//! channels as a whole: both are sampled on a common grid over a time window (at the higher of the two sample rates,
+//! holding the last tick and including trigger delays) and the lag maximizing their normalized cross-correlation is
+//! returned. Lags up to half the window are searched, and the correlation is computed directly - `O(n^2)` in the
+//! number of grid points - so the window should cover the feature of interest rather than the whole sequence.
+//! Outside of its compiled range, a channel is taken to hold its first / last sample.
+//!
+//! [`BaseStreamer::measure_skew`]: crate::streamer::BaseStreamer::measure_skew
+//! [`BaseStreamer::relative_delay`]: crate::streamer::BaseStreamer::relative_delay
+//! [`BaseDev::set_trigger_delay`]: crate::device::BaseDev::set_trigger_delay
+
+use std::fmt;
+use std::fmt::Display;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SkewSpec {
+    /// `"<device>/<channel>"` of the reference channel
+    pub chan_a: String,
+    /// `"<device>/<channel>"` of the channel compared against `chan_a`
+    pub chan_b: String,
+    /// Window (in seconds) to look for edges in, both ends included
+    pub t_start: f64,
+    pub t_end: f64,
+    /// Edges are crossings of `|signal| > threshold`. Default: 0.5
+    pub threshold: f64,
+    /// Largest skew (in seconds) for two edges to be paired up. Default: `None` - no limit
+    pub max_skew: Option<f64>,
+}
+
+impl SkewSpec {
+    pub fn new(chan_a: &str, chan_b: &str, t_start: f64, t_end: f64) -> Self {
+        Self { chan_a: chan_a.to_string(), chan_b: chan_b.to_string(), t_start, t_end, threshold: 0.5, max_skew: None }
+    }
+    pub fn with_threshold(mut self, threshold: f64) -> Self {
+        self.threshold = threshold;
+        self
+    }
+    pub fn with_max_skew(mut self, max_skew: f64) -> Self {
+        self.max_skew = Some(max_skew);
+        self
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EdgeKind {
+    Rising,
+    Falling,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Edge {
+    /// Time on the trigger time axis in seconds
+    pub t: f64,
+    pub kind: EdgeKind,
+}
+
+impl Edge {
+    /// Edges of sorted, disjoint high intervals (in seconds) which ran until `stop_time` at the latest
+    pub fn from_intervals(intervals: &[(f64, f64)], stop_time: f64) -> Vec<Edge> {
+        let mut edges = Vec::new();
+        for &(start, end) in intervals {
+            if start > 0.0 {
+                edges.push(Edge { t: start, kind: EdgeKind::Rising })
+            }
+            if end < stop_time {
+                edges.push(Edge { t: end, kind: EdgeKind::Falling })
+            }
+        }
+        edges
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EdgePair {
+    pub kind: EdgeKind,
+    pub t_a: f64,
+    pub t_b: f64,
+}
+
+impl EdgePair {
+    /// `t_b - t_a` - positive if `chan_b` lags behind
+    pub fn skew(&self) -> f64 {
+        self.t_b - self.t_a
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SkewReport {
+    pub chan_a: String,
+    pub chan_b: String,
+    pub pairs: Vec<EdgePair>,
+    /// Edges without a partner within `max_skew`
+    pub unmatched_a: Vec<Edge>,
+    pub unmatched_b: Vec<Edge>,
+}
+
+impl SkewReport {
+    /// Pairs up `edges_a` and `edges_b` (both sorted by time), see the [module docs](self)
+    pub fn new(spec: &SkewSpec, edges_a: Vec<Edge>, edges_b: Vec<Edge>) -> Self {
+        let mut used_b = vec![false; edges_b.len()];
+        // Pairs keep time order - a partner is never searched before the last matched edge of `chan_b`
+        let mut first_free = 0;
+        let mut pairs = Vec::new();
+        let mut unmatched_a = Vec::new();
+        for edge_a in edges_a {
+            let partner = (first_free..edges_b.len())
+                .find(|&idx| !used_b[idx] && edges_b[idx].kind == edge_a.kind)
+                .filter(|&idx| spec.max_skew.is_none_or(|max_skew| (edges_b[idx].t - edge_a.t).abs() <= max_skew));
+            match partner {
+                Some(idx) => {
+                    used_b[idx] = true;
+                    first_free = idx + 1;
+                    pairs.push(EdgePair { kind: edge_a.kind, t_a: edge_a.t, t_b: edges_b[idx].t })
+                },
+                None => unmatched_a.push(edge_a),
+            }
+        }
+        let unmatched_b = edges_b.into_iter().zip(used_b).filter(|(_edge, used)| !used).map(|(edge, _used)| edge).collect();
+        Self { chan_a: spec.chan_a.clone(), chan_b: spec.chan_b.clone(), pairs, unmatched_a, unmatched_b }
+    }
+
+    /// Largest skew by magnitude (with its sign), `None` if no edges were paired
+    pub fn max_skew(&self) -> Option<f64> {
+        self.pairs.iter().map(EdgePair::skew).max_by(|skew_a, skew_b| skew_a.abs().total_cmp(&skew_b.abs()))
+    }
+
+    pub fn mean_skew(&self) -> Option<f64> {
+        match self.pairs.len() {
+            0 => None,
+            n_pairs => Some(self.pairs.iter().map(EdgePair::skew).sum::<f64>() / n_pairs as f64),
+        }
+    }
+
+    /// All edges were paired up
+    pub fn is_complete(&self) -> bool {
+        self.unmatched_a.is_empty() && self.unmatched_b.is_empty()
+    }
+
+    pub fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let edge_tuple = |edge: &Edge| (format!("{:?}", edge.kind).to_lowercase(), edge.t);
+        let dict = PyDict::new_bound(py);
+        dict.set_item("chan_a", &self.chan_a)?;
+        dict.set_item("chan_b", &self.chan_b)?;
+        dict.set_item(
+            "pairs",
+            self.pairs.iter().map(|pair| (format!("{:?}", pair.kind).to_lowercase(), pair.t_a, pair.t_b, pair.skew())).collect::<Vec<_>>(),
+        )?;
+        dict.set_item("unmatched_a", self.unmatched_a.iter().map(edge_tuple).collect::<Vec<_>>())?;
+        dict.set_item("unmatched_b", self.unmatched_b.iter().map(edge_tuple).collect::<Vec<_>>())?;
+        dict.set_item("max_skew", self.max_skew())?;
+        dict.set_item("mean_skew", self.mean_skew())?;
+        Ok(dict)
+    }
+}
+
+impl Display for SkewReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Skew of {} relative to {}: {} edge pairs", self.chan_b, self.chan_a, self.pairs.len())?;
+        for pair in self.pairs.iter() {
+            writeln!(f, "    {:?} edge at {} s: {:+e} s", pair.kind, pair.t_a, pair.skew())?;
+        }
+        for (chan, edges) in [(&self.chan_a, &self.unmatched_a), (&self.chan_b, &self.unmatched_b)] {
+            for edge in edges {
+                writeln!(f, "    unmatched {:?} edge of {chan} at {} s", edge.kind, edge.t)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Best alignment of two channels found by cross-correlation, see the [module docs](self)
+#[derive(Clone, Debug, PartialEq)]
+pub struct RelativeDelay {
+    pub chan_a: String,
+    pub chan_b: String,
+    /// Rate of the common grid the channels were compared on
+    pub samp_rate: f64,
+    /// Lag in grid samples - positive if `chan_b` lags behind `chan_a`
+    pub lag_samps: isize,
+    /// Normalized cross-correlation at `lag_samps`, 1 for a perfect match
+    pub corr: f64,
+}
+
+impl RelativeDelay {
+    /// Lag in seconds - positive if `chan_b` lags behind `chan_a`
+    pub fn lag(&self) -> f64 {
+        self.lag_samps as f64 / self.samp_rate
+    }
+
+    pub fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("chan_a", &self.chan_a)?;
+        dict.set_item("chan_b", &self.chan_b)?;
+        dict.set_item("samp_rate", self.samp_rate)?;
+        dict.set_item("lag_samps", self.lag_samps)?;
+        dict.set_item("lag", self.lag())?;
+        dict.set_item("corr", self.corr)?;
+        Ok(dict)
+    }
+}
+
+impl Display for RelativeDelay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f, "{} lags {} by {:+e} s ({:+} samples at {} Hz, correlation {:.3})",
+            self.chan_b, self.chan_a, self.lag(), self.lag_samps, self.samp_rate, self.corr
+        )
+    }
+}
+
+/// Lag `lag` (`|lag| <= max_lag`) maximizing the normalized cross-correlation of `samps_b[i]` with `samps_a[i - lag]`
+/// over the overlapping samples, together with that correlation. Ties go to the smallest `|lag|`.
+///
+/// Returns `None` if either input is constant, so that no alignment is defined.
+pub fn best_lag(samps_a: &[f64], samps_b: &[f64], max_lag: usize) -> Option<(isize, f64)> {
+    let n_samps = samps_a.len().min(samps_b.len());
+    let centered = |samps: &[f64]| -> Option<Vec<f64>> {
+        let mean = samps[..n_samps].iter().sum::<f64>() / n_samps as f64;
+        let centered: Vec<f64> = samps[..n_samps].iter().map(|samp| samp - mean).collect();
+        let norm = centered.iter().map(|samp| samp * samp).sum::<f64>().sqrt();
+        (norm > 0.0).then(|| centered.into_iter().map(|samp| samp / norm).collect())
+    };
+    let (samps_a, samps_b) = (centered(samps_a)?, centered(samps_b)?);
+    let max_lag = max_lag.min(n_samps.saturating_sub(1)) as isize;
+    let corr_at = |lag: isize| -> f64 {
+        let shift = lag.unsigned_abs();
+        match lag >= 0 {
+            true => samps_a[..n_samps - shift].iter().zip(&samps_b[shift..]).map(|(a, b)| a * b).sum(),
+            false => samps_a[shift..].iter().zip(&samps_b[..n_samps - shift]).map(|(a, b)| a * b).sum(),
+        }
+    };
+    let mut best = (0, corr_at(0));
+    for shift in 1..=max_lag {
+        for lag in [-shift, shift] {
+            let corr = corr_at(lag);
+            if corr > best.1 {
+                best = (lag, corr)
+            }
+        }
+    }
+    Some(best)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::channel::BaseChan;
+    use crate::device::BaseDev;
+    use crate::error::StreamerError;
+    use crate::fn_lib_tools::StdFnLib;
+    use crate::mock::test_impls::TestStreamer;
+    use crate::streamer::BaseStreamer;
+
+    #[test]
+    fn measure_skew() {
+        let mut streamer = TestStreamer::new();
+        streamer.add_do_dev("DO", 1e3);
+        streamer.add_ao_dev("AO", 400.0);
+        streamer.do_devs["DO"].add_chan("trig", false);
+        streamer.ao_devs["AO"].add_chan("aom", 0.0);
+        for t in [0.1, 0.3] {
+            streamer.do_devs["DO"].chan_mut("trig").unwrap().constant(true, t, Some((0.05, false))).unwrap();
+        }
+        // The second pulse is one AO tick late, and an extra pulse is outside of `max_skew`
+        for t in [0.1, 0.3025, 0.4] {
+            streamer.ao_devs["AO"].chan_mut("aom").unwrap().constant(1.0, t, Some((0.05, false))).unwrap();
+        }
+        let spec = SkewSpec::new("DO/trig", "AO/aom", 0.0, 0.5).with_max_skew(0.01);
+        assert!(matches!(streamer.measure_skew(&spec), Err(StreamerError::NotCompiled { .. })));
+        streamer.compile(Some(0.5)).unwrap();
+
+        let report = streamer.measure_skew(&spec).unwrap();
+        assert_eq!(report.pairs.len(), 4);
+        assert_eq!((report.pairs[0].kind, report.pairs[1].kind), (EdgeKind::Rising, EdgeKind::Falling));
+        assert!(report.pairs[..2].iter().all(|pair| pair.skew().abs() < 1e-9));
+        assert!((report.max_skew().unwrap() - 2.5e-3).abs() < 1e-9);
+        assert!((report.mean_skew().unwrap() - 1.25e-3).abs() < 1e-9);
+        assert_eq!(report.unmatched_b.len(), 2);
+        assert!(report.unmatched_a.is_empty() && !report.is_complete());
+
+        // Trigger delays are part of the edge times
+        streamer.set_trigger_delay("AO", 0.005).unwrap();
+        streamer.compile(Some(0.5)).unwrap();
+        let report = streamer.measure_skew(&SkewSpec::new("DO/trig", "AO/aom", 0.0, 0.2)).unwrap();
+        assert_eq!(report.pairs.len(), 2);
+        assert!(report.pairs.iter().all(|pair| pair.skew().abs() < 1e-9));
+
+        assert!(matches!(streamer.measure_skew(&SkewSpec::new("DO/trig", "AO/nope", 0.0, 0.2)), Err(StreamerError::NotFound { .. })));
+        assert!(matches!(streamer.measure_skew(&SkewSpec::new("DO/trig", "AO/aom", 0.2, 0.1)), Err(StreamerError::InvalidArgument { .. })));
+    }
+
+    #[test]
+    fn relative_delay() {
+        let mut streamer = TestStreamer::new();
+        streamer.add_ao_dev("A", 1e3);
+        streamer.add_ao_dev("B", 2e3);
+        streamer.add_ao_dev("Flat", 1e3);
+        streamer.ao_devs["A"].add_chan("ao0", 0.0);
+        streamer.ao_devs["B"].add_chan("ao0", 0.0);
+        streamer.ao_devs["Flat"].add_chan("ao0", 0.0);
+        // The same 50 ms ramp, 3 ms later on B
+        let fn_lib = StdFnLib::new();
+        streamer.ao_devs["A"].chan_mut("ao0").unwrap().add_instr(fn_lib.LinFn(10.0, -1.0).unwrap().inner, 0.1, Some((0.05, false))).unwrap();
+        streamer.ao_devs["B"].chan_mut("ao0").unwrap().add_instr(fn_lib.LinFn(10.0, -1.03).unwrap().inner, 0.103, Some((0.05, false))).unwrap();
+        streamer.ao_devs["Flat"].chan_mut("ao0").unwrap().constant(1.0, 0.0, Some((0.1, false))).unwrap();
+        assert!(matches!(streamer.relative_delay("A/ao0", "B/ao0", (0.05, 0.25)), Err(StreamerError::NotCompiled { .. })));
+        streamer.compile(Some(0.3)).unwrap();
+
+        let delay = streamer.relative_delay("A/ao0", "B/ao0", (0.05, 0.25)).unwrap();
+        assert_eq!((delay.samp_rate, delay.lag_samps), (2e3, 6));
+        assert!((delay.lag() - 0.003).abs() < 1e-12 && delay.corr > 0.99);
+        assert_eq!(streamer.relative_delay("B/ao0", "A/ao0", (0.05, 0.25)).unwrap().lag_samps, -6);
+
+        // Compensating a trigger delay keeps the output timing
+        streamer.set_trigger_delay("A", 0.004).unwrap();
+        streamer.compile(Some(0.3)).unwrap();
+        assert_eq!(streamer.relative_delay("A/ao0", "B/ao0", (0.05, 0.25)).unwrap().lag_samps, 6);
+
+        assert!(matches!(streamer.relative_delay("A/ao0", "Flat/ao0", (0.15, 0.25)), Err(StreamerError::InvalidArgument { .. })));
+        assert!(matches!(streamer.relative_delay("A/ao0", "B/ao0", (0.1, 0.1)), Err(StreamerError::InvalidArgument { .. })));
+        assert!(matches!(streamer.relative_delay("A/ao0", "B", (0.0, 0.1)), Err(StreamerError::InvalidArgument { .. })));
+        assert!(matches!(streamer.relative_delay("A/ao0", "B/nope", (0.0, 0.1)), Err(StreamerError::NotFound { .. })));
+    }
+}
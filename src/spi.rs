@@ -0,0 +1,126 @@
+//! SPI transactions - clock, data, and chip-select waveforms for programming devices within the sequence.
+//!
+//! [`BaseDev::spi_transaction`] shifts one word out over three bool channels of a device with a single call.
+//! The waveforms are laid out on a grid of half bit periods `h = 1 / (2 * bit_rate)` starting at `t0`:
+//! - chip select (active low) falls at `t0` and rises again one bit period (`2h`) after the clock returned to idle,
+//!   i.e. at `t0 + (2*n_bits + 2)*h`;
+//! - the clock idles at `CPOL` and pulses once per bit, the leading edge at the middle of the bit slot `[t0 + 2*i*h, t0 + 2*(i+1)*h)`;
+//! - data bit `i` is set up at the start of its slot (`CPHA = 0`, sampled on the leading edge) or half a bit later
+//!   (`CPHA = 1`, sampled on the trailing edge).
+//!
+//! Each channel gets one [`BitPattern`] instruction covering `2*n_bits + 3` half bit periods, which ends on the idle level
+//! and keeps it. So the half bit period must be a whole number of clock ticks of the device.
+//!
+//! [`BaseDev::spi_transaction`]: crate::device::BaseDev::spi_transaction
+
+use std::fmt;
+use std::fmt::Display;
+use crate::fn_lib_tools::BitPattern;
+
+/// Clock polarity and phase, numbered as usual: `mode = 2*CPOL + CPHA`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SpiMode {
+    /// Clock idles low, data sampled on the rising edge
+    #[default]
+    Mode0,
+    /// Clock idles low, data sampled on the falling edge
+    Mode1,
+    /// Clock idles high, data sampled on the falling edge
+    Mode2,
+    /// Clock idles high, data sampled on the rising edge
+    Mode3,
+}
+
+impl SpiMode {
+    /// Clock idle level
+    pub fn cpol(&self) -> bool {
+        matches!(self, Self::Mode2 | Self::Mode3)
+    }
+    /// Whether data is sampled on the trailing (instead of the leading) clock edge
+    pub fn cpha(&self) -> bool {
+        matches!(self, Self::Mode1 | Self::Mode3)
+    }
+}
+
+impl Display for SpiMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SPI mode {} (CPOL={}, CPHA={})", 2 * self.cpol() as u8 + self.cpha() as u8, self.cpol() as u8, self.cpha() as u8)
+    }
+}
+
+/// Clock, data, and chip-select patterns shifting out `bits` (in order of output) from `t0`
+#[derive(Clone, Debug)]
+pub struct SpiPatterns {
+    pub clk: BitPattern,
+    pub data: BitPattern,
+    pub cs: BitPattern,
+    /// Length of the instructions carrying the patterns (in seconds)
+    pub dur: f64,
+}
+
+impl SpiPatterns {
+    pub fn new(t0: f64, bits: &[bool], bit_rate: f64, mode: SpiMode) -> Self {
+        let half_period = 0.5 / bit_rate;
+        let n_bits = bits.len();
+        let clk_bits: Vec<bool> = (0..n_bits).flat_map(|_| [mode.cpol(), !mode.cpol()]).collect();
+        Self {
+            clk: BitPattern::new(t0, half_period, clk_bits, mode.cpol()),
+            data: BitPattern::new(t0 + mode.cpha() as u8 as f64 * half_period, 2.0 * half_period, bits.to_vec(), false),
+            cs: BitPattern::new(t0, (2 * n_bits + 2) as f64 * half_period, vec![false], true),
+            dur: (2 * n_bits + 3) as f64 * half_period,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::channel::BaseChan;
+    use crate::device::BaseDev;
+    use crate::error::StreamerError;
+    use crate::mock::test_impls::TestDev;
+
+    /// Levels of `chan_name` at every tick in `[0, n_samps)`
+    fn levels(dev: &TestDev<bool>, chan_name: &str, n_samps: usize) -> Vec<u8> {
+        (0..n_samps).map(|pos| dev.chan(chan_name).unwrap().eval_point(pos as f64 * 1e-3).unwrap() as u8).collect()
+    }
+
+    #[test]
+    fn transaction() {
+        let mut dev = TestDev::<bool>::new("DO", 1e3);
+        for chan_name in ["sclk", "mosi", "cs"] {
+            dev.add_chan(chan_name, chan_name == "cs");
+        }
+        // Word 0b101 at 500 bit/s: half bit period is 1 tick
+        dev.spi_transaction("sclk", "mosi", "cs", 0.002, &[true, false, true], 500.0, SpiMode::Mode0).unwrap();
+        dev.compile(0.012).unwrap();
+        assert_eq!(levels(&dev, "sclk", 12), [0, 0, 0, 1, 0, 1, 0, 1, 0, 0, 0, 0]);
+        assert_eq!(levels(&dev, "mosi", 12), [0, 0, 1, 1, 0, 0, 1, 1, 0, 0, 0, 0]);
+        assert_eq!(levels(&dev, "cs", 12), [1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1]);
+
+        // Mode 3 - inverted clock, data half a bit later
+        dev.clear_edit_cache();
+        dev.spi_transaction("sclk", "mosi", "cs", 0.002, &[true, false, true], 500.0, SpiMode::Mode3).unwrap();
+        dev.compile(0.012).unwrap();
+        assert_eq!(levels(&dev, "sclk", 12), [0, 0, 1, 0, 1, 0, 1, 0, 1, 1, 1, 1]);
+        assert_eq!(levels(&dev, "mosi", 12), [0, 0, 0, 1, 1, 0, 0, 1, 1, 0, 0, 0]);
+        assert_eq!(SpiMode::Mode3.to_string(), "SPI mode 3 (CPOL=1, CPHA=1)");
+
+        // Collision on the chip select line - no channel is changed
+        dev.clear_edit_cache();
+        dev.chan_mut("cs").unwrap().constant(true, 0.005, Some((0.002, true))).unwrap();
+        assert!(matches!(
+            dev.spi_transaction("sclk", "mosi", "cs", 0.002, &[true, false, true], 500.0, SpiMode::Mode0),
+            Err(StreamerError::Collision { .. })
+        ));
+        assert!(dev.chan("sclk").unwrap().instr_list().is_empty() && dev.chan("mosi").unwrap().instr_list().is_empty());
+
+        // Half bit period off the clock grid, repeated channel, empty word
+        let spi = |dev: &mut TestDev<bool>, clk: &str, bits: &[bool], bit_rate: f64| {
+            dev.spi_transaction(clk, "mosi", "cs", 0.02, bits, bit_rate, SpiMode::Mode0)
+        };
+        assert!(matches!(spi(&mut dev, "sclk", &[true], 300.0), Err(StreamerError::InvalidArgument { .. })));
+        assert!(matches!(spi(&mut dev, "mosi", &[true], 500.0), Err(StreamerError::InvalidArgument { .. })));
+        assert!(matches!(spi(&mut dev, "sclk", &[], 500.0), Err(StreamerError::InvalidArgument { .. })));
+    }
+}
@@ -1,22 +1,174 @@
-use crate::device::BaseDev;
+use std::any::{Any, TypeId};
+use indexmap::IndexMap;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use crate::channel::{BaseChan, ChanSampCursor, DurDefaults, Event, Events, samp_to_f64};
+use crate::device::{ActivityWindows, BaseDev, DevEditState, DevPlotData};
+use crate::diff::{DiffReport, InstrSnapshot, diff_devs};
+use crate::hash::StableHasher;
+use crate::error::{ErrCtx, StreamerError};
+use crate::validation::{DevReport, StreamerReport};
+use crate::options::CompileOptions;
+use crate::diagnostics::Diagnostic;
+use crate::profiling::{ProfileEntry, ProfileReport};
+use crate::marker::{Marker, MarkerRule};
+use crate::fn_lib_tools::{FnArgs, FnRegistry, FnTraitSet};
+use crate::openpulse::PulseQobj;
+use crate::collisions::CollisionReport;
+use crate::schedule::{self, ChanSchedule, DevSchedule, InstrSchedule, ScheduleDoc, ScheduleRow, SCHEDULE_JSON_VERSION};
+use crate::inspect::DevInfo;
+use crate::summary::{DevSummary, StreamerSummary};
+use crate::budget::{DevBudget, DevPrediction, SampPrediction, StreamBudget};
+use crate::dry_run::{DevDryRun, DryRunReport};
+use crate::events::{Observer, ObserverId, Observers, StreamerEvent};
+use crate::timeline::Timeline;
+use crate::rules::{self, Segment, StreamerView, ValidationRule};
+use crate::selection::StreamSelection;
+use crate::abort::AbortSpec;
+use crate::padding::DevPadding;
+use crate::sync::{SyncSpec, sync_problems};
+use crate::skew::{best_lag, Edge, RelativeDelay, SkewReport, SkewSpec};
+use crate::rounding::TickRounding;
+use crate::closing_edge::ClosingEdge;
+use crate::spi::SpiMode;
+use crate::resample::Resampling;
+use crate::analysis::{Crossing, Peak};
+use crate::idle::DevIdle;
+use crate::export;
+use crate::shots::ShotSequence;
 
 /// Type-agnostic ("Tag") `BaseDevice` trait - set of methods which are not aware of the device's
 /// actual sample or channel types. `BaseStreamer` trait is only using these methods allowing for
 /// devices of different types being treated uniformly - as `dyn TagBaseDev` trait objects.
-pub trait TagBaseDev {
+pub trait TagBaseDev: Send {
     fn tag_name(&self) -> String;
     fn tag_samp_rate(&self) -> f64;
     fn tag_got_instructions(&self) -> bool;
     fn tag_last_instr_end_time(&self) -> Option<f64>;
-    fn tag_compile(&mut self, stop_time: f64) -> Result<(), String>;
+    fn tag_first_instr_start_time(&self) -> Option<f64>;
+    fn tag_activity_windows(&self) -> ActivityWindows;
+    fn tag_instr_count(&self) -> usize;
+    fn tag_segment_densities(&self) -> Result<IndexMap<String, f64>, StreamerError>;
+    fn tag_padding_report(&self) -> Result<DevPadding, StreamerError>;
+    fn tag_idle_map(&self, min_samps: usize) -> Result<DevIdle, StreamerError>;
+    fn tag_sync_spec(&self) -> Option<SyncSpec>;
+    /// Start trigger delay in seconds, 0 if the device doesn't support one
+    fn tag_trigger_delay(&self) -> f64;
+    fn tag_set_trigger_delay(&mut self, delay: f64) -> Result<(), StreamerError>;
+    fn tag_set_tick_rounding(&mut self, rounding: TickRounding) -> Result<(), StreamerError>;
+    fn tag_set_closing_edge(&mut self, policy: ClosingEdge) -> Result<(), StreamerError>;
+    fn tag_edges(&self, chan_name: &str, threshold: f64) -> Result<Vec<Edge>, StreamerError>;
+    fn tag_compile(&mut self, stop_time: f64) -> Result<(), StreamerError>;
+    fn tag_compile_with(&mut self, stop_time: f64, opts: &CompileOptions) -> Result<(), StreamerError>;
+    fn tag_samp_count(&self, stop_time: f64) -> Result<Option<usize>, StreamerError>;
     fn tag_clear_edit_cache(&mut self);
+    fn tag_clear_chan_edit_cache(&mut self, chan_name: &str) -> Result<(), StreamerError>;
+    fn tag_set_chan_enabled(&mut self, chan_name: &str, enabled: bool) -> Result<(), StreamerError>;
+    fn tag_set_chan_hold_last_val(&mut self, chan_name: &str, hold: bool) -> Result<(), StreamerError>;
+    fn tag_set_chan_dur_defaults(&mut self, chan_name: &str, defaults: DurDefaults) -> Result<(), StreamerError>;
     fn tag_clear_compile_cache(&mut self);
-    fn tag_validate_compile_cache(&self) -> Result<(), String>;
+    fn tag_validate_compile_cache(&self) -> Result<(), StreamerError>;
+    fn tag_validation_report(&self) -> DevReport;
+    fn tag_info(&self) -> DevInfo;
+    fn tag_summary(&self) -> DevSummary;
+    fn tag_stream_budget(&self) -> Result<DevBudget, StreamerError>;
+    fn tag_predicted_budget(&self, stop_time: f64) -> Result<DevPrediction, StreamerError>;
+    fn tag_check_finite(&self, max_samps_per_seg: Option<usize>) -> Result<(), StreamerError>;
     fn tag_compiled_stop_time(&self) -> f64;
-    fn tag_add_reset_instr(&mut self, reset_time: f64) -> Result<(), String>;
+    fn tag_try_compiled_stop_time(&self) -> Result<f64, StreamerError>;
+    fn tag_add_reset_instr(&mut self, reset_time: f64) -> Result<(), StreamerError>;
+    fn tag_safe_state(&self) -> IndexMap<String, f64>;
+    fn tag_compile_safe_state(&mut self, dur: f64) -> Result<(), StreamerError>;
+    /// [`BaseDev::edit_state`] as a type-erased box
+    fn tag_edit_state(&self) -> Box<dyn Any>;
+    /// [`BaseDev::restore_edit_state`] for a box returned by [`TagBaseDev::tag_edit_state`] of the same device
+    fn tag_restore_edit_state(&mut self, state: Box<dyn Any>);
+    fn tag_compile_abort(&mut self, spec: &AbortSpec) -> Result<(), StreamerError>;
+    fn tag_marker_intervals(&self, chan_name: &str, rule: &MarkerRule) -> Result<Vec<(f64, f64)>, StreamerError>;
+    fn tag_write_marker(&mut self, chan_name: &str, intervals: &[(f64, f64)]) -> Result<(), StreamerError>;
+    /// [`BaseDev::add_edges`] on channel `chan_name`
+    fn tag_add_edges(&mut self, chan_name: &str, edges: &[(f64, bool)]) -> Result<(), StreamerError>;
+    /// [`BaseDev::add_counter`] on channels `chan_names`
+    fn tag_add_counter(&mut self, chan_names: &[&str], t: f64, dur: f64, period: f64, gray: bool, keep_val: bool) -> Result<(), StreamerError>;
+    /// [`BaseDev::spi_transaction`] on channels `clk_chan`, `data_chan`, and `cs_chan`
+    #[allow(clippy::too_many_arguments)]
+    fn tag_spi_transaction(
+        &mut self, clk_chan: &str, data_chan: &str, cs_chan: &str, t0: f64, word: &[bool], bit_rate: f64, mode: SpiMode
+    ) -> Result<(), StreamerError>;
+    fn tag_has_preset(&self, name: &str) -> bool;
+    fn tag_add_preset_instr(&mut self, name: &str, t: f64) -> Result<(), StreamerError>;
+    /// Adds an instruction with function `func_name` built by `registry` for the channel's sample type
+    fn tag_add_instr_by_name(
+        &mut self, chan_name: &str, registry: &FnRegistry, func_name: &str, args: &FnArgs, t: f64, dur_spec: Option<(f64, bool)>
+    ) -> Result<(), StreamerError>;
+    /// [`BaseChan::add_pulse_array_by_name`] on channel `chan_name` with functions built for the channel's sample type
+    #[allow(clippy::too_many_arguments)]
+    fn tag_add_pulse_array_by_name(
+        &mut self, chan_name: &str, registry: &FnRegistry, func_name: &str, args: &FnArgs, overrides: &IndexMap<String, Vec<f64>>,
+        start_times: &[f64], dur: f64, keep_val: bool
+    ) -> Result<(), StreamerError>;
+    /// [`BaseDev::add_pulse_array`] with the template function type-erased as `Box<dyn FnTraitSet<Samp>>`
+    fn tag_add_pulse_array(&mut self, chan_name: &str, func: Box<dyn Any>, start_times: &[f64], dur: f64, keep_val: bool) -> Result<(), StreamerError>;
+    fn tag_add_to_pulse_qobj(&self, qobj: &mut PulseQobj) -> Result<(), StreamerError>;
+    fn tag_collision_report(&self, batch: &IndexMap<String, Vec<InstrSnapshot>>) -> Result<CollisionReport, StreamerError>;
+    /// Returns the `MockStreamTarget<Samp>` produced by [`BaseDev::run_mock`] as a type-erased box
+    fn tag_run_mock(&self, chunk_samps: usize) -> Result<Box<dyn Any>, StreamerError>;
+    /// Same as [`TagBaseDev::tag_run_mock`], see [`BaseDev::run_mock_masked`]
+    fn tag_run_mock_masked(&self, chunk_samps: usize, masked: &[String]) -> Result<Box<dyn Any>, StreamerError>;
+    fn tag_dry_run(&self, chunk_samps: usize) -> Result<DevDryRun, StreamerError>;
+    fn tag_chan_names(&self) -> Vec<String>;
+    fn tag_chan_tick_rounding(&self, chan_name: &str) -> Result<TickRounding, StreamerError>;
+    fn tag_content_hash(&self) -> Result<u64, StreamerError>;
+    fn tag_check_can_shift(&self, dt: f64) -> Result<(), StreamerError>;
+    fn tag_shift(&mut self, dt: f64) -> Result<(), StreamerError>;
+    fn tag_crop(&mut self, t_start: f64, t_end: f64) -> Result<(), StreamerError>;
+    /// Copies instructions from `other`, which must be a device of the same concrete type. See [`BaseDev::copy_instrs_from`].
+    fn tag_copy_instrs_from(&mut self, other: &dyn TagBaseDev) -> Result<(), StreamerError>;
+    /// Same as [`TagBaseDev::tag_copy_instrs_from`] with the copies moved later by `dt`. See [`BaseDev::copy_instrs_from_shifted`].
+    fn tag_copy_instrs_from_shifted(&mut self, other: &dyn TagBaseDev, dt: f64) -> Result<(), StreamerError>;
+    /// Checks that [`TagBaseDev::tag_copy_instrs_from_shifted`] can copy from `other`: same device type and [`BaseDev::check_can_copy_from`]
+    fn tag_check_can_copy_from(&self, other: &dyn TagBaseDev, dt: f64) -> Result<(), StreamerError>;
+    fn tag_as_any(&self) -> &dyn Any;
+    fn tag_diagnostics(&self) -> Vec<Diagnostic>;
+    fn tag_profile_entries(&self) -> Vec<ProfileEntry>;
+    fn tag_clear_profile(&self);
+    /// Channel name -> type-agnostic snapshots of its edit-cache instructions (includes channels without instructions)
+    fn tag_instr_snapshots(&self) -> IndexMap<String, Vec<InstrSnapshot>>;
+    /// Same as [`BaseChan::calc_nsamps`] with samples converted to `f64`
+    fn tag_calc_nsamps(&self, chan_name: &str, n_samps: usize, start_time: Option<f64>, end_time: Option<f64>) -> Result<Vec<f64>, StreamerError>;
+    /// [`BaseChan::plot_data`] of channel `chan_name` with samples converted to `f64`
+    fn tag_chan_plot_data(&self, chan_name: &str, n_samps: usize, start_time: Option<f64>, end_time: Option<f64>) -> Result<(Vec<f64>, Vec<f64>), StreamerError>;
+    fn tag_plot_data(&self, n_samps: usize, start_time: Option<f64>, end_time: Option<f64>) -> Result<DevPlotData, StreamerError>;
+    /// Compile cache segments of channel `chan_name`, see [`crate::rules::Segment`]
+    fn tag_compiled_segments(&self, chan_name: &str) -> Result<Vec<Segment>, StreamerError>;
+    /// Compiled samples of the ticks `[start_pos, end_pos)` of channel `chan_name` converted to `f64`
+    fn tag_calc_chan_samps(&self, chan_name: &str, start_pos: usize, end_pos: usize) -> Result<Vec<f64>, StreamerError>;
+    /// [`BaseChan::resample`] of channel `chan_name`
+    fn tag_resample_chan(&self, chan_name: &str, to_rate: f64, method: Resampling) -> Result<Vec<f64>, StreamerError>;
+    /// [`BaseChan::find_edges`] of channel `chan_name`
+    fn tag_find_edges(&self, chan_name: &str, threshold: f64) -> Result<Vec<Crossing>, StreamerError>;
+    /// [`BaseChan::find_peaks`] of channel `chan_name`
+    fn tag_find_peaks(&self, chan_name: &str, min_height: f64, min_spacing: f64) -> Result<Vec<Peak>, StreamerError>;
+    /// [`BaseChan::integral`] of channel `chan_name`
+    fn tag_integral(&self, chan_name: &str, start_time: Option<f64>, end_time: Option<f64>) -> Result<f64, StreamerError>;
+    /// [`BaseChan::compiled_events`] of all active channels (by name) for devices with `bool` samples, `None` for other sample types
+    fn tag_bool_events(&self) -> Result<Option<IndexMap<String, Events<bool>>>, StreamerError>;
+    /// Records `entry` in the diagnostics sink of the device
+    fn tag_push_diagnostic(&mut self, entry: Diagnostic);
+    /// Same as [`TagBaseDev::tag_calc_nsamps`] with `n_samps = res_arr.len()`, writing into caller-provided memory.
+    /// Channels with `f64` samples write directly into `res_arr`, other sample types go through a temporary buffer.
+    fn tag_calc_nsamps_into(&self, chan_name: &str, res_arr: &mut [f64], start_time: Option<f64>, end_time: Option<f64>) -> Result<(), StreamerError>;
 }
 
-impl<D: BaseDev> TagBaseDev for D {
+/// `other` as a device of the same type as `dev` - instructions can only be copied between devices of the same type
+fn same_type_dev<'a, D: BaseDev + 'static>(dev: &D, other: &'a dyn TagBaseDev) -> Result<&'a D, StreamerError> {
+    other.tag_as_any().downcast_ref::<D>().ok_or_else(|| StreamerError::Incompatible {
+        ctx: ErrCtx::dev(dev.name()),
+        msg: format!("cannot copy instructions from device {} of a different type", other.tag_name()),
+    })
+}
+
+impl<D: BaseDev + 'static> TagBaseDev for D {
     fn tag_name(&self) -> String {
         self.name()
     }
@@ -33,19 +185,119 @@ impl<D: BaseDev> TagBaseDev for D {
         self.last_instr_end_time()
     }
 
-    fn tag_compile(&mut self, stop_time: f64) -> Result<(), String> {
+    fn tag_first_instr_start_time(&self) -> Option<f64> {
+        self.first_instr_start_time()
+    }
+
+    fn tag_activity_windows(&self) -> ActivityWindows {
+        self.activity_windows()
+    }
+
+    fn tag_instr_count(&self) -> usize {
+        self.instr_count()
+    }
+
+    fn tag_segment_densities(&self) -> Result<IndexMap<String, f64>, StreamerError> {
+        self.segment_densities()
+    }
+
+    fn tag_padding_report(&self) -> Result<DevPadding, StreamerError> {
+        self.padding_report()
+    }
+
+    fn tag_idle_map(&self, min_samps: usize) -> Result<DevIdle, StreamerError> {
+        self.idle_map(min_samps)
+    }
+
+    fn tag_sync_spec(&self) -> Option<SyncSpec> {
+        self.sync_spec().cloned()
+    }
+
+    fn tag_trigger_delay(&self) -> f64 {
+        self.trigger_delay()
+    }
+
+    fn tag_set_trigger_delay(&mut self, delay: f64) -> Result<(), StreamerError> {
+        self.set_trigger_delay(delay)
+    }
+
+    fn tag_set_tick_rounding(&mut self, rounding: TickRounding) -> Result<(), StreamerError> {
+        self.set_tick_rounding(rounding)
+    }
+
+    fn tag_set_closing_edge(&mut self, policy: ClosingEdge) -> Result<(), StreamerError> {
+        self.set_closing_edge(policy)
+    }
+
+    fn tag_edges(&self, chan_name: &str, threshold: f64) -> Result<Vec<Edge>, StreamerError> {
+        self.edges(chan_name, threshold)
+    }
+
+    fn tag_compile(&mut self, stop_time: f64) -> Result<(), StreamerError> {
         self.compile(stop_time)
     }
 
+    fn tag_compile_with(&mut self, stop_time: f64, opts: &CompileOptions) -> Result<(), StreamerError> {
+        self.compile_with(stop_time, opts)
+    }
+
+    fn tag_samp_count(&self, stop_time: f64) -> Result<Option<usize>, StreamerError> {
+        self.samp_count(stop_time)
+    }
+
     fn tag_clear_edit_cache(&mut self) {
         self.clear_edit_cache()
     }
 
+    fn tag_clear_chan_edit_cache(&mut self, chan_name: &str) -> Result<(), StreamerError> {
+        self.chan_mut(chan_name)?.clear_edit_cache();
+        Ok(())
+    }
+
+    fn tag_set_chan_enabled(&mut self, chan_name: &str, enabled: bool) -> Result<(), StreamerError> {
+        let dev_name = self.name();
+        self.chan_mut(chan_name)?.set_enabled(enabled).map_err(|err| err.in_dev(dev_name))
+    }
+
+    fn tag_set_chan_hold_last_val(&mut self, chan_name: &str, hold: bool) -> Result<(), StreamerError> {
+        let dev_name = self.name();
+        self.chan_mut(chan_name)?.set_hold_last_val(hold).map_err(|err| err.in_dev(dev_name))
+    }
+
+    fn tag_set_chan_dur_defaults(&mut self, chan_name: &str, defaults: DurDefaults) -> Result<(), StreamerError> {
+        let dev_name = self.name();
+        self.chan_mut(chan_name)?.set_dur_defaults(defaults).map_err(|err| err.in_dev(dev_name))
+    }
+
     fn tag_clear_compile_cache(&mut self) {
         self.clear_compile_cache()
     }
 
-    fn tag_validate_compile_cache(&self) -> Result<(), String> {
+    fn tag_validation_report(&self) -> DevReport {
+        self.validation_report()
+    }
+
+    fn tag_info(&self) -> DevInfo {
+        self.info()
+    }
+
+    fn tag_summary(&self) -> DevSummary {
+        self.summary()
+    }
+
+    fn tag_stream_budget(&self) -> Result<DevBudget, StreamerError> {
+        self.stream_budget()
+    }
+
+    fn tag_predicted_budget(&self, stop_time: f64) -> Result<DevPrediction, StreamerError> {
+        self.predicted_budget(stop_time)
+    }
+
+    fn tag_check_finite(&self, max_samps_per_seg: Option<usize>) -> Result<(), StreamerError> {
+        self.check_finite(max_samps_per_seg)
+    }
+
+    fn tag_validate_compile_cache(&self) -> Result<(), StreamerError> {
         self.validate_compile_cache()
     }
 
@@ -53,19 +305,285 @@ impl<D: BaseDev> TagBaseDev for D {
         self.compiled_stop_time()
     }
 
-    fn tag_add_reset_instr(&mut self, reset_time: f64) -> Result<(), String> {
+    fn tag_try_compiled_stop_time(&self) -> Result<f64, StreamerError> {
+        self.try_compiled_stop_time()
+    }
+
+    fn tag_add_reset_instr(&mut self, reset_time: f64) -> Result<(), StreamerError> {
         self.add_reset_instr(reset_time)
     }
+
+    fn tag_safe_state(&self) -> IndexMap<String, f64> {
+        self.safe_state()
+    }
+
+    fn tag_compile_safe_state(&mut self, dur: f64) -> Result<(), StreamerError> {
+        self.compile_safe_state(dur)
+    }
+
+    fn tag_edit_state(&self) -> Box<dyn Any> {
+        Box::new(self.edit_state())
+    }
+
+    fn tag_restore_edit_state(&mut self, state: Box<dyn Any>) {
+        let state = state.downcast::<DevEditState<<D::Chan as BaseChan>::Samp>>().expect("edit state of a different device type");
+        self.restore_edit_state(*state)
+    }
+
+    fn tag_compile_abort(&mut self, spec: &AbortSpec) -> Result<(), StreamerError> {
+        self.compile_abort(spec)
+    }
+
+    fn tag_marker_intervals(&self, chan_name: &str, rule: &MarkerRule) -> Result<Vec<(f64, f64)>, StreamerError> {
+        self.marker_intervals(chan_name, rule)
+    }
+
+    fn tag_write_marker(&mut self, chan_name: &str, intervals: &[(f64, f64)]) -> Result<(), StreamerError> {
+        self.write_marker(chan_name, intervals)
+    }
+
+    fn tag_add_edges(&mut self, chan_name: &str, edges: &[(f64, bool)]) -> Result<(), StreamerError> {
+        self.add_edges(chan_name, edges)
+    }
+
+    fn tag_add_counter(&mut self, chan_names: &[&str], t: f64, dur: f64, period: f64, gray: bool, keep_val: bool) -> Result<(), StreamerError> {
+        self.add_counter(chan_names, t, dur, period, gray, keep_val)
+    }
+
+    fn tag_spi_transaction(
+        &mut self, clk_chan: &str, data_chan: &str, cs_chan: &str, t0: f64, word: &[bool], bit_rate: f64, mode: SpiMode
+    ) -> Result<(), StreamerError> {
+        self.spi_transaction(clk_chan, data_chan, cs_chan, t0, word, bit_rate, mode)
+    }
+
+    fn tag_has_preset(&self, name: &str) -> bool {
+        self.has_preset(name)
+    }
+
+    fn tag_add_preset_instr(&mut self, name: &str, t: f64) -> Result<(), StreamerError> {
+        self.add_preset_instr(name, t)
+    }
+
+    fn tag_add_instr_by_name(
+        &mut self, chan_name: &str, registry: &FnRegistry, func_name: &str, args: &FnArgs, t: f64, dur_spec: Option<(f64, bool)>
+    ) -> Result<(), StreamerError> {
+        let dev_name = self.name();
+        let func = registry.build::<<D::Chan as BaseChan>::Samp>(func_name, args).map_err(|err| err.in_dev(dev_name.clone()))?;
+        self.chan_mut(chan_name)?.add_instr(func, t, dur_spec).map_err(|err| err.in_dev(dev_name))
+    }
+
+    fn tag_add_pulse_array_by_name(
+        &mut self, chan_name: &str, registry: &FnRegistry, func_name: &str, args: &FnArgs, overrides: &IndexMap<String, Vec<f64>>,
+        start_times: &[f64], dur: f64, keep_val: bool
+    ) -> Result<(), StreamerError> {
+        let dev_name = self.name();
+        self.chan_mut(chan_name)?
+            .add_pulse_array_by_name(registry, func_name, args, overrides, start_times, dur, keep_val)
+            .map_err(|err| err.in_dev(dev_name))
+    }
+
+    fn tag_add_pulse_array(&mut self, chan_name: &str, func: Box<dyn Any>, start_times: &[f64], dur: f64, keep_val: bool) -> Result<(), StreamerError> {
+        let Ok(func) = func.downcast::<Box<dyn FnTraitSet<<D::Chan as BaseChan>::Samp>>>() else {
+            return Err(StreamerError::Incompatible {
+                ctx: ErrCtx::dev(self.name()),
+                msg: format!(
+                    "pulse array function for channel {chan_name} must have {} samples",
+                    std::any::type_name::<<D::Chan as BaseChan>::Samp>()
+                ),
+            })
+        };
+        self.add_pulse_array(chan_name, *func, start_times, dur, keep_val)
+    }
+
+    fn tag_add_to_pulse_qobj(&self, qobj: &mut PulseQobj) -> Result<(), StreamerError> {
+        self.add_to_pulse_qobj(qobj)
+    }
+
+    fn tag_collision_report(&self, batch: &IndexMap<String, Vec<InstrSnapshot>>) -> Result<CollisionReport, StreamerError> {
+        self.collision_report(batch)
+    }
+
+    fn tag_run_mock(&self, chunk_samps: usize) -> Result<Box<dyn Any>, StreamerError> {
+        Ok(Box::new(self.run_mock(chunk_samps)?))
+    }
+
+    fn tag_run_mock_masked(&self, chunk_samps: usize, masked: &[String]) -> Result<Box<dyn Any>, StreamerError> {
+        Ok(Box::new(self.run_mock_masked(chunk_samps, masked)?))
+    }
+
+    fn tag_dry_run(&self, chunk_samps: usize) -> Result<DevDryRun, StreamerError> {
+        self.dry_run(chunk_samps)
+    }
+
+    fn tag_chan_names(&self) -> Vec<String> {
+        self.chans().iter().map(|chan| chan.name()).collect()
+    }
+
+    fn tag_chan_tick_rounding(&self, chan_name: &str) -> Result<TickRounding, StreamerError> {
+        Ok(self.chan(chan_name)?.tick_rounding())
+    }
+
+    fn tag_content_hash(&self) -> Result<u64, StreamerError> {
+        self.content_hash()
+    }
+
+    fn tag_check_can_shift(&self, dt: f64) -> Result<(), StreamerError> {
+        self.check_can_shift(dt)
+    }
+
+    fn tag_shift(&mut self, dt: f64) -> Result<(), StreamerError> {
+        self.shift(dt)
+    }
+
+    fn tag_crop(&mut self, t_start: f64, t_end: f64) -> Result<(), StreamerError> {
+        self.crop(t_start, t_end)
+    }
+
+    fn tag_copy_instrs_from(&mut self, other: &dyn TagBaseDev) -> Result<(), StreamerError> {
+        self.tag_copy_instrs_from_shifted(other, 0.0)
+    }
+
+    fn tag_copy_instrs_from_shifted(&mut self, other: &dyn TagBaseDev, dt: f64) -> Result<(), StreamerError> {
+        let other = same_type_dev(self, other)?;
+        self.copy_instrs_from_shifted(other, dt)
+    }
+
+    fn tag_check_can_copy_from(&self, other: &dyn TagBaseDev, dt: f64) -> Result<(), StreamerError> {
+        self.check_can_copy_from(same_type_dev(self, other)?, dt)
+    }
+
+    fn tag_as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn tag_diagnostics(&self) -> Vec<Diagnostic> {
+        self.collect_diagnostics()
+    }
+
+    fn tag_profile_entries(&self) -> Vec<ProfileEntry> {
+        self.profile_entries()
+    }
+
+    fn tag_clear_profile(&self) {
+        self.clear_profile()
+    }
+
+    fn tag_instr_snapshots(&self) -> IndexMap<String, Vec<InstrSnapshot>> {
+        self.chans()
+            .iter()
+            .map(|chan| (chan.name(), chan.instr_list().iter().map(InstrSnapshot::from).collect()))
+            .collect()
+    }
+
+    fn tag_calc_nsamps(&self, chan_name: &str, n_samps: usize, start_time: Option<f64>, end_time: Option<f64>) -> Result<Vec<f64>, StreamerError> {
+        let samps = self.chan(chan_name)?.calc_nsamps(n_samps, start_time, end_time)?;
+        Ok(samps.iter().map(samp_to_f64).collect())
+    }
+
+    fn tag_chan_plot_data(&self, chan_name: &str, n_samps: usize, start_time: Option<f64>, end_time: Option<f64>) -> Result<(Vec<f64>, Vec<f64>), StreamerError> {
+        let (t_arr, samps) = self.chan(chan_name)?.plot_data(n_samps, start_time, end_time).map_err(|err| err.in_dev(self.name()))?;
+        Ok((t_arr, samps.iter().map(samp_to_f64).collect()))
+    }
+
+    fn tag_plot_data(&self, n_samps: usize, start_time: Option<f64>, end_time: Option<f64>) -> Result<DevPlotData, StreamerError> {
+        self.plot_data(n_samps, start_time, end_time)
+    }
+
+    fn tag_compiled_segments(&self, chan_name: &str) -> Result<Vec<Segment>, StreamerError> {
+        let chan = self.chan(chan_name)?;
+        chan.validate_compile_cache().map_err(|err| err.in_dev(self.name()))?;
+        let mut start_pos = 0;
+        Ok(chan.compile_cache_ends().iter().zip(chan.compile_cache_fns().iter()).map(|(&end_pos, func)| {
+            let seg = Segment { start_pos, end_pos, const_val: func.const_val().map(|val| samp_to_f64(&val)) };
+            start_pos = end_pos;
+            seg
+        }).collect())
+    }
+
+    fn tag_calc_chan_samps(&self, chan_name: &str, start_pos: usize, end_pos: usize) -> Result<Vec<f64>, StreamerError> {
+        let chan = self.chan(chan_name)?;
+        let mut samps = vec![chan.dflt_val(); end_pos.saturating_sub(start_pos)];
+        chan.fill_samps_from_ticks(&mut ChanSampCursor::new(), start_pos, &mut samps).map_err(|err| err.in_dev(self.name()))?;
+        Ok(samps.iter().map(samp_to_f64).collect())
+    }
+
+    fn tag_resample_chan(&self, chan_name: &str, to_rate: f64, method: Resampling) -> Result<Vec<f64>, StreamerError> {
+        self.chan(chan_name)?.resample(to_rate, method).map_err(|err| err.in_dev(self.name()))
+    }
+
+    fn tag_find_edges(&self, chan_name: &str, threshold: f64) -> Result<Vec<Crossing>, StreamerError> {
+        self.chan(chan_name)?.find_edges(threshold).map_err(|err| err.in_dev(self.name()))
+    }
+
+    fn tag_find_peaks(&self, chan_name: &str, min_height: f64, min_spacing: f64) -> Result<Vec<Peak>, StreamerError> {
+        self.chan(chan_name)?.find_peaks(min_height, min_spacing).map_err(|err| err.in_dev(self.name()))
+    }
+
+    fn tag_integral(&self, chan_name: &str, start_time: Option<f64>, end_time: Option<f64>) -> Result<f64, StreamerError> {
+        self.chan(chan_name)?.integral(start_time, end_time).map_err(|err| err.in_dev(self.name()))
+    }
+
+    fn tag_bool_events(&self) -> Result<Option<IndexMap<String, Events<bool>>>, StreamerError> {
+        type Samp<D> = <<D as BaseDev>::Chan as BaseChan>::Samp;
+        if TypeId::of::<Samp<D>>() != TypeId::of::<bool>() {
+            return Ok(None)
+        }
+        let mut res = IndexMap::new();
+        for chan in self.active_chans() {
+            let events: Box<dyn Any> = Box::new(chan.compiled_events().map_err(|err| err.in_dev(self.name()))?);
+            // Can't fail - `Samp` is `bool`
+            let events = events.downcast::<Events<bool>>().expect("sample type checked above");
+            res.insert(chan.name(), *events);
+        }
+        Ok(Some(res))
+    }
+
+    fn tag_push_diagnostic(&mut self, entry: Diagnostic) {
+        self.diagnostics_mut().push(entry)
+    }
+
+    fn tag_calc_nsamps_into(&self, chan_name: &str, res_arr: &mut [f64], start_time: Option<f64>, end_time: Option<f64>) -> Result<(), StreamerError> {
+        type Samp<D> = <<D as BaseDev>::Chan as BaseChan>::Samp;
+        let chan = self.chan(chan_name)?;
+        if TypeId::of::<Samp<D>>() == TypeId::of::<f64>() {
+            // SAFETY: `Samp` is `f64`, so the slice is reinterpreted as itself
+            let samp_arr = unsafe { std::slice::from_raw_parts_mut(res_arr.as_mut_ptr() as *mut Samp<D>, res_arr.len()) };
+            chan.calc_nsamps_into(samp_arr, start_time, end_time)
+        } else {
+            let samps = chan.calc_nsamps(res_arr.len(), start_time, end_time)?;
+            for (res, samp) in res_arr.iter_mut().zip(samps) {
+                *res = samp_to_f64(&samp);
+            }
+            Ok(())
+        }
+    }
+}
+
+fn dev_not_found(dev_name: &str) -> StreamerError {
+    StreamerError::NotFound { ctx: ErrCtx::dev(dev_name.to_string()), msg: "no such device is registered".to_string() }
 }
 
+/// Plotting data of all active channels on a shared time grid - `(t_arr, "dev/chan" -> samps)`, see [`BaseStreamer::calc_all`]
+pub type GridPlotData = (Vec<f64>, IndexMap<String, Vec<f64>>);
+
 pub trait BaseStreamer {
     fn devs(&self) -> Vec<&dyn TagBaseDev>;
     fn devs_mut(&mut self) -> Vec<&mut dyn TagBaseDev>;
+    /// Registered device `dev_name`, [`StreamerError::NotFound`] if there is none
+    fn dev(&self, dev_name: &str) -> Result<&dyn TagBaseDev, StreamerError> {
+        self.devs().into_iter().find(|dev| dev.tag_name() == dev_name).ok_or_else(|| dev_not_found(dev_name))
+    }
+    fn dev_mut(&mut self, dev_name: &str) -> Result<&mut dyn TagBaseDev, StreamerError> {
+        self.devs_mut().into_iter().find(|dev| dev.tag_name() == dev_name).ok_or_else(|| dev_not_found(dev_name))
+    }
 
-    fn check_can_add_dev(&self, name: String) -> Result<(), String> {
+    fn check_can_add_dev(&self, name: String) -> Result<(), StreamerError> {
         let dev_names: Vec<_> = self.devs().iter().map(|dev| dev.tag_name()).collect();
         if dev_names.contains(&name) {
-            return Err(format!("There is already a device with name {name} registered. Registered devices are {dev_names:?}"))
+            return Err(StreamerError::AlreadyExists {
+                ctx: ErrCtx::dev(name.clone()),
+                msg: format!("a device with this name is already registered. Registered devices are {dev_names:?}"),
+            })
         };
         Ok(())
     }
@@ -74,7 +592,168 @@ pub trait BaseStreamer {
         self.devs()
             .iter()
             .filter_map(|dev| dev.tag_last_instr_end_time())
-            .reduce(|largest_so_far, this| f64::max(largest_so_far, this))
+            .reduce(f64::max)
+    }
+
+    fn first_instr_start_time(&self) -> Option<f64> {
+        self.devs()
+            .iter()
+            .filter_map(|dev| dev.tag_first_instr_start_time())
+            .reduce(f64::min)
+    }
+
+    /// Number of instructions of all channels of all devices, see [`BaseChan::instr_count`]
+    fn instr_count(&self) -> usize {
+        self.devs().iter().map(|dev| dev.tag_instr_count()).sum()
+    }
+
+    /// Segment densities of all active channels grouped by device (active devices only), see [`BaseDev::segment_densities`]
+    fn segment_densities(&self) -> Result<IndexMap<String, IndexMap<String, f64>>, StreamerError> {
+        self.validate_compile_cache()?;
+        self.active_devs()
+            .iter()
+            .map(|dev| Ok((dev.tag_name(), dev.tag_segment_densities()?)))
+            .collect()
+    }
+
+    /// Trigger and clock metadata of all configured devices (active or not), see [`crate::sync`]
+    fn sync_specs(&self) -> IndexMap<String, SyncSpec> {
+        self.devs()
+            .iter()
+            .filter_map(|dev| dev.tag_sync_spec().map(|spec| (dev.tag_name(), spec)))
+            .collect()
+    }
+
+    /// Checks the trigger and clock metadata of all devices for consistency, see [`crate::sync`].
+    /// Nothing is checked if no device is configured.
+    ///
+    /// Returns [`StreamerError::Incompatible`] listing every problem found.
+    fn check_sync(&self) -> Result<(), StreamerError> {
+        let dev_names: Vec<String> = self.devs().iter().map(|dev| dev.tag_name()).collect();
+        let problems = sync_problems(&self.sync_specs(), &dev_names);
+        if problems.is_empty() {
+            return Ok(())
+        }
+        Err(StreamerError::Incompatible { ctx: ErrCtx::none(), msg: format!("Inconsistent device synchronization: {}", problems.join("; ")) })
+    }
+
+    /// Pairs up the edges of two compiled channels inside a time window and reports their skew, see [`crate::skew`].
+    ///
+    /// Returns [`StreamerError::InvalidArgument`] for an empty window or a malformed channel key
+    /// and [`StreamerError::NotFound`] for unknown devices and channels.
+    fn measure_skew(&self, spec: &SkewSpec) -> Result<SkewReport, StreamerError> {
+        if !(spec.t_start.is_finite() && spec.t_end.is_finite()) || spec.t_end < spec.t_start {
+            return Err(StreamerError::InvalidArgument {
+                ctx: ErrCtx::none(),
+                msg: format!("Invalid skew window [{}, {}]", spec.t_start, spec.t_end),
+            })
+        }
+        let window_edges = |key: &str| -> Result<Vec<Edge>, StreamerError> {
+            let Some((dev_name, chan_name)) = key.split_once('/') else {
+                return Err(StreamerError::InvalidArgument {
+                    ctx: ErrCtx::none(),
+                    msg: format!("Channel key {key} must have the form \"<device>/<channel>\""),
+                })
+            };
+            let dev = self.dev(dev_name)?;
+            let edges = dev.tag_edges(chan_name, spec.threshold)?;
+            Ok(edges.into_iter().filter(|edge| spec.t_start <= edge.t && edge.t <= spec.t_end).collect())
+        };
+        Ok(SkewReport::new(spec, window_edges(&spec.chan_a)?, window_edges(&spec.chan_b)?))
+    }
+
+    /// Lag of best alignment of channel `chan_b` relative to `chan_a` (both `"<device>/<channel>"`) over the window
+    /// `(t_start, t_end)` on the trigger time axis, found by cross-correlation - see [`crate::skew`].
+    ///
+    /// Returns [`StreamerError::InvalidArgument`] for a window shorter than two grid samples, a malformed channel key,
+    /// or a channel which is constant over the window, and [`StreamerError::NotFound`] for unknown devices and channels.
+    fn relative_delay(&self, chan_a: &str, chan_b: &str, window: (f64, f64)) -> Result<RelativeDelay, StreamerError> {
+        let (t_start, t_end) = window;
+        if !(t_start.is_finite() && t_end.is_finite()) || t_end < t_start {
+            return Err(StreamerError::InvalidArgument {
+                ctx: ErrCtx::none(),
+                msg: format!("Invalid relative delay window [{t_start}, {t_end}]"),
+            })
+        }
+        self.validate_compile_cache()?;
+        let find_chan = |key: &str| {
+            let Some((dev_name, chan_name)) = key.split_once('/') else {
+                return Err(StreamerError::InvalidArgument {
+                    ctx: ErrCtx::none(),
+                    msg: format!("Channel key {key} must have the form \"<device>/<channel>\""),
+                })
+            };
+            let dev = self.dev(dev_name)?;
+            Ok((dev, chan_name.to_string()))
+        };
+        let (dev_a, chan_name_a) = find_chan(chan_a)?;
+        let (dev_b, chan_name_b) = find_chan(chan_b)?;
+        let samp_rate = f64::max(dev_a.tag_samp_rate(), dev_b.tag_samp_rate());
+        let n_samps = ((t_end - t_start) * samp_rate).round() as usize;
+        if n_samps < 2 {
+            return Err(StreamerError::InvalidArgument {
+                ctx: ErrCtx::none(),
+                msg: format!("Relative delay window [{t_start}, {t_end}] is shorter than two samples at {samp_rate} Hz"),
+            })
+        }
+        // Holds the last tick of the channel at or before each grid time
+        let grid_samps = |dev: &dyn TagBaseDev, chan_name: &str| -> Result<Vec<f64>, StreamerError> {
+            let rate = dev.tag_samp_rate();
+            let last_pos = ((dev.tag_try_compiled_stop_time()? * rate).round() as usize).saturating_sub(1);
+            let tick = |idx: usize| {
+                let pos = ((t_start + idx as f64 / samp_rate - dev.tag_trigger_delay()) * rate + 1e-9).floor();
+                (pos.max(0.0) as usize).min(last_pos)
+            };
+            let first_pos = tick(0);
+            let samps = dev.tag_calc_chan_samps(chan_name, first_pos, tick(n_samps - 1) + 1)?;
+            Ok((0..n_samps).map(|idx| samps[tick(idx) - first_pos]).collect())
+        };
+        let samps_a = grid_samps(dev_a, &chan_name_a)?;
+        let samps_b = grid_samps(dev_b, &chan_name_b)?;
+        let Some((lag_samps, corr)) = best_lag(&samps_a, &samps_b, n_samps / 2) else {
+            return Err(StreamerError::InvalidArgument {
+                ctx: ErrCtx::none(),
+                msg: format!("{chan_a} or {chan_b} is constant over [{t_start}, {t_end}], the delay is undefined"),
+            })
+        };
+        Ok(RelativeDelay { chan_a: chan_a.to_string(), chan_b: chan_b.to_string(), samp_rate, lag_samps, corr })
+    }
+
+    /// Padding segments of all active channels grouped by device (active devices only), see [`crate::padding`]
+    fn padding_report(&self) -> Result<IndexMap<String, DevPadding>, StreamerError> {
+        self.active_devs()
+            .iter()
+            .map(|dev| Ok((dev.tag_name(), dev.tag_padding_report()?)))
+            .collect()
+    }
+
+    /// Idle maps of all active devices - intervals of at least `min_idle_time` seconds where all channels of a device hold
+    /// constant values, so that backends may pause the output. See [`crate::idle`].
+    ///
+    /// Returns [`StreamerError::InvalidArgument`] for a negative or non-finite `min_idle_time`.
+    fn idle_map(&self, min_idle_time: f64) -> Result<IndexMap<String, DevIdle>, StreamerError> {
+        if !(min_idle_time.is_finite() && min_idle_time >= 0.0) {
+            return Err(StreamerError::InvalidArgument {
+                ctx: ErrCtx::none(),
+                msg: format!("idle_map(): invalid min_idle_time {min_idle_time}"),
+            })
+        }
+        self.validate_compile_cache()?;
+        self.active_devs()
+            .iter()
+            .map(|dev| {
+                let min_samps = ((min_idle_time * dev.tag_samp_rate()).ceil() as usize).max(1);
+                Ok((dev.tag_name(), dev.tag_idle_map(min_samps)?))
+            })
+            .collect()
+    }
+
+    /// Activity windows of all active channels grouped by device (active devices only), see [`BaseDev::activity_windows`]
+    fn activity_windows(&self) -> IndexMap<String, ActivityWindows> {
+        self.active_devs()
+            .iter()
+            .map(|dev| (dev.tag_name(), dev.tag_activity_windows()))
+            .collect()
     }
 
     fn got_instructions(&self) -> bool {
@@ -104,35 +783,135 @@ pub trait BaseStreamer {
             .collect()
     }
 
-    fn compile(&mut self, stop_time: Option<f64>) -> Result<f64, String> {
+    /// Mutes or unmutes channel `chan_name` of device `dev_name`, see [`BaseChan::set_enabled`]
+    fn set_chan_enabled(&mut self, dev_name: &str, chan_name: &str, enabled: bool) -> Result<(), StreamerError> {
+        let dev = self.dev_mut(dev_name)?;
+        dev.tag_set_chan_enabled(chan_name, enabled)
+    }
+
+    /// Sets the gap-filling policy of channel `chan_name` of device `dev_name`, see [`BaseChan::set_hold_last_val`]
+    fn set_chan_hold_last_val(&mut self, dev_name: &str, chan_name: &str, hold: bool) -> Result<(), StreamerError> {
+        let dev = self.dev_mut(dev_name)?;
+        dev.tag_set_chan_hold_last_val(chan_name, hold)
+    }
+
+    /// Sets the `dur_spec` defaults of channel `chan_name` of device `dev_name`, see [`BaseChan::set_dur_defaults`]
+    fn set_chan_dur_defaults(&mut self, dev_name: &str, chan_name: &str, defaults: DurDefaults) -> Result<(), StreamerError> {
+        let dev = self.dev_mut(dev_name)?;
+        dev.tag_set_chan_dur_defaults(chan_name, defaults)
+    }
+
+    /// Sets the start trigger delay of device `dev_name`, see [`BaseDev::set_trigger_delay`]
+    fn set_trigger_delay(&mut self, dev_name: &str, delay: f64) -> Result<(), StreamerError> {
+        let dev = self.dev_mut(dev_name)?;
+        dev.tag_set_trigger_delay(delay)
+    }
+
+    /// Sets the time to tick conversion policy of device `dev_name` and its channels, see [`BaseDev::set_tick_rounding`]
+    fn set_tick_rounding(&mut self, dev_name: &str, rounding: TickRounding) -> Result<(), StreamerError> {
+        let dev = self.dev_mut(dev_name)?;
+        dev.tag_set_tick_rounding(rounding)
+    }
+
+    /// Sets the closing edge policy of device `dev_name`, see [`crate::closing_edge`]
+    fn set_closing_edge(&mut self, dev_name: &str, policy: ClosingEdge) -> Result<(), StreamerError> {
+        let dev = self.dev_mut(dev_name)?;
+        dev.tag_set_closing_edge(policy)
+    }
+
+    /// Sets the closing edge policy of all registered devices, see [`crate::closing_edge`]
+    fn set_closing_edge_all(&mut self, policy: ClosingEdge) -> Result<(), StreamerError> {
+        for dev in self.devs_mut() {
+            dev.tag_set_closing_edge(policy)?
+        }
+        Ok(())
+    }
+
+    fn compile(&mut self, stop_time: Option<f64>) -> Result<f64, StreamerError> {
+        self.compile_with(stop_time, &CompileOptions::default())
+    }
+
+    /// Compiles all active devices running the safety checks enabled in `opts` - see [`CompileOptions`].
+    /// If compiling any device or any of the checks fails, all compile caches are cleared so nothing rejected can be streamed.
+    fn compile_with(&mut self, stop_time: Option<f64>, opts: &CompileOptions) -> Result<f64, StreamerError> {
         if !self.got_instructions() {
-            return Err(format!("Streamer did not get any instructions"))
-        }
-        let stop_time = match stop_time {
-            Some(stop_time) => {
-                if stop_time < self.last_instr_end_time().unwrap() {
-                    return Err(format!(
-                        "Attempted to compile with stop_time={stop_time} [s] while the last instruction end time is {} [s]\n\
-                        If you intended to provide stop_time=last_instr_end_time, use stop_time=None",
-                        self.last_instr_end_time().unwrap()
-                    ))
-                };
-                stop_time
-            },
-            None => self.last_instr_end_time().unwrap(),
-        };
+            return Err(StreamerError::NoInstructions { ctx: ErrCtx::none(), msg: "Streamer did not get any instructions".to_string() })
+        }
+        let requested_stop_time = stop_time;
+        let stop_time = self.resolve_stop_time(stop_time)?;
+        self.check_samp_limit(stop_time)?;
+
+        let run_time = self.compile_devs_with(stop_time, opts).inspect_err(|_| self.clear_compile_cache())?;
+        self.emit(&StreamerEvent::Compile { stop_time: requested_stop_time, run_time });
+        Ok(run_time)
+    }
 
+    /// Device compile, marker update, and rule checks of `compile_with()`. Returns the shortest device run time.
+    fn compile_devs_with(&mut self, stop_time: f64, opts: &CompileOptions) -> Result<f64, StreamerError> {
+        #[cfg(not(feature = "parallel"))]
         for dev in self.active_devs_mut() {
-            dev.tag_compile(stop_time)?;
+            dev.tag_compile_with(stop_time, opts)?;
+        }
+        // Devices are independent until validation, so with the `parallel` feature they are compiled on the rayon thread pool
+        // (each device additionally compiles its channels in parallel). Results are collected in device order.
+        #[cfg(feature = "parallel")]
+        {
+            let results: Vec<Result<(), StreamerError>> = self
+                .active_devs_mut()
+                .into_par_iter()
+                .map(|dev| dev.tag_compile_with(stop_time, opts))
+                .collect();
+            for res in results {
+                res?
+            }
+        }
+
+        // Marker channels follow the freshly compiled sources - regenerate and recompile their devices
+        let marker_devs = self.update_markers()?;
+        for dev in self.devs_mut().into_iter().filter(|dev| marker_devs.contains(&dev.tag_name())) {
+            if dev.tag_got_instructions() {
+                dev.tag_compile_with(stop_time, opts)?
+            } else {
+                dev.tag_clear_compile_cache()
+            }
         }
 
-        Ok(self.shortest_dev_run_time())
+        // Rule findings are kept by the devices they refer to - and dropped with their compile caches
+        let findings = self.check_rules()?;
+        for entry in findings.iter() {
+            if let Some(dev) = self.devs_mut().into_iter().find(|dev| Some(dev.tag_name()) == entry.dev) {
+                dev.tag_push_diagnostic(entry.clone())
+            }
+        }
+        if let Some(err) = rules::findings_err(&findings, opts.strict) {
+            return Err(err)
+        }
+
+        self.try_shortest_dev_run_time()
+    }
+
+    /// Stop time `compile` uses for the requested `stop_time` - the last instruction end time for `None`.
+    /// Returns [`StreamerError::OutOfRange`] if `stop_time` is below the last instruction end time.
+    fn resolve_stop_time(&self, stop_time: Option<f64>) -> Result<f64, StreamerError> {
+        let last_instr_end_time = self.last_instr_end_time().unwrap();
+        match stop_time {
+            Some(stop_time) if stop_time < last_instr_end_time => Err(StreamerError::OutOfRange {
+                ctx: ErrCtx::none(),
+                msg: format!(
+                    "Attempted to compile with stop_time={stop_time} [s] while the last instruction end time is {last_instr_end_time} [s]\n\
+                    If you intended to provide stop_time=last_instr_end_time, use stop_time=None"
+                ),
+            }),
+            Some(stop_time) => Ok(stop_time),
+            None => Ok(last_instr_end_time),
+        }
     }
 
     fn clear_compile_cache(&mut self) {
         for dev in self.devs_mut() {
             dev.tag_clear_compile_cache()
         }
+        self.emit(&StreamerEvent::ClearCompileCache);
     }
 
     fn clear_edit_cache(&mut self) {
@@ -140,9 +919,10 @@ pub trait BaseStreamer {
             dev.tag_clear_edit_cache()
         };
         self.clear_compile_cache();
+        self.emit(&StreamerEvent::ClearEditCache);
     }
 
-    fn validate_compile_cache(&self) -> Result<(), String> {
+    fn validate_compile_cache(&self) -> Result<(), StreamerError> {
         // 2 checks:
         // - streamer got instructions in the first place;
         // - all active devices pass compile cache validation
@@ -152,83 +932,160 @@ pub trait BaseStreamer {
            will naturally stop at slightly different times even when asked to compile to the same one]*/
 
         if !self.got_instructions() {
-            return Err(format!("Streamer did not get any instructions"))
+            return Err(StreamerError::NoInstructions { ctx: ErrCtx::none(), msg: "Streamer did not get any instructions".to_string() })
         }
 
         let failed_dev_msgs: Vec<String> = self
             .active_devs()
             .iter()
             .map(|dev| dev.tag_validate_compile_cache())
-            .filter_map(|res| res.err())
+            .filter_map(|res| res.err().map(|err| err.to_string()))
             .collect();
         if !failed_dev_msgs.is_empty() {
             let mut full_err_msg = String::new();
             for msg in failed_dev_msgs {
                 full_err_msg.push_str(&format!("{msg}\n"))
             };
-            return Err(format!("The following devices failed compile cache validation:\n{full_err_msg}"))
+            return Err(StreamerError::NotCompiled {
+                ctx: ErrCtx::none(),
+                msg: format!("The following devices failed compile cache validation:\n{full_err_msg}"),
+            })
         }
 
         Ok(())
     }
 
-    fn shortest_dev_run_time(&self) -> f64 {
-        // Sanity checks:
-        /* @Backend developers: before trying to access compile cache
-           you should always ensure the streamer actually got some instructions
-           and that compile cache is valid (up-to-date with the current edit cache).
-           Compile cache typically gets invalid due to users forgetting to re-compile after adding pulses.
-
-           Functions `got_instructions()` and `validate_compile_cache()` are meant to be the place
-           to do these checks gracefully. Other functions typically assume these checks have been done.
-           They likely still double check but may just panic if the tests fail like in the example below.
-        */
-        if !self.got_instructions() {
-            panic!("Streamer did not get any instructions")
+    /// Detailed state of the compile cache of all active devices - see [`crate::validation`].
+    fn validation_report(&self) -> StreamerReport {
+        StreamerReport {
+            devs: self.active_devs().iter().map(|dev| dev.tag_validation_report()).collect(),
         }
-        self.validate_compile_cache().unwrap();
+    }
 
-        self.active_devs()
-            .iter()
-            .map(|dev| dev.tag_compiled_stop_time())
-            .reduce(|shortest_so_far, this| f64::min(shortest_so_far, this))
-            .unwrap()
+    /// Human-readable summary of all devices - see [`crate::summary`]
+    fn summary(&self) -> StreamerSummary {
+        StreamerSummary {
+            devs: self.devs().iter().map(|dev| dev.tag_summary()).collect(),
+        }
     }
 
-    fn longest_dev_run_time(&self) -> f64 {
-        // Sanity checks:
-        /* @Backend developers: before trying to access compile cache
-           you should always ensure the streamer actually got some instructions
-           and that compile cache is valid (up-to-date with the current edit cache).
-           Compile cache typically gets invalid due to users forgetting to re-compile after adding pulses.
-
-           Functions `got_instructions()` and `validate_compile_cache()` are meant to be the place
-           to do these checks gracefully. Other functions typically assume these checks have been done.
-           They likely still double check but may just panic if the tests fail like in the example below.
-        */
+    /// Per-device and total data rates and sample counts of the compiled experiment, to check against bus
+    /// bandwidth limits before starting a run - see [`crate::budget`].
+    ///
+    /// Returns [`StreamerError::NotCompiled`] if the compile cache is stale.
+    fn stream_budget(&self) -> Result<StreamBudget, StreamerError> {
+        self.validate_compile_cache()?;
+        Ok(StreamBudget {
+            devs: self.active_devs().iter().map(|dev| dev.tag_stream_budget()).collect::<Result<_, _>>()?,
+        })
+    }
+
+    /// Opt-in NaN/Inf detection pass over the compiled waveforms of all active devices - see [`BaseChan::check_finite`].
+    fn check_finite(&self, max_samps_per_seg: Option<usize>) -> Result<(), StreamerError> {
+        self.validate_compile_cache()?;
+        for dev in self.active_devs() {
+            dev.tag_check_finite(max_samps_per_seg)?
+        }
+        Ok(())
+    }
+
+    /// Per-device sample counts, closing edge and trigger delay ticks, and memory footprint compiling to `stop_time`
+    /// (`None` - the last instruction end time, as for `compile`) would produce. Only reads the edit caches -
+    /// compile caches are left untouched. See [`crate::budget`].
+    fn predicted_samples(&self, stop_time: Option<f64>) -> Result<SampPrediction, StreamerError> {
         if !self.got_instructions() {
-            panic!("Streamer did not get any instructions")
+            return Err(StreamerError::NoInstructions { ctx: ErrCtx::none(), msg: "Streamer did not get any instructions".to_string() })
         }
-        self.validate_compile_cache().unwrap();
+        let stop_time = self.resolve_stop_time(stop_time)?;
+        Ok(SampPrediction {
+            stop_time,
+            devs: self.active_devs().iter().map(|dev| dev.tag_predicted_budget(stop_time)).collect::<Result<_, _>>()?,
+        })
+    }
 
+    /// Compiled stop time of the device which finishes first.
+    ///
+    /// Returns `Err` if the streamer didn't get any instructions or if the compile cache is stale.
+    fn try_shortest_dev_run_time(&self) -> Result<f64, StreamerError> {
+        self.validate_compile_cache()?;
         self.active_devs()
             .iter()
-            .map(|dev| dev.tag_compiled_stop_time())
-            .reduce(|longest_so_far, this| f64::max(longest_so_far, this))
-            .unwrap()
+            .map(|dev| dev.tag_try_compiled_stop_time())
+            .try_fold(f64::INFINITY, |acc, stop_time| Ok(f64::min(acc, stop_time?)))
     }
 
-    fn add_reset_instr(&mut self, reset_time: Option<f64>) -> Result<(), String> {
-        let reset_time = match reset_time {
-            Some(reset_time) => {
-                if self.last_instr_end_time().is_some_and(|last_instr_end| reset_time < last_instr_end){
-                    return Err(format!(
-                        "Requested to insert the all-channel reset instruction at t = {reset_time} [s] \
-                        but some channels have instructions spanning until {} [s].\n\
-                        If you intended to provide `reset_time=last_instr_end_time`, use `reset_time=None`",
-                        self.last_instr_end_time().unwrap()
-                    ))
-                }
+    /// Compiled stop time of the device which finishes last.
+    ///
+    /// Returns `Err` if the streamer didn't get any instructions or if the compile cache is stale.
+    fn try_longest_dev_run_time(&self) -> Result<f64, StreamerError> {
+        self.validate_compile_cache()?;
+        self.active_devs()
+            .iter()
+            .map(|dev| dev.tag_try_compiled_stop_time())
+            .try_fold(0.0, |acc, stop_time| Ok(f64::max(acc, stop_time?)))
+    }
+
+    /// Panicking version of [`BaseStreamer::try_shortest_dev_run_time`].
+    fn shortest_dev_run_time(&self) -> f64 {
+        self.try_shortest_dev_run_time().unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Panicking version of [`BaseStreamer::try_longest_dev_run_time`].
+    fn longest_dev_run_time(&self) -> f64 {
+        self.try_longest_dev_run_time().unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Reset values of all registered channels grouped by device, see [`BaseDev::safe_state`]
+    fn safe_state(&self) -> IndexMap<String, IndexMap<String, f64>> {
+        self.devs().iter().map(|dev| (dev.tag_name(), dev.tag_safe_state())).collect()
+    }
+
+    /// Replaces the sequence with the emergency/abort waveform holding every registered channel at its reset value
+    /// for `dur` seconds (see [`BaseDev::compile_safe_state`]) and compiles it. Markers and rules are not run,
+    /// and the solo selection is cleared - the abort waveform has to reach every channel.
+    ///
+    /// Returns the compiled run time. The previous sequence is discarded.
+    /// All or nothing: if any device fails, every device and the solo selection are restored (with cleared compile caches).
+    fn compile_safe_state(&mut self, dur: f64) -> Result<f64, StreamerError> {
+        let selection = self.selection().cloned();
+        let states: Vec<Box<dyn Any>> = self.devs().iter().map(|dev| dev.tag_edit_state()).collect();
+        self.clear_solo();
+        if let Err(err) = self.devs_mut().into_iter().try_for_each(|dev| dev.tag_compile_safe_state(dur)) {
+            for (dev, state) in self.devs_mut().into_iter().zip(states) {
+                dev.tag_restore_edit_state(state)
+            }
+            if let (Some(slot), Some(selection)) = (self.selection_mut(), selection) {
+                *slot = selection
+            }
+            return Err(err)
+        }
+        self.try_longest_dev_run_time()
+    }
+
+    /// Cuts the compiled sequence at `spec.t_abort` and ramps every active channel down to its reset value,
+    /// see [`crate::abort`]. Returns the new longest device run time.
+    fn compile_abort(&mut self, spec: &AbortSpec) -> Result<f64, StreamerError> {
+        self.validate_compile_cache()?;
+        for dev in self.active_devs_mut() {
+            dev.tag_compile_abort(spec)?
+        }
+        self.try_longest_dev_run_time()
+    }
+
+    fn add_reset_instr(&mut self, reset_time: Option<f64>) -> Result<(), StreamerError> {
+        let reset_time = match reset_time {
+            Some(reset_time) => {
+                if self.last_instr_end_time().is_some_and(|last_instr_end| reset_time < last_instr_end){
+                    return Err(StreamerError::OutOfRange {
+                        ctx: ErrCtx::none(),
+                        msg: format!(
+                            "Requested to insert the all-channel reset instruction at t = {reset_time} [s] \
+                            but some channels have instructions spanning until {} [s].\n\
+                            If you intended to provide `reset_time=last_instr_end_time`, use `reset_time=None`",
+                            self.last_instr_end_time().unwrap()
+                        ),
+                    })
+                }
                 reset_time
             },
             None => self.last_instr_end_time().unwrap_or(0.0),
@@ -236,6 +1093,1473 @@ pub trait BaseStreamer {
         for dev in self.devs_mut() {
             dev.tag_add_reset_instr(reset_time)?
         };
+        self.emit(&StreamerEvent::AddResetInstr { reset_time });
+        Ok(())
+    }
+
+    /// Adds the instruction built by `registry` from `func_name` and `args` to channel `chan_name` of device `dev_name`,
+    /// see [`BaseChan::add_instr`] for `t` and `dur_spec`. Fires [`StreamerEvent::AddInstr`].
+    #[allow(clippy::too_many_arguments)]
+    fn add_instr_by_name(
+        &mut self, dev_name: &str, chan_name: &str, registry: &FnRegistry, func_name: &str, args: &FnArgs, t: f64, dur_spec: Option<(f64, bool)>
+    ) -> Result<(), StreamerError> {
+        let dev = self.dev_mut(dev_name)?;
+        dev.tag_add_instr_by_name(chan_name, registry, func_name, args, t, dur_spec)?;
+        self.emit(&StreamerEvent::AddInstr {
+            dev: dev_name.to_string(), chan: chan_name.to_string(), func: func_name.to_string(), args: args.clone(), t, dur_spec,
+        });
         Ok(())
     }
-}
\ No newline at end of file
+
+    /// Stamps function `func_name` built by `registry` at every time of `start_times` on channel `chan_name` of device `dev_name`,
+    /// with per-pulse parameter `overrides` - see [`BaseChan::add_pulse_array_by_name`].
+    #[allow(clippy::too_many_arguments)]
+    fn add_pulse_array_by_name(
+        &mut self, dev_name: &str, chan_name: &str, registry: &FnRegistry, func_name: &str, args: &FnArgs,
+        overrides: &IndexMap<String, Vec<f64>>, start_times: &[f64], dur: f64, keep_val: bool
+    ) -> Result<(), StreamerError> {
+        let dev = self.dev_mut(dev_name)?;
+        dev.tag_add_pulse_array_by_name(chan_name, registry, func_name, args, overrides, start_times, dur, keep_val)
+    }
+
+    /// Stamps the template `func` at every time of `start_times` on channel `chan_name` of device `dev_name` - see [`BaseChan::add_pulse_array`].
+    ///
+    /// Returns [`StreamerError::Incompatible`] if the sample type `T` of `func` is not the one of the device channels.
+    fn add_pulse_array<T: 'static>(
+        &mut self, dev_name: &str, chan_name: &str, func: Box<dyn FnTraitSet<T>>, start_times: &[f64], dur: f64, keep_val: bool
+    ) -> Result<(), StreamerError> {
+        let dev = self.dev_mut(dev_name)?;
+        dev.tag_add_pulse_array(chan_name, Box::new(func), start_times, dur, keep_val)
+    }
+
+    /// [`BaseDev::add_counter`] on the bool channels `chan_names` of device `dev_name` (least significant bit first)
+    #[allow(clippy::too_many_arguments)]
+    fn add_counter(
+        &mut self, dev_name: &str, chan_names: &[&str], t: f64, dur: f64, period: f64, gray: bool, keep_val: bool
+    ) -> Result<(), StreamerError> {
+        let dev = self.dev_mut(dev_name)?;
+        dev.tag_add_counter(chan_names, t, dur, period, gray, keep_val)
+    }
+
+    /// [`BaseDev::spi_transaction`] on the bool channels of device `dev_name`
+    #[allow(clippy::too_many_arguments)]
+    fn spi_transaction(
+        &mut self, dev_name: &str, clk_chan: &str, data_chan: &str, cs_chan: &str, t0: f64, word: &[bool], bit_rate: f64, mode: SpiMode
+    ) -> Result<(), StreamerError> {
+        let dev = self.dev_mut(dev_name)?;
+        dev.tag_spi_transaction(clk_chan, data_chan, cs_chan, t0, word, bit_rate, mode)
+    }
+
+    /// Registered event observers - see [`crate::events`]. The default `None` means the streamer doesn't support them.
+    fn observers(&self) -> Option<&Observers> {
+        None
+    }
+    fn observers_mut(&mut self) -> Option<&mut Observers> {
+        None
+    }
+    /// Registers `observer` to be called with every [`StreamerEvent`]. The returned id removes it with [`BaseStreamer::remove_observer`].
+    ///
+    /// Returns [`StreamerError::Incompatible`] if the streamer doesn't support observers.
+    fn add_observer(&mut self, observer: Observer) -> Result<ObserverId, StreamerError> {
+        let Some(observers) = self.observers_mut() else {
+            return Err(StreamerError::Incompatible { ctx: ErrCtx::none(), msg: "This streamer does not support event observers".to_string() })
+        };
+        Ok(observers.add(observer))
+    }
+    /// Returns [`StreamerError::NotFound`] if there is no observer `id` and [`StreamerError::Incompatible`]
+    /// if the streamer doesn't support observers.
+    fn remove_observer(&mut self, id: ObserverId) -> Result<(), StreamerError> {
+        let Some(observers) = self.observers_mut() else {
+            return Err(StreamerError::Incompatible { ctx: ErrCtx::none(), msg: "This streamer does not support event observers".to_string() })
+        };
+        if !observers.remove(id) {
+            return Err(StreamerError::NotFound { ctx: ErrCtx::none(), msg: format!("There is no observer {id:?} registered") })
+        }
+        Ok(())
+    }
+    /// Calls the registered observers with `event`
+    fn emit(&self, event: &StreamerEvent) {
+        if let Some(observers) = self.observers() {
+            observers.emit(event)
+        }
+    }
+
+    /// Registered marker channels - see [`crate::marker`]. The default `None` means the streamer doesn't support markers.
+    fn markers(&self) -> Option<&Vec<Marker>> {
+        None
+    }
+    fn markers_mut(&mut self) -> Option<&mut Vec<Marker>> {
+        None
+    }
+
+    /// Upper bound on the total number of samples (over all active devices and channels) a compile may produce.
+    /// The outer `None` (default) means the streamer doesn't support it, the inner one that there is no limit.
+    fn samp_limit(&self) -> Option<&Option<usize>> {
+        None
+    }
+    fn samp_limit_mut(&mut self) -> Option<&mut Option<usize>> {
+        None
+    }
+    /// Sets the upper bound on the total number of samples, `None` removes it. Guards against absurd buffer sizes
+    /// from pathological inputs (e.g. a stop time in hours at a MHz sample rate) - checked before compiling anything.
+    ///
+    /// Returns [`StreamerError::Incompatible`] if the streamer doesn't support it.
+    fn set_samp_limit(&mut self, limit: Option<usize>) -> Result<(), StreamerError> {
+        let Some(slot) = self.samp_limit_mut() else {
+            return Err(StreamerError::Incompatible { ctx: ErrCtx::none(), msg: "This streamer does not support a sample limit".to_string() })
+        };
+        *slot = limit;
+        Ok(())
+    }
+    /// Checks that compiling all active devices to `stop_time` stays within [`BaseStreamer::samp_limit`].
+    ///
+    /// Returns [`StreamerError::OutOfRange`] if it doesn't (or the count overflows).
+    fn check_samp_limit(&self, stop_time: f64) -> Result<(), StreamerError> {
+        let Some(&Some(limit)) = self.samp_limit() else {
+            return Ok(())
+        };
+        let mut total: Option<usize> = Some(0);
+        for dev in self.active_devs() {
+            let samp_count = dev.tag_samp_count(stop_time)?;
+            total = total.zip(samp_count).and_then(|(total, samp_count)| total.checked_add(samp_count));
+        }
+        match total {
+            Some(total) if total <= limit => Ok(()),
+            _ => Err(StreamerError::OutOfRange {
+                ctx: ErrCtx::none(),
+                msg: format!(
+                    "Compiling to stop_time={stop_time} [s] would produce {} samples, the limit is {limit}",
+                    total.map_or("more than usize::MAX".to_string(), |total| total.to_string())
+                ),
+            }),
+        }
+    }
+
+    /// Devices and channels to stream - see [`crate::selection`]. The default `None` means solo mode is not supported.
+    fn selection(&self) -> Option<&StreamSelection> {
+        None
+    }
+    fn selection_mut(&mut self) -> Option<&mut StreamSelection> {
+        None
+    }
+
+    /// Streams only the devices and channels in `keys` (`"Dev1"` or `"Dev1/ao0"`), replacing the previous selection.
+    /// Everything is still compiled, see [`crate::selection`]. An empty `keys` streams everything again.
+    ///
+    /// Returns [`StreamerError::NotFound`] if a key refers to an unknown device or channel
+    /// and [`StreamerError::Incompatible`] if the streamer doesn't support solo mode.
+    fn solo(&mut self, keys: &[&str]) -> Result<(), StreamerError> {
+        for key in keys {
+            let (dev_name, chan_name) = key.split_once('/').map_or((*key, None), |(dev, chan)| (dev, Some(chan)));
+            let known = self.devs().iter().any(|dev| {
+                dev.tag_name() == dev_name && chan_name.is_none_or(|chan_name| dev.tag_chan_names().iter().any(|name| name == chan_name))
+            });
+            if !known {
+                return Err(StreamerError::NotFound { ctx: ErrCtx::none(), msg: format!("Cannot solo {key}: no such device or channel") })
+            }
+        }
+        let Some(selection) = self.selection_mut() else {
+            return Err(StreamerError::Incompatible { ctx: ErrCtx::none(), msg: "This streamer does not support solo mode".to_string() })
+        };
+        *selection = StreamSelection::new(keys);
+        Ok(())
+    }
+    /// Streams everything again
+    fn clear_solo(&mut self) {
+        if let Some(selection) = self.selection_mut() {
+            selection.clear()
+        }
+    }
+    /// Active devices which are streamed under the current selection
+    fn streamed_devs(&self) -> Vec<&dyn TagBaseDev> {
+        self.active_devs()
+            .drain(..)
+            .filter(|dev| self.selection().is_none_or(|selection| selection.selects_dev(&dev.tag_name())))
+            .collect()
+    }
+    /// Channels of device `dev_name` which stream their default value under the current selection
+    fn masked_chans(&self, dev_name: &str) -> Vec<String> {
+        let Some(selection) = self.selection() else {
+            return Vec::new()
+        };
+        let Ok(dev) = self.dev(dev_name) else {
+            return Vec::new()
+        };
+        dev.tag_chan_names().into_iter().filter(|chan_name| !selection.selects_chan(dev_name, chan_name)).collect()
+    }
+
+    /// Validation rules run on every compile - see [`crate::rules`]. The default is `None` - rules are not supported.
+    fn rules(&self) -> Option<&Vec<Box<dyn ValidationRule>>> {
+        None
+    }
+    fn rules_mut(&mut self) -> Option<&mut Vec<Box<dyn ValidationRule>>> {
+        None
+    }
+
+    /// Registers `rule` to be run on every compile.
+    /// Returns [`StreamerError::Incompatible`] if the streamer doesn't support rules ([`BaseStreamer::rules`] is `None`).
+    fn add_rule(&mut self, rule: Box<dyn ValidationRule>) -> Result<(), StreamerError> {
+        let Some(rules) = self.rules_mut() else {
+            return Err(StreamerError::Incompatible { ctx: ErrCtx::none(), msg: "This streamer does not support validation rules".to_string() })
+        };
+        rules.push(rule);
+        Ok(())
+    }
+
+    /// Findings of all registered rules on the compiled sequence, see [`crate::rules`]
+    fn check_rules(&self) -> Result<Vec<Diagnostic>, StreamerError> {
+        let Some(rules) = self.rules().filter(|rules| !rules.is_empty()) else {
+            return Ok(Vec::new())
+        };
+        self.validate_compile_cache()?;
+        Ok(rules::run_rules(rules, &StreamerView::new(self.active_devs())))
+    }
+
+    /// Registers `marker`: from now on every `compile` regenerates channel `marker.dst_chan` from `marker.src_chan`.
+    ///
+    /// Both channels must exist, the destination can't already be driven by another marker, and markers can't be chained
+    /// (a marker channel can't be the source of another marker).
+    /// Returns [`StreamerError::Incompatible`] if the streamer doesn't support markers ([`BaseStreamer::markers`] is `None`).
+    fn add_marker(&mut self, marker: Marker) -> Result<(), StreamerError> {
+        for (dev_name, chan_name) in [(&marker.src_dev, &marker.src_chan), (&marker.dst_dev, &marker.dst_chan)] {
+            let chan_names = self.dev(dev_name).ok().map(|dev| dev.tag_chan_names());
+            if !chan_names.is_some_and(|chan_names| chan_names.contains(chan_name)) {
+                return Err(StreamerError::NotFound {
+                    ctx: ErrCtx::none(),
+                    msg: format!("Cannot add marker {marker}: there is no channel {dev_name}/{chan_name}"),
+                })
+            }
+        }
+        if marker.src_dev == marker.dst_dev && marker.src_chan == marker.dst_chan {
+            return Err(StreamerError::InvalidArgument {
+                ctx: ErrCtx::none(),
+                msg: format!("Cannot add marker {marker}: source and destination are the same channel"),
+            })
+        }
+        let Some(markers) = self.markers_mut() else {
+            return Err(StreamerError::Incompatible { ctx: ErrCtx::none(), msg: "This streamer does not support markers".to_string() })
+        };
+        // Marker channels are regenerated after the sources are compiled, so they can't be sources themselves
+        if let Some(existing) = markers.iter().find(|existing| {
+            (existing.dst_dev == marker.src_dev && existing.dst_chan == marker.src_chan)
+                || (existing.src_dev == marker.dst_dev && existing.src_chan == marker.dst_chan)
+        }) {
+            return Err(StreamerError::InvalidArgument {
+                ctx: ErrCtx::none(),
+                msg: format!("Cannot add marker {marker}: markers can't be chained, it would link to marker {existing}"),
+            })
+        }
+        if let Some(existing) = markers.iter().find(|existing| existing.dst_dev == marker.dst_dev && existing.dst_chan == marker.dst_chan) {
+            return Err(StreamerError::AlreadyExists {
+                ctx: ErrCtx::none(),
+                msg: format!("Cannot add marker {marker}: the destination channel is already driven by marker {existing}"),
+            })
+        }
+        markers.push(marker);
+        Ok(())
+    }
+
+    /// Regenerates the edit caches of all marker channels from their compiled sources.
+    /// Returns the names of the devices holding marker channels - they have to be compiled again.
+    fn update_markers(&mut self) -> Result<Vec<String>, StreamerError> {
+        let markers = self.markers().cloned().unwrap_or_default();
+        let mut marker_devs: Vec<String> = Vec::new();
+        for marker in markers {
+            let intervals = match self.dev(&marker.src_dev).ok() {
+                // A source device without instructions is not compiled - its marker stays low
+                Some(src_dev) if src_dev.tag_got_instructions() => src_dev.tag_marker_intervals(&marker.src_chan, &marker.rule)?,
+                _ => Vec::new(),
+            };
+            if let Ok(dst_dev) = self.dev_mut(&marker.dst_dev) {
+                dst_dev.tag_write_marker(&marker.dst_chan, &intervals)?
+            }
+            if !marker_devs.contains(&marker.dst_dev) {
+                marker_devs.push(marker.dst_dev)
+            }
+        }
+        Ok(marker_devs)
+    }
+
+    /// Moves every channel of every device defining preset `name` to its preset value at `t`,
+    /// see [`BaseDev::add_preset_instr`]. Channels without this preset are left untouched.
+    ///
+    /// Returns [`StreamerError::NotFound`] if no channel at all defines the preset.
+    fn add_preset_instr(&mut self, name: &str, t: f64) -> Result<(), StreamerError> {
+        if !self.devs().iter().any(|dev| dev.tag_has_preset(name)) {
+            return Err(StreamerError::NotFound {
+                ctx: ErrCtx::none(),
+                msg: format!("No channel defines preset \"{name}\""),
+            })
+        }
+        for dev in self.devs_mut().into_iter().filter(|dev| dev.tag_has_preset(name)) {
+            dev.tag_add_preset_instr(name, t)?
+        };
+        Ok(())
+    }
+
+    /// Loads the CSV/TSV schedule file at `path` (see [`crate::schedule`] for the format),
+    /// constructing the functions with the standard [`FnRegistry::std`].
+    fn load_schedule_csv(&mut self, path: &str) -> Result<(), StreamerError> {
+        self.load_schedule_csv_with(path, &FnRegistry::std())
+    }
+
+    /// Same as [`BaseStreamer::load_schedule_csv`] with a custom function registry
+    fn load_schedule_csv_with(&mut self, path: &str, registry: &FnRegistry) -> Result<(), StreamerError> {
+        let rows = schedule::read_csv(path)?;
+        self.add_schedule_rows(&rows, registry).map_err(|err| err.prefixed(path))
+    }
+
+    /// Adds the instructions of the JSON schedule `text` (see [`crate::schedule`] for the schema),
+    /// constructing the functions with the standard [`FnRegistry::std`].
+    fn import_schedule_json(&mut self, text: &str) -> Result<(), StreamerError> {
+        self.import_schedule_json_with(text, &FnRegistry::std())
+    }
+
+    /// Same as [`BaseStreamer::import_schedule_json`] with a custom function registry.
+    ///
+    /// All devices and their sample rates (where given) are checked before any instruction is added.
+    /// Existing instructions are kept - clear the edit caches first to replace the sequence.
+    fn import_schedule_json_with(&mut self, text: &str, registry: &FnRegistry) -> Result<(), StreamerError> {
+        let doc = ScheduleDoc::from_json(text)?;
+        for dev_doc in &doc.devices {
+            let dev = self.dev(&dev_doc.name).map_err(|err| err.prefixed("JSON schedule"))?;
+            if let Some(samp_rate) = dev_doc.samp_rate.filter(|&samp_rate| samp_rate != dev.tag_samp_rate()) {
+                return Err(StreamerError::Incompatible {
+                    ctx: ErrCtx::dev(dev_doc.name.clone()),
+                    msg: format!("JSON schedule was made for sample rate {samp_rate} Hz but the device runs at {} Hz", dev.tag_samp_rate()),
+                })
+            }
+        }
+        self.add_schedule_rows(&doc.rows(), registry)
+    }
+
+    /// Exports the edit caches of all devices as a JSON schedule (see [`crate::schedule`] for the schema).
+    ///
+    /// Returns [`StreamerError::Incompatible`] if some instruction function can't be written as name + numeric arguments.
+    fn export_schedule_json(&self) -> Result<String, StreamerError> {
+        let mut devices = Vec::new();
+        for dev in self.devs() {
+            let samp_rate = dev.tag_samp_rate();
+            let mut channels = Vec::new();
+            for (chan_name, snapshots) in dev.tag_instr_snapshots() {
+                let mut instructions = Vec::new();
+                for snapshot in snapshots {
+                    let (func, args) = schedule::parse_describe(&snapshot.func).ok_or_else(|| StreamerError::Incompatible {
+                        ctx: ErrCtx { dev: Some(dev.tag_name()), chan: Some(chan_name.clone()) },
+                        msg: format!("Instruction function {} can't be exported as name + numeric arguments", snapshot.func),
+                    })?;
+                    let start_pos = snapshot.start_pos;
+                    instructions.push(InstrSchedule {
+                        t: start_pos as f64 / samp_rate,
+                        dur: snapshot.end_spec.map(|(end_pos, _keep_val)| (end_pos - start_pos) as f64 / samp_rate),
+                        keep_val: snapshot.end_spec.is_some_and(|(_end_pos, keep_val)| keep_val),
+                        func,
+                        args,
+                    })
+                }
+                channels.push(ChanSchedule { name: chan_name, instructions })
+            }
+            devices.push(DevSchedule { name: dev.tag_name(), samp_rate: Some(samp_rate), channels })
+        }
+        Ok(ScheduleDoc { version: SCHEDULE_JSON_VERSION, devices }.to_json())
+    }
+
+    /// OpenPulse/Qobj-like description of the compiled schedule of all active devices, see [`crate::openpulse`]
+    fn pulse_qobj(&self) -> Result<PulseQobj, StreamerError> {
+        self.validate_compile_cache()?;
+        let mut qobj = PulseQobj::new();
+        for dev in self.active_devs() {
+            dev.tag_add_to_pulse_qobj(&mut qobj)?
+        }
+        Ok(qobj)
+    }
+
+    /// [`BaseStreamer::pulse_qobj`] as a JSON string
+    fn export_openpulse(&self) -> Result<String, StreamerError> {
+        Ok(self.pulse_qobj()?.to_json())
+    }
+
+    /// Timeline of the edit caches of all active channels, see [`crate::timeline`]
+    fn timeline(&self) -> Timeline {
+        let end_time = self.try_longest_dev_run_time().ok().or(self.last_instr_end_time()).unwrap_or(0.0);
+        let mut timeline = Timeline::new(end_time);
+        for dev in self.active_devs() {
+            for (chan_name, instrs) in dev.tag_instr_snapshots() {
+                timeline.add_chan(&dev.tag_name(), &chan_name, dev.tag_samp_rate(), &instrs)
+            }
+        }
+        timeline
+    }
+
+    /// [`BaseStreamer::timeline`] as a JSON string
+    fn export_timeline_json(&self) -> String {
+        self.timeline().to_json()
+    }
+
+    /// [`BaseStreamer::timeline`] as an SVG image `width` pixels wide
+    fn export_timeline_svg(&self, width: f64) -> String {
+        self.timeline().to_svg(width)
+    }
+
+    /// Every collision the schedule rows would run into when added with [`BaseStreamer::add_schedule_rows`] -
+    /// with the existing instructions or among themselves, see [`crate::collisions`]. Nothing is added.
+    ///
+    /// Colliding instructions are labeled with the row location. Returns [`StreamerError::NotFound`] for unknown devices or channels.
+    fn schedule_collision_report(&self, rows: &[ScheduleRow]) -> Result<CollisionReport, StreamerError> {
+        let mut dev_rows: IndexMap<&str, Vec<&ScheduleRow>> = IndexMap::new();
+        for row in rows {
+            dev_rows.entry(row.dev.as_str()).or_default().push(row);
+        }
+        let mut report = CollisionReport::default();
+        for (dev_name, rows) in dev_rows {
+            let dev = self.dev(dev_name).map_err(|err| err.prefixed("Schedule"))?;
+            let mut batch: IndexMap<String, Vec<InstrSnapshot>> = IndexMap::new();
+            for row in rows {
+                let rounding = dev.tag_chan_tick_rounding(&row.chan)?;
+                batch.entry(row.chan.clone()).or_default().push(row.snapshot(dev.tag_samp_rate(), rounding)?)
+            }
+            report.extend(dev.tag_collision_report(&batch)?)
+        }
+        Ok(report)
+    }
+
+    /// [`BaseStreamer::schedule_collision_report`] of the CSV/TSV schedule file at `path`
+    fn check_schedule_csv(&self, path: &str) -> Result<CollisionReport, StreamerError> {
+        self.schedule_collision_report(&schedule::read_csv(path)?)
+    }
+
+    /// Adds the instructions of parsed schedule rows to the edit caches.
+    ///
+    /// Stops at the first failing row - the error names its location - leaving the instructions of the preceding rows in place.
+    fn add_schedule_rows(&mut self, rows: &[ScheduleRow], registry: &FnRegistry) -> Result<(), StreamerError> {
+        for row in rows {
+            let loc_prefix = format!("Schedule {}", row.loc);
+            self.add_instr_by_name(&row.dev, &row.chan, registry, &row.func, &row.args, row.t, row.dur_spec)
+                .map_err(|err| err.prefixed(&loc_prefix))?
+        }
+        Ok(())
+    }
+
+    /// Stable fingerprint of the compile caches of all active devices, see [`BaseDev::content_hash`].
+    ///
+    /// Callers can compare it with the fingerprint of the previous run to detect that nothing has changed
+    /// and skip re-uploading buffers to hardware. The result is invariant to instruction edit order and to
+    /// device/channel registration order, but sensitive to timing and function parameters.
+    fn content_hash(&self) -> Result<u64, StreamerError> {
+        self.validate_compile_cache()?;
+
+        let mut dev_hashes = Vec::new();
+        for dev in self.active_devs() {
+            dev_hashes.push((dev.tag_name(), dev.tag_content_hash()?))
+        }
+        dev_hashes.sort_by(|(name_a, _), (name_b, _)| name_a.cmp(name_b));
+
+        let mut hasher = StableHasher::new();
+        hasher.update_usize(dev_hashes.len());
+        for (name, dev_hash) in dev_hashes {
+            hasher.update_str(&name);
+            hasher.update_u64(dev_hash);
+        }
+        Ok(hasher.finish())
+    }
+
+    /// Compares this streamer (treated as "old") with `other` (treated as "new").
+    ///
+    /// Devices and channels are matched by name. For every matching channel, edit-cache instructions are compared
+    /// and reported as added, removed, or re-timed (same function but different timing).
+    ///
+    /// If `samp_cmp` is `Some((n_samps, tol))`, compiled waveforms of matching channels are additionally sampled
+    /// at `n_samps` points over the common compiled duration and compared with tolerance `tol`.
+    /// This requires both streamers to be freshly compiled.
+    fn diff(&self, other: &Self, samp_cmp: Option<(usize, f64)>) -> Result<DiffReport, StreamerError>
+    where Self: Sized
+    {
+        if samp_cmp.is_some() {
+            self.validate_compile_cache()?;
+            other.validate_compile_cache()?;
+        }
+        let old_devs = self.devs();
+        let new_devs = other.devs();
+
+        let mut report = DiffReport::default();
+        for new_dev in new_devs.iter() {
+            if !old_devs.iter().any(|old_dev| old_dev.tag_name() == new_dev.tag_name()) {
+                report.added_devs.push(new_dev.tag_name())
+            }
+        }
+        for old_dev in old_devs.iter() {
+            match new_devs.iter().find(|new_dev| new_dev.tag_name() == old_dev.tag_name()) {
+                Some(new_dev) => {
+                    let dev_diff = diff_devs(*old_dev, *new_dev, samp_cmp)?;
+                    if !dev_diff.is_empty() {
+                        report.dev_diffs.push(dev_diff)
+                    }
+                },
+                None => report.removed_devs.push(old_dev.tag_name()),
+            }
+        }
+        Ok(report)
+    }
+
+    /// All diagnostic entries recorded by devices and channels - see [`crate::diagnostics`].
+    fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.devs()
+            .iter()
+            .flat_map(|dev| dev.tag_diagnostics())
+            .collect()
+    }
+
+    /// Time spent per channel in editing, compiling, and sample calculation - see [`crate::profiling`].
+    /// Empty unless the crate is built with the `profiling` feature.
+    fn profile_report(&self) -> ProfileReport {
+        ProfileReport {
+            entries: self.devs().iter().flat_map(|dev| dev.tag_profile_entries()).collect(),
+        }
+    }
+    fn clear_profile(&self) {
+        for dev in self.devs() {
+            dev.tag_clear_profile()
+        }
+    }
+
+    /// Moves instructions of all devices by `dt` seconds (positive `dt` - later in time). See [`BaseDev::shift`].
+    ///
+    /// Nothing is changed if any of the instructions would be moved to negative time.
+    fn shift_all(&mut self, dt: f64) -> Result<(), StreamerError> {
+        for dev in self.devs() {
+            dev.tag_check_can_shift(dt)?
+        }
+        for dev in self.devs_mut() {
+            dev.tag_shift(dt)?
+        }
+        self.emit(&StreamerEvent::Shift { dt });
+        Ok(())
+    }
+
+    /// Independent copy of this streamer with the template instructions of `seq` written in,
+    /// parameters substituted from `overrides`. See [`ShotSequence::instantiate`].
+    fn instantiate(&self, seq: &ShotSequence, overrides: &IndexMap<String, f64>) -> Result<Self, StreamerError>
+    where Self: Clone + Sized
+    {
+        seq.instantiate(self, overrides)
+    }
+
+    /// Restricts the sequence to the window `[t_start, t_end)` moved to start at `0` - e.g. to re-run only the tail
+    /// of a long sequence. Instructions straddling the window edges are split. See [`BaseChan::crop`].
+    fn crop(&mut self, t_start: f64, t_end: f64) -> Result<(), StreamerError> {
+        if t_start.is_nan() || t_start < 0.0 || t_end.is_nan() || t_end <= t_start {
+            return Err(StreamerError::InvalidArgument {
+                ctx: ErrCtx::none(),
+                msg: format!("crop(): the window [{t_start}, {t_end}) s must be non-empty and start at non-negative time"),
+            })
+        }
+        for dev in self.devs_mut() {
+            dev.tag_crop(t_start, t_end)?
+        }
+        self.emit(&StreamerEvent::Crop { t_start, t_end });
+        Ok(())
+    }
+
+    /// Checks that every active device of `block` is registered in this streamer and can be copied into its counterpart
+    /// (same device type and sample rate, all active channels present), so that [`BaseStreamer::prepend_block`]
+    /// and [`BaseStreamer::concat`] fail before changing anything
+    fn check_block_devs(&self, block: &Self) -> Result<(), StreamerError>
+    where Self: Sized
+    {
+        let self_dev_names: Vec<String> = self.devs().iter().map(|dev| dev.tag_name()).collect();
+        for block_dev in block.active_devs() {
+            let block_dev_name = block_dev.tag_name();
+            let Ok(dev) = self.dev(&block_dev_name) else {
+                return Err(StreamerError::NotFound {
+                    ctx: ErrCtx::dev(block_dev_name.clone()),
+                    msg: format!(
+                        "block contains instructions for this device which is not registered in this streamer. \
+                        Registered devices are {self_dev_names:?}"
+                    ),
+                })
+            };
+            dev.tag_check_can_copy_from(block_dev, 0.0)?
+        }
+        Ok(())
+    }
+
+    /// Inserts the full sequence of `block` in front of the current sequence.
+    ///
+    /// All existing instructions are shifted later by the `block` duration (its `last_instr_end_time()`),
+    /// then instructions of every `block` device are copied into the device with the same name.
+    /// Every active device of `block` must be present in this streamer, see [`BaseStreamer::check_block_devs`].
+    fn prepend_block(&mut self, block: &Self) -> Result<(), StreamerError>
+    where Self: Sized
+    {
+        let Some(block_dur) = block.last_instr_end_time() else {
+            return Ok(())
+        };
+        self.check_block_devs(block)?;
+
+        self.shift_all(block_dur)?;
+        for block_dev in block.active_devs() {
+            self.dev_mut(&block_dev.tag_name())?.tag_copy_instrs_from(block_dev)?;
+        }
+        Ok(())
+    }
+
+    /// Appends the full sequence of `block` after the current sequence, `gap` seconds after its `last_instr_end_time()`.
+    ///
+    /// Instructions of every `block` device are moved later by the current sequence duration plus `gap`
+    /// (see [`BaseDev::copy_instrs_from_shifted`]) and copied into the device with the same name.
+    /// Every active device of `block` must be present in this streamer, see [`BaseStreamer::check_block_devs`].
+    fn concat(&mut self, block: &Self, gap: f64) -> Result<(), StreamerError>
+    where Self: Sized
+    {
+        if gap < 0.0 {
+            return Err(StreamerError::InvalidArgument { ctx: ErrCtx::none(), msg: format!("concat(): gap must be non-negative, got {gap}") })
+        }
+        self.check_block_devs(block)?;
+
+        let offs = self.last_instr_end_time().unwrap_or(0.0) + gap;
+        for block_dev in block.active_devs() {
+            self.dev_mut(&block_dev.tag_name())?.tag_copy_instrs_from_shifted(block_dev, offs)?;
+        }
+        Ok(())
+    }
+
+    /// Streams every active device into its own [`MockStreamTarget`] in chunks of `chunk_samps` samples.
+    /// See [`BaseDev::run_mock`].
+    ///
+    /// Returns device name -> recorded target. Since devices may have different sample types, targets are type-erased
+    /// and should be downcast by the caller, e.g. `targets["Dev1"].downcast_ref::<MockStreamTarget<f64>>()`.
+    /// Only streamed devices are run and unselected channels stream their default value, see [`BaseStreamer::solo`].
+    ///
+    /// [`MockStreamTarget`]: crate::mock::MockStreamTarget
+    fn run_mock(&self, chunk_samps: usize) -> Result<IndexMap<String, Box<dyn Any>>, StreamerError> {
+        self.validate_compile_cache()?;
+
+        let mut targets = IndexMap::new();
+        for dev in self.streamed_devs() {
+            targets.insert(dev.tag_name(), dev.tag_run_mock_masked(chunk_samps, &self.masked_chans(&dev.tag_name()))?);
+        }
+        Ok(targets)
+    }
+
+    /// Samples of every active channel of every active device on one shared time grid of `n_samps` points
+    /// from `start_time` (default `0`) to `end_time` - `(t_arr, "dev/chan" -> samps)`, ready for an overview plot.
+    /// Each device evaluates its channels on the grid directly, regardless of its own sample rate (see [`BaseChan::calc_nsamps`]).
+    ///
+    /// `end_time` defaults to the compiled stop time of the device which finishes first, so that the grid is covered by all devices.
+    fn calc_all(&self, n_samps: usize, start_time: Option<f64>, end_time: Option<f64>) -> Result<GridPlotData, StreamerError> {
+        self.validate_compile_cache()?;
+        let start_time = start_time.unwrap_or(0.0);
+        let end_time = match end_time {
+            Some(end_time) => end_time,
+            None => self.try_shortest_dev_run_time()?,
+        };
+        let mut t_arr = Vec::new();
+        let mut chans = IndexMap::new();
+        for dev in self.active_devs() {
+            for (chan_name, (chan_t_arr, samps)) in dev.tag_plot_data(n_samps, Some(start_time), Some(end_time))? {
+                t_arr = chan_t_arr;
+                chans.insert(format!("{}/{chan_name}", dev.tag_name()), samps);
+            }
+        }
+        Ok((t_arr, chans))
+    }
+
+    /// Compiled waveform of channel `chan_name` of device `dev_name` resampled to `to_rate`, see [`BaseChan::resample`]
+    fn resample_chan(&self, dev_name: &str, chan_name: &str, to_rate: f64, method: Resampling) -> Result<Vec<f64>, StreamerError> {
+        let dev = self.dev(dev_name)?;
+        dev.tag_resample_chan(chan_name, to_rate, method)
+    }
+
+    /// Threshold crossings of channel `chan_name` of device `dev_name`, see [`crate::analysis`]
+    fn find_edges(&self, dev_name: &str, chan_name: &str, threshold: f64) -> Result<Vec<Crossing>, StreamerError> {
+        let dev = self.dev(dev_name)?;
+        dev.tag_find_edges(chan_name, threshold)
+    }
+
+    /// Peaks of channel `chan_name` of device `dev_name` of at least `min_height`, at least `min_spacing` seconds apart,
+    /// see [`crate::analysis`]
+    fn find_peaks(&self, dev_name: &str, chan_name: &str, min_height: f64, min_spacing: f64) -> Result<Vec<Peak>, StreamerError> {
+        let dev = self.dev(dev_name)?;
+        dev.tag_find_peaks(chan_name, min_height, min_spacing)
+    }
+
+    /// `∫ samp dt` of channel `chan_name` of device `dev_name` over `[start_time, end_time]`, see [`BaseChan::integral`]
+    fn integral(&self, dev_name: &str, chan_name: &str, start_time: Option<f64>, end_time: Option<f64>) -> Result<f64, StreamerError> {
+        let dev = self.dev(dev_name)?;
+        dev.tag_integral(chan_name, start_time, end_time)
+    }
+
+    /// Writes the level transitions of every active `bool` channel to the `.vcd` file `path`, one scope per device,
+    /// in output time (including trigger delays) - see [`crate::export`].
+    ///
+    /// Returns [`StreamerError::NotFound`] if no active device has `bool` samples.
+    fn export_vcd(&self, path: &str) -> Result<(), StreamerError> {
+        self.validate_compile_cache()?;
+        let mut scopes = Vec::new();
+        let mut stop_time: f64 = 0.0;
+        for dev in self.active_devs() {
+            let Some(chan_events) = dev.tag_bool_events()? else {
+                continue
+            };
+            let delay = dev.tag_trigger_delay();
+            stop_time = stop_time.max(dev.tag_try_compiled_stop_time()? + delay);
+            let signals = chan_events.into_iter().map(|(chan_name, events)| {
+                (chan_name, events.into_iter().map(|event| Event { t: event.t + delay, ..event }).collect())
+            }).collect();
+            scopes.push((dev.tag_name(), signals));
+        }
+        if scopes.is_empty() {
+            return Err(StreamerError::NotFound { ctx: ErrCtx::none(), msg: "There are no active devices with digital (bool) channels to export".to_string() })
+        }
+        export::write_vcd(path, &scopes, stop_time)
+    }
+
+    /// Replays the `.vcd` capture `path` - the reverse of [`BaseStreamer::export_vcd`]. Every 1-bit signal whose scope
+    /// and name match a registered device and one of its channels is added to that channel with [`BaseChan::add_edges`].
+    /// Capture times are output times like instruction times, so trigger delays are compensated by compile. Other signals are ignored.
+    ///
+    /// Returns the `"dev/chan"` names of the populated channels, or [`StreamerError::NotFound`] if no signal matched.
+    fn import_vcd(&mut self, path: &str) -> Result<Vec<String>, StreamerError> {
+        let signals = export::read_signals(path)?;
+        let mut imported = Vec::new();
+        for dev in self.devs_mut() {
+            let dev_name = dev.tag_name();
+            let chan_names = dev.tag_chan_names();
+            for signal in signals.iter().filter(|signal| signal.scope == dev_name && chan_names.contains(&signal.name)) {
+                dev.tag_add_edges(&signal.name, &signal.edges)?;
+                imported.push(format!("{dev_name}/{}", signal.name));
+            }
+        }
+        if imported.is_empty() {
+            return Err(StreamerError::NotFound { ctx: ErrCtx::none(), msg: format!("{path} has no signals matching a registered device and channel") })
+        }
+        Ok(imported)
+    }
+
+    /// Compiled waveforms of all active channels resampled to the common rate `to_rate` - `"dev/chan"` -> samples.
+    /// Devices may stop a few ticks apart, so all waveforms are cut to the shortest one to share the same time points.
+    /// See [`crate::resample`].
+    fn resample_all(&self, to_rate: f64, method: Resampling) -> Result<IndexMap<String, Vec<f64>>, StreamerError> {
+        self.validate_compile_cache()?;
+        let mut res = IndexMap::new();
+        for dev in self.active_devs() {
+            for chan in dev.tag_info().chans.into_iter().filter(|chan| chan.got_instructions) {
+                res.insert(format!("{}/{}", dev.tag_name(), chan.name), dev.tag_resample_chan(&chan.name, to_rate, method)?);
+            }
+        }
+        let len = res.values().map(Vec::len).min().unwrap_or(0);
+        res.values_mut().for_each(|samps| samps.truncate(len));
+        Ok(res)
+    }
+
+    /// Rehearses a streaming session: every streamed device calculates its full compiled sequence in chunks of
+    /// `chunk_samps` samples, which are timed, checked for NaN/±Inf, and discarded - see [`crate::dry_run`].
+    ///
+    /// Runtime errors are collected in the report. Returns `Err` if the compile cache is stale or `chunk_samps` is zero.
+    fn dry_run(&self, chunk_samps: usize) -> Result<DryRunReport, StreamerError> {
+        self.validate_compile_cache()?;
+        Ok(DryRunReport {
+            devs: self.streamed_devs().iter().map(|dev| dev.tag_dry_run(chunk_samps)).collect::<Result<_, _>>()?,
+        })
+    }
+}
+#[cfg(test)]
+mod test {
+    use crate::channel::{BaseChan, ConstFn};
+    use crate::instruction::InstrMeta;
+    use crate::device::BaseDev;
+    use crate::fn_lib_tools::StdFnLib;
+    use crate::mock::MockStreamTarget;
+    use crate::mock::test_impls::TestStreamer;
+    use crate::streamer::BaseStreamer;
+    use crate::error::StreamerError;
+
+    #[test]
+    fn markers() {
+        use crate::marker::{Marker, MarkerRule};
+        use crate::mock::test_impls::TestDev;
+
+        let mut streamer = TestStreamer::new();
+        streamer.add_ao_dev("AO", 1e3);
+        streamer.add_do_dev("DO", 1e2);
+        streamer.ao_devs["AO"].add_chan("ao0", 0.0);
+        streamer.do_devs["DO"].add_chan("gate", false);
+        streamer.do_devs["DO"].add_chan("thr", false);
+        streamer.do_devs["DO"].add_chan("thr2", false);
+        let ao0 = streamer.ao_devs["AO"].chan_mut("ao0").unwrap();
+        ao0.constant(1.0, 0.1, Some((0.2, false))).unwrap();
+        ao0.constant(-2.0, 0.5, Some((0.1, false))).unwrap();
+        ao0.constant(0.2, 0.6, Some((0.1, false))).unwrap();
+
+        streamer.add_marker(Marker::new("AO", "ao0", "DO", "gate", MarkerRule::DuringInstr)).unwrap();
+        streamer.add_marker(Marker::new("AO", "ao0", "DO", "thr", MarkerRule::Threshold(0.5))).unwrap();
+        assert!(matches!(
+            streamer.add_marker(Marker::new("AO", "ao0", "DO", "gate", MarkerRule::Threshold(0.1))),
+            Err(StreamerError::AlreadyExists { .. })
+        ));
+        assert!(matches!(
+            streamer.add_marker(Marker::new("AO", "ao1", "DO", "x", MarkerRule::DuringInstr)),
+            Err(StreamerError::NotFound { .. })
+        ));
+
+        streamer.compile(Some(1.0)).unwrap();
+        let samps = |streamer: &TestStreamer| {
+            let dev: &TestDev<bool> = &streamer.do_devs["DO"];
+            let mut samps = vec![false; 200];
+            dev.calc_samps(&mut samps, 0, 100).unwrap();
+            samps
+        };
+        let expected = |high: &[std::ops::Range<usize>]| -> Vec<bool> {
+            (0..100).map(|pos| high.iter().any(|range| range.contains(&pos))).collect()
+        };
+        let res = samps(&streamer);
+        assert_eq!(res[..100], expected(&[10..30, 50..70]));
+        assert_eq!(res[100..], expected(&[10..30, 50..60]));
+
+        // Edits of the source are followed on the next compile, manual edits of the marker channel are discarded
+        streamer.ao_devs["AO"].chan_mut("ao0").unwrap().constant(3.0, 0.8, Some((0.05, false))).unwrap();
+        streamer.do_devs["DO"].chan_mut("gate").unwrap().constant(true, 0.9, None).unwrap();
+        streamer.compile(Some(1.0)).unwrap();
+        let res = samps(&streamer);
+        assert_eq!(res[..100], expected(&[10..30, 50..70, 80..85]));
+        assert_eq!(res[100..], expected(&[10..30, 50..60, 80..85]));
+
+        // Marker channels must be boolean
+        streamer.ao_devs["AO"].add_chan("ao1", 0.0);
+        streamer.add_marker(Marker::new("AO", "ao0", "AO", "ao1", MarkerRule::DuringInstr)).unwrap();
+        assert!(matches!(streamer.compile(Some(1.0)), Err(StreamerError::Incompatible { .. })));
+        // No chains
+        assert!(matches!(
+            streamer.add_marker(Marker::new("DO", "gate", "DO", "thr2", MarkerRule::DuringInstr)),
+            Err(StreamerError::InvalidArgument { .. })
+        ));
+    }
+
+    #[test]
+    fn load_schedule_csv() {
+        let mut streamer = TestStreamer::new();
+        streamer.add_ao_dev("AO", 1e3);
+        streamer.add_do_dev("DO", 1e3);
+        streamer.ao_devs["AO"].add_chan("ao0", 0.0);
+        streamer.do_devs["DO"].add_chan("do0", false);
+
+        let path = std::env::temp_dir().join(format!("base_streamer_schedule_{}.csv", std::process::id()));
+        let path = path.to_str().unwrap();
+        std::fs::write(path, "\
+            device, channel, t, duration, keep_val, function, params\n\
+            AO, ao0, 0.0, 0.1, true, ConstF64, 1.5\n\
+            AO, ao0, 0.2, , , LinFn, slope=10, offs=0\n\
+            DO, do0, 0.05, 0.1, false, ConstBool, true\n\
+        ").unwrap();
+        streamer.load_schedule_csv(path).unwrap();
+        streamer.compile(Some(0.5)).unwrap();
+        let ao0 = streamer.ao_devs["AO"].chan("ao0").unwrap();
+        assert_eq!(ao0.eval_point(0.05).unwrap(), 1.5);
+        assert_eq!(ao0.eval_point(0.15).unwrap(), 1.5);
+        assert!((ao0.eval_point(0.3).unwrap() - 3.0).abs() < 1e-9);
+        let do0 = streamer.do_devs["DO"].chan("do0").unwrap();
+        assert!(do0.eval_point(0.1).unwrap());
+        assert!(!do0.eval_point(0.2).unwrap());
+
+        // Unknown device / function / argument, wrong function for the sample type - the error names the line
+        let load_row = |streamer: &mut TestStreamer, row: &str| {
+            std::fs::write(path, format!("\n{row}\n")).unwrap();
+            let err = streamer.load_schedule_csv(path).unwrap_err();
+            assert!(err.msg().contains("Schedule line 2"), "{}", err.msg());
+            err
+        };
+        assert!(matches!(load_row(&mut streamer, "XX, ao0, 1.0, 0.1, , ConstF64, 1"), StreamerError::NotFound { .. }));
+        assert!(matches!(load_row(&mut streamer, "AO, ao0, 1.0, 0.1, , Foo, 1"), StreamerError::NotFound { .. }));
+        assert!(matches!(load_row(&mut streamer, "AO, ao0, 1.0, 0.1, , LinFn, slope=1"), StreamerError::InvalidArgument { .. }));
+        assert!(matches!(load_row(&mut streamer, "DO, do0, 1.0, 0.1, , ConstF64, 1"), StreamerError::NotFound { .. }));
+        std::fs::remove_file(path).unwrap();
+        assert!(matches!(streamer.load_schedule_csv(path), Err(StreamerError::NotFound { .. })));
+    }
+
+    #[test]
+    fn schedule_json() {
+        use crate::fn_lib_tools::{ArrayFn, Interp};
+
+        let new_streamer = || {
+            let mut streamer = TestStreamer::new();
+            streamer.add_ao_dev("AO", 1e3);
+            streamer.add_do_dev("DO", 1e3);
+            streamer.ao_devs["AO"].add_chan("ao0", 0.0);
+            streamer.ao_devs["AO"].add_chan("ao1", 0.0);
+            streamer.do_devs["DO"].add_chan("do0", false);
+            streamer
+        };
+        let mut streamer = new_streamer();
+        let sine = StdFnLib::new().Sine(1.0, 10.0, 0.5, 0.0).unwrap().inner;
+        streamer.ao_devs["AO"].chan_mut("ao0").unwrap().add_instr(sine, 0.1, Some((0.2, true))).unwrap();
+        streamer.ao_devs["AO"].chan_mut("ao0").unwrap().constant(0.5, 0.4, None).unwrap();
+        streamer.do_devs["DO"].chan_mut("do0").unwrap().constant(true, 0.05, Some((0.1, false))).unwrap();
+        let json = streamer.export_schedule_json().unwrap();
+
+        // Round trip through a fresh streamer reproduces the edit caches
+        let mut copy = new_streamer();
+        copy.import_schedule_json(&json).unwrap();
+        assert!(streamer.diff(&copy, None).unwrap().is_empty(), "{json}");
+        assert_eq!(copy.export_schedule_json().unwrap(), json);
+
+        // Sample rate mismatch and unknown devices are rejected before anything is added
+        let mut other = TestStreamer::new();
+        other.add_ao_dev("AO", 2e3);
+        other.ao_devs["AO"].add_chan("ao0", 0.0);
+        other.add_do_dev("DO", 1e3);
+        other.do_devs["DO"].add_chan("do0", false);
+        assert!(matches!(other.import_schedule_json(&json), Err(StreamerError::Incompatible { .. })));
+        let mut other = TestStreamer::new();
+        other.add_ao_dev("AO", 1e3);
+        other.ao_devs["AO"].add_chan("ao0", 0.0);
+        assert!(matches!(other.import_schedule_json(&json), Err(StreamerError::NotFound { .. })));
+        assert!(!other.ao_devs["AO"].got_instructions());
+
+        // Functions with non-scalar parameters can't be exported
+        let arr = ArrayFn::new(vec![1.0, 2.0], 0.1, Interp::Linear, 0.0);
+        streamer.ao_devs["AO"].chan_mut("ao1").unwrap().add_instr(Box::new(arr), 0.0, None).unwrap();
+        assert!(matches!(streamer.export_schedule_json(), Err(StreamerError::Incompatible { .. })));
+    }
+
+    #[test]
+    fn export_openpulse() {
+        let mut streamer = TestStreamer::new();
+        streamer.add_ao_dev("AO", 1e3);
+        streamer.add_do_dev("DO", 1e2);
+        streamer.ao_devs["AO"].add_chan("ao0", 0.0);
+        streamer.ao_devs["AO"].add_chan("ao1", 0.0);
+        streamer.do_devs["DO"].add_chan("do0", false);
+        for chan_name in ["ao0", "ao1"] {
+            let sine = StdFnLib::new().Sine(1.0, 10.0, 0.0, 0.0).unwrap().inner;
+            streamer.ao_devs["AO"].chan_mut(chan_name).unwrap().add_instr(sine, 0.3, Some((0.05, false))).unwrap();
+        }
+        let ao0 = streamer.ao_devs["AO"].chan_mut("ao0").unwrap();
+        ao0.constant(1.0, 0.1, Some((0.05, false))).unwrap();
+        ao0.constant(1.0, 0.15, Some((0.05, false))).unwrap();
+        streamer.do_devs["DO"].chan_mut("do0").unwrap().constant(true, 0.1, Some((0.1, false))).unwrap();
+        assert!(matches!(streamer.pulse_qobj(), Err(StreamerError::NotCompiled { .. })));
+        streamer.compile(Some(0.5)).unwrap();
+
+        let qobj = streamer.pulse_qobj().unwrap();
+        assert_eq!(qobj.frames.iter().map(|frame| frame.name.as_str()).collect::<Vec<_>>(), ["AO/ao0", "AO/ao1", "DO/do0"]);
+        assert_eq!(qobj.frames[2].dt, 1e-2);
+        // Both sines share one waveform
+        assert_eq!(qobj.pulse_library.len(), 1);
+        assert_eq!(qobj.pulse_library[0].samples.len(), 50);
+        let [re, im] = qobj.pulse_library[0].samples[5];
+        assert!((re - (2.0 * std::f64::consts::PI * 10.0 * 0.305).sin()).abs() < 1e-9 && im == 0.0);
+
+        let summary = |ch: &str| -> Vec<(String, usize, usize)> {
+            qobj.instructions.iter().filter(|instr| instr.ch == ch).map(|instr| {
+                let duration = instr.duration.or(instr.parameters.as_ref().map(|prms| prms.duration)).unwrap_or(0);
+                (instr.name.clone(), instr.t0, duration)
+            }).collect()
+        };
+        let stop_pos = streamer.ao_devs["AO"].compiled_stop_pos();
+        // Back-to-back constants with the same value are merged
+        assert_eq!(summary("AO/ao0"), [
+            ("delay".to_string(), 0, 100),
+            ("parametric_pulse".to_string(), 100, 100),
+            ("delay".to_string(), 200, 100),
+            ("wf0".to_string(), 300, 0),
+            ("delay".to_string(), 350, stop_pos - 350),
+        ]);
+        assert_eq!(summary("DO/do0")[1], ("parametric_pulse".to_string(), 10, 10));
+        assert!(qobj.instructions.windows(2).all(|pair| pair[0].t0 <= pair[1].t0));
+
+        let json = streamer.export_openpulse().unwrap();
+        let parsed: crate::openpulse::PulseQobj = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.instructions, qobj.instructions);
+        assert!(json.contains("\"pulse_shape\": \"constant\""));
+    }
+
+    #[test]
+    fn schedule_collision_report() {
+        let mut streamer = TestStreamer::new();
+        streamer.add_ao_dev("AO", 1e3);
+        streamer.ao_devs["AO"].add_chan("ao0", 0.0);
+        streamer.ao_devs["AO"].add_chan("ao1", 0.0);
+        streamer.ao_devs["AO"].chan_mut("ao0").unwrap().constant(1.0, 0.0, Some((0.1, false))).unwrap();
+
+        let rows = crate::schedule::parse_csv("\
+            AO, ao0, 0.05, 0.1, , ConstF64, 1\n\
+            AO, ao0, 0.12, 0.1, , ConstF64, 2\n\
+            AO, ao0, 0.3, , , ConstF64, 3\n\
+            AO, ao0, 0.3, 0.1, , ConstF64, 4\n\
+            AO, ao1, 0.0, 0.1, , ConstF64, 5\n\
+            AO, ao1, 0.0999, 0.1, , ConstF64, 6\n\
+        ").unwrap();
+        let report = streamer.schedule_collision_report(&rows).unwrap();
+        // line 1 vs the existing instruction and vs line 2, lines 3 and 4 start together; the 1-tick overlap on ao1 is auto-fixable
+        assert_eq!(report.len(), 3, "{report}");
+        let overlaps: Vec<_> = report.collisions.iter().map(|collision| (collision.chan.as_str(), collision.overlap)).collect();
+        assert_eq!(overlaps, [("ao0", 50), ("ao0", 30), ("ao0", 1)]);
+        let collision = &report.collisions[1];
+        assert_eq!(collision.dev.as_deref(), Some("AO"));
+        assert_eq!(collision.first.meta.as_ref().unwrap().label.as_deref(), Some("line 1"));
+        assert_eq!(collision.second.func, "ConstF64(2.0)");
+        assert!(report.to_string().contains("line 2"));
+        // Nothing was added
+        assert_eq!(streamer.ao_devs["AO"].chan("ao0").unwrap().instr_list().len(), 1);
+
+        let rows = crate::schedule::parse_csv("AO, ao2, 0.0, 0.1, , ConstF64, 1").unwrap();
+        assert!(matches!(streamer.schedule_collision_report(&rows), Err(StreamerError::NotFound { .. })));
+    }
+
+    #[test]
+    fn timeline() {
+        let mut streamer = TestStreamer::new();
+        streamer.add_ao_dev("AO", 1e3);
+        streamer.add_do_dev("DO", 1e3);
+        streamer.ao_devs["AO"].add_chan("ao0", 0.0);
+        streamer.ao_devs["AO"].add_chan("ao1", 0.0);
+        streamer.do_devs["DO"].add_chan("do0", false);
+        let ao0 = streamer.ao_devs["AO"].chan_mut("ao0").unwrap();
+        ao0.add_instr_with_meta(Box::new(ConstFn::new(1.0)), 0.1, Some((0.1, true)), Some(InstrMeta::labeled("<ramp & hold>"))).unwrap();
+        ao0.constant(2.0, 0.3, None).unwrap();
+        ao0.constant(0.0, 0.4, None).unwrap();
+        streamer.do_devs["DO"].chan_mut("do0").unwrap().constant(true, 0.2, Some((0.3, false))).unwrap();
+
+        let timeline = streamer.timeline();
+        assert_eq!(timeline.end_time, 0.5);
+        // Channels without instructions are left out
+        assert_eq!(timeline.rows.iter().map(|row| format!("{}/{}", row.dev, row.chan)).collect::<Vec<_>>(), ["AO/ao0", "DO/do0"]);
+        let bars = &timeline.rows[0].bars;
+        assert_eq!((bars[0].start, bars[0].end, bars[0].open, bars[0].keep_val), (0.1, 0.2, false, true));
+        assert_eq!(bars[0].label.as_deref(), Some("<ramp & hold>"));
+        // Open bars run up to the next instruction or the timeline end
+        assert_eq!((bars[1].start, bars[1].end, bars[1].open), (0.3, 0.4, true));
+        assert_eq!((bars[2].start, bars[2].end, bars[2].open), (0.4, 0.5, true));
+
+        streamer.compile(Some(1.0)).unwrap();
+        let timeline = streamer.timeline();
+        assert_eq!(timeline.end_time, 1.0);
+        assert_eq!(timeline.rows[0].bars[2].end, 1.0);
+
+        let parsed: crate::timeline::Timeline = serde_json::from_str(&streamer.export_timeline_json()).unwrap();
+        assert_eq!(parsed, timeline);
+        let svg = streamer.export_timeline_svg(1160.0);
+        assert!(svg.starts_with("<svg") && svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches("<rect").count(), 4);
+        // 0.1 s at 1000 px per second after the label column
+        assert!(svg.contains(r#"<rect x="260" y="23" width="100""#));
+        assert!(svg.contains("&lt;ramp &amp; hold&gt;: ConstFn(val=1.0)"));
+    }
+
+    #[test]
+    fn activity_windows() {
+        let mut streamer = TestStreamer::new();
+        streamer.add_ao_dev("AO", 1e3);
+        streamer.add_do_dev("DO", 1e3);
+        streamer.ao_devs["AO"].add_chan("ao0", 0.0);
+        streamer.ao_devs["AO"].add_chan("ao1", 0.0);
+        streamer.ao_devs["AO"].add_chan("ao2", 0.0);
+        streamer.do_devs["DO"].add_chan("gate", false);
+        assert_eq!(streamer.first_instr_start_time(), None);
+        assert!(streamer.activity_windows().is_empty());
+
+        streamer.ao_devs["AO"].chan_mut("ao0").unwrap().constant(1.0, 0.2, Some((0.1, false))).unwrap();
+        streamer.ao_devs["AO"].chan_mut("ao0").unwrap().constant(2.0, 0.5, Some((0.1, false))).unwrap();
+        streamer.ao_devs["AO"].chan_mut("ao1").unwrap().constant(1.0, 0.1, None).unwrap();
+        let ao0 = streamer.ao_devs["AO"].chan("ao0").unwrap();
+        assert_eq!((ao0.first_instr_start_time(), ao0.activity_window()), (Some(0.2), Some((0.2, 0.6))));
+        assert_eq!(streamer.ao_devs["AO"].activity_window(), Some((0.1, 0.6)));
+        assert_eq!(streamer.first_instr_start_time(), Some(0.1));
+
+        // Only active channels and devices are listed
+        let windows = streamer.activity_windows();
+        assert_eq!(windows.keys().collect::<Vec<_>>(), vec!["AO"]);
+        // A "go-this" instruction is active for one clock tick
+        assert_eq!(windows["AO"].iter().collect::<Vec<_>>(), vec![(&"ao0".to_string(), &(0.2, 0.6)), (&"ao1".to_string(), &(0.1, 0.101))]);
+    }
+
+    #[test]
+    fn instr_count_and_density() {
+        let mut streamer = TestStreamer::new();
+        streamer.add_ao_dev("AO", 1e3);
+        streamer.add_do_dev("DO", 1e3);
+        streamer.ao_devs["AO"].add_chan("ao0", 0.0);
+        streamer.do_devs["DO"].add_chan("gate", false);
+        streamer.do_devs["DO"].add_chan("idle", false);
+        streamer.ao_devs["AO"].chan_mut("ao0").unwrap().constant(1.0, 0.1, Some((0.1, false))).unwrap();
+        // Pathological: a pulse every other tick
+        for tick in 0..100 {
+            streamer.do_devs["DO"].chan_mut("gate").unwrap().constant(true, tick as f64 * 2e-3, Some((1e-3, false))).unwrap();
+        }
+        assert_eq!((streamer.ao_devs["AO"].instr_count(), streamer.do_devs["DO"].instr_count(), streamer.instr_count()), (1, 100, 101));
+        assert!(matches!(streamer.segment_densities(), Err(StreamerError::NotCompiled { .. })));
+
+        streamer.compile(Some(1.0)).unwrap();
+        let densities = streamer.segment_densities().unwrap();
+        // AO: padding before, the pulse, padding after. DO: a pulse and a gap (the last one up to the end) per period
+        assert_eq!(densities["AO"]["ao0"], 3.0);
+        assert_eq!(densities["DO"]["gate"], 200.0);
+        assert!(!densities["DO"].contains_key("idle"));
+        assert_eq!(streamer.do_devs["DO"].chan("gate").unwrap().compiled_segment_count().unwrap(), 200);
+    }
+
+    #[test]
+    fn mute_chan() {
+        let mut streamer = TestStreamer::new();
+        streamer.add_ao_dev("AO", 1e3);
+        streamer.add_do_dev("DO", 1e3);
+        streamer.ao_devs["AO"].add_chan("ao0", 0.0);
+        streamer.ao_devs["AO"].add_chan("ao1", 0.5);
+        streamer.do_devs["DO"].add_chan("gate", false);
+        streamer.ao_devs["AO"].chan_mut("ao0").unwrap().constant(1.0, 0.1, Some((0.1, false))).unwrap();
+        streamer.ao_devs["AO"].chan_mut("ao1").unwrap().constant(2.0, 0.1, Some((0.1, false))).unwrap();
+        streamer.do_devs["DO"].chan_mut("gate").unwrap().constant(true, 0.5, Some((0.5, false))).unwrap();
+        streamer.compile(None).unwrap();
+
+        streamer.set_chan_enabled("AO", "ao1", false).unwrap();
+        streamer.set_chan_enabled("DO", "gate", false).unwrap();
+        assert!(matches!(streamer.set_chan_enabled("AO", "nope", false), Err(StreamerError::NotFound { .. })));
+        assert!(matches!(streamer.set_chan_enabled("nope", "ao1", false), Err(StreamerError::NotFound { .. })));
+        // Muted channels are inactive but keep their instructions - and the sequence length
+        assert_eq!(streamer.active_dev_names(), vec!["AO"]);
+        assert_eq!(streamer.ao_devs["AO"].active_chans().len(), 1);
+        assert_eq!(streamer.ao_devs["AO"].chan("ao1").unwrap().instr_count(), 1);
+        assert!(!streamer.ao_devs["AO"].info().chan("ao1").unwrap().is_enabled);
+        assert_eq!(streamer.compile(None).unwrap(), 1.0);
+        assert_eq!(streamer.ao_devs["AO"].chan("ao0").unwrap().eval_point(0.15).unwrap(), 1.0);
+        let segs = streamer.ao_devs["AO"].info().chan("ao0").unwrap().compiled_segments().unwrap();
+        assert_eq!(segs.iter().map(|&(start, end, _)| (start, end)).collect::<Vec<_>>(), [(0, 100), (100, 200), (200, 1000)]);
+
+        // Compiled directly, a muted channel gives its default value
+        let ao1 = streamer.ao_devs["AO"].chan_mut("ao1").unwrap();
+        ao1.compile(1000).unwrap();
+        assert_eq!(ao1.compile_cache_ends(), &vec![1000]);
+        assert_eq!(ao1.compile_cache_fns()[0].const_val(), Some(0.5));
+
+        streamer.set_chan_enabled("AO", "ao1", true).unwrap();
+        assert!(!streamer.ao_devs["AO"].chan("ao1").unwrap().is_fresh_compiled());
+        streamer.compile(None).unwrap();
+        assert_eq!(streamer.ao_devs["AO"].chan("ao1").unwrap().eval_point(0.15).unwrap(), 2.0);
+    }
+
+    #[test]
+    fn safe_state() {
+        let mut streamer = TestStreamer::new();
+        streamer.add_ao_dev("AO", 1e3);
+        streamer.add_do_dev("DO", 1e3);
+        streamer.ao_devs["AO"].add_chan("ao0", 0.5);
+        streamer.ao_devs["AO"].add_chan("ao1", -1.0);
+        streamer.do_devs["DO"].add_chan("gate", false);
+        streamer.ao_devs["AO"].chan_mut("ao0").unwrap().constant(3.0, 0.1, Some((0.5, true))).unwrap();
+        streamer.ao_devs["AO"].chan_mut("ao1").unwrap().constant(2.0, 0.0, None).unwrap();
+        streamer.set_chan_enabled("AO", "ao1", false).unwrap();
+        streamer.solo(&["AO/ao0"]).unwrap();
+
+        let state = streamer.safe_state();
+        assert_eq!(state["AO"].values().copied().collect::<Vec<_>>(), vec![0.5, -1.0]);
+        assert_eq!(state["DO"]["gate"], 0.0);
+
+        // All or nothing - AO succeeds, but DO can't compile within its trigger delay: nothing changes
+        streamer.set_trigger_delay("DO", 0.02).unwrap();
+        for dur in [0.0, 0.01] {
+            assert!(streamer.compile_safe_state(dur).is_err());
+            assert_eq!(streamer.instr_count(), 2);
+            assert!(!streamer.ao_devs["AO"].chan("ao1").unwrap().is_enabled());
+            assert!(streamer.selection().is_some_and(|selection| !selection.is_empty()));
+        }
+        streamer.set_trigger_delay("DO", 0.0).unwrap();
+
+        // Every channel is driven - including muted ones, inactive devices, and channels outside the solo selection
+        assert_eq!(streamer.compile_safe_state(0.01).unwrap(), 0.01);
+        assert_eq!(streamer.active_dev_names(), vec!["AO", "DO"]);
+        let targets = streamer.run_mock(100).unwrap();
+        let ao_target = targets["AO"].downcast_ref::<MockStreamTarget<f64>>().unwrap();
+        assert_eq!(ao_target.chan_samps("ao0").unwrap(), vec![0.5; 10]);
+        assert_eq!(ao_target.chan_samps("ao1").unwrap(), vec![-1.0; 10]);
+        assert_eq!(streamer.instr_count(), 3);
+    }
+
+    #[test]
+    fn shift_all() {
+        let mut streamer = TestStreamer::new();
+        streamer.add_ao_dev("AO", 1e3);
+        streamer.ao_devs["AO"].add_chan("ao0", 0.0);
+        let sine = StdFnLib::new().Sine(1.0, 10.0, 0.0, 0.0).unwrap().inner;
+        streamer.ao_devs["AO"].chan_mut("ao0").unwrap().add_instr(sine, 0.1, Some((0.1, false))).unwrap();
+        streamer.compile(None).unwrap();
+        let before = streamer.ao_devs["AO"].chan("ao0").unwrap().eval_point(0.125).unwrap();
+
+        // Moving to negative time is rejected and nothing is changed
+        assert!(matches!(streamer.shift_all(-0.2), Err(StreamerError::OutOfRange { .. })));
+        assert_eq!(streamer.ao_devs["AO"].first_instr_start_pos(), Some(100));
+
+        streamer.shift_all(0.5).unwrap();
+        let chan = streamer.ao_devs["AO"].chan("ao0").unwrap();
+        assert_eq!(chan.first_instr_start_pos(), Some(600));
+        assert_eq!(chan.last_instr_end_pos(), Some(700));
+        assert!(!chan.is_fresh_compiled());
+        // The waveform moves together with the instruction
+        assert!((chan.eval_point(0.625).unwrap() - before).abs() < 1e-9);
+    }
+
+    #[test]
+    fn compile_many_devs() {
+        // With the `parallel` feature devices are compiled concurrently - the outcome must be the same
+        let mut streamer = TestStreamer::new();
+        for dev_idx in 0..12 {
+            let dev_name = format!("AO{dev_idx}");
+            streamer.add_ao_dev(&dev_name, 1e3);
+            let dev = &mut streamer.ao_devs[dev_name.as_str()];
+            for chan_idx in 0..4 {
+                let chan_name = format!("ao{chan_idx}");
+                dev.add_chan(&chan_name, 0.0);
+                let t = 0.01 * (dev_idx + chan_idx) as f64;
+                dev.chan_mut(&chan_name).unwrap().constant(1.0, t, Some((0.1, false))).unwrap();
+            }
+        }
+        // Inactive device is skipped
+        streamer.add_ao_dev("AO_idle", 1e3);
+        streamer.ao_devs["AO_idle"].add_chan("ao0", 0.0);
+
+        assert_eq!(streamer.compile(Some(1.0)).unwrap(), 1.0);
+        assert!(streamer.validate_compile_cache().is_ok());
+        for dev_idx in 0..12 {
+            let dev = &streamer.ao_devs[format!("AO{dev_idx}").as_str()];
+            assert_eq!(dev.compiled_stop_pos(), 1000);
+            let start_pos = 10 * dev_idx;
+            let expected_ends = if start_pos == 0 { vec![100, 1000] } else { vec![start_pos, start_pos + 100, 1000] };
+            assert_eq!(dev.chan("ao0").unwrap().compile_cache_ends(), &expected_ends);
+        }
+        assert!(streamer.ao_devs["AO_idle"].chan("ao0").unwrap().compile_cache_ends().is_empty());
+    }
+
+    #[test]
+    fn prepend_block() {
+        let mut streamer = TestStreamer::new();
+        streamer.add_ao_dev("AO", 1e3);
+        streamer.ao_devs["AO"].add_chan("ao0", 0.0);
+        streamer.ao_devs["AO"].chan_mut("ao0").unwrap().constant(1.0, 0.0, Some((0.1, false))).unwrap();
+
+        let mut block = TestStreamer::new();
+        block.add_ao_dev("AO", 1e3);
+        block.ao_devs["AO"].add_chan("ao0", 0.0);
+        block.ao_devs["AO"].chan_mut("ao0").unwrap().constant(2.0, 0.0, Some((0.3, false))).unwrap();
+
+        streamer.prepend_block(&block).unwrap();
+        streamer.compile(None).unwrap();
+        let chan = streamer.ao_devs["AO"].chan("ao0").unwrap();
+        assert_eq!(chan.instr_list().len(), 2);
+        assert_eq!(chan.eval_point(0.1).unwrap(), 2.0);
+        assert_eq!(chan.eval_point(0.35).unwrap(), 1.0);
+        assert_eq!(chan.compiled_stop_pos(), 401);
+
+        // Block devices must be registered in the streamer
+        let mut block = TestStreamer::new();
+        block.add_do_dev("DO", 1e3);
+        block.do_devs["DO"].add_chan("port0/line0", false);
+        block.do_devs["DO"].chan_mut("port0/line0").unwrap().constant(true, 0.0, None).unwrap();
+        assert!(matches!(streamer.prepend_block(&block), Err(StreamerError::NotFound { .. })));
+
+        // A missing channel or a different sample rate is caught before the existing sequence is shifted
+        let mut block = TestStreamer::new();
+        block.add_ao_dev("AO", 1e3);
+        block.ao_devs["AO"].add_chan("ao1", 0.0);
+        block.ao_devs["AO"].chan_mut("ao1").unwrap().constant(2.0, 0.0, Some((0.3, false))).unwrap();
+        assert!(matches!(streamer.prepend_block(&block), Err(StreamerError::NotFound { .. })));
+        let mut block = TestStreamer::new();
+        block.add_ao_dev("AO", 2e3);
+        block.ao_devs["AO"].add_chan("ao0", 0.0);
+        block.ao_devs["AO"].chan_mut("ao0").unwrap().constant(2.0, 0.0, Some((0.3, false))).unwrap();
+        assert!(matches!(streamer.prepend_block(&block), Err(StreamerError::Incompatible { .. })));
+        assert_eq!(streamer.ao_devs["AO"].first_instr_start_pos(), Some(0));
+        assert_eq!(streamer.ao_devs["AO"].chan("ao0").unwrap().instr_list().len(), 2);
+    }
+
+    #[test]
+    fn crop() {
+        let mut streamer = TestStreamer::new();
+        streamer.add_ao_dev("AO", 1e3);
+        streamer.add_do_dev("DO", 1e3);
+        streamer.ao_devs["AO"].add_chan("ao0", 0.0);
+        streamer.ao_devs["AO"].add_chan("ao1", 0.0);
+        streamer.do_devs["DO"].add_chan("port0/line0", false);
+        let ramp = StdFnLib::new().LinFn(10.0, 0.0).unwrap().inner;
+        let ao0 = streamer.ao_devs["AO"].chan_mut("ao0").unwrap();
+        ao0.add_instr(ramp, 0.0, Some((0.5, false))).unwrap();
+        ao0.constant(-1.0, 0.7, Some((0.1, false))).unwrap();
+        // `keep_val` padding running across the window start
+        let ao1 = streamer.ao_devs["AO"].chan_mut("ao1").unwrap();
+        ao1.constant(2.0, 0.0, Some((0.1, true))).unwrap();
+        ao1.constant(3.0, 0.6, None).unwrap();
+        streamer.do_devs["DO"].chan_mut("port0/line0").unwrap().constant(true, 0.1, Some((0.1, false))).unwrap();
+
+        assert!(matches!(streamer.crop(0.3, 0.3), Err(StreamerError::InvalidArgument { .. })));
+        streamer.crop(0.25, 0.75).unwrap();
+        assert_eq!(streamer.last_instr_end_time(), Some(0.5));
+        assert_eq!(streamer.do_devs["DO"].active_chans().len(), 0);
+        streamer.compile(None).unwrap();
+
+        let ao0 = streamer.ao_devs["AO"].chan("ao0").unwrap();
+        assert_eq!(ao0.instr_list().len(), 2);
+        // The ramp continues from where it was cut
+        assert!((ao0.eval_point(0.0).unwrap() - 2.5).abs() < 1e-9);
+        assert!((ao0.eval_point(0.125).unwrap() - 3.75).abs() < 1e-9);
+        assert_eq!(ao0.eval_point(0.375).unwrap(), 0.0);
+        assert_eq!(ao0.eval_point(0.47).unwrap(), -1.0);
+        let ao1 = streamer.ao_devs["AO"].chan("ao1").unwrap();
+        assert_eq!((ao1.eval_point(0.0).unwrap(), ao1.eval_point(0.375).unwrap()), (2.0, 3.0));
+    }
+
+    #[test]
+    fn concat() {
+        let mut streamer = TestStreamer::new();
+        streamer.add_ao_dev("AO", 1e3);
+        streamer.ao_devs["AO"].add_chan("ao0", 0.0);
+        streamer.ao_devs["AO"].chan_mut("ao0").unwrap().constant(1.0, 0.0, Some((0.1, false))).unwrap();
+
+        let mut block = TestStreamer::new();
+        block.add_ao_dev("AO", 1e3);
+        block.ao_devs["AO"].add_chan("ao0", 0.0);
+        let ramp = StdFnLib::new().LinFn(10.0, 0.0).unwrap().inner;
+        block.ao_devs["AO"].chan_mut("ao0").unwrap().add_instr(ramp, 0.0, Some((0.2, false))).unwrap();
+
+        streamer.concat(&block, 0.05).unwrap();
+        assert!((streamer.last_instr_end_time().unwrap() - 0.35).abs() < 1e-9);
+        streamer.compile(None).unwrap();
+        let chan = streamer.ao_devs["AO"].chan("ao0").unwrap();
+        assert_eq!(chan.instr_list().len(), 2);
+        assert_eq!(chan.eval_point(0.12).unwrap(), 0.0);
+        // The ramp starts from 0 at its new start time
+        assert!((chan.eval_point(0.25).unwrap() - 1.0).abs() < 1e-9);
+
+        assert!(matches!(streamer.concat(&block, -0.1), Err(StreamerError::InvalidArgument { .. })));
+
+        // A later block device failing its checks leaves the earlier ones untouched
+        streamer.add_do_dev("DO", 1e3);
+        streamer.do_devs["DO"].add_chan("port0/line0", false);
+        let mut block = TestStreamer::new();
+        block.add_ao_dev("AO", 1e3);
+        block.add_do_dev("DO", 1e3);
+        block.ao_devs["AO"].add_chan("ao0", 0.0);
+        block.do_devs["DO"].add_chan("port0/line1", false);
+        block.ao_devs["AO"].chan_mut("ao0").unwrap().constant(3.0, 0.0, Some((0.1, false))).unwrap();
+        block.do_devs["DO"].chan_mut("port0/line1").unwrap().constant(true, 0.0, Some((0.1, false))).unwrap();
+        assert!(matches!(streamer.concat(&block, 0.0), Err(StreamerError::NotFound { .. })));
+        assert_eq!(streamer.ao_devs["AO"].chan("ao0").unwrap().instr_list().len(), 2);
+    }
+
+    #[test]
+    fn trigger_delay() {
+        let mut streamer = TestStreamer::new();
+        streamer.add_ao_dev("Fast", 100.0);
+        streamer.add_ao_dev("Slow", 100.0);
+        for dev_name in ["Fast", "Slow"] {
+            streamer.ao_devs[dev_name].add_chan("ao0", 0.0);
+            let ramp = StdFnLib::new().LinFn(10.0, 0.0).unwrap().inner;
+            streamer.ao_devs[dev_name].chan_mut("ao0").unwrap().add_instr(ramp, 0.1, Some((0.2, false))).unwrap();
+        }
+        assert!(matches!(streamer.set_trigger_delay("Slow", -0.01), Err(StreamerError::InvalidArgument { .. })));
+        assert!(matches!(streamer.set_trigger_delay("Nope", 0.01), Err(StreamerError::NotFound { .. })));
+        streamer.set_trigger_delay("Slow", 0.02).unwrap();
+        streamer.compile(Some(0.5)).unwrap();
+        assert!(streamer.validation_report().is_valid());
+
+        // The delayed device starts generating its waveform 2 ticks earlier and stops 2 ticks earlier
+        let targets = streamer.run_mock(16).unwrap();
+        let samps = |dev_name: &str| targets[dev_name].downcast_ref::<MockStreamTarget<f64>>().unwrap().chan_samps("ao0").unwrap().clone();
+        let (fast, slow) = (samps("Fast"), samps("Slow"));
+        assert_eq!((fast.len(), slow.len()), (50, 48));
+        assert!(fast[2..].iter().zip(slow.iter()).all(|(fast_val, slow_val)| (fast_val - slow_val).abs() < 1e-9));
+        assert_eq!((slow[7], slow[8]), (0.0, fast[10]));
+        let padding = streamer.padding_report().unwrap();
+        assert_eq!(padding["Slow"]["ao0"][0].end_pos, 8);
+
+        // Instructions can't start before the delay
+        streamer.set_trigger_delay("Slow", 0.2).unwrap();
+        assert!(matches!(streamer.compile(Some(0.5)), Err(StreamerError::OutOfRange { .. })));
+    }
+
+    #[test]
+    fn samp_limit() {
+        use crate::closing_edge::ClosingEdge;
+
+        let mut streamer = TestStreamer::new();
+        streamer.add_ao_dev("AO", 1e5);
+        streamer.add_do_dev("DO", 1e5);
+        streamer.ao_devs["AO"].add_chan("ao0", 0.0);
+        streamer.ao_devs["AO"].add_chan("ao1", 0.0);
+        streamer.do_devs["DO"].add_chan("port0/line0", false);
+        let chan = streamer.ao_devs["AO"].chan_mut("ao0").unwrap();
+        assert!(matches!(chan.constant(1.0, f64::NAN, None), Err(StreamerError::NonFinite { .. })));
+        assert!(matches!(chan.constant(1.0, -0.1, None), Err(StreamerError::OutOfRange { .. })));
+        assert!(matches!(chan.constant(1.0, 1e12, None), Err(StreamerError::OutOfRange { .. })));
+        chan.constant(1.0, 0.1, Some((0.1, false))).unwrap();
+        streamer.ao_devs["AO"].chan_mut("ao1").unwrap().constant(1.0, 0.1, Some((0.1, false))).unwrap();
+        streamer.do_devs["DO"].chan_mut("port0/line0").unwrap().constant(true, 0.1, Some((0.1, false))).unwrap();
+        assert!(matches!(streamer.compile(Some(1e12)), Err(StreamerError::OutOfRange { .. })));
+        assert!(matches!(streamer.compile(Some(f64::INFINITY)), Err(StreamerError::NonFinite { .. })));
+
+        // 2 AO channels and 1 DO channel with 100_000 samples each - no closing edge since nothing ends at the stop tick
+        streamer.set_samp_limit(Some(299_999)).unwrap();
+        assert!(matches!(streamer.compile(Some(1.0)), Err(StreamerError::OutOfRange { .. })));
+        streamer.set_samp_limit(Some(300_000)).unwrap();
+        streamer.compile(Some(1.0)).unwrap();
+        // Stopping at the pulse ends adds the closing edge samples
+        streamer.set_samp_limit(Some(60_002)).unwrap();
+        assert!(matches!(streamer.compile(Some(0.2)), Err(StreamerError::OutOfRange { .. })));
+        streamer.set_samp_limit(Some(60_003)).unwrap();
+        streamer.compile(Some(0.2)).unwrap();
+        streamer.set_closing_edge_all(ClosingEdge::Always).unwrap();
+        assert!(matches!(streamer.compile(Some(1.0)), Err(StreamerError::OutOfRange { .. })));
+        streamer.set_samp_limit(None).unwrap();
+        streamer.compile(Some(2.0)).unwrap();
+    }
+
+    #[test]
+    fn calc_all() {
+        let mut streamer = TestStreamer::new();
+        streamer.add_ao_dev("AO", 1e3);
+        streamer.add_do_dev("DO", 1e4);
+        streamer.add_ao_dev("Idle", 1e3);
+        streamer.ao_devs["AO"].add_chan("ao0", 0.0);
+        streamer.ao_devs["AO"].add_chan("ao1", 0.0);
+        streamer.do_devs["DO"].add_chan("port0/line0", false);
+        streamer.ao_devs["AO"].chan_mut("ao0").unwrap().constant(1.0, 0.15, Some((0.4, false))).unwrap();
+        streamer.do_devs["DO"].chan_mut("port0/line0").unwrap().constant(true, 0.25, Some((0.2, false))).unwrap();
+        assert!(matches!(streamer.calc_all(11, None, None), Err(StreamerError::NotCompiled { .. })));
+        streamer.compile(Some(1.0)).unwrap();
+
+        let (t_arr, chans) = streamer.calc_all(11, None, Some(1.0)).unwrap();
+        assert_eq!(t_arr.len(), 11);
+        assert!((t_arr[3] - 0.3).abs() < 1e-12);
+        // Only active channels, named "dev/chan"
+        assert_eq!(chans.keys().collect::<Vec<_>>(), ["AO/ao0", "DO/port0/line0"]);
+        assert_eq!(chans["AO/ao0"], [0.0, 0.0, 1.0, 1.0, 1.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0]);
+        assert_eq!(chans["DO/port0/line0"], [0.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]);
+
+        // The default end is the stop time of the device which finishes first
+        let (t_arr, _) = streamer.calc_all(3, Some(0.5), None).unwrap();
+        assert_eq!(t_arr, [0.5, 0.75, 1.0]);
+        assert!(matches!(streamer.calc_all(3, None, Some(1.0005)), Err(StreamerError::OutOfRange { .. })));
+    }
+
+    #[test]
+    fn pulse_array() {
+        use crate::fn_lib_tools::FnTraitSet;
+
+        let mut streamer = TestStreamer::new();
+        streamer.add_ao_dev("AO", 1e3);
+        streamer.ao_devs["AO"].add_chan("ao0", 0.0);
+        streamer.add_do_dev("DO", 1e3);
+        streamer.do_devs["DO"].add_chan("port0/line0", false);
+
+        let amp = || -> Box<dyn FnTraitSet<f64>> { Box::new(ConstFn::new(2.0)) };
+        streamer.add_pulse_array("AO", "ao0", amp(), &[0.0, 0.1], 0.01, false).unwrap();
+        assert_eq!(streamer.ao_devs["AO"].chan("ao0").unwrap().instr_count(), 2);
+        // Function of the wrong sample type, unknown device and channel
+        let err = streamer.add_pulse_array("DO", "port0/line0", amp(), &[0.0], 0.01, false).unwrap_err();
+        assert!(matches!(err, StreamerError::Incompatible { .. }));
+        assert_eq!(err.ctx().dev.as_deref(), Some("DO"));
+        assert!(matches!(streamer.add_pulse_array("AI", "ao0", amp(), &[0.0], 0.01, false), Err(StreamerError::NotFound { .. })));
+        assert!(matches!(streamer.add_pulse_array("AO", "ao1", amp(), &[0.0], 0.01, false), Err(StreamerError::NotFound { .. })));
+        // Channel errors carry the device name
+        let err = streamer.add_pulse_array("AO", "ao0", amp(), &[0.105], 0.01, false).unwrap_err();
+        assert!(matches!(err, StreamerError::Collision { .. }));
+        assert_eq!(err.to_string().split(']').next(), Some("[AO/ao0"));
+    }
+}
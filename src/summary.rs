@@ -0,0 +1,216 @@
+//! Human-readable summaries of the streamer state, e.g. for lab notebooks.
+//!
+//! A summary lists every device with its sample rate, active channels, instruction counts, first and last
+//! instruction times, and - once compiled - the compiled stop time, the total number of samples to stream,
+//! and the estimated data rate (`samp_rate * active channels * sample size`).
+//!
+//! Summaries are built by [`BaseDev::summary`] and [`BaseStreamer::summary`]. `Display` gives the pretty-printed form,
+//! use `to_dict()` to pass them to Python.
+//!
+//! [`BaseDev::summary`]: crate::device::BaseDev::summary
+//! [`BaseStreamer::summary`]: crate::streamer::BaseStreamer::summary
+
+use std::fmt;
+use std::fmt::Display;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChanSummary {
+    pub name: String,
+    /// Number of base-layer instructions
+    pub n_instrs: usize,
+    pub first_instr_time: Option<f64>,
+    pub last_instr_end_time: Option<f64>,
+}
+
+impl ChanSummary {
+    pub fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("name", &self.name)?;
+        dict.set_item("n_instrs", self.n_instrs)?;
+        dict.set_item("first_instr_time", self.first_instr_time)?;
+        dict.set_item("last_instr_end_time", self.last_instr_end_time)?;
+        Ok(dict)
+    }
+}
+
+impl Display for ChanSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} instructions", self.name, self.n_instrs)?;
+        if let (Some(first), Some(last)) = (self.first_instr_time, self.last_instr_end_time) {
+            write!(f, ", first starts at {first} s, last ends at {last} s")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DevSummary {
+    pub name: String,
+    pub samp_rate: f64,
+    /// Size of one sample in bytes
+    pub samp_bytes: usize,
+    /// Number of all channels, active or not
+    pub n_chans: usize,
+    /// Summaries of active channels only
+    pub chans: Vec<ChanSummary>,
+    /// `None` if the device is inactive or not freshly compiled
+    pub compiled_stop_pos: Option<usize>,
+}
+
+impl DevSummary {
+    pub fn n_instrs(&self) -> usize {
+        self.chans.iter().map(|chan| chan.n_instrs).sum()
+    }
+    pub fn first_instr_time(&self) -> Option<f64> {
+        self.chans.iter().filter_map(|chan| chan.first_instr_time).reduce(f64::min)
+    }
+    pub fn last_instr_end_time(&self) -> Option<f64> {
+        self.chans.iter().filter_map(|chan| chan.last_instr_end_time).reduce(f64::max)
+    }
+    pub fn compiled_stop_time(&self) -> Option<f64> {
+        self.compiled_stop_pos.map(|stop_pos| stop_pos as f64 / self.samp_rate)
+    }
+    /// Samples of all active channels over the compiled sequence
+    pub fn total_samps(&self) -> Option<usize> {
+        self.compiled_stop_pos.map(|stop_pos| stop_pos * self.chans.len())
+    }
+    /// Estimated streaming data rate in bytes per second
+    pub fn data_rate(&self) -> f64 {
+        self.samp_rate * (self.chans.len() * self.samp_bytes) as f64
+    }
+
+    pub fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("name", &self.name)?;
+        dict.set_item("samp_rate", self.samp_rate)?;
+        dict.set_item("n_chans", self.n_chans)?;
+        dict.set_item("n_instrs", self.n_instrs())?;
+        dict.set_item("first_instr_time", self.first_instr_time())?;
+        dict.set_item("last_instr_end_time", self.last_instr_end_time())?;
+        dict.set_item("compiled_stop_time", self.compiled_stop_time())?;
+        dict.set_item("total_samps", self.total_samps())?;
+        dict.set_item("data_rate", self.data_rate())?;
+        let chans = PyDict::new_bound(py);
+        for chan in self.chans.iter() {
+            chans.set_item(&chan.name, chan.to_dict(py)?)?;
+        }
+        dict.set_item("chans", chans)?;
+        Ok(dict)
+    }
+}
+
+impl Display for DevSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f, "device {}: samp_rate={} Hz, {}/{} channels active, {} instructions",
+            self.name, self.samp_rate, self.chans.len(), self.n_chans, self.n_instrs()
+        )?;
+        match (self.compiled_stop_time(), self.total_samps()) {
+            (Some(stop_time), Some(total_samps)) => writeln!(
+                f, ", compiled_stop_time={stop_time} s, total_samps={total_samps}, data_rate={}",
+                fmt_data_rate(self.data_rate())
+            )?,
+            _ => writeln!(f, ", not compiled")?,
+        }
+        for chan in self.chans.iter() {
+            writeln!(f, "\t{chan}")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct StreamerSummary {
+    /// Summaries of all devices, active or not
+    pub devs: Vec<DevSummary>,
+}
+
+impl StreamerSummary {
+    pub fn n_instrs(&self) -> usize {
+        self.devs.iter().map(|dev| dev.n_instrs()).sum()
+    }
+    pub fn total_samps(&self) -> usize {
+        self.devs.iter().filter_map(|dev| dev.total_samps()).sum()
+    }
+    pub fn data_rate(&self) -> f64 {
+        self.devs.iter().map(|dev| dev.data_rate()).sum()
+    }
+
+    pub fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("n_instrs", self.n_instrs())?;
+        dict.set_item("total_samps", self.total_samps())?;
+        dict.set_item("data_rate", self.data_rate())?;
+        let devs = PyDict::new_bound(py);
+        for dev in self.devs.iter() {
+            devs.set_item(&dev.name, dev.to_dict(py)?)?;
+        }
+        dict.set_item("devs", devs)?;
+        Ok(dict)
+    }
+}
+
+impl Display for StreamerSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f, "streamer: {} devices, {} instructions, total_samps={}, data_rate={}",
+            self.devs.len(), self.n_instrs(), self.total_samps(), fmt_data_rate(self.data_rate())
+        )?;
+        for dev in self.devs.iter() {
+            write!(f, "{dev}")?;
+        }
+        Ok(())
+    }
+}
+
+fn fmt_data_rate(bytes_per_sec: f64) -> String {
+    match bytes_per_sec {
+        rate if rate >= 1e9 => format!("{:.2} GB/s", rate / 1e9),
+        rate if rate >= 1e6 => format!("{:.2} MB/s", rate / 1e6),
+        rate if rate >= 1e3 => format!("{:.2} kB/s", rate / 1e3),
+        rate => format!("{rate:.0} B/s"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::channel::BaseChan;
+    use crate::device::BaseDev;
+    use crate::mock::test_impls::TestStreamer;
+    use crate::streamer::BaseStreamer;
+
+    #[test]
+    fn summary() {
+        let mut streamer = TestStreamer::new();
+        streamer.add_ao_dev("AO", 1e3);
+        streamer.add_do_dev("DO", 1e3);
+        streamer.ao_devs["AO"].add_chan("ao0", 0.0);
+        streamer.ao_devs["AO"].add_chan("ao1", 0.0);
+        streamer.ao_devs["AO"].add_chan("ao2", 0.0);
+        streamer.do_devs["DO"].add_chan("port0/line0", false);
+        streamer.ao_devs["AO"].chan_mut("ao0").unwrap().constant(1.0, 0.1, Some((0.1, false))).unwrap();
+        streamer.ao_devs["AO"].chan_mut("ao0").unwrap().constant(2.0, 0.3, Some((0.1, false))).unwrap();
+        streamer.ao_devs["AO"].chan_mut("ao1").unwrap().constant(1.0, 0.0, Some((0.5, false))).unwrap();
+
+        let summary = streamer.summary();
+        let ao = &summary.devs[0];
+        assert_eq!((ao.n_chans, ao.chans.len(), ao.n_instrs()), (3, 2, 3));
+        assert_eq!((ao.first_instr_time(), ao.last_instr_end_time()), (Some(0.0), Some(0.5)));
+        assert_eq!((ao.compiled_stop_time(), ao.total_samps()), (None, None));
+        assert!(ao.to_string().contains("not compiled"));
+        // Inactive devices are listed too
+        assert_eq!(summary.devs[1].chans.len(), 0);
+
+        streamer.compile(Some(1.0)).unwrap();
+        let summary = streamer.summary();
+        let ao = &summary.devs[0];
+        assert_eq!((ao.compiled_stop_time(), ao.total_samps()), (Some(1.0), Some(2000)));
+        assert_eq!(ao.data_rate(), 16e3);
+        assert_eq!((summary.n_instrs(), summary.total_samps(), summary.data_rate()), (3, 2000, 16e3));
+        let text = summary.to_string();
+        assert!(text.starts_with("streamer: 2 devices, 3 instructions, total_samps=2000, data_rate=16.00 kB/s\n"));
+        assert!(text.contains("\tao0: 2 instructions, first starts at 0.1 s, last ends at 0.4 s\n"));
+    }
+}
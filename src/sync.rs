@@ -0,0 +1,156 @@
+//! Trigger and clock metadata of devices - which device starts the others and which reference clock they share.
+//!
+//! Every hardware backend has to know this to arm devices in the right order (secondaries first, the primary last)
+//! and to route clocks. A [`SyncSpec`] describes one device: its [`SyncRole`], the device whose start trigger it
+//! follows, the terminal the trigger arrives on, and its reference clock. Specs are set with [`BaseDev::set_sync_spec`].
+//!
+//! [`BaseStreamer::check_sync`] validates the configured devices as a whole: exactly one primary which doesn't follow
+//! any trigger, trigger sources which are registered devices, no trigger cycles, and one frequency per reference clock source.
+//!
+//! [`BaseDev::set_sync_spec`]: crate::device::BaseDev::set_sync_spec
+//! [`BaseStreamer::check_sync`]: crate::streamer::BaseStreamer::check_sync
+
+use std::fmt;
+use std::fmt::Display;
+use indexmap::IndexMap;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SyncRole {
+    /// Starts the run (software start) and exports the start trigger
+    Primary,
+    /// Armed before the primary and started by a trigger
+    Secondary,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RefClock {
+    /// Where the clock comes from, e.g. `"PXI_Clk10"` or `"Dev1/RefClkOut"`
+    pub source: String,
+    /// Frequency in Hz
+    pub freq: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SyncSpec {
+    pub role: SyncRole,
+    /// Device whose start trigger this device follows
+    pub trig_dev: Option<String>,
+    /// Terminal the trigger arrives on, e.g. `"PFI0"`
+    pub trig_terminal: Option<String>,
+    /// `None` - the device runs on its own onboard clock
+    pub ref_clk: Option<RefClock>,
+}
+
+impl SyncSpec {
+    pub fn primary() -> Self {
+        Self { role: SyncRole::Primary, trig_dev: None, trig_terminal: None, ref_clk: None }
+    }
+    /// Secondary started by the trigger of device `trig_dev` arriving on `trig_terminal`
+    pub fn secondary(trig_dev: &str, trig_terminal: &str) -> Self {
+        Self { role: SyncRole::Secondary, trig_dev: Some(trig_dev.to_string()), trig_terminal: Some(trig_terminal.to_string()), ref_clk: None }
+    }
+    pub fn with_ref_clk(mut self, source: &str, freq: f64) -> Self {
+        self.ref_clk = Some(RefClock { source: source.to_string(), freq });
+        self
+    }
+}
+
+impl Display for SyncSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.role)?;
+        if let Some(trig_dev) = &self.trig_dev {
+            write!(f, ", triggered by {trig_dev}")?;
+            if let Some(terminal) = &self.trig_terminal {
+                write!(f, " on {terminal}")?;
+            }
+        }
+        if let Some(ref_clk) = &self.ref_clk {
+            write!(f, ", ref clock {} at {} Hz", ref_clk.source, ref_clk.freq)?;
+        }
+        Ok(())
+    }
+}
+
+/// Every consistency problem of the configured devices `specs` (device name -> spec), `dev_names` are all registered devices
+pub fn sync_problems(specs: &IndexMap<String, SyncSpec>, dev_names: &[String]) -> Vec<String> {
+    let mut problems = Vec::new();
+    if specs.is_empty() {
+        return problems
+    }
+    let primaries: Vec<&String> = specs.iter().filter(|(_name, spec)| spec.role == SyncRole::Primary).map(|(name, _spec)| name).collect();
+    if primaries.len() != 1 {
+        problems.push(format!("expected exactly one primary device, got {primaries:?}"));
+    }
+    for (name, spec) in specs {
+        match (&spec.trig_dev, spec.role) {
+            (Some(trig_dev), SyncRole::Primary) => problems.push(format!("primary device {name} follows the trigger of {trig_dev}")),
+            (Some(trig_dev), _) if !dev_names.contains(trig_dev) => problems.push(format!("{name} is triggered by {trig_dev} which is not registered")),
+            _ => {},
+        }
+    }
+    // Following the trigger sources from any device must not come back to it
+    for name in specs.keys() {
+        let mut cur = name;
+        for _ in 0..specs.len() {
+            let Some(next) = specs.get(cur).and_then(|spec| spec.trig_dev.as_ref()) else {
+                break
+            };
+            if next == name {
+                problems.push(format!("{name} is part of a trigger cycle"));
+                break
+            }
+            cur = next;
+        }
+    }
+    let mut clk_freqs: IndexMap<&str, (&String, f64)> = IndexMap::new();
+    for (name, ref_clk) in specs.iter().filter_map(|(name, spec)| spec.ref_clk.as_ref().map(|ref_clk| (name, ref_clk))) {
+        match clk_freqs.get(ref_clk.source.as_str()) {
+            Some(&(other, freq)) if freq != ref_clk.freq => problems.push(format!(
+                "{name} expects reference clock {} at {} Hz while {other} expects {freq} Hz", ref_clk.source, ref_clk.freq
+            )),
+            Some(_) => {},
+            None => {
+                clk_freqs.insert(&ref_clk.source, (name, ref_clk.freq));
+            },
+        }
+    }
+    problems
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::device::BaseDev;
+    use crate::error::StreamerError;
+    use crate::mock::test_impls::TestStreamer;
+    use crate::streamer::BaseStreamer;
+
+    #[test]
+    fn check_sync() {
+        let mut streamer = TestStreamer::new();
+        streamer.add_ao_dev("AO", 1e3);
+        streamer.add_ao_dev("AO2", 1e3);
+        streamer.add_do_dev("DO", 1e3);
+        // Nothing configured - nothing to check
+        streamer.check_sync().unwrap();
+
+        streamer.ao_devs["AO"].set_sync_spec(SyncSpec::primary().with_ref_clk("PXI_Clk10", 10e6)).unwrap();
+        streamer.ao_devs["AO2"].set_sync_spec(SyncSpec::secondary("AO", "PFI0").with_ref_clk("PXI_Clk10", 10e6)).unwrap();
+        streamer.do_devs["DO"].set_sync_spec(SyncSpec::secondary("AO2", "PFI1")).unwrap();
+        assert!(matches!(streamer.ao_devs["AO"].set_sync_spec(SyncSpec::secondary("AO", "PFI0")), Err(StreamerError::InvalidArgument { .. })));
+        streamer.check_sync().unwrap();
+        assert_eq!(streamer.sync_specs()["DO"].to_string(), "Secondary, triggered by AO2 on PFI1");
+
+        // Trigger cycle without a primary, clock mismatch
+        streamer.ao_devs["AO"].set_sync_spec(SyncSpec::secondary("DO", "PFI0").with_ref_clk("PXI_Clk10", 100e6)).unwrap();
+        let err = streamer.check_sync().unwrap_err();
+        assert!(matches!(err, StreamerError::Incompatible { .. }));
+        let msg = err.to_string();
+        assert!(msg.contains("expected exactly one primary device, got []"));
+        assert!(msg.contains("AO2 is part of a trigger cycle"));
+        assert!(msg.contains("AO2 expects reference clock PXI_Clk10 at 10000000 Hz while AO expects 100000000 Hz"));
+
+        streamer.ao_devs["AO"].set_sync_spec(SyncSpec::secondary("Nope", "PFI0")).unwrap();
+        assert!(streamer.check_sync().unwrap_err().to_string().contains("AO is triggered by Nope which is not registered"));
+    }
+}
@@ -0,0 +1,124 @@
+//! Timeline (Gantt chart) of the instruction schedule - one row of bars per active channel.
+//!
+//! The timeline is built from the edit caches, so it is cheap even for long sequences and doesn't require compiling.
+//! Every instruction becomes a bar from its start to its end time. Instructions without a specified end
+//! ("go-this" instructions) are drawn up to the start of the next instruction on the channel, or up to the
+//! timeline end for the last one, and are marked `open`.
+//! The timeline ends at the compiled stop time if the streamer is freshly compiled, otherwise at the end of the last instruction.
+//!
+//! [`Timeline::to_json`] gives the structure for external tools, [`Timeline::to_svg`] a self-contained
+//! SVG image with a tooltip (function and metadata) on every bar.
+//! Built by [`BaseStreamer::timeline`].
+//!
+//! [`BaseStreamer::timeline`]: crate::streamer::BaseStreamer::timeline
+
+use std::fmt::Write;
+use serde::{Deserialize, Serialize};
+use crate::diff::InstrSnapshot;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Bar {
+    /// Start time in seconds
+    pub start: f64,
+    /// End time in seconds
+    pub end: f64,
+    /// `true` for instructions without a specified end
+    pub open: bool,
+    pub keep_val: bool,
+    /// `describe()` of the instruction function
+    pub func: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TimelineRow {
+    pub dev: String,
+    pub chan: String,
+    pub bars: Vec<Bar>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Timeline {
+    /// End time in seconds
+    pub end_time: f64,
+    pub rows: Vec<TimelineRow>,
+}
+
+/// Label column width of the SVG
+const SVG_LABEL_WIDTH: f64 = 160.0;
+const SVG_ROW_HEIGHT: f64 = 20.0;
+const SVG_AXIS_HEIGHT: f64 = 20.0;
+
+impl Timeline {
+    pub fn new(end_time: f64) -> Self {
+        Self { end_time, rows: Vec::new() }
+    }
+
+    /// Adds a row for channel `chan` of device `dev` with sample rate `samp_rate`. Channels without instructions are skipped.
+    pub fn add_chan(&mut self, dev: &str, chan: &str, samp_rate: f64, instrs: &[InstrSnapshot]) {
+        if instrs.is_empty() {
+            return
+        }
+        let to_time = |pos: usize| pos as f64 / samp_rate;
+        let bars = instrs.iter().enumerate().map(|(idx, instr)| {
+            let start = to_time(instr.start_pos);
+            let (end, open, keep_val) = match instr.end_spec {
+                Some((end_pos, keep_val)) => (to_time(end_pos), false, keep_val),
+                None => {
+                    let end = instrs.get(idx + 1).map_or(self.end_time.max(start), |next| to_time(next.start_pos));
+                    (end, true, false)
+                },
+            };
+            Bar { start, end, open, keep_val, func: instr.func.clone(), label: instr.meta.as_ref().and_then(|meta| meta.label.clone()) }
+        }).collect();
+        self.rows.push(TimelineRow { dev: dev.to_string(), chan: chan.to_string(), bars })
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Timeline only holds JSON-compatible values")
+    }
+
+    /// Self-contained SVG image `width` pixels wide (including the channel label column)
+    pub fn to_svg(&self, width: f64) -> String {
+        let plot_width = (width - SVG_LABEL_WIDTH).max(1.0);
+        let height = SVG_AXIS_HEIGHT + SVG_ROW_HEIGHT * self.rows.len() as f64;
+        let scale = if self.end_time > 0.0 { plot_width / self.end_time } else { 0.0 };
+        let x = |t: f64| SVG_LABEL_WIDTH + t * scale;
+
+        let mut svg = String::new();
+        // Writing into a `String` never fails
+        let _ = writeln!(
+            svg, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" font-family="monospace" font-size="11">"#
+        );
+        let _ = writeln!(svg, r#"<line x1="{SVG_LABEL_WIDTH}" y1="{SVG_AXIS_HEIGHT}" x2="{width}" y2="{SVG_AXIS_HEIGHT}" stroke="black"/>"#);
+        for tick in 0..=4 {
+            let t = self.end_time * tick as f64 / 4.0;
+            let anchor = match tick { 0 => "start", 4 => "end", _ => "middle" };
+            let _ = writeln!(svg, r#"<text x="{}" y="{}" text-anchor="{anchor}">{t:.3e} s</text>"#, x(t), SVG_AXIS_HEIGHT - 6.0);
+        }
+        for (row_idx, row) in self.rows.iter().enumerate() {
+            let y = SVG_AXIS_HEIGHT + SVG_ROW_HEIGHT * row_idx as f64;
+            let _ = writeln!(svg, r#"<text x="4" y="{}">{}/{}</text>"#, y + SVG_ROW_HEIGHT - 6.0, escape_xml(&row.dev), escape_xml(&row.chan));
+            for bar in row.bars.iter() {
+                // Keep zero-length bars visible
+                let bar_width = ((bar.end - bar.start) * scale).max(1.0);
+                let fill = if bar.open { "#9ecae1" } else { "#3182bd" };
+                let mut title = format!("{} [{}, {}] s", bar.func, bar.start, bar.end);
+                if let Some(label) = &bar.label {
+                    title = format!("{label}: {title}")
+                }
+                let _ = writeln!(
+                    svg, r#"<rect x="{}" y="{}" width="{bar_width}" height="{}" fill="{fill}"><title>{}</title></rect>"#,
+                    x(bar.start), y + 3.0, SVG_ROW_HEIGHT - 6.0, escape_xml(&title)
+                );
+            }
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
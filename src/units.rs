@@ -0,0 +1,215 @@
+//! Unit-carrying time values.
+//!
+//! Instruction times are plain `f64` seconds throughout the crate, which makes it easy to pass `5.0` meaning
+//! microseconds and get a sequence that is a million times too long. [`Duration`] and [`TimePoint`] carry the unit
+//! in the type: they are only built through explicit unit constructors ([`ns`], [`us`], [`ms`], [`s`] or
+//! `Duration::from_*`) and read back with `as_*`, so a bare number can't slip in where a time is expected.
+//!
+//! The unit-aware channel methods ([`BaseChan::add_instr_at`], [`BaseChan::constant_at`], [`BaseChan::add_event_at`])
+//! take them next to the existing `f64`-seconds methods, which stay unchanged:
+//! ```ignore
+//! use base_streamer::units::{ms, us, TimePoint};
+//! chan.constant_at(true, TimePoint::ZERO + ms(1.5), Some((us(10.0), false)))?;
+//! chan.constant(true, 1.5e-3, Some((10e-6, false)))?; // the same instruction
+//! ```
+//!
+//! Values are stored as `f64` seconds. Constructors divide by the unit scale rather than multiplying by its inverse,
+//! so round numbers convert exactly (`us(5.0).as_secs() == 5e-6`).
+//!
+//! [`BaseChan::add_instr_at`]: crate::channel::BaseChan::add_instr_at
+//! [`BaseChan::constant_at`]: crate::channel::BaseChan::constant_at
+//! [`BaseChan::add_event_at`]: crate::channel::BaseChan::add_event_at
+
+use std::fmt;
+use std::fmt::Display;
+use std::ops::{Add, Mul, Sub};
+
+const MS_PER_S: f64 = 1e3;
+const US_PER_S: f64 = 1e6;
+const NS_PER_S: f64 = 1e9;
+
+/// Length of a time interval
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+pub struct Duration(f64);
+
+/// Time since the start of the sequence
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+pub struct TimePoint(f64);
+
+impl Duration {
+    pub const ZERO: Duration = Duration(0.0);
+
+    pub fn from_secs(secs: f64) -> Self {
+        Self(secs)
+    }
+    pub fn from_ms(ms: f64) -> Self {
+        Self(ms / MS_PER_S)
+    }
+    pub fn from_us(us: f64) -> Self {
+        Self(us / US_PER_S)
+    }
+    pub fn from_ns(ns: f64) -> Self {
+        Self(ns / NS_PER_S)
+    }
+    /// Length of `n_ticks` periods of a `samp_rate` clock
+    pub fn from_ticks(n_ticks: usize, samp_rate: f64) -> Self {
+        Self(n_ticks as f64 / samp_rate)
+    }
+
+    pub fn as_secs(&self) -> f64 {
+        self.0
+    }
+    pub fn as_ms(&self) -> f64 {
+        self.0 * MS_PER_S
+    }
+    pub fn as_us(&self) -> f64 {
+        self.0 * US_PER_S
+    }
+    pub fn as_ns(&self) -> f64 {
+        self.0 * NS_PER_S
+    }
+}
+
+impl TimePoint {
+    /// Start of the sequence
+    pub const ZERO: TimePoint = TimePoint(0.0);
+
+    pub fn from_secs(secs: f64) -> Self {
+        Self(secs)
+    }
+    /// Time point `offset` after the start of the sequence
+    pub fn after_start(offset: Duration) -> Self {
+        Self(offset.0)
+    }
+    /// Time of clock tick `pos` of a `samp_rate` clock
+    pub fn from_tick(pos: usize, samp_rate: f64) -> Self {
+        Self(pos as f64 / samp_rate)
+    }
+
+    pub fn as_secs(&self) -> f64 {
+        self.0
+    }
+    /// Time since the start of the sequence
+    pub fn since_start(&self) -> Duration {
+        Duration(self.0)
+    }
+}
+
+/// A bare duration given where a time point is expected is taken as an offset from the sequence start
+impl From<Duration> for TimePoint {
+    fn from(offset: Duration) -> Self {
+        TimePoint::after_start(offset)
+    }
+}
+
+impl Add for Duration {
+    type Output = Duration;
+    fn add(self, rhs: Duration) -> Duration {
+        Duration(self.0 + rhs.0)
+    }
+}
+impl Sub for Duration {
+    type Output = Duration;
+    fn sub(self, rhs: Duration) -> Duration {
+        Duration(self.0 - rhs.0)
+    }
+}
+impl Mul<f64> for Duration {
+    type Output = Duration;
+    fn mul(self, factor: f64) -> Duration {
+        Duration(self.0 * factor)
+    }
+}
+impl Add<Duration> for TimePoint {
+    type Output = TimePoint;
+    fn add(self, rhs: Duration) -> TimePoint {
+        TimePoint(self.0 + rhs.0)
+    }
+}
+impl Sub<Duration> for TimePoint {
+    type Output = TimePoint;
+    fn sub(self, rhs: Duration) -> TimePoint {
+        TimePoint(self.0 - rhs.0)
+    }
+}
+impl Sub for TimePoint {
+    type Output = Duration;
+    fn sub(self, rhs: TimePoint) -> Duration {
+        Duration(self.0 - rhs.0)
+    }
+}
+
+/// Prints with the largest unit keeping the value at least 1 (`1.5 ms`, `250 ns`)
+impl Display for Duration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let abs = self.0.abs();
+        match abs {
+            _ if abs >= 1.0 || abs == 0.0 => write!(f, "{} s", self.0),
+            _ if abs >= 1.0 / MS_PER_S => write!(f, "{} ms", self.as_ms()),
+            _ if abs >= 1.0 / US_PER_S => write!(f, "{} us", self.as_us()),
+            _ => write!(f, "{} ns", self.as_ns()),
+        }
+    }
+}
+impl Display for TimePoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "t = {}", self.since_start())
+    }
+}
+
+/// `x` seconds
+pub fn s(x: f64) -> Duration {
+    Duration::from_secs(x)
+}
+/// `x` milliseconds
+pub fn ms(x: f64) -> Duration {
+    Duration::from_ms(x)
+}
+/// `x` microseconds
+pub fn us(x: f64) -> Duration {
+    Duration::from_us(x)
+}
+/// `x` nanoseconds
+pub fn ns(x: f64) -> Duration {
+    Duration::from_ns(x)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::channel::BaseChan;
+    use crate::mock::test_impls::TestChan;
+    use crate::units::*;
+
+    #[test]
+    fn conversions() {
+        assert_eq!(us(5.0).as_secs(), 5e-6);
+        assert_eq!(ms(1.5).as_secs(), 1.5e-3);
+        assert_eq!(ns(250.0).as_secs(), 250e-9);
+        assert_eq!(s(2.0).as_ms(), 2000.0);
+        assert_eq!(Duration::from_ticks(10, 1e6), us(10.0));
+        assert_eq!(TimePoint::from_tick(500, 1e3).as_secs(), 0.5);
+
+        let t = TimePoint::ZERO + ms(1.0) + us(5.0);
+        assert!((t.as_secs() - 1.005e-3).abs() < 1e-18);
+        assert_eq!(TimePoint::from(ms(3.0)) - TimePoint::from(ms(1.0)), ms(2.0));
+        assert_eq!(us(3.0) * 2.0, us(6.0));
+
+        assert_eq!(ms(1.5).to_string(), "1.5 ms");
+        assert_eq!(ns(250.0).to_string(), "250 ns");
+        assert_eq!(s(0.0).to_string(), "0 s");
+        assert_eq!(TimePoint::from(s(2.0)).to_string(), "t = 2 s");
+    }
+
+    #[test]
+    fn unit_instrs() {
+        let mut with_units = TestChan::new("ao0", 1e6, 0.0);
+        with_units.constant_at(1.0, ms(1.0), Some((us(10.0), false))).unwrap();
+        with_units.add_event_at(TimePoint::ZERO + ms(2.0), 0.5).unwrap();
+
+        let mut with_secs = TestChan::new("ao0", 1e6, 0.0);
+        with_secs.constant(1.0, 1e-3, Some((10e-6, false))).unwrap();
+        with_secs.add_event(2e-3, 0.5).unwrap();
+        let describe = |chan: &TestChan<f64>| chan.instr_list().iter().map(|instr| instr.to_string()).collect::<Vec<_>>();
+        assert_eq!(describe(&with_units), describe(&with_secs));
+    }
+}
@@ -0,0 +1,187 @@
+//! Structured compile-cache validation reports.
+//!
+//! `validate_compile_cache()` only answers "is the cache valid?" with an error message.
+//! The reports in this module describe the cache state in detail instead - per-channel freshness,
+//! compiled stop positions, gaps in the instruction coverage which compile fills with padding,
+//! and the first edit-cache instruction which is not represented in the compile cache.
+//!
+//! Reports are built by [`BaseChan::validation_report`], [`BaseDev::validation_report`],
+//! and [`BaseStreamer::validation_report`]. Use `to_dict()` to pass them to Python.
+//!
+//! [`BaseChan::validation_report`]: crate::channel::BaseChan::validation_report
+//! [`BaseDev::validation_report`]: crate::device::BaseDev::validation_report
+//! [`BaseStreamer::validation_report`]: crate::streamer::BaseStreamer::validation_report
+
+use std::fmt;
+use std::fmt::Display;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use crate::diff::InstrSnapshot;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChanReport {
+    pub name: String,
+    pub got_instructions: bool,
+    pub is_fresh_compiled: bool,
+    /// Last compile cache end, `None` if the compile cache is empty
+    pub compiled_stop_pos: Option<usize>,
+    /// `[start, end)` intervals not covered by any instruction - compile fills them with padding
+    pub gaps: Vec<(usize, usize)>,
+    /// First edit-cache instruction whose interval or function does not match the compile cache
+    pub first_mismatch: Option<InstrSnapshot>,
+}
+
+impl ChanReport {
+    pub fn is_valid(&self) -> bool {
+        self.is_fresh_compiled && self.first_mismatch.is_none()
+    }
+
+    pub fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("name", &self.name)?;
+        dict.set_item("valid", self.is_valid())?;
+        dict.set_item("got_instructions", self.got_instructions)?;
+        dict.set_item("is_fresh_compiled", self.is_fresh_compiled)?;
+        dict.set_item("compiled_stop_pos", self.compiled_stop_pos)?;
+        dict.set_item("gaps", self.gaps.clone())?;
+        dict.set_item("first_mismatch", self.first_mismatch.as_ref().map(|instr| instr.to_string()))?;
+        Ok(dict)
+    }
+}
+
+impl Display for ChanReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f, "channel {}: fresh_compiled={}, compiled_stop_pos={:?}, gaps={:?}",
+            self.name, self.is_fresh_compiled, self.compiled_stop_pos, self.gaps
+        )?;
+        if let Some(instr) = &self.first_mismatch {
+            write!(f, ", first mismatching instruction: {instr}")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DevReport {
+    pub name: String,
+    pub got_instructions: bool,
+    /// Reports of active channels only
+    pub chans: Vec<ChanReport>,
+}
+
+impl DevReport {
+    /// `true` if all active channels were compiled to the same stop position
+    pub fn stop_pos_consistent(&self) -> bool {
+        self.chans.windows(2).all(|pair| pair[0].compiled_stop_pos == pair[1].compiled_stop_pos)
+    }
+    pub fn is_valid(&self) -> bool {
+        self.got_instructions
+            && self.chans.iter().all(|chan| chan.is_valid())
+            && self.stop_pos_consistent()
+    }
+
+    pub fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("name", &self.name)?;
+        dict.set_item("valid", self.is_valid())?;
+        dict.set_item("got_instructions", self.got_instructions)?;
+        dict.set_item("stop_pos_consistent", self.stop_pos_consistent())?;
+        let chans = PyDict::new_bound(py);
+        for chan in self.chans.iter() {
+            chans.set_item(&chan.name, chan.to_dict(py)?)?;
+        }
+        dict.set_item("chans", chans)?;
+        Ok(dict)
+    }
+}
+
+impl Display for DevReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.got_instructions {
+            return writeln!(f, "device {}: no instructions", self.name)
+        }
+        writeln!(f, "device {}: valid={}, stop_pos_consistent={}", self.name, self.is_valid(), self.stop_pos_consistent())?;
+        for chan in self.chans.iter() {
+            writeln!(f, "\t{chan}")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct StreamerReport {
+    /// Reports of active devices only
+    pub devs: Vec<DevReport>,
+}
+
+impl StreamerReport {
+    pub fn is_valid(&self) -> bool {
+        !self.devs.is_empty() && self.devs.iter().all(|dev| dev.is_valid())
+    }
+
+    pub fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new_bound(py);
+        dict.set_item("valid", self.is_valid())?;
+        let devs = PyDict::new_bound(py);
+        for dev in self.devs.iter() {
+            devs.set_item(&dev.name, dev.to_dict(py)?)?;
+        }
+        dict.set_item("devs", devs)?;
+        Ok(dict)
+    }
+}
+
+impl Display for StreamerReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.devs.is_empty() {
+            return writeln!(f, "streamer: no active devices")
+        }
+        for dev in self.devs.iter() {
+            write!(f, "{dev}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::channel::BaseChan;
+    use crate::device::BaseDev;
+    use crate::mock::test_impls::TestStreamer;
+    use crate::streamer::BaseStreamer;
+
+    #[test]
+    fn report() {
+        let mut streamer = TestStreamer::new();
+        streamer.add_ao_dev("AO", 1e3);
+        streamer.ao_devs["AO"].add_chan("ao0", 0.0);
+        streamer.ao_devs["AO"].add_chan("ao1", 0.0);
+        streamer.ao_devs["AO"].chan_mut("ao0").unwrap().constant(1.0, 0.1, Some((0.1, false))).unwrap();
+        streamer.ao_devs["AO"].chan_mut("ao0").unwrap().constant(2.0, 0.3, None).unwrap();
+        streamer.ao_devs["AO"].chan_mut("ao1").unwrap().constant(1.0, 0.0, Some((0.5, false))).unwrap();
+
+        // Not compiled yet
+        let report = streamer.validation_report();
+        assert!(!report.is_valid());
+        let chan_report = &report.devs[0].chans[0];
+        assert!(!chan_report.is_fresh_compiled);
+        assert_eq!(chan_report.compiled_stop_pos, None);
+        assert_eq!(chan_report.first_mismatch.as_ref().unwrap().start_pos, 100);
+
+        streamer.compile(Some(1.0)).unwrap();
+        let report = streamer.validation_report();
+        assert!(report.is_valid());
+        assert!(report.devs[0].stop_pos_consistent());
+        let chan_report = &report.devs[0].chans[0];
+        assert_eq!(chan_report.compiled_stop_pos, Some(1000));
+        assert_eq!(chan_report.gaps, vec![(0, 100), (200, 300)]);
+        assert_eq!(report.devs[0].chans[1].gaps, vec![(500, 1000)]);
+
+        // A new instruction is reported as the first mismatch
+        streamer.ao_devs["AO"].chan_mut("ao1").unwrap().constant(3.0, 0.7, Some((0.1, false))).unwrap();
+        let report = streamer.validation_report();
+        assert!(!report.is_valid());
+        assert_eq!(report.devs[0].chans[1].first_mismatch.as_ref().unwrap().start_pos, 700);
+    }
+}